@@ -0,0 +1,117 @@
+use crate::{author_guard::is_from_human, data::AppState, utils::execute_modal_on_button};
+use color_eyre::eyre::Result;
+use poise::{
+    serenity_prelude::{self as serenity},
+    Modal,
+};
+
+const ALT_TEXT_BUTTON_PREFIX: &str = "alt_text:";
+
+#[derive(Debug, Modal)]
+#[name = "Describe your image"]
+struct AltTextModal {
+    #[name = "What does the image show?"]
+    #[paragraph]
+    #[max_length = 1000]
+    description: String,
+}
+
+fn is_undescribed_image(attachment: &serenity::Attachment) -> bool {
+    attachment
+        .content_type
+        .as_ref()
+        .is_some_and(|content_type| content_type.starts_with("image"))
+        && attachment
+            .description
+            .as_ref()
+            .is_none_or(|description| description.trim().is_empty())
+}
+
+/// Asks the author to describe images that were posted without alt text.
+pub async fn nudge_alt_text(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<()> {
+    if !is_from_human(ctx, data, message).await
+        || !message.attachments.iter().any(is_undescribed_image)
+    {
+        return Ok(());
+    }
+
+    if !data
+        .config
+        .read()
+        .await
+        .alt_text_channels
+        .contains(&message.channel_id.get())
+    {
+        return Ok(());
+    }
+
+    message
+        .channel_id
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .reference_message(message)
+                .allowed_mentions(serenity::CreateAllowedMentions::new().replied_user(false))
+                .content("This image has no description, would you like to add one for people using screen readers?")
+                .button(
+                    serenity::CreateButton::new(format!(
+                        "{}{}",
+                        ALT_TEXT_BUTTON_PREFIX, message.author.id
+                    ))
+                    .label("Add description"),
+                ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Lets the author fill in a description, which replaces the nudge.
+pub async fn handle_alt_text_button(
+    ctx: &serenity::Context,
+    interaction: &serenity::ComponentInteraction,
+) -> Result<()> {
+    let Some(author_id) = interaction
+        .data
+        .custom_id
+        .strip_prefix(ALT_TEXT_BUTTON_PREFIX)
+    else {
+        return Ok(());
+    };
+
+    if interaction.user.id.get() != author_id.parse::<u64>()? {
+        interaction
+            .create_response(
+                ctx,
+                serenity::CreateInteractionResponse::Message(
+                    serenity::CreateInteractionResponseMessage::new()
+                        .ephemeral(true)
+                        .content("Only the person who posted the image can describe it."),
+                ),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    let Some(AltTextModal { description }) = execute_modal_on_button(ctx, interaction).await?
+    else {
+        return Ok(());
+    };
+
+    interaction
+        .message
+        .clone()
+        .edit(
+            ctx,
+            serenity::EditMessage::new()
+                .content(format!("**Image description:** {}", description))
+                .components(vec![]),
+        )
+        .await?;
+
+    Ok(())
+}
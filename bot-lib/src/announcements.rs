@@ -0,0 +1,376 @@
+use crate::{data::AppState, mirror::mirror_to_targets, mod_log::mod_log};
+use color_eyre::eyre::{OptionExt, Result};
+use poise::serenity_prelude::{self as serenity, ChannelId, Mentionable, RoleId, UserId};
+use serde::{Deserialize, Serialize};
+
+const PENDING_ANNOUNCEMENTS_TREE: &str = "pending_announcements";
+pub const ANNOUNCEMENT_BUTTON_PREFIX: &str = "announcement:";
+
+/// Who can use `/announce compose`, and where.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Announcements {
+    /// Members with one of these roles can compose announcements.
+    pub officer_role_ids: Vec<u64>,
+    /// Where announcements that need approval wait for a mod.
+    #[serde(default)]
+    pub approval_channel_id: Option<u64>,
+    pub channels: Vec<AnnouncementChannel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AnnouncementChannel {
+    pub channel_id: u64,
+    /// Whether a mod has to approve announcements before they go out.
+    #[serde(default)]
+    pub requires_approval: bool,
+}
+
+impl Announcements {
+    pub fn channel(&self, channel_id: ChannelId) -> Option<&AnnouncementChannel> {
+        self.channels
+            .iter()
+            .find(|channel| channel.channel_id == channel_id.get())
+    }
+
+    pub fn is_officer(&self, roles: &[RoleId]) -> bool {
+        roles
+            .iter()
+            .any(|role_id| self.officer_role_ids.contains(&role_id.get()))
+    }
+}
+
+/// Who gets pinged by an announcement.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Ping {
+    Nobody,
+    Here,
+    Everyone,
+    Role(u64),
+}
+
+impl Ping {
+    fn mention(&self) -> Option<String> {
+        match self {
+            Ping::Nobody => None,
+            Ping::Here => Some("@here".to_owned()),
+            Ping::Everyone => Some("@everyone".to_owned()),
+            Ping::Role(role_id) => Some(RoleId::new(*role_id).mention().to_string()),
+        }
+    }
+
+    fn allowed_mentions(&self) -> serenity::CreateAllowedMentions {
+        let allowed = serenity::CreateAllowedMentions::new();
+
+        match self {
+            Ping::Nobody => allowed,
+            Ping::Here | Ping::Everyone => allowed.everyone(true),
+            Ping::Role(role_id) => allowed.roles([*role_id]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Announcement {
+    pub author_id: u64,
+    pub channel_id: u64,
+    pub title: String,
+    pub body: String,
+    pub ping: Ping,
+}
+
+impl Announcement {
+    /// The message as it gets posted, so mirrors can copy it as is.
+    pub fn content(&self) -> String {
+        let mut content = String::new();
+
+        if let Some(mention) = self.ping.mention() {
+            content.push_str(&mention);
+            content.push('\n');
+        }
+
+        content.push_str(&format!("## {}\n{}", self.title, self.body));
+
+        content
+    }
+
+    /// What mods see while deciding whether to approve it.
+    pub fn preview(&self) -> serenity::CreateEmbed {
+        serenity::CreateEmbed::new()
+            .title(&self.title)
+            .description(&self.body)
+            .field(
+                "Channel",
+                ChannelId::new(self.channel_id).mention().to_string(),
+                true,
+            )
+            .field(
+                "Pings",
+                self.ping.mention().unwrap_or_else(|| "Nobody".to_owned()),
+                true,
+            )
+            .field(
+                "From",
+                UserId::new(self.author_id).mention().to_string(),
+                true,
+            )
+    }
+}
+
+/// Posts the announcement, publishes it if the channel auto-publishes, and sends it to any mirrors.
+pub async fn post_announcement(
+    ctx: &serenity::Context,
+    data: &AppState,
+    announcement: &Announcement,
+) -> Result<serenity::Message> {
+    let channel_id = ChannelId::new(announcement.channel_id);
+
+    let message = channel_id
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .content(announcement.content())
+                .allowed_mentions(announcement.ping.allowed_mentions()),
+        )
+        .await?;
+
+    let (publish, mirrors) = {
+        let config = data.config.read().await;
+
+        (
+            config
+                .auto_publish
+                .iter()
+                .any(|auto_publish| auto_publish.channel_id == announcement.channel_id),
+            config
+                .mirrors
+                .iter()
+                .filter(|mirror| mirror.source_channel_id == announcement.channel_id)
+                .cloned()
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    if publish {
+        message.crosspost(ctx).await?;
+    }
+
+    let author = UserId::new(announcement.author_id).to_user(ctx).await?;
+
+    for mirror in mirrors {
+        mirror_to_targets(
+            ctx,
+            &mirror,
+            &message.content,
+            "",
+            author.global_name.as_deref().unwrap_or(&author.name),
+            author.avatar_url(),
+        )
+        .await?;
+    }
+
+    Ok(message)
+}
+
+fn approval_buttons(id: u64) -> Vec<serenity::CreateActionRow> {
+    vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new(format!("{}approve:{}", ANNOUNCEMENT_BUTTON_PREFIX, id))
+            .label("Approve")
+            .style(serenity::ButtonStyle::Success),
+        serenity::CreateButton::new(format!("{}reject:{}", ANNOUNCEMENT_BUTTON_PREFIX, id))
+            .label("Reject")
+            .style(serenity::ButtonStyle::Danger),
+    ])]
+}
+
+/// Sends the announcement to the approval channel to wait for a mod.
+pub async fn request_approval(
+    ctx: &serenity::Context,
+    data: &AppState,
+    approval_channel_id: ChannelId,
+    announcement: &Announcement,
+) -> Result<()> {
+    let id = data.db.generate_id()?;
+
+    approval_channel_id
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .content("An announcement is waiting for approval.")
+                .embed(announcement.preview())
+                .components(approval_buttons(id)),
+        )
+        .await?;
+
+    data.db
+        .insert(PENDING_ANNOUNCEMENTS_TREE, id.to_be_bytes(), announcement)?;
+
+    Ok(())
+}
+
+/// Handles the approve/reject buttons on announcements waiting in the approval channel.
+pub async fn handle_announcement_button(
+    ctx: &serenity::Context,
+    data: &AppState,
+    interaction: &serenity::ComponentInteraction,
+) -> Result<()> {
+    let Some(action) = interaction
+        .data
+        .custom_id
+        .strip_prefix(ANNOUNCEMENT_BUTTON_PREFIX)
+    else {
+        return Ok(());
+    };
+
+    let (action, id) = action
+        .split_once(':')
+        .ok_or_eyre("Malformed announcement button")?;
+    let id = id.parse::<u64>()?;
+
+    let can_approve = interaction
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|permissions| permissions.manage_messages());
+
+    if !can_approve {
+        interaction
+            .create_response(
+                ctx,
+                serenity::CreateInteractionResponse::Message(
+                    serenity::CreateInteractionResponseMessage::new()
+                        .ephemeral(true)
+                        .content("Only mods can approve announcements."),
+                ),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    let Some(announcement) = data
+        .db
+        .remove::<Announcement>(PENDING_ANNOUNCEMENTS_TREE, id.to_be_bytes())?
+    else {
+        return Ok(());
+    };
+
+    let status = match action {
+        "approve" => {
+            // Taken out first so a double click can't post it twice, but put back if posting
+            // fails so it can be approved again
+            let message = match post_announcement(ctx, data, &announcement).await {
+                Ok(message) => message,
+                Err(e) => {
+                    data.db
+                        .insert(PENDING_ANNOUNCEMENTS_TREE, id.to_be_bytes(), &announcement)?;
+                    return Err(e);
+                }
+            };
+            format!(
+                "Approved by {}: {}",
+                interaction.user.mention(),
+                message.link()
+            )
+        }
+        _ => format!("Rejected by {}", interaction.user.mention()),
+    };
+
+    interaction
+        .create_response(
+            ctx,
+            serenity::CreateInteractionResponse::UpdateMessage(
+                serenity::CreateInteractionResponseMessage::new()
+                    .content(&status)
+                    .components(vec![]),
+            ),
+        )
+        .await?;
+
+    mod_log(
+        ctx,
+        data,
+        announcement
+            .preview()
+            .title(format!("Announcement: {}", announcement.title))
+            .field("Decision", &status, false),
+    )
+    .await?;
+
+    if let Err(e) = UserId::new(announcement.author_id)
+        .to_user(ctx)
+        .await?
+        .direct_message(
+            ctx,
+            serenity::CreateMessage::new().content(format!(
+                "Your announcement \"{}\" was {}.",
+                announcement.title,
+                if action == "approve" {
+                    "approved"
+                } else {
+                    "rejected"
+                }
+            )),
+        )
+        .await
+    {
+        tracing::debug!("Couldn't DM announcement author: {:?}", e);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formats_announcement() {
+        let announcement = Announcement {
+            author_id: 1,
+            channel_id: 2,
+            title: "Club meeting".to_owned(),
+            body: "Thursday at 6 in WEB 1230".to_owned(),
+            ping: Ping::Role(3),
+        };
+
+        assert_eq!(
+            announcement.content(),
+            "<@&3>\n## Club meeting\nThursday at 6 in WEB 1230"
+        );
+        assert_eq!(
+            Announcement {
+                ping: Ping::Nobody,
+                ..announcement
+            }
+            .content(),
+            "## Club meeting\nThursday at 6 in WEB 1230"
+        );
+    }
+
+    #[test]
+    fn reads_announcement_channels() {
+        let announcements: Announcements = toml::from_str(
+            r#"
+officer_role_ids = [10]
+approval_channel_id = 20
+
+[[channels]]
+channel_id = 1
+
+[[channels]]
+channel_id = 2
+requires_approval = true
+"#,
+        )
+        .unwrap();
+
+        assert!(announcements.is_officer(&[RoleId::new(5), RoleId::new(10)]));
+        assert!(!announcements.is_officer(&[RoleId::new(5)]));
+        assert_eq!(
+            announcements
+                .channel(ChannelId::new(2))
+                .map(|channel| channel.requires_approval),
+            Some(true)
+        );
+        assert_eq!(announcements.channel(ChannelId::new(3)), None);
+    }
+}
@@ -0,0 +1,321 @@
+use crate::{author_guard::is_from_human, data::AppState, db::KingFisherDb};
+use aws_credential_types::Credentials;
+use aws_sigv4::{
+    http_request::{
+        sign, PayloadChecksumKind, PercentEncodingMode, SignableBody, SignableRequest,
+        SigningSettings, UriPathNormalizationMode,
+    },
+    sign::v4,
+};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{OptionExt, Result, WrapErr};
+use poise::serenity_prelude::{self as serenity};
+use serde::{Deserialize, Serialize};
+use std::{path::Path, time::SystemTime};
+
+const ARCHIVED_FILES_TREE: &str = "archived_files";
+
+/// Keeps copies of files posted in the `-resources` channels, since Discord links expire.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AttachmentArchive {
+    pub storage: ArchiveStorage,
+    /// Bigger files are skipped.
+    #[serde(default = "get_default_max_file_bytes")]
+    pub max_file_bytes: u32,
+}
+
+fn get_default_max_file_bytes() -> u32 {
+    25 * 1024 * 1024
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ArchiveStorage {
+    /// A directory on the machine kingfisher runs on.
+    Local { path: String },
+    /// An S3 compatible bucket, addressed like `<endpoint>/<bucket>/<key>`.
+    S3 {
+        /// e.g. `https://s3.us-west-2.amazonaws.com`
+        endpoint: String,
+        bucket: String,
+        region: String,
+        /// The environment variables holding the credentials, so they stay out of the config.
+        #[serde(default = "get_default_access_key_env")]
+        access_key_env: String,
+        #[serde(default = "get_default_secret_key_env")]
+        secret_key_env: String,
+    },
+}
+
+fn get_default_access_key_env() -> String {
+    "S3_ACCESS_KEY_ID".to_owned()
+}
+
+fn get_default_secret_key_env() -> String {
+    "S3_SECRET_ACCESS_KEY".to_owned()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedFile {
+    pub attachment_id: u64,
+    /// The class number, e.g. `2420`
+    pub class: String,
+    pub file_name: String,
+    pub size: u32,
+    pub author_id: u64,
+    pub message_link: String,
+    /// Where the copy lives in the archive.
+    pub key: String,
+    /// A link to the copy, for storage that has one.
+    pub url: Option<String>,
+    pub archived_at: DateTime<Utc>,
+}
+
+/// The class a channel holds resources for, e.g. `2420` for `2420-resources`.
+pub fn resources_class(channel_name: &str) -> Option<&str> {
+    channel_name
+        .strip_suffix("-resources")
+        .filter(|class| !class.is_empty())
+}
+
+/// Where a file goes in the archive, keeping the name readable but safe for paths and urls.
+fn archive_key(class: &str, attachment_id: u64, file_name: &str) -> String {
+    let file_name = file_name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+
+    format!("{}/{}-{}", class, attachment_id, file_name)
+}
+
+impl ArchiveStorage {
+    /// Stores the file under the key, returning a link to it if the storage has one.
+    pub async fn store(&self, key: &str, bytes: Vec<u8>) -> Result<Option<String>> {
+        match self {
+            ArchiveStorage::Local { path } => {
+                let path = Path::new(path).join(key);
+
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+
+                tokio::fs::write(&path, bytes)
+                    .await
+                    .wrap_err_with(|| format!("Couldn't write {}", path.display()))?;
+
+                Ok(None)
+            }
+            ArchiveStorage::S3 {
+                endpoint,
+                bucket,
+                region,
+                access_key_env,
+                secret_key_env,
+            } => {
+                let access_key = std::env::var(access_key_env)
+                    .wrap_err_with(|| format!("Expected an access key in {}", access_key_env))?;
+                let secret_key = std::env::var(secret_key_env)
+                    .wrap_err_with(|| format!("Expected a secret key in {}", secret_key_env))?;
+
+                let url = reqwest::Url::parse(&format!(
+                    "{}/{}/{}",
+                    endpoint.trim_end_matches('/'),
+                    bucket,
+                    key
+                ))?;
+
+                // S3 wants the payload hash as a header, and the path encoded only once
+                let mut settings = SigningSettings::default();
+                settings.payload_checksum_kind = PayloadChecksumKind::XAmzSha256;
+                settings.percent_encoding_mode = PercentEncodingMode::Single;
+                settings.uri_path_normalization_mode = UriPathNormalizationMode::Disabled;
+
+                let identity =
+                    Credentials::new(access_key, secret_key, None, None, "kingfisher").into();
+                let params = v4::SigningParams::builder()
+                    .identity(&identity)
+                    .region(region)
+                    .name("s3")
+                    .time(SystemTime::now())
+                    .settings(settings)
+                    .build()?
+                    .into();
+                let request = SignableRequest::new(
+                    "PUT",
+                    url.as_str(),
+                    std::iter::empty(),
+                    SignableBody::Bytes(&bytes),
+                )?;
+                let (instructions, _) = sign(request, &params)?.into_parts();
+
+                let mut put = reqwest::Client::new().put(url.clone());
+
+                for (name, value) in instructions.headers() {
+                    put = put.header(name, value);
+                }
+
+                put.body(bytes).send().await?.error_for_status()?;
+
+                Ok(Some(url.to_string()))
+            }
+        }
+    }
+}
+
+/// Archived files for a class, oldest first.
+pub fn get_archived_files(db: &KingFisherDb, class: &str) -> Result<Vec<ArchivedFile>> {
+    let mut files = db
+        .values::<ArchivedFile>(ARCHIVED_FILES_TREE)?
+        .into_iter()
+        .filter(|file| file.class == class)
+        .collect::<Vec<_>>();
+
+    files.sort_by_key(|file| file.archived_at);
+
+    Ok(files)
+}
+
+pub fn format_size(bytes: u32) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1048575 => format!("{:.1} KB", f64::from(bytes) / 1024.),
+        _ => format!("{:.1} MB", f64::from(bytes) / (1024. * 1024.)),
+    }
+}
+
+pub fn format_archived_file(file: &ArchivedFile) -> String {
+    format!(
+        "**{}** ({}) from <@{}> {}\n{}{}",
+        file.file_name,
+        format_size(file.size),
+        file.author_id,
+        file.archived_at.format("%Y-%m-%d"),
+        file.message_link,
+        file.url
+            .as_ref()
+            .map(|url| format!("\nArchived copy: {}", url))
+            .unwrap_or_default()
+    )
+}
+
+/// Copies attachments posted in a resources channel to the archive.
+pub async fn archive_attachments(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<()> {
+    if message.attachments.is_empty() || !is_from_human(ctx, data, message).await {
+        return Ok(());
+    }
+
+    let Some(archive) = data.config.read().await.attachment_archive.clone() else {
+        return Ok(());
+    };
+
+    let channel = message
+        .channel_id
+        .to_channel(ctx)
+        .await?
+        .guild()
+        .ok_or_eyre("Not a guild channel")?;
+
+    let Some(class) = resources_class(&channel.name) else {
+        return Ok(());
+    };
+
+    // One file failing shouldn't keep the rest out of the archive
+    for attachment in &message.attachments {
+        if let Err(e) = archive_attachment(data, &archive, class, message, attachment).await {
+            tracing::warn!("Couldn't archive {}: {:?}", attachment.filename, e);
+        }
+    }
+
+    Ok(())
+}
+
+async fn archive_attachment(
+    data: &AppState,
+    archive: &AttachmentArchive,
+    class: &str,
+    message: &serenity::Message,
+    attachment: &serenity::Attachment,
+) -> Result<()> {
+    if attachment.size > archive.max_file_bytes {
+        tracing::info!(
+            "Not archiving {}, it's {}",
+            attachment.filename,
+            format_size(attachment.size)
+        );
+        return Ok(());
+    }
+
+    let bytes = attachment
+        .download()
+        .await
+        .wrap_err_with(|| format!("Couldn't download {}", attachment.filename))?;
+
+    let key = archive_key(class, attachment.id.get(), &attachment.filename);
+    let url = archive.storage.store(&key, bytes).await?;
+
+    data.db.insert(
+        ARCHIVED_FILES_TREE,
+        attachment.id.get().to_be_bytes(),
+        &ArchivedFile {
+            attachment_id: attachment.id.get(),
+            class: class.to_owned(),
+            file_name: attachment.filename.clone(),
+            size: attachment.size,
+            author_id: message.author.id.get(),
+            message_link: message.link(),
+            key,
+            url,
+            archived_at: Utc::now(),
+        },
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_resources_class() {
+        assert_eq!(resources_class("2420-resources"), Some("2420"));
+        assert_eq!(resources_class("2420-general"), None);
+        assert_eq!(resources_class("-resources"), None);
+    }
+
+    #[test]
+    fn sanitizes_keys() {
+        assert_eq!(
+            archive_key("2420", 7, "Lecture 3 (final).pdf"),
+            "2420/7-Lecture_3__final_.pdf"
+        );
+        assert_eq!(archive_key("2420", 7, "../../etc"), "2420/7-.._.._etc");
+    }
+
+    #[tokio::test]
+    async fn stores_locally() {
+        let dir = std::env::temp_dir().join(format!("kingfisher-archive-{}", std::process::id()));
+        let storage = ArchiveStorage::Local {
+            path: dir.display().to_string(),
+        };
+
+        let url = storage
+            .store("2420/1-notes.txt", b"notes".to_vec())
+            .await
+            .unwrap();
+
+        assert_eq!(url, None);
+        assert_eq!(
+            std::fs::read(dir.join("2420/1-notes.txt")).unwrap(),
+            b"notes"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{data::PoiseContext, mod_log::mod_log};
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, Mentionable};
+use serde::{Deserialize, Serialize};
+
+const COMMAND_AUDIT_TREE: &str = "command_audit";
+/// Embed field values can't be longer than 1024 characters.
+const MAX_FIELD_LENGTH: usize = 1000;
+
+/// A use of a privileged command, kept so changes like purges can be traced back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub command: String,
+    pub user_id: u64,
+    pub channel_id: u64,
+    /// The command as it was typed, with its arguments.
+    pub invocation: String,
+    /// Why it failed, if it did.
+    pub error: Option<String>,
+    pub timestamp: i64,
+}
+
+/// Called after every command (or after it errored), records it if it needs permissions to use.
+pub async fn audit_command(ctx: PoiseContext<'_>, error: Option<String>) {
+    if !is_privileged(ctx) {
+        return;
+    }
+
+    let entry = AuditEntry {
+        command: ctx.command().qualified_name.clone(),
+        user_id: ctx.author().id.get(),
+        channel_id: ctx.channel_id().get(),
+        invocation: ctx.invocation_string(),
+        error,
+        timestamp: chrono::Utc::now().timestamp(),
+    };
+
+    if let Err(e) = record(ctx, &entry).await {
+        tracing::warn!("Failed to audit /{}: {:?}", entry.command, e);
+    }
+}
+
+fn is_privileged(ctx: PoiseContext<'_>) -> bool {
+    ctx.parent_commands()
+        .iter()
+        .chain([&ctx.command()])
+        .any(|command| {
+            !command.required_permissions.is_empty()
+                || !command.checks.is_empty()
+                || command.owners_only
+        })
+}
+
+async fn record(ctx: PoiseContext<'_>, entry: &AuditEntry) -> Result<()> {
+    let db = &ctx.data().db;
+    db.insert(COMMAND_AUDIT_TREE, db.generate_id()?.to_be_bytes(), entry)?;
+
+    let (result, color) = match &entry.error {
+        Some(error) => (clip(error), serenity::Color::RED),
+        None => ("Succeeded".to_owned(), serenity::Color::DARK_GREEN),
+    };
+
+    mod_log(
+        ctx.serenity_context(),
+        ctx.data(),
+        serenity::CreateEmbed::new()
+            .title(format!("/{}", entry.command))
+            .field("By", ctx.author().mention().to_string(), true)
+            .field("In", ctx.channel_id().mention().to_string(), true)
+            .field("Invocation", clip(&entry.invocation), false)
+            .field("Result", result, false)
+            .color(color),
+    )
+    .await
+}
+
+fn clip(text: &str) -> String {
+    if text.chars().count() <= MAX_FIELD_LENGTH {
+        return text.to_owned();
+    }
+
+    format!(
+        "{}...",
+        text.chars().take(MAX_FIELD_LENGTH).collect::<String>()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clips_long_fields() {
+        assert_eq!(clip("/purge count:5"), "/purge count:5");
+
+        let clipped = clip(&"é".repeat(MAX_FIELD_LENGTH + 10));
+        assert_eq!(clipped.chars().count(), MAX_FIELD_LENGTH + 3);
+        assert!(clipped.ends_with("..."));
+    }
+}
@@ -0,0 +1,72 @@
+use crate::data::AppState;
+use poise::serenity_prelude::{self as serenity, UserId};
+
+/// Who sent a message, as far as deciding whether to act on it goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthorKind {
+    Kingfisher,
+    Webhook,
+    Bot,
+    /// A person, or a bot that's allowed to be treated like one.
+    Human,
+}
+
+pub fn author_kind(
+    message: &serenity::Message,
+    own_id: UserId,
+    allowed_bots: &[u64],
+) -> AuthorKind {
+    if message.author.id == own_id {
+        AuthorKind::Kingfisher
+    } else if message.webhook_id.is_some() {
+        AuthorKind::Webhook
+    } else if message.author.bot && !allowed_bots.contains(&message.author.id.get()) {
+        AuthorKind::Bot
+    } else {
+        AuthorKind::Human
+    }
+}
+
+/// Whether kingfisher should act on the message at all.
+///
+/// Every message handler checks this first, so kingfisher never talks to itself, its own
+/// webhooks, or other bots.
+pub async fn is_from_human(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> bool {
+    let allowed_bots = &data.config.read().await.allowed_bots;
+
+    author_kind(message, ctx.cache.current_user().id, allowed_bots) == AuthorKind::Human
+}
+
+/// How many people reacted, not counting kingfisher's own reaction.
+pub fn human_reaction_count(reaction: &serenity::MessageReaction) -> u64 {
+    reaction.count - u64::from(reaction.me)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn message(author_id: u64, bot: bool, webhook_id: Option<u64>) -> serenity::Message {
+        let mut message = serenity::Message::default();
+        message.author.id = UserId::new(author_id);
+        message.author.bot = bot;
+        message.webhook_id = webhook_id.map(serenity::WebhookId::new);
+        message
+    }
+
+    #[test]
+    fn classifies_authors() {
+        let own_id = UserId::new(100);
+        let kind = |message: &serenity::Message| author_kind(message, own_id, &[200]);
+
+        assert_eq!(kind(&message(100, true, None)), AuthorKind::Kingfisher);
+        assert_eq!(kind(&message(300, true, Some(5))), AuthorKind::Webhook);
+        assert_eq!(kind(&message(300, true, None)), AuthorKind::Bot);
+        assert_eq!(kind(&message(200, true, None)), AuthorKind::Human);
+        assert_eq!(kind(&message(300, false, None)), AuthorKind::Human);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::author_guard::is_from_human;
+use crate::data::AppState;
+use chrono::Duration;
+use color_eyre::eyre::Result;
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+const CANCEL_PUBLISH_BUTTON_PREFIX: &str = "cancel_publish:";
+
+/// Publishes messages in an announcement channel on its own, unless the author cancels.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AutoPublish {
+    pub channel_id: u64,
+    /// Only messages by members with one of these roles get published.
+    pub role_ids: Vec<u64>,
+    /// How long the author has to cancel, in seconds.
+    #[serde_as(as = "DurationSeconds<i64>")]
+    #[serde(default = "get_default_delay")]
+    pub delay: Duration,
+}
+
+fn get_default_delay() -> Duration {
+    Duration::minutes(1)
+}
+
+/// Waits out the delay (or a cancel), then crossposts the message.
+pub async fn auto_publish(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<()> {
+    if !is_from_human(ctx, data, message).await {
+        return Ok(());
+    }
+
+    let Some(auto_publish) = data
+        .config
+        .read()
+        .await
+        .auto_publish
+        .iter()
+        .find(|auto_publish| auto_publish.channel_id == message.channel_id.get())
+        .cloned()
+    else {
+        return Ok(());
+    };
+
+    let allowed = message.member.as_ref().is_some_and(|member| {
+        member
+            .roles
+            .iter()
+            .any(|role_id| auto_publish.role_ids.contains(&role_id.get()))
+    });
+
+    if !allowed {
+        return Ok(());
+    }
+
+    let custom_id = format!("{}{}", CANCEL_PUBLISH_BUTTON_PREFIX, message.id);
+    let delay = auto_publish.delay.to_std().unwrap_or_default();
+
+    let notice = message
+        .channel_id
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .reference_message(message)
+                .allowed_mentions(serenity::CreateAllowedMentions::new())
+                .content(format!("Publishing this in {} seconds.", delay.as_secs()))
+                .button(
+                    serenity::CreateButton::new(&custom_id)
+                        .label("Don't publish")
+                        .style(serenity::ButtonStyle::Secondary),
+                ),
+        )
+        .await?;
+
+    let author_id = message.author.id;
+
+    let cancel = serenity::ComponentInteractionCollector::new(ctx)
+        .custom_ids(vec![custom_id])
+        .filter(move |interaction| interaction.user.id == author_id)
+        .timeout(delay)
+        .await;
+
+    if let Some(cancel) = cancel {
+        cancel
+            .create_response(
+                ctx,
+                serenity::CreateInteractionResponse::UpdateMessage(
+                    serenity::CreateInteractionResponseMessage::new()
+                        .content("Not publishing this.")
+                        .components(vec![]),
+                ),
+            )
+            .await?;
+
+        return Ok(());
+    }
+
+    message.crosspost(ctx).await?;
+
+    if let Err(e) = notice.delete(ctx).await {
+        tracing::debug!("Couldn't delete publish notice: {:?}", e);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn deserializes_auto_publish() {
+    let auto_publish: AutoPublish = toml::from_str(
+        r#"
+channel_id = 1
+role_ids = [2]
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(auto_publish.delay, Duration::minutes(1));
+}
@@ -0,0 +1,171 @@
+use crate::{data::AppState, mod_log::mod_log};
+use color_eyre::eyre::Result;
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{self as serenity, ChannelId, Mentionable};
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    /// Messages per channel since the tuner last ran.
+    static ref MESSAGE_COUNTS: DashMap<ChannelId, u32> = DashMap::new();
+}
+
+/// Slowmode goes no lower than this once it's on, since 1-2 seconds does nothing.
+const LOWEST_STEP: u16 = 5;
+
+/// Raises slowmode while a channel is flooded, and lowers it again once things calm down.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AutoSlowmode {
+    pub channel_id: u64,
+    /// Messages per minute that count as busy.
+    #[serde(default = "get_default_busy_per_minute")]
+    pub busy_per_minute: u32,
+    #[serde(default)]
+    pub min_seconds: u16,
+    #[serde(default = "get_default_max_seconds")]
+    pub max_seconds: u16,
+}
+
+fn get_default_busy_per_minute() -> u32 {
+    30
+}
+
+fn get_default_max_seconds() -> u16 {
+    30
+}
+
+impl AutoSlowmode {
+    /// Doubles slowmode while the channel is busy and halves it once it's under half as busy.
+    fn next_slowmode(&self, per_minute: u32, current: u16) -> u16 {
+        let next = if per_minute > self.busy_per_minute {
+            current.saturating_mul(2).max(LOWEST_STEP)
+        } else if per_minute < self.busy_per_minute / 2 {
+            match current / 2 {
+                half if half < LOWEST_STEP => 0,
+                half => half,
+            }
+        } else {
+            current
+        };
+
+        next.clamp(self.min_seconds, self.max_seconds.max(self.min_seconds))
+    }
+}
+
+/// Counts the message towards its channel's rate, if the channel is tuned.
+pub async fn count_for_slowmode(data: &AppState, message: &serenity::Message) -> Result<()> {
+    if message.author.bot {
+        return Ok(());
+    }
+
+    let tuned = data
+        .config
+        .read()
+        .await
+        .auto_slowmode
+        .iter()
+        .any(|auto_slowmode| auto_slowmode.channel_id == message.channel_id.get());
+
+    if tuned {
+        *MESSAGE_COUNTS.entry(message.channel_id).or_default() += 1;
+    }
+
+    Ok(())
+}
+
+/// Adjusts slowmode in every tuned channel based on the last minute of messages.
+///
+/// A channel that fails is logged and skipped, so it doesn't hold the others back.
+pub async fn tune_slowmode(ctx: &serenity::Context, data: &AppState) -> Result<()> {
+    let channels = data.config.read().await.auto_slowmode.clone();
+
+    for auto_slowmode in channels {
+        if let Err(e) = tune_channel(ctx, data, &auto_slowmode).await {
+            tracing::warn!(
+                "Couldn't tune slowmode in {}: {:?}",
+                auto_slowmode.channel_id,
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn tune_channel(
+    ctx: &serenity::Context,
+    data: &AppState,
+    auto_slowmode: &AutoSlowmode,
+) -> Result<()> {
+    let channel_id = ChannelId::new(auto_slowmode.channel_id);
+    let per_minute = MESSAGE_COUNTS
+        .remove(&channel_id)
+        .map_or(0, |(_, count)| count);
+
+    let Some(channel) = channel_id.to_channel(ctx).await?.guild() else {
+        return Ok(());
+    };
+
+    let current = channel.rate_limit_per_user.unwrap_or(0);
+    let next = auto_slowmode.next_slowmode(per_minute, current);
+
+    if next == current {
+        return Ok(());
+    }
+
+    channel_id
+        .edit(ctx, serenity::EditChannel::new().rate_limit_per_user(next))
+        .await?;
+
+    mod_log(
+        ctx,
+        data,
+        serenity::CreateEmbed::new()
+            .title("Slowmode adjusted")
+            .description(format!(
+                "{} had {} messages in the last minute",
+                channel_id.mention(),
+                per_minute
+            ))
+            .field("Before", format!("{}s", current), true)
+            .field("After", format!("{}s", next), true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn steps_slowmode_within_bounds() {
+        let auto_slowmode = AutoSlowmode {
+            channel_id: 1,
+            busy_per_minute: 30,
+            min_seconds: 0,
+            max_seconds: 30,
+        };
+
+        // Busy: up in steps, stopping at the max
+        assert_eq!(auto_slowmode.next_slowmode(50, 0), 5);
+        assert_eq!(auto_slowmode.next_slowmode(50, 5), 10);
+        assert_eq!(auto_slowmode.next_slowmode(50, 20), 30);
+        assert_eq!(auto_slowmode.next_slowmode(50, 30), 30);
+
+        // Somewhat busy: leave it
+        assert_eq!(auto_slowmode.next_slowmode(20, 10), 10);
+
+        // Quiet: back down and off
+        assert_eq!(auto_slowmode.next_slowmode(5, 20), 10);
+        assert_eq!(auto_slowmode.next_slowmode(5, 5), 0);
+        assert_eq!(auto_slowmode.next_slowmode(5, 0), 0);
+
+        let always_on = AutoSlowmode {
+            min_seconds: 3,
+            ..auto_slowmode
+        };
+        assert_eq!(always_on.next_slowmode(0, 5), 3);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{author_guard::is_from_human, data::AppState, lang::ruleset::Ruleset};
+use color_eyre::eyre::{Result, WrapErr};
+use poise::serenity_prelude::{self as serenity};
+use serde::{Deserialize, Serialize};
+
+/// Discord's limit on thread names.
+const MAX_THREAD_NAME_LENGTH: usize = 100;
+
+/// Automatically opens a thread on messages in a busy channel.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AutoThread {
+    pub channel_id: u64,
+    /// Which messages get a thread. Every message if not set.
+    pub ruleset: Option<Ruleset>,
+}
+
+/// The first sentence of the message, trimmed down to fit a thread name.
+fn thread_name(content: &str) -> String {
+    let first_sentence = content
+        .split_inclusive(['.', '?', '!', '\n'])
+        .map(str::trim)
+        .find(|sentence| !sentence.is_empty())
+        .unwrap_or_default();
+
+    if first_sentence.is_empty() {
+        return "Discussion".to_owned();
+    }
+
+    if first_sentence.chars().count() <= MAX_THREAD_NAME_LENGTH {
+        return first_sentence.to_owned();
+    }
+
+    let truncated = first_sentence
+        .chars()
+        .take(MAX_THREAD_NAME_LENGTH - 3)
+        .collect::<String>();
+
+    format!("{}...", truncated.trim_end())
+}
+
+pub async fn create_auto_thread(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<()> {
+    if !is_from_human(ctx, data, message).await {
+        return Ok(());
+    }
+
+    let applies = data
+        .config
+        .read()
+        .await
+        .auto_threads
+        .iter()
+        .any(|auto_thread| {
+            auto_thread.channel_id == message.channel_id.get()
+                && auto_thread
+                    .ruleset
+                    .as_ref()
+                    .is_none_or(|ruleset| ruleset.matches(&message.content))
+        });
+
+    if !applies {
+        return Ok(());
+    }
+
+    message
+        .channel_id
+        .create_thread_from_message(
+            ctx,
+            message.id,
+            serenity::CreateThread::new(thread_name(&message.content)),
+        )
+        .await
+        .wrap_err("Couldn't create thread")?;
+
+    Ok(())
+}
+
+#[test]
+fn check_thread_name() {
+    assert_eq!(
+        thread_name("How do I free a linked list? I keep segfaulting."),
+        "How do I free a linked list?"
+    );
+    assert_eq!(thread_name("\n  hello there\nsecond line"), "hello there");
+    assert_eq!(thread_name(""), "Discussion");
+
+    let long_name = thread_name(&"a".repeat(200));
+    assert_eq!(long_name.chars().count(), MAX_THREAD_NAME_LENGTH);
+    assert!(long_name.ends_with("..."));
+}
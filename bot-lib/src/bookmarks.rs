@@ -0,0 +1,148 @@
+use crate::{data::AppState, db::KingFisherDb};
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, UserId};
+use serde::{Deserialize, Serialize};
+
+const BOOKMARKS_TREE: &str = "bookmarks";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub link: String,
+    pub author: String,
+    pub content: String,
+    pub saved_at: i64,
+}
+
+/// Bookmarks are keyed by user then message, so each user's sort together.
+fn bookmark_key(user_id: UserId, message_id: serenity::MessageId) -> Vec<u8> {
+    [user_id.get().to_be_bytes(), message_id.get().to_be_bytes()].concat()
+}
+
+/// A user's bookmarks, oldest first.
+pub fn get_bookmarks(db: &KingFisherDb, user_id: UserId) -> Result<Vec<Bookmark>> {
+    let prefix = user_id.get().to_be_bytes();
+
+    Ok(db
+        .entries::<Bookmark>(BOOKMARKS_TREE)?
+        .into_iter()
+        .filter(|(key, _)| key.starts_with(&prefix))
+        .map(|(_, bookmark)| bookmark)
+        .collect())
+}
+
+/// Removes all of a user's bookmarks, returning how many there were.
+pub fn clear_bookmarks(db: &KingFisherDb, user_id: UserId) -> Result<usize> {
+    let prefix = user_id.get().to_be_bytes();
+
+    let keys = db
+        .entries::<Bookmark>(BOOKMARKS_TREE)?
+        .into_iter()
+        .map(|(key, _)| key)
+        .filter(|key| key.starts_with(&prefix))
+        .collect::<Vec<_>>();
+
+    for key in &keys {
+        db.remove::<Bookmark>(BOOKMARKS_TREE, key)?;
+    }
+
+    Ok(keys.len())
+}
+
+pub fn format_bookmark(bookmark: &Bookmark) -> String {
+    format!(
+        "**{}** {}\n{}",
+        bookmark.author, bookmark.link, bookmark.content
+    )
+}
+
+/// Saves the message and DMs it to whoever reacted with the bookmark emoji.
+pub async fn save_bookmark(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+    reaction: &serenity::Reaction,
+) -> Result<()> {
+    let Some(bookmark_emoji) = data.config.read().await.bookmark_emoji.clone() else {
+        return Ok(());
+    };
+
+    let Some(user_id) = reaction.user_id else {
+        return Ok(());
+    };
+
+    if reaction.emoji.to_string() != bookmark_emoji {
+        return Ok(());
+    }
+
+    let key = bookmark_key(user_id, message.id);
+
+    if data.db.get::<Bookmark>(BOOKMARKS_TREE, &key)?.is_some() {
+        return Ok(());
+    }
+
+    let bookmark = Bookmark {
+        link: message.link(),
+        author: message.author.name.clone(),
+        content: message.content.clone(),
+        saved_at: Utc::now().timestamp(),
+    };
+
+    data.db.insert(BOOKMARKS_TREE, &key, &bookmark)?;
+
+    let embed = serenity::CreateEmbed::new()
+        .author(
+            serenity::CreateEmbedAuthor::new(&message.author.name).icon_url(message.author.face()),
+        )
+        .title("Jump to message")
+        .url(&bookmark.link)
+        .description(&message.content)
+        .timestamp(message.timestamp);
+
+    let user = user_id.to_user(ctx).await?;
+
+    if let Err(e) = user
+        .direct_message(ctx, serenity::CreateMessage::new().embed(embed))
+        .await
+    {
+        tracing::debug!("Couldn't DM bookmark to {}: {:?}", user_id, e);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keeps_bookmarks_per_user() {
+        let db = KingFisherDb::temporary().unwrap();
+        let (alice, bob) = (UserId::new(1), UserId::new(2));
+
+        let bookmark = |content: &str| Bookmark {
+            link: String::new(),
+            author: "kingfisher".to_owned(),
+            content: content.to_owned(),
+            saved_at: 0,
+        };
+
+        for (user_id, message_id, content) in [(alice, 10, "a"), (bob, 11, "b"), (alice, 12, "c")] {
+            db.insert(
+                BOOKMARKS_TREE,
+                bookmark_key(user_id, serenity::MessageId::new(message_id)),
+                &bookmark(content),
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            get_bookmarks(&db, alice).unwrap(),
+            vec![bookmark("a"), bookmark("c")]
+        );
+
+        assert_eq!(clear_bookmarks(&db, alice).unwrap(), 2);
+        assert!(get_bookmarks(&db, alice).unwrap().is_empty());
+        assert_eq!(get_bookmarks(&db, bob).unwrap(), vec![bookmark("b")]);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::data::AppState;
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, Mentionable, RoleId, UserId};
+use serde::{Deserialize, Serialize};
+
+const BOOSTERS_TREE: &str = "boosters";
+/// Boosts older than this, in seconds, are from before kingfisher was keeping track, and don't
+/// get thanked again.
+const RECENT_BOOST: i64 = 60 * 60;
+
+/// What members get for boosting the server, `[booster_perks]`, taken away again when they stop.
+///
+/// Custom roles are a perk too, through `boosters` in `[custom_roles]`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BoosterPerks {
+    /// Extra roles boosters get, on top of Discord's own booster role.
+    #[serde(default)]
+    pub role_ids: Vec<u64>,
+    /// Whether boosters can `/play` sounds without waiting for the cooldown.
+    #[serde(default)]
+    pub skip_soundboard_cooldown: bool,
+    /// Where to thank new boosters.
+    #[serde(default)]
+    pub shout_out_channel_id: Option<u64>,
+    /// `{user}` is replaced with a mention of the booster.
+    #[serde(default = "get_default_shout_out")]
+    pub shout_out: String,
+}
+
+fn get_default_shout_out() -> String {
+    "Thanks for boosting the server, {user}!".to_owned()
+}
+
+/// Someone who's boosting, as far as perks are concerned.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Booster {
+    since: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BoostChange {
+    Started,
+    Ended,
+}
+
+fn boost_change(was_boosting: bool, is_boosting: bool) -> Option<BoostChange> {
+    match (was_boosting, is_boosting) {
+        (false, true) => Some(BoostChange::Started),
+        (true, false) => Some(BoostChange::Ended),
+        _ => None,
+    }
+}
+
+fn is_recent_boost(since: i64, now: i64) -> bool {
+    now - since < RECENT_BOOST
+}
+
+fn is_booster(data: &AppState, user_id: UserId) -> Result<bool> {
+    Ok(data
+        .db
+        .get::<Booster>(BOOSTERS_TREE, user_id.get().to_be_bytes())?
+        .is_some())
+}
+
+/// Whether the member can skip the soundboard cooldown for boosting.
+pub async fn skips_soundboard_cooldown(data: &AppState, user_id: UserId) -> Result<bool> {
+    let skips = data
+        .config
+        .read()
+        .await
+        .booster_perks
+        .as_ref()
+        .is_some_and(|perks| perks.skip_soundboard_cooldown);
+
+    Ok(skips && is_booster(data, user_id)?)
+}
+
+/// Grants or revokes perks when a member starts or stops boosting.
+///
+/// Compares against who was boosting last time rather than the old member, which isn't always
+/// cached.
+pub async fn update_booster_perks(
+    ctx: &serenity::Context,
+    data: &AppState,
+    member: &serenity::Member,
+) -> Result<()> {
+    let Some(perks) = data.config.read().await.booster_perks.clone() else {
+        return Ok(());
+    };
+
+    let key = member.user.id.get().to_be_bytes();
+    let was_boosting = is_booster(data, member.user.id)?;
+
+    match boost_change(was_boosting, member.premium_since.is_some()) {
+        Some(BoostChange::Started) => {
+            let since = member
+                .premium_since
+                .map_or(0, |since| since.unix_timestamp());
+
+            data.db.insert(BOOSTERS_TREE, key, &Booster { since })?;
+
+            for &role_id in &perks.role_ids {
+                member.add_role(ctx, RoleId::new(role_id)).await?;
+            }
+
+            if let Some(channel_id) = perks
+                .shout_out_channel_id
+                .filter(|_| is_recent_boost(since, chrono::Utc::now().timestamp()))
+            {
+                serenity::ChannelId::new(channel_id)
+                    .say(
+                        ctx,
+                        perks
+                            .shout_out
+                            .replace("{user}", &member.mention().to_string()),
+                    )
+                    .await?;
+            }
+        }
+        Some(BoostChange::Ended) => {
+            data.db.remove::<Booster>(BOOSTERS_TREE, key)?;
+
+            for &role_id in &perks.role_ids {
+                member.remove_role(ctx, RoleId::new(role_id)).await?;
+            }
+        }
+        None => {}
+    }
+
+    Ok(())
+}
+
+/// Forgets a booster who left, their roles went with them.
+pub fn forget_booster(data: &AppState, user_id: UserId) -> Result<()> {
+    data.db
+        .remove::<Booster>(BOOSTERS_TREE, user_id.get().to_be_bytes())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_changes_in_boosting_count() {
+        assert_eq!(boost_change(false, true), Some(BoostChange::Started));
+        assert_eq!(boost_change(true, false), Some(BoostChange::Ended));
+        assert_eq!(boost_change(true, true), None);
+        assert_eq!(boost_change(false, false), None);
+    }
+
+    #[test]
+    fn only_thanks_new_boosts() {
+        assert!(is_recent_boost(1000, 1060));
+        assert!(!is_recent_boost(1000, 1000 + RECENT_BOOST));
+        assert!(!is_recent_boost(0, 1_700_000_000));
+    }
+}
@@ -0,0 +1,93 @@
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::ChannelId;
+
+lazy_static! {
+    static ref BUCKETS: DashMap<ChannelId, TokenBucket> = DashMap::new();
+}
+
+/// Allows `per_minute` responses in a burst, refilling steadily over the minute.
+#[derive(Debug, Clone, PartialEq)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: DateTime<Utc>,
+}
+
+impl TokenBucket {
+    fn new(per_minute: u32, now: DateTime<Utc>) -> Self {
+        TokenBucket {
+            tokens: f64::from(per_minute),
+            last_refill: now,
+        }
+    }
+
+    fn tokens_at(&self, per_minute: u32, now: DateTime<Utc>) -> f64 {
+        let elapsed = (now - self.last_refill).num_milliseconds().max(0) as f64 / 60_000.;
+
+        (self.tokens + elapsed * f64::from(per_minute)).min(f64::from(per_minute))
+    }
+
+    fn try_take(&mut self, per_minute: u32, now: DateTime<Utc>) -> bool {
+        self.tokens = self.tokens_at(per_minute, now);
+        self.last_refill = now;
+
+        if self.tokens < 1. {
+            return false;
+        }
+
+        self.tokens -= 1.;
+        true
+    }
+}
+
+/// Whether [`allow_response`] would let a response through, without using anything up.
+pub fn has_response_budget(channel_id: ChannelId, per_minute: u32, now: DateTime<Utc>) -> bool {
+    BUCKETS
+        .get(&channel_id)
+        .is_none_or(|bucket| bucket.tokens_at(per_minute, now) >= 1.)
+}
+
+/// Whether kingfisher can send another automated response in the channel right now.
+///
+/// Counts every response, whichever matched, so a spam wave can't make kingfisher spam too.
+pub fn allow_response(channel_id: ChannelId, per_minute: u32, now: DateTime<Utc>) -> bool {
+    BUCKETS
+        .entry(channel_id)
+        .or_insert_with(|| TokenBucket::new(per_minute, now))
+        .try_take(per_minute, now)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn limits_bursts() {
+        let now = Utc::now();
+        let mut bucket = TokenBucket::new(3, now);
+
+        assert!(bucket.try_take(3, now));
+        assert!(bucket.try_take(3, now));
+        assert!(bucket.try_take(3, now));
+        assert!(!bucket.try_take(3, now + Duration::seconds(10)));
+
+        // One token back every 20 seconds
+        assert!(bucket.try_take(3, now + Duration::seconds(20)));
+        assert!(!bucket.try_take(3, now + Duration::seconds(21)));
+    }
+
+    #[test]
+    fn limits_channels_separately() {
+        let now = Utc::now();
+        let (first, second) = (ChannelId::new(1001), ChannelId::new(1002));
+
+        assert!(has_response_budget(first, 1, now));
+        assert!(has_response_budget(first, 1, now));
+        assert!(allow_response(first, 1, now));
+        assert!(!has_response_budget(first, 1, now));
+        assert!(!allow_response(first, 1, now));
+        assert!(allow_response(second, 1, now));
+    }
+}
@@ -0,0 +1,114 @@
+use crate::data::PoiseContext;
+use color_eyre::eyre::Result;
+use poise::{
+    serenity_prelude::{Permissions, RoleId},
+    CreateReply,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Things roles can be allowed to do through kingfisher without the server wide Discord
+/// permission, configured in `[capabilities]` as lists of role ids.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// Creating class categories, for class managers.
+    ManageClasses,
+}
+
+impl Capability {
+    /// Members with this Discord permission have the capability without any role.
+    fn permission(self) -> Permissions {
+        match self {
+            Capability::ManageClasses => Permissions::MANAGE_CHANNELS,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Capability::ManageClasses => "manage classes",
+        }
+    }
+}
+
+fn has_capability(
+    capabilities: &HashMap<Capability, Vec<u64>>,
+    capability: Capability,
+    permissions: Permissions,
+    roles: &[RoleId],
+) -> bool {
+    permissions.contains(capability.permission())
+        || capabilities.get(&capability).is_some_and(|role_ids| {
+            roles
+                .iter()
+                .any(|role_id| role_ids.contains(&role_id.get()))
+        })
+}
+
+/// Checks the author has the capability, telling them why not if they don't.
+async fn require_capability(ctx: PoiseContext<'_>, capability: Capability) -> Result<bool> {
+    let (permissions, roles) = ctx
+        .author_member()
+        .await
+        .map(|member| (member.permissions.unwrap_or_default(), member.roles.clone()))
+        .unwrap_or_default();
+
+    if has_capability(
+        &ctx.data().config.read().await.capabilities,
+        capability,
+        permissions,
+        &roles,
+    ) {
+        return Ok(true);
+    }
+
+    ctx.send(CreateReply::default().ephemeral(true).content(format!(
+        "You need a role that can {} to use this.",
+        capability.describe()
+    )))
+    .await?;
+
+    Ok(false)
+}
+
+/// Poise check for commands class managers can use.
+pub async fn can_manage_classes(ctx: PoiseContext<'_>) -> Result<bool> {
+    require_capability(ctx, Capability::ManageClasses).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn roles_or_permissions_grant_capabilities() {
+        let capabilities = HashMap::from([(Capability::ManageClasses, vec![1])]);
+
+        assert!(has_capability(
+            &capabilities,
+            Capability::ManageClasses,
+            Permissions::empty(),
+            &[RoleId::new(2), RoleId::new(1)]
+        ));
+        assert!(has_capability(
+            &capabilities,
+            Capability::ManageClasses,
+            Permissions::MANAGE_CHANNELS,
+            &[]
+        ));
+        assert!(!has_capability(
+            &capabilities,
+            Capability::ManageClasses,
+            Permissions::MANAGE_MESSAGES,
+            &[RoleId::new(2)]
+        ));
+    }
+
+    #[test]
+    fn capabilities_deserialize() {
+        let capabilities: HashMap<Capability, Vec<u64>> =
+            toml::from_str("manage_classes = [1, 2]").unwrap();
+
+        assert_eq!(capabilities[&Capability::ManageClasses], vec![1, 2]);
+    }
+}
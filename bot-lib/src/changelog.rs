@@ -0,0 +1,277 @@
+use crate::data::Data;
+use color_eyre::eyre::Result;
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+
+const CHANGELOG_TREE: &str = "changelog";
+const ANNOUNCED_VERSION_KEY: &str = "announced_version";
+/// The changelog shipped with the bot, used when no other file is configured.
+const EMBEDDED_CHANGELOG: &str = include_str!("../../CHANGELOG.md");
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Discord's limit on embed descriptions.
+const MAX_NOTES_LENGTH: usize = 4096;
+/// Discord's limit on the text of all of a message's embeds together.
+const MAX_EMBEDS_LENGTH: usize = 6000;
+/// Older releases that would get less than this much of their notes are left out instead.
+const MIN_NOTES_LENGTH: usize = 200;
+
+/// Where release notes come from and where new versions get announced.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Default)]
+pub struct Changelog {
+    /// A markdown file to read instead of the changelog built into the bot.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Where the notes get posted when the bot starts up on a new version.
+    #[serde(default)]
+    pub announce_channel_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    pub version: String,
+    pub notes: String,
+}
+
+/// Splits a changelog into releases at each `## <version>` heading, newest first.
+pub fn parse_changelog(text: &str) -> Vec<Release> {
+    let mut releases: Vec<Release> = vec![];
+
+    for line in text.lines() {
+        if let Some(heading) = line.strip_prefix("## ") {
+            // Allows headings like `## [0.2.0] - 2024-08-20`
+            let version = heading
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .trim_matches(|c| c == '[' || c == ']');
+
+            releases.push(Release {
+                version: version.to_owned(),
+                notes: String::new(),
+            });
+        } else if let Some(release) = releases.last_mut() {
+            release.notes.push_str(line);
+            release.notes.push('\n');
+        }
+    }
+
+    for release in &mut releases {
+        release.notes = release.notes.trim().to_owned();
+    }
+
+    releases
+}
+
+/// The configured changelog, falling back to the embedded one if it can't be read.
+pub async fn load_releases(data: &Data) -> Vec<Release> {
+    let path = data
+        .config
+        .read()
+        .await
+        .changelog
+        .as_ref()
+        .and_then(|changelog| changelog.path.clone());
+
+    let text = match path {
+        Some(path) => match tokio::fs::read_to_string(&path).await {
+            Ok(text) => text,
+            Err(e) => {
+                tracing::warn!("Couldn't read changelog {}: {:?}", path, e);
+                EMBEDDED_CHANGELOG.to_owned()
+            }
+        },
+        None => EMBEDDED_CHANGELOG.to_owned(),
+    };
+
+    parse_changelog(&text)
+}
+
+fn release_title(release: &Release) -> String {
+    format!("Kingfisher {}", release.version)
+}
+
+fn truncate_notes(notes: &str, max_length: usize) -> String {
+    if notes.chars().count() > max_length {
+        format!(
+            "{}...",
+            notes
+                .chars()
+                .take(max_length.saturating_sub(3))
+                .collect::<String>()
+        )
+    } else if notes.is_empty() {
+        "No notes for this release.".to_owned()
+    } else {
+        notes.to_owned()
+    }
+}
+
+/// Each release's notes, cut down so they all fit in one message, newest first. Releases that
+/// don't fit are left out.
+fn budget_notes(releases: &[Release]) -> Vec<String> {
+    let mut remaining = MAX_EMBEDS_LENGTH;
+    let mut notes = vec![];
+
+    for release in releases {
+        let available = remaining
+            .saturating_sub(release_title(release).chars().count())
+            .min(MAX_NOTES_LENGTH);
+
+        let full_notes = truncate_notes(&release.notes, MAX_NOTES_LENGTH);
+
+        if available < MIN_NOTES_LENGTH.min(full_notes.chars().count()) {
+            break;
+        }
+
+        let release_notes = truncate_notes(&full_notes, available);
+        remaining -= release_title(release).chars().count() + release_notes.chars().count();
+        notes.push(release_notes);
+    }
+
+    notes
+}
+
+/// An embed per release, as many as fit in one message.
+pub fn release_embeds(releases: &[Release]) -> Vec<serenity::CreateEmbed> {
+    releases
+        .iter()
+        .zip(budget_notes(releases))
+        .map(|(release, notes)| {
+            serenity::CreateEmbed::new()
+                .title(release_title(release))
+                .description(notes)
+                .color(serenity::Color::BLURPLE)
+        })
+        .collect()
+}
+
+/// Whether a version should be announced, given the one last announced.
+///
+/// The first run only records the version, so installing the bot doesn't announce anything.
+fn is_new_version(announced: Option<&str>, current: &str) -> bool {
+    announced.is_some_and(|announced| announced != current)
+}
+
+/// Posts the current version's notes if it's changed since the last time the bot started.
+pub fn start(ctx: serenity::Context, data: Data) {
+    tokio::spawn(async move {
+        if let Err(e) = announce_new_version(&ctx, &data).await {
+            tracing::warn!("Couldn't announce version {}: {:?}", VERSION, e);
+        }
+    });
+}
+
+async fn announce_new_version(ctx: &serenity::Context, data: &Data) -> Result<()> {
+    let announced = data
+        .db
+        .get::<String>(CHANGELOG_TREE, ANNOUNCED_VERSION_KEY)?;
+
+    if announced.as_deref() == Some(VERSION) {
+        return Ok(());
+    }
+
+    let channel_id = data
+        .config
+        .read()
+        .await
+        .changelog
+        .as_ref()
+        .and_then(|changelog| changelog.announce_channel_id);
+
+    if let Some(channel_id) = channel_id.filter(|_| is_new_version(announced.as_deref(), VERSION)) {
+        let release = load_releases(data)
+            .await
+            .into_iter()
+            .find(|release| release.version == VERSION);
+
+        if let Some(release) = release {
+            serenity::ChannelId::new(channel_id)
+                .send_message(
+                    ctx,
+                    serenity::CreateMessage::new()
+                        .content("Kingfisher has been updated!")
+                        .embeds(release_embeds(&[release])),
+                )
+                .await?;
+        }
+    }
+
+    data.db
+        .insert(CHANGELOG_TREE, ANNOUNCED_VERSION_KEY, &VERSION.to_owned())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_releases() {
+        let releases = parse_changelog(
+            "# Changelog\n\nIntro text\n\n## [0.2.0] - 2024-08-20\n\n- New thing\n- Other thing\n\n## 0.1.0\n- First\n",
+        );
+
+        assert_eq!(
+            releases,
+            vec![
+                Release {
+                    version: "0.2.0".to_owned(),
+                    notes: "- New thing\n- Other thing".to_owned(),
+                },
+                Release {
+                    version: "0.1.0".to_owned(),
+                    notes: "- First".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn embedded_changelog_has_this_version() {
+        assert!(parse_changelog(EMBEDDED_CHANGELOG)
+            .iter()
+            .any(|release| release.version == VERSION));
+    }
+
+    #[test]
+    fn notes_fit_in_one_message() {
+        let release = |version: &str, length| Release {
+            version: version.to_owned(),
+            notes: "a".repeat(length),
+        };
+
+        let notes = budget_notes(&[
+            release("0.3.0", 5000),
+            release("0.2.0", 1500),
+            release("0.1.2", 1000),
+            release("0.1.1", 10),
+        ]);
+
+        assert_eq!(notes.len(), 3);
+        assert_eq!(notes[0].chars().count(), MAX_NOTES_LENGTH);
+        assert_eq!(notes[1], "a".repeat(1500));
+        assert!(notes[2].ends_with("..."));
+        assert!(
+            notes
+                .iter()
+                .map(|notes| notes.chars().count())
+                .sum::<usize>()
+                + "Kingfisher 0.3.0".len() * 3
+                <= MAX_EMBEDS_LENGTH
+        );
+
+        let notes = budget_notes(&[release("0.2.0", 100), release("0.1.0", 0)]);
+        assert_eq!(
+            notes,
+            vec!["a".repeat(100), "No notes for this release.".to_owned()]
+        );
+    }
+
+    #[test]
+    fn only_announces_changed_versions() {
+        assert!(!is_new_version(None, "0.1.0"));
+        assert!(!is_new_version(Some("0.1.0"), "0.1.0"));
+        assert!(is_new_version(Some("0.1.0"), "0.2.0"));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{data::AppState, db::KingFisherDb};
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, ChannelId};
+
+const CHANNEL_ACTIVITY_TREE: &str = "channel_activity";
+/// How far back the average goes.
+const AVERAGE_DAYS: i64 = 7;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Days since the epoch, in UTC.
+fn day_of(timestamp: i64) -> i64 {
+    timestamp.div_euclid(SECONDS_PER_DAY)
+}
+
+fn key(channel_id: ChannelId, day: i64) -> Vec<u8> {
+    [channel_id.get().to_be_bytes(), day.to_be_bytes()].concat()
+}
+
+fn count(db: &KingFisherDb, channel_id: ChannelId, timestamp: i64) -> Result<()> {
+    let key = key(channel_id, day_of(timestamp));
+    let messages = db.get::<u32>(CHANNEL_ACTIVITY_TREE, &key)?.unwrap_or(0);
+
+    db.insert(CHANNEL_ACTIVITY_TREE, &key, &(messages + 1))
+}
+
+/// Counts a message towards its channel's daily activity, which the starboards scale by.
+pub fn count_channel_activity(data: &AppState, message: &serenity::Message) -> Result<()> {
+    if message.author.bot {
+        return Ok(());
+    }
+
+    count(
+        &data.db,
+        message.channel_id,
+        message.timestamp.unix_timestamp(),
+    )
+}
+
+fn average_between(db: &KingFisherDb, channel_id: ChannelId, now: i64) -> Result<f64> {
+    let today = day_of(now);
+    let mut total = 0;
+
+    // Today isn't over, so the average is of the full days before it
+    for day in today - AVERAGE_DAYS..today {
+        total += db
+            .get::<u32>(CHANNEL_ACTIVITY_TREE, key(channel_id, day))?
+            .unwrap_or(0);
+    }
+
+    Ok(f64::from(total) / AVERAGE_DAYS as f64)
+}
+
+/// Messages per day in the channel over the last week.
+pub fn daily_average(db: &KingFisherDb, channel_id: ChannelId) -> Result<f64> {
+    average_between(db, channel_id, Utc::now().timestamp())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn averages_the_last_week() {
+        let db = KingFisherDb::temporary().unwrap();
+        let (busy, quiet) = (ChannelId::new(1), ChannelId::new(2));
+        let now = 100 * SECONDS_PER_DAY + 60;
+
+        for day in 93..100 {
+            for _ in 0..10 {
+                count(&db, busy, day * SECONDS_PER_DAY).unwrap();
+            }
+        }
+        // Too old, and today
+        count(&db, busy, 92 * SECONDS_PER_DAY).unwrap();
+        count(&db, busy, now).unwrap();
+        count(&db, quiet, 99 * SECONDS_PER_DAY).unwrap();
+
+        assert_eq!(average_between(&db, busy, now).unwrap(), 10.);
+        assert_eq!(average_between(&db, quiet, now).unwrap(), 1. / 7.);
+    }
+}
@@ -0,0 +1,240 @@
+use crate::class_emoji::DIGITS;
+use color_eyre::eyre::{eyre, Result};
+use dashmap::DashMap;
+use image::{ImageFormat, Rgba, RgbaImage};
+use lazy_static::lazy_static;
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    io::Cursor,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 400;
+/// Room around the plot for the axis labels.
+const MARGIN: u32 = 40;
+/// More bars than this wouldn't be readable at this size.
+const MAX_GROUPS: usize = 60;
+/// How long rendering can take before the text version is used instead.
+const RENDER_BUDGET: Duration = Duration::from_secs(3);
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+const CACHE_SIZE: usize = 32;
+
+const BACKGROUND: Rgba<u8> = Rgba([43, 45, 49, 255]);
+const AXIS: Rgba<u8> = Rgba([148, 155, 164, 255]);
+
+lazy_static! {
+    /// Recently rendered charts, keyed by a hash of the chart.
+    static ref CACHE: DashMap<u64, (Instant, Arc<Vec<u8>>)> = DashMap::new();
+}
+
+/// One set of bars, e.g. joins per week.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Series {
+    pub color: [u8; 3],
+    pub values: Vec<u64>,
+}
+
+/// Bars grouped by position, each series side by side. Groups are numbered from 1 under the x axis.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BarChart {
+    pub series: Vec<Series>,
+}
+
+/// The chart as a PNG, or `None` if it couldn't be made within the time budget.
+pub async fn render_chart(chart: BarChart) -> Option<Arc<Vec<u8>>> {
+    let mut hasher = DefaultHasher::new();
+    chart.hash(&mut hasher);
+    let key = hasher.finish();
+
+    if let Some(cached) = CACHE
+        .get(&key)
+        .filter(|cached| cached.0.elapsed() < CACHE_TTL)
+    {
+        return Some(Arc::clone(&cached.1));
+    }
+
+    let rendered = tokio::time::timeout(
+        RENDER_BUDGET,
+        tokio::task::spawn_blocking(move || render_bar_chart(&chart)),
+    )
+    .await;
+
+    let png = match rendered {
+        Ok(Ok(Ok(png))) => Arc::new(png),
+        Ok(Ok(Err(e))) => {
+            tracing::warn!("Couldn't render chart: {:?}", e);
+            return None;
+        }
+        Ok(Err(e)) => {
+            tracing::warn!("Chart rendering panicked: {:?}", e);
+            return None;
+        }
+        Err(_) => {
+            tracing::warn!("Chart took longer than {:?} to render", RENDER_BUDGET);
+            return None;
+        }
+    };
+
+    CACHE.retain(|_, cached| cached.0.elapsed() < CACHE_TTL);
+
+    if CACHE.len() >= CACHE_SIZE {
+        let oldest = CACHE
+            .iter()
+            .min_by_key(|cached| cached.0)
+            .map(|cached| *cached.key());
+
+        if let Some(oldest) = oldest {
+            CACHE.remove(&oldest);
+        }
+    }
+
+    CACHE.insert(key, (Instant::now(), Arc::clone(&png)));
+
+    Some(png)
+}
+
+fn render_bar_chart(chart: &BarChart) -> Result<Vec<u8>> {
+    let groups = chart
+        .series
+        .iter()
+        .map(|series| series.values.len())
+        .max()
+        .unwrap_or_default();
+
+    if groups == 0 || chart.series.is_empty() {
+        return Err(eyre!("Nothing to chart"));
+    }
+
+    if groups > MAX_GROUPS {
+        return Err(eyre!("Too many bars to chart ({})", groups));
+    }
+
+    let most = chart
+        .series
+        .iter()
+        .flat_map(|series| &series.values)
+        .copied()
+        .max()
+        .unwrap_or_default()
+        .max(1);
+
+    let mut image = RgbaImage::from_pixel(WIDTH, HEIGHT, BACKGROUND);
+
+    let plot_width = WIDTH - 2 * MARGIN;
+    let plot_height = HEIGHT - 2 * MARGIN;
+    let bottom = HEIGHT - MARGIN;
+    let group_width = plot_width / groups as u32;
+    let bar_width = (group_width * 4 / 5 / chart.series.len() as u32).max(1);
+
+    for (i, series) in chart.series.iter().enumerate() {
+        let [r, g, b] = series.color;
+        let color = Rgba([r, g, b, 255]);
+
+        for (group, value) in series.values.iter().enumerate() {
+            let height = (value * u64::from(plot_height) / most) as u32;
+            let left =
+                MARGIN + group as u32 * group_width + group_width / 10 + i as u32 * bar_width;
+
+            fill(&mut image, left, bottom - height, bar_width, height, color);
+        }
+    }
+
+    fill(&mut image, MARGIN, MARGIN, 1, plot_height, AXIS);
+    fill(&mut image, MARGIN, bottom, plot_width, 1, AXIS);
+
+    // The scale, at the top of the y axis
+    draw_number(&mut image, most, 4, MARGIN - 14, 2);
+
+    // Every label when they fit, otherwise every fifth
+    let label_every = if group_width >= 16 { 1 } else { 5 };
+
+    for group in (0..groups).filter(|&group| (group + 1) % label_every == 0 || group == 0) {
+        let x = MARGIN + group as u32 * group_width + group_width / 2;
+        draw_number(
+            &mut image,
+            group as u64 + 1,
+            x.saturating_sub(4),
+            bottom + 8,
+            2,
+        );
+    }
+
+    let mut png = vec![];
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+
+    Ok(png)
+}
+
+fn fill(image: &mut RgbaImage, left: u32, top: u32, width: u32, height: u32, color: Rgba<u8>) {
+    for x in left..(left + width).min(image.width()) {
+        for y in top..(top + height).min(image.height()) {
+            image.put_pixel(x, y, color);
+        }
+    }
+}
+
+fn draw_number(image: &mut RgbaImage, number: u64, left: u32, top: u32, scale: u32) {
+    for (i, digit) in number.to_string().bytes().enumerate() {
+        for (row, bits) in DIGITS[usize::from(digit - b'0')].iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) != 0 {
+                    fill(
+                        image,
+                        left + (i as u32 * 4 + column) * scale,
+                        top + row as u32 * scale,
+                        scale,
+                        scale,
+                        AXIS,
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chart(values: Vec<u64>) -> BarChart {
+        BarChart {
+            series: vec![
+                Series {
+                    color: [0, 255, 0],
+                    values: values.clone(),
+                },
+                Series {
+                    color: [255, 0, 0],
+                    values,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn draws_bars() {
+        let png = render_bar_chart(&chart(vec![3, 0, 7])).unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgba8();
+
+        assert_eq!(image.dimensions(), (WIDTH, HEIGHT));
+        assert_eq!(image.get_pixel(0, 0), &BACKGROUND);
+        assert!(image.pixels().any(|pixel| *pixel == Rgba([0, 255, 0, 255])));
+        assert!(image.pixels().any(|pixel| *pixel == Rgba([255, 0, 0, 255])));
+    }
+
+    #[test]
+    fn refuses_charts_it_cant_draw() {
+        assert!(render_bar_chart(&chart(vec![])).is_err());
+        assert!(render_bar_chart(&chart(vec![1; MAX_GROUPS + 1])).is_err());
+    }
+
+    #[tokio::test]
+    async fn caches_rendered_charts() {
+        let first = render_chart(chart(vec![1, 2, 3])).await.unwrap();
+        let second = render_chart(chart(vec![1, 2, 3])).await.unwrap();
+
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+}
@@ -0,0 +1,173 @@
+use color_eyre::eyre::{Result, WrapErr};
+use flate2::{write::GzEncoder, Compression};
+use futures::StreamExt;
+use poise::serenity_prelude::{self as serenity};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// Where class channel exports go before a class category is deleted.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ClassArchive {
+    /// Mod only channel to upload the archive to.
+    pub channel_id: Option<u64>,
+    /// Local directory to write the archive to.
+    pub path: Option<String>,
+    /// Stop collecting messages once the (uncompressed) export gets this big.
+    #[serde(default = "get_default_max_bytes")]
+    pub max_bytes: usize,
+}
+
+fn get_default_max_bytes() -> usize {
+    50 * 1024 * 1024
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ArchivedMessage {
+    pub author: String,
+    pub author_id: u64,
+    pub timestamp: String,
+    pub content: String,
+    pub attachments: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ArchivedChannel {
+    pub name: String,
+    pub id: u64,
+    /// Oldest first
+    pub messages: Vec<ArchivedMessage>,
+    /// Set if the size cap was hit before the whole history was read.
+    pub truncated: bool,
+}
+
+impl From<&serenity::Message> for ArchivedMessage {
+    fn from(message: &serenity::Message) -> Self {
+        ArchivedMessage {
+            author: message.author.name.clone(),
+            author_id: message.author.id.get(),
+            timestamp: message.timestamp.to_rfc3339().unwrap_or_default(),
+            content: message.content.clone(),
+            attachments: message
+                .attachments
+                .iter()
+                .map(|attachment| attachment.url.clone())
+                .collect(),
+        }
+    }
+}
+
+/// Reads the channel history (newest to oldest) until `remaining_bytes` runs out.
+pub async fn archive_channel(
+    ctx: &serenity::Context,
+    channel: &serenity::GuildChannel,
+    remaining_bytes: &mut usize,
+) -> Result<ArchivedChannel> {
+    let mut messages = vec![];
+    let mut truncated = false;
+    let mut history = channel.id.messages_iter(ctx).boxed();
+
+    while let Some(message) = history.next().await {
+        let message = ArchivedMessage::from(&message.wrap_err("Couldn't read history")?);
+        let size = serde_json::to_vec(&message)?.len();
+
+        if size > *remaining_bytes {
+            truncated = true;
+            break;
+        }
+
+        *remaining_bytes -= size;
+        messages.push(message);
+    }
+
+    messages.reverse();
+
+    Ok(ArchivedChannel {
+        name: channel.name.clone(),
+        id: channel.id.get(),
+        messages,
+        truncated,
+    })
+}
+
+pub fn compress_archive(channels: &[ArchivedChannel]) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec_pretty(channels).wrap_err("Couldn't serialize archive")?;
+
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(&json)?;
+
+    encoder.finish().wrap_err("Couldn't compress archive")
+}
+
+impl ClassArchive {
+    /// Exports the given channels and sends the archive everywhere that is configured.
+    pub async fn export(
+        &self,
+        ctx: &serenity::Context,
+        file_name: &str,
+        channels: &[&serenity::GuildChannel],
+    ) -> Result<()> {
+        let mut remaining_bytes = self.max_bytes;
+        let mut archived = vec![];
+
+        for channel in channels {
+            archived.push(archive_channel(ctx, channel, &mut remaining_bytes).await?);
+        }
+
+        let archive = compress_archive(&archived)?;
+
+        if let Some(path) = &self.path {
+            let path = std::path::Path::new(path).join(file_name);
+
+            tokio::fs::write(&path, &archive)
+                .await
+                .wrap_err("Couldn't write archive")?;
+
+            tracing::info!("Wrote class archive to {}", path.display());
+        }
+
+        if let Some(channel_id) = self.channel_id {
+            serenity::ChannelId::new(channel_id)
+                .send_message(
+                    ctx,
+                    serenity::CreateMessage::new()
+                        .content(format!("Archive of {}", file_name))
+                        .add_file(serenity::CreateAttachment::bytes(archive, file_name)),
+                )
+                .await
+                .wrap_err("Couldn't upload archive")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn check_compress_archive_round_trips() {
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    let channels = vec![ArchivedChannel {
+        name: "2420-general".to_owned(),
+        id: 1,
+        messages: vec![ArchivedMessage {
+            author: "kingfisher".to_owned(),
+            author_id: 2,
+            timestamp: "2024-01-01T00:00:00Z".to_owned(),
+            content: "hello".to_owned(),
+            attachments: vec![],
+        }],
+        truncated: false,
+    }];
+
+    let compressed = compress_archive(&channels).unwrap();
+
+    let mut json = String::new();
+    GzDecoder::new(&compressed[..])
+        .read_to_string(&mut json)
+        .unwrap();
+
+    assert_eq!(
+        serde_json::from_str::<Vec<ArchivedChannel>>(&json).unwrap(),
+        channels
+    );
+}
@@ -0,0 +1,225 @@
+use crate::{data::AppState, utils::get_class_category};
+use chrono::{Duration, Utc};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity, ChannelId, MessageId};
+use serde::{Deserialize, Serialize};
+
+const CLASS_ACTIVITY_TREE: &str = "class_activity";
+const DIGEST_SIZE: usize = 5;
+const DIGEST_PERIOD: Duration = match Duration::try_weeks(1) {
+    Some(period) => period,
+    None => panic!("Failed to create digest period"),
+};
+
+/// A message in a class channel, with how much attention it got.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TrackedMessage {
+    channel_id: u64,
+    timestamp: i64,
+    preview: String,
+    reactions: u64,
+    replies: u64,
+}
+
+impl TrackedMessage {
+    fn score(&self) -> u64 {
+        self.reactions + self.replies
+    }
+}
+
+/// Starts tracking a new message in a class channel, and counts it as a reply if it is one.
+pub async fn track_message(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<()> {
+    let class_categories = {
+        let config = data.config.read().await;
+
+        if !config.class_digest {
+            return Ok(());
+        }
+
+        config.class_categories.clone()
+    };
+
+    if get_class_category(ctx, message.channel_id, &class_categories)
+        .await
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    let tracked = TrackedMessage {
+        channel_id: message.channel_id.get(),
+        timestamp: message.timestamp.unix_timestamp(),
+        preview: message.content.chars().take(80).collect(),
+        reactions: 0,
+        replies: 0,
+    };
+
+    data.db.insert(
+        CLASS_ACTIVITY_TREE,
+        message.id.get().to_be_bytes(),
+        &tracked,
+    )?;
+
+    let Some(replied_to) = message
+        .message_reference
+        .as_ref()
+        .and_then(|reference| reference.message_id)
+    else {
+        return Ok(());
+    };
+
+    update_tracked(data, replied_to, |tracked| tracked.replies += 1)
+}
+
+/// Updates the reaction count of a tracked message.
+pub fn track_reactions(data: &AppState, message: &serenity::Message) -> Result<()> {
+    let reactions = message
+        .reactions
+        .iter()
+        .map(|reaction| reaction.count)
+        .sum();
+
+    update_tracked(data, message.id, |tracked| tracked.reactions = reactions)
+}
+
+fn update_tracked(
+    data: &AppState,
+    message_id: MessageId,
+    update: impl FnOnce(&mut TrackedMessage),
+) -> Result<()> {
+    let key = message_id.get().to_be_bytes();
+
+    let Some(mut tracked) = data.db.get::<TrackedMessage>(CLASS_ACTIVITY_TREE, key)? else {
+        return Ok(());
+    };
+
+    update(&mut tracked);
+
+    data.db.insert(CLASS_ACTIVITY_TREE, key, &tracked)
+}
+
+/// The best messages of each channel, best first.
+fn top_messages(
+    messages: Vec<(u64, TrackedMessage)>,
+    since: i64,
+) -> Vec<(u64, Vec<(u64, TrackedMessage)>)> {
+    messages
+        .into_iter()
+        .filter(|(_, message)| message.timestamp >= since && message.score() > 0)
+        .into_group_map_by(|(_, message)| message.channel_id)
+        .into_iter()
+        .map(|(channel_id, messages)| {
+            let top = messages
+                .into_iter()
+                .sorted_by_key(|(_, message)| std::cmp::Reverse(message.score()))
+                .take(DIGEST_SIZE)
+                .collect();
+
+            (channel_id, top)
+        })
+        .sorted_by_key(|(channel_id, _)| *channel_id)
+        .collect()
+}
+
+/// Posts a digest of the past week in every class channel, then forgets old messages.
+pub async fn post_class_digests(ctx: &serenity::Context, data: &AppState) -> Result<()> {
+    let (enabled, guild_id) = {
+        let config = data.config.read().await;
+        (config.class_digest, config.guild_id)
+    };
+
+    if !enabled {
+        return Ok(());
+    }
+
+    let since = (Utc::now() - DIGEST_PERIOD).timestamp();
+
+    let messages = data
+        .db
+        .entries::<TrackedMessage>(CLASS_ACTIVITY_TREE)?
+        .into_iter()
+        .filter_map(|(key, message)| Some((u64::from_be_bytes(key.try_into().ok()?), message)))
+        .collect_vec();
+
+    for (key, message) in &messages {
+        if message.timestamp < since {
+            data.db
+                .remove::<TrackedMessage>(CLASS_ACTIVITY_TREE, key.to_be_bytes())?;
+        }
+    }
+
+    for (channel_id, top) in top_messages(messages, since) {
+        let description = top
+            .iter()
+            .map(|(message_id, message)| {
+                format!(
+                    "- [{}](https://discord.com/channels/{}/{}/{}) ({} reactions, {} replies)",
+                    if message.preview.is_empty() {
+                        "(attachment)"
+                    } else {
+                        &message.preview
+                    },
+                    guild_id,
+                    channel_id,
+                    message_id,
+                    message.reactions,
+                    message.replies
+                )
+            })
+            .join("\n");
+
+        ChannelId::new(channel_id)
+            .send_message(
+                ctx,
+                serenity::CreateMessage::new().embed(
+                    serenity::CreateEmbed::new()
+                        .title("This week's top messages")
+                        .description(description),
+                ),
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tracked(channel_id: u64, timestamp: i64, reactions: u64) -> TrackedMessage {
+        TrackedMessage {
+            channel_id,
+            timestamp,
+            preview: String::new(),
+            reactions,
+            replies: 0,
+        }
+    }
+
+    #[test]
+    fn picks_top_messages_per_channel() {
+        let messages = vec![
+            (1, tracked(10, 100, 1)),
+            (2, tracked(10, 100, 5)),
+            (3, tracked(20, 100, 2)),
+            // Too old
+            (4, tracked(20, 0, 10)),
+            // Nobody cared
+            (5, tracked(20, 100, 0)),
+        ];
+
+        assert_eq!(
+            top_messages(messages, 50),
+            vec![
+                (10, vec![(2, tracked(10, 100, 5)), (1, tracked(10, 100, 1))]),
+                (20, vec![(3, tracked(20, 100, 2))]),
+            ]
+        );
+    }
+}
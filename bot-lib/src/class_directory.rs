@@ -0,0 +1,602 @@
+use crate::{
+    class_emoji::get_class_emojis,
+    class_info::{get_all_class_info, ClassInfo},
+    class_notifications::{find_notification_role, set_class_notifications},
+    data::AppState,
+    departments::{department_of, KnownClasses},
+};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity, ChannelId, GuildId, MessageId, RoleId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+const CLASS_DIRECTORY_TREE: &str = "class_directory";
+const DIRECTORY_SELECT_PREFIX: &str = "class_directory:";
+const NOTIFICATIONS_BUTTON_PREFIX: &str = "class_directory_notifications:";
+const NOTIFICATIONS_SELECT_PREFIX: &str = "class_directory_notifications_select:";
+/// Discord allows 25 options per select menu, and 5 rows per message, one of which is the
+/// notifications button.
+const OPTIONS_PER_MENU: usize = 25;
+const MENUS_PER_MESSAGE: usize = 4;
+/// Discord allows 20 different reactions on a message.
+const MAX_REACTIONS: usize = 20;
+
+/// A channel kingfisher keeps a list of every class in, with menus to join and leave them.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ClassDirectory {
+    pub channel_id: u64,
+}
+
+/// The directory message for a department.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct DirectoryMessage {
+    department: String,
+    message_id: u64,
+}
+
+/// Class roles by department, like `CS => [(1410, role), (2420, role)]`.
+pub fn group_classes(
+    roles: &[serenity::Role],
+    known: &KnownClasses,
+) -> BTreeMap<String, Vec<(u32, RoleId)>> {
+    let mut departments = BTreeMap::<String, Vec<(u32, RoleId)>>::new();
+
+    for role in roles {
+        if !known.is_class(&role.name) {
+            continue;
+        }
+
+        let Some(department) = department_of(&role.name) else {
+            continue;
+        };
+        let Some(number) = role
+            .name
+            .rsplit_once(' ')
+            .and_then(|(_, number)| number.parse().ok())
+        else {
+            continue;
+        };
+
+        departments
+            .entry(department.to_owned())
+            .or_default()
+            .push((number, role.id));
+    }
+
+    for classes in departments.values_mut() {
+        classes.sort();
+    }
+
+    departments
+}
+
+/// The embed and menus listing a department's classes.
+fn directory_message(
+    department: &str,
+    classes: &[(u32, RoleId)],
+    emojis: &HashMap<u32, u64>,
+    info: &HashMap<u32, ClassInfo>,
+) -> (serenity::CreateEmbed, Vec<serenity::CreateActionRow>) {
+    let list = classes
+        .iter()
+        .map(|(number, _)| {
+            let mut line = match emojis.get(number) {
+                Some(emoji_id) => format!("<:cs{}:{}> {} {}", number, emoji_id, department, number),
+                None => format!("{} {}", department, number),
+            };
+
+            if let Some(summary) = info.get(number).and_then(ClassInfo::summary) {
+                line.push_str(&format!(" ({})", summary));
+            }
+
+            line
+        })
+        .join("\n");
+
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("{} classes", department))
+        .description(list)
+        .footer(serenity::CreateEmbedFooter::new(
+            "Pick classes below to join them, or pick ones you're in to leave them",
+        ));
+
+    let mut rows = classes
+        .chunks(OPTIONS_PER_MENU)
+        .take(MENUS_PER_MESSAGE)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let options = chunk
+                .iter()
+                .map(|(number, _)| {
+                    serenity::CreateSelectMenuOption::new(
+                        format!("{} {}", department, number),
+                        number.to_string(),
+                    )
+                })
+                .collect_vec();
+
+            serenity::CreateActionRow::SelectMenu(
+                serenity::CreateSelectMenu::new(
+                    format!("{}{}:{}", DIRECTORY_SELECT_PREFIX, department, i),
+                    serenity::CreateSelectMenuKind::String { options },
+                )
+                .placeholder(format!(
+                    "Join or leave {} {}-{}",
+                    department,
+                    chunk.first().map_or(0, |(number, _)| *number),
+                    chunk.last().map_or(0, |(number, _)| *number)
+                ))
+                .min_values(0)
+                .max_values(chunk.len() as u8),
+            )
+        })
+        .collect_vec();
+
+    rows.push(serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new(format!("{}{}", NOTIFICATIONS_BUTTON_PREFIX, department))
+            .label("Class pings")
+            .style(serenity::ButtonStyle::Secondary),
+    ]));
+
+    (embed, rows)
+}
+
+/// Rebuilds the directory messages, editing the ones that are still around.
+pub async fn refresh_class_directory(
+    ctx: &serenity::Context,
+    data: &AppState,
+    guild_id: GuildId,
+) -> Result<()> {
+    let Some(channel_id) = data
+        .config
+        .read()
+        .await
+        .class_directory
+        .as_ref()
+        .map(|directory| ChannelId::new(directory.channel_id))
+    else {
+        return Ok(());
+    };
+
+    let roles = guild_id.roles(ctx).await?.into_values().collect_vec();
+    let known = KnownClasses::load(ctx, data, guild_id).await?;
+    let departments = group_classes(&roles, &known);
+    let emojis = get_class_emojis(&data.db)?;
+    let info = get_all_class_info(&data.db)?;
+
+    let old_messages = data
+        .db
+        .values::<DirectoryMessage>(CLASS_DIRECTORY_TREE)?
+        .into_iter()
+        .map(|message| (message.department.clone(), message))
+        .collect::<HashMap<_, _>>();
+
+    for (department, classes) in &departments {
+        let (embed, menus) = directory_message(department, classes, &emojis, &info);
+
+        let edited = match old_messages.get(department) {
+            Some(old) => channel_id
+                .edit_message(
+                    ctx,
+                    MessageId::new(old.message_id),
+                    serenity::EditMessage::new()
+                        .embed(embed.clone())
+                        .components(menus.clone()),
+                )
+                .await
+                .ok(),
+            None => None,
+        };
+
+        let message = match edited {
+            Some(message) => message,
+            None => {
+                channel_id
+                    .send_message(
+                        ctx,
+                        serenity::CreateMessage::new()
+                            .embed(embed)
+                            .components(menus),
+                    )
+                    .await?
+            }
+        };
+
+        data.db.insert(
+            CLASS_DIRECTORY_TREE,
+            department.as_bytes(),
+            &DirectoryMessage {
+                department: department.clone(),
+                message_id: message.id.get(),
+            },
+        )?;
+
+        // So there's something to click for joining by reaction
+        for (number, _) in classes.iter().take(MAX_REACTIONS) {
+            if let Some(emoji_id) = emojis.get(number) {
+                let emoji = serenity::ReactionType::Custom {
+                    animated: false,
+                    id: (*emoji_id).into(),
+                    name: Some(format!("cs{}", number)),
+                };
+
+                if let Err(e) = message.react(ctx, emoji).await {
+                    tracing::debug!("Couldn't react with cs{}: {:?}", number, e);
+                }
+            }
+        }
+    }
+
+    for (department, old) in old_messages {
+        if departments.contains_key(&department) {
+            continue;
+        }
+
+        if let Err(e) = channel_id
+            .delete_message(ctx, MessageId::new(old.message_id))
+            .await
+        {
+            tracing::debug!("Couldn't delete directory message: {:?}", e);
+        }
+
+        data.db
+            .remove::<DirectoryMessage>(CLASS_DIRECTORY_TREE, department.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Joins the picked classes the member isn't in, and leaves the ones they are.
+pub async fn handle_directory_select(
+    ctx: &serenity::Context,
+    data: &AppState,
+    interaction: &serenity::ComponentInteraction,
+) -> Result<()> {
+    let Some(department) = interaction
+        .data
+        .custom_id
+        .strip_prefix(DIRECTORY_SELECT_PREFIX)
+        .and_then(|rest| rest.split_once(':'))
+        .map(|(department, _)| department)
+    else {
+        return Ok(());
+    };
+
+    let (serenity::ComponentInteractionDataKind::StringSelect { values }, Some(guild_id)) =
+        (&interaction.data.kind, interaction.guild_id)
+    else {
+        return Ok(());
+    };
+
+    interaction
+        .create_response(
+            ctx,
+            serenity::CreateInteractionResponse::Defer(
+                serenity::CreateInteractionResponseMessage::new().ephemeral(true),
+            ),
+        )
+        .await?;
+
+    let roles = guild_id.roles(ctx).await?;
+    let known = KnownClasses::load(ctx, data, guild_id).await?;
+    let classes = group_classes(&roles.values().cloned().collect_vec(), &known)
+        .remove(department)
+        .unwrap_or_default();
+    let member_roles = interaction
+        .member
+        .as_ref()
+        .map(|member| member.roles.clone())
+        .unwrap_or_default();
+
+    let mut changes = vec![];
+
+    for value in values {
+        let Some((number, role_id)) = classes
+            .iter()
+            .find(|(number, _)| number.to_string() == *value)
+        else {
+            continue;
+        };
+
+        // Only ever hand out classes, whatever the menu said
+        if !roles
+            .get(role_id)
+            .is_some_and(|role| known.is_class(&role.name))
+        {
+            continue;
+        }
+
+        let joining = !member_roles.contains(role_id);
+
+        if joining {
+            ctx.http
+                .add_member_role(
+                    guild_id,
+                    interaction.user.id,
+                    *role_id,
+                    Some("Joined from the class directory"),
+                )
+                .await?;
+            changes.push(format!("Joined {} {}", department, number));
+        } else {
+            ctx.http
+                .remove_member_role(
+                    guild_id,
+                    interaction.user.id,
+                    *role_id,
+                    Some("Left from the class directory"),
+                )
+                .await?;
+            changes.push(format!("Left {} {}", department, number));
+        }
+
+        set_class_notifications(
+            ctx,
+            guild_id,
+            interaction.user.id,
+            find_notification_role(&roles, *role_id),
+            joining,
+        )
+        .await?;
+    }
+
+    interaction
+        .create_followup(
+            ctx,
+            serenity::CreateInteractionResponseFollowup::new()
+                .ephemeral(true)
+                .content(if changes.is_empty() {
+                    "Nothing changed.".to_owned()
+                } else {
+                    changes.join("\n")
+                }),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// The member's classes in the department that have notification roles, with whether they're
+/// getting pinged for each.
+fn notifiable_classes(
+    roles: &HashMap<RoleId, serenity::Role>,
+    known: &KnownClasses,
+    department: &str,
+    member_roles: &[RoleId],
+) -> Vec<(u32, RoleId, bool)> {
+    group_classes(&roles.values().cloned().collect_vec(), known)
+        .remove(department)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, role_id)| member_roles.contains(role_id))
+        .filter_map(|(number, role_id)| {
+            let notification_role_id = find_notification_role(roles, role_id)?;
+
+            Some((
+                number,
+                notification_role_id,
+                member_roles.contains(&notification_role_id),
+            ))
+        })
+        .take(OPTIONS_PER_MENU)
+        .collect()
+}
+
+/// Shows the member a menu of their classes in the department, with the ones pinging them picked.
+pub async fn handle_notifications_button(
+    ctx: &serenity::Context,
+    data: &AppState,
+    interaction: &serenity::ComponentInteraction,
+) -> Result<()> {
+    let (Some(department), Some(guild_id)) = (
+        interaction
+            .data
+            .custom_id
+            .strip_prefix(NOTIFICATIONS_BUTTON_PREFIX),
+        interaction.guild_id,
+    ) else {
+        return Ok(());
+    };
+
+    let roles = guild_id.roles(ctx).await?;
+    let member_roles = interaction
+        .member
+        .as_ref()
+        .map(|member| member.roles.clone())
+        .unwrap_or_default();
+    let known = KnownClasses::load(ctx, data, guild_id).await?;
+    let classes = notifiable_classes(&roles, &known, department, &member_roles);
+
+    let response = if classes.is_empty() {
+        serenity::CreateInteractionResponseMessage::new().content(format!(
+            "None of your {} classes have announcement pings.",
+            department
+        ))
+    } else {
+        let options = classes
+            .iter()
+            .map(|(number, _, on)| {
+                serenity::CreateSelectMenuOption::new(
+                    format!("{} {}", department, number),
+                    number.to_string(),
+                )
+                .default_selection(*on)
+            })
+            .collect_vec();
+
+        serenity::CreateInteractionResponseMessage::new()
+            .content("Pick the classes you want to be pinged for:")
+            .select_menu(
+                serenity::CreateSelectMenu::new(
+                    format!("{}{}", NOTIFICATIONS_SELECT_PREFIX, department),
+                    serenity::CreateSelectMenuKind::String { options },
+                )
+                .min_values(0)
+                .max_values(classes.len() as u8),
+            )
+    };
+
+    interaction
+        .create_response(
+            ctx,
+            serenity::CreateInteractionResponse::Message(response.ephemeral(true)),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Pings on for the picked classes, off for the rest.
+pub async fn handle_notifications_select(
+    ctx: &serenity::Context,
+    data: &AppState,
+    interaction: &serenity::ComponentInteraction,
+) -> Result<()> {
+    let Some(department) = interaction
+        .data
+        .custom_id
+        .strip_prefix(NOTIFICATIONS_SELECT_PREFIX)
+    else {
+        return Ok(());
+    };
+
+    let (serenity::ComponentInteractionDataKind::StringSelect { values }, Some(guild_id)) =
+        (&interaction.data.kind, interaction.guild_id)
+    else {
+        return Ok(());
+    };
+
+    let roles = guild_id.roles(ctx).await?;
+    let member_roles = interaction
+        .member
+        .as_ref()
+        .map(|member| member.roles.clone())
+        .unwrap_or_default();
+
+    let mut changes = vec![];
+
+    let known = KnownClasses::load(ctx, data, guild_id).await?;
+
+    for (number, notification_role_id, on) in
+        notifiable_classes(&roles, &known, department, &member_roles)
+    {
+        let wanted = values.contains(&number.to_string());
+
+        if wanted == on {
+            continue;
+        }
+
+        set_class_notifications(
+            ctx,
+            guild_id,
+            interaction.user.id,
+            Some(notification_role_id),
+            wanted,
+        )
+        .await?;
+        changes.push(format!(
+            "{} pings for {} {}",
+            if wanted { "Turned on" } else { "Turned off" },
+            department,
+            number
+        ));
+    }
+
+    interaction
+        .create_response(
+            ctx,
+            serenity::CreateInteractionResponse::UpdateMessage(
+                serenity::CreateInteractionResponseMessage::new()
+                    .content(if changes.is_empty() {
+                        "Nothing changed.".to_owned()
+                    } else {
+                        changes.join("\n")
+                    })
+                    .components(vec![]),
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn role(id: u64, name: &str) -> serenity::Role {
+        let mut role = serenity::Role::default();
+        role.id = RoleId::new(id);
+        role.name = name.to_owned();
+        role
+    }
+
+    #[test]
+    fn groups_classes_by_department() {
+        let roles = [
+            role(1, "CS 3500"),
+            role(2, "CS 2420"),
+            role(3, "MATH 2270"),
+            role(4, "Mods"),
+            role(5, "Level 5"),
+            role(6, "PHYS 2210"),
+        ];
+        let known = KnownClasses::new(["CS".to_owned(), "MATH".to_owned()], []);
+
+        let departments = group_classes(&roles, &known);
+
+        assert_eq!(departments.keys().collect_vec(), vec!["CS", "MATH"]);
+        assert_eq!(
+            departments["CS"],
+            vec![(2420, RoleId::new(2)), (3500, RoleId::new(1))]
+        );
+    }
+
+    #[test]
+    fn lists_classes_with_notification_roles() {
+        let roles = [
+            role(1, "CS 2420"),
+            role(2, "CS 2420 Announcements"),
+            role(3, "CS 3500"),
+            role(4, "CS 3500 Announcements"),
+            role(5, "CS 4400"),
+        ]
+        .into_iter()
+        .map(|role| (role.id, role))
+        .collect::<HashMap<_, _>>();
+
+        let member_roles = [1, 2, 3, 5].map(RoleId::new);
+
+        assert_eq!(
+            notifiable_classes(
+                &roles,
+                &KnownClasses::new(["CS".to_owned()], []),
+                "CS",
+                &member_roles
+            ),
+            vec![(2420, RoleId::new(2), true), (3500, RoleId::new(4), false)]
+        );
+    }
+
+    #[test]
+    fn splits_big_departments_into_menus() {
+        let classes = (1000..1030)
+            .map(|number| (number, RoleId::new(u64::from(number))))
+            .collect_vec();
+
+        let (_, menus) = directory_message("CS", &classes, &HashMap::new(), &HashMap::new());
+        let menus = serde_json::to_value(&menus).unwrap();
+
+        assert_eq!(menus.as_array().unwrap().len(), 3);
+        assert_eq!(
+            menus[0]["components"][0]["custom_id"],
+            "class_directory:CS:0"
+        );
+        assert_eq!(menus[1]["components"][0]["max_values"], 5);
+        assert_eq!(
+            menus[2]["components"][0]["custom_id"],
+            "class_directory_notifications:CS"
+        );
+    }
+}
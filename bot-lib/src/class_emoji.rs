@@ -0,0 +1,267 @@
+use crate::{commands::find_class_role, data::AppState, db::KingFisherDb, departments::HexColor};
+use color_eyre::eyre::Result;
+use image::{imageops, ImageFormat, Rgba, RgbaImage};
+use poise::serenity_prelude::{self as serenity, EmojiId, GuildId};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::{collections::HashMap, io::Cursor};
+
+const CLASS_EMOJIS_TREE: &str = "class_emojis";
+/// Discord shows emojis at most this big.
+const EMOJI_SIZE: u32 = 128;
+
+/// 3x5 pixel digits, each row's bits read left to right.
+pub(crate) const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// Per-class emojis, made from a template with the class number drawn on.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ClassEmoji {
+    /// Path to the template image.
+    pub template: String,
+    #[serde_as(as = "DisplayFromStr")]
+    #[serde(default = "get_default_digit_color")]
+    pub digit_color: HexColor,
+}
+
+fn get_default_digit_color() -> HexColor {
+    HexColor(0xffffff)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ClassEmojiRecord {
+    number: u32,
+    emoji_id: u64,
+}
+
+/// The template scaled to emoji size with the number drawn in the middle, as a PNG.
+pub fn render_class_emoji(template: &RgbaImage, number: u32, color: HexColor) -> Result<Vec<u8>> {
+    let mut image = imageops::resize(
+        template,
+        EMOJI_SIZE,
+        EMOJI_SIZE,
+        imageops::FilterType::Triangle,
+    );
+
+    let digits = number
+        .to_string()
+        .bytes()
+        .map(|digit| usize::from(digit - b'0'))
+        .collect::<Vec<_>>();
+
+    // Each digit is 3 units wide with a unit of space between
+    let units_wide = digits.len() as u32 * 4 - 1;
+    let scale = (EMOJI_SIZE * 9 / 10 / units_wide).clamp(1, EMOJI_SIZE / 2 / 5);
+    let left = (EMOJI_SIZE - units_wide * scale) / 2;
+    let top = (EMOJI_SIZE - 5 * scale) / 2;
+    let shadow = (scale / 4).max(1);
+
+    let HexColor(color) = color;
+    let color = Rgba([(color >> 16) as u8, (color >> 8) as u8, color as u8, 255]);
+
+    for (offset, pixel) in [(shadow, Rgba([0, 0, 0, 255])), (0, color)] {
+        for (i, digit) in digits.iter().enumerate() {
+            for (row, bits) in DIGITS[*digit].iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) == 0 {
+                        continue;
+                    }
+
+                    let x = left + (i as u32 * 4 + column) * scale + offset;
+                    let y = top + row as u32 * scale + offset;
+
+                    for dx in 0..scale {
+                        for dy in 0..scale {
+                            if x + dx < EMOJI_SIZE && y + dy < EMOJI_SIZE {
+                                image.put_pixel(x + dx, y + dy, pixel);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut png = vec![];
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+
+    Ok(png)
+}
+
+/// Uploads the `:cs<number>:` emoji and remembers which class it's for.
+pub async fn create_class_emoji(
+    ctx: &serenity::Context,
+    db: &KingFisherDb,
+    guild_id: GuildId,
+    class_emoji: &ClassEmoji,
+    number: u32,
+) -> Result<serenity::Emoji> {
+    let template = image::open(&class_emoji.template)?.to_rgba8();
+    let png = render_class_emoji(&template, number, class_emoji.digit_color)?;
+
+    let emoji = guild_id
+        .create_emoji(
+            ctx,
+            &format!("cs{}", number),
+            &serenity::CreateAttachment::bytes(png, format!("cs{}.png", number)).to_base64(),
+        )
+        .await?;
+
+    db.insert(
+        CLASS_EMOJIS_TREE,
+        number.to_be_bytes(),
+        &ClassEmojiRecord {
+            number,
+            emoji_id: emoji.id.get(),
+        },
+    )?;
+
+    Ok(emoji)
+}
+
+/// Deletes the class's emoji, if it has one.
+pub async fn remove_class_emoji(
+    ctx: &serenity::Context,
+    db: &KingFisherDb,
+    guild_id: GuildId,
+    number: u32,
+) -> Result<()> {
+    if let Some(record) = db.remove::<ClassEmojiRecord>(CLASS_EMOJIS_TREE, number.to_be_bytes())? {
+        guild_id.delete_emoji(ctx, record.emoji_id).await?;
+    }
+
+    Ok(())
+}
+
+/// Every class emoji, by class number.
+pub fn get_class_emojis(db: &KingFisherDb) -> Result<HashMap<u32, u64>> {
+    Ok(db
+        .values::<ClassEmojiRecord>(CLASS_EMOJIS_TREE)?
+        .into_iter()
+        .map(|record| (record.number, record.emoji_id))
+        .collect())
+}
+
+fn class_for_emoji(db: &KingFisherDb, emoji_id: EmojiId) -> Result<Option<u32>> {
+    Ok(db
+        .values::<ClassEmojiRecord>(CLASS_EMOJIS_TREE)?
+        .into_iter()
+        .find(|record| record.emoji_id == emoji_id.get())
+        .map(|record| record.number))
+}
+
+/// Joins (or leaves) a class when its emoji is reacted (or un-reacted) in the class directory.
+pub async fn class_reaction_role(
+    ctx: &serenity::Context,
+    data: &AppState,
+    reaction: &serenity::Reaction,
+    added: bool,
+) -> Result<()> {
+    let directory_channel_id = data
+        .config
+        .read()
+        .await
+        .class_directory
+        .as_ref()
+        .map(|directory| directory.channel_id);
+
+    if directory_channel_id != Some(reaction.channel_id.get()) {
+        return Ok(());
+    }
+
+    let (serenity::ReactionType::Custom { id, .. }, Some(guild_id), Some(user_id)) =
+        (&reaction.emoji, reaction.guild_id, reaction.user_id)
+    else {
+        return Ok(());
+    };
+
+    if user_id == ctx.cache.current_user().id {
+        return Ok(());
+    }
+
+    let Some(number) = class_for_emoji(&data.db, *id)? else {
+        return Ok(());
+    };
+
+    let Some(role_id) = find_class_role(ctx, guild_id, number).await? else {
+        return Ok(());
+    };
+
+    if added {
+        ctx.http
+            .add_member_role(
+                guild_id,
+                user_id,
+                role_id,
+                Some("Joined from the class directory"),
+            )
+            .await?;
+    } else {
+        ctx.http
+            .remove_member_role(
+                guild_id,
+                user_id,
+                role_id,
+                Some("Left from the class directory"),
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn draws_number_on_template() {
+        let template = RgbaImage::from_pixel(64, 64, Rgba([0, 0, 255, 255]));
+
+        let png = render_class_emoji(&template, 2420, HexColor(0xffffff)).unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgba8();
+
+        assert_eq!(image.dimensions(), (EMOJI_SIZE, EMOJI_SIZE));
+        assert_eq!(image.get_pixel(0, 0), &Rgba([0, 0, 255, 255]));
+        assert!(image
+            .pixels()
+            .any(|pixel| *pixel == Rgba([255, 255, 255, 255])));
+        assert!(image.pixels().any(|pixel| *pixel == Rgba([0, 0, 0, 255])));
+    }
+
+    #[test]
+    fn long_numbers_still_fit() {
+        let template = RgbaImage::new(EMOJI_SIZE, EMOJI_SIZE);
+
+        assert!(render_class_emoji(&template, 123456789, HexColor(0xffffff)).is_ok());
+    }
+
+    #[test]
+    fn remembers_class_emojis() {
+        let db = KingFisherDb::temporary().unwrap();
+
+        db.insert(
+            CLASS_EMOJIS_TREE,
+            2420u32.to_be_bytes(),
+            &ClassEmojiRecord {
+                number: 2420,
+                emoji_id: 77,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(class_for_emoji(&db, EmojiId::new(77)).unwrap(), Some(2420));
+        assert_eq!(class_for_emoji(&db, EmojiId::new(78)).unwrap(), None);
+    }
+}
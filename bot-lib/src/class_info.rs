@@ -0,0 +1,89 @@
+use crate::db::KingFisherDb;
+use color_eyre::eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const CLASS_INFO_TREE: &str = "class_info";
+
+/// Section details for a class, kept by the mods.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClassInfo {
+    pub number: u32,
+    pub professor: Option<String>,
+    /// Free form, like `MWF 10:45-11:35`.
+    pub meeting_time: Option<String>,
+}
+
+impl ClassInfo {
+    /// A one line summary, like `Prof. Smith, MWF 10:45`.
+    pub fn summary(&self) -> Option<String> {
+        match (&self.professor, &self.meeting_time) {
+            (Some(professor), Some(meeting_time)) => {
+                Some(format!("{}, {}", professor, meeting_time))
+            }
+            (Some(only), None) | (None, Some(only)) => Some(only.clone()),
+            (None, None) => None,
+        }
+    }
+}
+
+pub fn get_class_info(db: &KingFisherDb, number: u32) -> Result<ClassInfo> {
+    Ok(db
+        .get(CLASS_INFO_TREE, number.to_be_bytes())?
+        .unwrap_or(ClassInfo {
+            number,
+            ..Default::default()
+        }))
+}
+
+/// Every class with recorded info, by number.
+pub fn get_all_class_info(db: &KingFisherDb) -> Result<HashMap<u32, ClassInfo>> {
+    Ok(db
+        .values::<ClassInfo>(CLASS_INFO_TREE)?
+        .into_iter()
+        .map(|info| (info.number, info))
+        .collect())
+}
+
+/// Saves the info, or forgets it once every field is cleared.
+pub fn save_class_info(db: &KingFisherDb, info: &ClassInfo) -> Result<()> {
+    if info.summary().is_none() {
+        return db
+            .remove::<ClassInfo>(CLASS_INFO_TREE, info.number.to_be_bytes())
+            .map(|_| ());
+    }
+
+    db.insert(CLASS_INFO_TREE, info.number.to_be_bytes(), info)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trips_class_info() {
+        let db = KingFisherDb::temporary().unwrap();
+
+        let mut info = get_class_info(&db, 2420).unwrap();
+        assert_eq!(info.summary(), None);
+
+        info.professor = Some("Prof. Smith".to_owned());
+        save_class_info(&db, &info).unwrap();
+        info.meeting_time = Some("MWF 10:45".to_owned());
+        save_class_info(&db, &info).unwrap();
+
+        let saved = get_class_info(&db, 2420).unwrap();
+        assert_eq!(saved.summary().as_deref(), Some("Prof. Smith, MWF 10:45"));
+        assert_eq!(get_all_class_info(&db).unwrap().len(), 1);
+
+        save_class_info(
+            &db,
+            &ClassInfo {
+                number: 2420,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(get_all_class_info(&db).unwrap().is_empty());
+    }
+}
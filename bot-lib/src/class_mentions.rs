@@ -0,0 +1,216 @@
+use crate::{author_guard::is_from_human, data::AppState, webhooks::execute_webhook};
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{Result, WrapErr};
+use dashmap::DashMap;
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{self as serenity, Permissions, RoleId};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use std::collections::HashMap;
+
+/// Limits how often members who aren't mods can ping a class role.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ClassMentionLimit {
+    /// How long after a ping the same class can't be pinged again, in seconds.
+    #[serde_as(as = "DurationSeconds<i64>")]
+    #[serde(default = "get_default_cooldown")]
+    pub cooldown: Duration,
+}
+
+fn get_default_cooldown() -> Duration {
+    Duration::hours(1)
+}
+
+lazy_static! {
+    static ref CLASS_ROLE: Regex =
+        Regex::new(r"CS \d{4}").expect("Class role regex should be valid");
+    static ref ROLE_MENTION: Regex =
+        Regex::new(r"<@&(\d+)>").expect("Role mention regex should be valid");
+    static ref LAST_MENTIONED: DashMap<RoleId, DateTime<Utc>> = DashMap::new();
+}
+
+/// Swaps mentions of the given roles for their plain names, so they don't ping.
+fn strip_role_mentions(content: &str, role_names: &HashMap<RoleId, String>) -> String {
+    ROLE_MENTION
+        .replace_all(content, |captures: &regex::Captures| {
+            captures[1]
+                .parse::<u64>()
+                .ok()
+                .and_then(|role_id| role_names.get(&RoleId::new(role_id)))
+                .map_or(captures[0].to_owned(), |name| format!("@{}", name))
+        })
+        .into_owned()
+}
+
+/// Whether any of the roles were pinged within the cooldown. If not, they count as pinged now.
+fn is_over_budget(roles: &[RoleId], cooldown: Duration, now: DateTime<Utc>) -> bool {
+    let over_budget = roles.iter().any(|role_id| {
+        LAST_MENTIONED
+            .get(role_id)
+            .is_some_and(|last| now - *last < cooldown)
+    });
+
+    if !over_budget {
+        for role_id in roles {
+            LAST_MENTIONED.insert(*role_id, now);
+        }
+    }
+
+    over_budget
+}
+
+/// Re-posts over budget class pings without the ping. Returns true if the message was deleted.
+pub async fn limit_class_mentions(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<bool> {
+    if !is_from_human(ctx, data, message).await || message.mention_roles.is_empty() {
+        return Ok(false);
+    }
+
+    let Some(limit) = data.config.read().await.class_mention_limit.clone() else {
+        return Ok(false);
+    };
+
+    let (Some(guild_id), Some(member)) = (message.guild_id, &message.member) else {
+        return Ok(false);
+    };
+
+    let (class_roles, is_mod) = {
+        let Some(guild) = ctx.cache.guild(guild_id) else {
+            return Ok(false);
+        };
+
+        let class_roles = message
+            .mention_roles
+            .iter()
+            .filter_map(|role_id| guild.roles.get(role_id))
+            .filter(|role| CLASS_ROLE.is_match(&role.name))
+            .map(|role| (role.id, role.name.clone()))
+            .collect::<HashMap<_, _>>();
+
+        let permissions = member
+            .roles
+            .iter()
+            .filter_map(|role_id| guild.roles.get(role_id))
+            .fold(Permissions::empty(), |acc, role| acc | role.permissions);
+
+        (
+            class_roles,
+            permissions.intersects(Permissions::MANAGE_MESSAGES | Permissions::ADMINISTRATOR),
+        )
+    };
+
+    if class_roles.is_empty() || is_mod {
+        return Ok(false);
+    }
+
+    if !is_over_budget(
+        &class_roles.keys().copied().collect_vec(),
+        limit.cooldown,
+        Utc::now(),
+    ) {
+        return Ok(false);
+    }
+
+    tracing::info!("Class ping over budget in {}", message.link());
+
+    let name = member
+        .nick
+        .clone()
+        .or_else(|| message.author.global_name.clone())
+        .unwrap_or_else(|| message.author.name.clone());
+
+    let attachments = message
+        .attachments
+        .iter()
+        .map(|attachment| attachment.url.as_str())
+        .join("\n");
+
+    let content = format!(
+        "{}\n{}",
+        strip_role_mentions(&message.content, &class_roles),
+        attachments
+    );
+
+    let mut repost = serenity::ExecuteWebhook::new()
+        .content(content.trim_end())
+        .username(&name)
+        .allowed_mentions(serenity::CreateAllowedMentions::new());
+
+    if let Some(avatar_url) = message.author.avatar_url() {
+        repost = repost.avatar_url(avatar_url);
+    }
+
+    message
+        .delete(ctx)
+        .await
+        .wrap_err("Couldn't delete message")?;
+
+    execute_webhook(ctx, message.channel_id, repost).await?;
+
+    let notice = format!(
+        "{} was pinged recently, so your message was re-posted without the ping. \
+         Each class can be pinged once every {} minutes.",
+        class_roles.values().join(", "),
+        limit.cooldown.num_minutes()
+    );
+
+    if let Err(e) = message
+        .author
+        .direct_message(ctx, serenity::CreateMessage::new().content(notice))
+        .await
+    {
+        tracing::debug!("Couldn't tell {} about the class ping limit: {:?}", name, e);
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strips_class_mentions() {
+        let role_names = HashMap::from([(RoleId::new(1), "CS 2420".to_owned())]);
+
+        assert_eq!(
+            strip_role_mentions("<@&1> is the exam open book? <@&2>", &role_names),
+            "@CS 2420 is the exam open book? <@&2>"
+        );
+    }
+
+    #[test]
+    fn limits_pings_per_class() {
+        let now = Utc::now();
+        let cooldown = Duration::hours(1);
+        let (first, second) = (RoleId::new(1001), RoleId::new(1002));
+
+        assert!(!is_over_budget(&[first], cooldown, now));
+        assert!(is_over_budget(
+            &[first],
+            cooldown,
+            now + Duration::minutes(30)
+        ));
+        assert!(!is_over_budget(
+            &[second],
+            cooldown,
+            now + Duration::minutes(30)
+        ));
+        assert!(is_over_budget(
+            &[first, second],
+            cooldown,
+            now + Duration::minutes(45)
+        ));
+        assert!(!is_over_budget(
+            &[first],
+            cooldown,
+            now + Duration::minutes(61)
+        ));
+    }
+}
@@ -0,0 +1,83 @@
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, GuildId, RoleId, UserId};
+use std::collections::HashMap;
+
+const NOTIFICATION_SUFFIX: &str = " Announcements";
+
+/// Whether this is a class's notification role, like "CS 2420 Announcements".
+///
+/// Class roles are for access and notification roles are for pings, so members can mute a
+/// class's pings without losing its channels. Classes only have one if mods made it.
+pub fn is_notification_role(role_name: &str) -> bool {
+    role_name.ends_with(NOTIFICATION_SUFFIX)
+}
+
+/// The notification role of the class with this role, if it has one.
+pub fn find_notification_role(
+    roles: &HashMap<RoleId, serenity::Role>,
+    class_role_id: RoleId,
+) -> Option<RoleId> {
+    let class_role = roles.get(&class_role_id)?;
+    let name = format!("{}{}", class_role.name, NOTIFICATION_SUFFIX);
+
+    roles
+        .values()
+        .find(|role| role.name == name)
+        .map(|role| role.id)
+}
+
+/// Turns a class's pings on or off for a member, returning false if the class has no
+/// notification role.
+pub async fn set_class_notifications(
+    ctx: &serenity::Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    notification_role_id: Option<RoleId>,
+    on: bool,
+) -> Result<bool> {
+    let Some(role_id) = notification_role_id else {
+        return Ok(false);
+    };
+
+    if on {
+        ctx.http
+            .add_member_role(guild_id, user_id, role_id, Some("Class notifications on"))
+            .await?;
+    } else {
+        ctx.http
+            .remove_member_role(guild_id, user_id, role_id, Some("Class notifications off"))
+            .await?;
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn role(id: u64, name: &str) -> (RoleId, serenity::Role) {
+        let mut role = serenity::Role::default();
+        role.id = RoleId::new(id);
+        role.name = name.to_owned();
+        (role.id, role)
+    }
+
+    #[test]
+    fn finds_the_matching_notification_role() {
+        let roles = HashMap::from([
+            role(1, "CS 2420"),
+            role(2, "CS 2420 Announcements"),
+            role(3, "CS 3500"),
+            role(4, "CS 3500 Lab Announcements"),
+        ]);
+
+        assert!(is_notification_role("CS 2420 Announcements"));
+        assert!(!is_notification_role("CS 2420"));
+        assert_eq!(
+            find_notification_role(&roles, RoleId::new(1)),
+            Some(RoleId::new(2))
+        );
+        assert_eq!(find_notification_role(&roles, RoleId::new(3)), None);
+    }
+}
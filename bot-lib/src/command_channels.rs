@@ -0,0 +1,96 @@
+use crate::data::PoiseContext;
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::{
+    serenity_prelude::{self as serenity, Mentionable},
+    CreateReply,
+};
+use std::collections::HashMap;
+
+/// The channels a command is allowed in, configured in `[command_channels]`.
+///
+/// A subcommand like `"profile set"` can have its own entry, otherwise it follows its parent's.
+fn allowed_channels<'a>(
+    command_channels: &'a HashMap<String, Vec<u64>>,
+    qualified_name: &str,
+) -> Option<&'a [u64]> {
+    let words = qualified_name.split(' ').collect_vec();
+
+    (1..=words.len())
+        .rev()
+        .find_map(|len| command_channels.get(&words[..len].join(" ")))
+        .map(Vec::as_slice)
+}
+
+/// Global poise check keeping commands in the channels they're configured for.
+pub async fn check_command_channels(ctx: PoiseContext<'_>) -> Result<bool> {
+    let Some(allowed) = allowed_channels(
+        &ctx.data().config.read().await.command_channels,
+        &ctx.command().qualified_name,
+    )
+    .map(<[u64]>::to_vec) else {
+        return Ok(true);
+    };
+
+    if allowed.contains(&ctx.channel_id().get()) {
+        return Ok(true);
+    }
+
+    // Threads count as the channel they're in
+    if let Some(parent_id) = ctx
+        .guild_channel()
+        .await
+        .filter(|channel| channel.thread_metadata.is_some())
+        .and_then(|channel| channel.parent_id)
+    {
+        if allowed.contains(&parent_id.get()) {
+            return Ok(true);
+        }
+    }
+
+    let message = if allowed.is_empty() {
+        format!("`/{}` is turned off.", ctx.command().qualified_name)
+    } else {
+        format!(
+            "`/{}` can only be used in {}.",
+            ctx.command().qualified_name,
+            allowed
+                .iter()
+                .map(|&channel_id| serenity::ChannelId::new(channel_id).mention().to_string())
+                .join(", ")
+        )
+    };
+
+    ctx.send(CreateReply::default().ephemeral(true).content(message))
+        .await?;
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn subcommands_follow_their_parent() {
+        let command_channels = HashMap::from([
+            ("wordle".to_owned(), vec![1]),
+            ("profile".to_owned(), vec![2]),
+            ("profile set".to_owned(), vec![3]),
+        ]);
+
+        assert_eq!(
+            allowed_channels(&command_channels, "wordle"),
+            Some(&[1][..])
+        );
+        assert_eq!(
+            allowed_channels(&command_channels, "profile show"),
+            Some(&[2][..])
+        );
+        assert_eq!(
+            allowed_channels(&command_channels, "profile set"),
+            Some(&[3][..])
+        );
+        assert_eq!(allowed_channels(&command_channels, "help"), None);
+    }
+}
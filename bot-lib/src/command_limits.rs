@@ -0,0 +1,214 @@
+use crate::data::PoiseContext;
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::Result;
+use dashmap::DashMap;
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+/// Limits on how often a command can be run, configured per command in `[command_limits.<name>]`.
+#[serde_as]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct CommandLimit {
+    /// How long a single user has to wait between uses, in seconds.
+    #[serde_as(as = "Option<DurationSeconds<i64>>")]
+    #[serde(default)]
+    pub user_cooldown: Option<Duration>,
+    /// How long the whole guild has to wait between uses, in seconds.
+    #[serde_as(as = "Option<DurationSeconds<i64>>")]
+    #[serde(default)]
+    pub guild_cooldown: Option<Duration>,
+    /// Only allow one invocation of the command to run at a time.
+    #[serde(default)]
+    pub exclusive: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LimitRejection {
+    UserCooldown(Duration),
+    GuildCooldown(Duration),
+    AlreadyRunning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum LimitScope {
+    User(u64),
+    Guild(u64),
+}
+
+/// Runtime state backing [`CommandLimit`].
+///
+/// Entries remember which invocation created them, so checking the same invocation twice
+/// (poise runs checks for parent commands too) doesn't reject it.
+#[derive(Debug, Default)]
+pub struct CommandLimiter {
+    last_used: DashMap<(String, LimitScope), (DateTime<Utc>, u64)>,
+    running: DashMap<String, u64>,
+}
+
+impl CommandLimiter {
+    pub fn try_acquire(
+        &self,
+        command_name: &str,
+        limit: &CommandLimit,
+        invocation_id: u64,
+        user_id: u64,
+        guild_id: Option<u64>,
+        now: DateTime<Utc>,
+    ) -> Result<(), LimitRejection> {
+        let scopes = [
+            limit
+                .user_cooldown
+                .map(|cooldown| (LimitScope::User(user_id), cooldown)),
+            limit
+                .guild_cooldown
+                .zip(guild_id)
+                .map(|(cooldown, guild_id)| (LimitScope::Guild(guild_id), cooldown)),
+        ];
+
+        for (scope, cooldown) in scopes.iter().flatten() {
+            let key = (command_name.to_owned(), scope.clone());
+
+            let Some(entry) = self.last_used.get(&key) else {
+                continue;
+            };
+
+            let (last_used, last_invocation_id) = *entry;
+            let remaining = *cooldown - (now - last_used);
+
+            if last_invocation_id != invocation_id && remaining > Duration::zero() {
+                return Err(match scope {
+                    LimitScope::User(_) => LimitRejection::UserCooldown(remaining),
+                    LimitScope::Guild(_) => LimitRejection::GuildCooldown(remaining),
+                });
+            }
+        }
+
+        if limit.exclusive {
+            let running_id = *self
+                .running
+                .entry(command_name.to_owned())
+                .or_insert(invocation_id);
+
+            if running_id != invocation_id {
+                return Err(LimitRejection::AlreadyRunning);
+            }
+        }
+
+        for (scope, _) in scopes.into_iter().flatten() {
+            self.last_used
+                .insert((command_name.to_owned(), scope), (now, invocation_id));
+        }
+
+        Ok(())
+    }
+
+    /// Lets the next invocation of an exclusive command run.
+    pub fn release(&self, command_name: &str, invocation_id: u64) {
+        self.running
+            .remove_if(command_name, |_, running_id| *running_id == invocation_id);
+    }
+}
+
+/// Global poise check enforcing the configured [`CommandLimit`]s.
+pub async fn check_command_limits(ctx: PoiseContext<'_>) -> Result<bool> {
+    let command_name = &ctx.command().qualified_name;
+
+    let Some(limit) = ctx
+        .data()
+        .config
+        .read()
+        .await
+        .command_limits
+        .get(command_name)
+        .cloned()
+    else {
+        return Ok(true);
+    };
+
+    let rejection = ctx.data().command_limiter.try_acquire(
+        command_name,
+        &limit,
+        ctx.id(),
+        ctx.author().id.get(),
+        ctx.guild_id().map(|guild_id| guild_id.get()),
+        Utc::now(),
+    );
+
+    let message = match rejection {
+        Ok(()) => return Ok(true),
+        Err(LimitRejection::UserCooldown(remaining)) => format!(
+            "You're using this command too often, try again in {} seconds.",
+            remaining.num_seconds().max(1)
+        ),
+        Err(LimitRejection::GuildCooldown(remaining)) => format!(
+            "This command was used recently, try again in {} seconds.",
+            remaining.num_seconds().max(1)
+        ),
+        Err(LimitRejection::AlreadyRunning) => {
+            "This command is already running, wait for it to finish.".to_owned()
+        }
+    };
+
+    ctx.send(CreateReply::default().ephemeral(true).content(message))
+        .await?;
+
+    Ok(false)
+}
+
+/// Called once a command finishes (successfully or not).
+pub fn release_command_limits(ctx: PoiseContext<'_>) {
+    ctx.data()
+        .command_limiter
+        .release(&ctx.command().qualified_name, ctx.id());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn user_cooldown_is_per_user() {
+        let limiter = CommandLimiter::default();
+        let limit = CommandLimit {
+            user_cooldown: Some(Duration::seconds(10)),
+            ..Default::default()
+        };
+        let now = Utc::now();
+
+        assert!(limiter.try_acquire("a", &limit, 1, 1, None, now).is_ok());
+        // Same invocation checked again (parent command checks)
+        assert!(limiter.try_acquire("a", &limit, 1, 1, None, now).is_ok());
+        assert_eq!(
+            limiter.try_acquire("a", &limit, 2, 1, None, now + Duration::seconds(4)),
+            Err(LimitRejection::UserCooldown(Duration::seconds(6)))
+        );
+        assert!(limiter.try_acquire("a", &limit, 3, 2, None, now).is_ok());
+        assert!(limiter
+            .try_acquire("a", &limit, 4, 1, None, now + Duration::seconds(11))
+            .is_ok());
+    }
+
+    #[test]
+    fn exclusive_commands_run_once() {
+        let limiter = CommandLimiter::default();
+        let limit = CommandLimit {
+            exclusive: true,
+            ..Default::default()
+        };
+        let now = Utc::now();
+
+        assert!(limiter.try_acquire("a", &limit, 1, 1, None, now).is_ok());
+        assert_eq!(
+            limiter.try_acquire("a", &limit, 2, 2, None, now),
+            Err(LimitRejection::AlreadyRunning)
+        );
+
+        // Only the owner of the lock can release it
+        limiter.release("a", 2);
+        assert!(limiter.try_acquire("a", &limit, 2, 2, None, now).is_err());
+
+        limiter.release("a", 1);
+        assert!(limiter.try_acquire("a", &limit, 2, 2, None, now).is_ok());
+    }
+}
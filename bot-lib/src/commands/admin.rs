@@ -0,0 +1,162 @@
+use crate::{data::PoiseContext, scheduler};
+use color_eyre::eyre::{Result, WrapErr};
+use itertools::Itertools;
+
+/// Discord messages can't be longer than 2000 characters, leave room for the code block.
+const MAX_REPLY_LENGTH: usize = 1900;
+
+fn truncate(text: &str) -> String {
+    if text.len() <= MAX_REPLY_LENGTH {
+        return text.to_owned();
+    }
+
+    let end = (0..=MAX_REPLY_LENGTH)
+        .rev()
+        .find(|&i| text.is_char_boundary(i))
+        .unwrap_or(0);
+
+    format!("{}\n...", &text[..end])
+}
+
+#[poise::command(
+    slash_command,
+    prefix_command,
+    owners_only,
+    hide_in_help,
+    ephemeral = true,
+    subcommands(
+        "admin_config",
+        "admin_errors",
+        "admin_reload",
+        "admin_save",
+        "admin_flush_caches",
+        "admin_run_job",
+        "admin_dry_run"
+    ),
+    subcommand_required,
+    description_localized("en-US", "Owner only bot maintenance")
+)]
+pub async fn admin(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Show the current config, or a single top level value
+#[poise::command(slash_command, prefix_command, owners_only, rename = "config")]
+pub async fn admin_config(ctx: PoiseContext<'_>, key: Option<String>) -> Result<()> {
+    let config = toml::Value::try_from(&*ctx.data().config.read().await)
+        .wrap_err("Could not serialize config")?;
+
+    let text = match key {
+        Some(key) => config
+            .get(&key)
+            .map(|value| value.to_string())
+            .unwrap_or(format!("No config value named `{}`", key)),
+        None => toml::to_string_pretty(&config).wrap_err("Could not serialize config")?,
+    };
+
+    ctx.say(format!("```toml\n{}\n```", truncate(&text)))
+        .await?;
+
+    Ok(())
+}
+
+/// Show the most recent errors
+#[poise::command(slash_command, prefix_command, owners_only, rename = "errors")]
+pub async fn admin_errors(ctx: PoiseContext<'_>) -> Result<()> {
+    let errors = ctx.data().recent_errors();
+
+    if errors.is_empty() {
+        ctx.say("No errors recorded since startup.").await?;
+        return Ok(());
+    }
+
+    ctx.say(format!(
+        "```\n{}\n```",
+        truncate(&errors.iter().rev().join("\n"))
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Reload the config from disk
+#[poise::command(slash_command, prefix_command, owners_only, rename = "reload")]
+pub async fn admin_reload(ctx: PoiseContext<'_>) -> Result<()> {
+    let reloaded = ctx.data().config.write().await.reload();
+
+    match reloaded {
+        Ok(()) => ctx.say("Reloaded config!").await?,
+        Err(e) => {
+            ctx.say(format!(
+                "Kept the old config, the new one didn't load:\n```\n{}\n```",
+                truncate(&format!("{:#}", e))
+            ))
+            .await?
+        }
+    };
+
+    Ok(())
+}
+
+/// Write the in-memory config to disk
+#[poise::command(slash_command, prefix_command, owners_only, rename = "save")]
+pub async fn admin_save(ctx: PoiseContext<'_>) -> Result<()> {
+    ctx.data().config.read().await.save()?;
+
+    ctx.say("Saved config!").await?;
+
+    Ok(())
+}
+
+/// Forget cached react role members and recent starboard posts
+#[poise::command(slash_command, prefix_command, owners_only, rename = "flush_caches")]
+pub async fn admin_flush_caches(ctx: PoiseContext<'_>) -> Result<()> {
+    ctx.data().react_roles.clear(&ctx.data().db)?;
+
+    for starboard in &ctx.data().config.read().await.starboards {
+        starboard.recently_added_messages.write().clear();
+    }
+
+    ctx.say("Flushed caches!").await?;
+
+    Ok(())
+}
+
+/// Run a scheduled job right now
+#[poise::command(slash_command, prefix_command, owners_only, rename = "run_job")]
+pub async fn admin_run_job(
+    ctx: PoiseContext<'_>,
+    #[description = "The job to run, leave empty to list jobs"] name: Option<String>,
+) -> Result<()> {
+    let Some(name) = name else {
+        ctx.say(format!(
+            "Jobs: {}",
+            scheduler::JOBS.iter().map(|job| job.name).join(", ")
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    if let Err(e) = scheduler::run_job(&name, ctx.serenity_context(), ctx.data()).await {
+        ctx.say(format!("Job `{}` failed: {}", name, e)).await?;
+        return Err(e);
+    }
+
+    ctx.say(format!("Ran job `{}`!", name)).await?;
+
+    Ok(())
+}
+
+/// Make destructive commands only report what they would do
+#[poise::command(slash_command, prefix_command, owners_only, rename = "dry_run")]
+pub async fn admin_dry_run(ctx: PoiseContext<'_>, enabled: bool) -> Result<()> {
+    ctx.data().config.write().await.dry_run = enabled;
+
+    ctx.say(format!(
+        "Dry run {}! Use `/admin save` to keep this after a restart.",
+        if enabled { "enabled" } else { "disabled" }
+    ))
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use crate::{
+    announcements::{post_announcement, request_approval, Announcement, Ping},
+    data::{PoiseApplicationContext, PoiseContext},
+};
+use color_eyre::eyre::Result;
+use poise::{
+    serenity_prelude::{self as serenity, ChannelId, Mentionable},
+    ChoiceParameter, CreateReply, Modal,
+};
+use std::time::Duration;
+
+/// How long the officer has to confirm the preview.
+const PREVIEW_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ChoiceParameter)]
+pub enum PingLevel {
+    Nobody,
+    #[name = "@here"]
+    Here,
+    #[name = "@everyone"]
+    Everyone,
+}
+
+#[derive(Debug, Modal)]
+#[name = "Compose announcement"]
+struct AnnouncementModal {
+    #[name = "Title"]
+    #[max_length = 100]
+    title: String,
+    #[name = "Announcement"]
+    #[paragraph]
+    #[max_length = 1800]
+    body: String,
+}
+
+#[poise::command(
+    slash_command,
+    subcommands("announce_compose"),
+    subcommand_required,
+    description_localized("en-US", "Announcements for club officers")
+)]
+pub async fn announce(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Write an announcement, preview it, and post it
+#[poise::command(slash_command, ephemeral = true, rename = "compose")]
+pub async fn announce_compose(
+    ctx: PoiseApplicationContext<'_>,
+    #[description = "Where to post it"]
+    #[channel_types("Text", "News")]
+    channel: serenity::GuildChannel,
+    #[description = "Who to ping, defaults to nobody"] ping: Option<PingLevel>,
+    #[description = "A role to ping instead"] ping_role: Option<serenity::Role>,
+) -> Result<()> {
+    let Some(announcements) = ctx.data().config.read().await.announcements.clone() else {
+        ctx.say("Announcements aren't set up.").await?;
+        return Ok(());
+    };
+
+    let roles = ctx
+        .author_member()
+        .await
+        .map(|member| member.roles.clone())
+        .unwrap_or_default();
+
+    if !announcements.is_officer(&roles) {
+        ctx.say("Only officers can compose announcements.").await?;
+        return Ok(());
+    }
+
+    let Some(target) = announcements.channel(channel.id).cloned() else {
+        ctx.say(format!(
+            "{} isn't an announcement channel.",
+            channel.id.mention()
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    let Some(AnnouncementModal { title, body }) = AnnouncementModal::execute(ctx).await? else {
+        return Ok(());
+    };
+
+    let announcement = Announcement {
+        author_id: ctx.author().id.get(),
+        channel_id: channel.id.get(),
+        title,
+        body,
+        ping: match (ping_role, ping) {
+            (Some(role), _) => Ping::Role(role.id.get()),
+            (None, Some(PingLevel::Here)) => Ping::Here,
+            (None, Some(PingLevel::Everyone)) => Ping::Everyone,
+            (None, Some(PingLevel::Nobody) | None) => Ping::Nobody,
+        },
+    };
+
+    let approval_channel_id = target
+        .requires_approval
+        .then_some(announcements.approval_channel_id)
+        .flatten()
+        .map(ChannelId::new);
+
+    let confirm_id = format!("announce_confirm:{}", ctx.id());
+    let cancel_id = format!("announce_cancel:{}", ctx.id());
+
+    let preview = ctx
+        .send(
+            CreateReply::default()
+                .content(if approval_channel_id.is_some() {
+                    "Here's how it'll look. A mod has to approve it before it goes out."
+                } else {
+                    "Here's how it'll look."
+                })
+                .embed(announcement.preview())
+                .components(vec![serenity::CreateActionRow::Buttons(vec![
+                    serenity::CreateButton::new(&confirm_id)
+                        .label(if approval_channel_id.is_some() {
+                            "Send for approval"
+                        } else {
+                            "Post"
+                        })
+                        .style(serenity::ButtonStyle::Success),
+                    serenity::CreateButton::new(&cancel_id)
+                        .label("Cancel")
+                        .style(serenity::ButtonStyle::Secondary),
+                ])]),
+        )
+        .await?;
+
+    let author_id = ctx.author().id;
+
+    let Some(choice) = serenity::ComponentInteractionCollector::new(ctx)
+        .custom_ids(vec![confirm_id.clone(), cancel_id])
+        .filter(move |interaction| interaction.user.id == author_id)
+        .timeout(PREVIEW_TIMEOUT)
+        .await
+    else {
+        preview
+            .edit(
+                poise::Context::Application(ctx),
+                CreateReply::default()
+                    .content("Took too long, nothing was posted.")
+                    .components(vec![]),
+            )
+            .await?;
+        return Ok(());
+    };
+
+    let outcome = if choice.data.custom_id != confirm_id {
+        "Cancelled, nothing was posted.".to_owned()
+    } else if let Some(approval_channel_id) = approval_channel_id {
+        request_approval(
+            ctx.serenity_context(),
+            ctx.data(),
+            approval_channel_id,
+            &announcement,
+        )
+        .await?;
+        "Sent to the mods for approval, you'll get a DM once they decide.".to_owned()
+    } else {
+        let message = post_announcement(ctx.serenity_context(), ctx.data(), &announcement).await?;
+        format!("Posted: {}", message.link())
+    };
+
+    choice
+        .create_response(
+            ctx,
+            serenity::CreateInteractionResponse::UpdateMessage(
+                serenity::CreateInteractionResponseMessage::new()
+                    .content(outcome)
+                    .components(vec![]),
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use crate::{
+    bookmarks::{clear_bookmarks, format_bookmark, get_bookmarks},
+    data::PoiseContext,
+};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+
+#[poise::command(
+    slash_command,
+    subcommands("bookmarks_list", "bookmarks_clear"),
+    subcommand_required,
+    description_localized("en-US", "Messages you've bookmarked by reacting to them")
+)]
+pub async fn bookmarks(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// See your bookmarked messages
+#[poise::command(slash_command, ephemeral = true, rename = "list")]
+pub async fn bookmarks_list(ctx: PoiseContext<'_>) -> Result<()> {
+    let bookmarks = get_bookmarks(&ctx.data().db, ctx.author().id)?;
+
+    if bookmarks.is_empty() {
+        let emoji = ctx.data().config.read().await.bookmark_emoji.clone();
+
+        ctx.say(match emoji {
+            Some(emoji) => format!(
+                "No bookmarks yet, react to a message with {} to save it.",
+                emoji
+            ),
+            None => "No bookmarks yet.".to_owned(),
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let pages = bookmarks.iter().rev().map(format_bookmark).collect_vec();
+
+    poise::builtins::paginate(ctx, &pages.iter().map(String::as_str).collect_vec()).await?;
+
+    Ok(())
+}
+
+/// Forget all your bookmarks
+#[poise::command(slash_command, ephemeral = true, rename = "clear")]
+pub async fn bookmarks_clear(ctx: PoiseContext<'_>) -> Result<()> {
+    let cleared = clear_bookmarks(&ctx.data().db, ctx.author().id)?;
+
+    ctx.say(format!("Cleared {} bookmarks.", cleared)).await?;
+
+    Ok(())
+}
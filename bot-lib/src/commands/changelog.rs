@@ -0,0 +1,39 @@
+use crate::{
+    changelog::{load_releases, release_embeds},
+    data::PoiseContext,
+};
+use color_eyre::eyre::Result;
+use poise::CreateReply;
+
+/// Discord allows up to 10 embeds per message.
+const MAX_RELEASES: u8 = 10;
+
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    description_localized("en-US", "What's new in the last few versions of kingfisher")
+)]
+pub async fn changelog(
+    ctx: PoiseContext<'_>,
+    #[description = "How many releases to show, defaults to 3"]
+    #[min = 1]
+    #[max = 10]
+    count: Option<u8>,
+) -> Result<()> {
+    let count = count.unwrap_or(3).min(MAX_RELEASES);
+    let releases = load_releases(ctx.data()).await;
+    let releases = &releases[..releases.len().min(usize::from(count))];
+
+    if releases.is_empty() {
+        ctx.say("There aren't any release notes.").await?;
+        return Ok(());
+    }
+
+    let reply = release_embeds(releases)
+        .into_iter()
+        .fold(CreateReply::default(), CreateReply::embed);
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
@@ -0,0 +1,625 @@
+use crate::{commands::ensure_can_manage_role, data::PoiseContext};
+use color_eyre::eyre::{OptionExt, Result};
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use poise::{
+    serenity_prelude::{
+        self as serenity, ChannelId, ChannelType, GuildChannel, PermissionOverwrite,
+        PermissionOverwriteType, Permissions, Role, RoleId,
+    },
+    CreateReply,
+};
+use regex::Regex;
+use std::{fmt, ops::Range, time::Duration};
+
+/// How long the fix buttons keep working.
+const FIX_TIMEOUT: Duration = Duration::from_secs(600);
+/// Discord allows 5 rows of 5 buttons, and the last row is kept for paging.
+const MAX_FIX_BUTTONS: usize = 20;
+/// Discord messages can't be longer than 2000 characters, leave room for the page number.
+const MAX_REPORT_LENGTH: usize = 1900;
+
+lazy_static! {
+    static ref CLASS_NAME: Regex =
+        Regex::new(r"^CS (\d+)$").expect("Class name regex should be valid");
+    static ref CLASS_CHANNEL: Regex =
+        Regex::new(r"^(\d+)-").expect("Class channel regex should be valid");
+}
+
+/// Something out of line between the class roles, categories and `class_categories`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Discrepancy {
+    RoleWithoutCategory {
+        number: u32,
+        role_id: RoleId,
+    },
+    CategoryWithoutRole {
+        number: u32,
+        category_id: ChannelId,
+    },
+    /// A class category missing from `class_categories`.
+    UntrackedCategory {
+        number: u32,
+        category_id: ChannelId,
+    },
+    /// An id in `class_categories` that isn't a category anymore.
+    MissingCategory {
+        category_id: ChannelId,
+    },
+    /// A class channel outside of any class category, and the category it probably belongs in.
+    StrayChannel {
+        channel_id: ChannelId,
+        category_id: Option<ChannelId>,
+    },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discrepancy::RoleWithoutCategory { number, role_id } => {
+                write!(f, "<@&{}> has no CS {} category", role_id, number)
+            }
+            Discrepancy::CategoryWithoutRole {
+                number,
+                category_id,
+            } => write!(f, "<#{}> has no CS {} role", category_id, number),
+            Discrepancy::UntrackedCategory { category_id, .. } => {
+                write!(f, "<#{}> isn't in class_categories", category_id)
+            }
+            Discrepancy::MissingCategory { category_id } => write!(
+                f,
+                "class_categories has {}, which isn't a category",
+                category_id
+            ),
+            Discrepancy::StrayChannel { channel_id, .. } => {
+                write!(f, "<#{}> isn't in a class category", channel_id)
+            }
+        }
+    }
+}
+
+impl Discrepancy {
+    /// What the fix button does, if there is one.
+    fn fix_label(&self) -> Option<String> {
+        match self {
+            Discrepancy::RoleWithoutCategory { number, .. } => {
+                Some(format!("Delete role CS {}", number))
+            }
+            Discrepancy::CategoryWithoutRole { number, .. } => {
+                Some(format!("Create role CS {}", number))
+            }
+            Discrepancy::UntrackedCategory { number, .. } => {
+                Some(format!("Track category CS {}", number))
+            }
+            Discrepancy::MissingCategory { category_id } => {
+                Some(format!("Untrack {}", category_id))
+            }
+            Discrepancy::StrayChannel {
+                category_id: Some(_),
+                ..
+            } => Some("Move into its class category".to_owned()),
+            Discrepancy::StrayChannel {
+                category_id: None, ..
+            } => None,
+        }
+    }
+
+    async fn fix(&self, ctx: PoiseContext<'_>) -> Result<()> {
+        let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+
+        match self {
+            Discrepancy::RoleWithoutCategory { role_id, .. } => {
+                ensure_can_manage_role(ctx, Some(*role_id)).await?;
+
+                guild_id.delete_role(ctx, *role_id).await?;
+            }
+            Discrepancy::CategoryWithoutRole {
+                number,
+                category_id,
+            } => {
+                ensure_can_manage_role(ctx, None).await?;
+
+                let role = guild_id
+                    .create_role(
+                        ctx,
+                        serenity::EditRole::new()
+                            .hoist(true)
+                            .name(format!("CS {}", number)),
+                    )
+                    .await?;
+
+                category_id
+                    .create_permission(
+                        ctx,
+                        PermissionOverwrite {
+                            allow: Permissions::VIEW_CHANNEL,
+                            deny: Permissions::empty(),
+                            kind: PermissionOverwriteType::Role(role.id),
+                        },
+                    )
+                    .await?;
+            }
+            Discrepancy::UntrackedCategory { category_id, .. } => {
+                ctx.data()
+                    .config
+                    .write()
+                    .await
+                    .track_class_category(*category_id)?;
+            }
+            Discrepancy::MissingCategory { category_id } => {
+                ctx.data()
+                    .config
+                    .write()
+                    .await
+                    .untrack_class_category(*category_id)?;
+            }
+            Discrepancy::StrayChannel {
+                channel_id,
+                category_id: Some(category_id),
+            } => {
+                channel_id
+                    .edit(ctx, serenity::EditChannel::new().category(*category_id))
+                    .await?;
+            }
+            Discrepancy::StrayChannel {
+                category_id: None, ..
+            } => {}
+        }
+
+        Ok(())
+    }
+}
+
+fn class_number(regex: &Regex, name: &str) -> Option<u32> {
+    regex.captures(name)?[1].parse().ok()
+}
+
+/// The class number of a class category, like 2420 for "CS 2420".
+pub(crate) fn class_category_number(channel: &GuildChannel) -> Option<u32> {
+    (channel.kind == ChannelType::Category)
+        .then(|| class_number(&CLASS_NAME, &channel.name))
+        .flatten()
+}
+
+/// Cross-references the class roles, categories and channels with `class_categories`.
+fn audit(
+    channels: &[GuildChannel],
+    roles: &[Role],
+    class_categories: &[ChannelId],
+) -> Vec<Discrepancy> {
+    let class_roles = roles
+        .iter()
+        .filter_map(|role| Some((class_number(&CLASS_NAME, &role.name)?, role.id)))
+        .collect_vec();
+
+    let categories = channels
+        .iter()
+        .filter(|channel| channel.kind == ChannelType::Category)
+        .collect_vec();
+
+    let class_category_ids = categories
+        .iter()
+        .filter_map(|category| Some((class_category_number(category)?, category.id)))
+        .collect_vec();
+
+    let mut discrepancies = vec![];
+
+    for (number, role_id) in &class_roles {
+        if !class_category_ids.iter().any(|(n, _)| n == number) {
+            discrepancies.push(Discrepancy::RoleWithoutCategory {
+                number: *number,
+                role_id: *role_id,
+            });
+        }
+    }
+
+    for (number, category_id) in &class_category_ids {
+        if !class_roles.iter().any(|(n, _)| n == number) {
+            discrepancies.push(Discrepancy::CategoryWithoutRole {
+                number: *number,
+                category_id: *category_id,
+            });
+        }
+
+        if !class_categories.contains(category_id) {
+            discrepancies.push(Discrepancy::UntrackedCategory {
+                number: *number,
+                category_id: *category_id,
+            });
+        }
+    }
+
+    for category_id in class_categories {
+        if !categories
+            .iter()
+            .any(|category| category.id == *category_id)
+        {
+            discrepancies.push(Discrepancy::MissingCategory {
+                category_id: *category_id,
+            });
+        }
+    }
+
+    // Only numbers of actual classes, so channels like "2024-events" aren't mistaken for one
+    for channel in channels {
+        let Some(number) = class_number(&CLASS_CHANNEL, &channel.name).filter(|number| {
+            class_roles.iter().any(|(n, _)| n == number)
+                || class_category_ids.iter().any(|(n, _)| n == number)
+        }) else {
+            continue;
+        };
+
+        let in_class_category = channel.parent_id.is_some_and(|parent_id| {
+            class_categories.contains(&parent_id)
+                || class_category_ids.iter().any(|(_, id)| *id == parent_id)
+        });
+
+        if !in_class_category {
+            discrepancies.push(Discrepancy::StrayChannel {
+                channel_id: channel.id,
+                category_id: class_category_ids
+                    .iter()
+                    .find_map(|(n, id)| (*n == number).then_some(*id)),
+            });
+        }
+    }
+
+    discrepancies
+}
+
+fn report_line(i: usize, discrepancy: &Discrepancy, fixed: bool) -> String {
+    if fixed {
+        format!("{}. ~~{}~~ (fixed)", i + 1, discrepancy)
+    } else {
+        format!("{}. {}", i + 1, discrepancy)
+    }
+}
+
+/// Splits the discrepancies into pages that fit in a message, even once they're all fixed.
+fn audit_pages(discrepancies: &[Discrepancy]) -> Vec<Range<usize>> {
+    let mut pages: Vec<Range<usize>> = vec![];
+    let mut length = 0;
+
+    for (i, discrepancy) in discrepancies.iter().enumerate() {
+        let line_length = report_line(i, discrepancy, true).chars().count() + 1;
+
+        match pages.last_mut() {
+            Some(page)
+                if page.len() < MAX_FIX_BUTTONS && length + line_length <= MAX_REPORT_LENGTH =>
+            {
+                page.end = i + 1;
+                length += line_length;
+            }
+            _ => {
+                pages.push(i..i + 1);
+                length = line_length;
+            }
+        }
+    }
+
+    pages
+}
+
+fn audit_report(
+    discrepancies: &[Discrepancy],
+    fixed: &[usize],
+    pages: &[Range<usize>],
+    page: usize,
+) -> String {
+    let mut report = pages[page]
+        .clone()
+        .map(|i| report_line(i, &discrepancies[i], fixed.contains(&i)))
+        .join("\n");
+
+    if pages.len() > 1 {
+        report.push_str(&format!("\n-# Page {}/{}", page + 1, pages.len()));
+    }
+
+    report
+}
+
+fn audit_buttons(
+    discrepancies: &[Discrepancy],
+    fixed: &[usize],
+    pages: &[Range<usize>],
+    page: usize,
+    id_prefix: &str,
+) -> Vec<serenity::CreateActionRow> {
+    let mut rows = pages[page]
+        .clone()
+        .filter(|i| !fixed.contains(i))
+        .filter_map(|i| {
+            let discrepancy = &discrepancies[i];
+            let label = discrepancy.fix_label()?;
+
+            Some(
+                serenity::CreateButton::new(format!("{}{}", id_prefix, i))
+                    .label(format!("{}. {}", i + 1, label))
+                    .style(match discrepancy {
+                        Discrepancy::RoleWithoutCategory { .. } => serenity::ButtonStyle::Danger,
+                        _ => serenity::ButtonStyle::Secondary,
+                    }),
+            )
+        })
+        .chunks(5)
+        .into_iter()
+        .map(|row| serenity::CreateActionRow::Buttons(row.collect()))
+        .collect_vec();
+
+    if pages.len() > 1 {
+        rows.push(serenity::CreateActionRow::Buttons(vec![
+            serenity::CreateButton::new(format!("{}prev", id_prefix))
+                .label("Previous")
+                .disabled(page == 0),
+            serenity::CreateButton::new(format!("{}next", id_prefix))
+                .label("Next")
+                .disabled(page + 1 == pages.len()),
+        ]));
+    }
+
+    rows
+}
+
+/// Find class roles, categories and channels that don't line up
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_CHANNELS"
+)]
+pub async fn class_audit(ctx: PoiseContext<'_>) -> Result<()> {
+    let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+
+    let channels = guild_id.channels(ctx).await?.into_values().collect_vec();
+    let roles = guild_id.roles(ctx).await?.into_values().collect_vec();
+    let class_categories = ctx.data().config.read().await.class_categories.clone();
+
+    let discrepancies = audit(&channels, &roles, &class_categories);
+
+    if discrepancies.is_empty() {
+        ctx.say("Every class role, category and channel lines up.")
+            .await?;
+        return Ok(());
+    }
+
+    let id_prefix = format!("class_audit:{}:", ctx.id());
+    let pages = audit_pages(&discrepancies);
+    let mut page = 0;
+    let mut fixed = vec![];
+
+    let reply = ctx
+        .send(
+            CreateReply::default()
+                .content(audit_report(&discrepancies, &fixed, &pages, page))
+                .components(audit_buttons(
+                    &discrepancies,
+                    &fixed,
+                    &pages,
+                    page,
+                    &id_prefix,
+                )),
+        )
+        .await?;
+
+    let author_id = ctx.author().id;
+
+    while let Some(press) = serenity::ComponentInteractionCollector::new(ctx)
+        .filter({
+            let id_prefix = id_prefix.clone();
+            move |press| press.user.id == author_id && press.data.custom_id.starts_with(&id_prefix)
+        })
+        .timeout(FIX_TIMEOUT)
+        .await
+    {
+        let pressed = &press.data.custom_id[id_prefix.len()..];
+
+        if pressed == "prev" || pressed == "next" {
+            page = match pressed {
+                "prev" => page.saturating_sub(1),
+                _ => (page + 1).min(pages.len() - 1),
+            };
+
+            press
+                .create_response(
+                    ctx,
+                    serenity::CreateInteractionResponse::UpdateMessage(
+                        serenity::CreateInteractionResponseMessage::new()
+                            .content(audit_report(&discrepancies, &fixed, &pages, page))
+                            .components(audit_buttons(
+                                &discrepancies,
+                                &fixed,
+                                &pages,
+                                page,
+                                &id_prefix,
+                            )),
+                    ),
+                )
+                .await?;
+            continue;
+        }
+
+        let Some(i) = pressed
+            .parse::<usize>()
+            .ok()
+            .filter(|i| *i < discrepancies.len())
+        else {
+            continue;
+        };
+
+        let discrepancy = &discrepancies[i];
+
+        if ctx.data().config.read().await.dry_run {
+            press
+                .create_response(
+                    ctx,
+                    serenity::CreateInteractionResponse::Message(
+                        serenity::CreateInteractionResponseMessage::new()
+                            .ephemeral(true)
+                            .content(format!(
+                                "Dry run: would {}",
+                                discrepancy.fix_label().unwrap_or_default().to_lowercase()
+                            )),
+                    ),
+                )
+                .await?;
+            continue;
+        }
+
+        press
+            .create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
+            .await?;
+
+        match discrepancy.fix(ctx).await {
+            Ok(()) => fixed.push(i),
+            Err(e) => {
+                ctx.say(format!("Couldn't fix {}: {}", i + 1, e)).await?;
+                continue;
+            }
+        }
+
+        reply
+            .edit(
+                ctx,
+                CreateReply::default()
+                    .content(audit_report(&discrepancies, &fixed, &pages, page))
+                    .components(audit_buttons(
+                        &discrepancies,
+                        &fixed,
+                        &pages,
+                        page,
+                        &id_prefix,
+                    )),
+            )
+            .await?;
+    }
+
+    reply
+        .edit(
+            ctx,
+            CreateReply::default()
+                .content(audit_report(&discrepancies, &fixed, &pages, page))
+                .components(vec![]),
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn channel(id: u64, name: &str, kind: ChannelType, parent_id: Option<u64>) -> GuildChannel {
+        let mut channel = GuildChannel::default();
+        channel.id = ChannelId::new(id);
+        channel.name = name.to_owned();
+        channel.kind = kind;
+        channel.parent_id = parent_id.map(ChannelId::new);
+        channel
+    }
+
+    fn role(id: u64, name: &str) -> Role {
+        let mut role = Role::default();
+        role.id = RoleId::new(id);
+        role.name = name.to_owned();
+        role
+    }
+
+    #[test]
+    fn finds_discrepancies() {
+        let channels = [
+            channel(1, "CS 2420", ChannelType::Category, None),
+            channel(2, "2420-general", ChannelType::Text, Some(1)),
+            channel(3, "CS 3500", ChannelType::Category, None),
+            channel(4, "3500-general", ChannelType::Text, None),
+            channel(5, "1410-general", ChannelType::Text, None),
+            channel(6, "off-topic", ChannelType::Text, None),
+            channel(7, "2024-events", ChannelType::Text, None),
+        ];
+        let roles = [role(10, "CS 2420"), role(11, "CS 1410"), role(12, "Mods")];
+        let class_categories = [ChannelId::new(1), ChannelId::new(99)];
+
+        assert_eq!(
+            audit(&channels, &roles, &class_categories),
+            vec![
+                Discrepancy::RoleWithoutCategory {
+                    number: 1410,
+                    role_id: RoleId::new(11)
+                },
+                Discrepancy::CategoryWithoutRole {
+                    number: 3500,
+                    category_id: ChannelId::new(3)
+                },
+                Discrepancy::UntrackedCategory {
+                    number: 3500,
+                    category_id: ChannelId::new(3)
+                },
+                Discrepancy::MissingCategory {
+                    category_id: ChannelId::new(99)
+                },
+                Discrepancy::StrayChannel {
+                    channel_id: ChannelId::new(4),
+                    category_id: Some(ChannelId::new(3))
+                },
+                Discrepancy::StrayChannel {
+                    channel_id: ChannelId::new(5),
+                    category_id: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn all_good_when_lined_up() {
+        let channels = [
+            channel(1, "CS 2420", ChannelType::Category, None),
+            channel(2, "2420-general", ChannelType::Text, Some(1)),
+        ];
+        let roles = [role(10, "CS 2420")];
+
+        assert!(audit(&channels, &roles, &[ChannelId::new(1)]).is_empty());
+    }
+
+    #[test]
+    fn only_fixable_discrepancies_get_buttons() {
+        let discrepancies = [
+            Discrepancy::MissingCategory {
+                category_id: ChannelId::new(99),
+            },
+            Discrepancy::StrayChannel {
+                channel_id: ChannelId::new(5),
+                category_id: None,
+            },
+        ];
+
+        let pages = audit_pages(&discrepancies);
+
+        assert_eq!(audit_buttons(&discrepancies, &[], &pages, 0, "").len(), 1);
+        assert!(audit_buttons(&discrepancies, &[0], &pages, 0, "").is_empty());
+    }
+
+    #[test]
+    fn long_reports_get_paged() {
+        let discrepancies = (0..60)
+            .map(|i| Discrepancy::MissingCategory {
+                category_id: ChannelId::new(1_000_000_000_000_000_000 + i),
+            })
+            .collect_vec();
+        let pages = audit_pages(&discrepancies);
+
+        assert_eq!(pages.first().map(|page| page.start), Some(0));
+        assert_eq!(pages.last().map(|page| page.end), Some(60));
+        assert!(pages.windows(2).all(|pair| pair[0].end == pair[1].start));
+
+        let all_fixed = (0..60).collect_vec();
+        for page in 0..pages.len() {
+            assert!(pages[page].len() <= MAX_FIX_BUTTONS);
+            assert!(
+                audit_report(&discrepancies, &all_fixed, &pages, page)
+                    .chars()
+                    .count()
+                    <= 2000
+            );
+            assert!(audit_buttons(&discrepancies, &[], &pages, page, "").len() <= 5);
+        }
+    }
+}
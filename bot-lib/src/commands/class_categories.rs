@@ -0,0 +1,110 @@
+use crate::{commands::class_audit::class_category_number, data::PoiseContext};
+use color_eyre::eyre::{OptionExt, Result};
+use itertools::Itertools;
+use poise::serenity_prelude::{ChannelId, GuildChannel};
+
+#[poise::command(
+    slash_command,
+    subcommands("class_categories_sync"),
+    subcommand_required,
+    required_permissions = "MANAGE_CHANNELS",
+    description_localized("en-US", "The categories kingfisher treats as classes")
+)]
+pub async fn class_categories(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Every "CS <number>" category, in the order they appear in the channel list.
+fn find_class_categories(channels: &[GuildChannel]) -> Vec<ChannelId> {
+    channels
+        .iter()
+        .filter(|channel| class_category_number(channel).is_some())
+        .sorted_by_key(|channel| channel.position)
+        .map(|channel| channel.id)
+        .collect()
+}
+
+/// Rebuild class_categories from the server's "CS <number>" categories
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_CHANNELS",
+    rename = "sync"
+)]
+pub async fn class_categories_sync(ctx: PoiseContext<'_>) -> Result<()> {
+    let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+
+    let channels = guild_id.channels(ctx).await?.into_values().collect_vec();
+    let found = find_class_categories(&channels);
+
+    let mut config = ctx.data().config.write().await;
+
+    let added = found
+        .iter()
+        .filter(|id| !config.class_categories.contains(id))
+        .count();
+    let removed = config
+        .class_categories
+        .iter()
+        .filter(|id| !found.contains(id))
+        .count();
+
+    if added == 0 && removed == 0 {
+        ctx.say(format!(
+            "class_categories is already up to date with {} categories.",
+            found.len()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    if config.dry_run {
+        ctx.say(format!(
+            "Dry run: would add {} and remove {} class categories.",
+            added, removed
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    config.class_categories = found;
+    config.save()?;
+
+    ctx.say(format!(
+        "Synced class_categories: added {}, removed {}.",
+        added, removed
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use poise::serenity_prelude::ChannelType;
+
+    fn channel(id: u64, name: &str, kind: ChannelType, position: u16) -> GuildChannel {
+        let mut channel = GuildChannel::default();
+        channel.id = ChannelId::new(id);
+        channel.name = name.to_owned();
+        channel.kind = kind;
+        channel.position = position;
+        channel
+    }
+
+    #[test]
+    fn finds_class_categories() {
+        let channels = [
+            channel(1, "CS 3500", ChannelType::Category, 2),
+            channel(2, "CS 2420", ChannelType::Category, 1),
+            channel(3, "General", ChannelType::Category, 0),
+            channel(4, "CS 1410", ChannelType::Text, 3),
+        ];
+
+        assert_eq!(
+            find_class_categories(&channels),
+            vec![ChannelId::new(2), ChannelId::new(1)]
+        );
+    }
+}
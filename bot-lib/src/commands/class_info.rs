@@ -0,0 +1,91 @@
+use crate::{
+    class_directory::refresh_class_directory,
+    class_info::{get_class_info, save_class_info},
+    data::PoiseContext,
+};
+use color_eyre::eyre::{OptionExt, Result};
+use poise::{serenity_prelude as serenity, ChoiceParameter, CreateReply};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ChoiceParameter)]
+pub enum ClassInfoField {
+    Professor,
+    #[name = "Meeting time"]
+    MeetingTime,
+}
+
+/// See who teaches a class and when it meets
+#[poise::command(slash_command, ephemeral = true)]
+pub async fn course_info(
+    ctx: PoiseContext<'_>,
+    #[description = "The class number, eg. for CS2420 put in \"2420\""] class: u32,
+) -> Result<()> {
+    let info = get_class_info(&ctx.data().db, class)?;
+
+    let fields = [
+        ("Professor", info.professor),
+        ("Meeting time", info.meeting_time),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some((name, value?, true)))
+    .collect::<Vec<_>>();
+
+    if fields.is_empty() {
+        ctx.say(format!("No section info for CS {} yet.", class))
+            .await?;
+        return Ok(());
+    }
+
+    ctx.send(
+        CreateReply::default().embed(
+            serenity::CreateEmbed::new()
+                .title(format!("CS {}", class))
+                .fields(fields),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Set (or clear) a class's professor or meeting time
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_MESSAGES"
+)]
+pub async fn set_class_info(
+    ctx: PoiseContext<'_>,
+    #[description = "The class number, eg. for CS2420 put in \"2420\""] class: u32,
+    #[description = "What to set"] field: ClassInfoField,
+    #[description = "e.g. Prof. Smith or MWF 10:45-11:35. Leave empty to clear"]
+    #[max_length = 100]
+    value: Option<String>,
+) -> Result<()> {
+    let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+    let db = &ctx.data().db;
+    let mut info = get_class_info(db, class)?;
+
+    match field {
+        ClassInfoField::Professor => info.professor = value.clone(),
+        ClassInfoField::MeetingTime => info.meeting_time = value.clone(),
+    }
+
+    save_class_info(db, &info)?;
+    refresh_class_directory(ctx.serenity_context(), ctx.data(), guild_id).await?;
+
+    ctx.say(match value {
+        Some(_) => format!(
+            "Updated the {} for CS {}.",
+            field.name().to_lowercase(),
+            class
+        ),
+        None => format!(
+            "Cleared the {} for CS {}.",
+            field.name().to_lowercase(),
+            class
+        ),
+    })
+    .await?;
+
+    Ok(())
+}
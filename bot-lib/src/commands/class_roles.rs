@@ -1,4 +1,5 @@
-use crate::commands::{get_author, get_role};
+use crate::class_notifications::{find_notification_role, set_class_notifications};
+use crate::commands::{ensure_can_manage_role, get_author, get_role};
 use crate::data::PoiseContext;
 use color_eyre::eyre::{Result, WrapErr};
 
@@ -6,15 +7,30 @@ use color_eyre::eyre::{Result, WrapErr};
 pub async fn add_class_role(
     ctx: PoiseContext<'_>,
     #[description = "The class number, eg. for CS2420 put in \"2420\""] number: u32,
+    #[description = "Get pinged for class announcements (default: yes)"] notifications: Option<
+        bool,
+    >,
 ) -> Result<()> {
     let author = get_author(ctx).await?;
     let role_id = get_role(ctx, number).await?;
+    ensure_can_manage_role(ctx, Some(role_id)).await?;
 
     author
         .add_role(ctx, role_id)
         .await
         .wrap_err("Couldn't add role")?;
 
+    let notification_role_id = find_notification_role(&author.guild_id.roles(ctx).await?, role_id);
+    set_class_notifications(
+        ctx.serenity_context(),
+        author.guild_id,
+        author.user.id,
+        notification_role_id,
+        notifications.unwrap_or(true),
+    )
+    .await
+    .wrap_err("Couldn't change class notifications")?;
+
     ctx.say("Joined class!").await?;
 
     Ok(())
@@ -32,12 +48,24 @@ pub async fn remove_class_role(
 ) -> Result<()> {
     let author = get_author(ctx).await?;
     let role_id = get_role(ctx, number).await?;
+    ensure_can_manage_role(ctx, Some(role_id)).await?;
 
     author
         .remove_role(ctx, role_id)
         .await
         .wrap_err("Couldn't remove role")?;
 
+    let notification_role_id = find_notification_role(&author.guild_id.roles(ctx).await?, role_id);
+    set_class_notifications(
+        ctx.serenity_context(),
+        author.guild_id,
+        author.user.id,
+        notification_role_id,
+        false,
+    )
+    .await
+    .wrap_err("Couldn't change class notifications")?;
+
     ctx.say("Left class!").await?;
 
     Ok(())
@@ -0,0 +1,88 @@
+use crate::{data::PoiseContext, link_preview::parse_message_link, utils::member_permissions_in};
+use color_eyre::eyre::{OptionExt, Result};
+use poise::serenity_prelude::{self as serenity};
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    ephemeral = true,
+    required_permissions = "MANAGE_MESSAGES",
+    description_localized("en-US", "Remove every reaction, or one emoji's, from a message")
+)]
+pub async fn clear_reactions(
+    ctx: PoiseContext<'_>,
+    #[description = "Link to the message"] message_link: String,
+    #[description = "Only remove this emoji"] emoji: Option<String>,
+) -> Result<()> {
+    let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+
+    let Some((_, channel_id, message_id)) =
+        parse_message_link(&message_link).filter(|(link_guild_id, ..)| *link_guild_id == guild_id)
+    else {
+        ctx.say("That isn't a link to a message in this server.")
+            .await?;
+        return Ok(());
+    };
+
+    // The command's own permission check only covers the channel it was run in
+    let member = ctx
+        .author_member()
+        .await
+        .ok_or_eyre("Couldn't get member")?
+        .into_owned();
+    let allowed = member_permissions_in(ctx.serenity_context(), guild_id, channel_id, &member)
+        .await
+        .is_ok_and(|permissions| permissions.view_channel() && permissions.manage_messages());
+
+    if !allowed {
+        ctx.say("You can't manage messages in that channel.")
+            .await?;
+        return Ok(());
+    }
+
+    let message = channel_id.message(ctx, message_id).await?;
+
+    let removed = match emoji {
+        Some(emoji) => {
+            let Ok(reaction_type) = serenity::ReactionType::try_from(emoji.trim()) else {
+                ctx.say("That isn't an emoji.").await?;
+                return Ok(());
+            };
+
+            let count = message
+                .reactions
+                .iter()
+                .find(|reaction| reaction.reaction_type == reaction_type)
+                .map_or(0, |reaction| reaction.count);
+
+            if count > 0 {
+                message.delete_reaction_emoji(ctx, reaction_type).await?;
+            }
+
+            count
+        }
+        None => {
+            let count = message
+                .reactions
+                .iter()
+                .map(|reaction| reaction.count)
+                .sum();
+
+            if count > 0 {
+                message.delete_reactions(ctx).await?;
+            }
+
+            count
+        }
+    };
+
+    ctx.say(format!(
+        "Removed {} reaction{} from {}",
+        removed,
+        if removed == 1 { "" } else { "s" },
+        message.link()
+    ))
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use crate::data::PoiseContext;
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+const COMMAND_INVOCATIONS_TREE: &str = "command_invocations";
+/// Keeps each page of the table well under the message limit.
+const ROWS_PER_PAGE: usize = 25;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandInvocation {
+    pub name: String,
+    pub user_id: u64,
+    pub duration_ms: u64,
+    pub success: bool,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, PartialEq)]
+struct CommandSummary<'a> {
+    name: &'a str,
+    count: usize,
+    failures: usize,
+    p95_ms: u64,
+}
+
+/// Called before every command, remembers when it started.
+pub async fn record_command_start(ctx: PoiseContext<'_>) {
+    ctx.set_invocation_data(Instant::now()).await;
+}
+
+/// Called after every command (or after it errored), stores the invocation in the db.
+pub async fn record_command_end(ctx: PoiseContext<'_>, success: bool) {
+    let duration_ms = ctx
+        .invocation_data::<Instant>()
+        .await
+        .map(|start| start.elapsed().as_millis() as u64)
+        .unwrap_or_default();
+
+    let invocation = CommandInvocation {
+        name: ctx.command().qualified_name.clone(),
+        user_id: ctx.author().id.get(),
+        duration_ms,
+        success,
+        timestamp: chrono::Utc::now().timestamp(),
+    };
+
+    let db = &ctx.data().db;
+
+    if let Err(e) = db
+        .generate_id()
+        .and_then(|id| db.insert(COMMAND_INVOCATIONS_TREE, id.to_be_bytes(), &invocation))
+    {
+        tracing::warn!("Failed to record command invocation: {:?}", e);
+    }
+}
+
+fn summarize(invocations: &[CommandInvocation]) -> Vec<CommandSummary<'_>> {
+    invocations
+        .iter()
+        .into_group_map_by(|invocation| invocation.name.as_str())
+        .into_iter()
+        .map(|(name, invocations)| {
+            let durations = invocations
+                .iter()
+                .map(|invocation| invocation.duration_ms)
+                .sorted()
+                .collect_vec();
+
+            let p95_index = (durations.len() * 95).div_ceil(100).saturating_sub(1);
+
+            CommandSummary {
+                name,
+                count: invocations.len(),
+                failures: invocations.iter().filter(|i| !i.success).count(),
+                p95_ms: durations[p95_index],
+            }
+        })
+        .sorted_by(|a, b| b.count.cmp(&a.count).then(a.name.cmp(b.name)))
+        .collect()
+}
+
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    description_localized("en-US", "Shows how often each command is used")
+)]
+pub async fn command_stats(ctx: PoiseContext<'_>) -> Result<()> {
+    let invocations = ctx
+        .data()
+        .db
+        .values::<CommandInvocation>(COMMAND_INVOCATIONS_TREE)?;
+
+    if invocations.is_empty() {
+        ctx.say("No commands have been recorded yet.").await?;
+        return Ok(());
+    }
+
+    // One page would go over the message limit once there are enough commands
+    let pages = summarize(&invocations)
+        .into_iter()
+        .map(|summary| {
+            format!(
+                "{:<24} {:>6} {:>6} {:>8}ms",
+                summary.name, summary.count, summary.failures, summary.p95_ms
+            )
+        })
+        .chunks(ROWS_PER_PAGE)
+        .into_iter()
+        .map(|mut rows| {
+            format!(
+                "```\n{:<24} {:>6} {:>6} {:>10}\n{}\n```",
+                "command",
+                "uses",
+                "errors",
+                "p95",
+                rows.join("\n")
+            )
+        })
+        .collect_vec();
+
+    poise::builtins::paginate(ctx, &pages.iter().map(String::as_str).collect_vec()).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn invocation(name: &str, duration_ms: u64, success: bool) -> CommandInvocation {
+        CommandInvocation {
+            name: name.to_owned(),
+            user_id: 0,
+            duration_ms,
+            success,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn summarizes_by_command() {
+        let mut invocations = (1..=100)
+            .map(|duration| invocation("catalog", duration, true))
+            .collect_vec();
+        invocations.push(invocation("help", 5, false));
+
+        assert_eq!(
+            summarize(&invocations),
+            vec![
+                CommandSummary {
+                    name: "catalog",
+                    count: 100,
+                    failures: 0,
+                    p95_ms: 95,
+                },
+                CommandSummary {
+                    name: "help",
+                    count: 1,
+                    failures: 1,
+                    p95_ms: 5,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{
+    course_reviews::{
+        delete_review, format_review, get_review, get_reviews, paginate_reviews, save_review,
+        set_review_hidden, summarize, CourseReview,
+    },
+    data::{PoiseApplicationContext, PoiseContext},
+    mod_log::mod_log,
+};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::{
+    serenity_prelude::{self as serenity, Mentionable},
+    ChoiceParameter, Modal,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ChoiceParameter)]
+pub enum ReviewAction {
+    Hide,
+    Unhide,
+    Delete,
+}
+
+#[derive(Debug, Default, Modal)]
+#[name = "Review this class"]
+struct ReviewModal {
+    #[name = "Difficulty, 1 (easy) to 5 (brutal)"]
+    #[min_length = 1]
+    #[max_length = 1]
+    difficulty: String,
+    #[name = "Hours per week outside of class"]
+    #[max_length = 3]
+    workload: String,
+    #[name = "Anything else? Shown anonymously"]
+    #[paragraph]
+    #[max_length = 1000]
+    comments: Option<String>,
+}
+
+/// See what people thought of a class
+#[poise::command(slash_command, ephemeral = true)]
+pub async fn course_reviews(
+    ctx: PoiseContext<'_>,
+    #[description = "The class number, eg. for CS2420 put in \"2420\""] class: u32,
+) -> Result<()> {
+    let reviews = get_reviews(&ctx.data().db, class, false)?;
+
+    let Some(summary) = summarize(&reviews) else {
+        ctx.say(format!(
+            "No reviews for CS {} yet, `/review_course {}` if you've taken it!",
+            class, class
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    let header = format!(
+        "## CS {}\n{} reviews, difficulty {:.1}/5, ~{:.0} hrs/week\n",
+        class, summary.count, summary.difficulty, summary.workload
+    );
+
+    let pages = paginate_reviews(&header, &reviews);
+
+    poise::builtins::paginate(ctx, &pages.iter().map(String::as_str).collect_vec()).await?;
+
+    Ok(())
+}
+
+/// Anonymously review a class you've taken
+#[poise::command(slash_command, ephemeral = true)]
+pub async fn review_course(
+    ctx: PoiseApplicationContext<'_>,
+    #[description = "The class number, eg. for CS2420 put in \"2420\""] class: u32,
+) -> Result<()> {
+    let db = &ctx.data().db;
+    let old = get_review(db, class, ctx.author().id)?;
+
+    let defaults = old
+        .as_ref()
+        .map(|old| ReviewModal {
+            difficulty: old.difficulty.to_string(),
+            workload: old.workload.to_string(),
+            comments: old.comments.clone(),
+        })
+        .unwrap_or_default();
+
+    let Some(ReviewModal {
+        difficulty,
+        workload,
+        comments,
+    }) = ReviewModal::execute_with_defaults(ctx, defaults).await?
+    else {
+        return Ok(());
+    };
+
+    let (Ok(difficulty @ 1..=5), Ok(workload)) = (
+        difficulty.trim().parse::<u8>(),
+        workload.trim().parse::<u8>(),
+    ) else {
+        ctx.say("Difficulty has to be 1 to 5, and hours a whole number.")
+            .await?;
+        return Ok(());
+    };
+
+    save_review(
+        db,
+        CourseReview {
+            id: db.generate_id()?,
+            class,
+            user_id: ctx.author().id.get(),
+            difficulty,
+            workload,
+            comments: comments.filter(|comments| !comments.trim().is_empty()),
+            hidden: false,
+            created_at: chrono::Utc::now(),
+        },
+    )?;
+
+    ctx.say(match old {
+        Some(_) => format!("Updated your CS {} review, thanks!", class),
+        None => format!("Thanks for reviewing CS {}!", class),
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Hide, unhide or delete a class review
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_MESSAGES"
+)]
+pub async fn moderate_review(
+    ctx: PoiseContext<'_>,
+    #[description = "The class number, eg. for CS2420 put in \"2420\""] class: u32,
+    #[description = "The number after the # on the review"] review: u64,
+    #[description = "What to do with it"] action: ReviewAction,
+) -> Result<()> {
+    let db = &ctx.data().db;
+
+    let changed = match action {
+        ReviewAction::Hide => set_review_hidden(db, class, review, true)?,
+        ReviewAction::Unhide => set_review_hidden(db, class, review, false)?,
+        ReviewAction::Delete => delete_review(db, class, review)?,
+    };
+
+    let Some(changed) = changed else {
+        ctx.say(format!("CS {} has no review #{}.", class, review))
+            .await?;
+        return Ok(());
+    };
+
+    mod_log(
+        ctx.serenity_context(),
+        ctx.data(),
+        serenity::CreateEmbed::new()
+            .title(format!("Course review: {}", action.name()))
+            .description(format!(
+                "{} used {} on CS {}\n\n{}",
+                ctx.author().mention(),
+                action.name().to_lowercase(),
+                class,
+                format_review(&changed)
+            )),
+    )
+    .await?;
+
+    ctx.say(format!(
+        "Done, {} CS {} review #{}.",
+        match action {
+            ReviewAction::Hide => "hid",
+            ReviewAction::Unhide => "unhid",
+            ReviewAction::Delete => "deleted",
+        },
+        class,
+        review
+    ))
+    .await?;
+
+    Ok(())
+}
@@ -1,43 +1,150 @@
+use crate::capabilities::can_manage_classes;
+use crate::class_directory::refresh_class_directory;
+use crate::class_emoji::create_class_emoji;
+use crate::class_info::{save_class_info, ClassInfo};
+use crate::commands::ensure_can_manage_role;
 use crate::data::PoiseContext;
+use crate::departments::{has_role_icons, Department};
+use crate::discord_api::DiscordApi;
 use color_eyre::eyre::{OptionExt, Result, WrapErr};
 use poise::serenity_prelude::{self as serenity};
-use serenity::{ChannelType, PermissionOverwrite, PermissionOverwriteType, Permissions, RoleId};
+use serenity::{
+    ChannelId, ChannelType, GuildId, PermissionOverwrite, PermissionOverwriteType, Permissions,
+    RoleId,
+};
 
 const MOD_ROLE_ID: RoleId = RoleId::new(1192863993883279532);
 
 #[poise::command(
     slash_command,
-    required_permissions = "MANAGE_CHANNELS",
+    check = "can_manage_classes",
     description_localized("en-US", "Creates a class category")
 )]
 pub async fn create_class_category(
     ctx: PoiseContext<'_>,
     #[description = "The class number, eg. for CS2420 put in \"2420\""] number: u32,
+    #[description = "Also make a :cs<number>: emoji for joining from the class directory"]
+    emoji: Option<bool>,
+    #[description = "Who teaches it, e.g. Prof. Smith"] professor: Option<String>,
+    #[description = "When it meets, e.g. MWF 10:45-11:35"] meeting_time: Option<String>,
 ) -> Result<()> {
     let guild = ctx.guild().ok_or_eyre("Couldn't get guild")?.id;
-    let channels = guild.channels(ctx).await?;
 
-    let number_string = number.to_string();
-    for (_id, channel) in channels {
-        if channel.name.contains(&number_string) {
-            ctx.say("Category/channels already seem to exist!").await?;
-            return Ok(());
-        }
+    if class_exists(ctx.serenity_context(), guild, number).await? {
+        ctx.say("Category/channels already seem to exist!").await?;
+        return Ok(());
     }
 
-    let role = guild
+    ensure_can_manage_role(ctx, None).await?;
+
+    let department = ctx
+        .data()
+        .config
+        .read()
+        .await
+        .departments
+        .get("CS")
+        .cloned()
+        .unwrap_or_default();
+    let role_icons = ctx.guild().is_some_and(|guild| has_role_icons(&guild));
+    let icon = department.load_icon(role_icons).await?;
+
+    let category_id = create_class(
+        ctx.serenity_context(),
+        guild,
+        number,
+        &department,
+        icon.as_ref(),
+    )
+    .await?;
+
+    ctx.data()
+        .config
+        .write()
+        .await
+        .track_class_category(category_id)?;
+
+    save_class_info(
+        &ctx.data().db,
+        &ClassInfo {
+            number,
+            professor,
+            meeting_time,
+        },
+    )?;
+
+    let reply = match emoji {
+        Some(true) => {
+            let class_emoji = ctx.data().config.read().await.class_emoji.clone();
+
+            match class_emoji {
+                Some(class_emoji) => {
+                    let emoji = create_class_emoji(
+                        ctx.serenity_context(),
+                        &ctx.data().db,
+                        guild,
+                        &class_emoji,
+                        number,
+                    )
+                    .await
+                    .wrap_err("Created the class, but couldn't make its emoji")?;
+
+                    format!(
+                        "Success! React with {} in the class directory to join.",
+                        emoji
+                    )
+                }
+                None => "Created the class, but no class emoji template is configured.".to_owned(),
+            }
+        }
+        _ => "Success!".to_owned(),
+    };
+
+    refresh_class_directory(ctx.serenity_context(), ctx.data(), guild).await?;
+
+    ctx.say(reply).await?;
+    Ok(())
+}
+
+/// Whether any channel already has the class number in its name.
+async fn class_exists(api: &impl DiscordApi, guild: GuildId, number: u32) -> Result<bool> {
+    let number_string = number.to_string();
+
+    Ok(api
+        .guild_channels(guild)
+        .await?
+        .iter()
+        .any(|channel| channel.name.contains(&number_string)))
+}
+
+/// Makes the class role, and a category only it (and mods) can see with the class channels.
+///
+/// The role gets the department's color and icon. Returns the new category.
+async fn create_class(
+    api: &impl DiscordApi,
+    guild: GuildId,
+    number: u32,
+    department: &Department,
+    icon: Option<&serenity::CreateAttachment>,
+) -> Result<ChannelId> {
+    let number_string = number.to_string();
+
+    let role = api
         .create_role(
-            ctx,
-            serenity::EditRole::new()
-                .hoist(true)
-                .name(format!("CS {}", number_string)),
+            guild,
+            department.theme(
+                serenity::EditRole::new()
+                    .hoist(true)
+                    .name(format!("CS {}", number_string)),
+                icon,
+            ),
         )
         .await
         .wrap_err("Couldn't create role")?;
 
-    let category = guild
+    let category = api
         .create_channel(
-            ctx,
+            guild,
             serenity::CreateChannel::new(format!("CS {}", number_string))
                 .kind(ChannelType::Category)
                 .permissions(vec![
@@ -61,26 +168,83 @@ pub async fn create_class_category(
         .await
         .wrap_err("Couldn't create category")?;
 
-    guild
-        .create_channel(
-            ctx,
-            serenity::CreateChannel::new(format!("{}-resources", number_string))
-                .kind(ChannelType::Text)
-                .category(category.id),
-        )
-        .await
-        .wrap_err("Couldn't create resources channel")?;
+    api.create_channel(
+        guild,
+        serenity::CreateChannel::new(format!("{}-resources", number_string))
+            .kind(ChannelType::Text)
+            .category(category.id),
+    )
+    .await
+    .wrap_err("Couldn't create resources channel")?;
 
-    guild
-        .create_channel(
-            ctx,
-            serenity::CreateChannel::new(format!("{}-general", number_string))
-                .kind(ChannelType::Text)
-                .category(category.id),
-        )
-        .await
-        .wrap_err("Couldn't create general channel")?;
+    api.create_channel(
+        guild,
+        serenity::CreateChannel::new(format!("{}-general", number_string))
+            .kind(ChannelType::Text)
+            .category(category.id),
+    )
+    .await
+    .wrap_err("Couldn't create general channel")?;
 
-    ctx.say("Success!").await?;
-    Ok(())
+    Ok(category.id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::departments::HexColor;
+    use crate::discord_api::MockDiscord;
+
+    const GUILD: GuildId = GuildId::new(1065373537591894086);
+
+    #[tokio::test]
+    async fn detects_existing_classes() {
+        let discord = MockDiscord::default();
+        discord.add_channel("2420-general");
+
+        assert!(class_exists(&discord, GUILD, 2420).await.unwrap());
+        assert!(!class_exists(&discord, GUILD, 3500).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn creates_class() {
+        let discord = MockDiscord::default();
+
+        let department = Department {
+            color: Some(HexColor(0xcc0000)),
+            icon: None,
+        };
+        let category_id = create_class(&discord, GUILD, 2420, &department, None)
+            .await
+            .unwrap();
+
+        let roles = discord.roles.lock().clone();
+        assert_eq!(roles.len(), 1);
+        assert_eq!(roles[0].name, "CS 2420");
+        assert!(roles[0].hoist);
+        assert_eq!(roles[0].colour.0, 0xcc0000);
+
+        let category = discord.channel_named("CS 2420").unwrap();
+        assert_eq!(category.kind, ChannelType::Category);
+        assert_eq!(category.id, category_id);
+
+        let visible_to = |role_id: RoleId| {
+            category.permission_overwrites.iter().any(|overwrite| {
+                overwrite.kind == PermissionOverwriteType::Role(role_id)
+                    && overwrite.allow.contains(Permissions::VIEW_CHANNEL)
+            })
+        };
+        assert!(visible_to(roles[0].id));
+        assert!(visible_to(MOD_ROLE_ID));
+        assert!(!visible_to(GUILD.everyone_role()));
+
+        for name in ["2420-resources", "2420-general"] {
+            let channel = discord.channel_named(name).unwrap();
+
+            assert_eq!(channel.kind, ChannelType::Text);
+            assert_eq!(channel.parent_id, Some(category.id));
+        }
+
+        assert!(class_exists(&discord, GUILD, 2420).await.unwrap());
+    }
 }
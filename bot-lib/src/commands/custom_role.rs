@@ -0,0 +1,86 @@
+use crate::{
+    custom_roles::{delete_custom_role, set_custom_role},
+    data::PoiseContext,
+    departments::HexColor,
+};
+use color_eyre::eyre::{OptionExt, Result};
+use poise::serenity_prelude::{Mentionable, RoleId};
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("custom_role_set", "custom_role_remove"),
+    subcommand_required,
+    description_localized("en-US", "Your own role, for boosters and regulars")
+)]
+pub async fn custom_role(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Create or change your custom role
+#[poise::command(slash_command, ephemeral = true, rename = "set")]
+pub async fn custom_role_set(
+    ctx: PoiseContext<'_>,
+    #[description = "What the role is called"]
+    #[max_length = 100]
+    name: String,
+    #[description = "Its color, like #cc0000"] color: String,
+) -> Result<()> {
+    let Some(custom_roles) = ctx.data().config.read().await.custom_roles.clone() else {
+        ctx.say("Custom roles aren't set up.").await?;
+        return Ok(());
+    };
+
+    let member = ctx
+        .author_member()
+        .await
+        .ok_or_eyre("Couldn't get member")?;
+
+    if !custom_roles.is_eligible(&member) {
+        ctx.say("Custom roles are for server boosters and members who've leveled up.")
+            .await?;
+        return Ok(());
+    }
+
+    let Ok(color) = color.parse::<HexColor>() else {
+        ctx.say("That isn't a color, try something like `#cc0000`.")
+            .await?;
+        return Ok(());
+    };
+
+    let role_id = set_custom_role(
+        ctx.serenity_context(),
+        ctx.data(),
+        &member,
+        RoleId::new(custom_roles.anchor_role_id),
+        &name,
+        color,
+    )
+    .await?;
+
+    ctx.say(format!("You're now {}.", role_id.mention()))
+        .await?;
+
+    Ok(())
+}
+
+/// Delete your custom role
+#[poise::command(slash_command, ephemeral = true, rename = "remove")]
+pub async fn custom_role_remove(ctx: PoiseContext<'_>) -> Result<()> {
+    let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+
+    if delete_custom_role(
+        ctx.serenity_context(),
+        ctx.data(),
+        guild_id,
+        ctx.author().id,
+    )
+    .await?
+    {
+        ctx.say("Your custom role is gone.").await?;
+    } else {
+        ctx.say("You don't have a custom role.").await?;
+    }
+
+    Ok(())
+}
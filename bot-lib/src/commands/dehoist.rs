@@ -0,0 +1,49 @@
+use crate::{
+    data::PoiseContext,
+    name_policy::{enforce_name_policy, enforce_name_policy_everywhere},
+};
+use color_eyre::eyre::{OptionExt, Result};
+use poise::serenity_prelude::{Mentionable, User};
+
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_NICKNAMES",
+    description_localized(
+        "en-US",
+        "Renames a member (or everyone) whose name breaks the name policy"
+    )
+)]
+pub async fn dehoist(
+    ctx: PoiseContext<'_>,
+    #[description = "The member to check, leave empty to check everyone"] user: Option<User>,
+) -> Result<()> {
+    let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+
+    if ctx.data().config.read().await.name_policy.is_none() {
+        ctx.say("No name policy is configured.").await?;
+        return Ok(());
+    }
+
+    let Some(user) = user else {
+        ctx.defer_ephemeral().await?;
+
+        let renamed =
+            enforce_name_policy_everywhere(ctx.serenity_context(), ctx.data(), guild_id, true)
+                .await?;
+
+        ctx.say(format!("Renamed {} members.", renamed)).await?;
+        return Ok(());
+    };
+
+    let member = guild_id.member(ctx, user.id).await?;
+
+    if enforce_name_policy(ctx.serenity_context(), ctx.data(), &member, true).await? {
+        ctx.say(format!("Renamed {}!", user.mention())).await?;
+    } else {
+        ctx.say(format!("{}'s name is fine.", user.mention()))
+            .await?;
+    }
+
+    Ok(())
+}
@@ -1,6 +1,13 @@
-use crate::commands::{get_channels, get_role};
+use crate::class_directory::refresh_class_directory;
+use crate::class_emoji::remove_class_emoji;
+use crate::class_info::{get_class_info, save_class_info, ClassInfo};
+use crate::commands::{ensure_can_manage_role, get_channels, get_role};
 use crate::data::PoiseContext;
-use color_eyre::eyre::{OptionExt, Result};
+use crate::deleted_categories::{save_deleted_category, DeletedCategory};
+use crate::two_person::require_second_mod;
+use color_eyre::eyre::{OptionExt, Result, WrapErr};
+use itertools::Itertools;
+use poise::serenity_prelude::Mentionable;
 use regex::Regex;
 
 #[poise::command(
@@ -11,6 +18,7 @@ use regex::Regex;
 pub async fn delete_class_category(
     ctx: PoiseContext<'_>,
     #[description = "The class number, eg. for CS2420 put in \"2420\""] number: u32,
+    #[description = "Export the channel history before deleting"] export: Option<bool>,
 ) -> Result<()> {
     let guild = ctx.guild().ok_or_eyre("Couldn't get guild")?.id;
     let channels = guild.channels(ctx).await?;
@@ -23,9 +31,84 @@ pub async fn delete_class_category(
 
     let children_channels = channels
         .iter()
-        .filter(|x| matches!(x.1.parent_id, Some(parent) if parent.eq(&category_channel.id)));
+        .filter(|x| matches!(x.1.parent_id, Some(parent) if parent.eq(&category_channel.id)))
+        .collect_vec();
 
     let role_id = get_role(ctx, number).await?;
+    ensure_can_manage_role(ctx, Some(role_id)).await?;
+
+    if ctx.data().config.read().await.dry_run {
+        ctx.say(format!(
+            "Dry run: would delete category {}, channels {} and role {}",
+            category_channel.name,
+            children_channels
+                .iter()
+                .map(|channel| format!("#{}", channel.1.name))
+                .join(", "),
+            role_id.mention()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let description = format!(
+        "Delete category {}, its {} channels and {}",
+        category_channel.name,
+        children_channels.len(),
+        role_id.mention()
+    );
+
+    if !require_second_mod(ctx, description).await? {
+        return Ok(());
+    }
+
+    let role = guild
+        .roles(ctx)
+        .await?
+        .get(&role_id)
+        .ok_or_eyre("Could not find class role!")?
+        .into();
+
+    let deleted = DeletedCategory {
+        number,
+        deleted_by: ctx.author().id.get(),
+        deleted_at: chrono::Utc::now().timestamp(),
+        role,
+        category: category_channel.into(),
+        channels: children_channels
+            .iter()
+            .map(|channel| channel.1.into())
+            .collect(),
+        info: get_class_info(&ctx.data().db, number)?,
+    };
+
+    if let Some(true) = export {
+        let class_archive = ctx.data().config.read().await.class_archive.clone();
+
+        let Some(class_archive) = class_archive else {
+            ctx.say("No class archive is configured, nothing was deleted.")
+                .await?;
+            return Ok(());
+        };
+
+        ctx.defer().await?;
+
+        let channels = children_channels
+            .iter()
+            .map(|channel| channel.1)
+            .collect_vec();
+
+        class_archive
+            .export(
+                ctx.serenity_context(),
+                &format!("cs{}-archive.json.gz", number),
+                &channels,
+            )
+            .await
+            .wrap_err("Export failed, nothing was deleted")?;
+    }
+
+    save_deleted_category(&ctx.data().db, &deleted)?;
 
     category_channel.delete(ctx).await?;
     for channel in children_channels {
@@ -33,6 +116,23 @@ pub async fn delete_class_category(
     }
     guild.delete_role(ctx, role_id).await?;
 
-    ctx.say("Success!").await?;
+    ctx.data()
+        .config
+        .write()
+        .await
+        .untrack_class_category(category_channel.id)?;
+
+    remove_class_emoji(ctx.serenity_context(), &ctx.data().db, guild, number).await?;
+    save_class_info(
+        &ctx.data().db,
+        &ClassInfo {
+            number,
+            ..Default::default()
+        },
+    )?;
+    refresh_class_directory(ctx.serenity_context(), ctx.data(), guild).await?;
+
+    ctx.say("Success! `/undo_last_deletion` can bring it back within 24 hours.")
+        .await?;
     Ok(())
 }
@@ -0,0 +1,70 @@
+use crate::{
+    data::PoiseContext,
+    feature_flags::{is_enabled, Feature},
+    llm::LlmMessage,
+};
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::Attachment;
+
+/// Leaves room in the 2000 character message for the line in front of the draft.
+const MAX_DESCRIPTION_LENGTH: usize = 1900;
+const DESCRIBE_IMAGE_PROMPT: &str = "Write concise alt text (one or two sentences) for this image, \
+    for someone using a screen reader. Transcribe any important text. Reply with only the alt text.";
+
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    description_localized("en-US", "Drafts alt text for an image")
+)]
+pub async fn describe_image(
+    ctx: PoiseContext<'_>,
+    #[description = "The image to describe"] image: Attachment,
+) -> Result<()> {
+    let Some(llm) = ctx.data().config.read().await.llm.clone() else {
+        ctx.say("No LLM backend is configured.").await?;
+        return Ok(());
+    };
+
+    if !is_enabled(ctx.data(), Feature::Llm).await {
+        ctx.say("The LLM is switched off right now.").await?;
+        return Ok(());
+    }
+
+    if !image
+        .content_type
+        .as_ref()
+        .is_some_and(|content_type| content_type.starts_with("image"))
+    {
+        ctx.say("That doesn't look like an image.").await?;
+        return Ok(());
+    }
+
+    ctx.defer_ephemeral().await?;
+
+    let description = llm
+        .chat(&[LlmMessage::user_with_image(
+            DESCRIBE_IMAGE_PROMPT,
+            &image.url,
+        )])
+        .await?;
+
+    let description = if description.chars().count() > MAX_DESCRIPTION_LENGTH {
+        format!(
+            "{}...",
+            description
+                .chars()
+                .take(MAX_DESCRIPTION_LENGTH - 3)
+                .collect::<String>()
+        )
+    } else {
+        description
+    };
+
+    ctx.say(format!(
+        "Here's a draft, check it before using it:\n>>> {}",
+        description
+    ))
+    .await?;
+
+    Ok(())
+}
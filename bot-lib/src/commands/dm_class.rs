@@ -0,0 +1,288 @@
+use crate::{
+    commands::get_role, data::PoiseContext, db::KingFisherDb, mod_log::mod_log,
+    progress::ProgressReporter,
+};
+use color_eyre::eyre::{OptionExt, Result};
+use futures::StreamExt;
+use poise::serenity_prelude::{self as serenity, Mentionable, UserId};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const CLASS_DMS_TREE: &str = "class_dms";
+const DM_OPT_OUTS_TREE: &str = "dm_opt_outs";
+/// Time between DMs, so a big class doesn't get us rate limited (or flagged as spam).
+const DM_INTERVAL: Duration = Duration::from_millis(1500);
+/// How many times in a row reading the member list can fail before giving up on the rest.
+const MAX_LOOKUP_FAILURES: usize = 3;
+/// Failed recipients named in the report, the rest are only counted. All of them are kept.
+const MAX_FAILED_MENTIONS: usize = 20;
+
+/// A `/dm_class` announcement and who it reached.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClassDm {
+    pub class: u32,
+    pub content: String,
+    pub sent_by: u64,
+    pub timestamp: i64,
+    pub delivered: Vec<u64>,
+    /// DMs closed, or blocked the bot.
+    pub failed: Vec<u64>,
+    pub opted_out: Vec<u64>,
+    /// The member list couldn't be read to the end, so some of the class may have been missed.
+    #[serde(default)]
+    pub incomplete: bool,
+}
+
+impl ClassDm {
+    fn report(&self, finished: bool) -> String {
+        let mut report = format!(
+            "{} CS {} announcement: {} delivered, {} failed, {} opted out.",
+            if finished { "Sent the" } else { "Sending the" },
+            self.class,
+            self.delivered.len(),
+            self.failed.len(),
+            self.opted_out.len()
+        );
+
+        if self.incomplete {
+            report.push_str(" Couldn't read the whole member list, so some may have been missed.");
+        }
+
+        if finished && !self.failed.is_empty() {
+            let failed = self
+                .failed
+                .iter()
+                .take(MAX_FAILED_MENTIONS)
+                .map(|user_id| UserId::new(*user_id).mention().to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            report.push_str(&format!("\nCouldn't reach: {}", failed));
+
+            if self.failed.len() > MAX_FAILED_MENTIONS {
+                report.push_str(&format!(
+                    " and {} more",
+                    self.failed.len() - MAX_FAILED_MENTIONS
+                ));
+            }
+        }
+
+        report
+    }
+}
+
+fn is_opted_out(db: &KingFisherDb, user_id: UserId) -> Result<bool> {
+    Ok(db
+        .get::<bool>(DM_OPT_OUTS_TREE, user_id.get().to_be_bytes())?
+        .unwrap_or(false))
+}
+
+fn set_opted_out(db: &KingFisherDb, user_id: UserId, opted_out: bool) -> Result<()> {
+    if opted_out {
+        db.insert(DM_OPT_OUTS_TREE, user_id.get().to_be_bytes(), &true)
+    } else {
+        db.remove::<bool>(DM_OPT_OUTS_TREE, user_id.get().to_be_bytes())
+            .map(|_| ())
+    }
+}
+
+/// DM everyone in a class, for urgent things like room changes
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_MESSAGES"
+)]
+pub async fn dm_class(
+    ctx: PoiseContext<'_>,
+    #[description = "The class number, eg. for CS2420 put in \"2420\""] number: u32,
+    #[description = "What to tell them"]
+    #[max_length = 1800]
+    message: String,
+) -> Result<()> {
+    let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+    let role_id = get_role(ctx, number).await?;
+    let guild_name = ctx
+        .guild()
+        .map_or_else(|| "the server".to_owned(), |guild| guild.name.clone());
+
+    let db = &ctx.data().db;
+    let dm_id = db.generate_id()?;
+    let mut class_dm = ClassDm {
+        class: number,
+        content: message.clone(),
+        sent_by: ctx.author().id.get(),
+        timestamp: chrono::Utc::now().timestamp(),
+        ..Default::default()
+    };
+
+    let member_count = ctx.guild().map_or(0, |guild| guild.member_count as usize);
+    let mut progress = ProgressReporter::start(ctx, class_dm.report(false), member_count).await?;
+
+    let dm = serenity::CreateMessage::new()
+        .embed(
+            serenity::CreateEmbed::new()
+                .title(format!("CS {} announcement from {}", number, guild_name))
+                .description(&message)
+                .footer(serenity::CreateEmbedFooter::new(
+                    "Use /dm_opt_out in the server to stop getting these",
+                )),
+        )
+        .allowed_mentions(serenity::CreateAllowedMentions::new());
+
+    let mut members = guild_id.members_iter(ctx).boxed();
+    let mut lookup_failures = 0;
+
+    while let Some(member) = members.next().await {
+        progress.set_label(class_dm.report(false));
+        progress.advance().await;
+
+        // A failed page of members gets tried again, so give up if it keeps failing
+        let member = match member {
+            Ok(member) => {
+                lookup_failures = 0;
+                member
+            }
+            Err(e) => {
+                tracing::warn!("Couldn't read members for the class DM: {:?}", e);
+                lookup_failures += 1;
+
+                if lookup_failures >= MAX_LOOKUP_FAILURES {
+                    class_dm.incomplete = true;
+                    break;
+                }
+
+                tokio::time::sleep(DM_INTERVAL).await;
+                continue;
+            }
+        };
+
+        if member.user.bot || !member.roles.contains(&role_id) {
+            continue;
+        }
+
+        let user_id = member.user.id;
+
+        if is_opted_out(db, user_id)? {
+            class_dm.opted_out.push(user_id.get());
+            continue;
+        }
+
+        match member.user.direct_message(ctx, dm.clone()).await {
+            Ok(_) => class_dm.delivered.push(user_id.get()),
+            Err(e) => {
+                tracing::debug!("Couldn't DM {}: {:?}", user_id, e);
+                class_dm.failed.push(user_id.get());
+            }
+        }
+
+        // Saved after every DM, so if this stops partway it's clear who already got it
+        if let Err(e) = db.insert(CLASS_DMS_TREE, dm_id.to_be_bytes(), &class_dm) {
+            tracing::warn!("Couldn't save class DM progress: {:?}", e);
+        }
+
+        tokio::time::sleep(DM_INTERVAL).await;
+    }
+
+    db.insert(CLASS_DMS_TREE, dm_id.to_be_bytes(), &class_dm)?;
+
+    progress.finish(class_dm.report(true)).await;
+
+    mod_log(
+        ctx.serenity_context(),
+        ctx.data(),
+        serenity::CreateEmbed::new()
+            .title(format!("Class DM: CS {}", number))
+            .description(format!(
+                "{} sent\n>>> {}",
+                ctx.author().mention(),
+                class_dm.content
+            ))
+            .field("Delivered", class_dm.delivered.len().to_string(), true)
+            .field("Failed", class_dm.failed.len().to_string(), true)
+            .field("Opted out", class_dm.opted_out.len().to_string(), true)
+            .field(
+                "Finished",
+                if class_dm.incomplete { "No" } else { "Yes" },
+                true,
+            ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Stop (or start again) getting class announcement DMs
+#[poise::command(slash_command, ephemeral = true)]
+pub async fn dm_opt_out(
+    ctx: PoiseContext<'_>,
+    #[description = "Whether to stop getting class announcement DMs, defaults to true"]
+    opt_out: Option<bool>,
+) -> Result<()> {
+    let opt_out = opt_out.unwrap_or(true);
+
+    set_opted_out(&ctx.data().db, ctx.author().id, opt_out)?;
+
+    ctx.say(if opt_out {
+        "You won't get class announcement DMs anymore."
+    } else {
+        "You'll get class announcement DMs again."
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn opt_outs_round_trip() {
+        let db = KingFisherDb::temporary().unwrap();
+        let user_id = UserId::new(42);
+
+        assert!(!is_opted_out(&db, user_id).unwrap());
+
+        set_opted_out(&db, user_id, true).unwrap();
+        assert!(is_opted_out(&db, user_id).unwrap());
+
+        set_opted_out(&db, user_id, false).unwrap();
+        assert!(!is_opted_out(&db, user_id).unwrap());
+    }
+
+    #[test]
+    fn reports_delivery() {
+        let class_dm = ClassDm {
+            class: 2420,
+            delivered: vec![1, 2],
+            failed: vec![3],
+            opted_out: vec![4],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            class_dm.report(false),
+            "Sending the CS 2420 announcement: 2 delivered, 1 failed, 1 opted out."
+        );
+        assert_eq!(
+            class_dm.report(true),
+            "Sent the CS 2420 announcement: 2 delivered, 1 failed, 1 opted out.\nCouldn't reach: <@3>"
+        );
+
+        let many_failed = ClassDm {
+            failed: (1..=100).collect(),
+            ..class_dm.clone()
+        };
+        let report = many_failed.report(true);
+        assert!(report.ends_with("<@20> and 80 more"));
+        assert!(report.chars().count() < 2000);
+
+        let class_dm = ClassDm {
+            incomplete: true,
+            ..class_dm
+        };
+        assert!(class_dm
+            .report(false)
+            .ends_with("some may have been missed."));
+    }
+}
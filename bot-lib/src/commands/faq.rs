@@ -0,0 +1,103 @@
+use crate::{
+    data::PoiseContext,
+    faq::{add_faq, best_match, entry_titles, format_entry, get_faq, remove_faq},
+};
+use color_eyre::eyre::Result;
+use poise::CreateReply;
+
+async fn autocomplete_entry(ctx: PoiseContext<'_>, partial: &str) -> Vec<String> {
+    let entries = get_faq(&ctx.data().db).unwrap_or_default();
+
+    entry_titles(&entries)
+        .into_iter()
+        .filter(|title| title.to_lowercase().contains(&partial.to_lowercase()))
+        .take(25)
+        .collect()
+}
+
+#[poise::command(
+    slash_command,
+    subcommands("faq_ask", "faq_add", "faq_remove"),
+    subcommand_required,
+    description_localized("en-US", "Frequently asked questions")
+)]
+pub async fn faq(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Look something up in the FAQ
+#[poise::command(slash_command, rename = "ask")]
+pub async fn faq_ask(
+    ctx: PoiseContext<'_>,
+    #[description = "Your question"]
+    #[max_length = 200]
+    query: String,
+) -> Result<()> {
+    let entries = get_faq(&ctx.data().db)?;
+
+    match best_match(&entries, &query) {
+        Some((entry, _)) => ctx.say(format_entry(entry)).await?,
+        None => {
+            ctx.send(
+                CreateReply::default()
+                    .ephemeral(true)
+                    .content("Nothing in the FAQ about that, try asking in a help channel!"),
+            )
+            .await?
+        }
+    };
+
+    Ok(())
+}
+
+/// Add a question and its answer to the FAQ
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_MESSAGES",
+    rename = "add"
+)]
+pub async fn faq_add(
+    ctx: PoiseContext<'_>,
+    #[description = "The question, as people usually ask it"]
+    #[max_length = 200]
+    question: String,
+    #[description = "The answer"]
+    #[max_length = 1500]
+    answer: String,
+) -> Result<()> {
+    let id = add_faq(&ctx.data().db, question, answer, ctx.author().id.get())?;
+
+    ctx.say(format!("Added FAQ entry {}.", id)).await?;
+
+    Ok(())
+}
+
+/// Remove a question from the FAQ
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_MESSAGES",
+    rename = "remove"
+)]
+pub async fn faq_remove(
+    ctx: PoiseContext<'_>,
+    #[description = "The entry to remove"]
+    #[autocomplete = "autocomplete_entry"]
+    entry: String,
+) -> Result<()> {
+    let removed = entry
+        .split(':')
+        .next()
+        .and_then(|id| id.trim().parse::<u64>().ok())
+        .map(|id| remove_faq(&ctx.data().db, id))
+        .transpose()?
+        .flatten();
+
+    match removed {
+        Some(entry) => ctx.say(format!("Removed \"{}\".", entry.question)).await?,
+        None => ctx.say("No such FAQ entry.").await?,
+    };
+
+    Ok(())
+}
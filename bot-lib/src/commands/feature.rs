@@ -0,0 +1,86 @@
+use crate::{
+    data::PoiseContext,
+    feature_flags::{feature_enabled, get_override, set_override, Feature},
+};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::ChoiceParameter;
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "MANAGE_GUILD",
+    subcommands("feature_enable", "feature_disable", "feature_reset", "feature_list"),
+    subcommand_required,
+    description_localized("en-US", "Switch kingfisher's features on and off")
+)]
+pub async fn feature(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Turn a feature on, whatever the config says
+#[poise::command(slash_command, ephemeral = true, rename = "enable")]
+pub async fn feature_enable(ctx: PoiseContext<'_>, feature: Feature) -> Result<()> {
+    set_override(&ctx.data().db, feature, Some(true))?;
+    ctx.say(format!("{} is on.", feature.name())).await?;
+
+    Ok(())
+}
+
+/// Turn a feature off, whatever the config says
+#[poise::command(slash_command, ephemeral = true, rename = "disable")]
+pub async fn feature_disable(ctx: PoiseContext<'_>, feature: Feature) -> Result<()> {
+    set_override(&ctx.data().db, feature, Some(false))?;
+    ctx.say(format!("{} is off.", feature.name())).await?;
+
+    Ok(())
+}
+
+/// Go back to what the config says about a feature
+#[poise::command(slash_command, ephemeral = true, rename = "reset")]
+pub async fn feature_reset(ctx: PoiseContext<'_>, feature: Feature) -> Result<()> {
+    set_override(&ctx.data().db, feature, None)?;
+
+    let enabled = feature_enabled(&ctx.data().db, &*ctx.data().config.read().await, feature);
+    ctx.say(format!(
+        "{} follows the config again, so it's {}.",
+        feature.name(),
+        if enabled { "on" } else { "off" }
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// See which features are on
+#[poise::command(slash_command, ephemeral = true, rename = "list")]
+pub async fn feature_list(ctx: PoiseContext<'_>) -> Result<()> {
+    let db = &ctx.data().db;
+    let config = ctx.data().config.read().await;
+
+    let lines = Feature::ALL
+        .iter()
+        .map(|&feature| {
+            let source = match get_override(db, feature) {
+                Ok(Some(_)) => " (set with `/feature`)",
+                _ => "",
+            };
+
+            format!(
+                "{} **{}**{}",
+                if feature_enabled(db, &config, feature) {
+                    "🟢"
+                } else {
+                    "⚫"
+                },
+                feature.name(),
+                source
+            )
+        })
+        .join("\n");
+    drop(config);
+
+    ctx.say(lines).await?;
+
+    Ok(())
+}
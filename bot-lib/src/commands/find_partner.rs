@@ -0,0 +1,73 @@
+use crate::{
+    data::PoiseContext,
+    partners::{add_partner_request, get_partner_requests, remove_partner_request},
+};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity, Mentionable, UserId};
+
+/// Find someone to partner with on an assignment
+#[poise::command(slash_command, ephemeral = true)]
+pub async fn find_partner(
+    ctx: PoiseContext<'_>,
+    #[description = "The class number, eg. for CS2420 put in \"2420\""] class: u32,
+    #[description = "The assignment, like \"A3\""]
+    #[max_length = 50]
+    assignment: String,
+) -> Result<()> {
+    let db = &ctx.data().db;
+    let author = ctx.author();
+    let waiting = get_partner_requests(db, class, &assignment, author.id)?;
+
+    let Some(partner) = waiting.first() else {
+        add_partner_request(db, class, &assignment, author.id)?;
+
+        ctx.say(format!(
+            "Nobody else is looking for a CS {} {} partner yet. I'll DM you when someone is!",
+            class, assignment
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    let partner_id = UserId::new(partner.user_id);
+    let introduction = |other: &dyn Mentionable| {
+        serenity::CreateMessage::new().content(format!(
+            "You've been matched with {} for CS {} {}, say hi!",
+            other.mention(),
+            class,
+            partner.assignment
+        ))
+    };
+
+    let partner_user = partner_id.to_user(ctx).await?;
+
+    if let Err(e) = partner_user.direct_message(ctx, introduction(author)).await {
+        tracing::debug!("Couldn't DM partner match to {}: {:?}", partner_id, e);
+    }
+
+    if let Err(e) = author.direct_message(ctx, introduction(&partner_id)).await {
+        tracing::debug!("Couldn't DM partner match to {}: {:?}", author.id, e);
+    }
+
+    remove_partner_request(db, partner)?;
+
+    let mut reply = format!(
+        "You've been matched with {}, check your DMs!",
+        partner_id.mention()
+    );
+
+    if waiting.len() > 1 {
+        reply.push_str(&format!(
+            "\nAlso looking for a partner: {}",
+            waiting[1..]
+                .iter()
+                .map(|request| UserId::new(request.user_id).mention())
+                .join(", ")
+        ));
+    }
+
+    ctx.say(reply).await?;
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use crate::{
+    commands::{ensure_author_outranks_role, ensure_can_manage_role},
+    data::PoiseContext,
+    role_grants::{save_role_grant, MemberFilter, RoleGrant},
+};
+use chrono::{NaiveDate, Utc};
+use color_eyre::eyre::{OptionExt, Result};
+use poise::serenity_prelude::{self as serenity, Mentionable};
+
+/// Give a role to every member matching a filter, a batch a minute
+#[poise::command(slash_command, ephemeral = true, required_permissions = "MANAGE_ROLES")]
+pub async fn grant_role(
+    ctx: PoiseContext<'_>,
+    #[description = "The role to give out"] role: serenity::Role,
+    #[description = "Only members with this role"] has_role: Option<serenity::Role>,
+    #[description = "Only members who joined before this date, like 2024-08-19"]
+    joined_before: Option<String>,
+    #[description = "Only members with no roles at all"] no_roles: Option<bool>,
+    #[description = "Take the role away again after this many days"]
+    #[min = 1]
+    expire_after_days: Option<u32>,
+) -> Result<()> {
+    let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+
+    ensure_can_manage_role(ctx, Some(role.id)).await?;
+    ensure_author_outranks_role(ctx, role.id).await?;
+
+    let joined_before = match joined_before.as_deref() {
+        Some(date) => match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            Ok(date) => Some(date.and_time(chrono::NaiveTime::MIN).and_utc()),
+            Err(_) => {
+                ctx.say(format!(
+                    "Couldn't read `{}` as a date, use 2024-08-19.",
+                    date
+                ))
+                .await?;
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+
+    let filter = MemberFilter {
+        has_role: has_role.map(|role| role.id),
+        joined_before,
+        no_roles: no_roles.unwrap_or(false),
+    };
+
+    if filter.is_empty() {
+        ctx.say("Pick at least one filter, this would give the role to everyone.")
+            .await?;
+        return Ok(());
+    }
+
+    let expires_at = expire_after_days.map(|days| Utc::now() + chrono::Duration::days(days.into()));
+
+    let mut grant = RoleGrant {
+        id: ctx.data().db.generate_id()?,
+        guild_id: guild_id.get(),
+        role_id: role.id.get(),
+        role_name: role.name.clone(),
+        filter,
+        expires_at,
+        requested_by: ctx.author().id.get(),
+        channel_id: ctx.channel_id().get(),
+        message_id: 0,
+        dry_run: ctx.data().config.read().await.dry_run,
+        after: None,
+        checked: 0,
+        granted: vec![],
+        failed: vec![],
+        finished: false,
+    };
+
+    // Interaction replies can't be edited for long, so progress goes in a message of its own
+    let progress = ctx
+        .channel_id()
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .content(grant.report())
+                .allowed_mentions(serenity::CreateAllowedMentions::new()),
+        )
+        .await?;
+    grant.message_id = progress.id.get();
+    save_role_grant(&ctx.data().db, &grant)?;
+
+    ctx.say(format!(
+        "Queued, {} will go out a batch at a time. Progress: {}",
+        role.mention(),
+        progress.link()
+    ))
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use crate::{
+    charts::{render_chart, BarChart, Series},
+    data::PoiseContext,
+    member_growth::{
+        format_source, member_events, render_text_chart, top_sources, weekly_counts, Semester,
+    },
+};
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::serenity_prelude as serenity;
+
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_MESSAGES",
+    description_localized("en-US", "Shows joins, leaves and invite sources for a semester")
+)]
+pub async fn growth_report(
+    ctx: PoiseContext<'_>,
+    #[description = "e.g. \"fall 2024\", defaults to this semester"] semester: Option<String>,
+) -> Result<()> {
+    let today = Utc::now().date_naive();
+    let semester = match semester {
+        Some(semester) => match Semester::parse(&semester, today) {
+            Ok(semester) => semester,
+            Err(e) => {
+                ctx.say(e.to_string()).await?;
+                return Ok(());
+            }
+        },
+        None => Semester::containing(today),
+    };
+
+    let events = member_events(ctx.data())?;
+    let weeks = weekly_counts(&events, &semester);
+    let joins = weeks.iter().map(|week| week.joins).sum::<u64>();
+    let leaves = weeks.iter().map(|week| week.leaves).sum::<u64>();
+
+    if joins + leaves == 0 {
+        ctx.say(format!("Nobody joined or left in {}.", semester))
+            .await?;
+        return Ok(());
+    }
+
+    let sources = top_sources(&events, &semester);
+    let sources = if sources.is_empty() {
+        "None recorded".to_owned()
+    } else {
+        sources
+            .iter()
+            .map(|(code, inviter_id, count)| format_source(code, *inviter_id, *count))
+            .join("\n")
+    };
+
+    let chart = render_chart(BarChart {
+        series: vec![
+            Series {
+                color: [87, 242, 135],
+                values: weeks.iter().map(|week| week.joins).collect(),
+            },
+            Series {
+                color: [237, 66, 69],
+                values: weeks.iter().map(|week| week.leaves).collect(),
+            },
+        ],
+    })
+    .await;
+
+    // The text chart is the fallback when the image can't be made
+    let text_chart = match chart {
+        Some(_) => "Joins (green) and leaves (red) by week of the semester".to_owned(),
+        None => format!("```\n{}\n```", render_text_chart(&weeks)),
+    };
+
+    let mut reply = poise::CreateReply::default()
+        .content(format!(
+            "## Growth in {}\n{} joins, {} leaves, {:+} net\n{}\n**Top invites**\n{}",
+            semester,
+            joins,
+            leaves,
+            joins as i64 - leaves as i64,
+            text_chart,
+            sources
+        ))
+        .allowed_mentions(serenity::CreateAllowedMentions::new());
+
+    if let Some(png) = chart {
+        reply = reply.attachment(serenity::CreateAttachment::bytes(
+            png.as_slice(),
+            "growth.png",
+        ));
+    }
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use crate::{
+    data::PoiseContext,
+    helpers::{get_helpers, toggle_helper},
+};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+
+/// Sign up (or stop) to help people with a class
+#[poise::command(slash_command, ephemeral = true)]
+pub async fn volunteer(
+    ctx: PoiseContext<'_>,
+    #[description = "The class number, eg. for CS2420 put in \"2420\""] class: u32,
+) -> Result<()> {
+    let helping = toggle_helper(&ctx.data().db, class, ctx.author().id)?;
+
+    ctx.say(if helping {
+        format!(
+            "Thanks! You'll get pinged about unanswered CS {} questions. Run this again to stop.",
+            class
+        )
+    } else {
+        format!("You're no longer a CS {} helper.", class)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// See who volunteered to help with a class
+#[poise::command(slash_command, ephemeral = true)]
+pub async fn helpers(
+    ctx: PoiseContext<'_>,
+    #[description = "The class number, eg. for CS2420 put in \"2420\""] class: u32,
+) -> Result<()> {
+    let helpers = get_helpers(&ctx.data().db, class)?;
+
+    if helpers.is_empty() {
+        ctx.say(format!(
+            "Nobody has volunteered for CS {} yet, `/volunteer {}` to be the first!",
+            class, class
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    ctx.say(format!(
+        "CS {} helpers: {}",
+        class,
+        helpers
+            .iter()
+            .map(|helper| format!("<@{}>", helper.user_id))
+            .join(", ")
+    ))
+    .await?;
+
+    Ok(())
+}
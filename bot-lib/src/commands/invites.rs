@@ -0,0 +1,105 @@
+use crate::{
+    data::PoiseContext,
+    member_growth::{invite_labels, label_reports, member_events, save_invite_label, InviteLabel},
+};
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::serenity_prelude as serenity;
+
+/// Make a permanent invite for an outreach channel, to see how many people it brings in
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_MESSAGES"
+)]
+pub async fn create_invite(
+    ctx: PoiseContext<'_>,
+    #[description = "Where it's shared, e.g. \"flyer QR code\" or \"CS1030 syllabus\""]
+    #[max_length = 100]
+    label: String,
+    #[description = "The channel it leads to, defaults to this one"]
+    #[channel_types("Text")]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<()> {
+    let channel_id = channel.map_or(ctx.channel_id(), |channel| channel.id);
+
+    let invite = channel_id
+        .create_invite(
+            ctx,
+            serenity::CreateInvite::new()
+                .max_age(0)
+                .unique(true)
+                .audit_log_reason(&format!("Tracked invite: {}", label)),
+        )
+        .await?;
+
+    save_invite_label(
+        ctx.data(),
+        &InviteLabel {
+            code: invite.code.clone(),
+            label: label.clone(),
+            created_by: ctx.author().id.get(),
+            created_at: Utc::now().timestamp(),
+        },
+    )?;
+
+    ctx.say(format!(
+        "Made https://discord.gg/{} for \"{}\". See how it's doing with `/invites report`.",
+        invite.code, label
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    required_permissions = "MANAGE_MESSAGES",
+    subcommands("invites_report"),
+    subcommand_required,
+    description_localized("en-US", "Invites made with /create_invite")
+)]
+pub async fn invites(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// See which outreach channels bring people in
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    rename = "report",
+    required_permissions = "MANAGE_MESSAGES"
+)]
+pub async fn invites_report(ctx: PoiseContext<'_>) -> Result<()> {
+    let labels = invite_labels(ctx.data())?;
+
+    if labels.is_empty() {
+        ctx.say("No invites have been made with `/create_invite` yet.")
+            .await?;
+        return Ok(());
+    }
+
+    let events = member_events(ctx.data())?;
+    let reports = label_reports(&labels, &events, Utc::now().timestamp());
+
+    let lines = reports
+        .iter()
+        .map(|report| {
+            format!(
+                "**{}** (`{}`): {} joins, {} in the last 30 days, {} still here",
+                report.label.label,
+                report.label.code,
+                report.joins,
+                report.recent_joins,
+                report.stayed
+            )
+        })
+        .collect_vec();
+
+    let pages = lines.chunks(10).map(|chunk| chunk.join("\n")).collect_vec();
+
+    poise::builtins::paginate(ctx, &pages.iter().map(String::as_str).collect_vec()).await?;
+
+    Ok(())
+}
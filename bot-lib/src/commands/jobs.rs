@@ -0,0 +1,144 @@
+use crate::{
+    data::{PoiseApplicationContext, PoiseContext},
+    job_board::{save_posting, search_postings, JobPosting},
+};
+use chrono::{Duration, NaiveDate, Utc};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::{
+    serenity_prelude::{self as serenity, Mentionable},
+    Modal,
+};
+
+const POSTINGS_PER_PAGE: usize = 10;
+
+#[derive(Debug, Modal)]
+#[name = "Post a job"]
+struct JobModal {
+    #[name = "Company"]
+    #[max_length = 100]
+    company: String,
+    #[name = "Role"]
+    #[placeholder = "Software Engineering Intern"]
+    #[max_length = 100]
+    role: String,
+    #[name = "Application deadline"]
+    #[placeholder = "2024-10-01, leave empty if there isn't one"]
+    #[max_length = 10]
+    deadline: Option<String>,
+    #[name = "Link to apply"]
+    #[max_length = 500]
+    link: String,
+}
+
+/// Share an internship or job posting on the job board
+#[poise::command(slash_command, ephemeral = true)]
+pub async fn post_job(ctx: PoiseApplicationContext<'_>) -> Result<()> {
+    let Some(board) = ctx.data().config.read().await.job_board.clone() else {
+        ctx.say("The job board isn't set up.").await?;
+        return Ok(());
+    };
+
+    let Some(JobModal {
+        company,
+        role,
+        deadline,
+        link,
+    }) = JobModal::execute(ctx).await?
+    else {
+        return Ok(());
+    };
+
+    let today = Utc::now().date_naive();
+    let deadline = match deadline.as_deref().map(str::trim) {
+        Some(date) if !date.is_empty() => match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            Ok(date) if date >= today => date,
+            Ok(_) => {
+                ctx.say("That deadline already passed.").await?;
+                return Ok(());
+            }
+            Err(_) => {
+                ctx.say(format!(
+                    "Couldn't read `{}` as a date, use 2024-10-01.",
+                    date
+                ))
+                .await?;
+                return Ok(());
+            }
+        },
+        _ => today + Duration::days(i64::from(board.default_lifetime_days)),
+    };
+
+    if !link.starts_with("https://") && !link.starts_with("http://") {
+        ctx.say("The link has to start with https://.").await?;
+        return Ok(());
+    }
+
+    let db = &ctx.data().db;
+    let mut posting = JobPosting {
+        id: db.generate_id()?,
+        company,
+        role,
+        link,
+        deadline,
+        posted_by: ctx.author().id.get(),
+        message_id: 0,
+        archived: false,
+    };
+
+    let message = serenity::ChannelId::new(board.channel_id)
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .embed(posting.embed())
+                .allowed_mentions(serenity::CreateAllowedMentions::new()),
+        )
+        .await?;
+
+    posting.message_id = message.id.get();
+    save_posting(db, &posting)?;
+
+    ctx.say(format!(
+        "Posted in {}, thanks!",
+        serenity::ChannelId::new(board.channel_id).mention()
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    subcommands("jobs_search"),
+    subcommand_required,
+    description_localized("en-US", "Internships and jobs shared on the job board")
+)]
+pub async fn jobs(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Search job postings by company or role
+#[poise::command(slash_command, ephemeral = true, rename = "search")]
+pub async fn jobs_search(
+    ctx: PoiseContext<'_>,
+    #[description = "Something in the company or role, like \"intern\""]
+    #[max_length = 100]
+    keyword: String,
+) -> Result<()> {
+    let postings = search_postings(&ctx.data().db, &keyword)?;
+
+    if postings.is_empty() {
+        ctx.say(format!("No postings mention \"{}\".", keyword))
+            .await?;
+        return Ok(());
+    }
+
+    let pages = postings
+        .chunks(POSTINGS_PER_PAGE)
+        .map(|chunk| chunk.iter().map(JobPosting::summary).join("\n"))
+        .collect_vec();
+
+    poise::builtins::paginate(ctx, &pages.iter().map(String::as_str).collect_vec()).await?;
+
+    Ok(())
+}
@@ -115,17 +115,11 @@ pub async fn lynch(ctx: PoiseContext<'_>, victim: User) -> Result<()> {
     Ok(())
 }
 
-pub async fn update_interval() {
-    use futures::StreamExt;
-
-    // Every 1 hour, add a lynch opportunity up to the default, tokio interval
-    tokio_stream::wrappers::IntervalStream::new(tokio::time::interval(Duration::from_secs(3600)))
-        .for_each(|_| async {
-            let mut lynch_opportunities = LYNCH_OPPORTUNITIES.lock().await;
-            *lynch_opportunities = (*lynch_opportunities + 1).min(LYNCH_DEFAULT_OPPORTUNITIES);
-            tracing::info!("Updated lynch opportunities to {lynch_opportunities}");
-        })
-        .await
+/// Add a lynch opportunity up to the default, run hourly by the scheduler
+pub async fn refill_lynch_opportunities() {
+    let mut lynch_opportunities = LYNCH_OPPORTUNITIES.lock().await;
+    *lynch_opportunities = (*lynch_opportunities + 1).min(LYNCH_DEFAULT_OPPORTUNITIES);
+    tracing::info!("Updated lynch opportunities to {lynch_opportunities}");
 }
 
 // Handle a reaction
@@ -1,20 +1,68 @@
 pub mod add_bot_role;
+pub mod admin;
+pub mod announce;
+pub mod bookmarks;
+pub mod changelog;
+pub mod class_audit;
+pub mod class_categories;
+pub mod class_info;
 pub mod class_roles;
+pub mod clear_reactions;
+pub mod command_stats;
 pub mod course_catalog;
+pub mod course_reviews;
 pub mod create_class_category;
+pub mod custom_role;
+pub mod dehoist;
 pub mod delete_class_category;
+pub mod describe_image;
+pub mod dm_class;
+pub mod faq;
+pub mod feature;
+pub mod find_partner;
+pub mod grant_role;
+pub mod growth_report;
 pub mod help;
+pub mod helpers;
+pub mod invites;
+pub mod jobs;
 pub mod lynch;
+pub mod modmail;
+pub mod play;
+pub mod profile;
+pub mod quiet_hours;
+pub mod refresh_class_directory;
 pub mod register;
 pub mod remove_bot_role;
+pub mod rename_vote;
+pub mod report_message;
 pub mod reset_class_categories;
+pub mod resources;
+pub mod response;
+pub mod retheme_class_roles;
 pub mod sathya;
+pub mod schedule_message;
+pub mod season;
+pub mod set_status;
+pub mod setup_classes;
+pub mod shop;
+pub mod soundboard;
+pub mod starboard_rewind;
+pub mod sync_emojis;
+pub mod tempcheck;
+pub mod ticket;
 pub mod timeout;
+pub mod undo_last_deletion;
+pub mod voice_stats;
+pub mod when;
 
+use crate::class_notifications::is_notification_role;
 use crate::data::PoiseContext;
+use crate::discord_api::DiscordApi;
 use color_eyre::eyre::{OptionExt, Result};
 use color_eyre::Report;
-use poise::serenity_prelude::{GuildChannel, GuildId, Member, RoleId};
+use poise::serenity_prelude::{GuildChannel, GuildId, Member, Permissions, RoleId};
+use poise::CreateReply;
 use regex::Regex;
 
 /// Finds all channels in the given guild, where the name matches the given regex
@@ -36,13 +84,8 @@ pub async fn get_channels(
 
 pub async fn get_role(ctx: PoiseContext<'_>, number: u32) -> Result<RoleId> {
     let guild = ctx.guild().ok_or_eyre("Couldn't get guild")?.id;
-    let roles = guild.roles(ctx).await?;
 
-    let role_name = format!("CS {}", number);
-    let Some(role_id) = roles
-        .iter()
-        .find_map(|(role_id, role)| role.name.contains(&role_name).then_some(*role_id))
-    else {
+    let Some(role_id) = find_class_role(ctx.serenity_context(), guild, number).await? else {
         ctx.say("Couldn't find the class!").await?;
         return Err(Report::msg("Class role not found"));
     };
@@ -50,9 +93,167 @@ pub async fn get_role(ctx: PoiseContext<'_>, number: u32) -> Result<RoleId> {
     Ok(role_id)
 }
 
+/// The role of a class, like "CS 2420" for 2420.
+pub(crate) async fn find_class_role(
+    api: &impl DiscordApi,
+    guild: GuildId,
+    number: u32,
+) -> Result<Option<RoleId>> {
+    let role_name = format!("CS {}", number);
+
+    Ok(api.guild_roles(guild).await?.into_iter().find_map(|role| {
+        (role.name.contains(&role_name) && !is_notification_role(&role.name)).then_some(role.id)
+    }))
+}
+
 pub async fn get_author(ctx: PoiseContext<'_>) -> Result<Member> {
     let author = ctx.author();
     let guild = ctx.guild().ok_or_eyre("Couldn't get guild")?.id;
 
     Ok(guild.member(ctx, author.id).await?)
 }
+
+/// Makes sure the bot is actually allowed to create/edit/assign roles before we try to.
+///
+/// If `target_role` is given, the bot's highest role must also be above it.
+/// On failure the user gets an ephemeral explanation of how to fix it.
+pub async fn ensure_can_manage_role(
+    ctx: PoiseContext<'_>,
+    target_role: Option<RoleId>,
+) -> Result<()> {
+    let guild = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+    let bot_id = ctx.framework().bot_id;
+    let bot_member = guild.member(ctx, bot_id).await?;
+    let roles = guild.roles(ctx).await?;
+
+    let bot_roles = roles
+        .values()
+        .filter(|role| role.id == guild.everyone_role() || bot_member.roles.contains(&role.id));
+
+    let bot_permissions = bot_roles
+        .clone()
+        .fold(Permissions::empty(), |acc, role| acc | role.permissions);
+    let bot_top_position = bot_roles.map(|role| role.position).max().unwrap_or(0);
+
+    let target = target_role
+        .and_then(|role_id| roles.get(&role_id))
+        .map(|role| (role.name.as_str(), role.position));
+
+    let Some(problem) = find_role_management_problem(bot_permissions, bot_top_position, target)
+    else {
+        return Ok(());
+    };
+
+    ctx.send(CreateReply::default().ephemeral(true).content(&problem))
+        .await?;
+
+    Err(Report::msg(problem))
+}
+
+/// Makes sure the author's highest role is above the role, so they can't hand out roles
+/// (like mod roles) that Discord wouldn't let them give themselves.
+///
+/// On failure the user gets an ephemeral explanation.
+pub async fn ensure_author_outranks_role(ctx: PoiseContext<'_>, target_role: RoleId) -> Result<()> {
+    let guild = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+    let owner_id = ctx.guild().map(|guild| guild.owner_id);
+
+    if owner_id == Some(ctx.author().id) {
+        return Ok(());
+    }
+
+    let author = get_author(ctx).await?;
+    let roles = guild.roles(ctx).await?;
+
+    let author_top_position = author
+        .roles
+        .iter()
+        .filter_map(|role_id| roles.get(role_id))
+        .map(|role| role.position)
+        .max()
+        .unwrap_or(0);
+
+    let Some(role) = roles.get(&target_role) else {
+        return Ok(());
+    };
+
+    if role.position < author_top_position {
+        return Ok(());
+    }
+
+    let problem = format!(
+        "You can't hand out \"{}\" because it isn't below your highest role.",
+        role.name
+    );
+
+    ctx.send(CreateReply::default().ephemeral(true).content(&problem))
+        .await?;
+
+    Err(Report::msg(problem))
+}
+
+fn find_role_management_problem(
+    bot_permissions: Permissions,
+    bot_top_position: u16,
+    target: Option<(&str, u16)>,
+) -> Option<String> {
+    if !bot_permissions.intersects(Permissions::MANAGE_ROLES | Permissions::ADMINISTRATOR) {
+        return Some(
+            "I don't have the Manage Roles permission. \
+             Ask an admin to grant it to my role in Server Settings > Roles."
+                .to_owned(),
+        );
+    }
+
+    match target {
+        Some((name, position)) if position >= bot_top_position => Some(format!(
+            "I can't manage the role \"{}\" because it is above my highest role. \
+             Ask an admin to drag my role above it in Server Settings > Roles.",
+            name
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::discord_api::MockDiscord;
+
+    #[tokio::test]
+    async fn finds_class_roles() {
+        let discord = MockDiscord::default();
+        discord.add_role("Moderator");
+        discord.add_role("CS 2420 Announcements");
+        let role_id = discord.add_role("CS 2420");
+
+        assert_eq!(
+            find_class_role(&discord, GuildId::new(1), 2420)
+                .await
+                .unwrap(),
+            Some(role_id)
+        );
+        assert_eq!(
+            find_class_role(&discord, GuildId::new(1), 3500)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn role_management_needs_permission() {
+        assert!(find_role_management_problem(Permissions::empty(), 10, None).is_some());
+        assert!(find_role_management_problem(Permissions::MANAGE_ROLES, 10, None).is_none());
+        assert!(find_role_management_problem(Permissions::ADMINISTRATOR, 10, None).is_none());
+    }
+
+    #[test]
+    fn role_management_needs_hierarchy() {
+        let permissions = Permissions::MANAGE_ROLES;
+
+        assert!(find_role_management_problem(permissions, 10, Some(("CS 2420", 5))).is_none());
+        assert!(find_role_management_problem(permissions, 10, Some(("CS 2420", 10))).is_some());
+        assert!(find_role_management_problem(permissions, 10, Some(("CS 2420", 11))).is_some());
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{
+    data::PoiseContext,
+    modmail::{close_thread, get_thread, send_reply, set_blocked},
+};
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{Mentionable, User};
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "MODERATE_MEMBERS",
+    subcommands("modmail_reply", "modmail_close", "modmail_block", "modmail_unblock"),
+    subcommand_required,
+    description_localized("en-US", "Answer DMs sent to kingfisher")
+)]
+pub async fn modmail(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Reply in this modmail thread, choosing whether your name is shown
+#[poise::command(slash_command, ephemeral = true, rename = "reply")]
+pub async fn modmail_reply(
+    ctx: PoiseContext<'_>,
+    #[description = "What to send"]
+    #[max_length = 1900]
+    message: String,
+    #[description = "Hide who sent it"] anonymous: Option<bool>,
+) -> Result<()> {
+    let Some(settings) = ctx.data().config.read().await.modmail.clone() else {
+        ctx.say("Modmail isn't set up.").await?;
+        return Ok(());
+    };
+
+    let Some(thread) = get_thread(&ctx.data().db, ctx.channel_id())?.filter(|thread| thread.open)
+    else {
+        ctx.say("This isn't an open modmail thread.").await?;
+        return Ok(());
+    };
+
+    send_reply(
+        ctx.serenity_context(),
+        &settings,
+        &thread,
+        ctx.author(),
+        &message,
+        anonymous.unwrap_or(settings.anonymous),
+    )
+    .await?;
+
+    // The command's own reply is ephemeral, so leave a copy for the rest of staff
+    ctx.channel_id()
+        .say(ctx, format!("{} replied: {}", ctx.author().name, message))
+        .await?;
+    ctx.say("Sent.").await?;
+
+    Ok(())
+}
+
+/// Close this modmail thread
+#[poise::command(slash_command, ephemeral = true, rename = "close")]
+pub async fn modmail_close(ctx: PoiseContext<'_>) -> Result<()> {
+    let Some(thread) = get_thread(&ctx.data().db, ctx.channel_id())?.filter(|thread| thread.open)
+    else {
+        ctx.say("This isn't an open modmail thread.").await?;
+        return Ok(());
+    };
+
+    ctx.say("Closed.").await?;
+    close_thread(ctx.serenity_context(), ctx.data(), thread).await?;
+
+    Ok(())
+}
+
+/// Ignore someone's DMs from now on
+#[poise::command(slash_command, ephemeral = true, rename = "block")]
+pub async fn modmail_block(ctx: PoiseContext<'_>, user: User) -> Result<()> {
+    set_blocked(&ctx.data().db, user.id, true)?;
+
+    ctx.say(format!("DMs from {} are ignored now.", user.mention()))
+        .await?;
+
+    Ok(())
+}
+
+/// Start passing on someone's DMs again
+#[poise::command(slash_command, ephemeral = true, rename = "unblock")]
+pub async fn modmail_unblock(ctx: PoiseContext<'_>, user: User) -> Result<()> {
+    set_blocked(&ctx.data().db, user.id, false)?;
+
+    ctx.say(format!("DMs from {} get through again.", user.mention()))
+        .await?;
+
+    Ok(())
+}
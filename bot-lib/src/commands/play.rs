@@ -0,0 +1,90 @@
+use crate::{
+    booster_perks::skips_soundboard_cooldown,
+    data::PoiseContext,
+    economy::{take_token, Token},
+    voice::{list_sounds, play_sound, record_play, PlayResult},
+};
+use color_eyre::eyre::{OptionExt, Result};
+
+async fn autocomplete_sound(ctx: PoiseContext<'_>, partial: &str) -> Vec<String> {
+    let Some(soundboard) = ctx.data().config.read().await.soundboard.clone() else {
+        return vec![];
+    };
+
+    list_sounds(&soundboard.dir)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| name.to_lowercase().contains(&partial.to_lowercase()))
+        .take(25)
+        .collect()
+}
+
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    description_localized("en-US", "Plays a sound in your voice channel")
+)]
+pub async fn play(
+    ctx: PoiseContext<'_>,
+    #[description = "The sound to play"]
+    #[autocomplete = "autocomplete_sound"]
+    sound: String,
+) -> Result<()> {
+    let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+
+    let Some(soundboard) = ctx.data().config.read().await.soundboard.clone() else {
+        ctx.say("No soundboard is configured.").await?;
+        return Ok(());
+    };
+
+    let Some((_, path)) = list_sounds(&soundboard.dir)?
+        .into_iter()
+        .find(|(name, _)| *name == sound)
+    else {
+        ctx.say(format!("No sound named `{}`.", sound)).await?;
+        return Ok(());
+    };
+
+    let mut result = play_sound(
+        ctx.serenity_context(),
+        ctx.data(),
+        guild_id,
+        ctx.author().id,
+        &path,
+        skips_soundboard_cooldown(ctx.data(), ctx.author().id).await?,
+    )
+    .await?;
+
+    // Soundboard credits from the shop skip the cooldown
+    if let PlayResult::RateLimited(_) = result {
+        if take_token(&ctx.data().db, ctx.author().id, Token::SoundboardCredit)? {
+            result = play_sound(
+                ctx.serenity_context(),
+                ctx.data(),
+                guild_id,
+                ctx.author().id,
+                &path,
+                true,
+            )
+            .await?;
+        }
+    }
+
+    match result {
+        PlayResult::Playing => {
+            record_play(ctx.data(), &sound)?;
+            ctx.say(format!("Playing `{}`!", sound)).await?
+        }
+        PlayResult::NotInVoice => ctx.say("Join a voice channel first.").await?,
+        PlayResult::RateLimited(remaining) => {
+            ctx.say(format!(
+                "Give it a rest, try again in {} seconds.",
+                remaining.as_secs() + 1
+            ))
+            .await?
+        }
+    };
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use crate::{
+    commands::get_author,
+    data::PoiseContext,
+    helpers::get_helped_classes,
+    profile::{get_profile, parse_timezone, save_profile, sync_pronoun_roles},
+};
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use poise::{
+    serenity_prelude::{self as serenity, User},
+    ChoiceParameter, CreateReply,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ChoiceParameter)]
+pub enum ProfileField {
+    Pronouns,
+    Timezone,
+    Year,
+    Major,
+}
+
+#[poise::command(
+    slash_command,
+    subcommands("profile_set", "profile_view"),
+    subcommand_required,
+    description_localized("en-US", "Your pronouns, timezone, year and major")
+)]
+pub async fn profile(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Set (or clear) part of your profile
+#[poise::command(slash_command, ephemeral = true, rename = "set")]
+pub async fn profile_set(
+    ctx: PoiseContext<'_>,
+    #[description = "What to set"] field: ProfileField,
+    #[description = "e.g. they/them, UTC-7, Sophomore or Computer Science. Leave empty to clear"]
+    #[max_length = 100]
+    value: Option<String>,
+) -> Result<()> {
+    let db = &ctx.data().db;
+    let user_id = ctx.author().id;
+    let mut profile = get_profile(db, user_id)?;
+
+    match field {
+        ProfileField::Pronouns => profile.pronouns = value.clone(),
+        ProfileField::Timezone => {
+            profile.timezone = match value.as_deref().map(parse_timezone).transpose() {
+                Ok(timezone) => timezone.map(|timezone| timezone.local_minus_utc()),
+                Err(e) => {
+                    ctx.say(e.to_string()).await?;
+                    return Ok(());
+                }
+            }
+        }
+        ProfileField::Year => profile.year = value.clone(),
+        ProfileField::Major => profile.major = value.clone(),
+    }
+
+    save_profile(db, user_id, &profile)?;
+
+    if field == ProfileField::Pronouns {
+        let pronoun_roles = ctx.data().config.read().await.pronoun_roles.clone();
+
+        if !pronoun_roles.is_empty() {
+            let member = get_author(ctx).await?;
+
+            sync_pronoun_roles(
+                ctx.serenity_context(),
+                &member,
+                profile.pronouns.as_deref(),
+                &pronoun_roles,
+            )
+            .await?;
+        }
+    }
+
+    ctx.say(match value {
+        Some(_) => format!("Updated your {}.", field.name().to_lowercase()),
+        None => format!("Cleared your {}.", field.name().to_lowercase()),
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// See someone's profile
+#[poise::command(slash_command, ephemeral = true, rename = "view")]
+pub async fn profile_view(
+    ctx: PoiseContext<'_>,
+    #[description = "Whose profile, defaults to yours"] user: Option<User>,
+) -> Result<()> {
+    let user = user.as_ref().unwrap_or(ctx.author());
+    let profile = get_profile(&ctx.data().db, user.id)?;
+
+    let timezone = profile.timezone().map(|timezone| {
+        format!(
+            "UTC{} (it's {} there)",
+            timezone,
+            Utc::now().with_timezone(&timezone).format("%-I:%M %p")
+        )
+    });
+
+    let helping = get_helped_classes(&ctx.data().db, user.id)?;
+    let helping = (!helping.is_empty()).then(|| {
+        helping
+            .iter()
+            .map(|class| format!("CS {}", class))
+            .collect::<Vec<_>>()
+            .join(", ")
+    });
+
+    let fields = [
+        ("Pronouns", profile.pronouns),
+        ("Timezone", timezone),
+        ("Year", profile.year),
+        ("Major", profile.major),
+        ("Helps with", helping),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some((name, value?, true)))
+    .collect::<Vec<_>>();
+
+    if fields.is_empty() {
+        ctx.say(format!("{} hasn't set up a profile.", user.name))
+            .await?;
+        return Ok(());
+    }
+
+    ctx.send(
+        CreateReply::default().embed(
+            serenity::CreateEmbed::new()
+                .author(serenity::CreateEmbedAuthor::new(&user.name).icon_url(user.face()))
+                .fields(fields),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
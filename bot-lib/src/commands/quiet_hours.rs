@@ -0,0 +1,72 @@
+use crate::{
+    data::PoiseContext,
+    quiet_hours::{is_quiet, QuietOverride, QUIET_HOURS_OVERRIDES},
+    utils::GetRelativeTimestamp,
+};
+use chrono::{Duration, Utc};
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{GuildChannel, Mentionable};
+
+#[poise::command(
+    slash_command,
+    required_permissions = "MANAGE_MESSAGES",
+    subcommands("quiet_hours_override", "quiet_hours_status"),
+    subcommand_required,
+    description_localized("en-US", "Manage when kingfisher stays quiet")
+)]
+pub async fn quiet_hours(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Make a channel (or category) quiet or not for a while, regardless of its quiet hours
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_MESSAGES",
+    rename = "override"
+)]
+pub async fn quiet_hours_override(
+    ctx: PoiseContext<'_>,
+    #[description = "Whether kingfisher should be quiet"] quiet: bool,
+    #[description = "For how many minutes, defaults to 60"]
+    #[min = 1]
+    minutes: Option<i64>,
+    #[description = "The channel or category, defaults to this channel"] channel: Option<
+        GuildChannel,
+    >,
+) -> Result<()> {
+    let channel_id = channel.map_or(ctx.channel_id(), |channel| channel.id);
+    let until = Utc::now() + Duration::minutes(minutes.unwrap_or(60));
+
+    QUIET_HOURS_OVERRIDES.insert(channel_id.get(), QuietOverride { quiet, until });
+
+    ctx.say(format!(
+        "Kingfisher will {} in {} until {}, then go back to its usual quiet hours.",
+        if quiet { "stay quiet" } else { "respond" },
+        channel_id.mention(),
+        until.discord_relative_timestamp()
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Check whether kingfisher is quiet in this channel right now
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_MESSAGES",
+    rename = "status"
+)]
+pub async fn quiet_hours_status(ctx: PoiseContext<'_>) -> Result<()> {
+    let quiet = is_quiet(ctx.serenity_context(), ctx.data(), ctx.channel_id()).await;
+
+    ctx.say(if quiet {
+        "Kingfisher is quiet here right now."
+    } else {
+        "Kingfisher is responding here right now."
+    })
+    .await?;
+
+    Ok(())
+}
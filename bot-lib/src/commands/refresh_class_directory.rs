@@ -0,0 +1,25 @@
+use crate::{class_directory::refresh_class_directory as refresh, data::PoiseContext};
+use color_eyre::eyre::{OptionExt, Result};
+
+/// Rebuild the class directory messages
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_CHANNELS"
+)]
+pub async fn refresh_class_directory(ctx: PoiseContext<'_>) -> Result<()> {
+    let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+
+    if ctx.data().config.read().await.class_directory.is_none() {
+        ctx.say("No class directory channel is configured.").await?;
+        return Ok(());
+    }
+
+    ctx.defer_ephemeral().await?;
+
+    refresh(ctx.serenity_context(), ctx.data(), guild_id).await?;
+
+    ctx.say("Refreshed the class directory!").await?;
+
+    Ok(())
+}
@@ -16,26 +16,9 @@ pub async fn remove_bot_role(ctx: PoiseContext<'_>) -> Result<()> {
         .await
         .wrap_err("Couldn't remove role")?;
 
-    {
-        let members = &mut ctx
-            .framework()
-            .user_data
-            .config
-            .write()
-            .await
-            .bot_react_role_members;
-
-        let author_id = author.id.into();
-
-        members.retain(
-            |member| matches!(member, crate::config::ReactRole { user_id, .. } if user_id != &author_id),
-        );
-
-        members.push(crate::config::ReactRole {
-            user_id: author_id,
-            react: false,
-        });
-    }
+    ctx.data()
+        .react_roles
+        .set(&ctx.data().db, author.id, false)?;
 
     ctx.say("Removed role!").await?;
 
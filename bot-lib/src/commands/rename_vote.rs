@@ -0,0 +1,123 @@
+use crate::{
+    data::PoiseContext,
+    rename_votes::{
+        has_open_vote, normalize_channel_name, save_rename_vote, RenameVote, VOTE_NO, VOTE_YES,
+    },
+    utils::{member_permissions_in, GetRelativeTimestamp},
+};
+use chrono::Utc;
+use color_eyre::eyre::{OptionExt, Result};
+use poise::serenity_prelude::{self as serenity, CreateMessage, Mentionable};
+
+/// Put a channel's new name to a vote, which the scheduler closes and applies
+#[poise::command(
+    slash_command,
+    guild_only,
+    ephemeral = true,
+    description_localized("en-US", "Vote on renaming a channel")
+)]
+pub async fn rename_vote(
+    ctx: PoiseContext<'_>,
+    #[description = "The channel to rename"]
+    #[channel_types("Text", "News", "Voice", "Forum")]
+    channel: serenity::GuildChannel,
+    #[description = "What to call it"]
+    #[max_length = 100]
+    proposed_name: String,
+) -> Result<()> {
+    let Some(rules) = ctx.data().config.read().await.rename_votes.clone() else {
+        ctx.say("Rename votes aren't set up.").await?;
+        return Ok(());
+    };
+
+    // Slash command channel pickers list channels the invoker can't see
+    let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+    let member = ctx
+        .author_member()
+        .await
+        .ok_or_eyre("Couldn't get member")?
+        .into_owned();
+    let can_view = member_permissions_in(ctx.serenity_context(), guild_id, channel.id, &member)
+        .await
+        .is_ok_and(|permissions| permissions.view_channel());
+
+    if !can_view {
+        ctx.say("You can't see that channel.").await?;
+        return Ok(());
+    }
+
+    if rules.protected_channel_ids.contains(&channel.id.get()) {
+        ctx.say(format!(
+            "{} can't be renamed by vote.",
+            channel.id.mention()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let proposed_name = match channel.kind {
+        serenity::ChannelType::Text | serenity::ChannelType::News => {
+            normalize_channel_name(&proposed_name)
+        }
+        _ => proposed_name.trim().to_owned(),
+    };
+
+    if proposed_name.is_empty() || proposed_name == channel.name {
+        ctx.say("That's not a new name.").await?;
+        return Ok(());
+    }
+
+    let db = &ctx.data().db;
+    if has_open_vote(db, channel.id.get(), Utc::now())? {
+        ctx.say(format!(
+            "{} already has a rename vote going.",
+            channel.id.mention()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let closes_at = Utc::now() + chrono::Duration::minutes(rules.minutes);
+    let question = format!(
+        "**Rename vote:** {} from `{}` to `{}`?",
+        channel.id.mention(),
+        channel.name,
+        proposed_name
+    );
+
+    let message = ctx
+        .channel_id()
+        .send_message(
+            ctx,
+            CreateMessage::new()
+                .content(format!(
+                    "{}\nNeeds {} votes, closes {}",
+                    question,
+                    rules.quorum,
+                    closes_at.discord_relative_timestamp()
+                ))
+                .reactions([VOTE_YES, VOTE_NO])
+                .allowed_mentions(serenity::CreateAllowedMentions::new()),
+        )
+        .await?;
+
+    save_rename_vote(
+        db,
+        &RenameVote {
+            channel_id: channel.id.get(),
+            message_channel_id: message.channel_id.get(),
+            message_id: message.id.get(),
+            old_name: channel.name.clone(),
+            proposed_name,
+            proposed_by: ctx.author().id.get(),
+            closes_at,
+            yes: 0,
+            no: 0,
+            outcome: None,
+        },
+    )?;
+
+    ctx.say("Vote started!").await?;
+
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use crate::data::{AppState, PoiseApplicationContext, PoiseContext};
+use color_eyre::eyre::{OptionExt, Result};
+use poise::{
+    serenity_prelude::{self as serenity, Mentionable, User},
+    CreateReply, Modal,
+};
+use serde::{Deserialize, Serialize};
+
+const REPORTS_TREE: &str = "reports";
+const REPORT_BUTTON_PREFIX: &str = "report:";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReportStatus {
+    Open,
+    Claimed { by: u64 },
+    Resolved { by: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report {
+    pub reporter_id: u64,
+    pub author_id: u64,
+    pub author_name: String,
+    /// Snapshot of the message, in case it gets deleted
+    pub content: String,
+    pub message_link: String,
+    pub reason: String,
+    pub status: ReportStatus,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Modal)]
+#[name = "Report message"]
+struct ReportModal {
+    #[name = "Why are you reporting this message?"]
+    #[paragraph]
+    #[max_length = 1000]
+    reason: String,
+}
+
+fn report_buttons(report_id: u64, status: &ReportStatus) -> Vec<serenity::CreateActionRow> {
+    if let ReportStatus::Resolved { .. } = status {
+        return vec![];
+    }
+
+    vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new(format!("{}claim:{}", REPORT_BUTTON_PREFIX, report_id))
+            .label("Claim")
+            .style(serenity::ButtonStyle::Primary)
+            .disabled(matches!(status, ReportStatus::Claimed { .. })),
+        serenity::CreateButton::new(format!("{}resolve:{}", REPORT_BUTTON_PREFIX, report_id))
+            .label("Resolve")
+            .style(serenity::ButtonStyle::Success),
+    ])]
+}
+
+fn report_embed(report: &Report) -> serenity::CreateEmbed {
+    let status = match report.status {
+        ReportStatus::Open => "Open".to_owned(),
+        ReportStatus::Claimed { by } => format!("Claimed by <@{}>", by),
+        ReportStatus::Resolved { by } => format!("Resolved by <@{}>", by),
+    };
+
+    serenity::CreateEmbed::new()
+        .title("Message reported")
+        .author(serenity::CreateEmbedAuthor::new(&report.author_name))
+        .description(format!("{}\n{}", report.content, report.message_link))
+        .field("Reported by", format!("<@{}>", report.reporter_id), true)
+        .field("Reason", &report.reason, false)
+        .field("Status", status, false)
+}
+
+#[poise::command(context_menu_command = "Report message", ephemeral = true)]
+pub async fn report_message(
+    ctx: PoiseApplicationContext<'_>,
+    message: serenity::Message,
+) -> Result<()> {
+    let Some(report_channel_id) = ctx.data().config.read().await.report_channel_id else {
+        ctx.say("Reporting isn't set up, message a mod directly.")
+            .await?;
+        return Ok(());
+    };
+
+    let Some(ReportModal { reason }) = ReportModal::execute(ctx).await? else {
+        return Ok(());
+    };
+
+    let db = &ctx.data().db;
+    let report_id = db.generate_id()?;
+    let report = Report {
+        reporter_id: ctx.author().id.get(),
+        author_id: message.author.id.get(),
+        author_name: message.author.name.clone(),
+        content: message.content.clone(),
+        message_link: message.link(),
+        reason,
+        status: ReportStatus::Open,
+        timestamp: chrono::Utc::now().timestamp(),
+    };
+
+    serenity::ChannelId::new(report_channel_id)
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .embed(report_embed(&report))
+                .components(report_buttons(report_id, &report.status)),
+        )
+        .await?;
+
+    db.insert(REPORTS_TREE, report_id.to_be_bytes(), &report)?;
+
+    ctx.send(
+        CreateReply::default()
+            .ephemeral(true)
+            .content("Thanks, the mods have been notified."),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Handles the claim/resolve buttons on reports in the triage channel.
+pub async fn handle_report_button(
+    ctx: &serenity::Context,
+    data: &AppState,
+    interaction: &serenity::ComponentInteraction,
+) -> Result<()> {
+    let Some(action) = interaction
+        .data
+        .custom_id
+        .strip_prefix(REPORT_BUTTON_PREFIX)
+    else {
+        return Ok(());
+    };
+
+    let (action, report_id) = action
+        .split_once(':')
+        .ok_or_eyre("Malformed report button")?;
+    let report_id = report_id.parse::<u64>()?;
+
+    let mut report = data
+        .db
+        .get::<Report>(REPORTS_TREE, report_id.to_be_bytes())?
+        .ok_or_eyre("Unknown report")?;
+
+    let by = interaction.user.id.get();
+    report.status = match action {
+        "claim" => ReportStatus::Claimed { by },
+        "resolve" => ReportStatus::Resolved { by },
+        _ => return Ok(()),
+    };
+
+    data.db
+        .insert(REPORTS_TREE, report_id.to_be_bytes(), &report)?;
+
+    interaction
+        .create_response(
+            ctx,
+            serenity::CreateInteractionResponse::UpdateMessage(
+                serenity::CreateInteractionResponseMessage::new()
+                    .embed(report_embed(&report))
+                    .components(report_buttons(report_id, &report.status)),
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MODERATE_MEMBERS",
+    description_localized("en-US", "Shows how often a user reported or was reported")
+)]
+pub async fn report_stats(ctx: PoiseContext<'_>, user: User) -> Result<()> {
+    let reports = ctx.data().db.values::<Report>(REPORTS_TREE)?;
+    let user_id = user.id.get();
+
+    let filed = reports
+        .iter()
+        .filter(|report| report.reporter_id == user_id)
+        .count();
+    let received = reports
+        .iter()
+        .filter(|report| report.author_id == user_id)
+        .count();
+    let handled = reports
+        .iter()
+        .filter(|report| report.author_id == user_id && report.status != ReportStatus::Open)
+        .count();
+
+    ctx.say(format!(
+        "{} has filed {} reports and been reported {} times ({} handled).",
+        user.mention(),
+        filed,
+        received,
+        handled
+    ))
+    .await?;
+
+    Ok(())
+}
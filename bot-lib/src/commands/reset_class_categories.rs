@@ -1,11 +1,17 @@
-use crate::commands::{get_channels, get_role};
+use crate::commands::{ensure_can_manage_role, get_channels, get_role};
 use crate::data::PoiseContext;
+use crate::progress::ProgressReporter;
+use crate::two_person::require_second_mod;
 use color_eyre::eyre::{OptionExt, Result, WrapErr};
 use poise::serenity_prelude::{self as serenity};
 use regex::Regex;
-use serenity::ChannelType;
+use serenity::{ChannelType, Mentionable};
 
-pub async fn reset_class_category_backend(ctx: PoiseContext<'_>, number: u32) -> Result<()> {
+pub async fn reset_class_category_backend(
+    ctx: PoiseContext<'_>,
+    number: u32,
+    progress: &mut ProgressReporter<'_>,
+) -> Result<()> {
     let guild = ctx.guild().ok_or_eyre("Couldn't get guild")?.id;
     let members = guild.members(ctx, None, None).await?;
 
@@ -16,11 +22,28 @@ pub async fn reset_class_category_backend(ctx: PoiseContext<'_>, number: u32) ->
         .ok_or_eyre("Could not find general channel!")?;
 
     let role_id = get_role(ctx, number).await?;
+    ensure_can_manage_role(ctx, Some(role_id)).await?;
 
     let category_id = general_channel
         .parent_id
         .ok_or_eyre("Couldn't get category ID!")?;
 
+    let members_with_role = members
+        .iter()
+        .filter(|member| member.roles.contains(&role_id))
+        .collect::<Vec<_>>();
+
+    if ctx.data().config.read().await.dry_run {
+        ctx.say(format!(
+            "Dry run: would recreate #{} and remove {} from {} members",
+            general_channel_name,
+            role_id.mention(),
+            members_with_role.len()
+        ))
+        .await?;
+        return Ok(());
+    }
+
     general_channel.delete(ctx).await?;
 
     guild
@@ -33,12 +56,11 @@ pub async fn reset_class_category_backend(ctx: PoiseContext<'_>, number: u32) ->
         .await
         .wrap_err("Couldn't create general channel")?;
 
-    let members_with_role = members
-        .iter()
-        .filter(|member| member.roles.contains(&role_id));
+    progress.add_total(members_with_role.len());
 
     for member in members_with_role {
         member.remove_role(ctx, role_id).await?;
+        progress.advance().await;
     }
 
     Ok(())
@@ -56,8 +78,22 @@ pub async fn reset_class_category(
     ctx: PoiseContext<'_>,
     #[description = "The class number, eg. for CS2420 put in \"2420\""] number: u32,
 ) -> Result<()> {
-    reset_class_category_backend(ctx, number).await?;
-    ctx.say("Success!").await?;
+    if !ctx.data().config.read().await.dry_run {
+        let description = format!(
+            "Reset the CS {} category, clearing its general channel and role",
+            number
+        );
+
+        if !require_second_mod(ctx, description).await? {
+            return Ok(());
+        }
+    }
+
+    let mut progress =
+        ProgressReporter::start(ctx, format!("Removing the CS {} role", number), 0).await?;
+
+    reset_class_category_backend(ctx, number, &mut progress).await?;
+    progress.finish("Success!").await;
     Ok(())
 }
 
@@ -68,23 +104,36 @@ pub async fn reset_class_category(
 )]
 pub async fn reset_class_categories(ctx: PoiseContext<'_>) -> Result<()> {
     let guild = ctx.guild().ok_or_eyre("Couldn't get guild")?.id;
-    let removed_categories = get_channels(ctx, guild, Regex::new(r"\d{4}-general").unwrap())
-        .await?
-        .into_iter()
-        .map(|channel| {
-            channel
-                .name
-                .get(0..4)
-                .unwrap_or("Intentional parse error")
-                .parse::<u32>()
-                .context("Parse error")
-        });
+    let general_channels = get_channels(ctx, guild, Regex::new(r"\d{4}-general").unwrap()).await?;
+
+    if !ctx.data().config.read().await.dry_run {
+        let description = format!(
+            "Reset all {} class categories, clearing their general channels and roles",
+            general_channels.len()
+        );
+
+        if !require_second_mod(ctx, description).await? {
+            return Ok(());
+        }
+    }
+
+    let removed_categories = general_channels.into_iter().map(|channel| {
+        channel
+            .name
+            .get(0..4)
+            .unwrap_or("Intentional parse error")
+            .parse::<u32>()
+            .context("Parse error")
+    });
+
+    let mut progress =
+        ProgressReporter::start(ctx, "Removing class roles from everyone", 0).await?;
 
     for category in removed_categories {
-        reset_class_category_backend(ctx, category?).await?;
+        reset_class_category_backend(ctx, category?, &mut progress).await?;
     }
 
-    ctx.say("Success!").await?;
+    progress.finish("Success!").await;
 
     Ok(())
 }
@@ -0,0 +1,78 @@
+use crate::{
+    attachment_archive::{format_archived_file, get_archived_files},
+    data::PoiseContext,
+};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::serenity_prelude as serenity;
+
+#[poise::command(
+    slash_command,
+    subcommands("resources_files", "resources_mental_health"),
+    subcommand_required,
+    description_localized("en-US", "Class files and support resources")
+)]
+pub async fn resources(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// List the archived files for a class
+#[poise::command(slash_command, ephemeral = true, rename = "files")]
+pub async fn resources_files(
+    ctx: PoiseContext<'_>,
+    #[description = "The class number, eg. for CS2420 put in \"2420\""] class: u32,
+) -> Result<()> {
+    let files = get_archived_files(&ctx.data().db, &class.to_string())?;
+
+    if files.is_empty() {
+        ctx.say(format!("No files archived for {} yet.", class))
+            .await?;
+        return Ok(());
+    }
+
+    let pages = files
+        .iter()
+        .rev()
+        .chunks(5)
+        .into_iter()
+        .map(|chunk| chunk.map(format_archived_file).join("\n\n"))
+        .collect_vec();
+
+    poise::builtins::paginate(ctx, &pages.iter().map(String::as_str).collect_vec()).await?;
+
+    Ok(())
+}
+
+/// Campus counseling and crisis resources
+#[poise::command(slash_command, rename = "mental_health")]
+pub async fn resources_mental_health(ctx: PoiseContext<'_>) -> Result<()> {
+    let Some(resources) = ctx
+        .data()
+        .config
+        .read()
+        .await
+        .mental_health
+        .as_ref()
+        .map(|mental_health| mental_health.resources.clone())
+    else {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content("No mental health resources are set up."),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    ctx.send(
+        poise::CreateReply::default().embed(
+            serenity::CreateEmbed::new()
+                .title("Mental health resources")
+                .description(resources)
+                .color(serenity::Color::DARK_GREEN),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
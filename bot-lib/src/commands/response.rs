@@ -0,0 +1,182 @@
+use crate::{
+    config::ResponseKind,
+    data::PoiseContext,
+    response_variants::{variant_results, winner},
+};
+use color_eyre::eyre::Result;
+
+async fn autocomplete_response(ctx: PoiseContext<'_>, partial: &str) -> Vec<String> {
+    ctx.data()
+        .config
+        .read()
+        .await
+        .all_responses()
+        .map(|response| response.name().to_owned())
+        .filter(|name| name.to_lowercase().contains(&partial.to_lowercase()))
+        .take(25)
+        .collect()
+}
+
+#[poise::command(
+    slash_command,
+    required_permissions = "MANAGE_MESSAGES",
+    subcommands("reset_cooldown", "trigger", "ab_results"),
+    subcommand_required,
+    description_localized("en-US", "Manage the text detection responses")
+)]
+pub async fn response(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Clear the cooldown of a response
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_MESSAGES"
+)]
+pub async fn reset_cooldown(
+    ctx: PoiseContext<'_>,
+    #[description = "The response's name"]
+    #[autocomplete = "autocomplete_response"]
+    name: String,
+) -> Result<()> {
+    let config = ctx.data().config.read().await;
+
+    let found = config
+        .all_responses()
+        .find(|response| response.name() == name)
+        .map(|response| response.reset_cooldown(&config))
+        .is_some();
+
+    drop(config);
+
+    if found {
+        ctx.say(format!("Reset the cooldown of `{}`.", name))
+            .await?;
+    } else {
+        ctx.say(format!("No response named `{}`.", name)).await?;
+    }
+
+    Ok(())
+}
+
+/// Fire a response right now, ignoring its ruleset, hit rate and cooldown
+#[poise::command(slash_command, required_permissions = "MANAGE_MESSAGES")]
+pub async fn trigger(
+    ctx: PoiseContext<'_>,
+    #[description = "The response's name"]
+    #[autocomplete = "autocomplete_response"]
+    name: String,
+) -> Result<()> {
+    let response = ctx
+        .data()
+        .config
+        .read()
+        .await
+        .all_responses()
+        .find(|response| response.name() == name)
+        .map(|response| (response.message_response(), response.persona()));
+
+    let Some((message_response, persona)) = response else {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content(format!("No response named `{}`.", name)),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    // Responses are replies, so they need a message to reply to
+    let reply_target = ctx
+        .say(format!("Triggering `{}`:", name))
+        .await?
+        .into_message()
+        .await?;
+
+    ctx.data()
+        .run_action(
+            &name,
+            &message_response,
+            persona.as_ref(),
+            &reply_target,
+            ctx.serenity_context(),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// See which variant of a response gets the most reactions and replies
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_MESSAGES"
+)]
+pub async fn ab_results(
+    ctx: PoiseContext<'_>,
+    #[description = "The response's name"]
+    #[autocomplete = "autocomplete_response"]
+    name: String,
+) -> Result<()> {
+    let message_response = ctx
+        .data()
+        .config
+        .read()
+        .await
+        .all_responses()
+        .find(|response| response.name() == name)
+        .map(|response| response.message_response());
+
+    let Some(message_response) = message_response else {
+        ctx.say(format!("No response named `{}`.", name)).await?;
+        return Ok(());
+    };
+
+    let ResponseKind::Variants { variants } = message_response.as_ref() else {
+        ctx.say(format!("`{}` doesn't have variants.", name))
+            .await?;
+        return Ok(());
+    };
+
+    let results = variant_results(&ctx.data().db, &name, variants.len())?;
+
+    let Some(best) = winner(&results) else {
+        ctx.say(format!("No variant of `{}` has been sent yet.", name))
+            .await?;
+        return Ok(());
+    };
+
+    let preview = |variant: usize| -> String {
+        variants
+            .get(variant)
+            .map(|content| content.chars().take(50).collect())
+            .unwrap_or_else(|| "(removed)".to_owned())
+    };
+
+    let lines = results
+        .iter()
+        .map(|result| {
+            format!(
+                "{}. {} - {} sends, {} reactions, {} replies ({:.2} per send)",
+                result.variant + 1,
+                preview(result.variant),
+                result.sends,
+                result.reactions,
+                result.replies,
+                result.engagement()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ctx.say(format!(
+        "**Winner:** {}. {}\n\n{}",
+        best.variant + 1,
+        preview(best.variant),
+        lines
+    ))
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use crate::{
+    data::PoiseContext,
+    departments::{department_of, has_role_icons},
+    progress::ProgressReporter,
+};
+use color_eyre::eyre::{OptionExt, Result};
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity};
+use std::{collections::HashMap, time::Duration};
+
+/// Time between role edits, so retheming every class doesn't get us rate limited.
+const EDIT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Give every class role its department's color and icon
+#[poise::command(slash_command, ephemeral = true, required_permissions = "MANAGE_ROLES")]
+pub async fn retheme_class_roles(ctx: PoiseContext<'_>) -> Result<()> {
+    let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+
+    let (departments, dry_run) = {
+        let config = ctx.data().config.read().await;
+        (config.departments.clone(), config.dry_run)
+    };
+
+    if departments.is_empty() {
+        ctx.say("No departments are configured.").await?;
+        return Ok(());
+    }
+
+    let class_roles = guild_id
+        .roles(ctx)
+        .await?
+        .into_values()
+        .filter_map(|role| {
+            let department = departments.get(department_of(&role.name)?)?;
+            Some((role, department))
+        })
+        .sorted_by(|(a, _), (b, _)| a.name.cmp(&b.name))
+        .collect_vec();
+
+    if dry_run {
+        ctx.say(format!(
+            "Dry run: would retheme {}",
+            class_roles.iter().map(|(role, _)| &role.name).join(", ")
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let mut progress =
+        ProgressReporter::start(ctx, "Retheming class roles", class_roles.len()).await?;
+
+    let role_icons = ctx.guild().is_some_and(|guild| has_role_icons(&guild));
+
+    let mut icons = HashMap::new();
+    for (name, department) in &departments {
+        icons.insert(name.as_str(), department.load_icon(role_icons).await?);
+    }
+
+    let mut failed = vec![];
+
+    for (role, department) in &class_roles {
+        let icon = department_of(&role.name)
+            .and_then(|name| icons.get(name))
+            .and_then(Option::as_ref);
+
+        if let Err(e) = guild_id
+            .edit_role(
+                ctx,
+                role.id,
+                department.theme(serenity::EditRole::new(), icon),
+            )
+            .await
+        {
+            tracing::warn!("Couldn't retheme {}: {:?}", role.name, e);
+            failed.push(role.name.clone());
+        }
+
+        progress.advance().await;
+        tokio::time::sleep(EDIT_INTERVAL).await;
+    }
+
+    let mut reply = format!("Rethemed {} class roles.", class_roles.len() - failed.len());
+
+    if !failed.is_empty() {
+        reply.push_str(&format!("\nCouldn't retheme: {}", failed.join(", ")));
+    }
+
+    progress.finish(reply).await;
+
+    Ok(())
+}
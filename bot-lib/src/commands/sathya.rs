@@ -1,4 +1,4 @@
-use crate::{config::ReactRole, data::PoiseContext};
+use crate::data::PoiseContext;
 use color_eyre::eyre::{OptionExt, Result, WrapErr};
 use poise::serenity_prelude::{EditMember, GuildId, MessageBuilder, User, UserId};
 
@@ -17,15 +17,7 @@ pub async fn sathya(ctx: PoiseContext<'_>, victim: User) -> Result<()> {
         return Ok(());
     }
 
-    let author_has_role = ctx
-        .data()
-        .config
-        .read()
-        .await
-        .bot_react_role_members
-        .iter()
-        .find(|member| matches!(member, ReactRole { user_id, .. } if UserId::new(*user_id) == author.id))
-        .map(|member| member.react);
+    let author_has_role = ctx.data().react_roles.wants_reactions(author.id);
 
     if let Some(false) = author_has_role {
         ctx.say("Target doesn't have bot react role!").await?;
@@ -0,0 +1,298 @@
+use crate::{
+    announcements::Announcements,
+    data::PoiseContext,
+    datetime::{parse_when, When},
+    profile::get_profile,
+    scheduled_messages::{
+        get_scheduled_message, get_scheduled_messages, remove_scheduled_message,
+        save_scheduled_message, ScheduledMessage,
+    },
+};
+use chrono::{DateTime, Local, Offset, Utc};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity, Mentionable};
+
+const MESSAGES_PER_PAGE: usize = 10;
+
+fn is_mod(member: &serenity::Member) -> bool {
+    member
+        .permissions
+        .is_some_and(|permissions| permissions.manage_messages())
+}
+
+/// Mods and officers can schedule messages.
+async fn can_schedule(ctx: PoiseContext<'_>) -> Result<bool> {
+    let Some(member) = ctx.author_member().await else {
+        return Ok(false);
+    };
+
+    if is_mod(&member) {
+        return Ok(true);
+    }
+
+    Ok(ctx
+        .data()
+        .config
+        .read()
+        .await
+        .announcements
+        .as_ref()
+        .is_some_and(|announcements| announcements.is_officer(&member.roles)))
+}
+
+/// Officers can only schedule into announcement channels that don't need approval, anything
+/// else has to go through `/announce compose`.
+fn officer_can_post(
+    announcements: Option<&Announcements>,
+    channel_id: serenity::ChannelId,
+) -> bool {
+    announcements
+        .and_then(|announcements| announcements.channel(channel_id))
+        .is_some_and(|channel| !channel.requires_approval)
+}
+
+/// Reads a time in the author's timezone. Replies with what went wrong if it can't.
+async fn parse_send_at(ctx: PoiseContext<'_>, time: &str) -> Result<Option<DateTime<Utc>>> {
+    let saved_timezone = get_profile(&ctx.data().db, ctx.author().id)?.timezone();
+    let timezone = saved_timezone.unwrap_or_else(|| Local::now().offset().fix());
+    let now = Utc::now().with_timezone(&timezone);
+
+    let problem = match parse_when(time, now) {
+        Some(When::Once(when)) if when > now => return Ok(Some(when.with_timezone(&Utc))),
+        Some(When::Once(_)) => "That's in the past.".to_owned(),
+        Some(When::Every(_)) => "Scheduled messages only go out once.".to_owned(),
+        None => format!(
+            "Couldn't make sense of `{}`, try something like \"Friday 3pm\".",
+            time
+        ),
+    };
+
+    ctx.say(match saved_timezone {
+        Some(_) => problem,
+        None => format!(
+            "{} (Read as UTC{}, set your own timezone with `/profile set timezone`.)",
+            problem, timezone
+        ),
+    })
+    .await?;
+
+    Ok(None)
+}
+
+/// Finds a message the author may change: their own, or anyone's for mods.
+async fn find_own_message(ctx: PoiseContext<'_>, id: u64) -> Result<Option<ScheduledMessage>> {
+    let is_mod = ctx
+        .author_member()
+        .await
+        .is_some_and(|member| is_mod(&member));
+
+    let message = get_scheduled_message(&ctx.data().db, id)?
+        .filter(|message| is_mod || message.author_id == ctx.author().id.get());
+
+    if message.is_none() {
+        ctx.say(format!("You don't have a scheduled message #{}.", id))
+            .await?;
+    }
+
+    Ok(message)
+}
+
+#[poise::command(
+    slash_command,
+    subcommands(
+        "schedule_message_new",
+        "schedule_message_list",
+        "schedule_message_edit",
+        "schedule_message_cancel"
+    ),
+    subcommand_required,
+    description_localized("en-US", "Messages kingfisher posts later, for mods and officers")
+)]
+pub async fn schedule_message(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Schedule a message to be posted later
+#[poise::command(slash_command, ephemeral = true, rename = "new")]
+pub async fn schedule_message_new(
+    ctx: PoiseContext<'_>,
+    #[description = "Where to post it"]
+    #[channel_types("Text", "News")]
+    channel: serenity::GuildChannel,
+    #[description = "e.g. Friday 3pm, Dec 12 8am, in 2h. Uses your /profile timezone"] time: String,
+    #[description = "What to post, \\n for a new line"]
+    #[max_length = 2000]
+    content: String,
+    #[description = "Let it ping who it mentions, mods only"] allow_pings: Option<bool>,
+) -> Result<()> {
+    if !can_schedule(ctx).await? {
+        ctx.say("Only mods and officers can schedule messages.")
+            .await?;
+        return Ok(());
+    }
+
+    let is_mod = ctx
+        .author_member()
+        .await
+        .is_some_and(|member| is_mod(&member));
+    let allow_pings = allow_pings.unwrap_or(false);
+
+    if !is_mod {
+        if allow_pings {
+            ctx.say("Only mods can schedule messages that ping.")
+                .await?;
+            return Ok(());
+        }
+
+        let announcements = ctx.data().config.read().await.announcements.clone();
+
+        if !officer_can_post(announcements.as_ref(), channel.id) {
+            ctx.say(
+                "Officers can only schedule messages in announcement channels that don't need \
+                 approval. Use `/announce compose` for the others.",
+            )
+            .await?;
+            return Ok(());
+        }
+    }
+
+    let Some(send_at) = parse_send_at(ctx, &time).await? else {
+        return Ok(());
+    };
+
+    let message = ScheduledMessage {
+        id: ctx.data().db.generate_id()?,
+        channel_id: channel.id.get(),
+        author_id: ctx.author().id.get(),
+        content: content.replace("\\n", "\n"),
+        send_at,
+        allow_pings,
+    };
+
+    save_scheduled_message(&ctx.data().db, &message)?;
+
+    ctx.say(format!(
+        "Scheduled #{} for {} <t:{}:F>.",
+        message.id,
+        channel.id.mention(),
+        send_at.timestamp()
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// See messages waiting to be posted
+#[poise::command(slash_command, ephemeral = true, rename = "list")]
+pub async fn schedule_message_list(ctx: PoiseContext<'_>) -> Result<()> {
+    if !can_schedule(ctx).await? {
+        ctx.say("Only mods and officers can schedule messages.")
+            .await?;
+        return Ok(());
+    }
+
+    let messages = get_scheduled_messages(&ctx.data().db)?;
+
+    if messages.is_empty() {
+        ctx.say("Nothing is scheduled.").await?;
+        return Ok(());
+    }
+
+    let pages = messages
+        .chunks(MESSAGES_PER_PAGE)
+        .map(|chunk| chunk.iter().map(ScheduledMessage::summary).join("\n"))
+        .collect_vec();
+
+    poise::builtins::paginate(ctx, &pages.iter().map(String::as_str).collect_vec()).await?;
+
+    Ok(())
+}
+
+/// Change when a scheduled message goes out, or what it says
+#[poise::command(slash_command, ephemeral = true, rename = "edit")]
+pub async fn schedule_message_edit(
+    ctx: PoiseContext<'_>,
+    #[description = "The number after the # in /schedule_message list"] id: u64,
+    #[description = "The new time, e.g. Friday 3pm"] time: Option<String>,
+    #[description = "The new message, \\n for a new line"]
+    #[max_length = 2000]
+    content: Option<String>,
+) -> Result<()> {
+    let Some(mut message) = find_own_message(ctx, id).await? else {
+        return Ok(());
+    };
+
+    if let Some(time) = time {
+        let Some(send_at) = parse_send_at(ctx, &time).await? else {
+            return Ok(());
+        };
+
+        message.send_at = send_at;
+    }
+
+    if let Some(content) = content {
+        message.content = content.replace("\\n", "\n");
+    }
+
+    save_scheduled_message(&ctx.data().db, &message)?;
+
+    ctx.say(format!("Updated: {}", message.summary())).await?;
+
+    Ok(())
+}
+
+/// Stop a scheduled message from going out
+#[poise::command(slash_command, ephemeral = true, rename = "cancel")]
+pub async fn schedule_message_cancel(
+    ctx: PoiseContext<'_>,
+    #[description = "The number after the # in /schedule_message list"] id: u64,
+) -> Result<()> {
+    if find_own_message(ctx, id).await?.is_none() {
+        return Ok(());
+    }
+
+    remove_scheduled_message(&ctx.data().db, id)?;
+
+    ctx.say(format!("Cancelled #{}.", id)).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::announcements::AnnouncementChannel;
+
+    #[test]
+    fn officers_only_post_where_approval_isnt_needed() {
+        let announcements = Announcements {
+            officer_role_ids: vec![1],
+            approval_channel_id: Some(2),
+            channels: vec![
+                AnnouncementChannel {
+                    channel_id: 10,
+                    requires_approval: false,
+                },
+                AnnouncementChannel {
+                    channel_id: 11,
+                    requires_approval: true,
+                },
+            ],
+        };
+
+        assert!(officer_can_post(
+            Some(&announcements),
+            serenity::ChannelId::new(10)
+        ));
+        assert!(!officer_can_post(
+            Some(&announcements),
+            serenity::ChannelId::new(11)
+        ));
+        assert!(!officer_can_post(
+            Some(&announcements),
+            serenity::ChannelId::new(12)
+        ));
+        assert!(!officer_can_post(None, serenity::ChannelId::new(10)));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::data::PoiseContext;
+use chrono::{Local, NaiveDate};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+
+#[poise::command(
+    slash_command,
+    required_permissions = "MANAGE_MESSAGES",
+    subcommands("season_preview"),
+    subcommand_required,
+    description_localized("en-US", "Manage seasonal responses")
+)]
+pub async fn season(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// See which seasonal responses are live on a day
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_MESSAGES",
+    rename = "preview"
+)]
+pub async fn season_preview(
+    ctx: PoiseContext<'_>,
+    #[description = "The day to check, like 2024-04-01, defaults to today"] date: Option<String>,
+) -> Result<()> {
+    let date = match date {
+        Some(date) => match NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => {
+                ctx.say(format!("`{}` isn't a date like 2024-04-01.", date))
+                    .await?;
+                return Ok(());
+            }
+        },
+        None => Local::now().date_naive(),
+    };
+
+    let config = ctx.data().config.read().await;
+
+    let packs = config
+        .response_packs
+        .iter()
+        .filter(|pack| pack.season.contains(date))
+        .map(|pack| {
+            format!(
+                "- Pack `{}` ({}), {} responses",
+                pack.name,
+                pack.season,
+                pack.responses.len()
+            )
+        });
+
+    let responses = config
+        .responses
+        .iter()
+        .filter_map(|response| Some((response.name(), response.season()?)))
+        .filter(|(_, season)| season.contains(date))
+        .map(|(name, season)| format!("- `{}` ({})", name, season));
+
+    let live = packs.chain(responses).collect_vec();
+
+    let upcoming = config
+        .response_packs
+        .iter()
+        .filter(|pack| !pack.season.contains(date))
+        .filter_map(|pack| Some((pack.season.next_start(date)?, pack)))
+        .sorted_by_key(|(start, _)| *start)
+        .map(|(start, pack)| format!("- Pack `{}` on {}", pack.name, start))
+        .collect_vec();
+
+    drop(config);
+
+    let mut message = if live.is_empty() {
+        format!("Nothing seasonal is live on {}.", date)
+    } else {
+        format!("Live on {}:\n{}", date, live.join("\n"))
+    };
+
+    if !upcoming.is_empty() {
+        message.push_str(&format!("\n\nComing up:\n{}", upcoming.join("\n")));
+    }
+
+    ctx.say(message).await?;
+
+    Ok(())
+}
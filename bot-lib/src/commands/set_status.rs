@@ -0,0 +1,36 @@
+use crate::{
+    data::PoiseContext,
+    presence::{parse_activity, STATUS_OVERRIDE},
+};
+use chrono::{Duration, Utc};
+use color_eyre::eyre::Result;
+
+/// Show a status instead of the usual rotation for a while
+#[poise::command(slash_command, owners_only, hide_in_help, ephemeral = true)]
+pub async fn set_status(
+    ctx: PoiseContext<'_>,
+    #[description = "e.g. Watching the servers burn, leave empty to go back to the rotation"]
+    #[max_length = 128]
+    status: Option<String>,
+    #[description = "How long to show it, defaults to an hour"]
+    #[min = 1]
+    #[max = 10080]
+    minutes: Option<i64>,
+) -> Result<()> {
+    let Some(status) = status else {
+        *STATUS_OVERRIDE.lock() = None;
+        ctx.say("Back to the usual statuses, starting with the next rotation.")
+            .await?;
+        return Ok(());
+    };
+
+    let until = Utc::now() + Duration::minutes(minutes.unwrap_or(60));
+
+    ctx.serenity_context()
+        .set_activity(Some(parse_activity(&status)));
+    *STATUS_OVERRIDE.lock() = Some((status, until));
+
+    ctx.say("Status set!").await?;
+
+    Ok(())
+}
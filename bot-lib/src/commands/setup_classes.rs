@@ -0,0 +1,353 @@
+use crate::{
+    class_directory::group_classes,
+    commands::ensure_can_manage_role,
+    data::{PoiseApplicationContext, PoiseContext},
+    departments::{department_of, KnownClasses},
+    onboarding::{find_class_by_name, parse_classes},
+    utils::execute_modal_on_button,
+};
+use color_eyre::eyre::{OptionExt, Result, WrapErr};
+use itertools::Itertools;
+use poise::{
+    serenity_prelude::{self as serenity, RoleId},
+    CreateReply, Modal,
+};
+use std::{collections::BTreeSet, time::Duration};
+
+/// How long each step waits for a pick.
+const STEP_TIMEOUT: Duration = Duration::from_secs(600);
+/// Discord allows 5 rows per message, one of which is the buttons.
+const CLASS_MENUS: usize = 4;
+const OPTIONS_PER_MENU: usize = 25;
+/// How many new class roles one run can make, so nobody can fill the server with roles.
+const MAX_NEW_CLASSES: usize = 3;
+
+#[derive(Debug, Modal)]
+#[name = "Classes that aren't listed"]
+struct OtherClassesModal {
+    #[name = "Classes, separated by commas"]
+    #[placeholder = "CS 2420, MATH 2270"]
+    #[paragraph]
+    #[max_length = 500]
+    classes: String,
+}
+
+/// Pick your year and classes, and get all their roles at once
+#[poise::command(slash_command, guild_only, ephemeral = true)]
+pub async fn setup_classes(ctx: PoiseApplicationContext<'_>) -> Result<()> {
+    let poise_ctx = PoiseContext::Application(ctx);
+    let onboarding = ctx
+        .data()
+        .config
+        .read()
+        .await
+        .onboarding
+        .clone()
+        .unwrap_or_default();
+    let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+    let author_id = ctx.author().id;
+
+    ensure_can_manage_role(poise_ctx, None).await?;
+
+    let roles = guild_id.roles(ctx).await?;
+    let known = KnownClasses::load(ctx.serenity_context(), ctx.data(), guild_id).await?;
+    let classes = group_classes(&roles.values().cloned().collect_vec(), &known)
+        .into_iter()
+        .flat_map(|(department, classes)| {
+            classes
+                .into_iter()
+                .map(move |(number, role_id)| (format!("{} {}", department, number), role_id))
+        })
+        .collect_vec();
+
+    let year_id = format!("setup_classes_year:{}", ctx.id());
+    let done_id = format!("setup_classes_done:{}", ctx.id());
+    let other_id = format!("setup_classes_other:{}", ctx.id());
+    let menu_ids = (0..CLASS_MENUS)
+        .map(|i| format!("setup_classes_menu:{}:{}", ctx.id(), i))
+        .collect_vec();
+
+    let class_rows = classes
+        .chunks(OPTIONS_PER_MENU)
+        .take(CLASS_MENUS)
+        .zip(&menu_ids)
+        .map(|(chunk, menu_id)| {
+            let options = chunk
+                .iter()
+                .map(|(name, role_id)| {
+                    serenity::CreateSelectMenuOption::new(name, role_id.to_string())
+                })
+                .collect_vec();
+
+            serenity::CreateActionRow::SelectMenu(
+                serenity::CreateSelectMenu::new(
+                    menu_id,
+                    serenity::CreateSelectMenuKind::String { options },
+                )
+                .placeholder(format!(
+                    "{} to {}",
+                    chunk.first().map_or("", |(name, _)| name.as_str()),
+                    chunk.last().map_or("", |(name, _)| name.as_str())
+                ))
+                .min_values(0)
+                .max_values(chunk.len() as u8),
+            )
+        })
+        .chain([serenity::CreateActionRow::Buttons(vec![
+            serenity::CreateButton::new(&done_id)
+                .label("Done")
+                .style(serenity::ButtonStyle::Success),
+            serenity::CreateButton::new(&other_id)
+                .label("My class isn't listed")
+                .style(serenity::ButtonStyle::Secondary),
+        ])])
+        .collect_vec();
+    let classes_prompt = "Pick the classes you're taking, then hit Done.";
+
+    // Step one, the year
+    let (reply, year) = if onboarding.years.is_empty() {
+        let reply = ctx
+            .send(
+                CreateReply::default()
+                    .content(classes_prompt)
+                    .components(class_rows),
+            )
+            .await?;
+
+        (reply, None)
+    } else {
+        let options = onboarding
+            .years
+            .iter()
+            .map(|year| serenity::CreateSelectMenuOption::new(&year.name, year.role_id.to_string()))
+            .collect_vec();
+        let reply = ctx
+            .send(
+                CreateReply::default()
+                    .content("What year are you?")
+                    .components(vec![serenity::CreateActionRow::SelectMenu(
+                        serenity::CreateSelectMenu::new(
+                            &year_id,
+                            serenity::CreateSelectMenuKind::String { options },
+                        ),
+                    )]),
+            )
+            .await?;
+
+        let Some(pick) = serenity::ComponentInteractionCollector::new(ctx)
+            .custom_ids(vec![year_id.clone()])
+            .filter(move |interaction| interaction.user.id == author_id)
+            .timeout(STEP_TIMEOUT)
+            .await
+        else {
+            reply
+                .edit(
+                    poise_ctx,
+                    CreateReply::default()
+                        .content("Took too long, run `/setup_classes` again when you're ready.")
+                        .components(vec![]),
+                )
+                .await?;
+            return Ok(());
+        };
+
+        let year = match &pick.data.kind {
+            serenity::ComponentInteractionDataKind::StringSelect { values } => values
+                .first()
+                .and_then(|value| value.parse::<u64>().ok())
+                .map(RoleId::new),
+            _ => None,
+        };
+
+        pick.create_response(
+            ctx,
+            serenity::CreateInteractionResponse::UpdateMessage(
+                serenity::CreateInteractionResponseMessage::new()
+                    .content(classes_prompt)
+                    .components(class_rows),
+            ),
+        )
+        .await?;
+
+        (reply, year)
+    };
+
+    // Step two, the classes, as many menu picks and typed classes as they like until Done
+    let mut picked = vec![BTreeSet::<RoleId>::new(); CLASS_MENUS];
+    let mut typed = BTreeSet::<String>::new();
+    let mut invalid = vec![];
+
+    let done = loop {
+        let Some(interaction) = serenity::ComponentInteractionCollector::new(ctx)
+            .custom_ids(
+                menu_ids
+                    .iter()
+                    .cloned()
+                    .chain([done_id.clone(), other_id.clone()])
+                    .collect(),
+            )
+            .filter(move |interaction| interaction.user.id == author_id)
+            .timeout(STEP_TIMEOUT)
+            .await
+        else {
+            break None;
+        };
+
+        if interaction.data.custom_id == done_id {
+            break Some(interaction);
+        }
+
+        if interaction.data.custom_id == other_id {
+            if let Some(OtherClassesModal { classes }) =
+                execute_modal_on_button(ctx.serenity_context(), &interaction).await?
+            {
+                let (classes, unparsed) = parse_classes(&classes, &known);
+                typed.extend(classes);
+                invalid.extend(unparsed);
+            }
+            continue;
+        }
+
+        if let (Some(menu), serenity::ComponentInteractionDataKind::StringSelect { values }) = (
+            menu_ids
+                .iter()
+                .position(|menu_id| *menu_id == interaction.data.custom_id),
+            &interaction.data.kind,
+        ) {
+            picked[menu] = values
+                .iter()
+                .filter_map(|value| value.parse::<u64>().ok())
+                .map(RoleId::new)
+                .collect();
+        }
+
+        interaction
+            .create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
+            .await?;
+    };
+
+    let Some(done) = done else {
+        reply
+            .edit(
+                poise_ctx,
+                CreateReply::default()
+                    .content("Took too long, run `/setup_classes` again when you're ready.")
+                    .components(vec![]),
+            )
+            .await?;
+        return Ok(());
+    };
+
+    // Now everything at once
+    let member = guild_id.member(ctx, author_id).await?;
+    let mut to_add = picked.into_iter().flatten().collect::<BTreeSet<_>>();
+    let mut created = vec![];
+    let mut missing = vec![];
+
+    for name in typed {
+        if let Some(role_id) = find_class_by_name(&roles, &known, &name) {
+            to_add.insert(role_id);
+            continue;
+        }
+
+        let department = match department_of(&name) {
+            Some(department)
+                if onboarding.create_missing_classes
+                    && known.has_department(department)
+                    && created.len() < MAX_NEW_CLASSES =>
+            {
+                department.to_owned()
+            }
+            _ => {
+                missing.push(name);
+                continue;
+            }
+        };
+
+        // It might have been made since the menus were put together
+        let current_roles = guild_id.roles(ctx).await?;
+        if let Some(role_id) = find_class_by_name(&current_roles, &known, &name) {
+            to_add.insert(role_id);
+            continue;
+        }
+
+        let department = ctx
+            .data()
+            .config
+            .read()
+            .await
+            .departments
+            .get(&department)
+            .cloned()
+            .unwrap_or_default();
+        let role = guild_id
+            .create_role(
+                ctx,
+                department.theme(
+                    serenity::EditRole::new()
+                        .hoist(true)
+                        .name(&name)
+                        .audit_log_reason("Added in /setup_classes"),
+                    None,
+                ),
+            )
+            .await
+            .wrap_err_with(|| format!("Couldn't create a role for {}", name))?;
+        to_add.insert(role.id);
+        created.push(name);
+    }
+
+    if let Some(year) = year {
+        for other_year in &onboarding.years {
+            let other_year = RoleId::new(other_year.role_id);
+
+            if other_year != year && member.roles.contains(&other_year) {
+                member.remove_role(ctx, other_year).await?;
+            }
+        }
+
+        to_add.insert(year);
+    }
+
+    let to_add = to_add
+        .into_iter()
+        .filter(|role_id| !member.roles.contains(role_id))
+        .collect_vec();
+
+    member
+        .add_roles(ctx, &to_add)
+        .await
+        .wrap_err("Couldn't add class roles")?;
+
+    let mut summary = vec![format!("You're all set, added {} roles.", to_add.len())];
+
+    if !created.is_empty() {
+        summary.push(format!("New classes: {}", created.join(", ")));
+    }
+
+    if !missing.is_empty() {
+        summary.push(format!(
+            "These classes don't exist yet, ask a mod to add them: {}",
+            missing.join(", ")
+        ));
+    }
+
+    if !invalid.is_empty() {
+        summary.push(format!(
+            "Couldn't tell what these were: {}",
+            invalid.join(", ")
+        ));
+    }
+
+    done.create_response(
+        ctx,
+        serenity::CreateInteractionResponse::UpdateMessage(
+            serenity::CreateInteractionResponseMessage::new()
+                .content(summary.join("\n"))
+                .components(vec![]),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,208 @@
+use crate::{
+    data::{PoiseApplicationContext, PoiseContext},
+    departments::HexColor,
+    economy::{
+        balance, buy_role_color, give_token, spend, take_token, token_count, transact, ItemKind,
+        Token,
+    },
+};
+use color_eyre::eyre::{OptionExt, Result};
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity, Mentionable};
+
+async fn autocomplete_item(ctx: PoiseContext<'_>, partial: &str) -> Vec<String> {
+    let Some(economy) = ctx.data().config.read().await.economy.clone() else {
+        return vec![];
+    };
+
+    economy
+        .shop
+        .into_iter()
+        .map(|item| item.name)
+        .filter(|name| name.to_lowercase().contains(&partial.to_lowercase()))
+        .take(25)
+        .collect()
+}
+
+#[poise::command(
+    slash_command,
+    subcommands("shop_list", "shop_buy", "shop_balance"),
+    subcommand_required,
+    description_localized("en-US", "Spend what you've earned by chatting")
+)]
+pub async fn shop(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// See what's for sale
+#[poise::command(slash_command, ephemeral = true, rename = "list")]
+pub async fn shop_list(ctx: PoiseContext<'_>) -> Result<()> {
+    let Some(economy) = ctx.data().config.read().await.economy.clone() else {
+        ctx.say("The shop isn't open.").await?;
+        return Ok(());
+    };
+
+    if economy.shop.is_empty() {
+        ctx.say("Nothing's for sale right now.").await?;
+        return Ok(());
+    }
+
+    ctx.say(
+        economy
+            .shop
+            .iter()
+            .map(|item| {
+                format!(
+                    "**{}** ({} {}): {}",
+                    item.name, item.price, economy.currency, item.description
+                )
+            })
+            .join("\n"),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Buy something from the shop
+#[poise::command(slash_command, ephemeral = true, rename = "buy")]
+pub async fn shop_buy(
+    ctx: PoiseContext<'_>,
+    #[description = "What to buy"]
+    #[autocomplete = "autocomplete_item"]
+    item: String,
+    #[description = "For role colors, like #cc0000"] color: Option<String>,
+) -> Result<()> {
+    let Some(economy) = ctx.data().config.read().await.economy.clone() else {
+        ctx.say("The shop isn't open.").await?;
+        return Ok(());
+    };
+
+    let Some(item) = economy.shop.iter().find(|shop_item| shop_item.name == item) else {
+        ctx.say(format!("Nothing called `{}` is for sale.", item))
+            .await?;
+        return Ok(());
+    };
+
+    let color = match (&item.kind, color.map(|color| color.parse::<HexColor>())) {
+        (ItemKind::RoleColor { .. }, None) => {
+            ctx.say("Pick a color, like `#cc0000`.").await?;
+            return Ok(());
+        }
+        (_, Some(Err(_))) => {
+            ctx.say("That isn't a color, try something like `#cc0000`.")
+                .await?;
+            return Ok(());
+        }
+        (_, color) => color.and_then(Result::ok),
+    };
+
+    if matches!(item.kind, ItemKind::RoleColor { .. }) && economy.color_anchor_role_id.is_none() {
+        ctx.say("Role colors aren't set up yet, ask a mod.").await?;
+        return Ok(());
+    }
+
+    let db = &ctx.data().db;
+    let user_id = ctx.author().id;
+
+    if !spend(db, user_id, item.price, &format!("Bought {}", item.name))? {
+        ctx.say(format!(
+            "That costs {} {}, you have {}.",
+            item.price,
+            economy.currency,
+            balance(db, user_id)?
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let bought = match &item.kind {
+        ItemKind::RoleColor { duration } => {
+            let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+            let color = color.ok_or_eyre("Role colors need a color")?;
+            let anchor_role_id = economy
+                .color_anchor_role_id
+                .map(serenity::RoleId::new)
+                .ok_or_eyre("Role colors need an anchor role")?;
+
+            buy_role_color(
+                ctx.serenity_context(),
+                db,
+                guild_id,
+                user_id,
+                anchor_role_id,
+                color,
+                *duration,
+            )
+            .await
+            .map(|role_id| format!("You're now {} for a while.", role_id.mention()))
+        }
+        ItemKind::SoundboardCredit => give_token(db, user_id, Token::SoundboardCredit).map(|_| {
+            "Next time `/play` says to wait, a credit gets used to play anyway.".to_owned()
+        }),
+        ItemKind::PinToken => give_token(db, user_id, Token::Pin).map(|_| {
+            "Right click a message and pick *Apps > Pin with token* to use it.".to_owned()
+        }),
+    };
+
+    match bought {
+        Ok(message) => ctx.say(message).await?,
+        Err(e) => {
+            transact(
+                db,
+                user_id,
+                item.price as i64,
+                &format!("Refunded {}", item.name),
+            )?;
+            return Err(e);
+        }
+    };
+
+    Ok(())
+}
+
+/// See what you have to spend
+#[poise::command(slash_command, ephemeral = true, rename = "balance")]
+pub async fn shop_balance(ctx: PoiseContext<'_>) -> Result<()> {
+    let Some(economy) = ctx.data().config.read().await.economy.clone() else {
+        ctx.say("The shop isn't open.").await?;
+        return Ok(());
+    };
+
+    let db = &ctx.data().db;
+    let user_id = ctx.author().id;
+
+    ctx.say(format!(
+        "You have {} {}, {} soundboard credits and {} pin tokens.",
+        balance(db, user_id)?,
+        economy.currency,
+        token_count(db, user_id, Token::SoundboardCredit)?,
+        token_count(db, user_id, Token::Pin)?
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(context_menu_command = "Pin with token", ephemeral = true)]
+pub async fn pin_with_token(
+    ctx: PoiseApplicationContext<'_>,
+    message: serenity::Message,
+) -> Result<()> {
+    let db = &ctx.data().db;
+
+    if !take_token(db, ctx.author().id, Token::Pin)? {
+        ctx.say("You don't have any pin tokens, they're in `/shop`.")
+            .await?;
+        return Ok(());
+    }
+
+    if let Err(e) = message.pin(ctx).await {
+        give_token(db, ctx.author().id, Token::Pin)?;
+        return Err(e.into());
+    }
+
+    ctx.say("Pinned!").await?;
+
+    Ok(())
+}
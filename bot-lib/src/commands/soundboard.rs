@@ -0,0 +1,191 @@
+use crate::{
+    data::PoiseContext,
+    voice::{
+        forget_plays, is_valid_sound_name, list_sounds, play_count, sound_duration,
+        SOUND_EXTENSIONS,
+    },
+};
+use color_eyre::eyre::{OptionExt, Result, WrapErr};
+use itertools::Itertools;
+use poise::serenity_prelude::Attachment;
+use std::path::Path;
+
+#[poise::command(
+    slash_command,
+    subcommands("soundboard_add", "soundboard_list", "soundboard_remove"),
+    subcommand_required,
+    description_localized("en-US", "Manage the sounds `/play` can play")
+)]
+pub async fn soundboard(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Add a sound to the soundboard
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_MESSAGES",
+    rename = "add"
+)]
+pub async fn soundboard_add(
+    ctx: PoiseContext<'_>,
+    #[description = "What to call the sound (letters, numbers, - and _)"] name: String,
+    #[description = "An mp3, ogg, wav or flac file"] sound: Attachment,
+) -> Result<()> {
+    let soundboard = ctx
+        .data()
+        .config
+        .read()
+        .await
+        .soundboard
+        .clone()
+        .ok_or_eyre("No soundboard is configured")?;
+
+    if !is_valid_sound_name(&name) {
+        ctx.say("Sound names can only have letters, numbers, - and _.")
+            .await?;
+        return Ok(());
+    }
+
+    let extension = Path::new(&sound.filename)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .filter(|extension| SOUND_EXTENSIONS.contains(&extension.as_str()));
+
+    let Some(extension) = extension else {
+        ctx.say(format!(
+            "Sounds have to be one of: {}.",
+            SOUND_EXTENSIONS.join(", ")
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    if sound.size > soundboard.max_bytes {
+        ctx.say(format!(
+            "That file is too big, sounds can be at most {} KB.",
+            soundboard.max_bytes / 1024
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    if list_sounds(&soundboard.dir)?
+        .iter()
+        .any(|(existing, _)| *existing == name)
+    {
+        ctx.say(format!("There already is a sound named `{}`.", name))
+            .await?;
+        return Ok(());
+    }
+
+    ctx.defer_ephemeral().await?;
+
+    let bytes = sound.download().await?;
+
+    let duration = match sound_duration(bytes.clone(), &extension) {
+        Ok(duration) => duration,
+        Err(e) => {
+            ctx.say(format!("Couldn't read that sound: {}", e)).await?;
+            return Ok(());
+        }
+    };
+
+    if duration > soundboard.max_duration.to_std().unwrap_or_default() {
+        ctx.say(format!(
+            "That sound is too long, sounds can be at most {} seconds.",
+            soundboard.max_duration.num_seconds()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(&soundboard.dir).wrap_err("Could not create soundboard directory")?;
+    std::fs::write(
+        Path::new(&soundboard.dir).join(format!("{}.{}", name, extension)),
+        bytes,
+    )
+    .wrap_err("Could not save sound")?;
+
+    ctx.say(format!("Added `{}`!", name)).await?;
+
+    Ok(())
+}
+
+/// List every sound and how often it was played
+#[poise::command(slash_command, ephemeral = true, rename = "list")]
+pub async fn soundboard_list(ctx: PoiseContext<'_>) -> Result<()> {
+    let soundboard = ctx
+        .data()
+        .config
+        .read()
+        .await
+        .soundboard
+        .clone()
+        .ok_or_eyre("No soundboard is configured")?;
+
+    let sounds = list_sounds(&soundboard.dir).unwrap_or_default();
+
+    if sounds.is_empty() {
+        ctx.say("The soundboard is empty.").await?;
+        return Ok(());
+    }
+
+    let list = sounds
+        .iter()
+        .map(|(name, _)| {
+            Ok(format!(
+                "- `{}` ({} plays)",
+                name,
+                play_count(ctx.data(), name)?
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?
+        .join("\n");
+
+    ctx.say(list).await?;
+
+    Ok(())
+}
+
+/// Remove a sound from the soundboard
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    required_permissions = "MANAGE_MESSAGES",
+    rename = "remove"
+)]
+pub async fn soundboard_remove(
+    ctx: PoiseContext<'_>,
+    #[description = "The sound to remove"] name: String,
+) -> Result<()> {
+    let soundboard = ctx
+        .data()
+        .config
+        .read()
+        .await
+        .soundboard
+        .clone()
+        .ok_or_eyre("No soundboard is configured")?;
+
+    let paths = list_sounds(&soundboard.dir)?
+        .into_iter()
+        .filter(|(existing, _)| *existing == name)
+        .map(|(_, path)| path)
+        .collect_vec();
+
+    if paths.is_empty() {
+        ctx.say(format!("No sound named `{}`.", name)).await?;
+        return Ok(());
+    }
+
+    for path in paths {
+        std::fs::remove_file(path).wrap_err("Could not remove sound")?;
+    }
+
+    forget_plays(ctx.data(), &name)?;
+
+    ctx.say(format!("Removed `{}`.", name)).await?;
+
+    Ok(())
+}
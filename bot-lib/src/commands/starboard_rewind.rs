@@ -0,0 +1,57 @@
+use crate::{
+    data::PoiseContext,
+    starboard_rewind::{format_post, starboard_posts, top_posts},
+};
+use chrono::{TimeZone, Utc};
+use color_eyre::eyre::{OptionExt, Result};
+use itertools::Itertools;
+
+#[poise::command(
+    slash_command,
+    description_localized("en-US", "Shows the best starboard posts of a year")
+)]
+pub async fn starboard_rewind(
+    ctx: PoiseContext<'_>,
+    #[description = "The year to look back on"] year: i32,
+) -> Result<()> {
+    let size = ctx
+        .data()
+        .config
+        .read()
+        .await
+        .starboard_rewind
+        .as_ref()
+        .map_or(10, |rewind| rewind.size);
+
+    let from = Utc
+        .with_ymd_and_hms(year, 1, 1, 0, 0, 0)
+        .single()
+        .ok_or_eyre("Invalid year")?;
+    let to = Utc
+        .with_ymd_and_hms(year + 1, 1, 1, 0, 0, 0)
+        .single()
+        .ok_or_eyre("Invalid year")?;
+
+    let posts = top_posts(
+        starboard_posts(ctx.data())?,
+        from.timestamp(),
+        to.timestamp(),
+        size,
+    );
+
+    if posts.is_empty() {
+        ctx.say(format!("Nothing made it onto a starboard in {}.", year))
+            .await?;
+        return Ok(());
+    }
+
+    let pages = posts
+        .iter()
+        .enumerate()
+        .map(|(i, post)| format!("## {} rewind\n{}", year, format_post(i + 1, post)))
+        .collect_vec();
+
+    poise::builtins::paginate(ctx, &pages.iter().map(String::as_str).collect_vec()).await?;
+
+    Ok(())
+}
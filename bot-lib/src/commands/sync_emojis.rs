@@ -0,0 +1,66 @@
+use crate::{
+    data::PoiseContext,
+    emoji_sync::{apply_change, get_synced_assets, plan_sync},
+};
+use color_eyre::eyre::{OptionExt, Result};
+use itertools::Itertools;
+
+/// Make the server's emojis and stickers match the assets folder
+#[poise::command(
+    slash_command,
+    required_permissions = "ADMINISTRATOR",
+    ephemeral = true
+)]
+pub async fn sync_emojis(
+    ctx: PoiseContext<'_>,
+    #[description = "Only show what would change"] preview: Option<bool>,
+) -> Result<()> {
+    let guild_id = ctx.guild_id().ok_or_eyre("Couldn't get guild")?;
+
+    let (emoji_assets, dry_run) = {
+        let config = ctx.data().config.read().await;
+        (config.emoji_assets.clone(), config.dry_run)
+    };
+
+    let Some(emoji_assets) = emoji_assets else {
+        ctx.say("No emoji assets folder is configured.").await?;
+        return Ok(());
+    };
+
+    let db = &ctx.data().db;
+    let changes = plan_sync(&emoji_assets.read_assets()?, &get_synced_assets(db)?);
+
+    if changes.is_empty() {
+        ctx.say("Emojis and stickers are already in sync.").await?;
+        return Ok(());
+    }
+
+    let diff = changes.iter().join("\n");
+
+    if preview.unwrap_or(false) || dry_run {
+        ctx.say(format!("Would make these changes:\n{}", diff))
+            .await?;
+        return Ok(());
+    }
+
+    ctx.defer_ephemeral().await?;
+
+    let mut failed = vec![];
+
+    for change in &changes {
+        if let Err(e) = apply_change(ctx.serenity_context(), db, guild_id, change).await {
+            tracing::warn!("Couldn't sync {}: {:?}", change, e);
+            failed.push(format!("{} ({})", change, e));
+        }
+    }
+
+    let mut reply = format!("Synced emojis and stickers:\n{}", diff);
+
+    if !failed.is_empty() {
+        reply.push_str(&format!("\n\nThese failed:\n{}", failed.join("\n")));
+    }
+
+    ctx.say(reply).await?;
+
+    Ok(())
+}
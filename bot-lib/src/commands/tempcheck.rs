@@ -0,0 +1,273 @@
+use crate::{
+    author_guard::human_reaction_count,
+    data::{AppState, PoiseContext},
+    db::KingFisherDb,
+    utils::GetRelativeTimestamp,
+};
+use chrono::{TimeZone, Utc};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity, CreateMessage, EditMessage};
+use serde::{Deserialize, Serialize};
+
+const TEMPCHECKS_TREE: &str = "tempchecks";
+const TEMPCHECK_REACTIONS: [char; 3] = ['👍', '🤷', '👎'];
+/// How many past temperature checks `/tempcheck history` shows.
+const HISTORY_SIZE: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TempCheck {
+    pub question: String,
+    pub channel_id: u64,
+    pub message_id: u64,
+    pub asked_by: u64,
+    pub closes_at: i64,
+    /// 👍, 🤷 and 👎 counts, not counting kingfisher's own. None while still open.
+    pub results: Option<[u64; 3]>,
+}
+
+/// e.g. "👍 60% (3) · 🤷 20% (1) · 👎 20% (1)"
+fn summarize(results: [u64; 3]) -> String {
+    let total = results.iter().sum::<u64>();
+
+    if total == 0 {
+        return "Nobody voted.".to_owned();
+    }
+
+    TEMPCHECK_REACTIONS
+        .iter()
+        .zip(results)
+        .map(|(reaction, count)| {
+            format!(
+                "{} {}% ({})",
+                reaction,
+                (count as f64 / total as f64 * 100.).round(),
+                count
+            )
+        })
+        .join(" · ")
+}
+
+fn count_votes(message: &serenity::Message) -> [u64; 3] {
+    TEMPCHECK_REACTIONS.map(|reaction| {
+        message
+            .reactions
+            .iter()
+            .find(|r| r.reaction_type.unicode_eq(&reaction.to_string()))
+            .map_or(0, human_reaction_count)
+    })
+}
+
+/// Open temperature checks whose time is up, soonest first.
+fn due_tempchecks(db: &KingFisherDb, now: i64) -> Result<Vec<TempCheck>> {
+    let mut due = db
+        .values::<TempCheck>(TEMPCHECKS_TREE)?
+        .into_iter()
+        .filter(|tempcheck| tempcheck.results.is_none() && tempcheck.closes_at <= now)
+        .collect_vec();
+
+    due.sort_by_key(|tempcheck| tempcheck.closes_at);
+
+    Ok(due)
+}
+
+async fn close_tempcheck(
+    ctx: &serenity::Context,
+    db: &KingFisherDb,
+    mut tempcheck: TempCheck,
+) -> Result<()> {
+    let key = tempcheck.message_id.to_be_bytes();
+    let mut message = match serenity::ChannelId::new(tempcheck.channel_id)
+        .message(ctx, tempcheck.message_id)
+        .await
+    {
+        Ok(message) => message,
+        Err(e) => {
+            // The question was probably deleted, so stop retrying it eventually
+            if Utc::now().timestamp() - tempcheck.closes_at > 24 * 3600 {
+                tempcheck.results = Some([0; 3]);
+                db.insert(TEMPCHECKS_TREE, key, &tempcheck)?;
+            }
+
+            return Err(e.into());
+        }
+    };
+    let results = count_votes(&message);
+
+    tempcheck.results = Some(results);
+    db.insert(TEMPCHECKS_TREE, key, &tempcheck)?;
+
+    message
+        .edit(
+            ctx,
+            EditMessage::new().content(format!(
+                "**Temperature check:** {}\n{}",
+                tempcheck.question,
+                summarize(results)
+            )),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Closes every temperature check whose time is up, including ones a restart cut off.
+pub async fn close_tempchecks(ctx: &serenity::Context, data: &AppState) -> Result<()> {
+    for tempcheck in due_tempchecks(&data.db, Utc::now().timestamp())? {
+        let message_id = tempcheck.message_id;
+
+        if let Err(e) = close_tempcheck(ctx, &data.db, tempcheck).await {
+            tracing::warn!("Couldn't close temperature check {}: {:?}", message_id, e);
+        }
+    }
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    subcommands("tempcheck_ask", "tempcheck_history"),
+    subcommand_required,
+    description_localized("en-US", "Take the temperature of the room")
+)]
+pub async fn tempcheck(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Ask a question, and see how everyone feels about it after a while
+#[poise::command(slash_command, ephemeral = true, rename = "ask")]
+pub async fn tempcheck_ask(
+    ctx: PoiseContext<'_>,
+    #[description = "What to ask"]
+    #[max_length = 1000]
+    question: String,
+    #[description = "How many minutes to collect reactions for, defaults to 10"]
+    #[min = 1]
+    #[max = 1440]
+    minutes: Option<i64>,
+) -> Result<()> {
+    let closes_at = Utc::now() + chrono::Duration::minutes(minutes.unwrap_or(10));
+
+    let message = ctx
+        .channel_id()
+        .send_message(
+            ctx,
+            CreateMessage::new()
+                .content(format!(
+                    "**Temperature check:** {}\nReact below, closes {}",
+                    question,
+                    closes_at.discord_relative_timestamp()
+                ))
+                .reactions(TEMPCHECK_REACTIONS)
+                .allowed_mentions(serenity::CreateAllowedMentions::new()),
+        )
+        .await?;
+
+    ctx.data().db.insert(
+        TEMPCHECKS_TREE,
+        message.id.get().to_be_bytes(),
+        &TempCheck {
+            question,
+            channel_id: message.channel_id.get(),
+            message_id: message.id.get(),
+            asked_by: ctx.author().id.get(),
+            closes_at: closes_at.timestamp(),
+            results: None,
+        },
+    )?;
+
+    ctx.say("Temperature check started!").await?;
+
+    Ok(())
+}
+
+/// See how past temperature checks went
+#[poise::command(slash_command, ephemeral = true, rename = "history")]
+pub async fn tempcheck_history(ctx: PoiseContext<'_>) -> Result<()> {
+    let tempchecks = ctx.data().db.values::<TempCheck>(TEMPCHECKS_TREE)?;
+
+    if tempchecks.is_empty() {
+        ctx.say("There haven't been any temperature checks yet.")
+            .await?;
+        return Ok(());
+    }
+
+    let pages = tempchecks
+        .iter()
+        .rev()
+        .take(HISTORY_SIZE)
+        .map(|tempcheck| {
+            let result = tempcheck.results.map_or_else(
+                || {
+                    Utc.timestamp_opt(tempcheck.closes_at, 0)
+                        .single()
+                        .map_or("Still open".to_owned(), |closes_at| {
+                            format!("Closes {}", closes_at.discord_relative_timestamp())
+                        })
+                },
+                summarize,
+            );
+
+            format!(
+                "**{}** https://discord.com/channels/{}/{}/{}\n{}",
+                tempcheck.question,
+                ctx.guild_id().map_or(0, |guild_id| guild_id.get()),
+                tempcheck.channel_id,
+                tempcheck.message_id,
+                result
+            )
+        })
+        .collect_vec();
+
+    poise::builtins::paginate(ctx, &pages.iter().map(String::as_str).collect_vec()).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn summarizes_votes() {
+        assert_eq!(summarize([3, 1, 1]), "👍 60% (3) · 🤷 20% (1) · 👎 20% (1)");
+        assert_eq!(summarize([0, 0, 0]), "Nobody voted.");
+        assert_eq!(summarize([1, 1, 1]), "👍 33% (1) · 🤷 33% (1) · 👎 33% (1)");
+    }
+
+    #[test]
+    fn finds_tempchecks_to_close() {
+        let db = KingFisherDb::temporary().unwrap();
+        let tempcheck = |message_id: u64, closes_at, results| TempCheck {
+            question: "Pizza?".to_owned(),
+            channel_id: 1,
+            message_id,
+            asked_by: 2,
+            closes_at,
+            results,
+        };
+
+        for tempcheck in [
+            tempcheck(1, 200, None),
+            tempcheck(2, 90, None),
+            tempcheck(3, 50, None),
+            tempcheck(4, 50, Some([1, 0, 0])),
+        ] {
+            db.insert(
+                TEMPCHECKS_TREE,
+                tempcheck.message_id.to_be_bytes(),
+                &tempcheck,
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            due_tempchecks(&db, 100)
+                .unwrap()
+                .iter()
+                .map(|tempcheck| tempcheck.message_id)
+                .collect_vec(),
+            vec![3, 2]
+        );
+    }
+}
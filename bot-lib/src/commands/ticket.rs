@@ -0,0 +1,91 @@
+use crate::{
+    data::PoiseContext,
+    tickets::{close_ticket, get_ticket, open_ticket},
+};
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{ChannelId, Mentionable, RoleId};
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("ticket_open", "ticket_close"),
+    subcommand_required,
+    description_localized("en-US", "Talk to the mods privately")
+)]
+pub async fn ticket(_ctx: PoiseContext<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Open a private thread with the mods
+#[poise::command(slash_command, ephemeral = true, rename = "open")]
+pub async fn ticket_open(
+    ctx: PoiseContext<'_>,
+    #[description = "What it's about"]
+    #[max_length = 200]
+    topic: String,
+) -> Result<()> {
+    let Some(settings) = ctx.data().config.read().await.tickets.clone() else {
+        ctx.say("Tickets aren't set up, message a mod directly.")
+            .await?;
+        return Ok(());
+    };
+
+    let ticket = open_ticket(
+        ctx.serenity_context(),
+        ctx.data(),
+        &settings,
+        ctx.author().id,
+        &topic,
+    )
+    .await?;
+
+    ctx.say(format!(
+        "Opened {}, the mods will be with you soon.",
+        ChannelId::new(ticket.thread_id).mention()
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Close this ticket and archive its transcript
+#[poise::command(slash_command, ephemeral = true, rename = "close")]
+pub async fn ticket_close(ctx: PoiseContext<'_>) -> Result<()> {
+    let Some(settings) = ctx.data().config.read().await.tickets.clone() else {
+        ctx.say("Tickets aren't set up.").await?;
+        return Ok(());
+    };
+
+    let Some(ticket) =
+        get_ticket(&ctx.data().db, ctx.channel_id())?.filter(|ticket| ticket.closed_at.is_none())
+    else {
+        ctx.say("This isn't an open ticket.").await?;
+        return Ok(());
+    };
+
+    let is_mod = ctx
+        .author_member()
+        .await
+        .is_some_and(|member| member.roles.contains(&RoleId::new(settings.mod_role_id)));
+
+    if !is_mod && ticket.user_id != ctx.author().id.get() {
+        ctx.say("Only the person who opened it or a mod can close it.")
+            .await?;
+        return Ok(());
+    }
+
+    // Answer before the thread gets locked
+    ctx.say("Closing, the transcript is going to the mods.")
+        .await?;
+
+    close_ticket(
+        ctx.serenity_context(),
+        ctx.data(),
+        &settings,
+        ticket,
+        ctx.author().id,
+    )
+    .await?;
+
+    Ok(())
+}
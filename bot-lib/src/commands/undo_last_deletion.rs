@@ -0,0 +1,49 @@
+use crate::class_directory::refresh_class_directory;
+use crate::class_info::save_class_info;
+use crate::commands::ensure_can_manage_role;
+use crate::data::PoiseContext;
+use crate::deleted_categories::{forget_deletion, last_deletion, restore_category};
+use color_eyre::eyre::{OptionExt, Result};
+
+#[poise::command(
+    slash_command,
+    required_permissions = "MANAGE_CHANNELS",
+    description_localized(
+        "en-US",
+        "Brings back the last deleted class category, without its messages"
+    )
+)]
+pub async fn undo_last_deletion(ctx: PoiseContext<'_>) -> Result<()> {
+    let guild = ctx.guild().ok_or_eyre("Couldn't get guild")?.id;
+
+    ensure_can_manage_role(ctx, None).await?;
+
+    let Some((key, deleted)) = last_deletion(&ctx.data().db)? else {
+        ctx.say("No class category was deleted in the last 24 hours.")
+            .await?;
+        return Ok(());
+    };
+
+    ctx.defer().await?;
+
+    // Only forgotten once it's back, so a failed restore can be tried again
+    let category_id = restore_category(ctx.serenity_context(), guild, &deleted).await?;
+    forget_deletion(&ctx.data().db, &key)?;
+
+    ctx.data()
+        .config
+        .write()
+        .await
+        .track_class_category(category_id)?;
+
+    save_class_info(&ctx.data().db, &deleted.info)?;
+    refresh_class_directory(ctx.serenity_context(), ctx.data(), guild).await?;
+
+    ctx.say(format!(
+        "Brought back CS {}. Members have to rejoin the class, and its emoji has to be made again.",
+        deleted.number
+    ))
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use crate::{
+    data::PoiseContext,
+    voice_activity::{
+        all_sessions, format_leaderboard, format_seconds, month_bounds, study_totals,
+    },
+};
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, Mentionable};
+
+#[poise::command(
+    slash_command,
+    ephemeral = true,
+    description_localized("en-US", "Shows time spent in voice channels and study rooms")
+)]
+pub async fn voice_stats(
+    ctx: PoiseContext<'_>,
+    #[description = "Whose stats to show, defaults to you"] user: Option<serenity::User>,
+) -> Result<()> {
+    let user = user.as_ref().unwrap_or(ctx.author());
+    let study_rooms = ctx.data().config.read().await.study_rooms.clone();
+    let sessions = all_sessions(ctx.data())?;
+
+    let total = sessions
+        .iter()
+        .filter(|session| session.user_id == user.id.get())
+        .map(|session| session.end - session.start)
+        .sum::<i64>();
+
+    let mut response = format!(
+        "{} has spent {} in voice channels.",
+        user.mention(),
+        format_seconds(total)
+    );
+
+    if let Some(study_rooms) = study_rooms {
+        let (from, to) = month_bounds(Utc::now().date_naive());
+        let totals = study_totals(
+            &sessions,
+            &study_rooms.channel_ids,
+            from.timestamp(),
+            to.timestamp(),
+        );
+
+        let studied = totals
+            .iter()
+            .find(|(user_id, _)| *user_id == user.id.get())
+            .map(|(_, seconds)| *seconds)
+            .unwrap_or_default();
+
+        response += &format!(
+            " {} of that was in study rooms this month.\n\n**Most studious this month**\n{}",
+            format_seconds(studied),
+            if totals.is_empty() {
+                "Nobody yet!".to_owned()
+            } else {
+                format_leaderboard(&totals)
+            }
+        );
+    }
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content(response)
+            .allowed_mentions(serenity::CreateAllowedMentions::new()),
+    )
+    .await?;
+
+    Ok(())
+}
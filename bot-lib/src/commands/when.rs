@@ -0,0 +1,67 @@
+use crate::{
+    data::PoiseContext,
+    datetime::{parse_when, When},
+    profile::get_profile,
+};
+use chrono::{Local, Offset, Utc};
+use color_eyre::eyre::Result;
+
+/// Discord's timestamp styles, and what they look like.
+const TIMESTAMP_STYLES: [(char, &str); 7] = [
+    ('t', "Short time"),
+    ('T', "Long time"),
+    ('d', "Short date"),
+    ('D', "Long date"),
+    ('f', "Short date/time"),
+    ('F', "Long date/time"),
+    ('R', "Relative"),
+];
+
+/// Turn a time like "Friday 3pm" into a timestamp that shows up in everyone's own timezone
+#[poise::command(slash_command, ephemeral = true)]
+pub async fn when(
+    ctx: PoiseContext<'_>,
+    #[description = "e.g. Friday 3pm, Dec 12 8am, in 2h, every Monday at noon"] time: String,
+) -> Result<()> {
+    let saved_timezone = get_profile(&ctx.data().db, ctx.author().id)?.timezone();
+    let timezone = saved_timezone.unwrap_or_else(|| Local::now().offset().fix());
+    let now = Utc::now().with_timezone(&timezone);
+
+    let parsed = parse_when(&time, now);
+
+    let Some(when) = parsed.and_then(|when| when.next(now)) else {
+        ctx.say(format!(
+            "Couldn't make sense of `{}`, try something like \"Friday 3pm\".",
+            time
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    let timestamp = when.timestamp();
+    let mut reply = TIMESTAMP_STYLES
+        .iter()
+        .map(|(style, name)| {
+            format!(
+                "{}: <t:{}:{}> `<t:{}:{}>`",
+                name, timestamp, style, timestamp, style
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if let Some(When::Every(_)) = parsed {
+        reply = format!("Next time:\n{}", reply);
+    }
+
+    if saved_timezone.is_none() {
+        reply.push_str(&format!(
+            "\n\nRead as UTC{}, set your own timezone with `/profile set timezone`.",
+            timezone
+        ));
+    }
+
+    ctx.say(reply).await?;
+
+    Ok(())
+}
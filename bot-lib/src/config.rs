@@ -1,20 +1,64 @@
+use crate::announcements::Announcements;
+use crate::attachment_archive::AttachmentArchive;
+use crate::auto_publish::AutoPublish;
+use crate::auto_slowmode::AutoSlowmode;
+use crate::auto_thread::AutoThread;
+use crate::booster_perks::BoosterPerks;
+use crate::capabilities::Capability;
+use crate::changelog::Changelog;
+use crate::class_archive::ClassArchive;
+use crate::class_directory::ClassDirectory;
+use crate::class_emoji::ClassEmoji;
+use crate::class_mentions::ClassMentionLimit;
+use crate::command_limits::CommandLimit;
+use crate::content_policy::ContentPolicy;
+use crate::custom_roles::CustomRoles;
+use crate::departments::Department;
+use crate::economy::Economy;
+use crate::emoji_sync::EmojiAssets;
+use crate::error_help::ErrorHelp;
+use crate::error_reporting::ErrorReporting;
+use crate::faq::FaqSuggestions;
+use crate::feature_flags::Feature;
+use crate::intents::Intents;
+use crate::introductions::Introductions;
+use crate::job_board::JobBoard;
 use crate::lang::ruleset::Ruleset;
+use crate::llm::Llm;
+use crate::mental_health::MentalHealth;
+use crate::mention_replies::MentionReplies;
+use crate::mirror::Mirror;
+use crate::moderation::Moderation;
+use crate::modmail::Modmail;
+use crate::name_policy::NamePolicy;
+use crate::ocr::Ocr;
+use crate::onboarding::Onboarding;
+use crate::presence::Presence;
+use crate::quiet_hours::QuietHours;
+use crate::rename_votes::RenameVotes;
+use crate::reposts::Reposts;
+use crate::role_expiry::TemporaryRole;
+use crate::seasons::{ResponsePack, Season};
+use crate::serious_gate::SeriousGate;
+use crate::server_themes::ServerTheme;
 use crate::starboard::Starboard;
-use chrono::{DateTime, Utc};
+use crate::starboard_export::StarboardExport;
+use crate::starboard_rewind::StarboardRewind;
+use crate::tickets::Tickets;
+use crate::toml_merge::merge_toml;
+use crate::unanswered_questions::UnansweredQuestions;
+use crate::voice::Soundboard;
+use crate::voice_activity::StudyRooms;
+use chrono::{DateTime, NaiveDate, Utc};
 use chrono::{Duration, Local};
 use color_eyre::eyre::{Result, WrapErr};
 use parking_lot::Mutex;
 use poise::serenity_prelude::ChannelId;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DurationSeconds};
+use std::collections::HashMap;
 use std::sync::Arc;
 
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
-pub struct ReactRole {
-    pub react: bool,
-    pub user_id: u64,
-}
-
 #[serde_as]
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Config {
@@ -41,17 +85,213 @@ pub struct Config {
     pub skip_hit_rate_text: String,
     /// Verbatim text to skip the duration check.
     /// Intentionally only a single string to prevent having to check a lot of different strings.
-    pub skip_duration_text: String,
+    /// Leave it out to never skip the cooldown.
+    pub skip_duration_text: Option<String>,
     /// The path to the config file.
     /// This is to allow for saving / reloading the config.
     #[serde(skip)]
     pub config_path: String,
-    /// Our own cache of members with the bot react role.
-    /// This may be rate limiting us, so we cache it.
+    /// When each response cooldown group was last triggered.
     #[serde(skip)]
-    pub bot_react_role_members: Vec<ReactRole>,
+    pub cooldown_groups: Mutex<HashMap<String, DateTime<Utc>>>,
     /// The list of class categories we currently support
+    #[serde(default)]
     pub class_categories: Vec<ChannelId>,
+    /// Where kingfisher keeps its database.
+    #[serde(default = "get_default_db_path")]
+    pub db_path: String,
+    /// Cooldowns and concurrency limits, keyed by command name.
+    #[serde(default)]
+    pub command_limits: HashMap<String, CommandLimit>,
+    /// When set, destructive commands only report what they would have done.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Where class channel history is exported to before deletion.
+    #[serde(default)]
+    pub class_archive: Option<ClassArchive>,
+    /// Post a weekly digest of the most popular messages in each class channel.
+    #[serde(default)]
+    pub class_digest: bool,
+    /// Ping helpers when questions in class channels go unanswered.
+    #[serde(default)]
+    pub unanswered_questions: Option<UnansweredQuestions>,
+    /// Channels where messages automatically get their own thread.
+    #[serde(default)]
+    pub auto_threads: Vec<AutoThread>,
+    /// The mod channel where reported messages are sent for triage.
+    pub report_channel_id: Option<u64>,
+    /// The mod only channel automated and privileged actions get logged to.
+    pub mod_log_channel_id: Option<u64>,
+    /// Tiered rules for removing rule breaking messages.
+    #[serde(default)]
+    pub moderation: Option<Moderation>,
+    /// Rules for member names, checked on join, on change, and daily.
+    #[serde(default)]
+    pub name_policy: Option<NamePolicy>,
+    /// Channels where images posted without alt text get a reminder.
+    #[serde(default)]
+    pub alt_text_channels: Vec<u64>,
+    /// The LLM backend, for features that need one.
+    #[serde(default)]
+    pub llm: Option<Llm>,
+    /// Study voice channels, for `/voice_stats` and the monthly shout-out.
+    #[serde(default)]
+    pub study_rooms: Option<StudyRooms>,
+    /// Where and when the end of semester starboard rewind gets posted.
+    #[serde(default)]
+    pub starboard_rewind: Option<StarboardRewind>,
+    /// Times when kingfisher doesn't respond to messages in certain channels.
+    #[serde(default)]
+    pub quiet_hours: Vec<QuietHours>,
+    /// Sounds for `/play`, only playable when built with the `voice` feature.
+    #[serde(default)]
+    pub soundboard: Option<Soundboard>,
+    /// Channels where links to messages get a quote of the message.
+    #[serde(default)]
+    pub link_preview_channels: Vec<u64>,
+    /// Announcement channels whose messages get published automatically.
+    #[serde(default)]
+    pub auto_publish: Vec<AutoPublish>,
+    /// Channels whose announcements get re-posted into other channels.
+    #[serde(default)]
+    pub mirrors: Vec<Mirror>,
+    /// Files of responses that are only active during a season.
+    #[serde(default)]
+    pub response_packs: Vec<ResponsePack>,
+    /// Roles given out to match `/profile set pronouns`, keyed by pronouns like `"they/them"`.
+    #[serde(default)]
+    pub pronoun_roles: HashMap<String, u64>,
+    /// Where new members introduce themselves.
+    #[serde(default)]
+    pub introductions: Option<Introductions>,
+    /// How often members who aren't mods can ping class roles.
+    #[serde(default)]
+    pub class_mention_limit: Option<ClassMentionLimit>,
+    /// How kingfisher answers when it's @mentioned.
+    #[serde(default)]
+    pub mention_replies: Option<MentionReplies>,
+    /// Help channels where questions like ones in the FAQ get the answer suggested.
+    #[serde(default)]
+    pub faq_suggestions: Option<FaqSuggestions>,
+    /// Reacting with this DMs you the message and saves it to `/bookmarks`.
+    /// Either a unicode emoji or `<:name:id>`.
+    #[serde(default)]
+    pub bookmark_emoji: Option<String>,
+    /// Users kingfisher never replies to, e.g. other bots or a mirror's webhook.
+    #[serde(default)]
+    pub ignored_users: Vec<u64>,
+    /// Members with any of these roles never get replies.
+    #[serde(default)]
+    pub ignored_roles: Vec<u64>,
+    /// Channels kingfisher never replies in, e.g. serious announcements.
+    #[serde(default)]
+    pub ignored_channels: Vec<u64>,
+    /// Bots whose messages kingfisher handles like anyone else's, every other bot is ignored.
+    #[serde(default)]
+    pub allowed_bots: Vec<u64>,
+    /// The most responses kingfisher sends in a channel per minute, whichever responses matched.
+    #[serde(default)]
+    pub response_burst_limit: Option<u32>,
+    /// The statuses kingfisher rotates through.
+    #[serde(default)]
+    pub presence: Option<Presence>,
+    /// The folder `/sync_emojis` keeps the server's emojis and stickers in sync with.
+    #[serde(default)]
+    pub emoji_assets: Option<EmojiAssets>,
+    /// Where copies of files posted in the `-resources` channels go.
+    #[serde(default)]
+    pub attachment_archive: Option<AttachmentArchive>,
+    /// Colors and icons for class roles, by department like `CS`.
+    #[serde(default)]
+    pub departments: HashMap<String, Department>,
+    /// Template for the per-class emojis `/create_class_category` can make.
+    #[serde(default)]
+    pub class_emoji: Option<ClassEmoji>,
+    /// Where kingfisher keeps a list of classes to join and leave.
+    #[serde(default)]
+    pub class_directory: Option<ClassDirectory>,
+    /// Where `/post_job` posts internships and jobs.
+    #[serde(default)]
+    pub job_board: Option<JobBoard>,
+    /// Which officers can compose announcements with `/announce compose`, and where.
+    #[serde(default)]
+    pub announcements: Option<Announcements>,
+    /// Roles that get removed again after a while.
+    #[serde(default)]
+    pub temporary_roles: Vec<TemporaryRole>,
+    /// Channels whose slowmode follows how busy they are.
+    #[serde(default)]
+    pub auto_slowmode: Vec<AutoSlowmode>,
+    /// Where to keep a static copy of the starboards for browsing outside Discord.
+    #[serde(default)]
+    pub starboard_export: Option<StarboardExport>,
+    /// Holds the fun responses back from messages that seem upset or serious.
+    #[serde(default)]
+    pub serious_gate: Option<SeriousGate>,
+    /// Counseling and crisis resources, and whether to look out for crisis language.
+    #[serde(default)]
+    pub mental_health: Option<MentalHealth>,
+    /// Channels each command is limited to, keyed by command name, e.g. fun commands in #bot-spam.
+    #[serde(default)]
+    pub command_channels: HashMap<String, Vec<u64>>,
+    /// Roles allowed to do things through kingfisher without the Discord permission for it.
+    #[serde(default)]
+    pub capabilities: HashMap<Capability, Vec<u64>>,
+    /// Release notes for `/changelog`, and where to announce new versions.
+    #[serde(default)]
+    pub changelog: Option<Changelog>,
+    /// Where panics and command errors get reported, if anywhere.
+    #[serde(default)]
+    pub error_reporting: Option<ErrorReporting>,
+    /// Which privileged intents to connect with.
+    #[serde(default)]
+    pub intents: Intents,
+    /// Never ask for message content, turning off everything that reads messages.
+    #[serde(default)]
+    pub privacy_mode: bool,
+    /// Text posted again in another channel within this many seconds doesn't get responses there.
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    #[serde(default = "get_default_cross_post_window")]
+    pub cross_post_window: Duration,
+    /// Icons, banners and role colors for parts of the year, like finals week or game day.
+    #[serde(default)]
+    pub server_themes: Vec<ServerTheme>,
+    /// A currency earned by chatting, spent in `/shop`.
+    #[serde(default)]
+    pub economy: Option<Economy>,
+    /// Personal vanity roles for boosters and members with level roles.
+    #[serde(default)]
+    pub custom_roles: Option<CustomRoles>,
+    /// Roles, soundboard access and a shout out for server boosters.
+    #[serde(default)]
+    pub booster_perks: Option<BoosterPerks>,
+    /// Year roles and whether new classes can be added from `/setup_classes`.
+    #[serde(default)]
+    pub onboarding: Option<Onboarding>,
+    /// Private threads for talking to the mods, with transcripts when they're closed.
+    #[serde(default)]
+    pub tickets: Option<Tickets>,
+    /// Relays DMs to staff threads and staff replies back.
+    #[serde(default)]
+    pub modmail: Option<Modmail>,
+    /// What's allowed in particular channels, like images only in #memes.
+    #[serde(default)]
+    pub content_policies: Vec<ContentPolicy>,
+    /// Reacts to images that were already posted, for meme channels.
+    #[serde(default)]
+    pub reposts: Option<Reposts>,
+    /// Reads text out of images, so responses can match screenshots too.
+    #[serde(default)]
+    pub ocr: Option<Ocr>,
+    /// Explains pasted and screenshotted errors in help channels.
+    #[serde(default)]
+    pub error_help: Option<ErrorHelp>,
+    /// How long `/rename_vote` runs and how many votes it needs.
+    #[serde(default)]
+    pub rename_votes: Option<RenameVotes>,
+    /// Features to switch off, e.g. `llm = false`. `/feature` overrides these.
+    #[serde(default)]
+    pub features: HashMap<Feature, bool>,
 }
 
 impl PartialEq for Config {
@@ -65,6 +305,71 @@ impl PartialEq for Config {
             && self.skip_hit_rate_text == other.skip_hit_rate_text
             && self.config_path == other.config_path
             && self.class_categories == other.class_categories
+            && self.db_path == other.db_path
+            && self.command_limits == other.command_limits
+            && self.dry_run == other.dry_run
+            && self.class_archive == other.class_archive
+            && self.class_digest == other.class_digest
+            && self.unanswered_questions == other.unanswered_questions
+            && self.auto_threads == other.auto_threads
+            && self.report_channel_id == other.report_channel_id
+            && self.mod_log_channel_id == other.mod_log_channel_id
+            && self.moderation == other.moderation
+            && self.name_policy == other.name_policy
+            && self.alt_text_channels == other.alt_text_channels
+            && self.llm == other.llm
+            && self.study_rooms == other.study_rooms
+            && self.starboard_rewind == other.starboard_rewind
+            && self.quiet_hours == other.quiet_hours
+            && self.soundboard == other.soundboard
+            && self.link_preview_channels == other.link_preview_channels
+            && self.auto_publish == other.auto_publish
+            && self.mirrors == other.mirrors
+            && self.response_packs == other.response_packs
+            && self.pronoun_roles == other.pronoun_roles
+            && self.introductions == other.introductions
+            && self.class_mention_limit == other.class_mention_limit
+            && self.mention_replies == other.mention_replies
+            && self.faq_suggestions == other.faq_suggestions
+            && self.bookmark_emoji == other.bookmark_emoji
+            && self.ignored_users == other.ignored_users
+            && self.ignored_roles == other.ignored_roles
+            && self.ignored_channels == other.ignored_channels
+            && self.allowed_bots == other.allowed_bots
+            && self.response_burst_limit == other.response_burst_limit
+            && self.presence == other.presence
+            && self.emoji_assets == other.emoji_assets
+            && self.attachment_archive == other.attachment_archive
+            && self.departments == other.departments
+            && self.class_emoji == other.class_emoji
+            && self.class_directory == other.class_directory
+            && self.job_board == other.job_board
+            && self.announcements == other.announcements
+            && self.temporary_roles == other.temporary_roles
+            && self.auto_slowmode == other.auto_slowmode
+            && self.starboard_export == other.starboard_export
+            && self.serious_gate == other.serious_gate
+            && self.mental_health == other.mental_health
+            && self.command_channels == other.command_channels
+            && self.capabilities == other.capabilities
+            && self.changelog == other.changelog
+            && self.error_reporting == other.error_reporting
+            && self.intents == other.intents
+            && self.privacy_mode == other.privacy_mode
+            && self.cross_post_window == other.cross_post_window
+            && self.server_themes == other.server_themes
+            && self.economy == other.economy
+            && self.custom_roles == other.custom_roles
+            && self.booster_perks == other.booster_perks
+            && self.onboarding == other.onboarding
+            && self.tickets == other.tickets
+            && self.modmail == other.modmail
+            && self.content_policies == other.content_policies
+            && self.reposts == other.reposts
+            && self.ocr == other.ocr
+            && self.error_help == other.error_help
+            && self.rename_votes == other.rename_votes
+            && self.features == other.features
     }
 }
 
@@ -74,15 +379,80 @@ impl Default for Config {
             default_text_detect_cooldown: get_default_text_detect_cooldown(),
             starboards: vec![],
             guild_id: 0,
-            skip_duration_text: "".to_owned(),
+            skip_duration_text: None,
             help_text: None,
             bot_react_role_id: 0,
             responses: vec![],
             default_hit_rate: 1.,
             skip_hit_rate_text: "".to_owned(),
             config_path: "".to_owned(),
-            bot_react_role_members: vec![],
+            cooldown_groups: Mutex::new(HashMap::new()),
             class_categories: vec![],
+            db_path: get_default_db_path(),
+            command_limits: HashMap::new(),
+            dry_run: false,
+            class_archive: None,
+            class_digest: false,
+            unanswered_questions: None,
+            auto_threads: vec![],
+            report_channel_id: None,
+            mod_log_channel_id: None,
+            moderation: None,
+            name_policy: None,
+            alt_text_channels: vec![],
+            llm: None,
+            study_rooms: None,
+            starboard_rewind: None,
+            quiet_hours: vec![],
+            soundboard: None,
+            link_preview_channels: vec![],
+            auto_publish: vec![],
+            mirrors: vec![],
+            response_packs: vec![],
+            pronoun_roles: HashMap::new(),
+            introductions: None,
+            class_mention_limit: None,
+            mention_replies: None,
+            faq_suggestions: None,
+            bookmark_emoji: None,
+            ignored_users: vec![],
+            ignored_roles: vec![],
+            ignored_channels: vec![],
+            allowed_bots: vec![],
+            response_burst_limit: None,
+            presence: None,
+            emoji_assets: None,
+            attachment_archive: None,
+            departments: HashMap::new(),
+            class_emoji: None,
+            class_directory: None,
+            job_board: None,
+            announcements: None,
+            temporary_roles: vec![],
+            auto_slowmode: vec![],
+            starboard_export: None,
+            serious_gate: None,
+            mental_health: None,
+            command_channels: HashMap::new(),
+            capabilities: HashMap::new(),
+            changelog: None,
+            error_reporting: None,
+            intents: Intents::default(),
+            privacy_mode: false,
+            cross_post_window: get_default_cross_post_window(),
+            server_themes: vec![],
+            economy: None,
+            custom_roles: None,
+            booster_perks: None,
+            onboarding: None,
+            tickets: None,
+            modmail: None,
+            content_policies: vec![],
+            reposts: None,
+            ocr: None,
+            error_help: None,
+            rename_votes: None,
+            features: HashMap::new(),
         }
     }
 }
@@ -92,7 +462,11 @@ impl Config {
     pub fn create_from_file(config_path: &str) -> Result<Config> {
         let file = std::fs::read_to_string(config_path).wrap_err("Could not read config file")?;
 
-        let config = toml::from_str(&file).wrap_err("Could not parse config file")?;
+        let mut config: Config = toml::from_str(&file).wrap_err("Could not parse config file")?;
+
+        for pack in &mut config.response_packs {
+            pack.load()?;
+        }
 
         Ok(Config {
             config_path: config_path.to_owned(),
@@ -100,16 +474,80 @@ impl Config {
         })
     }
 
-    /// Reloads the config file and updates the configuration.
-    pub fn reload(&mut self) {
-        if let Ok(config) = Config::create_from_file(&self.config_path) {
-            *self = config;
+    /// Reloads the config file and updates the configuration, keeping the old one if the file
+    /// doesn't parse.
+    pub fn reload(&mut self) -> Result<()> {
+        *self = Config::create_from_file(&self.config_path)?;
+
+        Ok(())
+    }
+
+    /// Every response, including ones out of season.
+    pub fn all_responses(&self) -> impl Iterator<Item = &RegisteredResponse> {
+        self.responses
+            .iter()
+            .chain(self.response_packs.iter().flat_map(|pack| &pack.responses))
+    }
+
+    /// The responses that can trigger on the given day.
+    pub fn active_responses(&self, date: NaiveDate) -> impl Iterator<Item = &RegisteredResponse> {
+        self.responses
+            .iter()
+            .chain(
+                self.response_packs
+                    .iter()
+                    .filter(move |pack| pack.season.contains(date))
+                    .flat_map(|pack| &pack.responses),
+            )
+            .filter(move |response| response.is_in_season(date))
+    }
+
+    /// Whether text detection should leave a message alone, before looking at any responses.
+    pub fn is_ignored(&self, user_id: u64, role_ids: &[u64], channel_id: u64) -> bool {
+        self.ignored_users.contains(&user_id)
+            || self.ignored_channels.contains(&channel_id)
+            || role_ids
+                .iter()
+                .any(|role_id| self.ignored_roles.contains(role_id))
+    }
+
+    /// Whether messages come with their content, without it most message features are skipped.
+    pub fn reads_message_content(&self) -> bool {
+        self.intents.message_content && !self.privacy_mode
+    }
+
+    /// Adds a category to `class_categories`, saving the config if it wasn't there yet.
+    pub fn track_class_category(&mut self, category_id: ChannelId) -> Result<()> {
+        if self.class_categories.contains(&category_id) {
+            return Ok(());
         }
+
+        self.class_categories.push(category_id);
+        self.save()
+    }
+
+    /// Removes a category from `class_categories`, saving the config if it was there.
+    pub fn untrack_class_category(&mut self, category_id: ChannelId) -> Result<()> {
+        if !self.class_categories.contains(&category_id) {
+            return Ok(());
+        }
+
+        self.class_categories.retain(|id| *id != category_id);
+        self.save()
     }
 
+    /// Writes the config back to its file, keeping the file's comments and formatting.
     pub fn save(&self) -> Result<()> {
         let toml = toml::to_string(&self).wrap_err("Could not serialize config")?;
 
+        let toml = match std::fs::read_to_string(&self.config_path) {
+            Ok(existing) => merge_toml(&existing, &toml).unwrap_or_else(|e| {
+                tracing::warn!("Couldn't keep the config's formatting: {:?}", e);
+                toml
+            }),
+            Err(_) => toml,
+        };
+
         std::fs::write(&self.config_path, toml).wrap_err("Could not save config")
     }
 }
@@ -121,6 +559,14 @@ const fn get_default_text_detect_cooldown() -> Duration {
     }
 }
 
+fn get_default_cross_post_window() -> Duration {
+    Duration::seconds(10)
+}
+
+fn get_default_db_path() -> String {
+    "kingfisher.db".to_owned()
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Default)]
 #[serde(untagged)]
 pub enum ResponseKind {
@@ -131,10 +577,26 @@ pub enum ResponseKind {
     Text { content: String },
     /// A random text response.
     RandomText { content: Vec<String> },
+    /// A random text response, tracking which variant gets the most reactions and replies.
+    Variants { variants: Vec<String> },
     /// An image response.
     Image { path: String },
     /// A text and image response.
     TextAndImage { content: String, path: String },
+    /// A random image (or gif) from a directory, picked when the response triggers.
+    RandomImage { dir: String },
+    /// A sound played in the author's voice channel.
+    Sound {
+        #[serde(rename = "sound")]
+        path: String,
+    },
+}
+
+/// A character a response can be sent as, instead of kingfisher itself.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Persona {
+    pub name: String,
+    pub avatar_url: Option<String>,
 }
 
 #[serde_as]
@@ -163,6 +625,13 @@ pub struct RegisteredResponse {
     /// Whether or not the response can be skipped via the `skip_hit_rate_text` config option.
     #[serde(default)]
     unskippable: bool,
+    /// Responses in the same group share when they were last triggered,
+    /// so triggering one puts the others on cooldown too.
+    cooldown_group: Option<String>,
+    /// Who the response looks like it came from. Sent through a webhook, so it isn't a reply.
+    persona: Option<Persona>,
+    /// Only trigger during this part of the year.
+    season: Option<Season>,
 }
 
 impl PartialEq for RegisteredResponse {
@@ -172,6 +641,9 @@ impl PartialEq for RegisteredResponse {
             && self.ruleset == other.ruleset
             && self.message_response == other.message_response
             && self.cooldown == other.cooldown
+            && self.cooldown_group == other.cooldown_group
+            && self.persona == other.persona
+            && self.season == other.season
     }
 }
 
@@ -180,27 +652,62 @@ fn default_time() -> Mutex<DateTime<Utc>> {
 }
 
 impl RegisteredResponse {
-    pub fn find_valid_response(
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn message_response(&self) -> Arc<ResponseKind> {
+        Arc::clone(&self.message_response)
+    }
+
+    pub fn persona(&self) -> Option<Persona> {
+        self.persona.clone()
+    }
+
+    pub fn season(&self) -> Option<Season> {
+        self.season
+    }
+
+    /// Whether the message matches the ruleset, regardless of hit rate and cooldown.
+    pub fn matches(&self, input: &str) -> bool {
+        self.ruleset.matches(input)
+    }
+
+    pub fn is_in_season(&self, date: NaiveDate) -> bool {
+        self.season.is_none_or(|season| season.contains(date))
+    }
+
+    /// Lets the response (and the rest of its cooldown group) trigger again right away.
+    pub fn reset_cooldown(&self, config: &Config) {
+        *self.last_triggered.lock() = DateTime::<Utc>::MIN_UTC;
+
+        if let Some(group) = &self.cooldown_group {
+            config.cooldown_groups.lock().remove(group);
+        }
+    }
+
+    /// Whether the response is off cooldown, or the message skips the cooldown.
+    fn is_off_cooldown(
         &self,
         input: &str,
-        Config {
-            default_text_detect_cooldown: global_cooldown,
-            skip_hit_rate_text,
-            default_hit_rate,
-            skip_duration_text,
-            ..
-        }: &Config,
+        last_triggered: DateTime<Utc>,
+        cooldown_groups: &HashMap<String, DateTime<Utc>>,
+        config: &Config,
         message_link: &str,
-    ) -> Option<Arc<ResponseKind>> {
-        if !self.ruleset.matches(input) {
-            return None;
-        }
-
-        let mut last_triggered = self.last_triggered.lock();
-        let cooldown = self.cooldown.unwrap_or(*global_cooldown);
-        let time_since_last_triggered = Utc::now() - *last_triggered;
+    ) -> bool {
+        let group_last_triggered = self
+            .cooldown_group
+            .as_ref()
+            .and_then(|group| cooldown_groups.get(group))
+            .copied()
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let cooldown = self.cooldown.unwrap_or(config.default_text_detect_cooldown);
+        let time_since_last_triggered = Utc::now() - last_triggered.max(group_last_triggered);
         let allowed = time_since_last_triggered > cooldown;
-        let blocked = !input.contains(skip_duration_text);
+        let blocked = !config
+            .skip_duration_text
+            .as_deref()
+            .is_some_and(|skip| !skip.is_empty() && input.contains(skip));
 
         if !allowed && blocked {
             tracing::debug!(
@@ -210,13 +717,42 @@ impl RegisteredResponse {
                 cooldown - time_since_last_triggered
             );
 
+            return false;
+        }
+
+        true
+    }
+
+    /// The response, if it matches and passes its cooldown and hit rate.
+    ///
+    /// Doesn't start the cooldown, so the response can still be held back. Call
+    /// [`RegisteredResponse::mark_triggered`] once it's really being sent.
+    pub fn find_valid_response(
+        &self,
+        input: &str,
+        config: &Config,
+        message_link: &str,
+    ) -> Option<(Arc<str>, Arc<ResponseKind>, Option<Persona>)> {
+        if !self.matches(input) {
+            return None;
+        }
+
+        let off_cooldown = self.is_off_cooldown(
+            input,
+            *self.last_triggered.lock(),
+            &config.cooldown_groups.lock(),
+            config,
+            message_link,
+        );
+
+        if !off_cooldown {
             return None;
         }
 
         let now = Local::now().format("%Y-%m-%d %H:%M:%S");
-        let hit_rate = self.hit_rate.unwrap_or(*default_hit_rate);
+        let hit_rate = self.hit_rate.unwrap_or(config.default_hit_rate);
         let miss = rand::random::<f64>() > hit_rate;
-        let blocked = self.unskippable || !input.contains(skip_hit_rate_text);
+        let blocked = self.unskippable || !input.contains(&config.skip_hit_rate_text);
 
         if miss && blocked {
             tracing::debug!("Miss `{}` {} {}", self.name, message_link, now);
@@ -225,9 +761,37 @@ impl RegisteredResponse {
 
         tracing::debug!("Hit `{}` {} {}", self.name, message_link, now);
 
+        Some((
+            Arc::clone(&self.name),
+            Arc::clone(&self.message_response),
+            self.persona.clone(),
+        ))
+    }
+
+    /// Starts the cooldown of the response (and its group).
+    ///
+    /// Returns false if another message triggered it since it was found, so only one gets sent.
+    pub fn mark_triggered(&self, input: &str, config: &Config, message_link: &str) -> bool {
+        let mut last_triggered = self.last_triggered.lock();
+        let mut cooldown_groups = config.cooldown_groups.lock();
+
+        if !self.is_off_cooldown(
+            input,
+            *last_triggered,
+            &cooldown_groups,
+            config,
+            message_link,
+        ) {
+            return false;
+        }
+
         *last_triggered = Utc::now();
 
-        Some(Arc::clone(&self.message_response))
+        if let Some(group) = &self.cooldown_group {
+            cooldown_groups.insert(group.clone(), *last_triggered);
+        }
+
+        true
     }
 }
 
@@ -284,10 +848,269 @@ content = "literally 1984""#;
                     last_triggered: Mutex::new(DateTime::<Utc>::MIN_UTC),
                     cooldown: None,
                     unskippable: false,
+                    cooldown_group: None,
+                    persona: None,
+                    season: None,
                 }],
                 skip_hit_rate_text: "kf please".to_owned(),
                 ..Default::default()
             }
         );
     }
+
+    #[test]
+    fn cooldown_groups_share_cooldown() {
+        let response = |name: &str, group: Option<&str>| RegisteredResponse {
+            name: name.into(),
+            hit_rate: None,
+            ruleset: fast_ruleset!("r meme"),
+            message_response: Arc::new(ResponseKind::None),
+            last_triggered: default_time(),
+            cooldown: None,
+            unskippable: false,
+            cooldown_group: group.map(str::to_owned),
+            persona: None,
+            season: None,
+        };
+
+        let config = Config {
+            responses: vec![
+                response("a", Some("memes")),
+                response("b", Some("memes")),
+                response("c", None),
+            ],
+            skip_hit_rate_text: "kf please".to_owned(),
+            skip_duration_text: Some("kf skip".to_owned()),
+            ..Default::default()
+        };
+
+        let trigger = |i: usize| {
+            config.responses[i]
+                .find_valid_response("meme", &config, "")
+                .is_some()
+                && config.responses[i].mark_triggered("meme", &config, "")
+        };
+
+        assert!(trigger(0));
+        assert!(!trigger(1));
+        assert!(trigger(2));
+
+        config.responses[1].reset_cooldown(&config);
+
+        assert!(!trigger(0));
+        assert!(trigger(1));
+    }
+
+    #[test]
+    fn finding_a_response_leaves_it_ready() {
+        let config = Config {
+            responses: vec![RegisteredResponse {
+                name: "a".into(),
+                hit_rate: None,
+                ruleset: fast_ruleset!("r meme"),
+                message_response: Arc::new(ResponseKind::None),
+                last_triggered: default_time(),
+                cooldown: None,
+                unskippable: false,
+                cooldown_group: None,
+                persona: None,
+                season: None,
+            }],
+            skip_hit_rate_text: "kf please".to_owned(),
+            skip_duration_text: Some("kf skip".to_owned()),
+            ..Default::default()
+        };
+        let response = &config.responses[0];
+
+        // Held back responses don't go on cooldown
+        assert!(response.find_valid_response("meme", &config, "").is_some());
+        assert!(response.find_valid_response("meme", &config, "").is_some());
+
+        // Only the first of two messages found at once gets sent
+        assert!(response.mark_triggered("meme", &config, ""));
+        assert!(!response.mark_triggered("meme", &config, ""));
+        assert!(response.find_valid_response("meme", &config, "").is_none());
+    }
+
+    #[test]
+    fn cooldowns_apply_without_skip_text() {
+        let config: Config = toml::from_str(
+            r#"
+bot_react_role_id = 1
+default_hit_rate = 1.0
+guild_id = 1
+skip_hit_rate_text = "kf please"
+starboards = []
+
+[[responses]]
+name = "meme"
+ruleset = "r meme"
+content = "meme""#,
+        )
+        .unwrap();
+        let response = &config.responses[0];
+
+        assert_eq!(config.skip_duration_text, None);
+        assert!(response.find_valid_response("meme", &config, "").is_some());
+        assert!(response.mark_triggered("meme", &config, ""));
+        assert!(response.find_valid_response("meme", &config, "").is_none());
+    }
+
+    #[test]
+    fn ignores_users_roles_and_channels() {
+        let config = Config {
+            ignored_users: vec![1],
+            ignored_roles: vec![2],
+            ignored_channels: vec![3],
+            ..Default::default()
+        };
+
+        assert!(config.is_ignored(1, &[], 10));
+        assert!(config.is_ignored(10, &[5, 2], 10));
+        assert!(config.is_ignored(10, &[], 3));
+        assert!(!config.is_ignored(10, &[5], 10));
+    }
+
+    #[test]
+    fn tracks_class_categories_in_the_file() {
+        let config_path = std::env::temp_dir()
+            .join(format!("kingfisher-config-{}.toml", std::process::id()))
+            .display()
+            .to_string();
+        let mut config = Config {
+            config_path: config_path.clone(),
+            ..Default::default()
+        };
+
+        config.track_class_category(ChannelId::new(1)).unwrap();
+        config.track_class_category(ChannelId::new(2)).unwrap();
+        config.track_class_category(ChannelId::new(1)).unwrap();
+        assert_eq!(
+            Config::create_from_file(&config_path)
+                .unwrap()
+                .class_categories,
+            vec![ChannelId::new(1), ChannelId::new(2)]
+        );
+
+        config.untrack_class_category(ChannelId::new(1)).unwrap();
+        assert_eq!(
+            Config::create_from_file(&config_path)
+                .unwrap()
+                .class_categories,
+            vec![ChannelId::new(2)]
+        );
+
+        std::fs::remove_file(config_path).unwrap();
+    }
+
+    #[test]
+    fn deserializes_random_image_response() {
+        let response: RegisteredResponse = toml::from_str(
+            r#"
+name = "memes"
+ruleset = "r meme"
+dir = "assets/memes"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            *response.message_response,
+            ResponseKind::RandomImage {
+                dir: "assets/memes".to_owned()
+            }
+        );
+    }
+
+    #[test]
+    fn deserializes_variants_response() {
+        let response: RegisteredResponse = toml::from_str(
+            r#"
+name = "greeting"
+ruleset = "r hello"
+variants = ["hi", "hey there"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            *response.message_response,
+            ResponseKind::Variants {
+                variants: vec!["hi".to_owned(), "hey there".to_owned()]
+            }
+        );
+    }
+
+    #[test]
+    fn deserializes_persona() {
+        let response: RegisteredResponse = toml::from_str(
+            r#"
+name = "wizard"
+ruleset = "r magic"
+content = "You shall not pass"
+persona = { name = "Gandalf", avatar_url = "https://example.com/gandalf.png" }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            response.persona,
+            Some(Persona {
+                name: "Gandalf".to_owned(),
+                avatar_url: Some("https://example.com/gandalf.png".to_owned()),
+            })
+        );
+        assert_eq!(
+            *response.message_response,
+            ResponseKind::Text {
+                content: "You shall not pass".to_owned()
+            }
+        );
+    }
+
+    #[test]
+    fn only_seasonal_responses_in_season_are_active() {
+        let response = |name: &str, season: Option<&str>| {
+            toml::from_str::<RegisteredResponse>(&format!(
+                "name = \"{}\"\nruleset = \"r meme\"\ncontent = \"meme\"\n{}",
+                name,
+                season.unwrap_or_default()
+            ))
+            .unwrap()
+        };
+        let halloween: Season = toml::from_str(
+            r#"start = "10-25"
+end = "10-31""#,
+        )
+        .unwrap();
+
+        let config = Config {
+            responses: vec![
+                response("always", None),
+                response(
+                    "april fools",
+                    Some(r#"season = { start = "04-01", end = "04-01" }"#),
+                ),
+            ],
+            response_packs: vec![ResponsePack {
+                name: "halloween".to_owned(),
+                season: halloween,
+                path: "".to_owned(),
+                responses: vec![response("spooky", None)],
+            }],
+            ..Default::default()
+        };
+
+        let active = |month: u32, day: u32| {
+            config
+                .active_responses(NaiveDate::from_ymd_opt(2024, month, day).unwrap())
+                .map(RegisteredResponse::name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(active(4, 1), vec!["always", "april fools"]);
+        assert_eq!(active(10, 31), vec!["always", "spooky"]);
+        assert_eq!(active(6, 1), vec!["always"]);
+        assert_eq!(config.all_responses().count(), 3);
+    }
 }
@@ -0,0 +1,298 @@
+use crate::{author_guard::is_from_human, data::AppState, mod_log::mod_log};
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{Result, WrapErr};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{self as serenity, Mentionable, Permissions, UserId};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// How long the explanation stays up before it cleans itself up.
+const NOTICE_LIFETIME: std::time::Duration = std::time::Duration::from_secs(10);
+/// This many removals within a day gets a member reported to the mods.
+const REPORT_AFTER: usize = 3;
+/// Members get told why their message was removed at most once in this many seconds.
+const NOTICE_WINDOW: i64 = 60;
+
+lazy_static! {
+    static ref LINK: Regex = Regex::new(r"https?://\S+").expect("Link regex should be valid");
+    static ref IMAGE_LINK: Regex = Regex::new(
+        r"(?i)https?://(?:\S+\.(?:png|jpe?g|gif|webp)(?:\?\S*)?|(?:www\.)?(?:tenor|giphy)\.com/\S+)"
+    )
+    .expect("Image link regex should be valid");
+    static ref VIOLATIONS: DashMap<UserId, Vec<DateTime<Utc>>> = DashMap::new();
+    static ref LAST_NOTICE: DashMap<UserId, DateTime<Utc>> = DashMap::new();
+}
+
+/// What's allowed in a channel, like images only in #memes.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ContentPolicy {
+    pub channel_id: u64,
+    #[serde(default)]
+    pub rule: Option<ContentRule>,
+    /// Longest message allowed, in characters.
+    #[serde(default)]
+    pub max_length: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentRule {
+    /// Every message needs a link, text around it is fine.
+    LinksOnly,
+    /// Every message needs an image, attached or linked.
+    ImagesOnly,
+    NoLinks,
+}
+
+/// Just what the policy needs to know about a message.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageContent<'a> {
+    pub text: &'a str,
+    pub image_attachments: usize,
+}
+
+impl<'a> From<&'a serenity::Message> for MessageContent<'a> {
+    fn from(message: &'a serenity::Message) -> Self {
+        MessageContent {
+            text: &message.content,
+            image_attachments: message
+                .attachments
+                .iter()
+                .filter(|attachment| {
+                    attachment
+                        .content_type
+                        .as_deref()
+                        .is_some_and(|kind| kind.starts_with("image/"))
+                })
+                .count(),
+        }
+    }
+}
+
+impl ContentPolicy {
+    /// Why the message isn't allowed, if it isn't.
+    pub fn violation(&self, content: &MessageContent) -> Option<String> {
+        if let Some(max_length) = self.max_length {
+            if content.text.chars().count() > max_length {
+                return Some(format!(
+                    "messages here can be at most {} characters",
+                    max_length
+                ));
+            }
+        }
+
+        match self.rule? {
+            ContentRule::LinksOnly if !LINK.is_match(content.text) => {
+                Some("this channel is for links".to_owned())
+            }
+            ContentRule::ImagesOnly
+                if content.image_attachments == 0 && !IMAGE_LINK.is_match(content.text) =>
+            {
+                Some("this channel is for images".to_owned())
+            }
+            ContentRule::NoLinks if LINK.is_match(content.text) => {
+                Some("links aren't allowed here".to_owned())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Records a removal, returning true the first time the member reaches the report threshold.
+fn record_violation(user_id: UserId, now: DateTime<Utc>) -> bool {
+    // Forget everyone's old removals, not just this member's, so the map doesn't keep growing
+    VIOLATIONS.retain(|_, violations| {
+        violations.retain(|at| now - *at < Duration::days(1));
+        !violations.is_empty()
+    });
+
+    let mut violations = VIOLATIONS.entry(user_id).or_default();
+    violations.push(now);
+
+    violations.len() == REPORT_AFTER
+}
+
+/// Whether the member should be told about a removal, so a burst of removed messages only gets
+/// one notice. If so, it counts as told now.
+fn claim_notice(user_id: UserId, now: DateTime<Utc>) -> bool {
+    let window = Duration::seconds(NOTICE_WINDOW);
+    LAST_NOTICE.retain(|_, at| now - *at < window);
+
+    if LAST_NOTICE.contains_key(&user_id) {
+        return false;
+    }
+
+    LAST_NOTICE.insert(user_id, now);
+    true
+}
+
+/// Removes messages that break their channel's policy. Returns true if the message was deleted.
+pub async fn enforce_content_policy(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<bool> {
+    if !is_from_human(ctx, data, message).await {
+        return Ok(false);
+    }
+
+    let Some(policy) = data
+        .config
+        .read()
+        .await
+        .content_policies
+        .iter()
+        .find(|policy| policy.channel_id == message.channel_id.get())
+        .cloned()
+    else {
+        return Ok(false);
+    };
+
+    let Some(reason) = policy.violation(&MessageContent::from(message)) else {
+        return Ok(false);
+    };
+
+    let is_mod = match (message.guild_id, &message.member) {
+        (Some(guild_id), Some(member)) => ctx.cache.guild(guild_id).is_some_and(|guild| {
+            member
+                .roles
+                .iter()
+                .filter_map(|role_id| guild.roles.get(role_id))
+                .fold(Permissions::empty(), |acc, role| acc | role.permissions)
+                .intersects(Permissions::MANAGE_MESSAGES | Permissions::ADMINISTRATOR)
+        }),
+        _ => false,
+    };
+
+    if is_mod {
+        return Ok(false);
+    }
+
+    message
+        .delete(ctx)
+        .await
+        .wrap_err("Couldn't delete message")?;
+
+    if claim_notice(message.author.id, Utc::now()) {
+        let notice = message
+            .channel_id
+            .say(
+                ctx,
+                format!(
+                    "{}, your message was removed: {}.",
+                    message.author.mention(),
+                    reason
+                ),
+            )
+            .await?;
+        let http = ctx.http.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(NOTICE_LIFETIME).await;
+            let _ = notice.delete(&http).await;
+        });
+    }
+
+    if record_violation(message.author.id, Utc::now()) {
+        mod_log(
+            ctx,
+            data,
+            serenity::CreateEmbed::new()
+                .title("Repeated content policy violations")
+                .description(format!(
+                    "{} has had {} messages removed from {} and other channels today, most \
+                     recently because {}.",
+                    message.author.mention(),
+                    REPORT_AFTER,
+                    message.channel_id.mention(),
+                    reason
+                ))
+                .color(serenity::Color::ORANGE),
+        )
+        .await?;
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn policy(rule: Option<ContentRule>, max_length: Option<usize>) -> ContentPolicy {
+        ContentPolicy {
+            channel_id: 1,
+            rule,
+            max_length,
+        }
+    }
+
+    fn text(text: &str) -> MessageContent<'_> {
+        MessageContent {
+            text,
+            image_attachments: 0,
+        }
+    }
+
+    #[test]
+    fn enforces_each_rule() {
+        let links = policy(Some(ContentRule::LinksOnly), None);
+        assert!(links.violation(&text("nice notes")).is_some());
+        assert!(links
+            .violation(&text("notes: https://example.com/notes.pdf"))
+            .is_none());
+
+        let images = policy(Some(ContentRule::ImagesOnly), None);
+        assert!(images.violation(&text("lol")).is_some());
+        assert!(images
+            .violation(&text("https://example.com/article"))
+            .is_some());
+        assert!(images
+            .violation(&text("https://cdn.example.com/meme.PNG?size=2"))
+            .is_none());
+        assert!(images
+            .violation(&text("https://tenor.com/view/cat-123"))
+            .is_none());
+        assert!(images
+            .violation(&MessageContent {
+                text: "caption",
+                image_attachments: 1
+            })
+            .is_none());
+
+        let no_links = policy(Some(ContentRule::NoLinks), Some(10));
+        assert!(no_links.violation(&text("http://x.co")).is_some());
+        assert!(no_links.violation(&text("short")).is_none());
+        assert!(no_links.violation(&text("a bit too long")).is_some());
+    }
+
+    #[test]
+    fn reports_repeat_offenders_once() {
+        let now = Utc::now();
+        let user_id = UserId::new(4001);
+
+        assert!(!record_violation(user_id, now - Duration::days(2)));
+        assert!(!record_violation(user_id, now));
+        assert!(!record_violation(user_id, now));
+        assert!(record_violation(user_id, now));
+        assert!(!record_violation(user_id, now));
+
+        record_violation(UserId::new(4002), now - Duration::days(2));
+        record_violation(UserId::new(4003), now);
+        assert!(!VIOLATIONS.contains_key(&UserId::new(4002)));
+    }
+
+    #[test]
+    fn notices_once_per_window() {
+        let now = Utc::now();
+        let user_id = UserId::new(5001);
+
+        assert!(claim_notice(user_id, now));
+        assert!(!claim_notice(user_id, now + Duration::seconds(10)));
+        assert!(claim_notice(UserId::new(5002), now + Duration::seconds(10)));
+        assert!(claim_notice(
+            user_id,
+            now + Duration::seconds(NOTICE_WINDOW)
+        ));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::db::KingFisherDb;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::UserId;
+use serde::{Deserialize, Serialize};
+
+const COURSE_REVIEWS_TREE: &str = "course_reviews";
+const REVIEWS_PER_PAGE: usize = 5;
+/// Pages are shown as embed descriptions, which Discord caps at 4096 characters.
+const MAX_PAGE_LENGTH: usize = 4096;
+
+/// One member's review of a class. Who wrote it is only kept so they can't review twice.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CourseReview {
+    /// Shown instead of the author, so mods can point at a review.
+    pub id: u64,
+    pub class: u32,
+    pub user_id: u64,
+    /// 1 (easy) to 5 (brutal).
+    pub difficulty: u8,
+    /// Hours per week outside of class.
+    pub workload: u8,
+    pub comments: Option<String>,
+    /// Hidden by a mod, left out of the summary and listing.
+    #[serde(default)]
+    pub hidden: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The averages over a class's visible reviews.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReviewSummary {
+    pub count: usize,
+    pub difficulty: f64,
+    pub workload: f64,
+}
+
+/// Keyed by class then user, so a new review replaces the old one.
+fn review_key(class: u32, user_id: u64) -> Vec<u8> {
+    [class.to_be_bytes().as_slice(), &user_id.to_be_bytes()].concat()
+}
+
+/// A class's reviews, newest first. Hidden ones are only included for mods.
+pub fn get_reviews(
+    db: &KingFisherDb,
+    class: u32,
+    include_hidden: bool,
+) -> Result<Vec<CourseReview>> {
+    let mut reviews = db
+        .values::<CourseReview>(COURSE_REVIEWS_TREE)?
+        .into_iter()
+        .filter(|review| review.class == class && (include_hidden || !review.hidden))
+        .collect::<Vec<_>>();
+
+    reviews.sort_by_key(|review| std::cmp::Reverse(review.created_at));
+
+    Ok(reviews)
+}
+
+pub fn get_review(db: &KingFisherDb, class: u32, user_id: UserId) -> Result<Option<CourseReview>> {
+    db.get(COURSE_REVIEWS_TREE, review_key(class, user_id.get()))
+}
+
+/// Saves a review, replacing the author's old one for the class. A review a mod hid stays hidden.
+pub fn save_review(db: &KingFisherDb, mut review: CourseReview) -> Result<()> {
+    let key = review_key(review.class, review.user_id);
+
+    if let Some(old) = db.get::<CourseReview>(COURSE_REVIEWS_TREE, &key)? {
+        review.id = old.id;
+        review.hidden = old.hidden;
+    }
+
+    db.insert(COURSE_REVIEWS_TREE, key, &review)
+}
+
+fn find_review(db: &KingFisherDb, class: u32, id: u64) -> Result<Option<CourseReview>> {
+    Ok(get_reviews(db, class, true)?
+        .into_iter()
+        .find(|review| review.id == id))
+}
+
+/// Hides or unhides a review by id, returning it if it exists.
+pub fn set_review_hidden(
+    db: &KingFisherDb,
+    class: u32,
+    id: u64,
+    hidden: bool,
+) -> Result<Option<CourseReview>> {
+    let Some(mut review) = find_review(db, class, id)? else {
+        return Ok(None);
+    };
+
+    review.hidden = hidden;
+    db.insert(
+        COURSE_REVIEWS_TREE,
+        review_key(review.class, review.user_id),
+        &review,
+    )?;
+
+    Ok(Some(review))
+}
+
+/// Deletes a review by id so its author can write a new one, returning it if it existed.
+pub fn delete_review(db: &KingFisherDb, class: u32, id: u64) -> Result<Option<CourseReview>> {
+    let Some(review) = find_review(db, class, id)? else {
+        return Ok(None);
+    };
+
+    db.remove::<CourseReview>(
+        COURSE_REVIEWS_TREE,
+        review_key(review.class, review.user_id),
+    )
+}
+
+pub fn summarize(reviews: &[CourseReview]) -> Option<ReviewSummary> {
+    let visible = reviews
+        .iter()
+        .filter(|review| !review.hidden)
+        .collect::<Vec<_>>();
+
+    if visible.is_empty() {
+        return None;
+    }
+
+    let count = visible.len();
+    let average = |value: fn(&CourseReview) -> u8| {
+        visible
+            .iter()
+            .map(|review| f64::from(value(review)))
+            .sum::<f64>()
+            / count as f64
+    };
+
+    Some(ReviewSummary {
+        count,
+        difficulty: average(|review| review.difficulty),
+        workload: average(|review| review.workload),
+    })
+}
+
+pub fn format_review(review: &CourseReview) -> String {
+    let mut line = format!(
+        "**#{}** Difficulty {}/5, ~{} hrs/week ({})",
+        review.id,
+        review.difficulty,
+        review.workload,
+        review.created_at.format("%b %Y")
+    );
+
+    if review.hidden {
+        line.push_str(" *hidden*");
+    }
+
+    if let Some(comments) = &review.comments {
+        line.push_str(&format!("\n> {}", comments.replace('\n', "\n> ")));
+    }
+
+    line
+}
+
+/// Splits the reviews into pages under the header, starting a new page before one would get
+/// too long.
+pub fn paginate_reviews(header: &str, reviews: &[CourseReview]) -> Vec<String> {
+    let mut pages: Vec<String> = vec![];
+    let mut on_page = 0;
+
+    for review in reviews.iter().map(format_review) {
+        match pages.last_mut() {
+            Some(page)
+                if on_page < REVIEWS_PER_PAGE
+                    && page.chars().count() + review.chars().count() + 2 <= MAX_PAGE_LENGTH =>
+            {
+                page.push_str("\n\n");
+                page.push_str(&review);
+                on_page += 1;
+            }
+            _ => {
+                pages.push(format!("{}\n{}", header, review));
+                on_page = 1;
+            }
+        }
+    }
+
+    // A lone review is at most a few thousand characters, but don't trust that
+    for page in &mut pages {
+        if page.chars().count() > MAX_PAGE_LENGTH {
+            *page = page.chars().take(MAX_PAGE_LENGTH).collect();
+        }
+    }
+
+    pages
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn review(id: u64, user_id: u64, difficulty: u8, workload: u8) -> CourseReview {
+        CourseReview {
+            id,
+            class: 2420,
+            user_id,
+            difficulty,
+            workload,
+            comments: None,
+            hidden: false,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn keeps_one_review_per_user() {
+        let db = KingFisherDb::temporary().unwrap();
+
+        save_review(&db, review(1, 10, 5, 20)).unwrap();
+        save_review(&db, review(2, 11, 3, 10)).unwrap();
+        save_review(&db, review(3, 10, 4, 12)).unwrap();
+
+        let reviews = get_reviews(&db, 2420, false).unwrap();
+        assert_eq!(reviews.len(), 2);
+        assert_eq!(
+            get_review(&db, 2420, UserId::new(10))
+                .unwrap()
+                .map(|r| (r.id, r.difficulty)),
+            Some((1, 4))
+        );
+
+        set_review_hidden(&db, 2420, 1, true).unwrap();
+        save_review(&db, review(4, 10, 1, 1)).unwrap();
+        assert_eq!(get_reviews(&db, 2420, false).unwrap().len(), 1);
+        assert_eq!(get_reviews(&db, 2420, true).unwrap().len(), 2);
+
+        assert!(delete_review(&db, 2420, 1).unwrap().is_some());
+        assert_eq!(get_review(&db, 2420, UserId::new(10)).unwrap(), None);
+        assert_eq!(delete_review(&db, 2420, 1).unwrap(), None);
+    }
+
+    #[test]
+    fn averages_visible_reviews() {
+        let mut hidden = review(3, 12, 1, 1);
+        hidden.hidden = true;
+
+        let summary = summarize(&[review(1, 10, 5, 20), review(2, 11, 3, 10), hidden]).unwrap();
+
+        assert_eq!(summary.count, 2);
+        assert_eq!(summary.difficulty, 4.0);
+        assert_eq!(summary.workload, 15.0);
+        assert_eq!(summarize(&[]), None);
+    }
+
+    #[test]
+    fn pages_fit_in_an_embed() {
+        let long = CourseReview {
+            comments: Some("a".repeat(1000)),
+            ..review(1, 10, 5, 20)
+        };
+        let reviews = vec![long; 12];
+        let pages = paginate_reviews("## CS 2420", &reviews);
+
+        assert!(pages
+            .iter()
+            .all(|page| page.chars().count() <= MAX_PAGE_LENGTH));
+        assert_eq!(pages.len(), 4);
+
+        let short = vec![review(1, 10, 5, 20); 7];
+        assert_eq!(paginate_reviews("## CS 2420", &short).len(), 2);
+        assert!(paginate_reviews("## CS 2420", &[]).is_empty());
+    }
+}
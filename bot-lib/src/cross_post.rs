@@ -0,0 +1,83 @@
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::ChannelId;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+lazy_static! {
+    /// Where and when text was first seen, by a hash of the text.
+    static ref RECENT: DashMap<u64, (ChannelId, DateTime<Utc>)> = DashMap::new();
+}
+
+/// Ignores case and spacing, so a copy that's been reformatted a little still counts.
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .join(" ")
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Whether the same text was posted in a different channel within the window.
+///
+/// Only the first channel it shows up in gets responses, so cross-posting to every class
+/// doesn't set off the same meme everywhere.
+pub fn is_cross_post(
+    channel_id: ChannelId,
+    content: &str,
+    window: Duration,
+    now: DateTime<Utc>,
+) -> bool {
+    if content.trim().is_empty() || window <= Duration::zero() {
+        return false;
+    }
+
+    RECENT.retain(|_, (_, seen)| now - *seen < window);
+
+    let mut first_seen = RECENT
+        .entry(content_hash(content))
+        .or_insert((channel_id, now));
+
+    if first_seen.0 != channel_id {
+        return true;
+    }
+
+    // The same channel again is up to the usual cooldowns, it just keeps the entry fresh
+    first_seen.1 = now;
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_the_first_channel_counts() {
+        let now = Utc::now();
+        let window = Duration::seconds(10);
+        let (first, second) = (ChannelId::new(2001), ChannelId::new(2002));
+
+        assert!(!is_cross_post(first, "cross posted MEME", window, now));
+        assert!(is_cross_post(
+            second,
+            "cross  posted meme",
+            window,
+            now + Duration::seconds(3)
+        ));
+        assert!(!is_cross_post(
+            first,
+            "cross posted meme",
+            window,
+            now + Duration::seconds(4)
+        ));
+        assert!(!is_cross_post(
+            second,
+            "cross posted meme",
+            window,
+            now + Duration::seconds(30)
+        ));
+    }
+}
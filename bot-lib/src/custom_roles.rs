@@ -0,0 +1,159 @@
+use crate::{data::AppState, departments::HexColor, utils::is_not_found};
+use color_eyre::eyre::{OptionExt, Result, WrapErr};
+use poise::serenity_prelude::{self as serenity, EditRole, GuildId, RoleId, UserId};
+use serde::{Deserialize, Serialize};
+
+const CUSTOM_ROLES_TREE: &str = "custom_roles";
+
+/// Personal vanity roles for boosters and members who've reached a level, `[custom_roles]`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CustomRoles {
+    /// Custom roles go just below this one, so they can't outrank mod roles.
+    pub anchor_role_id: u64,
+    /// Whether server boosters can have one.
+    #[serde(default = "yes")]
+    pub boosters: bool,
+    /// Level roles (or any others) that earn a custom role.
+    #[serde(default)]
+    pub eligible_role_ids: Vec<u64>,
+}
+
+fn yes() -> bool {
+    true
+}
+
+impl CustomRoles {
+    pub fn is_eligible(&self, member: &serenity::Member) -> bool {
+        (self.boosters && member.premium_since.is_some())
+            || member
+                .roles
+                .iter()
+                .any(|role_id| self.eligible_role_ids.contains(&role_id.get()))
+    }
+}
+
+fn get_custom_role(data: &AppState, user_id: UserId) -> Result<Option<RoleId>> {
+    Ok(data
+        .db
+        .get::<u64>(CUSTOM_ROLES_TREE, user_id.get().to_be_bytes())?
+        .map(RoleId::new))
+}
+
+/// Creates the member's role, or renames and recolors the one they have.
+pub async fn set_custom_role(
+    ctx: &serenity::Context,
+    data: &AppState,
+    member: &serenity::Member,
+    anchor_role_id: RoleId,
+    name: &str,
+    color: HexColor,
+) -> Result<RoleId> {
+    let guild_id = member.guild_id;
+    let reason = format!("Custom role for {}", member.user.name);
+    let edit = EditRole::new()
+        .name(name)
+        .colour(color.0)
+        .audit_log_reason(&reason);
+
+    if let Some(role_id) = get_custom_role(data, member.user.id)? {
+        match guild_id.edit_role(ctx, role_id, edit.clone()).await {
+            Ok(role) => return Ok(role.id),
+            // Deleted by hand, make a new one
+            Err(e) if is_not_found(&e) => {}
+            Err(e) => return Err(e).wrap_err("Couldn't edit custom role"),
+        }
+    }
+
+    let role = guild_id.create_role(ctx, edit).await?;
+    data.db.insert(
+        CUSTOM_ROLES_TREE,
+        member.user.id.get().to_be_bytes(),
+        &role.id.get(),
+    )?;
+
+    // Creating the role moved everything above it up one, so look the anchor up again
+    let anchor_position = guild_id
+        .roles(ctx)
+        .await?
+        .get(&anchor_role_id)
+        .map(|anchor| anchor.position)
+        .ok_or_eyre("The custom role anchor role doesn't exist")?;
+
+    guild_id
+        .edit_role_position(ctx, role.id, anchor_position.saturating_sub(1))
+        .await?;
+    member.add_role(ctx, role.id).await?;
+
+    Ok(role.id)
+}
+
+pub async fn delete_custom_role(
+    ctx: &serenity::Context,
+    data: &AppState,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<bool> {
+    let Some(role_id) = data
+        .db
+        .remove::<u64>(CUSTOM_ROLES_TREE, user_id.get().to_be_bytes())?
+        .map(RoleId::new)
+    else {
+        return Ok(false);
+    };
+
+    if let Err(e) = guild_id.delete_role(ctx, role_id).await {
+        // Probably already deleted by hand
+        tracing::warn!("Couldn't delete custom role {}: {:?}", role_id, e);
+    }
+
+    Ok(true)
+}
+
+/// Takes the custom role away from a member who's stopped boosting or lost their level role.
+pub async fn check_custom_role_eligibility(
+    ctx: &serenity::Context,
+    data: &AppState,
+    member: &serenity::Member,
+) -> Result<()> {
+    let Some(custom_roles) = data.config.read().await.custom_roles.clone() else {
+        return Ok(());
+    };
+
+    if custom_roles.is_eligible(member) {
+        return Ok(());
+    }
+
+    delete_custom_role(ctx, data, member.guild_id, member.user.id).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn boosters_and_level_roles_are_eligible() {
+        let custom_roles = CustomRoles {
+            anchor_role_id: 1,
+            boosters: true,
+            eligible_role_ids: vec![10],
+        };
+        let mut member = serenity::Member::default();
+
+        assert!(!custom_roles.is_eligible(&member));
+
+        member.roles.push(RoleId::new(10));
+        assert!(custom_roles.is_eligible(&member));
+
+        member.roles.clear();
+        member.premium_since = Some(serenity::Timestamp::now());
+        assert!(custom_roles.is_eligible(&member));
+
+        let levels_only = CustomRoles {
+            boosters: false,
+            ..custom_roles
+        };
+        assert!(!levels_only.is_eligible(&member));
+    }
+}
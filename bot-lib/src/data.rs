@@ -1,15 +1,35 @@
-use crate::config::{Config, ResponseKind};
+use crate::command_limits::CommandLimiter;
+use crate::config::{Config, Persona, ResponseKind};
+use crate::db::KingFisherDb;
+use crate::random_image::pick_random_file;
+use crate::react_role_cache::ReactRoleCache;
+use crate::response_variants::record_variant_send;
+use crate::voice::play_sound;
+use crate::webhooks::execute_webhook;
+use chrono::{Local, Utc};
 use color_eyre::eyre::{Error, OptionExt, Result};
 use poise::serenity_prelude as serenity;
 use poise::serenity_prelude::Message;
-use rand::seq::SliceRandom;
-use std::{path::Path, sync::Arc};
+use rand::seq::{IteratorRandom, SliceRandom};
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use tokio::sync::RwLock;
 use tracing::{event, Level};
 
+const RECENT_ERRORS_CAPACITY: usize = 20;
+
 #[derive(Debug)]
 pub struct AppState {
     pub config: Arc<RwLock<Config>>,
+    pub db: KingFisherDb,
+    pub command_limiter: CommandLimiter,
+    /// Who has the bot react role.
+    pub react_roles: ReactRoleCache,
+    /// The last few errors, for `/admin errors`
+    recent_errors: parking_lot::Mutex<VecDeque<String>>,
     /// Config file watcher that refreshes the config if it changes
     ///
     /// Attached to the AppState to keep the watcher alive
@@ -19,6 +39,8 @@ pub struct AppState {
 impl AppState {
     pub fn new(config: Config) -> AppState {
         let config_path = config.config_path.to_owned();
+        let db = KingFisherDb::new(&config.db_path).expect("Failed to open database");
+        let react_roles = ReactRoleCache::load(&db).expect("Failed to load react role members");
         let config = Arc::new(RwLock::new(config));
 
         use notify::{
@@ -35,7 +57,9 @@ impl AppState {
             }) => {
                 event!(Level::INFO, "config changed, reloading...");
 
-                config_clone.blocking_write().reload();
+                if let Err(e) = config_clone.blocking_write().reload() {
+                    event!(Level::ERROR, "Couldn't reload config: {:?}", e);
+                }
             }
             Err(e) => event!(Level::ERROR, "watch error: {:?}", e),
             _ => {}
@@ -48,31 +72,147 @@ impl AppState {
 
         AppState {
             config,
+            db,
+            command_limiter: CommandLimiter::default(),
+            react_roles,
+            recent_errors: parking_lot::Mutex::new(VecDeque::new()),
             _watcher: watcher,
         }
     }
 
+    /// Remembers an error so it can be looked at without access to the logs.
+    pub fn record_error(&self, error: String) {
+        let mut recent_errors = self.recent_errors.lock();
+
+        if recent_errors.len() >= RECENT_ERRORS_CAPACITY {
+            recent_errors.pop_front();
+        }
+
+        recent_errors.push_back(format!(
+            "{} {}",
+            Utc::now().format("%Y-%m-%d %H:%M:%S"),
+            error
+        ));
+    }
+
+    pub fn recent_errors(&self) -> Vec<String> {
+        self.recent_errors.lock().iter().cloned().collect()
+    }
+
     /// If the message contents match any pattern, return the name of the response type.
     /// Otherwise, return None
     pub async fn find_response(
         &self,
         message: &str,
         message_link: &str,
-    ) -> Option<Arc<ResponseKind>> {
+    ) -> Option<(Arc<str>, Arc<ResponseKind>, Option<Persona>)> {
         let config = self.config.read().await;
 
-        config
-            .responses
-            .iter()
-            .find_map(|response| response.find_valid_response(message, &config, message_link))
+        let response = config
+            .active_responses(Local::now().date_naive())
+            .find_map(|response| response.find_valid_response(message, &config, message_link));
+
+        response
+    }
+
+    /// Starts the cooldown of the response found by [`AppState::find_response`], right before
+    /// it's sent. Returns false if another message already set it off.
+    pub async fn mark_triggered(&self, name: &str, message: &str, message_link: &str) -> bool {
+        let config = self.config.read().await;
+
+        let triggered = config
+            .all_responses()
+            .find(|response| response.name() == name)
+            .is_some_and(|response| response.mark_triggered(message, &config, message_link));
+
+        triggered
+    }
+
+    /// Sends the response as the persona, through the channel's webhook.
+    ///
+    /// Returns false for responses that aren't messages, like sounds.
+    async fn run_persona_action(
+        &self,
+        message_response: &ResponseKind,
+        persona: &Persona,
+        reply_target: &Message,
+        ctx: &serenity::Context,
+    ) -> Result<bool> {
+        let (content, path) = match message_response {
+            ResponseKind::Text { content } => (Some(content.clone()), None),
+            ResponseKind::RandomText { content } => {
+                let response = content
+                    .choose(&mut rand::thread_rng())
+                    .ok_or_eyre("The responses list is empty")?;
+
+                (Some(response.clone()), None)
+            }
+            ResponseKind::Image { path } => (None, Some(PathBuf::from(path))),
+            ResponseKind::TextAndImage { content, path } => {
+                (Some(content.clone()), Some(PathBuf::from(path)))
+            }
+            ResponseKind::RandomImage { dir } => (None, Some(pick_random_file(dir)?)),
+            ResponseKind::Variants { .. } | ResponseKind::Sound { .. } | ResponseKind::None => {
+                return Ok(false)
+            }
+        };
+
+        send_as_persona(persona, content, path, reply_target, ctx).await?;
+
+        Ok(true)
+    }
+
+    /// Sends a random variant of the response, and remembers which one was sent.
+    async fn run_variant_action(
+        &self,
+        name: &str,
+        variants: &[String],
+        persona: Option<&Persona>,
+        reply_target: &Message,
+        ctx: &serenity::Context,
+    ) -> Result<()> {
+        let variant = (0..variants.len())
+            .choose(&mut rand::thread_rng())
+            .ok_or_eyre("The variants list is empty")?;
+        let content = variants[variant].clone();
+
+        let sent = match persona {
+            Some(persona) => {
+                send_as_persona(persona, Some(content), None, reply_target, ctx).await?
+            }
+            None => Some(reply_target.reply(ctx, content).await?),
+        };
+
+        if let Some(sent) = sent {
+            record_variant_send(&self.db, name, variant, &sent)?;
+        }
+
+        Ok(())
     }
 
     pub async fn run_action(
         &self,
+        name: &str,
         message_response: &ResponseKind,
+        persona: Option<&Persona>,
         reply_target: &Message,
         ctx: &serenity::Context,
     ) -> Result<()> {
+        if let ResponseKind::Variants { variants } = message_response {
+            return self
+                .run_variant_action(name, variants, persona, reply_target, ctx)
+                .await;
+        }
+
+        if let Some(persona) = persona {
+            if self
+                .run_persona_action(message_response, persona, reply_target, ctx)
+                .await?
+            {
+                return Ok(());
+            }
+        }
+
         match message_response {
             ResponseKind::Text { content } => {
                 reply_target.reply(ctx, content).await?;
@@ -113,12 +253,72 @@ impl AppState {
                     )
                     .await?;
             }
-            ResponseKind::None => {}
+            ResponseKind::RandomImage { dir } => {
+                let path = pick_random_file(dir)?;
+
+                reply_target
+                    .channel_id
+                    .send_message(
+                        ctx,
+                        serenity::CreateMessage::new()
+                            .reference_message(reply_target)
+                            .allowed_mentions(
+                                serenity::CreateAllowedMentions::new().replied_user(false),
+                            )
+                            .add_file(serenity::CreateAttachment::path(&path).await?),
+                    )
+                    .await?;
+            }
+            ResponseKind::Sound { path } => {
+                let guild_id = reply_target
+                    .guild_id
+                    .ok_or_eyre("Sounds only play in guilds")?;
+
+                play_sound(
+                    ctx,
+                    self,
+                    guild_id,
+                    reply_target.author.id,
+                    Path::new(path),
+                    false,
+                )
+                .await?;
+            }
+            // Sent above, so the chosen variant can be tracked
+            ResponseKind::Variants { .. } | ResponseKind::None => {}
         }
 
         Ok(())
     }
 }
 
+async fn send_as_persona(
+    persona: &Persona,
+    content: Option<String>,
+    path: Option<PathBuf>,
+    reply_target: &Message,
+    ctx: &serenity::Context,
+) -> Result<Option<Message>> {
+    let mut builder = serenity::ExecuteWebhook::new()
+        .username(&persona.name)
+        .allowed_mentions(serenity::CreateAllowedMentions::new());
+
+    if let Some(avatar_url) = &persona.avatar_url {
+        builder = builder.avatar_url(avatar_url);
+    }
+
+    if let Some(content) = content {
+        builder = builder.content(content);
+    }
+
+    if let Some(path) = path {
+        builder = builder.add_file(serenity::CreateAttachment::path(&path).await?);
+    }
+
+    execute_webhook(ctx, reply_target.channel_id, builder).await
+}
+
 // User data, which is stored and accessible in all command invocations
-pub type PoiseContext<'a> = poise::Context<'a, AppState, Error>;
+pub type Data = Arc<AppState>;
+pub type PoiseContext<'a> = poise::Context<'a, Data, Error>;
+pub type PoiseApplicationContext<'a> = poise::ApplicationContext<'a, Data, Error>;
@@ -0,0 +1,395 @@
+use chrono::{
+    DateTime, Datelike, Days, Duration, FixedOffset, Month, NaiveDate, NaiveTime, TimeZone, Weekday,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref RELATIVE: Regex =
+        Regex::new(r"^(\s*\d+\s*[a-z]+)+\s*$").expect("Relative time regex should be valid");
+    static ref RELATIVE_PART: Regex =
+        Regex::new(r"(\d+)\s*([a-z]+)").expect("Relative time regex should be valid");
+}
+
+/// A time someone typed, like "in 2h", "Dec 12 8am" or "every Monday".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum When {
+    Once(DateTime<FixedOffset>),
+    Every(Recurrence),
+}
+
+/// Something that happens every day, or every week on a certain day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recurrence {
+    /// Every day if None.
+    pub weekday: Option<Weekday>,
+    pub time: NaiveTime,
+}
+
+impl Recurrence {
+    /// The first time it happens after `now`.
+    pub fn next_after(&self, now: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+        (0..=7)
+            .map(|days| now.date_naive() + Days::new(days))
+            .filter(|date| self.weekday.is_none_or(|weekday| date.weekday() == weekday))
+            .filter_map(|date| at(now, date, self.time))
+            .find(|when| *when > now)
+    }
+}
+
+impl When {
+    /// When it (next) happens.
+    pub fn next(&self, now: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+        match self {
+            When::Once(when) => Some(*when),
+            When::Every(recurrence) => recurrence.next_after(now),
+        }
+    }
+}
+
+fn at(
+    now: DateTime<FixedOffset>,
+    date: NaiveDate,
+    time: NaiveTime,
+) -> Option<DateTime<FixedOffset>> {
+    now.timezone()
+        .from_local_datetime(&date.and_time(time))
+        .single()
+}
+
+/// `3pm`, `3:30pm`, `15:00`, `noon` or `midnight`.
+fn parse_time(word: &str) -> Option<NaiveTime> {
+    match word {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return NaiveTime::from_hms_opt(0, 0, 0),
+        _ => {}
+    }
+
+    let (clock, pm) = if let Some(clock) = word.strip_suffix("pm") {
+        (clock, Some(true))
+    } else if let Some(clock) = word.strip_suffix("am") {
+        (clock, Some(false))
+    } else {
+        (word, None)
+    };
+
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) => (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?),
+        // A bare number is only a time with am/pm, otherwise it's probably a date
+        None if pm.is_some() => (clock.parse::<u32>().ok()?, 0),
+        None => return None,
+    };
+
+    let hour = match pm {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(true) => hour % 12 + 12,
+        Some(false) => hour % 12,
+        None => hour,
+    };
+
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// `12`, `12th` or `1st`.
+fn parse_day_of_month(word: &str) -> Option<u32> {
+    let day = ["st", "nd", "rd", "th"]
+        .iter()
+        .find_map(|suffix| word.strip_suffix(suffix))
+        .unwrap_or(word);
+
+    day.parse().ok().filter(|day| (1..=31).contains(day))
+}
+
+/// `2h`, `2 hours`, `1h30m` or `3 days`.
+fn parse_relative(input: &str) -> Option<Duration> {
+    if !RELATIVE.is_match(input) {
+        return None;
+    }
+
+    let mut total = Duration::zero();
+
+    for part in RELATIVE_PART.captures_iter(input) {
+        let amount = part[1].parse::<i64>().ok()?;
+
+        let unit = match &part[2] {
+            "s" | "sec" | "secs" | "second" | "seconds" => Duration::try_seconds(amount),
+            "m" | "min" | "mins" | "minute" | "minutes" => Duration::try_minutes(amount),
+            "h" | "hr" | "hrs" | "hour" | "hours" => Duration::try_hours(amount),
+            "d" | "day" | "days" => Duration::try_days(amount),
+            "w" | "wk" | "wks" | "week" | "weeks" => Duration::try_weeks(amount),
+            _ => None,
+        }?;
+
+        total = total.checked_add(&unit)?;
+    }
+
+    Some(total)
+}
+
+/// Parses a time relative to `now`, in its timezone.
+///
+/// - Relative: "in 2h", "in 1 hour 30 minutes"
+/// - Absolute: "Friday 3pm", "tomorrow at noon", "Dec 12 8am", "2024-12-12 17:00", "5:30pm"
+/// - Recurring: "every Monday", "every day at 9am"
+///
+/// Days without a time are at midnight. Times and dates without a day or year are the next time
+/// they come around.
+pub fn parse_when(input: &str, now: DateTime<FixedOffset>) -> Option<When> {
+    let input = input.to_lowercase().replace(',', " ");
+    let input = input.trim();
+
+    if let Some(relative) = input.strip_prefix("in ") {
+        return Some(When::Once(now + parse_relative(relative)?));
+    }
+
+    if let Some(recurring) = input.strip_prefix("every ") {
+        return parse_recurrence(recurring).map(When::Every);
+    }
+
+    parse_absolute(input, now).map(When::Once)
+}
+
+fn parse_recurrence(input: &str) -> Option<Recurrence> {
+    let mut day = None;
+    let mut time = None;
+
+    for word in input.split_whitespace() {
+        if word == "at" {
+            continue;
+        }
+
+        if let Some(parsed) = parse_time(word) {
+            // Two times is ambiguous
+            if time.replace(parsed).is_some() {
+                return None;
+            }
+
+            continue;
+        }
+
+        let weekday = match word {
+            "day" => None,
+            word => Some(word.parse::<Weekday>().ok()?),
+        };
+
+        if day.replace(weekday).is_some() {
+            return None;
+        }
+    }
+
+    Some(Recurrence {
+        weekday: day?,
+        time: time.unwrap_or(NaiveTime::MIN),
+    })
+}
+
+/// A day someone typed, and whether it's a weekday name that should roll over to next week.
+enum Day {
+    Date(NaiveDate),
+    Weekday(NaiveDate),
+    /// No year given, so it rolls over to next year.
+    Yearless(Month, u32),
+}
+
+fn parse_absolute(input: &str, now: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+    let mut day = None;
+    let mut time = None;
+    let mut words = input.split_whitespace().peekable();
+
+    while let Some(word) = words.next() {
+        let word = word.trim_end_matches('.');
+
+        if word == "at" || word == "on" || word == "next" {
+            continue;
+        }
+
+        if let Some(parsed) = parse_time(word) {
+            // Two times is ambiguous
+            if time.replace(parsed).is_some() {
+                return None;
+            }
+
+            continue;
+        }
+
+        let parsed = if word == "today" {
+            Day::Date(now.date_naive())
+        } else if word == "tomorrow" {
+            Day::Date(now.date_naive() + Days::new(1))
+        } else if let Ok(weekday) = word.parse::<Weekday>() {
+            let days_ahead =
+                (7 + weekday.num_days_from_monday() - now.weekday().num_days_from_monday()) % 7;
+
+            Day::Weekday(now.date_naive() + Days::new(days_ahead.into()))
+        } else if let Ok(month) = word.parse::<Month>() {
+            let day_of_month = parse_day_of_month(words.next()?)?;
+
+            match words.peek().and_then(|year| year.parse::<i32>().ok()) {
+                Some(year) if year >= 1000 => {
+                    words.next();
+                    Day::Date(NaiveDate::from_ymd_opt(
+                        year,
+                        month.number_from_month(),
+                        day_of_month,
+                    )?)
+                }
+                _ => Day::Yearless(month, day_of_month),
+            }
+        } else if let Ok(date) = NaiveDate::parse_from_str(word, "%Y-%m-%d") {
+            Day::Date(date)
+        } else if let Some((month, day_of_month)) = word.split_once('/') {
+            let month = Month::try_from(u8::try_from(month.parse::<u32>().ok()?).ok()?).ok()?;
+
+            Day::Yearless(month, parse_day_of_month(day_of_month)?)
+        } else {
+            return None;
+        };
+
+        if day.replace(parsed).is_some() {
+            return None;
+        }
+    }
+
+    let time_or_midnight = time.unwrap_or(NaiveTime::MIN);
+
+    match day {
+        None => {
+            let time = time?;
+            let today = at(now, now.date_naive(), time)?;
+
+            if today > now {
+                Some(today)
+            } else {
+                at(now, now.date_naive() + Days::new(1), time)
+            }
+        }
+        Some(Day::Date(date)) => at(now, date, time_or_midnight),
+        Some(Day::Weekday(date)) => {
+            let when = at(now, date, time_or_midnight)?;
+
+            // "friday 3pm" on a friday evening means next week
+            if when <= now {
+                at(now, date + Days::new(7), time_or_midnight)
+            } else {
+                Some(when)
+            }
+        }
+        Some(Day::Yearless(month, day_of_month)) => (now.year()..=now.year() + 4)
+            .filter_map(|year| {
+                NaiveDate::from_ymd_opt(year, month.number_from_month(), day_of_month)
+            })
+            .filter_map(|date| at(now, date, time_or_midnight))
+            .find(|when| *when > now),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mountain() -> FixedOffset {
+        FixedOffset::west_opt(7 * 3600).unwrap()
+    }
+
+    // Wednesday, April 17 2024, 10:00 in UTC-7
+    fn now() -> DateTime<FixedOffset> {
+        mountain().with_ymd_and_hms(2024, 4, 17, 10, 0, 0).unwrap()
+    }
+
+    fn at(month: u32, day: u32, hour: u32, minute: u32) -> DateTime<FixedOffset> {
+        mountain()
+            .with_ymd_and_hms(2024, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn once(input: &str) -> Option<DateTime<FixedOffset>> {
+        match parse_when(input, now())? {
+            When::Once(when) => Some(when),
+            When::Every(_) => None,
+        }
+    }
+
+    #[test]
+    fn parses_times() {
+        assert_eq!(parse_time("3pm"), NaiveTime::from_hms_opt(15, 0, 0));
+        assert_eq!(parse_time("12am"), NaiveTime::from_hms_opt(0, 0, 0));
+        assert_eq!(parse_time("12:30pm"), NaiveTime::from_hms_opt(12, 30, 0));
+        assert_eq!(parse_time("17:45"), NaiveTime::from_hms_opt(17, 45, 0));
+        assert_eq!(parse_time("noon"), NaiveTime::from_hms_opt(12, 0, 0));
+        assert_eq!(parse_time("13pm"), None);
+        assert_eq!(parse_time("17"), None);
+    }
+
+    #[test]
+    fn parses_relative_times() {
+        assert_eq!(once("in 2h"), Some(now() + Duration::hours(2)));
+        assert_eq!(
+            once("in 1 hour 30 minutes"),
+            Some(now() + Duration::minutes(90))
+        );
+        assert_eq!(once("in 1h30m"), Some(now() + Duration::minutes(90)));
+        assert_eq!(once("in 3 days"), Some(now() + Duration::days(3)));
+        assert_eq!(once("in 2 fortnights"), None);
+        assert_eq!(once("in 2h please"), None);
+        assert_eq!(once("in"), None);
+    }
+
+    #[test]
+    fn parses_days_and_times() {
+        assert_eq!(once("Friday 3pm"), Some(at(4, 19, 15, 0)));
+        assert_eq!(once("tomorrow at noon"), Some(at(4, 18, 12, 0)));
+        assert_eq!(once("5:30pm"), Some(at(4, 17, 17, 30)));
+        assert_eq!(once("9am"), Some(at(4, 18, 9, 0)));
+        assert_eq!(once("today"), Some(at(4, 17, 0, 0)));
+        assert_eq!(once("Wednesday 9am"), Some(at(4, 24, 9, 0)));
+        assert_eq!(once("wed 11am"), Some(at(4, 17, 11, 0)));
+    }
+
+    #[test]
+    fn parses_dates() {
+        assert_eq!(once("Dec 12 8am"), Some(at(12, 12, 8, 0)));
+        assert_eq!(once("december 12th, 8am"), Some(at(12, 12, 8, 0)));
+        assert_eq!(once("2024-12-12 17:00"), Some(at(12, 12, 17, 0)));
+        assert_eq!(once("12/12"), Some(at(12, 12, 0, 0)));
+        assert_eq!(
+            once("april 1"),
+            mountain().with_ymd_and_hms(2025, 4, 1, 0, 0, 0).single()
+        );
+        assert_eq!(
+            once("april 1 2030 noon"),
+            mountain().with_ymd_and_hms(2030, 4, 1, 12, 0, 0).single()
+        );
+        assert_eq!(once("feb 30"), None);
+    }
+
+    #[test]
+    fn parses_recurrences() {
+        let monday = Recurrence {
+            weekday: Some(Weekday::Mon),
+            time: NaiveTime::MIN,
+        };
+
+        assert_eq!(parse_when("every Monday", now()), Some(When::Every(monday)));
+        assert_eq!(monday.next_after(now()), Some(at(4, 22, 0, 0)));
+
+        let daily = Recurrence {
+            weekday: None,
+            time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+        };
+
+        assert_eq!(
+            parse_when("every day at 9am", now()),
+            Some(When::Every(daily))
+        );
+        assert_eq!(daily.next_after(now()), Some(at(4, 18, 9, 0)));
+        assert_eq!(parse_when("every so often", now()), None);
+    }
+
+    #[test]
+    fn rejects_nonsense() {
+        assert_eq!(parse_when("", now()), None);
+        assert_eq!(parse_when("whenever", now()), None);
+        assert_eq!(parse_when("3pm 4pm", now()), None);
+        assert_eq!(parse_when("friday tomorrow", now()), None);
+    }
+}
@@ -0,0 +1,231 @@
+use color_eyre::eyre::{Result, WrapErr};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Persistent storage for anything kingfisher needs to remember across restarts.
+///
+/// Each feature gets its own tree, values are stored as json.
+#[derive(Debug, Clone)]
+pub struct KingFisherDb {
+    db: sled::Db,
+}
+
+impl KingFisherDb {
+    pub fn new(path: &str) -> Result<KingFisherDb> {
+        let db = sled::open(path).wrap_err("Could not open database")?;
+
+        Ok(KingFisherDb { db })
+    }
+
+    /// An in-memory database, useful for tests.
+    pub fn temporary() -> Result<KingFisherDb> {
+        let db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .wrap_err("Could not open temporary database")?;
+
+        Ok(KingFisherDb { db })
+    }
+
+    /// A monotonically increasing id, useful as a key for append-only trees.
+    pub fn generate_id(&self) -> Result<u64> {
+        self.db.generate_id().wrap_err("Could not generate id")
+    }
+
+    pub fn insert<T: Serialize>(&self, tree: &str, key: impl AsRef<[u8]>, value: &T) -> Result<()> {
+        let value = serde_json::to_vec(value).wrap_err("Could not serialize value")?;
+
+        self.db
+            .open_tree(tree)?
+            .insert(key, value)
+            .wrap_err("Could not insert value")?;
+
+        Ok(())
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, tree: &str, key: impl AsRef<[u8]>) -> Result<Option<T>> {
+        self.db
+            .open_tree(tree)?
+            .get(key)?
+            .map(|value| serde_json::from_slice(&value).wrap_err("Could not deserialize value"))
+            .transpose()
+    }
+
+    /// Replaces the value with what `f` makes of it, atomically. If another write gets there
+    /// first, `f` runs again on the new value. Nothing is written if `f` fails.
+    pub fn update<T: Serialize + DeserializeOwned>(
+        &self,
+        tree: &str,
+        key: impl AsRef<[u8]>,
+        mut f: impl FnMut(Option<T>) -> Result<T>,
+    ) -> Result<T> {
+        let tree = self.db.open_tree(tree)?;
+        let key = key.as_ref();
+
+        loop {
+            let current = tree.get(key)?;
+            let value = current
+                .as_ref()
+                .map(|value| serde_json::from_slice(value).wrap_err("Could not deserialize value"))
+                .transpose()?;
+
+            let updated = f(value)?;
+            let serialized = serde_json::to_vec(&updated).wrap_err("Could not serialize value")?;
+
+            if tree
+                .compare_and_swap(key, current, Some(serialized))
+                .wrap_err("Could not update value")?
+                .is_ok()
+            {
+                return Ok(updated);
+            }
+        }
+    }
+
+    pub fn remove<T: DeserializeOwned>(
+        &self,
+        tree: &str,
+        key: impl AsRef<[u8]>,
+    ) -> Result<Option<T>> {
+        self.db
+            .open_tree(tree)?
+            .remove(key)?
+            .map(|value| serde_json::from_slice(&value).wrap_err("Could not deserialize value"))
+            .transpose()
+    }
+
+    /// Every value in the tree, in key order. Values that fail to deserialize are skipped.
+    pub fn values<T: DeserializeOwned>(&self, tree: &str) -> Result<Vec<T>> {
+        Ok(self
+            .db
+            .open_tree(tree)?
+            .iter()
+            .values()
+            .filter_map(|value| serde_json::from_slice(&value.ok()?).ok())
+            .collect())
+    }
+
+    /// Every key/value pair in the tree, in key order. Values that fail to deserialize are skipped.
+    pub fn entries<T: DeserializeOwned>(&self, tree: &str) -> Result<Vec<(Vec<u8>, T)>> {
+        Ok(self
+            .db
+            .open_tree(tree)?
+            .iter()
+            .filter_map(|entry| {
+                let (key, value) = entry.ok()?;
+                Some((key.to_vec(), serde_json::from_slice(&value).ok()?))
+            })
+            .collect())
+    }
+
+    /// Everything in the database as `{ tree: { hex key: value } }`, for backups and poking around.
+    pub fn export_json(&self) -> Result<serde_json::Value> {
+        let mut trees = serde_json::Map::new();
+
+        for name in self.db.tree_names() {
+            let entries = self
+                .db
+                .open_tree(&name)?
+                .iter()
+                .map(|entry| {
+                    let (key, value) = entry?;
+                    let key = key.iter().map(|byte| format!("{:02x}", byte)).collect();
+                    // Not everything is json, like sled's own bookkeeping
+                    let value = serde_json::from_slice(&value).unwrap_or_else(|_| {
+                        serde_json::Value::String(String::from_utf8_lossy(&value).into_owned())
+                    });
+
+                    Ok((key, value))
+                })
+                .collect::<Result<serde_json::Map<_, _>>>()?;
+
+            if !entries.is_empty() {
+                trees.insert(
+                    String::from_utf8_lossy(&name).into_owned(),
+                    serde_json::Value::Object(entries),
+                );
+            }
+        }
+
+        Ok(serde_json::Value::Object(trees))
+    }
+
+    /// Copies every tree into a new database at `path`, which must not exist yet.
+    ///
+    /// This goes through sled's export format, so it also moves data across sled versions.
+    pub fn copy_to(&self, path: &str) -> Result<KingFisherDb> {
+        if std::path::Path::new(path).exists() {
+            color_eyre::eyre::bail!("{} already exists", path);
+        }
+
+        let copy = KingFisherDb::new(path)?;
+        copy.db.import(self.db.export());
+        copy.db.flush().wrap_err("Could not flush database")?;
+
+        Ok(copy)
+    }
+
+    pub fn clear(&self, tree: &str) -> Result<()> {
+        self.db
+            .open_tree(tree)?
+            .clear()
+            .wrap_err("Could not clear tree")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trips_values() {
+        let db = KingFisherDb::temporary().unwrap();
+
+        db.insert("test", "a", &1u32).unwrap();
+        db.insert("test", "b", &2u32).unwrap();
+
+        assert_eq!(db.get::<u32>("test", "a").unwrap(), Some(1));
+        assert_eq!(db.values::<u32>("test").unwrap(), vec![1, 2]);
+        assert_eq!(db.remove::<u32>("test", "a").unwrap(), Some(1));
+        assert_eq!(db.get::<u32>("test", "a").unwrap(), None);
+    }
+
+    #[test]
+    fn updates_atomically() {
+        let db = KingFisherDb::temporary().unwrap();
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..100 {
+                        db.update("test", "count", |count: Option<u32>| {
+                            Ok(count.unwrap_or(0) + 1)
+                        })
+                        .unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(db.get::<u32>("test", "count").unwrap(), Some(400));
+
+        // A failed update leaves the value alone
+        assert!(db
+            .update("test", "count", |_: Option<u32>| Err(
+                color_eyre::eyre::eyre!("no")
+            ))
+            .is_err());
+        assert_eq!(db.get::<u32>("test", "count").unwrap(), Some(400));
+    }
+
+    #[test]
+    fn exports_json() {
+        let db = KingFisherDb::temporary().unwrap();
+
+        db.insert("test", 1u64.to_be_bytes(), &"one").unwrap();
+
+        assert_eq!(
+            db.export_json().unwrap(),
+            serde_json::json!({ "test": { "0000000000000001": "one" } })
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use crate::{class_info::ClassInfo, db::KingFisherDb, discord_api::DiscordApi};
+use chrono::{Duration, Utc};
+use color_eyre::eyre::{Result, WrapErr};
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, ChannelType, GuildId, PermissionOverwrite,
+    PermissionOverwriteType, Permissions, RoleId,
+};
+use serde::{Deserialize, Serialize};
+
+const DELETED_CATEGORIES_TREE: &str = "deleted_categories";
+/// How long after deleting a class category it can still be brought back.
+const UNDO_WINDOW: Duration = match Duration::try_hours(24) {
+    Some(window) => window,
+    None => panic!("Failed to create undo window"),
+};
+
+/// Everything needed to recreate a deleted class category, except the message history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeletedCategory {
+    pub number: u32,
+    pub deleted_by: u64,
+    pub deleted_at: i64,
+    pub role: RoleSnapshot,
+    pub category: ChannelSnapshot,
+    pub channels: Vec<ChannelSnapshot>,
+    pub info: ClassInfo,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoleSnapshot {
+    /// The id it had, so permission overwrites can point at the new role.
+    pub id: RoleId,
+    pub name: String,
+    pub color: u32,
+    pub hoist: bool,
+    pub mentionable: bool,
+    pub permissions: Permissions,
+}
+
+impl From<&serenity::Role> for RoleSnapshot {
+    fn from(role: &serenity::Role) -> Self {
+        RoleSnapshot {
+            id: role.id,
+            name: role.name.clone(),
+            color: role.colour.0,
+            hoist: role.hoist,
+            mentionable: role.mentionable,
+            permissions: role.permissions,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelSnapshot {
+    pub name: String,
+    pub kind: ChannelType,
+    pub position: u16,
+    pub topic: Option<String>,
+    pub nsfw: bool,
+    pub rate_limit_per_user: Option<u16>,
+    pub permission_overwrites: Vec<PermissionOverwrite>,
+}
+
+impl From<&serenity::GuildChannel> for ChannelSnapshot {
+    fn from(channel: &serenity::GuildChannel) -> Self {
+        ChannelSnapshot {
+            name: channel.name.clone(),
+            kind: channel.kind,
+            position: channel.position,
+            topic: channel.topic.clone(),
+            nsfw: channel.nsfw,
+            rate_limit_per_user: channel.rate_limit_per_user,
+            permission_overwrites: channel.permission_overwrites.clone(),
+        }
+    }
+}
+
+impl ChannelSnapshot {
+    /// The channel to create, with overwrites for the old role moved to the new one.
+    fn to_builder(&self, old_role: RoleId, new_role: RoleId) -> serenity::CreateChannel<'_> {
+        let permissions = self
+            .permission_overwrites
+            .iter()
+            .map(|overwrite| PermissionOverwrite {
+                kind: match overwrite.kind {
+                    PermissionOverwriteType::Role(role_id) if role_id == old_role => {
+                        PermissionOverwriteType::Role(new_role)
+                    }
+                    kind => kind,
+                },
+                ..overwrite.clone()
+            })
+            .collect::<Vec<_>>();
+
+        let mut builder = serenity::CreateChannel::new(&self.name)
+            .kind(self.kind)
+            .position(self.position)
+            .nsfw(self.nsfw)
+            .permissions(permissions);
+
+        if let Some(topic) = &self.topic {
+            builder = builder.topic(topic);
+        }
+
+        if let Some(rate_limit) = self.rate_limit_per_user {
+            builder = builder.rate_limit_per_user(rate_limit);
+        }
+
+        builder
+    }
+}
+
+pub fn save_deleted_category(db: &KingFisherDb, deleted: &DeletedCategory) -> Result<()> {
+    db.insert(
+        DELETED_CATEGORIES_TREE,
+        db.generate_id()?.to_be_bytes(),
+        deleted,
+    )
+}
+
+/// The most recently deleted category, if it's still within the undo window, with its key for
+/// [`forget_deletion`] once it's been brought back.
+pub fn last_deletion(db: &KingFisherDb) -> Result<Option<(Vec<u8>, DeletedCategory)>> {
+    let now = Utc::now().timestamp();
+    let mut last = None;
+
+    for (key, deleted) in db.entries::<DeletedCategory>(DELETED_CATEGORIES_TREE)? {
+        if now - deleted.deleted_at > UNDO_WINDOW.num_seconds() {
+            db.remove::<DeletedCategory>(DELETED_CATEGORIES_TREE, &key)?;
+            continue;
+        }
+
+        if last
+            .as_ref()
+            .is_none_or(|(_, last): &(_, DeletedCategory)| deleted.deleted_at >= last.deleted_at)
+        {
+            last = Some((key, deleted));
+        }
+    }
+
+    Ok(last)
+}
+
+pub fn forget_deletion(db: &KingFisherDb, key: &[u8]) -> Result<()> {
+    db.remove::<DeletedCategory>(DELETED_CATEGORIES_TREE, key)?;
+
+    Ok(())
+}
+
+/// Recreates the role, category and channels. Returns the new category.
+pub async fn restore_category(
+    api: &impl DiscordApi,
+    guild: GuildId,
+    deleted: &DeletedCategory,
+) -> Result<ChannelId> {
+    let role = api
+        .create_role(
+            guild,
+            serenity::EditRole::new()
+                .name(&deleted.role.name)
+                .colour(deleted.role.color)
+                .hoist(deleted.role.hoist)
+                .mentionable(deleted.role.mentionable)
+                .permissions(deleted.role.permissions),
+        )
+        .await
+        .wrap_err("Couldn't recreate role")?;
+
+    let category = api
+        .create_channel(guild, deleted.category.to_builder(deleted.role.id, role.id))
+        .await
+        .wrap_err("Couldn't recreate category")?;
+
+    for channel in &deleted.channels {
+        api.create_channel(
+            guild,
+            channel
+                .to_builder(deleted.role.id, role.id)
+                .category(category.id),
+        )
+        .await
+        .wrap_err_with(|| format!("Couldn't recreate #{}", channel.name))?;
+    }
+
+    Ok(category.id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::discord_api::MockDiscord;
+
+    const GUILD: GuildId = GuildId::new(1065373537591894086);
+
+    fn channel(name: &str, kind: ChannelType, role_id: RoleId) -> ChannelSnapshot {
+        ChannelSnapshot {
+            name: name.to_owned(),
+            kind,
+            position: 0,
+            topic: None,
+            nsfw: false,
+            rate_limit_per_user: None,
+            permission_overwrites: vec![PermissionOverwrite {
+                allow: Permissions::VIEW_CHANNEL,
+                deny: Permissions::empty(),
+                kind: PermissionOverwriteType::Role(role_id),
+            }],
+        }
+    }
+
+    fn deleted(deleted_at: i64) -> DeletedCategory {
+        let old_role = RoleId::new(42);
+
+        DeletedCategory {
+            number: 2420,
+            deleted_by: 1,
+            deleted_at,
+            role: RoleSnapshot {
+                id: old_role,
+                name: "CS 2420".to_owned(),
+                color: 0xcc0000,
+                hoist: true,
+                mentionable: false,
+                permissions: Permissions::empty(),
+            },
+            category: channel("CS 2420", ChannelType::Category, old_role),
+            channels: vec![channel("2420-general", ChannelType::Text, old_role)],
+            info: ClassInfo::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn restores_the_category() {
+        let discord = MockDiscord::default();
+
+        let category_id = restore_category(&discord, GUILD, &deleted(0))
+            .await
+            .unwrap();
+
+        let roles = discord.roles.lock().clone();
+        assert_eq!(roles.len(), 1);
+        assert_eq!(roles[0].name, "CS 2420");
+        assert!(roles[0].hoist);
+
+        let category = discord.channel_named("CS 2420").unwrap();
+        assert_eq!(category.id, category_id);
+        assert_eq!(category.kind, ChannelType::Category);
+
+        let general = discord.channel_named("2420-general").unwrap();
+        assert_eq!(general.parent_id, Some(category_id));
+        assert_eq!(
+            general.permission_overwrites[0].kind,
+            PermissionOverwriteType::Role(roles[0].id)
+        );
+    }
+
+    #[test]
+    fn only_undoes_recent_deletions() {
+        let db = KingFisherDb::temporary().unwrap();
+        let now = Utc::now().timestamp();
+
+        let take = |db: &KingFisherDb| {
+            let (key, deleted) = last_deletion(db).unwrap()?;
+            forget_deletion(db, &key).unwrap();
+            Some(deleted)
+        };
+
+        save_deleted_category(&db, &deleted(now - UNDO_WINDOW.num_seconds() - 1)).unwrap();
+        assert_eq!(take(&db), None);
+
+        save_deleted_category(&db, &deleted(now - 20)).unwrap();
+        save_deleted_category(&db, &deleted(now - 10)).unwrap();
+
+        // Looking doesn't use it up, in case bringing it back fails
+        assert_eq!(
+            last_deletion(&db).unwrap().map(|(_, deleted)| deleted),
+            Some(deleted(now - 10))
+        );
+        assert_eq!(take(&db), Some(deleted(now - 10)));
+        assert_eq!(take(&db), Some(deleted(now - 20)));
+        assert_eq!(take(&db), None);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::data::AppState;
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, ChannelType, CreateAttachment, GuildId};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::{collections::HashSet, fmt, str::FromStr};
+
+/// A color written like `#cc0000`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexColor(pub u32);
+
+impl FromStr for HexColor {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        u32::from_str_radix(s.trim_start_matches('#'), 16).map(HexColor)
+    }
+}
+
+impl fmt::Display for HexColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:06x}", self.0)
+    }
+}
+
+/// How a department's class roles look.
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Department {
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub color: Option<HexColor>,
+    /// Image for the role icon, only used if the server is boosted enough for role icons.
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+/// The department of a class role, like `CS` for "CS 2420".
+pub fn department_of(role_name: &str) -> Option<&str> {
+    let (department, number) = role_name.split_once(' ')?;
+
+    (!department.is_empty() && !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+        .then_some(department)
+}
+
+/// Which roles are classes, so ones like "Level 5" or "Year 4" can't be joined as one.
+///
+/// A class is in a department from `[departments]`, or has a class category of the same name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KnownClasses {
+    departments: HashSet<String>,
+    categories: HashSet<String>,
+}
+
+impl KnownClasses {
+    pub fn new(
+        departments: impl IntoIterator<Item = String>,
+        categories: impl IntoIterator<Item = String>,
+    ) -> Self {
+        KnownClasses {
+            departments: departments
+                .into_iter()
+                .map(|department| department.to_uppercase())
+                .collect(),
+            categories: categories
+                .into_iter()
+                .map(|category| category.to_uppercase())
+                .collect(),
+        }
+    }
+
+    pub async fn load(ctx: &serenity::Context, data: &AppState, guild_id: GuildId) -> Result<Self> {
+        let departments = data
+            .config
+            .read()
+            .await
+            .departments
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        let categories = guild_id
+            .channels(ctx)
+            .await?
+            .into_values()
+            .filter(|channel| channel.kind == ChannelType::Category)
+            .map(|channel| channel.name);
+
+        Ok(KnownClasses::new(departments, categories))
+    }
+
+    pub fn has_department(&self, department: &str) -> bool {
+        self.departments.contains(&department.to_uppercase())
+    }
+
+    pub fn is_class(&self, role_name: &str) -> bool {
+        let Some(department) = department_of(role_name) else {
+            return false;
+        };
+
+        self.has_department(department) || self.categories.contains(&role_name.to_uppercase())
+    }
+}
+
+/// Whether the server can have role icons at all.
+pub fn has_role_icons(guild: &serenity::Guild) -> bool {
+    guild.features.iter().any(|feature| feature == "ROLE_ICONS")
+}
+
+impl Department {
+    /// Reads the icon image, if there is one and the server can use it.
+    pub async fn load_icon(&self, role_icons: bool) -> Result<Option<CreateAttachment>> {
+        match &self.icon {
+            Some(icon) if role_icons => Ok(Some(CreateAttachment::path(icon).await?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Applies the department's color and icon to a role edit.
+    pub fn theme<'a>(
+        &self,
+        role: serenity::EditRole<'a>,
+        icon: Option<&CreateAttachment>,
+    ) -> serenity::EditRole<'a> {
+        let role = match self.color {
+            Some(HexColor(color)) => role.colour(color),
+            None => role,
+        };
+
+        match icon {
+            Some(icon) => role.icon(Some(icon)),
+            None => role,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn reads_departments() {
+        let departments: HashMap<String, Department> = toml::from_str(
+            r##"
+[CS]
+color = "#cc0000"
+icon = "assets/cs.png"
+
+[MATH]
+"##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            departments["CS"],
+            Department {
+                color: Some(HexColor(0xcc0000)),
+                icon: Some("assets/cs.png".to_owned()),
+            }
+        );
+        assert_eq!(departments["MATH"], Department::default());
+        assert_eq!(HexColor(0xcc0000).to_string(), "#cc0000");
+    }
+
+    #[test]
+    fn finds_department() {
+        assert_eq!(department_of("CS 2420"), Some("CS"));
+        assert_eq!(department_of("MATH 2270"), Some("MATH"));
+        assert_eq!(department_of("Mods"), None);
+        assert_eq!(department_of("Class Rep"), None);
+    }
+
+    #[test]
+    fn only_knows_configured_or_categorized_classes() {
+        let known = KnownClasses::new(["MATH".to_owned()], ["CS 2420".to_owned()]);
+
+        assert!(known.is_class("MATH 2270"));
+        assert!(known.is_class("cs 2420"));
+        assert!(!known.is_class("CS 3500"));
+        assert!(!known.is_class("Level 5"));
+        assert!(!known.is_class("Year 4"));
+        assert!(known.has_department("math"));
+    }
+}
@@ -0,0 +1,373 @@
+use color_eyre::eyre::{OptionExt, Result};
+use poise::serenity_prelude::{self as serenity, ChannelId, GuildId};
+
+/// The Discord calls commands and responders make.
+///
+/// Implemented by the real [`serenity::Context`], and by `MockDiscord` in tests
+/// so the logic can be checked without a live guild.
+pub(crate) trait DiscordApi {
+    async fn guild_channels(&self, guild_id: GuildId) -> Result<Vec<serenity::GuildChannel>>;
+
+    async fn guild_roles(&self, guild_id: GuildId) -> Result<Vec<serenity::Role>>;
+
+    async fn create_role(
+        &self,
+        guild_id: GuildId,
+        role: serenity::EditRole<'_>,
+    ) -> Result<serenity::Role>;
+
+    async fn create_channel(
+        &self,
+        guild_id: GuildId,
+        channel: serenity::CreateChannel<'_>,
+    ) -> Result<serenity::GuildChannel>;
+
+    /// The name of a guild channel, if it is one.
+    async fn channel_name(&self, channel_id: ChannelId) -> Option<String>;
+
+    /// The latest messages in a channel.
+    async fn channel_messages(&self, channel_id: ChannelId) -> Result<Vec<serenity::Message>>;
+
+    async fn send_message(
+        &self,
+        channel_id: ChannelId,
+        message: serenity::CreateMessage,
+    ) -> Result<serenity::Message>;
+
+    async fn attachment_from_url(&self, url: &str) -> Result<serenity::CreateAttachment>;
+
+    async fn guild_channel(&self, channel_id: ChannelId) -> Result<serenity::GuildChannel>;
+
+    /// Active and archived public threads in a channel, or posts in a forum.
+    async fn threads(&self, parent_id: ChannelId) -> Result<Vec<serenity::GuildChannel>>;
+
+    /// A public thread in a channel, or a post in a forum, starting with `message`.
+    async fn create_thread(
+        &self,
+        parent_id: ChannelId,
+        name: &str,
+        message: serenity::CreateMessage,
+    ) -> Result<serenity::GuildChannel>;
+
+    async fn reopen_thread(&self, thread_id: ChannelId) -> Result<()>;
+}
+
+impl DiscordApi for serenity::Context {
+    async fn guild_channels(&self, guild_id: GuildId) -> Result<Vec<serenity::GuildChannel>> {
+        Ok(guild_id.channels(self).await?.into_values().collect())
+    }
+
+    async fn guild_roles(&self, guild_id: GuildId) -> Result<Vec<serenity::Role>> {
+        Ok(guild_id.roles(self).await?.into_values().collect())
+    }
+
+    async fn create_role(
+        &self,
+        guild_id: GuildId,
+        role: serenity::EditRole<'_>,
+    ) -> Result<serenity::Role> {
+        Ok(guild_id.create_role(self, role).await?)
+    }
+
+    async fn create_channel(
+        &self,
+        guild_id: GuildId,
+        channel: serenity::CreateChannel<'_>,
+    ) -> Result<serenity::GuildChannel> {
+        Ok(guild_id.create_channel(self, channel).await?)
+    }
+
+    async fn channel_name(&self, channel_id: ChannelId) -> Option<String> {
+        channel_id
+            .to_channel(self)
+            .await
+            .ok()?
+            .guild()
+            .map(|channel| channel.name)
+    }
+
+    async fn channel_messages(&self, channel_id: ChannelId) -> Result<Vec<serenity::Message>> {
+        Ok(channel_id
+            .messages(self, serenity::GetMessages::new())
+            .await?)
+    }
+
+    async fn send_message(
+        &self,
+        channel_id: ChannelId,
+        message: serenity::CreateMessage,
+    ) -> Result<serenity::Message> {
+        Ok(channel_id.send_message(self, message).await?)
+    }
+
+    async fn attachment_from_url(&self, url: &str) -> Result<serenity::CreateAttachment> {
+        Ok(serenity::CreateAttachment::url(self, url).await?)
+    }
+
+    async fn guild_channel(&self, channel_id: ChannelId) -> Result<serenity::GuildChannel> {
+        channel_id
+            .to_channel(self)
+            .await?
+            .guild()
+            .ok_or_eyre("Not a guild channel")
+    }
+
+    async fn threads(&self, parent_id: ChannelId) -> Result<Vec<serenity::GuildChannel>> {
+        let guild_id = self.guild_channel(parent_id).await?.guild_id;
+
+        let mut threads = guild_id
+            .get_active_threads(self)
+            .await?
+            .threads
+            .into_iter()
+            .filter(|thread| thread.parent_id == Some(parent_id))
+            .collect::<Vec<_>>();
+
+        threads.extend(
+            parent_id
+                .get_archived_public_threads(self, None, None)
+                .await?
+                .threads,
+        );
+
+        Ok(threads)
+    }
+
+    async fn create_thread(
+        &self,
+        parent_id: ChannelId,
+        name: &str,
+        message: serenity::CreateMessage,
+    ) -> Result<serenity::GuildChannel> {
+        if self.guild_channel(parent_id).await?.kind == serenity::ChannelType::Forum {
+            return Ok(parent_id
+                .create_forum_post(self, serenity::CreateForumPost::new(name, message))
+                .await?);
+        }
+
+        let thread = parent_id
+            .create_thread(
+                self,
+                serenity::CreateThread::new(name).kind(serenity::ChannelType::PublicThread),
+            )
+            .await?;
+        thread.id.send_message(self, message).await?;
+
+        Ok(thread)
+    }
+
+    async fn reopen_thread(&self, thread_id: ChannelId) -> Result<()> {
+        thread_id
+            .edit_thread(self, serenity::EditThread::new().archived(false))
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// An in memory guild. Builders are read back through their JSON, like Discord would.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MockDiscord {
+    pub channels: parking_lot::Mutex<Vec<serenity::GuildChannel>>,
+    pub roles: parking_lot::Mutex<Vec<serenity::Role>>,
+    /// Every message in the guild, including ones sent through the mock.
+    pub messages: parking_lot::Mutex<Vec<serenity::Message>>,
+    next_id: std::sync::atomic::AtomicU64,
+}
+
+#[cfg(test)]
+impl MockDiscord {
+    fn next_id(&self) -> u64 {
+        self.next_id
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+            + 1
+    }
+
+    pub fn add_channel(&self, name: &str) -> ChannelId {
+        let mut channel = serenity::GuildChannel::default();
+        channel.id = ChannelId::new(self.next_id());
+        channel.name = name.to_owned();
+
+        self.channels.lock().push(channel.clone());
+
+        channel.id
+    }
+
+    pub fn add_role(&self, name: &str) -> serenity::RoleId {
+        let mut role = serenity::Role::default();
+        role.id = serenity::RoleId::new(self.next_id());
+        role.name = name.to_owned();
+
+        self.roles.lock().push(role.clone());
+
+        role.id
+    }
+
+    pub fn add_thread(&self, parent_id: ChannelId, name: &str, archived: bool) -> ChannelId {
+        let mut thread = serenity::GuildChannel::default();
+        thread.id = ChannelId::new(self.next_id());
+        thread.name = name.to_owned();
+        thread.kind = serenity::ChannelType::PublicThread;
+        thread.parent_id = Some(parent_id);
+        thread.thread_metadata = Some(
+            serde_json::from_value(serde_json::json!({
+                "archived": archived,
+                "auto_archive_duration": 10080,
+            }))
+            .unwrap(),
+        );
+
+        self.channels.lock().push(thread.clone());
+
+        thread.id
+    }
+
+    pub fn channel_named(&self, name: &str) -> Option<serenity::GuildChannel> {
+        self.channels
+            .lock()
+            .iter()
+            .find(|channel| channel.name == name)
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+impl DiscordApi for MockDiscord {
+    async fn guild_channels(&self, _guild_id: GuildId) -> Result<Vec<serenity::GuildChannel>> {
+        Ok(self.channels.lock().clone())
+    }
+
+    async fn guild_roles(&self, _guild_id: GuildId) -> Result<Vec<serenity::Role>> {
+        Ok(self.roles.lock().clone())
+    }
+
+    async fn create_role(
+        &self,
+        _guild_id: GuildId,
+        role: serenity::EditRole<'_>,
+    ) -> Result<serenity::Role> {
+        let json = serde_json::to_value(&role)?;
+
+        let mut role = serenity::Role::default();
+        role.id = serenity::RoleId::new(self.next_id());
+        role.name = json["name"].as_str().unwrap_or_default().to_owned();
+        role.hoist = json["hoist"].as_bool().unwrap_or_default();
+        role.colour = serenity::Colour(json["color"].as_u64().unwrap_or_default() as u32);
+
+        self.roles.lock().push(role.clone());
+
+        Ok(role)
+    }
+
+    async fn create_channel(
+        &self,
+        guild_id: GuildId,
+        channel: serenity::CreateChannel<'_>,
+    ) -> Result<serenity::GuildChannel> {
+        let json = serde_json::to_value(&channel)?;
+
+        let mut channel = serenity::GuildChannel::default();
+        channel.id = ChannelId::new(self.next_id());
+        channel.guild_id = guild_id;
+        channel.name = json["name"].as_str().unwrap_or_default().to_owned();
+        channel.kind = serde_json::from_value(json["type"].clone())?;
+        channel.parent_id = serde_json::from_value(json["parent_id"].clone())?;
+        channel.permission_overwrites =
+            serde_json::from_value(json["permission_overwrites"].clone()).unwrap_or_default();
+
+        self.channels.lock().push(channel.clone());
+
+        Ok(channel)
+    }
+
+    async fn channel_name(&self, channel_id: ChannelId) -> Option<String> {
+        self.channels
+            .lock()
+            .iter()
+            .find(|channel| channel.id == channel_id)
+            .map(|channel| channel.name.clone())
+    }
+
+    async fn channel_messages(&self, channel_id: ChannelId) -> Result<Vec<serenity::Message>> {
+        Ok(self
+            .messages
+            .lock()
+            .iter()
+            .filter(|message| message.channel_id == channel_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn send_message(
+        &self,
+        channel_id: ChannelId,
+        message: serenity::CreateMessage,
+    ) -> Result<serenity::Message> {
+        let json = serde_json::to_value(&message)?;
+
+        let mut message = serenity::Message::default();
+        message.id = serenity::MessageId::new(self.next_id());
+        message.channel_id = channel_id;
+        message.content = json["content"].as_str().unwrap_or_default().to_owned();
+        message.embeds = serde_json::from_value(json["embeds"].clone()).unwrap_or_default();
+
+        self.messages.lock().push(message.clone());
+
+        Ok(message)
+    }
+
+    async fn attachment_from_url(&self, url: &str) -> Result<serenity::CreateAttachment> {
+        Ok(serenity::CreateAttachment::bytes(
+            vec![],
+            url.rsplit('/').next().unwrap_or(url),
+        ))
+    }
+
+    async fn guild_channel(&self, channel_id: ChannelId) -> Result<serenity::GuildChannel> {
+        self.channels
+            .lock()
+            .iter()
+            .find(|channel| channel.id == channel_id)
+            .cloned()
+            .ok_or_eyre("No such channel")
+    }
+
+    async fn threads(&self, parent_id: ChannelId) -> Result<Vec<serenity::GuildChannel>> {
+        Ok(self
+            .channels
+            .lock()
+            .iter()
+            .filter(|channel| {
+                channel.parent_id == Some(parent_id) && channel.thread_metadata.is_some()
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn create_thread(
+        &self,
+        parent_id: ChannelId,
+        name: &str,
+        message: serenity::CreateMessage,
+    ) -> Result<serenity::GuildChannel> {
+        let thread_id = self.add_thread(parent_id, name, false);
+        self.send_message(thread_id, message).await?;
+
+        self.guild_channel(thread_id).await
+    }
+
+    async fn reopen_thread(&self, thread_id: ChannelId) -> Result<()> {
+        let mut channels = self.channels.lock();
+        let thread = channels
+            .iter_mut()
+            .find(|channel| channel.id == thread_id)
+            .ok_or_eyre("No such thread")?;
+
+        if let Some(metadata) = &mut thread.thread_metadata {
+            metadata.archived = false;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,459 @@
+use crate::{
+    data::AppState,
+    db::KingFisherDb,
+    departments::HexColor,
+    feature_flags::{is_enabled, Feature},
+    role_expiry::set_personal_role_expiry,
+};
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{eyre, OptionExt, Result};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{self as serenity, EditRole, GuildId, RoleId, UserId};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+const BALANCES_TREE: &str = "economy_balances";
+const LEDGER_TREE: &str = "economy_ledger";
+const INVENTORY_TREE: &str = "economy_inventory";
+const REWARDED_REACTIONS_TREE: &str = "economy_rewarded_reactions";
+/// Each buyer's color role, kept and recolored for their next purchase.
+const COLOR_ROLES_TREE: &str = "economy_color_roles";
+
+lazy_static! {
+    /// When each member last earned anything for a message.
+    static ref LAST_MESSAGE_REWARD: DashMap<UserId, DateTime<Utc>> = DashMap::new();
+}
+
+/// A currency members earn by chatting and getting reactions, spent in `/shop`.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Economy {
+    /// What the currency is called, e.g. `"feathers"`.
+    #[serde(default = "get_default_currency")]
+    pub currency: String,
+    #[serde(default = "get_default_reward")]
+    pub message_reward: u64,
+    /// Messages only earn once per this many seconds, so spamming doesn't pay.
+    #[serde_as(as = "DurationSeconds<i64>")]
+    #[serde(default = "get_default_message_cooldown")]
+    pub message_cooldown: Duration,
+    /// Earned by the author for each person who reacts to their message.
+    #[serde(default = "get_default_reward")]
+    pub reaction_reward: u64,
+    /// What mods have put up for sale.
+    #[serde(default)]
+    pub shop: Vec<ShopItem>,
+    /// Bought color roles go just below this one, so they can't outrank mod roles.
+    /// Role colors can't be bought without it.
+    #[serde(default)]
+    pub color_anchor_role_id: Option<u64>,
+}
+
+fn get_default_currency() -> String {
+    "feathers".to_owned()
+}
+
+fn get_default_reward() -> u64 {
+    1
+}
+
+fn get_default_message_cooldown() -> Duration {
+    Duration::minutes(1)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ShopItem {
+    pub name: String,
+    pub description: String,
+    pub price: u64,
+    #[serde(flatten)]
+    pub kind: ItemKind,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ItemKind {
+    /// A role in a color of the buyer's choosing, for a while. The role is deleted when it runs out.
+    RoleColor {
+        #[serde_as(as = "DurationSeconds<i64>")]
+        duration: Duration,
+    },
+    /// Lets `/play` skip the cooldown once.
+    SoundboardCredit,
+    /// Pins a message with the "Pin with token" context menu.
+    PinToken,
+}
+
+/// The things that are kept in an inventory until they're used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token {
+    SoundboardCredit,
+    Pin,
+}
+
+impl Token {
+    fn key(self, user_id: UserId) -> Vec<u8> {
+        let name: &[u8] = match self {
+            Token::SoundboardCredit => b"soundboard_credit",
+            Token::Pin => b"pin",
+        };
+
+        [&user_id.get().to_be_bytes()[..], name].concat()
+    }
+}
+
+/// A change to someone's balance, kept forever.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub user_id: u64,
+    pub amount: i64,
+    pub reason: String,
+    pub timestamp: i64,
+}
+
+pub fn balance(db: &KingFisherDb, user_id: UserId) -> Result<u64> {
+    Ok(db
+        .get::<u64>(BALANCES_TREE, user_id.get().to_be_bytes())?
+        .unwrap_or(0))
+}
+
+/// Adds to (or takes from) a balance, recording why. Fails rather than going below zero.
+pub fn transact(db: &KingFisherDb, user_id: UserId, amount: i64, reason: &str) -> Result<u64> {
+    let updated = db.update(
+        BALANCES_TREE,
+        user_id.get().to_be_bytes(),
+        |current: Option<u64>| {
+            current
+                .unwrap_or(0)
+                .checked_add_signed(amount)
+                .ok_or_else(|| eyre!("Balance of {} can't go below zero", user_id))
+        },
+    )?;
+
+    db.insert(
+        LEDGER_TREE,
+        db.generate_id()?.to_be_bytes(),
+        &LedgerEntry {
+            user_id: user_id.get(),
+            amount,
+            reason: reason.to_owned(),
+            timestamp: Utc::now().timestamp(),
+        },
+    )?;
+
+    Ok(updated)
+}
+
+/// Takes the price from a balance if it covers it, returning whether it did.
+///
+/// The check and the deduction happen together, so rewards landing at the same time can't undo
+/// a purchase.
+pub fn spend(db: &KingFisherDb, user_id: UserId, price: u64, reason: &str) -> Result<bool> {
+    let mut covered = false;
+
+    db.update(
+        BALANCES_TREE,
+        user_id.get().to_be_bytes(),
+        |current: Option<u64>| {
+            let current = current.unwrap_or(0);
+            covered = current >= price;
+
+            Ok(if covered { current - price } else { current })
+        },
+    )?;
+
+    if covered {
+        db.insert(
+            LEDGER_TREE,
+            db.generate_id()?.to_be_bytes(),
+            &LedgerEntry {
+                user_id: user_id.get(),
+                amount: -(price as i64),
+                reason: reason.to_owned(),
+                timestamp: Utc::now().timestamp(),
+            },
+        )?;
+    }
+
+    Ok(covered)
+}
+
+pub fn token_count(db: &KingFisherDb, user_id: UserId, token: Token) -> Result<u32> {
+    Ok(db
+        .get::<u32>(INVENTORY_TREE, token.key(user_id))?
+        .unwrap_or(0))
+}
+
+pub fn give_token(db: &KingFisherDb, user_id: UserId, token: Token) -> Result<()> {
+    db.update(INVENTORY_TREE, token.key(user_id), |count: Option<u32>| {
+        Ok(count.unwrap_or(0) + 1)
+    })?;
+
+    Ok(())
+}
+
+/// Uses up one of the token, returning whether there was one to use.
+pub fn take_token(db: &KingFisherDb, user_id: UserId, token: Token) -> Result<bool> {
+    let mut taken = false;
+
+    db.update(INVENTORY_TREE, token.key(user_id), |count: Option<u32>| {
+        let count = count.unwrap_or(0);
+        taken = count > 0;
+
+        Ok(count.saturating_sub(1))
+    })?;
+
+    Ok(taken)
+}
+
+/// Gives the member their own role in the color, just below the anchor role so the color
+/// shows without outranking mods, and deletes it again once the time's up.
+pub async fn buy_role_color(
+    ctx: &serenity::Context,
+    db: &KingFisherDb,
+    guild_id: GuildId,
+    user_id: UserId,
+    anchor_role_id: RoleId,
+    color: HexColor,
+    duration: Duration,
+) -> Result<RoleId> {
+    let guild = guild_id.to_partial_guild(ctx).await?;
+    let existing = db
+        .get::<u64>(COLOR_ROLES_TREE, user_id.get().to_be_bytes())?
+        .map(RoleId::new)
+        .filter(|role_id| guild.roles.contains_key(role_id));
+
+    let role_id = match existing {
+        Some(role_id) => {
+            guild_id
+                .edit_role(ctx, role_id, EditRole::new().colour(color.0))
+                .await?;
+            role_id
+        }
+        None => {
+            let user = user_id.to_user(ctx).await?;
+            let role = guild_id
+                .create_role(
+                    ctx,
+                    EditRole::new()
+                        .name(format!("{}'s color", user.name))
+                        .colour(color.0)
+                        .audit_log_reason("Bought from the shop"),
+                )
+                .await?;
+            db.insert(
+                COLOR_ROLES_TREE,
+                user_id.get().to_be_bytes(),
+                &role.id.get(),
+            )?;
+
+            // Creating the role moved everything above it up one, so look the anchor up again
+            let anchor_position = guild_id
+                .roles(ctx)
+                .await?
+                .get(&anchor_role_id)
+                .map(|anchor| anchor.position)
+                .ok_or_eyre("The role color anchor role doesn't exist")?;
+
+            guild_id
+                .edit_role_position(ctx, role.id, anchor_position.saturating_sub(1))
+                .await?;
+
+            role.id
+        }
+    };
+
+    ctx.http
+        .add_member_role(guild_id, user_id, role_id, Some("Bought from the shop"))
+        .await?;
+    set_personal_role_expiry(db, guild_id, user_id, role_id, Utc::now() + duration)?;
+
+    Ok(role_id)
+}
+
+/// Whether the member can earn for a message yet, starting their cooldown if so.
+fn claim_message_reward(user_id: UserId, cooldown: Duration, now: DateTime<Utc>) -> bool {
+    let mut last = LAST_MESSAGE_REWARD
+        .entry(user_id)
+        .or_insert(DateTime::UNIX_EPOCH);
+
+    if now - *last < cooldown {
+        return false;
+    }
+
+    *last = now;
+    true
+}
+
+pub async fn reward_message(data: &AppState, message: &serenity::Message) -> Result<()> {
+    let Some(economy) = data.config.read().await.economy.clone() else {
+        return Ok(());
+    };
+
+    if !is_enabled(data, Feature::EconomyRewards).await {
+        return Ok(());
+    }
+
+    if message.author.bot
+        || economy.message_reward == 0
+        || !claim_message_reward(message.author.id, economy.message_cooldown, Utc::now())
+    {
+        return Ok(());
+    }
+
+    transact(
+        &data.db,
+        message.author.id,
+        economy.message_reward as i64,
+        "Message",
+    )?;
+
+    Ok(())
+}
+
+/// Pays the author of a message the first time each person reacts to it.
+pub async fn reward_reaction(
+    data: &AppState,
+    message: &serenity::Message,
+    reaction: &serenity::Reaction,
+) -> Result<()> {
+    let Some(economy) = data.config.read().await.economy.clone() else {
+        return Ok(());
+    };
+
+    if !is_enabled(data, Feature::EconomyRewards).await {
+        return Ok(());
+    }
+
+    let Some(reactor_id) = reaction.user_id else {
+        return Ok(());
+    };
+
+    if message.author.bot || reactor_id == message.author.id || economy.reaction_reward == 0 {
+        return Ok(());
+    }
+
+    let key = [
+        message.id.get().to_be_bytes(),
+        reactor_id.get().to_be_bytes(),
+    ]
+    .concat();
+
+    // Taking a reaction off and putting it back doesn't pay twice
+    if data
+        .db
+        .get::<bool>(REWARDED_REACTIONS_TREE, &key)?
+        .is_some()
+    {
+        return Ok(());
+    }
+
+    data.db.insert(REWARDED_REACTIONS_TREE, &key, &true)?;
+    transact(
+        &data.db,
+        message.author.id,
+        economy.reaction_reward as i64,
+        "Reaction",
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn balances_never_go_negative() {
+        let db = KingFisherDb::temporary().unwrap();
+        let user_id = UserId::new(1);
+
+        assert_eq!(transact(&db, user_id, 10, "Message").unwrap(), 10);
+        assert_eq!(transact(&db, user_id, -4, "Bought a thing").unwrap(), 6);
+        assert!(transact(&db, user_id, -7, "Bought a thing").is_err());
+        assert_eq!(balance(&db, user_id).unwrap(), 6);
+        assert_eq!(db.values::<LedgerEntry>(LEDGER_TREE).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn spending_checks_the_balance() {
+        let db = KingFisherDb::temporary().unwrap();
+        let user_id = UserId::new(2);
+
+        transact(&db, user_id, 10, "Message").unwrap();
+
+        assert!(!spend(&db, user_id, 11, "Bought a thing").unwrap());
+        assert_eq!(balance(&db, user_id).unwrap(), 10);
+        assert!(spend(&db, user_id, 10, "Bought a thing").unwrap());
+        assert_eq!(balance(&db, user_id).unwrap(), 0);
+        assert_eq!(db.values::<LedgerEntry>(LEDGER_TREE).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn tokens_get_used_up() {
+        let db = KingFisherDb::temporary().unwrap();
+        let user_id = UserId::new(1);
+
+        assert!(!take_token(&db, user_id, Token::Pin).unwrap());
+        give_token(&db, user_id, Token::Pin).unwrap();
+        assert_eq!(
+            token_count(&db, user_id, Token::SoundboardCredit).unwrap(),
+            0
+        );
+        assert!(take_token(&db, user_id, Token::Pin).unwrap());
+        assert!(!take_token(&db, user_id, Token::Pin).unwrap());
+    }
+
+    #[test]
+    fn messages_earn_once_per_cooldown() {
+        let now = Utc::now();
+        let user_id = UserId::new(3001);
+
+        assert!(claim_message_reward(user_id, Duration::minutes(1), now));
+        assert!(!claim_message_reward(
+            user_id,
+            Duration::minutes(1),
+            now + Duration::seconds(30)
+        ));
+        assert!(claim_message_reward(
+            user_id,
+            Duration::minutes(1),
+            now + Duration::seconds(61)
+        ));
+    }
+
+    #[test]
+    fn shop_items_deserialize() {
+        let economy: Economy = toml::from_str(
+            r#"
+            color_anchor_role_id = 7
+
+            [[shop]]
+            name = "Role color"
+            description = "Your own color for a week"
+            price = 500
+            kind = "role_color"
+            duration = 604800
+
+            [[shop]]
+            name = "Pin"
+            description = "Pin any message"
+            price = 200
+            kind = "pin_token"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(economy.currency, "feathers");
+        assert_eq!(economy.color_anchor_role_id, Some(7));
+        assert_eq!(
+            economy.shop[0].kind,
+            ItemKind::RoleColor {
+                duration: Duration::weeks(1)
+            }
+        );
+        assert_eq!(economy.shop[1].kind, ItemKind::PinToken);
+    }
+}
@@ -0,0 +1,343 @@
+use crate::db::KingFisherDb;
+use color_eyre::eyre::{Result, WrapErr};
+use poise::serenity_prelude::{self as serenity, CreateAttachment, GuildId};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+const SYNCED_ASSETS_TREE: &str = "synced_assets";
+
+/// A folder of emojis and stickers to keep the server's in sync with.
+///
+/// Emojis go in `<dir>/emojis`, stickers in `<dir>/stickers`, named like `kingfisher.png`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct EmojiAssets {
+    pub dir: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AssetKind {
+    Emoji,
+    Sticker,
+}
+
+impl AssetKind {
+    fn folder(self) -> &'static str {
+        match self {
+            AssetKind::Emoji => "emojis",
+            AssetKind::Sticker => "stickers",
+        }
+    }
+}
+
+impl fmt::Display for AssetKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetKind::Emoji => write!(f, "emoji"),
+            AssetKind::Sticker => write!(f, "sticker"),
+        }
+    }
+}
+
+/// A file in the assets folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Asset {
+    pub kind: AssetKind,
+    pub name: String,
+    pub path: PathBuf,
+    pub hash: u64,
+}
+
+/// An emoji or sticker kingfisher uploaded. Ones it didn't upload are never touched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncedAsset {
+    pub id: u64,
+    pub kind: AssetKind,
+    pub name: String,
+    pub hash: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Add(Asset),
+    /// Same image, new file name.
+    Rename {
+        synced: SyncedAsset,
+        asset: Asset,
+    },
+    /// Same file name, new image.
+    Replace {
+        synced: SyncedAsset,
+        asset: Asset,
+    },
+    Remove(SyncedAsset),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Add(asset) => write!(f, "+ {} `{}`", asset.kind, asset.name),
+            Change::Rename { synced, asset } => {
+                write!(f, "~ {} `{}` -> `{}`", asset.kind, synced.name, asset.name)
+            }
+            Change::Replace { asset, .. } => {
+                write!(f, "~ {} `{}` (new image)", asset.kind, asset.name)
+            }
+            Change::Remove(synced) => write!(f, "- {} `{}`", synced.kind, synced.name),
+        }
+    }
+}
+
+/// FNV-1a, stable across builds unlike the std hasher.
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl EmojiAssets {
+    /// Every emoji and sticker file in the folder.
+    pub fn read_assets(&self) -> Result<Vec<Asset>> {
+        let mut assets = vec![];
+
+        for kind in [AssetKind::Emoji, AssetKind::Sticker] {
+            let folder = Path::new(&self.dir).join(kind.folder());
+
+            if !folder.is_dir() {
+                continue;
+            }
+
+            for entry in std::fs::read_dir(&folder)
+                .wrap_err_with(|| format!("Could not read {}", folder.display()))?
+            {
+                let path = entry?.path();
+
+                let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                    continue;
+                };
+
+                assets.push(Asset {
+                    kind,
+                    name: name.to_owned(),
+                    hash: hash_bytes(&std::fs::read(&path)?),
+                    path: path.clone(),
+                });
+            }
+        }
+
+        assets.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(assets)
+    }
+}
+
+/// What needs to change for the server to match the assets folder.
+pub fn plan_sync(assets: &[Asset], synced: &[SyncedAsset]) -> Vec<Change> {
+    let mut changes = vec![];
+    let mut unmatched = synced.to_vec();
+
+    // Unchanged and replaced files first, so renames only pick from what's left over
+    let mut new_assets = vec![];
+
+    for asset in assets {
+        let same_name = unmatched
+            .iter()
+            .position(|synced| synced.kind == asset.kind && synced.name == asset.name);
+
+        match same_name {
+            Some(i) => {
+                let synced = unmatched.remove(i);
+
+                if synced.hash != asset.hash {
+                    changes.push(Change::Replace {
+                        synced,
+                        asset: asset.clone(),
+                    });
+                }
+            }
+            None => new_assets.push(asset),
+        }
+    }
+
+    for asset in new_assets {
+        let same_image = unmatched
+            .iter()
+            .position(|synced| synced.kind == asset.kind && synced.hash == asset.hash);
+
+        match same_image {
+            Some(i) => changes.push(Change::Rename {
+                synced: unmatched.remove(i),
+                asset: asset.clone(),
+            }),
+            None => changes.push(Change::Add(asset.clone())),
+        }
+    }
+
+    changes.extend(unmatched.into_iter().map(Change::Remove));
+
+    changes
+}
+
+pub fn get_synced_assets(db: &KingFisherDb) -> Result<Vec<SyncedAsset>> {
+    db.values(SYNCED_ASSETS_TREE)
+}
+
+async fn upload(ctx: &serenity::Context, guild_id: GuildId, asset: &Asset) -> Result<u64> {
+    let file = CreateAttachment::path(&asset.path).await?;
+
+    Ok(match asset.kind {
+        AssetKind::Emoji => guild_id
+            .create_emoji(ctx, &asset.name, &file.to_base64())
+            .await?
+            .id
+            .get(),
+        AssetKind::Sticker => guild_id
+            .create_sticker(
+                ctx,
+                serenity::CreateSticker::new(&asset.name, file).tags(&asset.name),
+            )
+            .await?
+            .id
+            .get(),
+    })
+}
+
+async fn delete(ctx: &serenity::Context, guild_id: GuildId, synced: &SyncedAsset) -> Result<()> {
+    match synced.kind {
+        AssetKind::Emoji => guild_id.delete_emoji(ctx, synced.id).await?,
+        AssetKind::Sticker => guild_id.delete_sticker(ctx, synced.id).await?,
+    }
+
+    Ok(())
+}
+
+/// Makes one change on discord and records it.
+pub async fn apply_change(
+    ctx: &serenity::Context,
+    db: &KingFisherDb,
+    guild_id: GuildId,
+    change: &Change,
+) -> Result<()> {
+    let record = |id: u64, asset: &Asset| {
+        db.insert(
+            SYNCED_ASSETS_TREE,
+            id.to_be_bytes(),
+            &SyncedAsset {
+                id,
+                kind: asset.kind,
+                name: asset.name.clone(),
+                hash: asset.hash,
+            },
+        )
+    };
+
+    match change {
+        Change::Add(asset) => {
+            let id = upload(ctx, guild_id, asset).await?;
+            record(id, asset)?;
+        }
+        Change::Rename { synced, asset } => {
+            match asset.kind {
+                AssetKind::Emoji => {
+                    guild_id.edit_emoji(ctx, synced.id, &asset.name).await?;
+                }
+                AssetKind::Sticker => {
+                    guild_id
+                        .edit_sticker(
+                            ctx,
+                            synced.id,
+                            serenity::EditSticker::new().name(&asset.name),
+                        )
+                        .await?;
+                }
+            }
+            record(synced.id, asset)?;
+        }
+        Change::Replace { synced, asset } => {
+            delete(ctx, guild_id, synced).await?;
+            db.remove::<SyncedAsset>(SYNCED_ASSETS_TREE, synced.id.to_be_bytes())?;
+
+            let id = upload(ctx, guild_id, asset).await?;
+            record(id, asset)?;
+        }
+        Change::Remove(synced) => {
+            delete(ctx, guild_id, synced).await?;
+            db.remove::<SyncedAsset>(SYNCED_ASSETS_TREE, synced.id.to_be_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn asset(kind: AssetKind, name: &str, hash: u64) -> Asset {
+        Asset {
+            kind,
+            name: name.to_owned(),
+            path: PathBuf::new(),
+            hash,
+        }
+    }
+
+    fn synced(id: u64, kind: AssetKind, name: &str, hash: u64) -> SyncedAsset {
+        SyncedAsset {
+            id,
+            kind,
+            name: name.to_owned(),
+            hash,
+        }
+    }
+
+    #[test]
+    fn plans_changes() {
+        let assets = [
+            asset(AssetKind::Emoji, "same", 1),
+            asset(AssetKind::Emoji, "renamed", 2),
+            asset(AssetKind::Emoji, "redrawn", 30),
+            asset(AssetKind::Emoji, "new", 4),
+            asset(AssetKind::Sticker, "same", 1),
+        ];
+        let synced = [
+            synced(10, AssetKind::Emoji, "same", 1),
+            synced(11, AssetKind::Emoji, "old_name", 2),
+            synced(12, AssetKind::Emoji, "redrawn", 3),
+            synced(13, AssetKind::Emoji, "gone", 5),
+        ];
+
+        assert_eq!(
+            plan_sync(&assets, &synced),
+            vec![
+                Change::Replace {
+                    synced: synced[2].clone(),
+                    asset: assets[2].clone()
+                },
+                Change::Rename {
+                    synced: synced[1].clone(),
+                    asset: assets[1].clone()
+                },
+                Change::Add(assets[3].clone()),
+                Change::Add(assets[4].clone()),
+                Change::Remove(synced[3].clone()),
+            ]
+        );
+    }
+
+    #[test]
+    fn nothing_to_do_when_in_sync() {
+        let assets = [asset(AssetKind::Emoji, "kingfisher", 1)];
+        let synced = [synced(10, AssetKind::Emoji, "kingfisher", 1)];
+
+        assert!(plan_sync(&assets, &synced).is_empty());
+    }
+
+    #[test]
+    fn hashes_are_stable() {
+        assert_eq!(hash_bytes(b""), 0xcbf29ce484222325);
+        assert_eq!(hash_bytes(b"a"), 0xaf63dc4c8601ec8c);
+    }
+}
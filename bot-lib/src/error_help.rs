@@ -0,0 +1,255 @@
+use crate::{
+    author_guard::is_from_human,
+    data::AppState,
+    feature_flags::{feature_enabled, Feature},
+    lang::ruleset::Ruleset,
+    llm::{Llm, LlmMessage},
+};
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::Result;
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{self as serenity, ChannelId};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+/// Discord's limit on message length.
+const MAX_MESSAGE_LENGTH: usize = 2000;
+const LLM_DISCLAIMER: &str = "\n-# Written by an AI, double check it.";
+
+const EXPLAINER_PROMPT: &str = "You help university computer science students understand \
+compiler errors, exceptions and crashes. Explain what the error in the message means and the \
+usual ways to fix it, in a few sentences, without writing their assignment for them. If there's \
+no error in the message, answer with only NONE.";
+
+lazy_static! {
+    /// Anything that looks like it came out of a compiler, interpreter or crashed program.
+    static ref LOOKS_LIKE_ERROR: Regex = Regex::new(
+        r"(?i)\berror(\[E\d+\])?:|exception\b|traceback \(most recent call last\)|panicked at|segmentation fault|core dumped|\bat [\w.$]+\(\w+\.java:\d+\)"
+    )
+    .expect("Error regex should be valid");
+    static ref LAST_LLM_CALL: DashMap<ChannelId, DateTime<Utc>> = DashMap::new();
+}
+
+/// Explains error messages pasted or screenshotted in help channels, `[error_help]`.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ErrorHelp {
+    pub channel_ids: Vec<u64>,
+    /// Curated explanations, checked in order before asking the `llm`.
+    #[serde(default)]
+    pub explanations: Vec<ErrorExplanation>,
+    /// Ask the `llm` about errors none of the explanations cover.
+    #[serde(default)]
+    pub use_llm: bool,
+    /// How long after asking the `llm` a channel has to wait to ask again, in seconds.
+    #[serde_as(as = "DurationSeconds<i64>")]
+    #[serde(default = "get_default_llm_cooldown")]
+    pub llm_cooldown: Duration,
+}
+
+fn get_default_llm_cooldown() -> Duration {
+    Duration::minutes(2)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ErrorExplanation {
+    /// e.g. `Borrow checker`
+    pub name: String,
+    /// e.g. `r cannot borrow .* as mutable`
+    pub ruleset: Ruleset,
+    pub explanation: String,
+}
+
+impl ErrorHelp {
+    /// The first curated explanation for the error in `text`.
+    pub fn explain(&self, text: &str) -> Option<&ErrorExplanation> {
+        self.explanations
+            .iter()
+            .find(|explanation| explanation.ruleset.matches(text))
+    }
+}
+
+/// Whether the channel can ask the LLM again. If so, it counts as asking now.
+fn claim_llm_call(channel_id: ChannelId, cooldown: Duration, now: DateTime<Utc>) -> bool {
+    let mut last_call = LAST_LLM_CALL
+        .entry(channel_id)
+        .or_insert(DateTime::UNIX_EPOCH);
+
+    if now - *last_call < cooldown {
+        return false;
+    }
+
+    *last_call = now;
+    true
+}
+
+async fn ask_llm(llm: &Llm, text: &str) -> Option<String> {
+    let messages = [LlmMessage::system(EXPLAINER_PROMPT), LlmMessage::user(text)];
+
+    match llm.chat(&messages).await {
+        Ok(answer) if !answer.starts_with("NONE") => Some(answer),
+        Ok(_) => None,
+        Err(e) => {
+            tracing::warn!("Couldn't explain error: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Replies to errors in help channels, from the curated explanations or else the LLM.
+pub async fn explain_errors(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<()> {
+    let (settings, ocr, llm) = {
+        let config = data.config.read().await;
+
+        let Some(settings) = config
+            .error_help
+            .clone()
+            .filter(|settings| settings.channel_ids.contains(&message.channel_id.get()))
+        else {
+            return Ok(());
+        };
+
+        let llm = config
+            .llm
+            .clone()
+            .filter(|_| feature_enabled(&data.db, &config, Feature::Llm));
+
+        (settings, config.ocr.clone(), llm)
+    };
+
+    if !is_from_human(ctx, data, message).await {
+        return Ok(());
+    }
+
+    let mut text = message.content.clone();
+    if let Some(screenshot) = match ocr {
+        Some(ocr) => ocr.read_images(message).await,
+        None => None,
+    } {
+        text.push('\n');
+        text.push_str(&screenshot);
+    }
+
+    if !LOOKS_LIKE_ERROR.is_match(&text) {
+        return Ok(());
+    }
+
+    let reply = match (settings.explain(&text), llm) {
+        (Some(explanation), _) => {
+            format!("**{}**\n{}", explanation.name, explanation.explanation)
+        }
+        (None, Some(llm))
+            if settings.use_llm
+                && claim_llm_call(message.channel_id, settings.llm_cooldown, Utc::now()) =>
+        {
+            match ask_llm(&llm, &text).await {
+                Some(answer) => answer
+                    .chars()
+                    .take(MAX_MESSAGE_LENGTH - LLM_DISCLAIMER.chars().count())
+                    .chain(LLM_DISCLAIMER.chars())
+                    .collect(),
+                None => return Ok(()),
+            }
+        }
+        _ => return Ok(()),
+    };
+    let reply = reply.chars().take(MAX_MESSAGE_LENGTH).collect::<String>();
+
+    message
+        .channel_id
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .content(reply)
+                .reference_message(message)
+                .allowed_mentions(serenity::CreateAllowedMentions::new()),
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast_ruleset;
+
+    fn explanation(name: &str, ruleset: Ruleset) -> ErrorExplanation {
+        ErrorExplanation {
+            name: name.to_owned(),
+            ruleset,
+            explanation: String::new(),
+        }
+    }
+
+    #[test]
+    fn recognizes_common_errors() {
+        let help = ErrorHelp {
+            channel_ids: vec![],
+            explanations: vec![
+                explanation(
+                    "Borrow checker",
+                    fast_ruleset!("r cannot borrow .* as mutable"),
+                ),
+                explanation("Null pointer", fast_ruleset!("r NullPointerException")),
+                explanation("Segfault", fast_ruleset!("r (?i)segmentation fault")),
+            ],
+            use_llm: false,
+            llm_cooldown: Duration::zero(),
+        };
+
+        let explain = |text| {
+            help.explain(text)
+                .map(|explanation| explanation.name.as_str())
+        };
+
+        assert_eq!(
+            explain("error[E0502]: cannot borrow `v` as mutable because it is also borrowed as immutable"),
+            Some("Borrow checker")
+        );
+        assert_eq!(
+            explain("Exception in thread \"main\" java.lang.NullPointerException\n\tat Main.main(Main.java:5)"),
+            Some("Null pointer")
+        );
+        assert_eq!(
+            explain("Segmentation fault (core dumped)"),
+            Some("Segfault")
+        );
+        assert_eq!(explain("error: expected `;`, found `}`"), None);
+    }
+
+    #[test]
+    fn limits_llm_calls_per_channel() {
+        let now = Utc::now();
+        let cooldown = Duration::minutes(2);
+        let channel_id = ChannelId::new(1);
+
+        assert!(claim_llm_call(channel_id, cooldown, now));
+        assert!(!claim_llm_call(
+            channel_id,
+            cooldown,
+            now + Duration::minutes(1)
+        ));
+        assert!(claim_llm_call(ChannelId::new(2), cooldown, now));
+        assert!(claim_llm_call(
+            channel_id,
+            cooldown,
+            now + Duration::minutes(2)
+        ));
+    }
+
+    #[test]
+    fn only_errors_get_explained() {
+        assert!(LOOKS_LIKE_ERROR.is_match("error[E0382]: borrow of moved value: `s`"));
+        assert!(LOOKS_LIKE_ERROR.is_match("thread 'main' panicked at src/main.rs:2:5"));
+        assert!(LOOKS_LIKE_ERROR.is_match("\tat com.example.App.run(App.java:12)"));
+        assert!(LOOKS_LIKE_ERROR.is_match("Segmentation fault (core dumped)"));
+        assert!(!LOOKS_LIKE_ERROR.is_match("anyone know when the exam is?"));
+    }
+}
@@ -0,0 +1,313 @@
+use chrono::Utc;
+use color_eyre::eyre::{eyre, OptionExt, Report, Result};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{panic::PanicHookInfo, time::Duration};
+
+const CLIENT: &str = concat!("kingfisher/", env!("CARGO_PKG_VERSION"));
+/// Panics are sent from a thread of their own, which shouldn't hang around long after.
+const PANIC_SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where panics and command errors get sent, off unless configured.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ErrorReporting {
+    /// A Sentry (or Sentry compatible, like GlitchTip) DSN, read once at startup.
+    pub dsn: String,
+    /// e.g. `"production"`, to tell reports from a test bot apart.
+    #[serde(default)]
+    pub environment: Option<String>,
+}
+
+/// The parts of a DSN like `https://<key>@sentry.example.com/<project id>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Dsn {
+    key: String,
+    envelope_url: String,
+}
+
+impl Dsn {
+    fn parse(dsn: &str) -> Result<Dsn> {
+        let url = reqwest::Url::parse(dsn)?;
+
+        if url.username().is_empty() {
+            return Err(eyre!("DSN is missing its key"));
+        }
+
+        let mut path = url
+            .path_segments()
+            .ok_or_eyre("DSN is missing its project id")?
+            .collect::<Vec<_>>();
+        let project_id = path
+            .pop()
+            .filter(|project_id| !project_id.is_empty())
+            .ok_or_eyre("DSN is missing its project id")?;
+        let prefix = path
+            .iter()
+            .map(|segment| format!("/{}", segment))
+            .collect::<String>();
+        let port = url
+            .port()
+            .map(|port| format!(":{}", port))
+            .unwrap_or_default();
+
+        Ok(Dsn {
+            key: url.username().to_owned(),
+            envelope_url: format!(
+                "{}://{}{}{}/api/{}/envelope/",
+                url.scheme(),
+                url.host_str().unwrap_or_default(),
+                port,
+                prefix,
+                project_id
+            ),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Reporter {
+    dsn: Dsn,
+    environment: Option<String>,
+}
+
+lazy_static! {
+    static ref REPORTER: Mutex<Option<Reporter>> = Mutex::new(None);
+    /// Quoted text in error messages is usually user input, like the `content: "..."` of a
+    /// message in debug output.
+    static ref QUOTED: Regex = Regex::new(r#""(?:[^"\\]|\\.)*""#).unwrap();
+    /// Anything between spaces and the punctuation of debug output.
+    static ref TOKEN: Regex = Regex::new(r"[^\s{}()\[\]<>,;=|]+").unwrap();
+    /// Tokens that can be sent as they are: words and Rust paths, and numbers too short to be
+    /// ids, like status codes.
+    static ref ALLOWED_TOKEN: Regex =
+        Regex::new(r"^([A-Za-z_][A-Za-z_'-]*(::[A-Za-z_]+)*[.:!?]?|\d{1,4}[.:]?)$").unwrap();
+}
+
+/// Turns on reporting, and reports panics from here on.
+pub fn install(config: &ErrorReporting) -> Result<()> {
+    let reporter = Reporter {
+        dsn: Dsn::parse(&config.dsn)?,
+        environment: config.environment.clone(),
+    };
+
+    *REPORTER.lock() = Some(reporter);
+
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        report_panic(info);
+        previous_hook(info);
+    }));
+
+    Ok(())
+}
+
+/// Redacts everything in the text that isn't plainly part of the error, like message content,
+/// ids, names and links, before it leaves the bot.
+fn scrub(text: &str) -> String {
+    let text = QUOTED.replace_all(text, "\"\"");
+
+    TOKEN
+        .replace_all(&text, |captures: &regex::Captures| {
+            let token = &captures[0];
+
+            if token == "\"\"" || token.chars().count() <= 40 && ALLOWED_TOKEN.is_match(token) {
+                token.to_owned()
+            } else {
+                "[redacted]".to_owned()
+            }
+        })
+        .replace("\"\"", "\"[redacted]\"")
+}
+
+/// A Sentry event, with the exception chain oldest cause first like Sentry expects.
+fn build_event(
+    reporter: &Reporter,
+    level: &str,
+    exceptions: Vec<(String, String)>,
+    tags: Value,
+) -> Value {
+    json!({
+        "event_id": format!("{:032x}", rand::random::<u128>()),
+        "timestamp": Utc::now().to_rfc3339(),
+        "platform": "rust",
+        "level": level,
+        "release": CLIENT,
+        "environment": reporter.environment,
+        "tags": tags,
+        "exception": {
+            "values": exceptions
+                .into_iter()
+                .rev()
+                .map(|(kind, value)| json!({ "type": kind, "value": scrub(&value) }))
+                .collect::<Vec<_>>(),
+        },
+    })
+}
+
+/// Wraps the event in the envelope format the `/envelope/` endpoint takes.
+fn envelope(event: &Value) -> String {
+    let payload = event.to_string();
+
+    format!(
+        "{}\n{}\n{}\n",
+        json!({ "event_id": event["event_id"] }),
+        json!({ "type": "event", "length": payload.len() }),
+        payload
+    )
+}
+
+fn auth_header(dsn: &Dsn) -> String {
+    format!(
+        "Sentry sentry_version=7, sentry_key={}, sentry_client={}",
+        dsn.key, CLIENT
+    )
+}
+
+/// Sends a command's error, with its context chain, if reporting is on.
+pub fn report_error(command: &str, error: &Report) {
+    let Some(reporter) = REPORTER.lock().clone() else {
+        return;
+    };
+
+    let exceptions = error
+        .chain()
+        .map(|cause| ("Error".to_owned(), cause.to_string()))
+        .collect();
+    let event = build_event(
+        &reporter,
+        "error",
+        exceptions,
+        json!({ "command": command }),
+    );
+
+    tokio::spawn(async move {
+        let sent = reqwest::Client::new()
+            .post(&reporter.dsn.envelope_url)
+            .header("X-Sentry-Auth", auth_header(&reporter.dsn))
+            .body(envelope(&event))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+
+        if let Err(e) = sent {
+            tracing::warn!("Couldn't report error: {:?}", e);
+        }
+    });
+}
+
+fn report_panic(info: &PanicHookInfo) {
+    let Some(reporter) = REPORTER.lock().clone() else {
+        return;
+    };
+
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_owned());
+    let location = info
+        .location()
+        .map(|location| format!("{}:{}", location.file(), location.line()))
+        .unwrap_or_default();
+
+    let event = build_event(
+        &reporter,
+        "fatal",
+        vec![("Panic".to_owned(), message)],
+        json!({ "location": location }),
+    );
+
+    // The panicking thread might be inside the async runtime, where blocking isn't allowed, and
+    // shouldn't be held up waiting on the send either
+    std::thread::spawn(move || {
+        let sent = reqwest::blocking::Client::new()
+            .post(&reporter.dsn.envelope_url)
+            .timeout(PANIC_SEND_TIMEOUT)
+            .header("X-Sentry-Auth", auth_header(&reporter.dsn))
+            .body(envelope(&event))
+            .send()
+            .and_then(reqwest::blocking::Response::error_for_status);
+
+        if let Err(e) = sent {
+            tracing::warn!("Couldn't report panic: {:?}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use color_eyre::eyre::WrapErr;
+
+    #[test]
+    fn parses_dsns() {
+        assert_eq!(
+            Dsn::parse("https://abc123@o1.ingest.sentry.io/42").unwrap(),
+            Dsn {
+                key: "abc123".to_owned(),
+                envelope_url: "https://o1.ingest.sentry.io/api/42/envelope/".to_owned(),
+            }
+        );
+        assert_eq!(
+            Dsn::parse("http://abc@localhost:8000/glitchtip/7")
+                .unwrap()
+                .envelope_url,
+            "http://localhost:8000/glitchtip/api/7/envelope/"
+        );
+        assert!(Dsn::parse("https://sentry.io/42").is_err());
+        assert!(Dsn::parse("https://abc@sentry.io/").is_err());
+    }
+
+    #[test]
+    fn scrubs_message_content() {
+        assert_eq!(
+            scrub(r#"Message { content: "my \"password\" is hunter2", author: <@1234> } in <#99>"#),
+            r#"Message { content: "[redacted]", author: <[redacted]> } in <[redacted]>"#
+        );
+        assert_eq!(
+            scrub("Unknown Member: no member alice@example.com (1234567890123) at https://x.io/a"),
+            "Unknown Member: no member [redacted] ([redacted]) at [redacted]"
+        );
+        assert_eq!(
+            scrub("Http(UnsuccessfulRequest(ErrorResponse { status_code: 403, error: DiscordJsonError { code: 50013 } }))"),
+            "Http(UnsuccessfulRequest(ErrorResponse { status_code: 403, error: DiscordJsonError { code: [redacted] } }))"
+        );
+        assert_eq!(
+            scrub("Couldn't create role: serenity::Error"),
+            "Couldn't create role: serenity::Error"
+        );
+    }
+
+    #[test]
+    fn sends_the_root_cause_first() {
+        let reporter = Reporter {
+            dsn: Dsn::parse("https://abc@sentry.io/1").unwrap(),
+            environment: None,
+        };
+        let error = Err::<(), _>(eyre!("Missing Access"))
+            .wrap_err("Couldn't create role")
+            .unwrap_err();
+        let event = build_event(
+            &reporter,
+            "error",
+            error
+                .chain()
+                .map(|cause| ("Error".to_owned(), cause.to_string()))
+                .collect(),
+            json!({}),
+        );
+
+        assert_eq!(event["exception"]["values"][0]["value"], "Missing Access");
+        assert_eq!(
+            event["exception"]["values"][1]["value"],
+            "Couldn't create role"
+        );
+
+        let envelope = envelope(&event);
+        assert_eq!(envelope.lines().count(), 3);
+    }
+}
@@ -1,25 +1,153 @@
 use crate::{
-    commands::lynch::handle_lynching, data::AppState, handle_starboards::handle_starboards,
+    alt_text::{handle_alt_text_button, nudge_alt_text},
+    announcements::handle_announcement_button,
+    attachment_archive::archive_attachments,
+    auto_publish::auto_publish,
+    auto_slowmode::count_for_slowmode,
+    auto_thread::create_auto_thread,
+    bookmarks::save_bookmark,
+    booster_perks::{forget_booster, update_booster_perks},
+    channel_activity::count_channel_activity,
+    class_digest::{track_message, track_reactions},
+    class_directory::{
+        handle_directory_select, handle_notifications_button, handle_notifications_select,
+    },
+    class_emoji::class_reaction_role,
+    class_mentions::limit_class_mentions,
+    commands::{lynch::handle_lynching, report_message::handle_report_button},
+    content_policy::enforce_content_policy,
+    custom_roles::{check_custom_role_eligibility, delete_custom_role},
+    data::{AppState, Data},
+    economy::{reward_message, reward_reaction},
+    error_help::explain_errors,
+    faq::suggest_faq,
+    handle_starboards::handle_starboards,
+    introductions::welcome_introduction,
+    link_preview::preview_message_links,
+    member_growth::{prime_invite_uses, record_join, record_leave},
+    mental_health::detect_crisis,
+    mention_replies::reply_to_mention,
+    mirror::mirror_message,
+    moderation::moderate_message,
+    modmail::relay_modmail,
+    name_policy::enforce_name_policy,
+    react_role_cache::track_react_role,
+    reposts::police_reposts,
+    response_variants::{track_variant_reactions, track_variant_reply},
+    role_expiry::track_temporary_roles,
     text_detection::text_detection,
+    unanswered_questions::watch_for_answer,
+    voice_activity::{reconcile_voice_sessions, track_voice_state},
 };
-use color_eyre::eyre::{Error, Result};
+use color_eyre::eyre::{eyre, Error, Result};
 use poise::serenity_prelude as serenity;
 use tap::Pipe;
 
 pub async fn event_handler(
     ctx: &serenity::Context,
     event: &serenity::FullEvent,
-    framework: poise::FrameworkContext<'_, AppState, Error>,
-    _data: &AppState,
+    framework: poise::FrameworkContext<'_, Data, Error>,
+    _data: &Data,
 ) -> Result<()> {
+    let reads_message_content = framework
+        .user_data
+        .config
+        .read()
+        .await
+        .reads_message_content();
+
     if let Err(e) = match event {
+        serenity::FullEvent::Message { new_message } if !reads_message_content => {
+            handle_message_without_content(ctx, framework.user_data, new_message).await
+        }
         serenity::FullEvent::Message { new_message } => {
             let message_text = &new_message.content;
             let message_link = &new_message.link();
 
             tracing::trace!("message {} received {}", message_text, message_link);
 
-            text_detection(ctx, framework.user_data, new_message).await
+            match moderate_message(ctx, framework.user_data, new_message).await {
+                // The message is gone, don't react to it
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(e) => tracing::error!("Error moderating message: {:?}", e),
+            }
+
+            match limit_class_mentions(ctx, framework.user_data, new_message).await {
+                // The re-post takes its place
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(e) => tracing::error!("Error limiting class mentions: {:?}", e),
+            }
+
+            match enforce_content_policy(ctx, framework.user_data, new_message).await {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(e) => tracing::error!("Error enforcing content policy: {:?}", e),
+            }
+
+            let (
+                detection,
+                digest,
+                questions,
+                thread,
+                alt_text,
+                link_preview,
+                publish,
+                mirror,
+                introduction,
+                mention,
+                faq,
+                archive,
+                slowmode,
+                crisis,
+                reward,
+                modmail,
+                repost,
+                error_help,
+            ) = tokio::join!(
+                text_detection(ctx, framework.user_data, new_message),
+                track_message(ctx, framework.user_data, new_message),
+                watch_for_answer(ctx, framework.user_data, new_message),
+                create_auto_thread(ctx, framework.user_data, new_message),
+                nudge_alt_text(ctx, framework.user_data, new_message),
+                preview_message_links(ctx, framework.user_data, new_message),
+                auto_publish(ctx, framework.user_data, new_message),
+                mirror_message(ctx, framework.user_data, new_message),
+                welcome_introduction(ctx, framework.user_data, new_message),
+                reply_to_mention(ctx, framework.user_data, new_message),
+                suggest_faq(ctx, framework.user_data, new_message),
+                archive_attachments(ctx, framework.user_data, new_message),
+                count_for_slowmode(framework.user_data, new_message),
+                detect_crisis(ctx, framework.user_data, new_message),
+                reward_message(framework.user_data, new_message),
+                relay_modmail(ctx, framework.user_data, new_message),
+                police_reposts(ctx, framework.user_data, new_message),
+                explain_errors(ctx, framework.user_data, new_message)
+            );
+
+            log_failures([
+                ("text detection", detection),
+                ("class digest", digest),
+                ("questions", questions),
+                ("auto thread", thread),
+                ("alt text", alt_text),
+                ("link previews", link_preview),
+                ("auto publish", publish),
+                ("mirroring", mirror),
+                ("introductions", introduction),
+                ("mention replies", mention),
+                ("faq", faq),
+                ("attachment archive", archive),
+                ("auto slowmode", slowmode),
+                ("crisis detection", crisis),
+                ("economy", reward),
+                ("modmail", modmail),
+                ("reposts", repost),
+                ("error help", error_help),
+            ])
+            .and(track_variant_reply(framework.user_data, new_message))
+            .and(count_channel_activity(framework.user_data, new_message))
         }
         serenity::FullEvent::ReactionAdd {
             add_reaction: reaction,
@@ -46,14 +174,80 @@ pub async fn event_handler(
 
             tokio::join!(
                 handle_lynching(ctx, &message),
-                handle_starboards(ctx, framework.user_data, &message, reaction)
+                handle_starboards(ctx, framework.user_data, &message, reaction),
+                save_bookmark(ctx, framework.user_data, &message, reaction),
+                class_reaction_role(ctx, framework.user_data, reaction, true),
+                reward_reaction(framework.user_data, &message, reaction)
             )
-            .pipe(|(err1, err2)| match (err1, err2) {
-                (Err(e), _) => Err(e),
-                (_, Err(e)) => Err(e),
-                _ => Ok(()),
+            .pipe(|(lynching, starboards, bookmarks, class_roles, reward)| {
+                log_failures([
+                    ("lynching", lynching),
+                    ("starboards", starboards),
+                    ("bookmarks", bookmarks),
+                    ("class reaction roles", class_roles),
+                    ("economy", reward),
+                ])
+                .and(track_reactions(framework.user_data, &message))
+                .and(track_variant_reactions(framework.user_data, &message))
             })
         }
+        serenity::FullEvent::ReactionRemove { removed_reaction } => {
+            class_reaction_role(ctx, framework.user_data, removed_reaction, false).await
+        }
+        serenity::FullEvent::InteractionCreate {
+            interaction: serenity::Interaction::Component(interaction),
+        } => handle_report_button(ctx, framework.user_data, interaction)
+            .await
+            .and(handle_alt_text_button(ctx, interaction).await)
+            .and(handle_directory_select(ctx, framework.user_data, interaction).await)
+            .and(handle_notifications_button(ctx, framework.user_data, interaction).await)
+            .and(handle_notifications_select(ctx, framework.user_data, interaction).await)
+            .and(handle_announcement_button(ctx, framework.user_data, interaction).await),
+        serenity::FullEvent::GuildMemberAddition { new_member } => {
+            enforce_name_policy(ctx, framework.user_data, new_member, false)
+                .await
+                .map(|_| ())
+                .and(track_temporary_roles(framework.user_data, new_member).await)
+                .and(track_react_role(framework.user_data, new_member).await)
+                .and(record_join(ctx, framework.user_data, new_member).await)
+        }
+        serenity::FullEvent::GuildMemberUpdate {
+            new: Some(member), ..
+        } => enforce_name_policy(ctx, framework.user_data, member, false)
+            .await
+            .map(|_| ())
+            .and(track_temporary_roles(framework.user_data, member).await)
+            .and(track_react_role(framework.user_data, member).await)
+            .and(check_custom_role_eligibility(ctx, framework.user_data, member).await)
+            .and(update_booster_perks(ctx, framework.user_data, member).await),
+        serenity::FullEvent::GuildMemberRemoval { guild_id, user, .. } => framework
+            .user_data
+            .react_roles
+            .forget(&framework.user_data.db, user.id)
+            .and(record_leave(framework.user_data, user))
+            .and(forget_booster(framework.user_data, user.id))
+            .and(
+                delete_custom_role(ctx, framework.user_data, *guild_id, user.id)
+                    .await
+                    .map(|_| ()),
+            ),
+        serenity::FullEvent::GuildCreate { guild, .. } => prime_invite_uses(ctx, guild.id)
+            .await
+            .and(reconcile_voice_sessions(framework.user_data, guild)),
+        serenity::FullEvent::GuildRoleDelete {
+            removed_role_id, ..
+        } => {
+            let data = framework.user_data;
+
+            if removed_role_id.get() == data.config.read().await.bot_react_role_id {
+                data.react_roles.clear(&data.db)
+            } else {
+                Ok(())
+            }
+        }
+        serenity::FullEvent::VoiceStateUpdate { new, .. } => {
+            track_voice_state(framework.user_data, new)
+        }
         serenity::FullEvent::Ratelimit { data } => {
             tracing::warn!("Ratelimited: {:?}", data);
             Ok(())
@@ -61,7 +255,55 @@ pub async fn event_handler(
         _ => Ok(()),
     } {
         tracing::error!("Error in event handler: {:?}", e);
+        framework
+            .user_data
+            .record_error(format!("event handler: {:?}", e));
     }
 
     Ok(())
 }
+
+/// Privacy mode, or no message content intent. Only what doesn't read messages runs, the rest
+/// would only ever see empty messages.
+/// Logs every subsystem that failed by name, so one failing doesn't hide the others, and fails
+/// if any of them did.
+fn log_failures<const N: usize>(results: [(&str, Result<()>); N]) -> Result<()> {
+    let failed = results
+        .into_iter()
+        .filter_map(|(name, result)| {
+            let e = result.err()?;
+            tracing::error!("Error in {}: {:?}", name, e);
+            Some(name)
+        })
+        .collect::<Vec<_>>();
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(eyre!("{} failed", failed.join(", ")))
+    }
+}
+
+async fn handle_message_without_content(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<()> {
+    let (digest, thread, publish, slowmode, reward, modmail) = tokio::join!(
+        track_message(ctx, data, message),
+        create_auto_thread(ctx, data, message),
+        auto_publish(ctx, data, message),
+        count_for_slowmode(data, message),
+        reward_message(data, message),
+        relay_modmail(ctx, data, message)
+    );
+
+    digest
+        .and(thread)
+        .and(publish)
+        .and(slowmode)
+        .and(reward)
+        .and(modmail)
+        .and(track_variant_reply(data, message))
+        .and(count_channel_activity(data, message))
+}
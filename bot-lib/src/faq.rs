@@ -0,0 +1,194 @@
+use crate::{author_guard::is_from_human, data::AppState, db::KingFisherDb};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+const FAQ_TREE: &str = "faq";
+
+/// Words that say nothing about what a question is about.
+const STOP_WORDS: [&str; 32] = [
+    "a", "an", "and", "are", "can", "do", "does", "for", "get", "how", "i", "in", "is", "it", "my",
+    "of", "on", "or", "should", "the", "there", "this", "to", "we", "what", "when", "where",
+    "which", "who", "why", "will", "you",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FaqEntry {
+    pub id: u64,
+    pub question: String,
+    pub answer: String,
+    pub added_by: u64,
+}
+
+/// Suggests FAQ entries when someone asks something similar in a help channel.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FaqSuggestions {
+    pub channel_ids: Vec<u64>,
+    /// How similar a question has to be to an entry, from 0 to 1.
+    #[serde(default = "get_default_threshold")]
+    pub threshold: f64,
+}
+
+fn get_default_threshold() -> f64 {
+    0.5
+}
+
+pub fn get_faq(db: &KingFisherDb) -> Result<Vec<FaqEntry>> {
+    db.values(FAQ_TREE)
+}
+
+pub fn add_faq(db: &KingFisherDb, question: String, answer: String, added_by: u64) -> Result<u64> {
+    let id = db.generate_id()?;
+
+    db.insert(
+        FAQ_TREE,
+        id.to_be_bytes(),
+        &FaqEntry {
+            id,
+            question,
+            answer,
+            added_by,
+        },
+    )?;
+
+    Ok(id)
+}
+
+pub fn remove_faq(db: &KingFisherDb, id: u64) -> Result<Option<FaqEntry>> {
+    db.remove(FAQ_TREE, id.to_be_bytes())
+}
+
+/// The words of a question that matter, lowercased and loosely de-pluralized.
+fn keywords(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|word| !word.is_empty() && !STOP_WORDS.contains(&word.as_str()))
+        .map(|word| match word.strip_suffix('s') {
+            Some(stem) if stem.len() > 2 && !stem.ends_with('s') => stem.to_owned(),
+            _ => word,
+        })
+        .collect()
+}
+
+/// How much two questions share, from 0 (nothing) to 1 (the same keywords).
+fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (keywords(a), keywords(b));
+    let union = a.union(&b).count();
+
+    if union == 0 {
+        return 0.;
+    }
+
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+/// The entry whose question is most like the query, and how alike they are.
+pub fn best_match<'a>(entries: &'a [FaqEntry], query: &str) -> Option<(&'a FaqEntry, f64)> {
+    entries
+        .iter()
+        .map(|entry| (entry, similarity(&entry.question, query)))
+        .filter(|(_, score)| *score > 0.)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+}
+
+pub fn format_entry(entry: &FaqEntry) -> String {
+    format!("**{}**\n{}", entry.question, entry.answer)
+}
+
+/// If a question in a help channel looks like one in the FAQ, reply with the answer.
+pub async fn suggest_faq(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<()> {
+    if !is_from_human(ctx, data, message).await || !message.content.trim_end().ends_with('?') {
+        return Ok(());
+    }
+
+    let Some(threshold) = data
+        .config
+        .read()
+        .await
+        .faq_suggestions
+        .as_ref()
+        .filter(|suggestions| suggestions.channel_ids.contains(&message.channel_id.get()))
+        .map(|suggestions| suggestions.threshold)
+    else {
+        return Ok(());
+    };
+
+    let entries = get_faq(&data.db)?;
+
+    let Some((entry, _)) =
+        best_match(&entries, &message.content).filter(|(_, score)| *score >= threshold)
+    else {
+        return Ok(());
+    };
+
+    message
+        .reply(
+            ctx,
+            format!("This might be in the FAQ:\n{}", format_entry(entry)),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// A short list of entries, for autocomplete and listing.
+pub fn entry_titles(entries: &[FaqEntry]) -> Vec<String> {
+    entries
+        .iter()
+        .map(|entry| format!("{}: {}", entry.id, entry.question))
+        .collect_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(id: u64, question: &str) -> FaqEntry {
+        FaqEntry {
+            id,
+            question: question.to_owned(),
+            answer: String::new(),
+            added_by: 0,
+        }
+    }
+
+    #[test]
+    fn finds_similar_questions() {
+        let entries = [
+            entry(1, "How do I get a CADE lab account?"),
+            entry(2, "When are the TA office hours?"),
+            entry(3, "Where can I find old exams?"),
+        ];
+
+        let find = |query| best_match(&entries, query).map(|(entry, _)| entry.id);
+
+        assert_eq!(find("how do i make a cade account"), Some(1));
+        assert_eq!(find("office hours today?"), Some(2));
+        assert_eq!(find("are there any old exams to study from"), Some(3));
+        assert_eq!(find("what's for lunch?"), None);
+    }
+
+    #[test]
+    fn scores_similarity() {
+        assert_eq!(similarity("Office hours?", "office hour"), 1.);
+        assert_eq!(similarity("How do I?", "What is it?"), 0.);
+        assert!(similarity("CADE lab account", "CADE account") > 0.5);
+    }
+
+    #[test]
+    fn round_trips_entries() {
+        let db = KingFisherDb::temporary().unwrap();
+
+        let id = add_faq(&db, "Q".to_owned(), "A".to_owned(), 7).unwrap();
+        assert_eq!(get_faq(&db).unwrap().len(), 1);
+
+        assert_eq!(remove_faq(&db, id).unwrap().unwrap().question, "Q");
+        assert!(get_faq(&db).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{config::Config, data::AppState, db::KingFisherDb};
+use color_eyre::eyre::Result;
+use poise::ChoiceParameter;
+use serde::{Deserialize, Serialize};
+
+const FEATURE_FLAGS_TREE: &str = "feature_flags";
+
+/// Subsystems mods can switch off without touching the config, with `/feature`.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, ChoiceParameter)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// Automatic replies to messages matching `[[responses]]`.
+    Responses,
+    Starboard,
+    /// Everything that asks the `llm`.
+    #[name = "LLM"]
+    Llm,
+    /// Earning currency for messages and reactions.
+    #[name = "Economy rewards"]
+    EconomyRewards,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 4] = [
+        Feature::Responses,
+        Feature::Starboard,
+        Feature::Llm,
+        Feature::EconomyRewards,
+    ];
+
+    fn key(self) -> &'static str {
+        match self {
+            Feature::Responses => "responses",
+            Feature::Starboard => "starboard",
+            Feature::Llm => "llm",
+            Feature::EconomyRewards => "economy_rewards",
+        }
+    }
+}
+
+/// What `/feature` set, if anything.
+pub fn get_override(db: &KingFisherDb, feature: Feature) -> Result<Option<bool>> {
+    db.get(FEATURE_FLAGS_TREE, feature.key())
+}
+
+pub fn set_override(db: &KingFisherDb, feature: Feature, enabled: Option<bool>) -> Result<()> {
+    match enabled {
+        Some(enabled) => db.insert(FEATURE_FLAGS_TREE, feature.key(), &enabled),
+        None => db
+            .remove::<bool>(FEATURE_FLAGS_TREE, feature.key())
+            .map(|_| ()),
+    }
+}
+
+/// `/feature` wins over `[features]`, and everything's on unless one of them says otherwise.
+///
+/// For use while the config is already locked.
+pub fn feature_enabled(db: &KingFisherDb, config: &Config, feature: Feature) -> bool {
+    let configured = config.features.get(&feature).copied().unwrap_or(true);
+
+    match get_override(db, feature) {
+        Ok(enabled) => enabled.unwrap_or(configured),
+        Err(e) => {
+            tracing::warn!("Couldn't read feature flag {:?}: {:?}", feature, e);
+            configured
+        }
+    }
+}
+
+pub async fn is_enabled(data: &AppState, feature: Feature) -> bool {
+    feature_enabled(&data.db, &*data.config.read().await, feature)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn overrides_win_over_config() {
+        let db = KingFisherDb::temporary().unwrap();
+        let mut config = Config::default();
+
+        assert!(feature_enabled(&db, &config, Feature::Llm));
+
+        config.features.insert(Feature::Llm, false);
+        assert!(!feature_enabled(&db, &config, Feature::Llm));
+        assert!(feature_enabled(&db, &config, Feature::Starboard));
+
+        set_override(&db, Feature::Llm, Some(true)).unwrap();
+        assert!(feature_enabled(&db, &config, Feature::Llm));
+
+        set_override(&db, Feature::Starboard, Some(false)).unwrap();
+        assert!(!feature_enabled(&db, &config, Feature::Starboard));
+
+        set_override(&db, Feature::Llm, None).unwrap();
+        assert!(!feature_enabled(&db, &config, Feature::Llm));
+        assert_eq!(get_override(&db, Feature::Llm).unwrap(), None);
+    }
+
+    #[test]
+    fn reads_config() {
+        let features: std::collections::HashMap<Feature, bool> =
+            toml::from_str("economy_rewards = false").unwrap();
+
+        assert_eq!(features.get(&Feature::EconomyRewards), Some(&false));
+    }
+}
@@ -1,4 +1,11 @@
-use crate::data::AppState;
+use crate::{
+    author_guard::human_reaction_count,
+    channel_activity::daily_average,
+    data::AppState,
+    feature_flags::{is_enabled, Feature},
+    outbound::send_webhook,
+    starboard_rewind::{record_starboard_post, update_starboard_post},
+};
 use color_eyre::eyre::{bail, Result};
 use poise::serenity_prelude::{self as serenity};
 use serenity::{Message, Reaction, ReactionType};
@@ -9,6 +16,10 @@ pub async fn handle_starboards(
     message: &Message,
     reaction: &Reaction,
 ) -> Result<()> {
+    if !is_enabled(data, Feature::Starboard).await {
+        return Ok(());
+    }
+
     let reaction_type = &reaction.emoji;
 
     let name = match reaction_type {
@@ -23,7 +34,7 @@ pub async fn handle_starboards(
         .reactions
         .iter()
         .find(|reaction| reaction.reaction_type == *reaction_type)
-        .map_or(0, |reaction| reaction.count);
+        .map_or(0, human_reaction_count);
 
     let config = data.config.read().await;
 
@@ -42,15 +53,41 @@ pub async fn handle_starboards(
             starboard_name
         );
 
+        let messages_per_day = if starboard.scales_by_activity() {
+            daily_average(&data.db, message.channel_id).unwrap_or_else(|e| {
+                tracing::warn!("Couldn't get channel activity: {:?}", e);
+                0.
+            })
+        } else {
+            0.
+        };
+
         if starboard
-            .does_starboard_apply(ctx, message, reaction_count, &name)
+            .does_starboard_apply(ctx, message, reaction_count, &name, messages_per_day)
             .await
         {
-            starboard.reply(ctx, message, reaction_type).await.ok();
+            let posted = starboard
+                .reply(ctx, message, reaction_type)
+                .await
+                .and_then(|_| record_starboard_post(data, message));
+
+            if let Err(e) = posted {
+                tracing::warn!("Couldn't post to starboard: {:?}", e);
+                return;
+            }
+
+            if let Some(webhook) = &starboard.external_webhook {
+                let channel = message.channel_id.name(ctx).await.unwrap_or_default();
+                let body = webhook.render(message, &channel, reaction_count);
+
+                if let Err(e) = send_webhook(&data.db, &webhook.url, body).await {
+                    tracing::warn!("Couldn't queue starboard webhook: {:?}", e);
+                }
+            }
         }
     });
 
     futures::future::join_all(futures).await;
 
-    Ok(())
+    update_starboard_post(data, message)
 }
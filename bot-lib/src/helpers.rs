@@ -0,0 +1,107 @@
+use crate::db::KingFisherDb;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::UserId;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+const HELPERS_TREE: &str = "helpers";
+/// Most volunteers pinged for one unanswered question, so nobody gets pinged for everything.
+const MAX_PINGED_HELPERS: usize = 5;
+
+/// Someone who volunteered to help with a class.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Helper {
+    pub class: u32,
+    pub user_id: u64,
+    pub since: DateTime<Utc>,
+}
+
+fn helper_key(class: u32, user_id: UserId) -> Vec<u8> {
+    [class.to_be_bytes().as_slice(), &user_id.get().to_be_bytes()].concat()
+}
+
+/// Volunteers for a class, longest serving first.
+pub fn get_helpers(db: &KingFisherDb, class: u32) -> Result<Vec<Helper>> {
+    let mut helpers = db
+        .values::<Helper>(HELPERS_TREE)?
+        .into_iter()
+        .filter(|helper| helper.class == class)
+        .collect::<Vec<_>>();
+
+    helpers.sort_by_key(|helper| helper.since);
+
+    Ok(helpers)
+}
+
+/// The classes someone helps with.
+pub fn get_helped_classes(db: &KingFisherDb, user_id: UserId) -> Result<Vec<u32>> {
+    let mut classes = db
+        .values::<Helper>(HELPERS_TREE)?
+        .into_iter()
+        .filter(|helper| helper.user_id == user_id.get())
+        .map(|helper| helper.class)
+        .collect::<Vec<_>>();
+
+    classes.sort();
+
+    Ok(classes)
+}
+
+/// Signs someone up to help with a class, or takes them off. Returns whether they're a helper now.
+pub fn toggle_helper(db: &KingFisherDb, class: u32, user_id: UserId) -> Result<bool> {
+    if db
+        .remove::<Helper>(HELPERS_TREE, helper_key(class, user_id))?
+        .is_some()
+    {
+        return Ok(false);
+    }
+
+    db.insert(
+        HELPERS_TREE,
+        helper_key(class, user_id),
+        &Helper {
+            class,
+            user_id: user_id.get(),
+            since: Utc::now(),
+        },
+    )?;
+
+    Ok(true)
+}
+
+/// A few of the class's volunteers to ping about a question, never the asker.
+pub fn helpers_to_ping(db: &KingFisherDb, class: u32, asker: UserId) -> Result<Vec<UserId>> {
+    let helpers = get_helpers(db, class)?
+        .into_iter()
+        .map(|helper| UserId::new(helper.user_id))
+        .filter(|user_id| *user_id != asker)
+        .collect::<Vec<_>>();
+
+    Ok(helpers
+        .choose_multiple(&mut rand::thread_rng(), MAX_PINGED_HELPERS)
+        .copied()
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn toggles_helpers() {
+        let db = KingFisherDb::temporary().unwrap();
+        let (alice, bob) = (UserId::new(1), UserId::new(2));
+
+        assert!(toggle_helper(&db, 2420, alice).unwrap());
+        assert!(toggle_helper(&db, 2420, bob).unwrap());
+        assert!(toggle_helper(&db, 3500, alice).unwrap());
+
+        assert_eq!(get_helpers(&db, 2420).unwrap().len(), 2);
+        assert_eq!(get_helped_classes(&db, alice).unwrap(), vec![2420, 3500]);
+        assert_eq!(helpers_to_ping(&db, 2420, alice).unwrap(), vec![bob]);
+
+        assert!(!toggle_helper(&db, 2420, bob).unwrap());
+        assert!(helpers_to_ping(&db, 2420, alice).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,203 @@
+use crate::{
+    config::Config,
+    starboard::{EmoteType, Starboard},
+};
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use poise::serenity_prelude::ChannelId;
+use std::{
+    io::{BufRead, Write},
+    str::FromStr,
+    sync::Arc,
+};
+
+/// Asks a question until it gets an answer that parses. Empty answers take the default.
+fn ask<T: FromStr>(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+    default: Option<&str>,
+) -> Result<T> {
+    loop {
+        match default {
+            Some(default) if !default.is_empty() => write!(output, "{} [{}]: ", question, default)?,
+            _ => write!(output, "{}: ", question)?,
+        }
+        output.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Err(eyre!("Ran out of input at \"{}\"", question));
+        }
+
+        let answer = match line.trim() {
+            "" => default.unwrap_or_default(),
+            answer => answer,
+        };
+
+        match answer.parse() {
+            Ok(value) => return Ok(value),
+            Err(_) => writeln!(output, "`{}` doesn't look right, try again.", answer)?,
+        }
+    }
+}
+
+/// Asks for ids until an empty answer.
+fn ask_ids(input: &mut impl BufRead, output: &mut impl Write, question: &str) -> Result<Vec<u64>> {
+    let mut ids = vec![];
+
+    while let Some(id) = ask::<OptionalId>(input, output, question, Some(""))?.0 {
+        ids.push(id);
+    }
+
+    Ok(ids)
+}
+
+/// An id, or nothing for an empty answer.
+struct OptionalId(Option<u64>);
+
+impl FromStr for OptionalId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "" => Ok(OptionalId(None)),
+            s => s.parse().map(|id| OptionalId(Some(id))),
+        }
+    }
+}
+
+/// Walks through the settings kingfisher needs to start, and builds a config from the answers.
+pub fn run_wizard(input: &mut impl BufRead, output: &mut impl Write) -> Result<Config> {
+    writeln!(
+        output,
+        "Setting up kingfisher. Turn on developer mode in Discord to copy ids with right click."
+    )?;
+
+    let guild_id = ask(input, output, "Server (guild) id", None)?;
+    let bot_react_role_id = ask(
+        input,
+        output,
+        "Id of the role that opts into bot replies",
+        None,
+    )?;
+    let mod_log_channel_id = ask::<OptionalId>(input, output, "Mod log channel id", Some(""))?.0;
+    let report_channel_id =
+        ask::<OptionalId>(input, output, "Reported messages channel id", Some(""))?.0;
+
+    let mut starboards = vec![];
+    writeln!(output, "Starboards, leave the channel id empty when done.")?;
+
+    while let Some(channel_id) =
+        ask::<OptionalId>(input, output, "Starboard channel id", Some(""))?.0
+    {
+        let emote_name = ask::<String>(
+            input,
+            output,
+            "Emote name, or empty for any emote",
+            Some(""),
+        )?;
+        let reaction_count = ask(input, output, "Reactions needed", Some("3"))?;
+
+        starboards.push(Arc::new(Starboard {
+            reaction_count,
+            channel_id,
+            emote_type: match emote_name.as_str() {
+                "" => EmoteType::AllEmotes { all_emotes: true },
+                _ => EmoteType::CustomEmote { emote_name },
+            },
+            ..Default::default()
+        }));
+    }
+
+    writeln!(output, "Class categories, leave empty when done.")?;
+    let class_categories = ask_ids(input, output, "Class category id")?
+        .into_iter()
+        .map(ChannelId::new)
+        .collect();
+
+    Ok(Config {
+        guild_id,
+        bot_react_role_id,
+        mod_log_channel_id,
+        report_channel_id,
+        starboards,
+        class_categories,
+        ..Default::default()
+    })
+}
+
+/// Runs the wizard on the terminal and writes the config, checking that it loads.
+pub fn run(config_path: &str) -> Result<()> {
+    if std::path::Path::new(config_path).exists() {
+        return Err(eyre!("{} already exists, not overwriting it", config_path));
+    }
+
+    let config = Config {
+        config_path: config_path.to_owned(),
+        ..run_wizard(&mut std::io::stdin().lock(), &mut std::io::stdout())?
+    };
+
+    config.save()?;
+    Config::create_from_file(config_path).wrap_err("The new config doesn't load")?;
+
+    println!("Wrote {}", config_path);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builds_config_from_answers() {
+        let answers = "\
+1065373537591894086
+nope
+1234
+
+5678
+42
+star
+
+
+98
+99
+
+";
+
+        let mut output = vec![];
+        let config = run_wizard(&mut answers.as_bytes(), &mut output).unwrap();
+
+        assert_eq!(config.guild_id, 1065373537591894086);
+        assert_eq!(config.bot_react_role_id, 1234);
+        assert_eq!(config.mod_log_channel_id, None);
+        assert_eq!(config.report_channel_id, Some(5678));
+        assert_eq!(
+            config.starboards,
+            vec![Arc::new(Starboard {
+                reaction_count: 3,
+                channel_id: 42,
+                emote_type: EmoteType::CustomEmote {
+                    emote_name: "star".to_owned()
+                },
+                ..Default::default()
+            })]
+        );
+        assert_eq!(
+            config.class_categories,
+            vec![ChannelId::new(98), ChannelId::new(99)]
+        );
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .contains("`nope` doesn't look right"));
+
+        let toml = toml::to_string(&config).unwrap();
+        assert_eq!(toml::from_str::<Config>(&toml).unwrap(), config);
+    }
+
+    #[test]
+    fn stops_when_input_runs_out() {
+        assert!(run_wizard(&mut "123\n".as_bytes(), &mut vec![]).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{data::AppState, mod_log::mod_log};
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, Write},
+    sync::Arc,
+    time::Duration,
+};
+
+const INSTANCE_TREE: &str = "instance";
+const HEARTBEAT_KEY: &str = "heartbeat";
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// A heartbeat older than this is from an instance that's stopped.
+const HEARTBEAT_STALE_AFTER: i64 = 90;
+
+/// Held for as long as the bot runs, so a second copy on this machine can't start.
+pub struct InstanceLock {
+    _file: File,
+}
+
+impl InstanceLock {
+    /// Locks `<db_path>.lock`, failing if another copy of the bot already has it.
+    pub fn acquire(db_path: &str) -> Result<InstanceLock> {
+        let path = format!("{}.lock", db_path);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .wrap_err_with(|| format!("Couldn't open {}", path))?;
+
+        if file.try_lock().is_err() {
+            let mut holder = String::new();
+            let _ = file.read_to_string(&mut holder);
+
+            return Err(eyre!(
+                "Another copy of kingfisher ({}) is already running with {}",
+                holder.trim(),
+                db_path
+            ));
+        }
+
+        file.set_len(0)?;
+        file.rewind()?;
+        write!(file, "{}", Heartbeat::new(0).describe())?;
+
+        Ok(InstanceLock { _file: file })
+    }
+}
+
+/// Written to the db every so often by the running instance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Heartbeat {
+    pub instance_id: u64,
+    pub host: String,
+    pub pid: u32,
+    pub at: i64,
+}
+
+impl Heartbeat {
+    fn new(instance_id: u64) -> Heartbeat {
+        Heartbeat {
+            instance_id,
+            host: hostname(),
+            pid: std::process::id(),
+            at: Utc::now().timestamp(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("pid {} on {}", self.pid, self.host)
+    }
+
+    /// Whether this is from a different copy of the bot that's still running.
+    ///
+    /// One from this machine is from a copy that's stopped, the file lock would've caught it
+    /// otherwise. So is one written before `started_at`, which is the last one a copy wrote before
+    /// handing over, like a container that's been replaced.
+    fn is_other_instance(&self, instance_id: u64, host: &str, started_at: i64, now: i64) -> bool {
+        self.instance_id != instance_id
+            && self.host != host
+            && self.at >= started_at
+            && now - self.at < HEARTBEAT_STALE_AFTER
+    }
+}
+
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .map(|host| host.trim().to_owned())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "unknown host".to_owned())
+}
+
+/// Lets the mods know a second copy tried to start, before this one exits.
+pub async fn warn_mods_of_duplicate(token: &str, mod_log_channel_id: Option<u64>, reason: &str) {
+    let Some(channel_id) = mod_log_channel_id else {
+        return;
+    };
+
+    let sent = serenity::ChannelId::new(channel_id)
+        .send_message(
+            &serenity::Http::new(token),
+            serenity::CreateMessage::new().embed(
+                serenity::CreateEmbed::new()
+                    .title("A second copy of kingfisher tried to start")
+                    .description(format!(
+                        "{}\nIt was stopped from starting ({}).",
+                        reason,
+                        Heartbeat::new(0).describe()
+                    ))
+                    .color(serenity::Color::ORANGE)
+                    .timestamp(serenity::Timestamp::now()),
+            ),
+        )
+        .await;
+
+    if let Err(e) = sent {
+        tracing::warn!("Couldn't warn mods about a duplicate instance: {:?}", e);
+    }
+}
+
+/// Keeps this instance's heartbeat fresh, warning mods if another instance's shows up.
+pub fn start_heartbeat(ctx: serenity::Context, data: Arc<AppState>) {
+    tokio::spawn(async move {
+        let instance_id = match data.db.generate_id() {
+            Ok(instance_id) => instance_id,
+            Err(e) => {
+                tracing::warn!("Couldn't start the instance heartbeat: {:?}", e);
+                return;
+            }
+        };
+        let started_at = Utc::now().timestamp();
+        let mut warned = false;
+        let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let me = Heartbeat::new(instance_id);
+
+            if let Ok(Some(other)) = data.db.get::<Heartbeat>(INSTANCE_TREE, HEARTBEAT_KEY) {
+                if !warned && other.is_other_instance(me.instance_id, &me.host, started_at, me.at) {
+                    warned = true;
+                    tracing::error!("Another instance is running: {}", other.describe());
+
+                    let _ = mod_log(
+                        &ctx,
+                        &data,
+                        serenity::CreateEmbed::new()
+                            .title("Two copies of kingfisher are running")
+                            .description(format!(
+                                "This one is {}, the other is {}. Both will respond to everything \
+                                 until one is stopped.",
+                                me.describe(),
+                                other.describe()
+                            ))
+                            .color(serenity::Color::RED),
+                    )
+                    .await;
+                }
+            }
+
+            if let Err(e) = data.db.insert(INSTANCE_TREE, HEARTBEAT_KEY, &me) {
+                tracing::warn!("Couldn't write the instance heartbeat: {:?}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn heartbeat(instance_id: u64, host: &str, at: i64) -> Heartbeat {
+        Heartbeat {
+            instance_id,
+            host: host.to_owned(),
+            pid: 1,
+            at,
+        }
+    }
+
+    #[test]
+    fn only_fresh_heartbeats_from_elsewhere_count() {
+        assert!(heartbeat(1, "other", 100).is_other_instance(2, "here", 90, 130));
+        // Stale
+        assert!(!heartbeat(1, "other", 100).is_other_instance(2, "here", 90, 200));
+        // Our own
+        assert!(!heartbeat(2, "other", 100).is_other_instance(2, "here", 90, 130));
+        // A copy on this machine that's since stopped
+        assert!(!heartbeat(1, "here", 100).is_other_instance(2, "here", 90, 130));
+        // The last heartbeat of the copy this one took over from
+        assert!(!heartbeat(1, "other", 100).is_other_instance(2, "here", 110, 130));
+    }
+
+    #[test]
+    fn lock_is_exclusive() {
+        let dir = std::env::temp_dir().join(format!("kingfisher-lock-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("kingfisher.db").to_string_lossy().into_owned();
+
+        let lock = InstanceLock::acquire(&db_path).unwrap();
+        assert!(InstanceLock::acquire(&db_path).is_err());
+
+        drop(lock);
+        assert!(InstanceLock::acquire(&db_path).is_ok());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,69 @@
+use poise::serenity_prelude::GatewayIntents;
+use serde::{Deserialize, Serialize};
+
+/// The privileged gateway intents kingfisher asks for, `[intents]` in the config.
+///
+/// Each has to be turned on for the bot in the developer portal too, or Discord refuses to connect.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Intents {
+    /// Needed for responses, moderation and anything else that reads messages.
+    #[serde(default = "yes")]
+    pub message_content: bool,
+    /// Needed for join and leave tracking, name policy and the react role cache.
+    #[serde(default = "yes")]
+    pub guild_members: bool,
+}
+
+fn yes() -> bool {
+    true
+}
+
+impl Default for Intents {
+    fn default() -> Self {
+        Intents {
+            message_content: true,
+            guild_members: true,
+        }
+    }
+}
+
+/// The intents to connect with. Privacy mode never asks for message content.
+pub fn gateway_intents(intents: Intents, privacy_mode: bool) -> GatewayIntents {
+    let mut gateway_intents = GatewayIntents::non_privileged()
+        | GatewayIntents::GUILD_MESSAGE_REACTIONS
+        | GatewayIntents::GUILD_MESSAGES
+        | GatewayIntents::GUILD_VOICE_STATES;
+
+    if intents.message_content && !privacy_mode {
+        gateway_intents |= GatewayIntents::MESSAGE_CONTENT;
+    }
+
+    if intents.guild_members {
+        gateway_intents |= GatewayIntents::GUILD_MEMBERS;
+    }
+
+    gateway_intents
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn privacy_mode_drops_message_content() {
+        let intents = Intents::default();
+
+        assert!(gateway_intents(intents, false).contains(GatewayIntents::MESSAGE_CONTENT));
+        assert!(!gateway_intents(intents, true).contains(GatewayIntents::MESSAGE_CONTENT));
+        assert!(gateway_intents(intents, true).contains(GatewayIntents::GUILD_MEMBERS));
+
+        let restricted = gateway_intents(
+            Intents {
+                message_content: false,
+                guild_members: false,
+            },
+            false,
+        );
+        assert!(!restricted.intersects(GatewayIntents::privileged()));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{author_guard::is_from_human, data::AppState, db::KingFisherDb};
+use chrono::Utc;
+use color_eyre::eyre::{Result, WrapErr};
+use poise::serenity_prelude::{self as serenity, UserId};
+use serde::{Deserialize, Serialize};
+
+const INTRODUCTIONS_TREE: &str = "introductions";
+
+/// Welcomes members who introduce themselves.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Introductions {
+    pub channel_id: u64,
+    /// Reacted to introductions, either a unicode emoji or `<:name:id>`.
+    #[serde(default = "get_default_welcome_emoji")]
+    pub welcome_emoji: String,
+    /// Given to members once they've introduced themselves.
+    pub introduced_role_id: Option<u64>,
+}
+
+fn get_default_welcome_emoji() -> String {
+    "👋".to_owned()
+}
+
+/// A member's first introduction, kept to see how many people make it through onboarding.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Introduction {
+    pub message_id: u64,
+    pub thread_id: u64,
+    pub introduced_at: i64,
+}
+
+pub fn get_introduction(db: &KingFisherDb, user_id: UserId) -> Result<Option<Introduction>> {
+    db.get(INTRODUCTIONS_TREE, user_id.get().to_be_bytes())
+}
+
+/// React to, open a thread on and hand out the role for a member's first introduction.
+pub async fn welcome_introduction(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<()> {
+    if !is_from_human(ctx, data, message).await {
+        return Ok(());
+    }
+
+    let Some(introductions) = data
+        .config
+        .read()
+        .await
+        .introductions
+        .clone()
+        .filter(|introductions| introductions.channel_id == message.channel_id.get())
+    else {
+        return Ok(());
+    };
+
+    // Only the first introduction gets the welcome, follow ups go in the thread
+    if get_introduction(&data.db, message.author.id)?.is_some() {
+        return Ok(());
+    }
+
+    let emoji = serenity::ReactionType::try_from(introductions.welcome_emoji.as_str())
+        .wrap_err("Invalid welcome emoji")?;
+    message.react(ctx, emoji).await?;
+
+    let name = message
+        .member
+        .as_ref()
+        .and_then(|member| member.nick.as_deref())
+        .or(message.author.global_name.as_deref())
+        .unwrap_or(&message.author.name);
+
+    let thread = message
+        .channel_id
+        .create_thread_from_message(
+            ctx,
+            message.id,
+            serenity::CreateThread::new(format!("Welcome, {}!", name)),
+        )
+        .await
+        .wrap_err("Couldn't create introduction thread")?;
+
+    if let (Some(role_id), Some(guild_id)) = (introductions.introduced_role_id, message.guild_id) {
+        ctx.http
+            .add_member_role(
+                guild_id,
+                message.author.id,
+                serenity::RoleId::new(role_id),
+                Some("Introduced themselves"),
+            )
+            .await
+            .wrap_err("Couldn't give the introduced role")?;
+    }
+
+    data.db.insert(
+        INTRODUCTIONS_TREE,
+        message.author.id.get().to_be_bytes(),
+        &Introduction {
+            message_id: message.id.get(),
+            thread_id: thread.id.get(),
+            introduced_at: Utc::now().timestamp(),
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_config() {
+        let introductions: Introductions = toml::from_str("channel_id = 1").unwrap();
+
+        assert_eq!(
+            introductions,
+            Introductions {
+                channel_id: 1,
+                welcome_emoji: "👋".to_owned(),
+                introduced_role_id: None,
+            }
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{data::AppState, db::KingFisherDb};
+use chrono::{NaiveDate, Utc};
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, ChannelId, MessageId};
+use serde::{Deserialize, Serialize};
+
+const JOB_POSTINGS_TREE: &str = "job_postings";
+const JOB_BOARD_TREE: &str = "job_board";
+const ARCHIVE_THREAD_KEY: &str = "archive_thread";
+
+/// A channel for internship and job postings made with `/post_job`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct JobBoard {
+    pub channel_id: u64,
+    /// How long a posting without a deadline stays up.
+    #[serde(default = "get_default_lifetime_days")]
+    pub default_lifetime_days: u32,
+}
+
+fn get_default_lifetime_days() -> u32 {
+    30
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobPosting {
+    pub id: u64,
+    pub company: String,
+    pub role: String,
+    pub link: String,
+    /// Applications close at the end of this day, then the posting gets archived.
+    pub deadline: NaiveDate,
+    pub posted_by: u64,
+    pub message_id: u64,
+    #[serde(default)]
+    pub archived: bool,
+}
+
+impl JobPosting {
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
+        self.deadline < today
+    }
+
+    /// Whether the keyword shows up in the company or role, ignoring case.
+    pub fn matches(&self, keyword: &str) -> bool {
+        let keyword = keyword.to_lowercase();
+
+        self.company.to_lowercase().contains(&keyword)
+            || self.role.to_lowercase().contains(&keyword)
+    }
+
+    pub fn embed(&self) -> serenity::CreateEmbed {
+        serenity::CreateEmbed::new()
+            .title(format!("{} at {}", self.role, self.company))
+            .url(&self.link)
+            .field("Company", &self.company, true)
+            .field("Role", &self.role, true)
+            .field(
+                "Apply by",
+                self.deadline.format("%b %-d, %Y").to_string(),
+                true,
+            )
+            .field("Posted by", format!("<@{}>", self.posted_by), true)
+            .footer(serenity::CreateEmbedFooter::new(format!(
+                "Posting #{}, archived after the deadline",
+                self.id
+            )))
+    }
+
+    /// A one line version for search results.
+    pub fn summary(&self) -> String {
+        format!(
+            "**{}** at {} (apply by {}){} {}",
+            self.role,
+            self.company,
+            self.deadline.format("%b %-d"),
+            if self.archived { ", closed" } else { "" },
+            self.link
+        )
+    }
+}
+
+pub fn save_posting(db: &KingFisherDb, posting: &JobPosting) -> Result<()> {
+    db.insert(JOB_POSTINGS_TREE, posting.id.to_be_bytes(), posting)
+}
+
+/// Postings mentioning the keyword, open ones first and then by deadline.
+pub fn search_postings(db: &KingFisherDb, keyword: &str) -> Result<Vec<JobPosting>> {
+    let mut postings = db
+        .values::<JobPosting>(JOB_POSTINGS_TREE)?
+        .into_iter()
+        .filter(|posting| posting.matches(keyword))
+        .collect::<Vec<_>>();
+
+    postings.sort_by_key(|posting| (posting.archived, posting.deadline));
+
+    Ok(postings)
+}
+
+/// The thread expired postings get moved into, made the first time it's needed.
+async fn archive_thread(
+    ctx: &serenity::Context,
+    db: &KingFisherDb,
+    channel_id: ChannelId,
+) -> Result<ChannelId> {
+    if let Some(thread_id) = db.get::<u64>(JOB_BOARD_TREE, ARCHIVE_THREAD_KEY)? {
+        let thread_id = ChannelId::new(thread_id);
+
+        if thread_id.to_channel(ctx).await.is_ok() {
+            return Ok(thread_id);
+        }
+    }
+
+    let thread = channel_id
+        .create_thread(
+            ctx,
+            serenity::CreateThread::new("Closed postings")
+                .kind(serenity::ChannelType::PublicThread)
+                .auto_archive_duration(serenity::AutoArchiveDuration::OneWeek),
+        )
+        .await?;
+
+    db.insert(JOB_BOARD_TREE, ARCHIVE_THREAD_KEY, &thread.id.get())?;
+
+    Ok(thread.id)
+}
+
+/// Moves postings past their deadline out of the jobs channel and into the archive thread.
+pub async fn archive_expired_postings(ctx: &serenity::Context, data: &AppState) -> Result<()> {
+    let Some(board) = data.config.read().await.job_board.clone() else {
+        return Ok(());
+    };
+
+    let today = Utc::now().date_naive();
+    let expired = data
+        .db
+        .values::<JobPosting>(JOB_POSTINGS_TREE)?
+        .into_iter()
+        .filter(|posting| !posting.archived && posting.is_expired(today))
+        .collect::<Vec<_>>();
+
+    if expired.is_empty() {
+        return Ok(());
+    }
+
+    let channel_id = ChannelId::new(board.channel_id);
+    let thread_id = archive_thread(ctx, &data.db, channel_id).await?;
+
+    for mut posting in expired {
+        thread_id
+            .send_message(ctx, serenity::CreateMessage::new().embed(posting.embed()))
+            .await?;
+
+        if let Err(e) = channel_id
+            .delete_message(ctx, MessageId::new(posting.message_id))
+            .await
+        {
+            tracing::debug!("Couldn't delete job posting #{}: {:?}", posting.id, e);
+        }
+
+        posting.archived = true;
+        save_posting(&data.db, &posting)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn posting(id: u64, company: &str, role: &str, deadline: NaiveDate) -> JobPosting {
+        JobPosting {
+            id,
+            company: company.to_owned(),
+            role: role.to_owned(),
+            link: "https://example.com".to_owned(),
+            deadline,
+            posted_by: 1,
+            message_id: id,
+            archived: false,
+        }
+    }
+
+    #[test]
+    fn expires_after_deadline() {
+        let deadline = NaiveDate::from_ymd_opt(2024, 10, 1).unwrap();
+        let posting = posting(1, "Acme", "SWE Intern", deadline);
+
+        assert!(!posting.is_expired(deadline));
+        assert!(posting.is_expired(deadline.succ_opt().unwrap()));
+    }
+
+    #[test]
+    fn searches_postings() {
+        let db = KingFisherDb::temporary().unwrap();
+        let soon = NaiveDate::from_ymd_opt(2024, 10, 1).unwrap();
+        let later = NaiveDate::from_ymd_opt(2024, 11, 1).unwrap();
+
+        let mut closed = posting(1, "Acme", "Backend Intern", soon);
+        closed.archived = true;
+        save_posting(&db, &closed).unwrap();
+        save_posting(&db, &posting(2, "Initech", "Intern, Data", later)).unwrap();
+        save_posting(&db, &posting(3, "Acme", "QA Engineer", soon)).unwrap();
+
+        let ids = |keyword| {
+            search_postings(&db, keyword)
+                .unwrap()
+                .iter()
+                .map(|posting| posting.id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids("intern"), vec![2, 1]);
+        assert_eq!(ids("ACME"), vec![3, 1]);
+        assert_eq!(ids("hedge fund"), Vec::<u64>::new());
+    }
+}
@@ -1,9 +1,95 @@
+mod alt_text;
+mod announcements;
+mod attachment_archive;
+pub mod audit_log;
+mod author_guard;
+mod auto_publish;
+mod auto_slowmode;
+mod auto_thread;
+mod bookmarks;
+mod booster_perks;
+mod burst_limit;
+mod capabilities;
+pub mod changelog;
+mod channel_activity;
+mod charts;
+mod class_archive;
+mod class_digest;
+mod class_directory;
+mod class_emoji;
+mod class_info;
+mod class_mentions;
+mod class_notifications;
+pub mod command_channels;
+pub mod command_limits;
 pub mod commands;
 pub mod config;
+mod content_policy;
+mod course_reviews;
+mod cross_post;
+mod custom_roles;
 pub mod data;
+mod datetime;
+pub mod db;
+mod deleted_categories;
+mod departments;
+mod discord_api;
+mod economy;
+mod emoji_sync;
+mod error_help;
+pub mod error_reporting;
 pub mod event_handler;
+mod faq;
+mod feature_flags;
 mod handle_starboards;
+mod helpers;
+pub mod init;
+pub mod instance_lock;
+pub mod intents;
+mod introductions;
+mod job_board;
 mod lang;
+mod link_preview;
+mod llm;
+mod member_growth;
+mod mental_health;
+mod mention_replies;
+mod mirror;
+mod mod_log;
+mod moderation;
+mod modmail;
+mod name_policy;
+mod ocr;
+mod onboarding;
+mod outbound;
+mod partners;
+pub mod presence;
+mod profile;
+mod progress;
+mod quiet_hours;
+mod random_image;
+mod react_role_cache;
+mod rename_votes;
+mod reposts;
+pub mod response_import;
+mod response_variants;
+mod role_expiry;
+mod role_grants;
+mod scheduled_messages;
+pub mod scheduler;
+mod seasons;
+mod serious_gate;
+mod server_themes;
+pub mod simulate;
 mod starboard;
+mod starboard_export;
+mod starboard_rewind;
 mod text_detection;
+mod tickets;
+mod toml_merge;
+mod two_person;
+mod unanswered_questions;
 mod utils;
+mod voice;
+mod voice_activity;
+mod webhooks;
@@ -0,0 +1,158 @@
+use crate::author_guard::is_from_human;
+use crate::data::AppState;
+use crate::utils::member_permissions_in;
+use color_eyre::eyre::Result;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{self as serenity, ChannelId, GuildId, MessageId};
+use regex::Regex;
+
+/// Only the first few links get a preview, so a list of links doesn't flood the channel.
+const MAX_PREVIEWS: usize = 3;
+const MAX_PREVIEW_LENGTH: usize = 1000;
+
+lazy_static! {
+    static ref MESSAGE_LINK: Regex =
+        Regex::new(r"https://(?:ptb\.|canary\.)?discord(?:app)?\.com/channels/(\d+)/(\d+)/(\d+)")
+            .expect("Message link regex should be valid");
+}
+
+/// Every message link in the text, as (guild, channel, message) ids.
+fn parse_message_links(text: &str) -> Vec<(GuildId, ChannelId, MessageId)> {
+    MESSAGE_LINK
+        .captures_iter(text)
+        .filter_map(|captures| {
+            let id = |i: usize| captures[i].parse::<u64>().ok().filter(|id| *id != 0);
+
+            Some((
+                GuildId::new(id(1)?),
+                ChannelId::new(id(2)?),
+                MessageId::new(id(3)?),
+            ))
+        })
+        .take(MAX_PREVIEWS)
+        .collect()
+}
+
+/// The first message link in the text, for commands that take one.
+pub fn parse_message_link(text: &str) -> Option<(GuildId, ChannelId, MessageId)> {
+    parse_message_links(text).into_iter().next()
+}
+
+/// Whether the author of the link can see the channel it points to, so previews can't leak private channels.
+async fn can_view(
+    ctx: &serenity::Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    user_id: serenity::UserId,
+) -> Result<bool> {
+    let member = guild_id.member(ctx, user_id).await?;
+    let permissions = member_permissions_in(ctx, guild_id, channel_id, &member).await?;
+
+    Ok(permissions.view_channel() && permissions.read_message_history())
+}
+
+fn preview_embed(message: &serenity::Message) -> serenity::CreateEmbed {
+    let mut content = message
+        .content
+        .chars()
+        .take(MAX_PREVIEW_LENGTH)
+        .collect::<String>();
+
+    if content.len() < message.content.len() {
+        content.push_str("...");
+    }
+
+    let author = serenity::CreateEmbedAuthor::new(&message.author.name).icon_url(
+        message
+            .author
+            .avatar_url()
+            .as_deref()
+            .unwrap_or("https://cdn.discordapp.com/embed/avatars/0.png"),
+    );
+
+    serenity::CreateEmbed::new()
+        .author(author)
+        .description(format!(
+            "{}\n\n[Jump to message]({})",
+            content,
+            message.link()
+        ))
+        .timestamp(message.timestamp)
+}
+
+/// Quotes the messages linked to in a message, in channels where that's turned on.
+pub async fn preview_message_links(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<()> {
+    if !is_from_human(ctx, data, message).await {
+        return Ok(());
+    }
+
+    if !data
+        .config
+        .read()
+        .await
+        .link_preview_channels
+        .contains(&message.channel_id.get())
+    {
+        return Ok(());
+    }
+
+    let Some(guild_id) = message.guild_id else {
+        return Ok(());
+    };
+
+    let mut embeds = vec![];
+
+    for (link_guild_id, channel_id, message_id) in parse_message_links(&message.content) {
+        if link_guild_id != guild_id {
+            continue;
+        }
+
+        match can_view(ctx, guild_id, channel_id, message.author.id).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                tracing::debug!("Couldn't check permissions for link preview: {:?}", e);
+                continue;
+            }
+        }
+
+        if let Ok(linked) = channel_id.message(ctx, message_id).await {
+            embeds.push(preview_embed(&linked));
+        }
+    }
+
+    if embeds.is_empty() {
+        return Ok(());
+    }
+
+    message
+        .channel_id
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .reference_message(message)
+                .allowed_mentions(serenity::CreateAllowedMentions::new())
+                .embeds(embeds),
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[test]
+fn finds_message_links() {
+    assert_eq!(
+        parse_message_links(
+            "see https://discord.com/channels/1/2/3 and https://canary.discordapp.com/channels/4/5/6, \
+            not https://discord.com/channels/1/2 or https://example.com/channels/1/2/3"
+        ),
+        vec![
+            (GuildId::new(1), ChannelId::new(2), MessageId::new(3)),
+            (GuildId::new(4), ChannelId::new(5), MessageId::new(6)),
+        ]
+    );
+}
@@ -0,0 +1,102 @@
+use color_eyre::eyre::{OptionExt, Result, WrapErr};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Long enough for a slow model to describe an image, short enough that a hung endpoint
+/// doesn't hang whatever is waiting on it.
+const LLM_TIMEOUT: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    static ref CLIENT: reqwest::Client = reqwest::Client::builder()
+        .timeout(LLM_TIMEOUT)
+        .build()
+        .expect("Couldn't build the LLM http client");
+}
+
+/// An OpenAI compatible chat completions backend.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Llm {
+    /// e.g. `https://api.openai.com/v1/chat/completions`
+    pub api_url: String,
+    pub model: String,
+    /// The environment variable holding the api key, so it stays out of the config.
+    #[serde(default = "get_default_api_key_env")]
+    pub api_key_env: String,
+}
+
+fn get_default_api_key_env() -> String {
+    "LLM_API_KEY".to_owned()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LlmMessage {
+    role: &'static str,
+    content: Value,
+}
+
+impl LlmMessage {
+    pub fn system(text: &str) -> Self {
+        LlmMessage {
+            role: "system",
+            content: json!(text),
+        }
+    }
+
+    pub fn user(text: &str) -> Self {
+        LlmMessage {
+            role: "user",
+            content: json!(text),
+        }
+    }
+
+    pub fn user_with_image(text: &str, image_url: &str) -> Self {
+        LlmMessage {
+            role: "user",
+            content: json!([
+                { "type": "text", "text": text },
+                { "type": "image_url", "image_url": { "url": image_url } },
+            ]),
+        }
+    }
+}
+
+impl Llm {
+    pub async fn chat(&self, messages: &[LlmMessage]) -> Result<String> {
+        let api_key = std::env::var(&self.api_key_env)
+            .wrap_err_with(|| format!("Expected an api key in {}", self.api_key_env))?;
+
+        let response: Value = CLIENT
+            .post(&self.api_url)
+            .bearer_auth(api_key)
+            .json(&json!({
+                "model": self.model,
+                "messages": messages,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        response["choices"][0]["message"]["content"]
+            .as_str()
+            .map(|content| content.trim().to_owned())
+            .ok_or_eyre("LLM response had no content")
+    }
+}
+
+#[test]
+fn check_message_serialization() {
+    assert_eq!(
+        serde_json::to_value(LlmMessage::user_with_image("describe", "https://a.png")).unwrap(),
+        json!({
+            "role": "user",
+            "content": [
+                { "type": "text", "text": "describe" },
+                { "type": "image_url", "image_url": { "url": "https://a.png" } },
+            ]
+        })
+    );
+}
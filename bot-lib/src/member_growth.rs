@@ -0,0 +1,594 @@
+use crate::data::AppState;
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use color_eyre::eyre::{eyre, Result};
+use itertools::Itertools;
+use parking_lot::Mutex;
+use poise::serenity_prelude::{self as serenity, GuildId, UserId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const MEMBER_EVENTS_TREE: &str = "member_events";
+const INVITE_LABELS_TREE: &str = "invite_labels";
+const CHART_WIDTH: u64 = 30;
+const TOP_SOURCES: usize = 5;
+
+lazy_static::lazy_static! {
+    /// How many times each invite had been used, as of the last join.
+    ///
+    /// `None` until it's been filled in, since diffing against nothing would credit every invite.
+    static ref INVITE_USES: Mutex<Option<HashMap<String, u64>>> = Mutex::new(None);
+}
+
+/// Someone joining or leaving the server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemberEvent {
+    pub user_id: u64,
+    pub joined: bool,
+    pub timestamp: i64,
+    /// The invite they (probably) joined through.
+    pub invite_code: Option<String>,
+    pub inviter_id: Option<u64>,
+}
+
+/// An invite made with `/create_invite`, for telling outreach channels apart.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InviteLabel {
+    pub code: String,
+    /// e.g. "flyer QR code" or "CS1030 syllabus".
+    pub label: String,
+    pub created_by: u64,
+    pub created_at: i64,
+}
+
+pub fn save_invite_label(data: &AppState, label: &InviteLabel) -> Result<()> {
+    data.db.insert(INVITE_LABELS_TREE, &label.code, label)?;
+
+    // A brand new invite hasn't been used yet
+    if let Some(invite_uses) = INVITE_USES.lock().as_mut() {
+        invite_uses.insert(label.code.clone(), 0);
+    }
+
+    Ok(())
+}
+
+pub fn invite_labels(data: &AppState) -> Result<Vec<InviteLabel>> {
+    data.db.values(INVITE_LABELS_TREE)
+}
+
+/// How many people a labeled invite brought in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelReport<'a> {
+    pub label: &'a InviteLabel,
+    pub joins: usize,
+    /// Joins in the last 30 days.
+    pub recent_joins: usize,
+    /// Joins that haven't left since.
+    pub stayed: usize,
+}
+
+/// Labeled invites by how many people they brought in, most first.
+pub fn label_reports<'a>(
+    labels: &'a [InviteLabel],
+    events: &[MemberEvent],
+    now: i64,
+) -> Vec<LabelReport<'a>> {
+    let recent = now - 30 * 24 * 60 * 60;
+    let last_leave = events
+        .iter()
+        .filter(|event| !event.joined)
+        .into_grouping_map_by(|event| event.user_id)
+        .max_by_key(|_, event| event.timestamp);
+
+    labels
+        .iter()
+        .map(|label| {
+            let joins = events
+                .iter()
+                .filter(|event| event.joined && event.invite_code.as_ref() == Some(&label.code))
+                .collect_vec();
+
+            LabelReport {
+                label,
+                joins: joins.len(),
+                recent_joins: joins.iter().filter(|join| join.timestamp >= recent).count(),
+                stayed: joins
+                    .iter()
+                    .filter(|join| {
+                        last_leave
+                            .get(&join.user_id)
+                            .is_none_or(|leave| leave.timestamp < join.timestamp)
+                    })
+                    .count(),
+            }
+        })
+        .sorted_by(|a, b| {
+            b.joins
+                .cmp(&a.joins)
+                .then(a.label.label.cmp(&b.label.label))
+        })
+        .collect()
+}
+
+/// Remembers how often each invite has been used, so the next join can be attributed.
+pub async fn prime_invite_uses(ctx: &serenity::Context, guild_id: GuildId) -> Result<()> {
+    let invites = guild_id.invites(ctx).await?;
+
+    *INVITE_USES.lock() = Some(
+        invites
+            .into_iter()
+            .map(|invite| (invite.code, invite.uses))
+            .collect(),
+    );
+
+    Ok(())
+}
+
+/// Records a join, with the invite whose uses went up since the last one.
+pub async fn record_join(
+    ctx: &serenity::Context,
+    data: &AppState,
+    member: &serenity::Member,
+) -> Result<()> {
+    if member.user.bot {
+        return Ok(());
+    }
+
+    let used_invite = match member.guild_id.invites(ctx).await {
+        Ok(invites) => {
+            let mut invite_uses = INVITE_USES.lock();
+
+            let used = invite_uses
+                .as_ref()
+                .and_then(|before| find_used_invite(before, &invites))
+                .map(|invite| {
+                    (
+                        invite.code.clone(),
+                        invite.inviter.as_ref().map(|inviter| inviter.id.get()),
+                    )
+                });
+
+            *invite_uses = Some(
+                invites
+                    .into_iter()
+                    .map(|invite| (invite.code, invite.uses))
+                    .collect(),
+            );
+
+            used
+        }
+        Err(e) => {
+            tracing::warn!("Couldn't fetch invites, needs Manage Server: {:?}", e);
+            None
+        }
+    };
+
+    let (invite_code, inviter_id) = used_invite.unzip();
+
+    record_event(
+        data,
+        MemberEvent {
+            user_id: member.user.id.get(),
+            joined: true,
+            timestamp: Utc::now().timestamp(),
+            invite_code,
+            inviter_id: inviter_id.flatten(),
+        },
+    )
+}
+
+pub fn record_leave(data: &AppState, user: &serenity::User) -> Result<()> {
+    if user.bot {
+        return Ok(());
+    }
+
+    record_event(
+        data,
+        MemberEvent {
+            user_id: user.id.get(),
+            joined: false,
+            timestamp: Utc::now().timestamp(),
+            invite_code: None,
+            inviter_id: None,
+        },
+    )
+}
+
+fn record_event(data: &AppState, event: MemberEvent) -> Result<()> {
+    data.db.insert(
+        MEMBER_EVENTS_TREE,
+        data.db.generate_id()?.to_be_bytes(),
+        &event,
+    )
+}
+
+pub fn member_events(data: &AppState) -> Result<Vec<MemberEvent>> {
+    data.db.values(MEMBER_EVENTS_TREE)
+}
+
+/// The one invite that got used since `before`, if it's clear which.
+fn find_used_invite<'a>(
+    before: &HashMap<String, u64>,
+    after: &'a [serenity::RichInvite],
+) -> Option<&'a serenity::RichInvite> {
+    after
+        .iter()
+        .filter(|invite| invite.uses > before.get(&invite.code).copied().unwrap_or_default())
+        .exactly_one()
+        .ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Term {
+    Spring,
+    Summer,
+    Fall,
+}
+
+/// A semester, e.g. `fall 2024`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Semester {
+    pub term: Term,
+    pub year: i32,
+}
+
+/// Years a semester can be in, from when the server could have started, so every semester has
+/// real start and end dates.
+const SEMESTER_YEARS: std::ops::RangeInclusive<i32> = 2015..=2100;
+
+impl Semester {
+    /// Spring is January through April, summer May through July, and fall August through December.
+    pub fn containing(date: NaiveDate) -> Semester {
+        let term = match date.month() {
+            1..=4 => Term::Spring,
+            5..=7 => Term::Summer,
+            _ => Term::Fall,
+        };
+
+        Semester {
+            term,
+            year: date.year(),
+        }
+    }
+
+    /// Parses `fall 2024`, or just `fall` for this year's.
+    pub fn parse(input: &str, today: NaiveDate) -> Result<Semester> {
+        let mut words = input.split_whitespace();
+
+        let term = match words.next().map(str::to_lowercase).as_deref() {
+            Some("spring") => Term::Spring,
+            Some("summer") => Term::Summer,
+            Some("fall") => Term::Fall,
+            _ => return Err(eyre!("Expected a semester like `fall 2024`")),
+        };
+
+        let year = match words.next() {
+            Some(year) => year
+                .parse()
+                .ok()
+                .filter(|year| SEMESTER_YEARS.contains(year))
+                .ok_or_else(|| eyre!("`{}` isn't a year", year))?,
+            None => today.year(),
+        };
+
+        Ok(Semester { term, year })
+    }
+
+    pub fn bounds(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        let date = |year, month| {
+            let date =
+                NaiveDate::from_ymd_opt(year, month, 1).expect("Semesters start on real days");
+            Utc.from_utc_datetime(&date.and_time(chrono::NaiveTime::MIN))
+        };
+
+        match self.term {
+            Term::Spring => (date(self.year, 1), date(self.year, 5)),
+            Term::Summer => (date(self.year, 5), date(self.year, 8)),
+            Term::Fall => (date(self.year, 8), date(self.year + 1, 1)),
+        }
+    }
+}
+
+impl std::fmt::Display for Semester {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let term = match self.term {
+            Term::Spring => "Spring",
+            Term::Summer => "Summer",
+            Term::Fall => "Fall",
+        };
+
+        write!(f, "{} {}", term, self.year)
+    }
+}
+
+/// Joins and leaves per week of the semester.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WeekCounts {
+    pub joins: u64,
+    pub leaves: u64,
+}
+
+pub fn weekly_counts(events: &[MemberEvent], semester: &Semester) -> Vec<WeekCounts> {
+    let (start, end) = semester.bounds();
+    let (start, end) = (start.timestamp(), end.timestamp());
+    let week = 7 * 24 * 60 * 60;
+    let mut weeks = vec![WeekCounts::default(); ((end - start + week - 1) / week) as usize];
+
+    for event in events
+        .iter()
+        .filter(|event| (start..end).contains(&event.timestamp))
+    {
+        let counts = &mut weeks[((event.timestamp - start) / week) as usize];
+
+        if event.joined {
+            counts.joins += 1;
+        } else {
+            counts.leaves += 1;
+        }
+    }
+
+    weeks
+}
+
+/// The invites most people joined through in the semester, most first.
+pub fn top_sources(
+    events: &[MemberEvent],
+    semester: &Semester,
+) -> Vec<(String, Option<u64>, usize)> {
+    let (start, end) = semester.bounds();
+
+    events
+        .iter()
+        .filter(|event| {
+            event.joined && (start.timestamp()..end.timestamp()).contains(&event.timestamp)
+        })
+        .filter_map(|event| Some((event.invite_code.clone()?, event.inviter_id)))
+        .counts()
+        .into_iter()
+        .map(|((code, inviter_id), count)| (code, inviter_id, count))
+        .sorted_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)))
+        .take(TOP_SOURCES)
+        .collect()
+}
+
+/// A bar chart of the weekly joins (`+`) and leaves (`-`), for a code block.
+pub fn render_text_chart(weeks: &[WeekCounts]) -> String {
+    let most = weeks
+        .iter()
+        .map(|week| week.joins.max(week.leaves))
+        .max()
+        .unwrap_or_default()
+        .max(1);
+    let bar = |count: u64, c: &str| c.repeat((count * CHART_WIDTH).div_ceil(most) as usize);
+
+    weeks
+        .iter()
+        .enumerate()
+        .map(|(i, week)| {
+            format!(
+                "wk {:>2} +{:<4} {}\n      -{:<4} {}",
+                i + 1,
+                week.joins,
+                bar(week.joins, "█"),
+                week.leaves,
+                bar(week.leaves, "░")
+            )
+        })
+        .join("\n")
+}
+
+/// Who invited whoever used the invite, for the report.
+pub fn format_source(code: &str, inviter_id: Option<u64>, count: usize) -> String {
+    use serenity::Mentionable;
+
+    format!(
+        "`{}`{} - {} joins",
+        code,
+        inviter_id
+            .map(|id| format!(" by {}", UserId::new(id).mention()))
+            .unwrap_or_default(),
+        count
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn invite(code: &str, uses: u64) -> serenity::RichInvite {
+        serde_json::from_value(serde_json::json!({
+            "code": code,
+            "uses": uses,
+            "channel": { "id": "1", "name": "general", "type": 0 },
+            "created_at": "2024-01-01T00:00:00Z",
+            "max_age": 0,
+            "max_uses": 0,
+            "temporary": false,
+        }))
+        .unwrap()
+    }
+
+    fn event(joined: bool, timestamp: i64, invite_code: Option<&str>) -> MemberEvent {
+        MemberEvent {
+            user_id: 1,
+            joined,
+            timestamp,
+            invite_code: invite_code.map(str::to_owned),
+            inviter_id: None,
+        }
+    }
+
+    #[test]
+    fn finds_the_invite_that_was_used() {
+        let before = HashMap::from([("abc".to_owned(), 3), ("xyz".to_owned(), 1)]);
+
+        let used = [invite("abc", 3), invite("xyz", 2)];
+        assert_eq!(
+            find_used_invite(&before, &used).map(|invite| invite.code.as_str()),
+            Some("xyz")
+        );
+
+        let new = [invite("abc", 3), invite("new", 1)];
+        assert_eq!(
+            find_used_invite(&before, &new).map(|invite| invite.code.as_str()),
+            Some("new")
+        );
+
+        let ambiguous = [invite("abc", 4), invite("xyz", 2)];
+        assert!(find_used_invite(&before, &ambiguous).is_none());
+    }
+
+    #[test]
+    fn parses_semesters() {
+        let today = date(2024, 10, 1);
+
+        assert_eq!(
+            Semester::parse("Spring 2025", today).unwrap(),
+            Semester {
+                term: Term::Spring,
+                year: 2025
+            }
+        );
+        assert_eq!(
+            Semester::parse("fall", today).unwrap(),
+            Semester::containing(today)
+        );
+        assert!(Semester::parse("winter 2024", today).is_err());
+        assert!(Semester::parse("fall twenty", today).is_err());
+        assert!(Semester::parse("fall 999999", today).is_err());
+        assert!(Semester::parse("spring -5", today).is_err());
+    }
+
+    #[test]
+    fn counts_by_week() {
+        let semester = Semester::parse("fall 2024", date(2024, 1, 1)).unwrap();
+        let start = semester.bounds().0.timestamp();
+        let week = 7 * 24 * 60 * 60;
+
+        let weeks = weekly_counts(
+            &[
+                event(true, start, None),
+                event(true, start + 10, None),
+                event(false, start + week, None),
+                event(true, start - 10, None),
+            ],
+            &semester,
+        );
+
+        assert_eq!(weeks.len(), 22);
+        assert_eq!(
+            weeks[0],
+            WeekCounts {
+                joins: 2,
+                leaves: 0
+            }
+        );
+        assert_eq!(
+            weeks[1],
+            WeekCounts {
+                joins: 0,
+                leaves: 1
+            }
+        );
+    }
+
+    #[test]
+    fn ranks_invite_sources() {
+        let semester = Semester::parse("fall 2024", date(2024, 1, 1)).unwrap();
+        let start = semester.bounds().0.timestamp();
+
+        let sources = top_sources(
+            &[
+                event(true, start, Some("club")),
+                event(true, start, Some("club")),
+                event(true, start, Some("flyer")),
+                event(true, start, None),
+                event(false, start, Some("flyer")),
+            ],
+            &semester,
+        );
+
+        assert_eq!(
+            sources,
+            vec![("club".to_owned(), None, 2), ("flyer".to_owned(), None, 1)]
+        );
+    }
+
+    #[test]
+    fn reports_labeled_invites() {
+        let label = |code: &str| InviteLabel {
+            code: code.to_owned(),
+            label: format!("{} label", code),
+            created_by: 1,
+            created_at: 0,
+        };
+        let labels = [label("flyer"), label("syllabus")];
+        let join = |user_id, timestamp, code: &str| MemberEvent {
+            user_id,
+            joined: true,
+            timestamp,
+            invite_code: Some(code.to_owned()),
+            inviter_id: None,
+        };
+        let leave = |user_id, timestamp| MemberEvent {
+            user_id,
+            joined: false,
+            timestamp,
+            invite_code: None,
+            inviter_id: None,
+        };
+        let now = 100 * 24 * 60 * 60;
+
+        let reports = label_reports(
+            &labels,
+            &[
+                join(1, 0, "syllabus"),
+                join(2, now - 10, "syllabus"),
+                leave(2, now - 5),
+                leave(3, 0),
+                join(3, 10, "syllabus"),
+                join(4, 0, "flyer"),
+            ],
+            now,
+        );
+
+        assert_eq!(
+            reports,
+            vec![
+                LabelReport {
+                    label: &labels[1],
+                    joins: 3,
+                    recent_joins: 1,
+                    stayed: 2,
+                },
+                LabelReport {
+                    label: &labels[0],
+                    joins: 1,
+                    recent_joins: 0,
+                    stayed: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn charts_relative_to_the_busiest_week() {
+        let chart = render_text_chart(&[
+            WeekCounts {
+                joins: 2,
+                leaves: 0,
+            },
+            WeekCounts {
+                joins: 1,
+                leaves: 1,
+            },
+        ]);
+
+        assert_eq!(chart.lines().count(), 4);
+        assert_eq!(chart.lines().next().unwrap().matches('█').count(), 30);
+        assert_eq!(chart.lines().nth(2).unwrap().matches('█').count(), 15);
+    }
+}
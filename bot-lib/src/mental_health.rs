@@ -0,0 +1,205 @@
+use crate::{author_guard::is_from_human, data::AppState, lang::ruleset::Ruleset};
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, ChannelId, Mentionable, RoleId};
+use serde::{Deserialize, Serialize};
+
+const CRISIS_ALERTS_TREE: &str = "crisis_alerts";
+
+/// Campus crisis and counseling info, for `/resources mental_health`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MentalHealth {
+    /// Posted by `/resources mental_health`, and DMed to members the detector notices.
+    pub resources: String,
+    /// Looking out for crisis language, off unless set.
+    pub crisis_detection: Option<CrisisDetection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CrisisDetection {
+    /// Matched against the lowercased message.
+    pub ruleset: Ruleset,
+    /// The staff role that gets quietly alerted.
+    pub staff_role_id: u64,
+    /// A staff only channel for the alerts.
+    pub alert_channel_id: u64,
+    /// How long before the same member can be DMed again, in hours.
+    #[serde(default = "get_default_cooldown_hours")]
+    pub cooldown_hours: u32,
+    /// The most alerts across everyone per hour, so a bad pattern can't flood anyone.
+    #[serde(default = "get_default_max_per_hour")]
+    pub max_per_hour: u32,
+}
+
+fn get_default_cooldown_hours() -> u32 {
+    24
+}
+
+fn get_default_max_per_hour() -> u32 {
+    3
+}
+
+/// DMs resources to members whose message sounds like they're in crisis, and lets staff know.
+pub async fn detect_crisis(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<()> {
+    let Some((resources, detection)) =
+        data.config
+            .read()
+            .await
+            .mental_health
+            .as_ref()
+            .and_then(|mental_health| {
+                mental_health
+                    .crisis_detection
+                    .clone()
+                    .map(|detection| (mental_health.resources.clone(), detection))
+            })
+    else {
+        return Ok(());
+    };
+
+    if message.guild_id.is_none() || !is_from_human(ctx, data, message).await {
+        return Ok(());
+    }
+
+    if !detection.ruleset.matches(&message.content.to_lowercase()) {
+        return Ok(());
+    }
+
+    let now = Utc::now().timestamp();
+    let key = message.author.id.get().to_be_bytes();
+    let alerts = data.db.values::<i64>(CRISIS_ALERTS_TREE)?;
+    let last_alert = data.db.get::<i64>(CRISIS_ALERTS_TREE, key)?;
+
+    if !should_alert(&alerts, last_alert, now, &detection) {
+        tracing::debug!("Crisis alert rate limited {}", message.link());
+        return Ok(());
+    }
+
+    data.db.insert(CRISIS_ALERTS_TREE, key, &now)?;
+
+    let dm = message
+        .author
+        .direct_message(
+            ctx,
+            serenity::CreateMessage::new().embed(
+                serenity::CreateEmbed::new()
+                    .title("You're not alone")
+                    .description(format!(
+                        "It sounds like things might be rough right now. \
+                        If you want to talk to someone, these can help:\n\n{}",
+                        resources
+                    ))
+                    .color(serenity::Color::DARK_GREEN),
+            ),
+        )
+        .await;
+
+    let delivered = match dm {
+        Ok(_) => "Resources were DMed to them.",
+        Err(_) => "Their DMs are closed, so they didn't get resources.",
+    };
+
+    let staff_role = RoleId::new(detection.staff_role_id);
+
+    ChannelId::new(detection.alert_channel_id)
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .content(staff_role.mention().to_string())
+                .embed(
+                    serenity::CreateEmbed::new()
+                        .title("Possible crisis")
+                        .description(format!(
+                            "{} might need someone to check in, {}\n\n{}",
+                            message.author.mention(),
+                            message.link(),
+                            delivered
+                        ))
+                        .color(serenity::Color::ORANGE)
+                        .timestamp(serenity::Timestamp::now()),
+                )
+                .allowed_mentions(serenity::CreateAllowedMentions::new().roles([staff_role])),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Whether a member can be alerted about, given when everyone was last alerted about.
+fn should_alert(
+    alerts: &[i64],
+    last_alert: Option<i64>,
+    now: i64,
+    detection: &CrisisDetection,
+) -> bool {
+    let cooldown = i64::from(detection.cooldown_hours) * 60 * 60;
+
+    if last_alert.is_some_and(|last_alert| now - last_alert < cooldown) {
+        return false;
+    }
+
+    let past_hour = alerts
+        .iter()
+        .filter(|&&alerted| now - alerted < 60 * 60)
+        .count();
+
+    past_hour < detection.max_per_hour as usize
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast_ruleset;
+
+    fn detection() -> CrisisDetection {
+        CrisisDetection {
+            ruleset: fast_ruleset!("r crisis"),
+            staff_role_id: 1,
+            alert_channel_id: 2,
+            cooldown_hours: 24,
+            max_per_hour: 2,
+        }
+    }
+
+    #[test]
+    fn members_have_a_cooldown() {
+        let now = 100 * 60 * 60;
+
+        assert!(should_alert(&[], None, now, &detection()));
+        assert!(!should_alert(
+            &[now - 60 * 60 * 2],
+            Some(now - 60 * 60 * 2),
+            now,
+            &detection()
+        ));
+        assert!(should_alert(
+            &[now - 60 * 60 * 25],
+            Some(now - 60 * 60 * 25),
+            now,
+            &detection()
+        ));
+    }
+
+    #[test]
+    fn alerts_are_capped_per_hour() {
+        let now = 100 * 60 * 60;
+
+        assert!(should_alert(&[now - 60], None, now, &detection()));
+        assert!(!should_alert(
+            &[now - 60, now - 120],
+            None,
+            now,
+            &detection()
+        ));
+        assert!(should_alert(
+            &[now - 60, now - 60 * 60 * 2],
+            None,
+            now,
+            &detection()
+        ));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    author_guard::is_from_human,
+    data::AppState,
+    feature_flags::{feature_enabled, Feature},
+    lang::ruleset::Ruleset,
+    llm::{Llm, LlmMessage},
+};
+use color_eyre::eyre::Result;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{self as serenity};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Discord's limit on message length.
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+/// How kingfisher answers when it's @mentioned, instead of the usual responses.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MentionReplies {
+    /// Canned answers, the first one that matches wins.
+    #[serde(default)]
+    pub faq: Vec<MentionFaq>,
+    /// Ask the LLM backend when nothing in the FAQ matches.
+    #[serde(default)]
+    pub use_llm: bool,
+    #[serde(default = "get_default_system_prompt")]
+    pub system_prompt: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MentionFaq {
+    pub ruleset: Ruleset,
+    pub answer: String,
+}
+
+fn get_default_system_prompt() -> String {
+    "You are Kingfisher, the bot of the University of Utah Computer Science Discord server. \
+     Answer in a sentence or two, and say so if you don't know."
+        .to_owned()
+}
+
+lazy_static! {
+    static ref USER_MENTION: Regex =
+        Regex::new(r"<@!?\d+>").expect("User mention regex should be valid");
+}
+
+/// Whether the message is someone @mentioning kingfisher, and should get a mention reply.
+pub async fn is_mention(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> bool {
+    message.mentions_user_id(ctx.cache.current_user().id)
+        && is_from_human(ctx, data, message).await
+        && data.config.read().await.mention_replies.is_some()
+}
+
+impl MentionReplies {
+    fn find_faq(&self, question: &str) -> Option<&str> {
+        self.faq
+            .iter()
+            .find(|faq| faq.ruleset.matches(question))
+            .map(|faq| faq.answer.as_str())
+    }
+
+    async fn answer(&self, llm: Option<&Llm>, question: &str) -> Result<Option<String>> {
+        if let Some(answer) = self.find_faq(question) {
+            return Ok(Some(answer.to_owned()));
+        }
+
+        let Some(llm) = llm.filter(|_| self.use_llm) else {
+            return Ok(None);
+        };
+
+        let answer = llm
+            .chat(&[
+                LlmMessage::system(&self.system_prompt),
+                LlmMessage::user(question),
+            ])
+            .await?;
+
+        Ok(Some(answer.chars().take(MAX_MESSAGE_LENGTH).collect()))
+    }
+}
+
+/// Answers questions asked of kingfisher with an FAQ entry or the LLM.
+pub async fn reply_to_mention(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<()> {
+    if !is_mention(ctx, data, message).await {
+        return Ok(());
+    }
+
+    let question = USER_MENTION.replace_all(&message.content, "");
+    let question = question.trim();
+
+    if question.is_empty() {
+        return Ok(());
+    }
+
+    let (mention_replies, llm) = {
+        let config = data.config.read().await;
+
+        let Some(mention_replies) = config.mention_replies.clone() else {
+            return Ok(());
+        };
+
+        let llm = config
+            .llm
+            .clone()
+            .filter(|_| feature_enabled(&data.db, &config, Feature::Llm));
+
+        (mention_replies, llm)
+    };
+
+    let typing = message.channel_id.start_typing(&ctx.http);
+    let answer = mention_replies.answer(llm.as_ref(), question).await;
+    typing.stop();
+
+    // The answer might be from the llm, which can be talked into pinging everyone
+    if let Some(answer) = answer? {
+        message
+            .channel_id
+            .send_message(
+                ctx,
+                serenity::CreateMessage::new()
+                    .content(answer)
+                    .reference_message(message)
+                    .allowed_mentions(serenity::CreateAllowedMentions::new()),
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast_ruleset;
+
+    #[test]
+    fn finds_faq_answers() {
+        let mention_replies = MentionReplies {
+            faq: vec![MentionFaq {
+                ruleset: fast_ruleset!(r"r \bcade\b"),
+                answer: "CADE lab accounts are made by the CS department.".to_owned(),
+            }],
+            use_llm: false,
+            system_prompt: get_default_system_prompt(),
+        };
+
+        assert_eq!(
+            mention_replies.find_faq("how do I get a cade account?"),
+            Some("CADE lab accounts are made by the CS department.")
+        );
+        assert_eq!(mention_replies.find_faq("what's for lunch?"), None);
+    }
+}
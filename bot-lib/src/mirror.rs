@@ -0,0 +1,152 @@
+use crate::{author_guard::is_from_human, data::AppState, webhooks::execute_webhook};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{self as serenity, ChannelId};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::collections::HashMap;
+
+/// Re-posts messages by certain roles in one channel into other channels.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Mirror {
+    pub source_channel_id: u64,
+    /// Only messages by members with one of these roles get mirrored.
+    pub role_ids: Vec<u64>,
+    pub targets: Vec<MirrorTarget>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MirrorTarget {
+    pub channel_id: u64,
+    /// Role mentions to swap out, e.g. a general class role for that section's role.
+    ///
+    /// Only mapped roles get pinged in the mirror.
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    #[serde(default)]
+    pub role_mapping: HashMap<u64, u64>,
+}
+
+lazy_static! {
+    static ref ROLE_MENTION: Regex =
+        Regex::new(r"<@&(\d+)>").expect("Role mention regex should be valid");
+}
+
+/// Swaps role mentions according to the mapping.
+fn map_role_mentions(content: &str, role_mapping: &HashMap<u64, u64>) -> String {
+    ROLE_MENTION
+        .replace_all(content, |captures: &regex::Captures| {
+            captures[1]
+                .parse::<u64>()
+                .ok()
+                .and_then(|role_id| role_mapping.get(&role_id))
+                .map_or(captures[0].to_owned(), |role_id| format!("<@&{}>", role_id))
+        })
+        .into_owned()
+}
+
+/// Re-posts content into each of the mirror's targets under the given name.
+pub async fn mirror_to_targets(
+    ctx: &serenity::Context,
+    mirror: &Mirror,
+    content: &str,
+    attachments: &str,
+    name: &str,
+    avatar_url: Option<String>,
+) -> Result<()> {
+    for target in &mirror.targets {
+        let content = format!(
+            "{}\n{}",
+            map_role_mentions(content, &target.role_mapping),
+            attachments
+        );
+
+        let mut mirrored = serenity::ExecuteWebhook::new()
+            .content(content.trim_end())
+            .username(name)
+            .allowed_mentions(
+                serenity::CreateAllowedMentions::new().roles(target.role_mapping.values().copied()),
+            );
+
+        if let Some(avatar_url) = &avatar_url {
+            mirrored = mirrored.avatar_url(avatar_url);
+        }
+
+        execute_webhook(ctx, ChannelId::new(target.channel_id), mirrored).await?;
+    }
+
+    Ok(())
+}
+
+/// Mirrors the message into every target channel, as if the author posted it there.
+pub async fn mirror_message(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<()> {
+    if !is_from_human(ctx, data, message).await {
+        return Ok(());
+    }
+
+    let mirrors = data
+        .config
+        .read()
+        .await
+        .mirrors
+        .iter()
+        .filter(|mirror| mirror.source_channel_id == message.channel_id.get())
+        .cloned()
+        .collect_vec();
+
+    let roles = message
+        .member
+        .as_ref()
+        .map(|member| member.roles.clone())
+        .unwrap_or_default();
+
+    let name = message
+        .member
+        .as_ref()
+        .and_then(|member| member.nick.clone())
+        .or_else(|| message.author.global_name.clone())
+        .unwrap_or_else(|| message.author.name.clone());
+
+    let attachments = message
+        .attachments
+        .iter()
+        .map(|attachment| attachment.url.as_str())
+        .join("\n");
+
+    for mirror in mirrors {
+        if !roles
+            .iter()
+            .any(|role_id| mirror.role_ids.contains(&role_id.get()))
+        {
+            continue;
+        }
+
+        mirror_to_targets(
+            ctx,
+            &mirror,
+            &message.content,
+            &attachments,
+            &name,
+            message.author.avatar_url(),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn maps_role_mentions() {
+    let role_mapping = HashMap::from([(1, 2), (2, 20)]);
+
+    assert_eq!(
+        map_role_mentions("<@&1> and <@&2>, not <@&3> or <@1>", &role_mapping),
+        "<@&2> and <@&20>, not <@&3> or <@1>"
+    );
+}
@@ -0,0 +1,27 @@
+use crate::data::AppState;
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, ChannelId};
+
+/// Posts a note about an automated or privileged action to the configured mod log channel.
+///
+/// Does nothing if no mod log channel is configured.
+pub async fn mod_log(
+    ctx: &serenity::Context,
+    data: &AppState,
+    embed: serenity::CreateEmbed,
+) -> Result<()> {
+    let Some(channel_id) = data.config.read().await.mod_log_channel_id else {
+        return Ok(());
+    };
+
+    ChannelId::new(channel_id)
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .embed(embed.timestamp(serenity::Timestamp::now()))
+                .allowed_mentions(serenity::CreateAllowedMentions::new()),
+        )
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,308 @@
+use crate::{
+    author_guard::is_from_human, data::AppState, lang::ruleset::Ruleset, mod_log::mod_log,
+};
+use chrono::Duration;
+use color_eyre::eyre::{Result, WrapErr};
+use poise::serenity_prelude::{self as serenity, Mentionable};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr, DurationSeconds};
+use std::collections::HashMap;
+
+const MAX_QUOTE_LENGTH: usize = 1024;
+
+/// Moderation rules, kept separate from the fun responses.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Moderation {
+    pub rules: Vec<ModerationRule>,
+    /// How long the `delete_and_timeout` action times people out for, in seconds.
+    #[serde_as(as = "DurationSeconds<i64>")]
+    #[serde(default = "get_default_timeout")]
+    pub timeout: Duration,
+    /// Strictness per channel id, channels not listed are `standard`.
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    #[serde(default)]
+    pub channel_strictness: HashMap<u64, Strictness>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ModerationRule {
+    /// Used only for logging.
+    pub name: String,
+    pub action: ModerationAction,
+    /// Matched against the normalized message (lowercase, no leetspeak or invisible characters).
+    pub ruleset: Ruleset,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationAction {
+    Warn,
+    Delete,
+    DeleteAndTimeout,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strictness {
+    /// No moderation at all.
+    Off,
+    /// Only the most severe rules apply.
+    Lenient,
+    /// Every rule applies as configured.
+    #[default]
+    Standard,
+    /// Every rule applies, warnings become deletions.
+    Strict,
+}
+
+fn get_default_timeout() -> Duration {
+    Duration::minutes(10)
+}
+
+/// Undoes the usual tricks for dodging filters.
+pub fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| {
+            !matches!(
+                c,
+                '\u{200B}'..='\u{200F}' | '\u{2060}' | '\u{FEFF}' | '\u{00AD}'
+            )
+        })
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | '!' => 'i',
+            '3' => 'e',
+            '4' | '@' => 'a',
+            '5' | '$' => 's',
+            '7' => 't',
+            c => c,
+        })
+        .collect()
+}
+
+impl Strictness {
+    /// What actually happens when a rule with `action` matches in a channel this strict.
+    fn apply(self, action: ModerationAction) -> Option<ModerationAction> {
+        match self {
+            Strictness::Off => None,
+            Strictness::Lenient => (action == ModerationAction::DeleteAndTimeout).then_some(action),
+            Strictness::Standard => Some(action),
+            Strictness::Strict => Some(action.max(ModerationAction::Delete)),
+        }
+    }
+}
+
+impl Moderation {
+    /// The most severe action any rule calls for, along with the rule's name.
+    pub(crate) fn find_action(
+        &self,
+        channel_id: u64,
+        content: &str,
+    ) -> Option<(ModerationAction, &str)> {
+        let strictness = self
+            .channel_strictness
+            .get(&channel_id)
+            .copied()
+            .unwrap_or_default();
+
+        let normalized = normalize(content);
+
+        self.rules
+            .iter()
+            .filter(|rule| rule.ruleset.matches(&normalized))
+            .filter_map(|rule| Some((strictness.apply(rule.action)?, rule.name.as_str())))
+            .max_by_key(|(action, _)| *action)
+    }
+}
+
+/// Runs the moderation rules on a message. Returns true if the message was deleted.
+pub async fn moderate_message(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<bool> {
+    if !is_from_human(ctx, data, message).await {
+        return Ok(false);
+    }
+
+    let (action, rule_name, timeout) = {
+        let config = data.config.read().await;
+
+        let Some(moderation) = &config.moderation else {
+            return Ok(false);
+        };
+
+        let Some((action, rule_name)) =
+            moderation.find_action(message.channel_id.get(), &message.content)
+        else {
+            return Ok(false);
+        };
+
+        (action, rule_name.to_owned(), moderation.timeout)
+    };
+
+    tracing::info!(
+        "Moderation rule `{}` matched {}, {:?}",
+        rule_name,
+        message.link(),
+        action
+    );
+
+    let notice = match action {
+        ModerationAction::Warn => "Please keep it civil.",
+        ModerationAction::Delete => "Your message was removed for breaking the rules.",
+        ModerationAction::DeleteAndTimeout => {
+            "Your message was removed for breaking the rules, and you've been timed out."
+        }
+    };
+
+    if action >= ModerationAction::Delete {
+        message
+            .delete(ctx)
+            .await
+            .wrap_err("Couldn't delete message")?;
+    }
+
+    // The message is gone by now, so the rest failing shouldn't let anything else respond to it
+    if action == ModerationAction::DeleteAndTimeout {
+        if let Some(guild_id) = message.guild_id {
+            let timeout_end = chrono::Utc::now() + timeout;
+
+            if let Err(e) = guild_id
+                .edit_member(
+                    ctx,
+                    message.author.id,
+                    serenity::EditMember::new()
+                        .disable_communication_until(timeout_end.to_rfc3339()),
+                )
+                .await
+            {
+                tracing::warn!("Couldn't time out {}: {:?}", message.author.name, e);
+            }
+        }
+    }
+
+    let dm = message
+        .author
+        .direct_message(ctx, serenity::CreateMessage::new().content(notice))
+        .await;
+
+    if dm.is_err() && action == ModerationAction::Warn {
+        if let Err(e) = message.reply(ctx, notice).await {
+            tracing::warn!("Couldn't warn {}: {:?}", message.author.name, e);
+        }
+    }
+
+    if let Err(e) = mod_log(
+        ctx,
+        data,
+        serenity::CreateEmbed::new()
+            .title(format!("Moderation: {:?}", action))
+            .description(format!(
+                "{} in {}: rule `{}`\n>>> {}",
+                message.author.mention(),
+                message.channel_id.mention(),
+                rule_name,
+                clip(&message.content)
+            )),
+    )
+    .await
+    {
+        tracing::warn!("Couldn't log moderation of {}: {:?}", message.link(), e);
+    }
+
+    Ok(action >= ModerationAction::Delete)
+}
+
+/// Keeps the quoted message well inside an embed description.
+fn clip(content: &str) -> String {
+    if content.chars().count() <= MAX_QUOTE_LENGTH {
+        return content.to_owned();
+    }
+
+    format!(
+        "{}...",
+        content.chars().take(MAX_QUOTE_LENGTH).collect::<String>()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast_ruleset;
+
+    #[test]
+    fn deserializes_channel_strictness() {
+        let moderation: Moderation = toml::from_str(
+            r#"
+rules = []
+
+[channel_strictness]
+123 = "lenient"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            moderation.channel_strictness,
+            HashMap::from([(123, Strictness::Lenient)])
+        );
+    }
+
+    #[test]
+    fn normalizes_obfuscation() {
+        assert_eq!(normalize("B4D\u{200B}W0RD"), "badword");
+        assert_eq!(normalize("$h!7"), "shit");
+    }
+
+    #[test]
+    fn strictness_changes_actions() {
+        let moderation = Moderation {
+            rules: vec![
+                ModerationRule {
+                    name: "mild".to_owned(),
+                    action: ModerationAction::Warn,
+                    ruleset: fast_ruleset!("r darn"),
+                },
+                ModerationRule {
+                    name: "severe".to_owned(),
+                    action: ModerationAction::DeleteAndTimeout,
+                    ruleset: fast_ruleset!("r badword"),
+                },
+            ],
+            timeout: get_default_timeout(),
+            channel_strictness: HashMap::from([
+                (1, Strictness::Off),
+                (2, Strictness::Lenient),
+                (3, Strictness::Strict),
+            ]),
+        };
+
+        assert_eq!(
+            moderation.find_action(0, "d4rn"),
+            Some((ModerationAction::Warn, "mild"))
+        );
+        assert_eq!(
+            moderation.find_action(0, "darn b4dword"),
+            Some((ModerationAction::DeleteAndTimeout, "severe"))
+        );
+        assert_eq!(moderation.find_action(1, "badword"), None);
+        assert_eq!(moderation.find_action(2, "darn"), None);
+        assert_eq!(
+            moderation.find_action(3, "darn"),
+            Some((ModerationAction::Delete, "mild"))
+        );
+        assert_eq!(moderation.find_action(0, "hello"), None);
+    }
+
+    #[test]
+    fn clips_quoted_messages() {
+        assert_eq!(clip("darn"), "darn");
+
+        let clipped = clip(&"a".repeat(4000));
+        assert_eq!(clipped.chars().count(), MAX_QUOTE_LENGTH + 3);
+        assert!(clipped.ends_with("..."));
+    }
+}
@@ -0,0 +1,368 @@
+use crate::{author_guard::is_from_human, data::AppState, db::KingFisherDb};
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity, ChannelId, Mentionable, UserId};
+use serde::{Deserialize, Serialize};
+
+const MODMAIL_THREADS_TREE: &str = "modmail_threads";
+const MODMAIL_USERS_TREE: &str = "modmail_users";
+const MODMAIL_BLOCKED_TREE: &str = "modmail_blocked";
+/// Staff messages starting with this stay in the thread, for talking it over.
+const NOTE_PREFIX: &str = "//";
+/// The most an embed description can hold.
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+/// A DM holds 2000, leaving room for who sent the reply.
+const MAX_REPLY_LENGTH: usize = 1900;
+
+/// DMs to kingfisher go to staff as threads, `[modmail]`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Modmail {
+    /// A channel only staff can see, where each member gets a thread.
+    pub channel_id: u64,
+    /// Whether replies hide which staff member sent them, unless `/modmail reply` says otherwise.
+    #[serde(default)]
+    pub anonymous: bool,
+    /// Who anonymous replies are from.
+    #[serde(default = "get_default_staff_name")]
+    pub staff_name: String,
+}
+
+fn get_default_staff_name() -> String {
+    "Staff".to_owned()
+}
+
+/// A member's conversation with staff.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModmailThread {
+    pub user_id: u64,
+    pub thread_id: u64,
+    pub open: bool,
+    pub opened_at: i64,
+}
+
+fn get_thread_for_user(db: &KingFisherDb, user_id: UserId) -> Result<Option<ModmailThread>> {
+    db.get(MODMAIL_THREADS_TREE, user_id.get().to_be_bytes())
+}
+
+pub fn get_thread(db: &KingFisherDb, thread_id: ChannelId) -> Result<Option<ModmailThread>> {
+    let Some(user_id) = db.get::<u64>(MODMAIL_USERS_TREE, thread_id.get().to_be_bytes())? else {
+        return Ok(None);
+    };
+
+    get_thread_for_user(db, UserId::new(user_id))
+}
+
+fn save_thread(db: &KingFisherDb, thread: &ModmailThread) -> Result<()> {
+    db.insert(MODMAIL_THREADS_TREE, thread.user_id.to_be_bytes(), thread)?;
+    db.insert(
+        MODMAIL_USERS_TREE,
+        thread.thread_id.to_be_bytes(),
+        &thread.user_id,
+    )
+}
+
+fn is_blocked(db: &KingFisherDb, user_id: UserId) -> Result<bool> {
+    Ok(db
+        .get::<bool>(MODMAIL_BLOCKED_TREE, user_id.get().to_be_bytes())?
+        .is_some())
+}
+
+pub fn set_blocked(db: &KingFisherDb, user_id: UserId, blocked: bool) -> Result<()> {
+    if blocked {
+        db.insert(MODMAIL_BLOCKED_TREE, user_id.get().to_be_bytes(), &true)
+    } else {
+        db.remove::<bool>(MODMAIL_BLOCKED_TREE, user_id.get().to_be_bytes())?;
+        Ok(())
+    }
+}
+
+/// The text and attachment links of a message, as one string.
+fn with_attachments(message: &serenity::Message, max_length: usize) -> String {
+    let urls = message
+        .attachments
+        .iter()
+        .map(|attachment| attachment.url.as_str())
+        .collect_vec();
+    clamp_with_attachments(&message.content, &urls, max_length)
+}
+
+/// Cuts the text short so the attachment links always make it through.
+fn clamp_with_attachments(content: &str, urls: &[&str], max_length: usize) -> String {
+    let links = urls.iter().filter(|url| !url.is_empty()).join("\n");
+    let room = max_length.saturating_sub(links.chars().count() + 1);
+
+    let content = if content.chars().count() > room {
+        format!(
+            "{}...",
+            content
+                .chars()
+                .take(room.saturating_sub(3))
+                .collect::<String>()
+        )
+    } else {
+        content.to_owned()
+    };
+
+    [content, links]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .join("\n")
+}
+
+/// How a staff reply looks in the member's DMs.
+fn format_reply(text: &str, staff_member: &str, anonymous: bool, staff_name: &str) -> String {
+    if anonymous {
+        format!("**{}**: {}", staff_name, text)
+    } else {
+        format!("**{}** ({}): {}", staff_member, staff_name, text)
+    }
+}
+
+/// The member's open thread, or a new one if they don't have one.
+async fn thread_for(
+    ctx: &serenity::Context,
+    data: &AppState,
+    settings: &Modmail,
+    user: &serenity::User,
+) -> Result<ChannelId> {
+    if let Some(thread) = get_thread_for_user(&data.db, user.id)?.filter(|thread| thread.open) {
+        return Ok(ChannelId::new(thread.thread_id));
+    }
+
+    let thread = ChannelId::new(settings.channel_id)
+        .create_thread(
+            ctx,
+            serenity::CreateThread::new(format!("modmail-{}", user.name))
+                .kind(serenity::ChannelType::PublicThread)
+                .auto_archive_duration(serenity::AutoArchiveDuration::OneWeek),
+        )
+        .await?;
+
+    thread
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .content(format!(
+                    "Modmail from {} ({}). Messages here go to them, unless they start with `{}`. \
+                     `/modmail close` when it's done.",
+                    user.mention(),
+                    user.name,
+                    NOTE_PREFIX
+                ))
+                .allowed_mentions(serenity::CreateAllowedMentions::new()),
+        )
+        .await?;
+
+    save_thread(
+        &data.db,
+        &ModmailThread {
+            user_id: user.id.get(),
+            thread_id: thread.id.get(),
+            open: true,
+            opened_at: Utc::now().timestamp(),
+        },
+    )?;
+
+    Ok(thread.id)
+}
+
+/// Sends a staff reply to the member's DMs.
+pub async fn send_reply(
+    ctx: &serenity::Context,
+    settings: &Modmail,
+    thread: &ModmailThread,
+    staff_member: &serenity::User,
+    text: &str,
+    anonymous: bool,
+) -> Result<()> {
+    UserId::new(thread.user_id)
+        .to_user(ctx)
+        .await?
+        .direct_message(
+            ctx,
+            serenity::CreateMessage::new().content(format_reply(
+                text,
+                &staff_member.name,
+                anonymous,
+                &settings.staff_name,
+            )),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Passes DMs to staff, and staff replies back to DMs.
+pub async fn relay_modmail(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<()> {
+    let Some(settings) = data.config.read().await.modmail.clone() else {
+        return Ok(());
+    };
+
+    if !is_from_human(ctx, data, message).await {
+        return Ok(());
+    }
+
+    if message.guild_id.is_none() {
+        if is_blocked(&data.db, message.author.id)? {
+            return Ok(());
+        }
+
+        let thread_id = thread_for(ctx, data, &settings, &message.author).await?;
+        thread_id
+            .send_message(
+                ctx,
+                serenity::CreateMessage::new()
+                    .embed(
+                        serenity::CreateEmbed::new()
+                            .author(
+                                serenity::CreateEmbedAuthor::new(&message.author.name)
+                                    .icon_url(message.author.face()),
+                            )
+                            .description(with_attachments(message, MAX_DESCRIPTION_LENGTH))
+                            .timestamp(message.timestamp),
+                    )
+                    .allowed_mentions(serenity::CreateAllowedMentions::new()),
+            )
+            .await?;
+        message.react(ctx, '✅').await?;
+
+        return Ok(());
+    }
+
+    let Some(thread) = get_thread(&data.db, message.channel_id)?.filter(|thread| thread.open)
+    else {
+        return Ok(());
+    };
+
+    if message.content.starts_with(NOTE_PREFIX) {
+        return Ok(());
+    }
+
+    let sent = send_reply(
+        ctx,
+        &settings,
+        &thread,
+        &message.author,
+        &with_attachments(message, MAX_REPLY_LENGTH),
+        settings.anonymous,
+    )
+    .await;
+
+    match sent {
+        Ok(()) => message.react(ctx, '📨').await.map(|_| ())?,
+        // They've probably closed their DMs
+        Err(e) => {
+            message
+                .reply(ctx, format!("Couldn't deliver that: {}", e))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Lets the member know, and archives the thread. Their next DM opens a new one.
+pub async fn close_thread(
+    ctx: &serenity::Context,
+    data: &AppState,
+    mut thread: ModmailThread,
+) -> Result<()> {
+    thread.open = false;
+    save_thread(&data.db, &thread)?;
+
+    if let Err(e) = UserId::new(thread.user_id)
+        .to_user(ctx)
+        .await?
+        .direct_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .content("Staff closed this conversation. Message again any time."),
+        )
+        .await
+    {
+        tracing::debug!("Couldn't tell {} modmail closed: {:?}", thread.user_id, e);
+    }
+
+    ChannelId::new(thread.thread_id)
+        .edit_thread(ctx, serenity::EditThread::new().archived(true))
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn anonymous_replies_hide_the_sender() {
+        assert_eq!(
+            format_reply("We're on it", "alice", true, "Staff"),
+            "**Staff**: We're on it"
+        );
+        assert_eq!(
+            format_reply("We're on it", "alice", false, "Staff"),
+            "**alice** (Staff): We're on it"
+        );
+    }
+
+    #[test]
+    fn long_messages_keep_their_attachments() {
+        let content = "a".repeat(5000);
+        let urls = [
+            "https://cdn.example.com/1.png",
+            "https://cdn.example.com/2.png",
+        ];
+
+        let clamped = clamp_with_attachments(&content, &urls, MAX_DESCRIPTION_LENGTH);
+        assert_eq!(clamped.chars().count(), MAX_DESCRIPTION_LENGTH);
+        assert!(
+            clamped.ends_with("...\nhttps://cdn.example.com/1.png\nhttps://cdn.example.com/2.png")
+        );
+
+        assert_eq!(clamp_with_attachments("hi", &[], 10), "hi");
+        assert_eq!(
+            clamp_with_attachments("", &urls[..1], 100),
+            "https://cdn.example.com/1.png"
+        );
+    }
+
+    #[test]
+    fn threads_are_found_both_ways() {
+        let db = KingFisherDb::temporary().unwrap();
+        let thread = ModmailThread {
+            user_id: 1,
+            thread_id: 2,
+            open: true,
+            opened_at: 0,
+        };
+
+        save_thread(&db, &thread).unwrap();
+
+        assert_eq!(
+            get_thread(&db, ChannelId::new(2)).unwrap(),
+            Some(thread.clone())
+        );
+        assert_eq!(
+            get_thread_for_user(&db, UserId::new(1)).unwrap(),
+            Some(thread)
+        );
+        assert_eq!(get_thread(&db, ChannelId::new(1)).unwrap(), None);
+    }
+
+    #[test]
+    fn blocks_can_be_lifted() {
+        let db = KingFisherDb::temporary().unwrap();
+
+        set_blocked(&db, UserId::new(1), true).unwrap();
+        assert!(is_blocked(&db, UserId::new(1)).unwrap());
+
+        set_blocked(&db, UserId::new(1), false).unwrap();
+        assert!(!is_blocked(&db, UserId::new(1)).unwrap());
+    }
+}
@@ -0,0 +1,316 @@
+use crate::{data::AppState, lang::ruleset::Ruleset, mod_log::mod_log, moderation::normalize};
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{Result, WrapErr};
+use dashmap::DashMap;
+use futures::StreamExt;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{self as serenity, GuildId, Mentionable, UserId};
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    /// The last name each member was reported for, and when.
+    static ref LAST_REPORTED: DashMap<UserId, (String, DateTime<Utc>)> = DashMap::new();
+}
+
+/// How long after a report the same member can't be reported again.
+fn report_cooldown() -> Duration {
+    Duration::minutes(30)
+}
+
+/// Rules for member display names.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NamePolicy {
+    /// Rename offending members, otherwise they are only reported to the mod log.
+    #[serde(default)]
+    pub auto_rename: bool,
+    /// Names only staff may use, compared after normalization.
+    #[serde(default)]
+    pub protected_names: Vec<String>,
+    /// Members with any of these roles may use protected names.
+    #[serde(default)]
+    pub staff_role_ids: Vec<u64>,
+    /// Names matching this are not allowed.
+    pub ruleset: Option<Ruleset>,
+    /// What to rename people to when nothing of their name can be salvaged.
+    #[serde(default = "get_default_fallback_name")]
+    pub fallback_name: String,
+}
+
+fn get_default_fallback_name() -> String {
+    "Renamed User".to_owned()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameViolation {
+    /// Starts with symbols to sort above everyone else in the member list.
+    Hoisted,
+    /// Zalgo or nothing but symbols.
+    Unpingable,
+    Impersonation,
+    Banned,
+}
+
+fn is_combining_mark(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE20}'..='\u{FE2F}')
+}
+
+fn dehoist(name: &str) -> &str {
+    name.trim_start_matches(|c: char| !c.is_alphanumeric())
+}
+
+impl NamePolicy {
+    /// What is wrong with the name, and what it should be changed to.
+    pub fn check(&self, name: &str, is_staff: bool) -> Option<(NameViolation, String)> {
+        let normalized = normalize(name);
+
+        if !is_staff
+            && self
+                .protected_names
+                .iter()
+                .any(|protected| normalized.contains(&protected.to_lowercase()))
+        {
+            return Some((NameViolation::Impersonation, self.fallback_name.clone()));
+        }
+
+        if self
+            .ruleset
+            .as_ref()
+            .is_some_and(|ruleset| ruleset.matches(&normalized))
+        {
+            return Some((NameViolation::Banned, self.fallback_name.clone()));
+        }
+
+        let marks = name.chars().filter(|c| is_combining_mark(*c)).count();
+        let cleaned = name
+            .chars()
+            .filter(|c| !is_combining_mark(*c))
+            .collect::<String>();
+
+        if marks > 3 || !cleaned.chars().any(char::is_alphanumeric) {
+            let cleaned = dehoist(&cleaned).trim();
+
+            return Some((
+                NameViolation::Unpingable,
+                if cleaned.is_empty() {
+                    self.fallback_name.clone()
+                } else {
+                    cleaned.to_owned()
+                },
+            ));
+        }
+
+        let dehoisted = dehoist(name);
+
+        if dehoisted != name {
+            return Some((NameViolation::Hoisted, dehoisted.to_owned()));
+        }
+
+        None
+    }
+}
+
+/// Whether a violation should go to the mod log. Names that were already reported aren't again,
+/// like on every role change, and a member changing names over and over is only reported so
+/// often. If it should, it counts as reported now.
+fn should_report(user_id: UserId, name: &str, now: DateTime<Utc>) -> bool {
+    if let Some(last) = LAST_REPORTED.get(&user_id) {
+        let (last_name, reported_at) = &*last;
+
+        if last_name == name || now - *reported_at < report_cooldown() {
+            return false;
+        }
+    }
+
+    LAST_REPORTED.insert(user_id, (name.to_owned(), now));
+    true
+}
+
+/// Checks a member's display name, renaming them or reporting them to the mod log.
+///
+/// Returns whether the member was renamed.
+pub async fn enforce_name_policy(
+    ctx: &serenity::Context,
+    data: &AppState,
+    member: &serenity::Member,
+    force_rename: bool,
+) -> Result<bool> {
+    if member.user.bot {
+        return Ok(false);
+    }
+
+    let Some(policy) = data.config.read().await.name_policy.clone() else {
+        return Ok(false);
+    };
+
+    let is_staff = member
+        .roles
+        .iter()
+        .any(|role_id| policy.staff_role_ids.contains(&role_id.get()));
+
+    let name = member.display_name().to_owned();
+
+    let Some((violation, new_name)) = policy.check(&name, is_staff) else {
+        return Ok(false);
+    };
+
+    let rename = policy.auto_rename || force_rename;
+
+    if rename {
+        member
+            .guild_id
+            .edit_member(
+                ctx,
+                member.user.id,
+                serenity::EditMember::new().nickname(&new_name),
+            )
+            .await
+            .wrap_err("Couldn't rename member")?;
+    }
+
+    // Mods running /dehoist want to see what it did
+    if !force_rename && !should_report(member.user.id, &name, Utc::now()) {
+        return Ok(rename);
+    }
+
+    tracing::info!(
+        "Name policy {:?} for {} ({})",
+        violation,
+        name,
+        member.user.id
+    );
+
+    mod_log(
+        ctx,
+        data,
+        serenity::CreateEmbed::new()
+            .title(format!("Name policy: {:?}", violation))
+            .description(format!(
+                "{} `{}` {}",
+                member.mention(),
+                name,
+                if rename {
+                    format!("was renamed to `{}`", new_name)
+                } else {
+                    "should be renamed".to_owned()
+                }
+            )),
+    )
+    .await?;
+
+    Ok(rename)
+}
+
+/// Checks every member of the guild, returning how many were renamed.
+pub async fn enforce_name_policy_everywhere(
+    ctx: &serenity::Context,
+    data: &AppState,
+    guild_id: GuildId,
+    force_rename: bool,
+) -> Result<usize> {
+    let mut members = guild_id.members_iter(ctx).boxed();
+    let mut renamed = 0;
+
+    while let Some(member) = members.next().await {
+        let member = member?;
+
+        match enforce_name_policy(ctx, data, &member, force_rename).await {
+            Ok(true) => renamed += 1,
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Name policy failed for {}: {:?}", member.user.id, e),
+        }
+    }
+
+    Ok(renamed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast_ruleset;
+
+    fn policy() -> NamePolicy {
+        NamePolicy {
+            auto_rename: true,
+            protected_names: vec!["KingFisher".to_owned()],
+            staff_role_ids: vec![],
+            ruleset: Some(fast_ruleset!("r badword")),
+            fallback_name: get_default_fallback_name(),
+        }
+    }
+
+    #[test]
+    fn allows_normal_names() {
+        assert_eq!(policy().check("Stefan", false), None);
+        assert_eq!(policy().check("Сатья", false), None);
+    }
+
+    #[test]
+    fn dehoists_names() {
+        assert_eq!(
+            policy().check("!!! Stefan", false),
+            Some((NameViolation::Hoisted, "Stefan".to_owned()))
+        );
+    }
+
+    #[test]
+    fn cleans_unpingable_names() {
+        assert_eq!(
+            policy().check("S\u{0301}\u{0302}\u{0303}\u{0304}tefan", false),
+            Some((NameViolation::Unpingable, "Stefan".to_owned()))
+        );
+        assert_eq!(
+            policy().check("★☆", false).map(|(violation, _)| violation),
+            Some(NameViolation::Unpingable)
+        );
+        assert_eq!(
+            policy().check("★★★", false),
+            Some((NameViolation::Unpingable, "Renamed User".to_owned()))
+        );
+    }
+
+    #[test]
+    fn reports_each_name_once() {
+        let now = Utc::now();
+        let user_id = UserId::new(1);
+
+        assert!(should_report(user_id, "!!! Stefan", now));
+        assert!(!should_report(
+            user_id,
+            "!!! Stefan",
+            now + Duration::hours(1)
+        ));
+        assert!(!should_report(
+            user_id,
+            "!! Stefan",
+            now + Duration::minutes(5)
+        ));
+        assert!(should_report(UserId::new(2), "!! Stefan", now));
+        assert!(should_report(
+            user_id,
+            "!! Stefan",
+            now + Duration::hours(1)
+        ));
+    }
+
+    #[test]
+    fn protects_staff_names() {
+        assert_eq!(
+            policy()
+                .check("K1ngF1sher", false)
+                .map(|(violation, _)| violation),
+            Some(NameViolation::Impersonation)
+        );
+        assert_eq!(policy().check("KingFisher", true), None);
+        assert_eq!(
+            policy()
+                .check("b4dword", false)
+                .map(|(violation, _)| violation),
+            Some(NameViolation::Banned)
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{bail, OptionExt, Result, WrapErr};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use poise::serenity_prelude::{self as serenity, MessageId};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{process::Stdio, sync::Arc};
+use tokio::{io::AsyncWriteExt, sync::OnceCell};
+
+/// A message's images, read once however many features want them.
+type SharedRead = Arc<OnceCell<Option<String>>>;
+
+lazy_static! {
+    static ref RECENT_RUNS: Mutex<Vec<DateTime<Utc>>> = Mutex::new(vec![]);
+    static ref MESSAGE_READS: DashMap<MessageId, (DateTime<Utc>, SharedRead)> = DashMap::new();
+}
+
+/// Reads text out of posted images so responses can match screenshots, `[ocr]`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Ocr {
+    #[serde(flatten)]
+    pub backend: OcrBackend,
+    /// Images bigger than this, in bytes, are skipped.
+    #[serde(default = "get_default_max_size")]
+    pub max_size: u32,
+    /// How many images of one message get read.
+    #[serde(default = "get_default_max_images")]
+    pub max_images: usize,
+    /// How many images get read per minute across the server.
+    #[serde(default = "get_default_per_minute")]
+    pub per_minute: usize,
+    /// Gives up on an image after this many seconds.
+    #[serde(default = "get_default_timeout")]
+    pub timeout: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum OcrBackend {
+    /// Runs a local `tesseract`.
+    Tesseract {
+        #[serde(default = "get_default_command")]
+        command: String,
+        /// e.g. `eng+spa`
+        #[serde(default = "get_default_languages")]
+        languages: String,
+    },
+    /// POSTs `{"url": ...}` and expects `{"text": ...}` back.
+    Api {
+        api_url: String,
+        /// The environment variable holding the api key, so it stays out of the config.
+        #[serde(default = "get_default_api_key_env")]
+        api_key_env: String,
+    },
+}
+
+fn get_default_max_size() -> u32 {
+    2 * 1024 * 1024
+}
+
+fn get_default_max_images() -> usize {
+    1
+}
+
+fn get_default_per_minute() -> usize {
+    10
+}
+
+fn get_default_timeout() -> u64 {
+    20
+}
+
+fn get_default_command() -> String {
+    "tesseract".to_owned()
+}
+
+fn get_default_languages() -> String {
+    "eng".to_owned()
+}
+
+fn get_default_api_key_env() -> String {
+    "OCR_API_KEY".to_owned()
+}
+
+/// Takes one of the minute's runs, if there are any left.
+fn claim_run(runs: &mut Vec<DateTime<Utc>>, per_minute: usize, now: DateTime<Utc>) -> bool {
+    runs.retain(|at| now - *at < Duration::minutes(1));
+
+    if runs.len() >= per_minute {
+        return false;
+    }
+
+    runs.push(now);
+    true
+}
+
+async fn run_tesseract(command: &str, languages: &str, image: &[u8]) -> Result<String> {
+    let mut child = tokio::process::Command::new(command)
+        .args(["stdin", "stdout", "-l", languages])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .wrap_err_with(|| format!("Couldn't run {}", command))?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_eyre("tesseract should have stdin")?;
+    stdin.write_all(image).await?;
+    drop(stdin);
+
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        bail!("{} exited with {}", command, output.status);
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+async fn call_api(api_url: &str, api_key_env: &str, image_url: &str) -> Result<String> {
+    let api_key = std::env::var(api_key_env)
+        .wrap_err_with(|| format!("Expected an api key in {}", api_key_env))?;
+
+    let response: Value = reqwest::Client::new()
+        .post(api_url)
+        .bearer_auth(api_key)
+        .json(&json!({ "url": image_url }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    response["text"]
+        .as_str()
+        .map(str::to_owned)
+        .ok_or_eyre("OCR response had no text")
+}
+
+impl Ocr {
+    async fn read(&self, attachment: &serenity::Attachment) -> Result<String> {
+        match &self.backend {
+            OcrBackend::Tesseract { command, languages } => {
+                run_tesseract(command, languages, &attachment.download().await?).await
+            }
+            OcrBackend::Api {
+                api_url,
+                api_key_env,
+            } => call_api(api_url, api_key_env, &attachment.url).await,
+        }
+    }
+
+    /// The text in the message's images, or nothing if there aren't any or the limits are hit.
+    ///
+    /// Everything handling the same message shares one read, so it only counts against the
+    /// limits once.
+    pub async fn read_images(&self, message: &serenity::Message) -> Option<String> {
+        let now = Utc::now();
+        MESSAGE_READS.retain(|_, (read_at, _)| now - *read_at < Duration::minutes(5));
+
+        let read = MESSAGE_READS
+            .entry(message.id)
+            .or_insert_with(|| (now, SharedRead::default()))
+            .1
+            .clone();
+
+        read.get_or_init(|| self.read_unshared(message))
+            .await
+            .clone()
+    }
+
+    async fn read_unshared(&self, message: &serenity::Message) -> Option<String> {
+        let images = message
+            .attachments
+            .iter()
+            .filter(|attachment| {
+                attachment.size <= self.max_size
+                    && attachment
+                        .content_type
+                        .as_deref()
+                        .is_some_and(|kind| kind.starts_with("image/"))
+            })
+            .take(self.max_images);
+
+        let mut text = String::new();
+
+        for attachment in images {
+            if !claim_run(&mut RECENT_RUNS.lock(), self.per_minute, Utc::now()) {
+                tracing::debug!("OCR limit hit, skipping {}", attachment.url);
+                break;
+            }
+
+            match tokio::time::timeout(
+                std::time::Duration::from_secs(self.timeout),
+                self.read(attachment),
+            )
+            .await
+            {
+                Ok(Ok(read)) => {
+                    text.push_str(&read);
+                    text.push('\n');
+                }
+                Ok(Err(e)) => tracing::warn!("Couldn't read {}: {:?}", attachment.url, e),
+                Err(_) => tracing::warn!("Reading {} timed out", attachment.url),
+            }
+        }
+
+        Some(text).filter(|text| !text.trim().is_empty())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn limits_runs_per_minute() {
+        let now = Utc::now();
+        let mut runs = vec![now - Duration::minutes(2)];
+
+        assert!(claim_run(&mut runs, 2, now));
+        assert!(claim_run(&mut runs, 2, now));
+        assert!(!claim_run(&mut runs, 2, now));
+        assert!(claim_run(&mut runs, 2, now + Duration::minutes(1)));
+    }
+
+    #[test]
+    fn parses_backends() {
+        let ocr: Ocr = toml::from_str("backend = \"tesseract\"\nmax_images = 2").unwrap();
+        assert_eq!(
+            ocr.backend,
+            OcrBackend::Tesseract {
+                command: "tesseract".to_owned(),
+                languages: "eng".to_owned()
+            }
+        );
+        assert_eq!(ocr.max_images, 2);
+
+        let ocr: Ocr =
+            toml::from_str("backend = \"api\"\napi_url = \"https://ocr.example.com\"").unwrap();
+        assert_eq!(
+            ocr.backend,
+            OcrBackend::Api {
+                api_url: "https://ocr.example.com".to_owned(),
+                api_key_env: "OCR_API_KEY".to_owned()
+            }
+        );
+    }
+}
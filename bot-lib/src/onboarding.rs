@@ -0,0 +1,112 @@
+use crate::departments::KnownClasses;
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity, RoleId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// `/setup_classes`, where new members pick their year and classes in one go.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Onboarding {
+    /// Year roles to pick from first, skipped if there aren't any.
+    #[serde(default)]
+    pub years: Vec<Year>,
+    /// Whether classes typed in that don't have a role yet get one. Mods turn this on once
+    /// they're happy for members to add classes themselves. Only classes in `[departments]`
+    /// get made, a few per run.
+    #[serde(default)]
+    pub create_missing_classes: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Year {
+    pub name: String,
+    pub role_id: u64,
+}
+
+/// Turns typed classes like `"cs2420, MATH 2270, 3500"` into role names, with a bare number
+/// meaning a CS class. Returns what it couldn't make sense of too, including classes outside
+/// the known departments.
+pub fn parse_classes(text: &str, known: &KnownClasses) -> (Vec<String>, Vec<String>) {
+    let (classes, invalid): (Vec<_>, Vec<_>) = text
+        .split([',', '\n'])
+        .map(str::trim)
+        .filter(|class| !class.is_empty())
+        .map(|class| {
+            let compact = class.replace(' ', "").to_uppercase();
+            let split = compact
+                .find(|c: char| c.is_ascii_digit())
+                .unwrap_or(compact.len());
+            let (department, number) = compact.split_at(split);
+            let department = if department.is_empty() {
+                "CS"
+            } else {
+                department
+            };
+            let name = format!("{} {}", department, number);
+
+            if department.chars().all(|c| c.is_ascii_alphabetic())
+                && (known.has_department(department) || known.is_class(&name))
+            {
+                Ok(name)
+            } else {
+                Err(class.to_owned())
+            }
+        })
+        .partition_result();
+
+    (classes.into_iter().unique().collect(), invalid)
+}
+
+/// The class role with exactly this name, ignoring case.
+pub fn find_class_by_name(
+    roles: &HashMap<RoleId, serenity::Role>,
+    known: &KnownClasses,
+    name: &str,
+) -> Option<RoleId> {
+    roles
+        .values()
+        .find(|role| role.name.eq_ignore_ascii_case(name) && known.is_class(&role.name))
+        .map(|role| role.id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_typed_classes() {
+        let known = KnownClasses::new(["CS".to_owned(), "MATH".to_owned()], []);
+
+        assert_eq!(
+            parse_classes("cs2420, MATH 2270\n3500, cs2420, Calculus, Level 5", &known),
+            (
+                vec![
+                    "CS 2420".to_owned(),
+                    "MATH 2270".to_owned(),
+                    "CS 3500".to_owned()
+                ],
+                vec!["Calculus".to_owned(), "Level 5".to_owned()]
+            )
+        );
+    }
+
+    #[test]
+    fn only_finds_class_roles() {
+        let roles = [(1, "CS 2420"), (2, "Year 4")]
+            .into_iter()
+            .map(|(id, name)| {
+                let mut role = serenity::Role::default();
+                role.id = RoleId::new(id);
+                role.name = name.to_owned();
+                (role.id, role)
+            })
+            .collect::<HashMap<_, _>>();
+        let known = KnownClasses::new(["CS".to_owned()], []);
+
+        assert_eq!(
+            find_class_by_name(&roles, &known, "cs 2420"),
+            Some(RoleId::new(1))
+        );
+        assert_eq!(find_class_by_name(&roles, &known, "Year 4"), None);
+    }
+}
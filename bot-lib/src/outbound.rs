@@ -0,0 +1,136 @@
+use crate::{data::AppState, db::KingFisherDb};
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const OUTBOUND_TREE: &str = "outbound_requests";
+/// Gives up after this many tries, about a day of backing off.
+const MAX_ATTEMPTS: u32 = 10;
+
+/// A JSON POST to somewhere outside Discord that hasn't gone through yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutboundRequest {
+    pub id: u64,
+    pub url: String,
+    pub body: String,
+    pub attempts: u32,
+    pub next_attempt: DateTime<Utc>,
+}
+
+impl OutboundRequest {
+    /// Waits twice as long after each failure, starting at a minute.
+    fn backoff(&self) -> Duration {
+        Duration::minutes(1 << self.attempts.min(MAX_ATTEMPTS))
+    }
+}
+
+async fn post_json(url: &str, body: &str) -> Result<()> {
+    reqwest::Client::new()
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_owned())
+        .send()
+        .await?
+        .error_for_status()
+        .wrap_err("Webhook refused the request")?;
+
+    Ok(())
+}
+
+/// Records a failed try, rescheduling it or giving up once it's failed too often.
+fn record_failure(
+    db: &KingFisherDb,
+    mut request: OutboundRequest,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    request.attempts += 1;
+
+    if request.attempts >= MAX_ATTEMPTS {
+        tracing::warn!(
+            "Giving up on webhook to {} after {} tries",
+            request.url,
+            request.attempts
+        );
+        db.remove::<OutboundRequest>(OUTBOUND_TREE, request.id.to_be_bytes())?;
+        return Ok(());
+    }
+
+    request.next_attempt = now + request.backoff();
+    db.insert(OUTBOUND_TREE, request.id.to_be_bytes(), &request)
+}
+
+/// POSTs the JSON body to the URL, queueing it for retries if that doesn't work.
+pub async fn send_webhook(db: &KingFisherDb, url: &str, body: String) -> Result<()> {
+    let Err(e) = post_json(url, &body).await else {
+        return Ok(());
+    };
+
+    tracing::debug!("Webhook to {} failed, will retry: {:?}", url, e);
+
+    record_failure(
+        db,
+        OutboundRequest {
+            id: db.generate_id()?,
+            url: url.to_owned(),
+            body,
+            attempts: 0,
+            next_attempt: Utc::now(),
+        },
+        Utc::now(),
+    )
+}
+
+/// Retries every queued request that's due.
+pub async fn retry_outbound(data: &AppState) -> Result<()> {
+    let now = Utc::now();
+    let due = data
+        .db
+        .values::<OutboundRequest>(OUTBOUND_TREE)?
+        .into_iter()
+        .filter(|request| request.next_attempt <= now);
+
+    for request in due {
+        match post_json(&request.url, &request.body).await {
+            Ok(()) => {
+                data.db
+                    .remove::<OutboundRequest>(OUTBOUND_TREE, request.id.to_be_bytes())?;
+            }
+            Err(e) => {
+                tracing::debug!("Webhook to {} failed again: {:?}", request.url, e);
+                record_failure(&data.db, request, now)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backs_off_then_gives_up() {
+        let db = KingFisherDb::temporary().unwrap();
+        let now = Utc::now();
+        let mut request = OutboundRequest {
+            id: 1,
+            url: "https://example.com".to_owned(),
+            body: "{}".to_owned(),
+            attempts: 0,
+            next_attempt: now,
+        };
+
+        record_failure(&db, request.clone(), now).unwrap();
+        let queued = db
+            .get::<OutboundRequest>(OUTBOUND_TREE, 1u64.to_be_bytes())
+            .unwrap()
+            .unwrap();
+        assert_eq!(queued.attempts, 1);
+        assert_eq!(queued.next_attempt, now + Duration::minutes(2));
+
+        request.attempts = MAX_ATTEMPTS - 1;
+        record_failure(&db, request, now).unwrap();
+        assert_eq!(db.values::<OutboundRequest>(OUTBOUND_TREE).unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::db::KingFisherDb;
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::UserId;
+use serde::{Deserialize, Serialize};
+
+const PARTNER_REQUESTS_TREE: &str = "partner_requests";
+/// How long someone stays in the pool before we assume they found someone.
+const REQUEST_LIFETIME_DAYS: i64 = 14;
+
+/// Someone looking for a partner on an assignment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartnerRequest {
+    pub class: u32,
+    /// As they typed it, for showing back.
+    pub assignment: String,
+    pub user_id: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PartnerRequest {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now - self.created_at > Duration::days(REQUEST_LIFETIME_DAYS)
+    }
+}
+
+/// So "A3", "a3" and "a 3" are the same assignment.
+fn normalize_assignment(assignment: &str) -> String {
+    assignment
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Keyed by class and assignment then user, so everyone on an assignment sorts together.
+fn request_key(class: u32, assignment: &str, user_id: u64) -> Vec<u8> {
+    [
+        class.to_be_bytes().as_slice(),
+        normalize_assignment(assignment).as_bytes(),
+        &[0],
+        &user_id.to_be_bytes(),
+    ]
+    .concat()
+}
+
+/// Everyone else still looking for a partner on the assignment, longest waiting first.
+/// Expired requests get cleaned up along the way.
+pub fn get_partner_requests(
+    db: &KingFisherDb,
+    class: u32,
+    assignment: &str,
+    except: UserId,
+) -> Result<Vec<PartnerRequest>> {
+    let now = Utc::now();
+    let assignment = normalize_assignment(assignment);
+    let mut requests = vec![];
+
+    for request in db.values::<PartnerRequest>(PARTNER_REQUESTS_TREE)? {
+        if request.is_expired(now) {
+            remove_partner_request(db, &request)?;
+            continue;
+        }
+
+        if request.class == class
+            && normalize_assignment(&request.assignment) == assignment
+            && request.user_id != except.get()
+        {
+            requests.push(request);
+        }
+    }
+
+    requests.sort_by_key(|request| request.created_at);
+
+    Ok(requests)
+}
+
+/// Puts someone in the pool for an assignment, or refreshes their spot.
+pub fn add_partner_request(
+    db: &KingFisherDb,
+    class: u32,
+    assignment: &str,
+    user_id: UserId,
+) -> Result<()> {
+    db.insert(
+        PARTNER_REQUESTS_TREE,
+        request_key(class, assignment, user_id.get()),
+        &PartnerRequest {
+            class,
+            assignment: assignment.to_owned(),
+            user_id: user_id.get(),
+            created_at: Utc::now(),
+        },
+    )?;
+
+    Ok(())
+}
+
+pub fn remove_partner_request(db: &KingFisherDb, request: &PartnerRequest) -> Result<()> {
+    db.remove::<PartnerRequest>(
+        PARTNER_REQUESTS_TREE,
+        request_key(request.class, &request.assignment, request.user_id),
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pools_requests_by_assignment() {
+        let db = KingFisherDb::temporary().unwrap();
+        let (alice, bob, carol) = (UserId::new(1), UserId::new(2), UserId::new(3));
+
+        add_partner_request(&db, 2420, "A3", alice).unwrap();
+        add_partner_request(&db, 2420, "a 3", bob).unwrap();
+        add_partner_request(&db, 2420, "A4", carol).unwrap();
+        add_partner_request(&db, 3500, "A3", carol).unwrap();
+
+        let waiting = get_partner_requests(&db, 2420, "a3", carol).unwrap();
+        assert_eq!(
+            waiting
+                .iter()
+                .map(|request| request.user_id)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        remove_partner_request(&db, &waiting[0]).unwrap();
+        assert_eq!(get_partner_requests(&db, 2420, "A3", bob).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn forgets_old_requests() {
+        let db = KingFisherDb::temporary().unwrap();
+        let request = PartnerRequest {
+            class: 2420,
+            assignment: "A3".to_owned(),
+            user_id: 1,
+            created_at: Utc::now() - Duration::days(REQUEST_LIFETIME_DAYS + 1),
+        };
+
+        db.insert(PARTNER_REQUESTS_TREE, request_key(2420, "A3", 1), &request)
+            .unwrap();
+
+        assert_eq!(
+            get_partner_requests(&db, 2420, "A3", UserId::new(2)).unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            db.values::<PartnerRequest>(PARTNER_REQUESTS_TREE).unwrap(),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use crate::data::Data;
+use chrono::{DateTime, Duration, Utc};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use poise::serenity_prelude::{self as serenity, ActivityData, ActivityType};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+/// Statuses kingfisher cycles through.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Presence {
+    /// e.g. `"Watching CS 2420 struggle"` or `"Listening to /help"`.
+    /// Anything not starting with Playing, Watching, Listening to or Competing in is a custom status.
+    pub activities: Vec<String>,
+    /// How long each activity shows for, in seconds.
+    #[serde_as(as = "DurationSeconds<i64>")]
+    #[serde(default = "get_default_interval")]
+    pub interval: Duration,
+}
+
+fn get_default_interval() -> Duration {
+    Duration::minutes(10)
+}
+
+const ACTIVITY_PREFIXES: [(&str, ActivityType); 4] = [
+    ("Playing ", ActivityType::Playing),
+    ("Watching ", ActivityType::Watching),
+    ("Listening to ", ActivityType::Listening),
+    ("Competing in ", ActivityType::Competing),
+];
+
+lazy_static! {
+    /// A status set by `/set_status`, and when rotation picks back up.
+    pub static ref STATUS_OVERRIDE: Mutex<Option<(String, DateTime<Utc>)>> = Mutex::new(None);
+}
+
+/// Turns "Watching CS 2420 struggle" into a watching activity, and so on.
+pub fn parse_activity(text: &str) -> ActivityData {
+    ACTIVITY_PREFIXES
+        .iter()
+        .find_map(|(prefix, kind)| {
+            text.get(..prefix.len())
+                .filter(|start| start.eq_ignore_ascii_case(prefix))
+                .map(|_| ActivityData {
+                    kind: *kind,
+                    ..ActivityData::playing(&text[prefix.len()..])
+                })
+        })
+        .unwrap_or_else(|| ActivityData::custom(text))
+}
+
+/// The override, if it hasn't run out yet.
+fn current_override(now: DateTime<Utc>) -> Option<String> {
+    let mut status_override = STATUS_OVERRIDE.lock();
+
+    match &*status_override {
+        Some((status, until)) if *until > now => Some(status.clone()),
+        _ => {
+            *status_override = None;
+            None
+        }
+    }
+}
+
+/// Cycles through the configured activities forever, re-reading the config each time.
+pub fn start(ctx: serenity::Context, data: Data) {
+    tokio::spawn(async move {
+        let mut next = 0;
+
+        loop {
+            let presence = data.config.read().await.presence.clone();
+
+            let Some(presence) = presence.filter(|presence| !presence.activities.is_empty()) else {
+                // Check back in case the config gets reloaded with a presence
+                tokio::time::sleep(get_default_interval().to_std().unwrap_or_default()).await;
+                continue;
+            };
+
+            let activity = match current_override(Utc::now()) {
+                Some(status) => status,
+                None => {
+                    let activity = presence.activities[next % presence.activities.len()].clone();
+                    next += 1;
+                    activity
+                }
+            };
+
+            ctx.set_activity(Some(parse_activity(&activity)));
+
+            tokio::time::sleep(presence.interval.to_std().unwrap_or_default()).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_activities() {
+        let kind = |text: &str| {
+            let activity = parse_activity(text);
+            (activity.kind, activity.name, activity.state)
+        };
+
+        assert_eq!(
+            kind("Watching CS 2420 struggle"),
+            (ActivityType::Watching, "CS 2420 struggle".to_owned(), None)
+        );
+        assert_eq!(
+            kind("listening to /help"),
+            (ActivityType::Listening, "/help".to_owned(), None)
+        );
+        assert_eq!(
+            kind("Studying for finals"),
+            (
+                ActivityType::Custom,
+                "~".to_owned(),
+                Some("Studying for finals".to_owned())
+            )
+        );
+    }
+
+    #[test]
+    fn overrides_expire() {
+        let now = Utc::now();
+
+        *STATUS_OVERRIDE.lock() =
+            Some(("Down for maintenance".to_owned(), now + Duration::hours(1)));
+        assert_eq!(
+            current_override(now).as_deref(),
+            Some("Down for maintenance")
+        );
+        assert_eq!(current_override(now + Duration::hours(2)), None);
+        assert!(STATUS_OVERRIDE.lock().is_none());
+    }
+}
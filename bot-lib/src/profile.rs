@@ -0,0 +1,203 @@
+use crate::db::KingFisherDb;
+use chrono::FixedOffset;
+use color_eyre::eyre::{eyre, Result};
+use poise::serenity_prelude::{self as serenity, RoleId, UserId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const PROFILES_TREE: &str = "profiles";
+
+/// What a member has told kingfisher about themselves.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+    pub pronouns: Option<String>,
+    /// Seconds east of UTC.
+    pub timezone: Option<i32>,
+    pub year: Option<String>,
+    pub major: Option<String>,
+}
+
+impl Profile {
+    /// For anything that schedules on the member's behalf.
+    pub fn timezone(&self) -> Option<FixedOffset> {
+        self.timezone.and_then(FixedOffset::east_opt)
+    }
+}
+
+pub fn get_profile(db: &KingFisherDb, user_id: UserId) -> Result<Profile> {
+    Ok(db
+        .get(PROFILES_TREE, user_id.get().to_be_bytes())?
+        .unwrap_or_default())
+}
+
+pub fn save_profile(db: &KingFisherDb, user_id: UserId, profile: &Profile) -> Result<()> {
+    db.insert(PROFILES_TREE, user_id.get().to_be_bytes(), profile)
+}
+
+/// Parses a UTC offset like `UTC-7`, `-07:00` or `+5:30`.
+///
+/// Offsets rather than names, so daylight saving time means updating it twice a year.
+pub fn parse_timezone(input: &str) -> Result<FixedOffset> {
+    let trimmed = input.trim();
+    let offset = ["utc", "gmt"]
+        .iter()
+        .find_map(|prefix| {
+            trimmed
+                .get(..prefix.len())
+                .filter(|start| start.eq_ignore_ascii_case(prefix))
+                .map(|_| &trimmed[prefix.len()..])
+        })
+        .unwrap_or(trimmed)
+        .trim();
+
+    if offset.is_empty() {
+        return Ok(FixedOffset::east_opt(0).expect("UTC is a valid offset"));
+    }
+
+    let invalid = || eyre!("`{}` isn't a UTC offset like UTC-7 or +5:30", input);
+
+    let (sign, rest) = if let Some(rest) = offset.strip_prefix('+') {
+        (1, rest)
+    } else if let Some(rest) = offset.strip_prefix('-') {
+        (-1, rest)
+    } else {
+        return Err(invalid());
+    };
+
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if rest.len() == 4 && rest.is_ascii() => rest.split_at(2),
+        None => (rest, "0"),
+    };
+
+    let hours = hours.parse::<i32>().map_err(|_| invalid())?;
+    let minutes = minutes.parse::<i32>().map_err(|_| invalid())?;
+
+    if hours > 14 || minutes >= 60 {
+        return Err(invalid());
+    }
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
+
+/// The pronoun roles that fit, e.g. "she/they" gets both the she/her and they/them roles.
+///
+/// Roles are keyed by their pronouns, and match on the first one.
+pub fn pronoun_roles_for(pronouns: &str, pronoun_roles: &HashMap<String, u64>) -> Vec<RoleId> {
+    let parts = pronouns
+        .split('/')
+        .map(|part| part.trim().to_lowercase())
+        .collect::<Vec<_>>();
+
+    let mut roles = pronoun_roles
+        .iter()
+        .filter(|(key, _)| {
+            key.split('/')
+                .next()
+                .is_some_and(|first| parts.contains(&first.trim().to_lowercase()))
+        })
+        .map(|(_, role_id)| RoleId::new(*role_id))
+        .collect::<Vec<_>>();
+
+    roles.sort();
+    roles
+}
+
+/// Gives the member the pronoun roles that fit and takes away the rest.
+pub async fn sync_pronoun_roles(
+    ctx: &serenity::Context,
+    member: &serenity::Member,
+    pronouns: Option<&str>,
+    pronoun_roles: &HashMap<String, u64>,
+) -> Result<()> {
+    let wanted = pronouns.map_or_else(Vec::new, |pronouns| {
+        pronoun_roles_for(pronouns, pronoun_roles)
+    });
+
+    let unwanted = pronoun_roles
+        .values()
+        .map(|role_id| RoleId::new(*role_id))
+        .filter(|role_id| member.roles.contains(role_id) && !wanted.contains(role_id))
+        .collect::<Vec<_>>();
+
+    let missing = wanted
+        .into_iter()
+        .filter(|role_id| !member.roles.contains(role_id))
+        .collect::<Vec<_>>();
+
+    if !unwanted.is_empty() {
+        member.remove_roles(ctx, &unwanted).await?;
+    }
+
+    if !missing.is_empty() {
+        member.add_roles(ctx, &missing).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_timezones() {
+        let hours = |hours: i32| FixedOffset::east_opt(hours * 3600).unwrap();
+
+        assert_eq!(parse_timezone("UTC-7").unwrap(), hours(-7));
+        assert_eq!(parse_timezone("utc").unwrap(), hours(0));
+        assert_eq!(parse_timezone("-07:00").unwrap(), hours(-7));
+        assert_eq!(parse_timezone("GMT+1").unwrap(), hours(1));
+        assert_eq!(
+            parse_timezone("+5:30").unwrap(),
+            FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap()
+        );
+        assert_eq!(
+            parse_timezone("+0545").unwrap(),
+            FixedOffset::east_opt(5 * 3600 + 45 * 60).unwrap()
+        );
+        assert!(parse_timezone("Mountain").is_err());
+        assert!(parse_timezone("UTC+25").is_err());
+        assert!(parse_timezone("7").is_err());
+    }
+
+    #[test]
+    fn matches_pronoun_roles() {
+        let pronoun_roles = HashMap::from([
+            ("he/him".to_owned(), 1),
+            ("she/her".to_owned(), 2),
+            ("they/them".to_owned(), 3),
+        ]);
+
+        assert_eq!(
+            pronoun_roles_for("He/Him", &pronoun_roles),
+            vec![RoleId::new(1)]
+        );
+        assert_eq!(
+            pronoun_roles_for("she/they", &pronoun_roles),
+            vec![RoleId::new(2), RoleId::new(3)]
+        );
+        assert!(pronoun_roles_for("xe/xem", &pronoun_roles).is_empty());
+    }
+
+    #[test]
+    fn round_trips_profiles() {
+        let db = KingFisherDb::temporary().unwrap();
+        let user_id = UserId::new(7);
+
+        assert_eq!(get_profile(&db, user_id).unwrap(), Profile::default());
+
+        let profile = Profile {
+            timezone: Some(-7 * 3600),
+            major: Some("Computer Science".to_owned()),
+            ..Default::default()
+        };
+        save_profile(&db, user_id, &profile).unwrap();
+
+        assert_eq!(get_profile(&db, user_id).unwrap(), profile);
+        assert_eq!(
+            get_profile(&db, user_id).unwrap().timezone(),
+            FixedOffset::east_opt(-7 * 3600)
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use crate::data::PoiseContext;
+use color_eyre::eyre::Result;
+use poise::{serenity_prelude as serenity, CreateReply, ReplyHandle};
+use std::time::{Duration, Instant};
+
+/// Discord allows about 5 message edits per 5 seconds, this stays well under that.
+const MIN_EDIT_INTERVAL: Duration = Duration::from_secs(3);
+const BAR_WIDTH: usize = 20;
+
+/// Keeps a single status message up to date while a command works through a long loop.
+///
+/// Updates are best-effort: the interaction token runs out after 15 minutes, after which the
+/// status moves to a plain message in the channel rather than stopping the command partway.
+pub struct ProgressReporter<'a> {
+    ctx: PoiseContext<'a>,
+    message: ReplyHandle<'a>,
+    /// Posted in the channel once the reply can't be edited anymore.
+    fallback: Option<serenity::Message>,
+    label: String,
+    total: usize,
+    done: usize,
+    started: Instant,
+    last_edit: Instant,
+}
+
+impl<'a> ProgressReporter<'a> {
+    /// Sends the status message, as the reply if there isn't one yet.
+    pub async fn start(
+        ctx: PoiseContext<'a>,
+        label: impl Into<String>,
+        total: usize,
+    ) -> Result<ProgressReporter<'a>> {
+        let label = label.into();
+        let message = ctx
+            .send(CreateReply::default().content(render(&label, 0, total, None)))
+            .await?;
+
+        Ok(ProgressReporter {
+            ctx,
+            message,
+            fallback: None,
+            label,
+            total,
+            done: 0,
+            started: Instant::now(),
+            last_edit: Instant::now(),
+        })
+    }
+
+    /// For loops that only find out how much work there is as they go.
+    pub fn add_total(&mut self, more: usize) {
+        self.total += more;
+    }
+
+    /// For labels that count things up as they go, shown with the next update.
+    pub fn set_label(&mut self, label: impl Into<String>) {
+        self.label = label.into();
+    }
+
+    /// Marks one more item done, editing the message if it's been a while since the last edit.
+    pub async fn advance(&mut self) {
+        self.done += 1;
+
+        if self.last_edit.elapsed() < MIN_EDIT_INTERVAL {
+            return;
+        }
+
+        self.last_edit = Instant::now();
+
+        let eta = eta(self.started.elapsed(), self.done, self.total);
+
+        self.show(render(&self.label, self.done, self.total, eta))
+            .await;
+    }
+
+    /// Replaces the progress bar with how it went.
+    pub async fn finish(mut self, summary: impl Into<String>) {
+        self.show(summary.into()).await;
+    }
+
+    async fn show(&mut self, content: String) {
+        if let Some(fallback) = &mut self.fallback {
+            if let Err(e) = fallback
+                .edit(self.ctx, serenity::EditMessage::new().content(content))
+                .await
+            {
+                tracing::warn!("Couldn't update progress: {:?}", e);
+            }
+            return;
+        }
+
+        let Err(e) = self
+            .message
+            .edit(self.ctx, CreateReply::default().content(&content))
+            .await
+        else {
+            return;
+        };
+
+        tracing::warn!(
+            "Couldn't update progress, posting it in the channel instead: {:?}",
+            e
+        );
+
+        match self
+            .ctx
+            .channel_id()
+            .send_message(
+                self.ctx,
+                serenity::CreateMessage::new()
+                    .content(content)
+                    .allowed_mentions(serenity::CreateAllowedMentions::new()),
+            )
+            .await
+        {
+            Ok(message) => self.fallback = Some(message),
+            Err(e) => tracing::warn!("Couldn't post progress either: {:?}", e),
+        }
+    }
+}
+
+/// How much longer the rest will take, going by how long the done part took.
+fn eta(elapsed: Duration, done: usize, total: usize) -> Option<Duration> {
+    if done == 0 || done >= total {
+        return None;
+    }
+
+    Some(elapsed.mul_f64((total - done) as f64 / done as f64))
+}
+
+fn render(label: &str, done: usize, total: usize, eta: Option<Duration>) -> String {
+    let fraction = if total == 0 {
+        0.0
+    } else {
+        (done as f64 / total as f64).min(1.0)
+    };
+    let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+
+    let mut status = format!(
+        "{}\n`[{}{}]` {:.0}% ({}/{})",
+        label,
+        "█".repeat(filled),
+        "░".repeat(BAR_WIDTH - filled),
+        fraction * 100.0,
+        done,
+        total
+    );
+
+    if let Some(eta) = eta {
+        let seconds = eta.as_secs();
+        status.push_str(&format!(", about {}m {}s left", seconds / 60, seconds % 60));
+    }
+
+    status
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn estimates_time_left() {
+        assert_eq!(eta(Duration::from_secs(10), 0, 10), None);
+        assert_eq!(
+            eta(Duration::from_secs(10), 5, 20),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(eta(Duration::from_secs(10), 20, 20), None);
+    }
+
+    #[test]
+    fn renders_a_progress_bar() {
+        assert_eq!(
+            render("Removing roles", 5, 20, Some(Duration::from_secs(90))),
+            "Removing roles\n`[█████░░░░░░░░░░░░░░░]` 25% (5/20), about 1m 30s left"
+        );
+        assert_eq!(
+            render("Nothing yet", 0, 0, None),
+            "Nothing yet\n`[░░░░░░░░░░░░░░░░░░░░]` 0% (0/0)"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use crate::data::AppState;
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, NaiveTime, Utc, Weekday};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{self as serenity, ChannelId};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+
+/// A weekly window (exams, club meetings) in which kingfisher doesn't respond to messages.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct QuietHours {
+    /// Channels or whole categories.
+    pub channel_ids: Vec<u64>,
+    /// e.g. `["Mon", "Wed"]`, every day if empty.
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    /// Server local time, e.g. `"14:00:00"`.
+    #[serde_as(as = "DisplayFromStr")]
+    pub start: NaiveTime,
+    /// May be before `start` for windows going past midnight.
+    #[serde_as(as = "DisplayFromStr")]
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    fn is_active(&self, now: NaiveDateTime) -> bool {
+        if !self.days.is_empty() && !self.days.contains(&now.weekday()) {
+            return false;
+        }
+
+        let time = now.time();
+
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+/// A mod forcing a channel to be quiet (or not) for a while.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietOverride {
+    pub quiet: bool,
+    pub until: DateTime<Utc>,
+}
+
+lazy_static! {
+    /// Overrides keyed by channel or category id. They expire on their own.
+    pub static ref QUIET_HOURS_OVERRIDES: DashMap<u64, QuietOverride> = DashMap::new();
+}
+
+fn find_override(ids: &[u64], now: DateTime<Utc>) -> Option<bool> {
+    QUIET_HOURS_OVERRIDES.retain(|_, quiet_override| quiet_override.until > now);
+
+    ids.iter()
+        .find_map(|id| QUIET_HOURS_OVERRIDES.get(id).map(|o| o.quiet))
+}
+
+/// Whether text detection responses are suppressed in a channel right now.
+pub async fn is_quiet(ctx: &serenity::Context, data: &AppState, channel_id: ChannelId) -> bool {
+    let parent_id = channel_id
+        .to_channel(ctx)
+        .await
+        .ok()
+        .and_then(|channel| channel.guild())
+        .and_then(|channel| channel.parent_id);
+
+    let ids = [Some(channel_id), parent_id]
+        .into_iter()
+        .flatten()
+        .map(ChannelId::get)
+        .collect::<Vec<_>>();
+
+    if let Some(quiet) = find_override(&ids, Utc::now()) {
+        return quiet;
+    }
+
+    let now = Local::now().naive_local();
+
+    data.config
+        .read()
+        .await
+        .quiet_hours
+        .iter()
+        .filter(|quiet_hours| quiet_hours.channel_ids.iter().any(|id| ids.contains(id)))
+        .any(|quiet_hours| quiet_hours.is_active(now))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn deserializes_quiet_hours() {
+        let quiet_hours: QuietHours = toml::from_str(
+            r#"
+channel_ids = [1]
+days = ["Mon", "wednesday"]
+start = "14:00:00"
+end = "15:30:00"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(quiet_hours.days, vec![Weekday::Mon, Weekday::Wed]);
+        assert_eq!(quiet_hours.end, NaiveTime::from_hms_opt(15, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn checks_windows() {
+        let window = |days: Vec<Weekday>, start: u32, end: u32| QuietHours {
+            channel_ids: vec![],
+            days,
+            start: NaiveTime::from_hms_opt(start, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(end, 0, 0).unwrap(),
+        };
+        // A Monday
+        let at = |hour: u32| {
+            NaiveDate::from_ymd_opt(2024, 4, 15)
+                .unwrap()
+                .and_hms_opt(hour, 0, 0)
+                .unwrap()
+        };
+
+        assert!(window(vec![], 14, 16).is_active(at(15)));
+        assert!(!window(vec![], 14, 16).is_active(at(16)));
+        assert!(!window(vec![Weekday::Tue], 14, 16).is_active(at(15)));
+        assert!(window(vec![], 22, 2).is_active(at(23)));
+        assert!(window(vec![], 22, 2).is_active(at(1)));
+        assert!(!window(vec![], 22, 2).is_active(at(12)));
+    }
+
+    #[test]
+    fn overrides_expire() {
+        let now = Utc::now();
+
+        QUIET_HOURS_OVERRIDES.insert(
+            1001,
+            QuietOverride {
+                quiet: true,
+                until: now + chrono::Duration::minutes(5),
+            },
+        );
+        QUIET_HOURS_OVERRIDES.insert(
+            1002,
+            QuietOverride {
+                quiet: false,
+                until: now - chrono::Duration::minutes(5),
+            },
+        );
+
+        assert_eq!(find_override(&[1002, 1001], now), Some(true));
+        assert_eq!(find_override(&[1002], now), None);
+    }
+}
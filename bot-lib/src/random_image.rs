@@ -0,0 +1,81 @@
+use color_eyre::eyre::{OptionExt, Result, WrapErr};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use rand::seq::SliceRandom;
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// Discord rejects uploads bigger than this.
+const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+/// How long a directory listing is reused, so new files show up without a restart.
+const LISTING_TTL: Duration = Duration::from_secs(5 * 60);
+
+lazy_static! {
+    static ref LISTINGS: DashMap<PathBuf, (Instant, Vec<PathBuf>)> = DashMap::new();
+}
+
+/// Every file in `dir` small enough to upload.
+fn list_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let files = std::fs::read_dir(dir)
+        .wrap_err_with(|| format!("Could not read {}", dir.display()))?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok()?;
+
+            if !metadata.is_file() {
+                return None;
+            }
+
+            if metadata.len() > MAX_FILE_SIZE {
+                tracing::warn!("{} is too big to upload", entry.path().display());
+                return None;
+            }
+
+            Some(entry.path())
+        })
+        .collect();
+
+    Ok(files)
+}
+
+/// A random uploadable file from `dir`.
+pub fn pick_random_file(dir: &str) -> Result<PathBuf> {
+    let dir = PathBuf::from(dir);
+
+    let cached = LISTINGS
+        .get(&dir)
+        .filter(|listing| listing.0.elapsed() < LISTING_TTL)
+        .map(|listing| listing.1.clone());
+
+    let files = match cached {
+        Some(files) => files,
+        None => {
+            let files = list_files(&dir)?;
+            LISTINGS.insert(dir.clone(), (Instant::now(), files.clone()));
+            files
+        }
+    };
+
+    files
+        .choose(&mut rand::thread_rng())
+        .cloned()
+        .ok_or_eyre("No files to pick from")
+}
+
+#[test]
+fn skips_big_files_and_directories() {
+    let dir = std::env::temp_dir().join(format!("kingfisher-random-image-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("nested")).unwrap();
+    std::fs::write(dir.join("small.png"), [0; 16]).unwrap();
+    std::fs::File::create(dir.join("big.gif"))
+        .unwrap()
+        .set_len(MAX_FILE_SIZE + 1)
+        .unwrap();
+
+    let files = list_files(&dir);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(files.unwrap(), vec![dir.join("small.png")]);
+}
@@ -0,0 +1,120 @@
+use crate::{data::AppState, db::KingFisherDb};
+use color_eyre::eyre::Result;
+use dashmap::DashMap;
+use poise::serenity_prelude::{self as serenity, RoleId, UserId};
+
+const REACT_ROLE_MEMBERS_TREE: &str = "react_role_members";
+
+/// Who has the bot react role, so text detection doesn't have to ask Discord for every message.
+///
+/// Kept up to date from member events, and saved to the database so it survives restarts.
+#[derive(Debug, Default)]
+pub struct ReactRoleCache {
+    members: DashMap<UserId, bool>,
+}
+
+impl ReactRoleCache {
+    /// Picks up where the last run left off.
+    pub fn load(db: &KingFisherDb) -> Result<Self> {
+        let members = db
+            .entries::<bool>(REACT_ROLE_MEMBERS_TREE)?
+            .into_iter()
+            .filter_map(|(key, react)| {
+                let user_id = u64::from_be_bytes(key.as_slice().try_into().ok()?);
+
+                Some((UserId::new(user_id), react))
+            })
+            .collect();
+
+        Ok(ReactRoleCache { members })
+    }
+
+    /// Whether the member wants kingfisher's reactions, or None if we don't know yet.
+    pub fn wants_reactions(&self, user_id: UserId) -> Option<bool> {
+        self.members.get(&user_id).map(|react| *react)
+    }
+
+    pub fn set(&self, db: &KingFisherDb, user_id: UserId, react: bool) -> Result<()> {
+        if self.members.insert(user_id, react) == Some(react) {
+            return Ok(());
+        }
+
+        db.insert(REACT_ROLE_MEMBERS_TREE, user_id.get().to_be_bytes(), &react)
+    }
+
+    pub fn forget(&self, db: &KingFisherDb, user_id: UserId) -> Result<()> {
+        self.members.remove(&user_id);
+        db.remove::<bool>(REACT_ROLE_MEMBERS_TREE, user_id.get().to_be_bytes())?;
+
+        Ok(())
+    }
+
+    /// Forgets everyone, so they get looked up again.
+    pub fn clear(&self, db: &KingFisherDb) -> Result<()> {
+        for user_id in self
+            .members
+            .iter()
+            .map(|entry| *entry.key())
+            .collect::<Vec<_>>()
+        {
+            self.forget(db, user_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Records whether the member has the role, after they join or their roles change.
+    pub fn update_member(
+        &self,
+        db: &KingFisherDb,
+        role_id: RoleId,
+        member: &serenity::Member,
+    ) -> Result<()> {
+        self.set(db, member.user.id, member.roles.contains(&role_id))
+    }
+}
+
+/// Keeps the cache current as members join or their roles change.
+pub async fn track_react_role(data: &AppState, member: &serenity::Member) -> Result<()> {
+    let role_id = data.config.read().await.bot_react_role_id;
+
+    data.react_roles
+        .update_member(&data.db, role_id.into(), member)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn persists_members() {
+        let db = KingFisherDb::temporary().unwrap();
+        let cache = ReactRoleCache::default();
+        let role_id = RoleId::new(10);
+
+        let mut member = serenity::Member::default();
+        member.user.id = UserId::new(1);
+        member.roles = vec![role_id];
+
+        cache.update_member(&db, role_id, &member).unwrap();
+        cache.set(&db, UserId::new(2), false).unwrap();
+        assert_eq!(cache.wants_reactions(UserId::new(1)), Some(true));
+        assert_eq!(cache.wants_reactions(UserId::new(3)), None);
+
+        member.roles.clear();
+        cache.update_member(&db, role_id, &member).unwrap();
+
+        let reloaded = ReactRoleCache::load(&db).unwrap();
+        assert_eq!(reloaded.wants_reactions(UserId::new(1)), Some(false));
+        assert_eq!(reloaded.wants_reactions(UserId::new(2)), Some(false));
+
+        reloaded.clear(&db).unwrap();
+        assert_eq!(reloaded.wants_reactions(UserId::new(1)), None);
+        assert_eq!(
+            ReactRoleCache::load(&db)
+                .unwrap()
+                .wants_reactions(UserId::new(2)),
+            None
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use crate::{
+    author_guard::human_reaction_count, data::AppState, db::KingFisherDb, mod_log::mod_log,
+};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, EditMessage, Mentionable};
+use serde::{Deserialize, Serialize};
+
+const RENAME_VOTES_TREE: &str = "rename_votes";
+
+pub const VOTE_YES: char = '✅';
+pub const VOTE_NO: char = '❌';
+
+/// How `/rename_vote` decides, `[rename_votes]`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RenameVotes {
+    /// How long votes stay open.
+    #[serde(default = "get_default_minutes")]
+    pub minutes: i64,
+    /// How many people have to vote, either way, for the result to count.
+    #[serde(default = "get_default_quorum")]
+    pub quorum: u64,
+    /// The share of votes that have to be yes, from 0 to 1.
+    #[serde(default = "get_default_pass_ratio")]
+    pub pass_ratio: f64,
+    /// Channels that can't be voted on, like the rules.
+    #[serde(default)]
+    pub protected_channel_ids: Vec<u64>,
+}
+
+fn get_default_minutes() -> i64 {
+    60
+}
+
+fn get_default_quorum() -> u64 {
+    5
+}
+
+fn get_default_pass_ratio() -> f64 {
+    0.5
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VoteOutcome {
+    Passed,
+    Failed,
+    NoQuorum,
+}
+
+impl RenameVotes {
+    /// Ties fail at the default ratio, so half the room can't rename over the other half.
+    pub fn outcome(&self, yes: u64, no: u64) -> VoteOutcome {
+        let total = yes + no;
+
+        if total < self.quorum || total == 0 {
+            VoteOutcome::NoQuorum
+        } else if yes as f64 / total as f64 > self.pass_ratio {
+            VoteOutcome::Passed
+        } else {
+            VoteOutcome::Failed
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenameVote {
+    pub channel_id: u64,
+    /// Where the vote was posted, which isn't always the channel being renamed.
+    #[serde(default)]
+    pub message_channel_id: u64,
+    pub message_id: u64,
+    pub old_name: String,
+    pub proposed_name: String,
+    pub proposed_by: u64,
+    pub closes_at: DateTime<Utc>,
+    pub yes: u64,
+    pub no: u64,
+    /// None while the vote is open.
+    pub outcome: Option<VoteOutcome>,
+}
+
+pub fn save_rename_vote(db: &KingFisherDb, vote: &RenameVote) -> Result<()> {
+    db.insert(RENAME_VOTES_TREE, vote.message_id.to_be_bytes(), vote)
+}
+
+/// Whether the channel already has a vote going. Votes cut off by a restart stop counting once
+/// they'd have closed.
+pub fn has_open_vote(db: &KingFisherDb, channel_id: u64, now: DateTime<Utc>) -> Result<bool> {
+    Ok(db
+        .values::<RenameVote>(RENAME_VOTES_TREE)?
+        .iter()
+        .any(|vote| {
+            vote.channel_id == channel_id && vote.outcome.is_none() && vote.closes_at > now
+        }))
+}
+
+/// Open votes whose time is up, soonest first.
+fn due_votes(db: &KingFisherDb, now: DateTime<Utc>) -> Result<Vec<RenameVote>> {
+    let mut due = db
+        .values::<RenameVote>(RENAME_VOTES_TREE)?
+        .into_iter()
+        .filter(|vote| vote.outcome.is_none() && vote.closes_at <= now)
+        .collect::<Vec<_>>();
+
+    due.sort_by_key(|vote| vote.closes_at);
+
+    Ok(due)
+}
+
+fn count(message: &serenity::Message, reaction: char) -> u64 {
+    message
+        .reactions
+        .iter()
+        .find(|r| r.reaction_type.unicode_eq(&reaction.to_string()))
+        .map_or(0, human_reaction_count)
+}
+
+/// Counts the vote, renames the channel if it passed, and edits the result into the vote.
+async fn close_rename_vote(
+    ctx: &serenity::Context,
+    data: &AppState,
+    rules: &RenameVotes,
+    mut vote: RenameVote,
+) -> Result<()> {
+    let channel_id = serenity::ChannelId::new(vote.channel_id);
+    let mut message = match serenity::ChannelId::new(vote.message_channel_id)
+        .message(ctx, vote.message_id)
+        .await
+    {
+        Ok(message) => message,
+        Err(e) => {
+            // The vote was probably deleted, so stop retrying it eventually
+            if Utc::now() - vote.closes_at > chrono::Duration::days(1) {
+                vote.outcome = Some(VoteOutcome::NoQuorum);
+                save_rename_vote(&data.db, &vote)?;
+            }
+
+            return Err(e.into());
+        }
+    };
+    vote.yes = count(&message, VOTE_YES);
+    vote.no = count(&message, VOTE_NO);
+
+    let outcome = rules.outcome(vote.yes, vote.no);
+    let mut renamed = false;
+
+    if outcome == VoteOutcome::Passed {
+        let reason = format!("Rename vote by {}", vote.proposed_by);
+        match channel_id
+            .edit(
+                ctx,
+                serenity::EditChannel::new()
+                    .name(&vote.proposed_name)
+                    .audit_log_reason(&reason),
+            )
+            .await
+        {
+            Ok(_) => renamed = true,
+            Err(e) => tracing::warn!("Couldn't apply rename vote: {:?}", e),
+        }
+    }
+
+    vote.outcome = Some(outcome);
+    save_rename_vote(&data.db, &vote)?;
+
+    let result = match outcome {
+        VoteOutcome::Passed if renamed => "Passed, renamed.",
+        VoteOutcome::Passed => "Passed, but kingfisher couldn't rename it.",
+        VoteOutcome::Failed => "Didn't pass.",
+        VoteOutcome::NoQuorum => "Not enough votes.",
+    };
+
+    message
+        .edit(
+            ctx,
+            EditMessage::new().content(format!(
+                "**Rename vote:** {} from `{}` to `{}`?\n{} {} · {} {} · {}",
+                channel_id.mention(),
+                vote.old_name,
+                vote.proposed_name,
+                VOTE_YES,
+                vote.yes,
+                VOTE_NO,
+                vote.no,
+                result
+            )),
+        )
+        .await?;
+
+    mod_log(
+        ctx,
+        data,
+        serenity::CreateEmbed::new()
+            .title("Rename vote")
+            .description(format!(
+                "{} proposed renaming {} from `{}` to `{}`.\n{} {} · {} {} · {}",
+                serenity::UserId::new(vote.proposed_by).mention(),
+                channel_id.mention(),
+                vote.old_name,
+                vote.proposed_name,
+                VOTE_YES,
+                vote.yes,
+                VOTE_NO,
+                vote.no,
+                result
+            )),
+    )
+    .await
+}
+
+/// Closes every rename vote whose time is up, including ones a restart cut off.
+pub async fn close_rename_votes(ctx: &serenity::Context, data: &AppState) -> Result<()> {
+    let Some(rules) = data.config.read().await.rename_votes.clone() else {
+        return Ok(());
+    };
+
+    for vote in due_votes(&data.db, Utc::now())? {
+        let message_id = vote.message_id;
+
+        if let Err(e) = close_rename_vote(ctx, data, &rules, vote).await {
+            tracing::warn!("Couldn't close rename vote {}: {:?}", message_id, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Roughly what Discord does to text channel names, so the vote shows the name it'd really get.
+pub fn normalize_channel_name(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rules() -> RenameVotes {
+        toml::from_str("quorum = 4").unwrap()
+    }
+
+    #[test]
+    fn needs_quorum_and_majority() {
+        assert_eq!(rules().outcome(3, 0), VoteOutcome::NoQuorum);
+        assert_eq!(rules().outcome(3, 1), VoteOutcome::Passed);
+        assert_eq!(rules().outcome(2, 2), VoteOutcome::Failed);
+        assert_eq!(rules().outcome(1, 5), VoteOutcome::Failed);
+
+        let strict = RenameVotes {
+            pass_ratio: 0.75,
+            ..rules()
+        };
+        assert_eq!(strict.outcome(3, 1), VoteOutcome::Failed);
+        assert_eq!(strict.outcome(4, 1), VoteOutcome::Passed);
+    }
+
+    #[test]
+    fn only_one_vote_per_channel() {
+        let db = KingFisherDb::temporary().unwrap();
+        let mut vote = RenameVote {
+            channel_id: 1,
+            message_channel_id: 5,
+            message_id: 2,
+            old_name: "memes".to_owned(),
+            proposed_name: "meme-zone".to_owned(),
+            proposed_by: 3,
+            closes_at: Utc::now() + chrono::Duration::hours(1),
+            yes: 0,
+            no: 0,
+            outcome: None,
+        };
+
+        let now = Utc::now();
+
+        save_rename_vote(&db, &vote).unwrap();
+        assert!(has_open_vote(&db, 1, now).unwrap());
+        assert!(!has_open_vote(&db, 4, now).unwrap());
+        assert!(!has_open_vote(&db, 1, now + chrono::Duration::hours(2)).unwrap());
+
+        vote.outcome = Some(VoteOutcome::Passed);
+        save_rename_vote(&db, &vote).unwrap();
+        assert!(!has_open_vote(&db, 1, now).unwrap());
+    }
+
+    #[test]
+    fn finds_votes_to_close() {
+        let db = KingFisherDb::temporary().unwrap();
+        let now = Utc::now();
+        let vote = |message_id, minutes, outcome| RenameVote {
+            channel_id: 1,
+            message_channel_id: 5,
+            message_id,
+            old_name: "memes".to_owned(),
+            proposed_name: "meme-zone".to_owned(),
+            proposed_by: 3,
+            closes_at: now + chrono::Duration::minutes(minutes),
+            yes: 0,
+            no: 0,
+            outcome,
+        };
+
+        save_rename_vote(&db, &vote(1, 10, None)).unwrap();
+        save_rename_vote(&db, &vote(2, -5, None)).unwrap();
+        save_rename_vote(&db, &vote(3, -10, None)).unwrap();
+        save_rename_vote(&db, &vote(4, -10, Some(VoteOutcome::Failed))).unwrap();
+
+        assert_eq!(
+            due_votes(&db, now)
+                .unwrap()
+                .iter()
+                .map(|vote| vote.message_id)
+                .collect::<Vec<_>>(),
+            vec![3, 2]
+        );
+    }
+
+    #[test]
+    fn normalizes_names() {
+        assert_eq!(normalize_channel_name("  Meme  Zone "), "meme-zone");
+        assert_eq!(normalize_channel_name("cs-3500"), "cs-3500");
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{author_guard::is_from_human, data::AppState, db::KingFisherDb};
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{Result, WrapErr};
+use image::{imageops::FilterType, DynamicImage};
+use poise::serenity_prelude::{self as serenity, ChannelId, MessageId};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+const REPOST_HASHES_TREE: &str = "repost_hashes";
+/// Bigger attachments aren't worth downloading to check.
+const MAX_ATTACHMENT_SIZE: u32 = 10 * 1024 * 1024;
+
+/// Points out images that have already been posted, `[reposts]`.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Reposts {
+    pub channel_ids: Vec<u64>,
+    /// Reacted to reposts, either a unicode emoji or `<:name:id>`.
+    #[serde(default = "get_default_emoji")]
+    pub emoji: String,
+    /// Whether to reply with a link to where it was first posted.
+    #[serde(default)]
+    pub link_original: bool,
+    /// How long an image counts as already posted, in seconds.
+    #[serde_as(as = "DurationSeconds<i64>")]
+    #[serde(default = "get_default_window")]
+    pub window: Duration,
+    /// How many of the 64 hash bits can differ for two images to count as the same,
+    /// so re-encoded or slightly resized copies are still caught.
+    #[serde(default = "get_default_max_distance")]
+    pub max_distance: u32,
+}
+
+fn get_default_emoji() -> String {
+    "♻️".to_owned()
+}
+
+fn get_default_window() -> Duration {
+    Duration::days(30)
+}
+
+fn get_default_max_distance() -> u32 {
+    6
+}
+
+/// Where an image was first seen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PostedImage {
+    pub hash: u64,
+    pub channel_id: u64,
+    pub message_id: u64,
+    pub posted_at: DateTime<Utc>,
+}
+
+/// A difference hash, which compares each pixel to its neighbour in a 9x8 grayscale copy of the
+/// image, so it survives resizing, recompression and small color changes.
+pub fn perceptual_hash(image: &DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+    (0..8)
+        .flat_map(|y| (0..8).map(move |x| (x, y)))
+        .fold(0, |hash, (x, y)| {
+            let brighter = small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0];
+            (hash << 1) | brighter as u64
+        })
+}
+
+fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// The earliest image in the window close enough to `hash`, forgetting anything older.
+fn find_original(
+    db: &KingFisherDb,
+    settings: &Reposts,
+    hash: u64,
+    now: DateTime<Utc>,
+) -> Result<Option<PostedImage>> {
+    let mut original: Option<PostedImage> = None;
+
+    for (key, posted) in db.entries::<PostedImage>(REPOST_HASHES_TREE)? {
+        if now - posted.posted_at > settings.window {
+            db.remove::<PostedImage>(REPOST_HASHES_TREE, key)?;
+            continue;
+        }
+
+        if distance(posted.hash, hash) <= settings.max_distance
+            && original
+                .as_ref()
+                .is_none_or(|original| posted.posted_at < original.posted_at)
+        {
+            original = Some(posted);
+        }
+    }
+
+    Ok(original)
+}
+
+fn remember_image(db: &KingFisherDb, posted: &PostedImage) -> Result<()> {
+    let mut key = posted.message_id.to_be_bytes().to_vec();
+    key.extend(posted.hash.to_be_bytes());
+
+    db.insert(REPOST_HASHES_TREE, key, posted)
+}
+
+async fn hash_attachment(attachment: &serenity::Attachment) -> Result<u64> {
+    let bytes = attachment.download().await?;
+
+    tokio::task::spawn_blocking(move || {
+        image::load_from_memory(&bytes)
+            .map(|image| perceptual_hash(&image))
+            .wrap_err("Couldn't decode attachment")
+    })
+    .await?
+}
+
+/// Reacts to images that were already posted in a repost channel recently.
+pub async fn police_reposts(
+    ctx: &serenity::Context,
+    data: &AppState,
+    message: &serenity::Message,
+) -> Result<()> {
+    let Some(settings) = data
+        .config
+        .read()
+        .await
+        .reposts
+        .clone()
+        .filter(|reposts| reposts.channel_ids.contains(&message.channel_id.get()))
+    else {
+        return Ok(());
+    };
+
+    if !is_from_human(ctx, data, message).await {
+        return Ok(());
+    }
+
+    let images = message.attachments.iter().filter(|attachment| {
+        attachment.size <= MAX_ATTACHMENT_SIZE
+            && attachment
+                .content_type
+                .as_deref()
+                .is_some_and(|kind| kind.starts_with("image/"))
+    });
+
+    let mut original = None;
+    let now = Utc::now();
+
+    for attachment in images {
+        let hash = match hash_attachment(attachment).await {
+            Ok(hash) => hash,
+            Err(e) => {
+                tracing::debug!("Couldn't hash {}: {:?}", attachment.url, e);
+                continue;
+            }
+        };
+
+        match find_original(&data.db, &settings, hash, now)? {
+            Some(posted) => {
+                original.get_or_insert(posted);
+            }
+            None => remember_image(
+                &data.db,
+                &PostedImage {
+                    hash,
+                    channel_id: message.channel_id.get(),
+                    message_id: message.id.get(),
+                    posted_at: now,
+                },
+            )?,
+        }
+    }
+
+    let Some(original) = original else {
+        return Ok(());
+    };
+
+    let emoji = serenity::ReactionType::try_from(settings.emoji.as_str())
+        .wrap_err("Invalid repost emoji")?;
+    message.react(ctx, emoji).await?;
+
+    if settings.link_original {
+        message
+            .channel_id
+            .send_message(
+                ctx,
+                serenity::CreateMessage::new()
+                    .content(format!(
+                        "Seen before: {}",
+                        MessageId::new(original.message_id)
+                            .link(ChannelId::new(original.channel_id), message.guild_id)
+                    ))
+                    .reference_message(message)
+                    .allowed_mentions(serenity::CreateAllowedMentions::new()),
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn gradient(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            Rgb([(x * 255 / width) as u8, (y * 255 / height) as u8, 128])
+        }))
+    }
+
+    fn settings() -> Reposts {
+        Reposts {
+            channel_ids: vec![1],
+            emoji: get_default_emoji(),
+            link_original: true,
+            window: get_default_window(),
+            max_distance: get_default_max_distance(),
+        }
+    }
+
+    #[test]
+    fn resized_copies_hash_alike() {
+        let original = perceptual_hash(&gradient(300, 200));
+        let resized = perceptual_hash(&gradient(150, 100));
+        let flipped = perceptual_hash(&gradient(300, 200).fliph());
+
+        assert!(distance(original, resized) <= get_default_max_distance());
+        assert!(distance(original, flipped) > get_default_max_distance());
+    }
+
+    #[test]
+    fn finds_the_first_post_within_the_window() {
+        let db = KingFisherDb::temporary().unwrap();
+        let now = Utc::now();
+        let posted = |message_id, hash, days_ago| PostedImage {
+            hash,
+            channel_id: 1,
+            message_id,
+            posted_at: now - Duration::days(days_ago),
+        };
+
+        remember_image(&db, &posted(1, 0b1111, 40)).unwrap();
+        remember_image(&db, &posted(2, 0b1110, 10)).unwrap();
+        remember_image(&db, &posted(3, 0b1111, 5)).unwrap();
+        remember_image(&db, &posted(4, u64::MAX, 20)).unwrap();
+
+        assert_eq!(
+            find_original(&db, &settings(), 0b1111, now).unwrap(),
+            Some(posted(2, 0b1110, 10))
+        );
+        // Too old to count, so it was forgotten
+        assert_eq!(
+            db.values::<PostedImage>(REPOST_HASHES_TREE).unwrap().len(),
+            3
+        );
+    }
+}
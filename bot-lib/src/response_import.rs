@@ -0,0 +1,369 @@
+use crate::{config::ResponseKind, lang::ruleset::Ruleset};
+use color_eyre::eyre::{bail, Result, WrapErr};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{path::Path, str::FromStr};
+
+/// The bots whose responses `kingfisher import` understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// A JSON array of YAGPDB custom commands.
+    Yagpdb,
+    /// A JSON array of Carl-bot autoresponders.
+    Carlbot,
+}
+
+impl FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "yagpdb" => Ok(ImportFormat::Yagpdb),
+            "carlbot" | "carl-bot" => Ok(ImportFormat::Carlbot),
+            _ => Err(format!(
+                "unknown format `{}`, expected yagpdb or carlbot",
+                s
+            )),
+        }
+    }
+}
+
+/// How a trigger is compared to the message, common to both bots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TriggerKind {
+    /// YAGPDB's commands, which start with its prefix.
+    Command,
+    StartsWith,
+    EndsWith,
+    Contains,
+    /// Contains the trigger as a whole word.
+    Word,
+    Exact,
+    Regex,
+}
+
+#[derive(Debug, Deserialize)]
+struct YagpdbCommand {
+    /// Either YAGPDB's number for the trigger type, or its name.
+    trigger_type: serde_json::Value,
+    #[serde(default)]
+    trigger: String,
+    #[serde(default)]
+    case_sensitive: bool,
+    #[serde(default)]
+    responses: Vec<String>,
+}
+
+impl YagpdbCommand {
+    fn trigger_kind(&self) -> Option<TriggerKind> {
+        match &self.trigger_type {
+            serde_json::Value::Number(number) => match number.as_u64()? {
+                0 => Some(TriggerKind::Command),
+                1 => Some(TriggerKind::StartsWith),
+                2 => Some(TriggerKind::Contains),
+                3 => Some(TriggerKind::Regex),
+                4 => Some(TriggerKind::Exact),
+                _ => None,
+            },
+            serde_json::Value::String(name) => {
+                match name.to_lowercase().replace(['_', ' '], "").as_str() {
+                    "command" => Some(TriggerKind::Command),
+                    "startswith" | "prefix" => Some(TriggerKind::StartsWith),
+                    "contains" => Some(TriggerKind::Contains),
+                    "regex" => Some(TriggerKind::Regex),
+                    "exact" | "exactmatch" => Some(TriggerKind::Exact),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CarlbotAutoresponder {
+    trigger: String,
+    response: String,
+    /// Carl-bot's match type, `contains` if not given.
+    #[serde(default, rename = "match")]
+    match_type: Option<String>,
+}
+
+impl CarlbotAutoresponder {
+    fn trigger_kind(&self) -> Option<TriggerKind> {
+        match self
+            .match_type
+            .as_deref()
+            .unwrap_or("contains")
+            .to_lowercase()
+            .replace(['_', ' '], "")
+            .as_str()
+        {
+            "contains" => Some(TriggerKind::Contains),
+            "word" | "containsword" => Some(TriggerKind::Word),
+            "startswith" => Some(TriggerKind::StartsWith),
+            "endswith" => Some(TriggerKind::EndsWith),
+            "exact" | "exactmatch" => Some(TriggerKind::Exact),
+            "regex" => Some(TriggerKind::Regex),
+            _ => None,
+        }
+    }
+}
+
+/// A response in the shape of `[[responses]]`, without the options neither bot has.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportedResponse {
+    pub name: String,
+    pub ruleset: String,
+    #[serde(flatten)]
+    pub message_response: ResponseKind,
+}
+
+/// What came out of an import, and what had to be left behind.
+#[derive(Debug, Default)]
+pub struct Import {
+    pub responses: Vec<ImportedResponse>,
+    /// Why each skipped trigger couldn't be brought over.
+    pub skipped: Vec<String>,
+}
+
+/// The prefix YAGPDB commands use unless a server changes it.
+const YAGPDB_PREFIX: &str = "-";
+
+/// Turns a trigger into a single line of the ruleset language.
+fn trigger_to_ruleset(trigger: &str, kind: TriggerKind, case_sensitive: bool) -> Option<String> {
+    let trigger = trigger.trim();
+
+    if trigger.is_empty() {
+        return None;
+    }
+
+    let escaped = regex::escape(trigger);
+    let pattern = match kind {
+        TriggerKind::Command => format!(r"^\s*{}{}(\s|$)", regex::escape(YAGPDB_PREFIX), escaped),
+        TriggerKind::StartsWith => format!(r"^\s*{}", escaped),
+        TriggerKind::EndsWith => format!(r"{}\s*$", escaped),
+        TriggerKind::Contains => escaped,
+        TriggerKind::Word => format!(r"\b{}\b", escaped),
+        TriggerKind::Exact => format!(r"^\s*{}\s*$", escaped),
+        // Both bots use Go or Python regexes, most of which mean the same thing here
+        TriggerKind::Regex => trigger.to_owned(),
+    };
+
+    let ruleset = if case_sensitive {
+        format!("r {}", pattern)
+    } else {
+        format!("r (?i){}", pattern)
+    };
+
+    Ruleset::parse(&ruleset).map(|_| ruleset)
+}
+
+lazy_static! {
+    /// Carl-bot's `{user}`, `{args}`, `{choose:a|b}` and the like.
+    static ref CARLBOT_VARIABLE: Regex = Regex::new(r"\{[a-z_.]+(:[^}]*)?\}").unwrap();
+}
+
+/// Both bots fill in placeholders, which would be sent as-is.
+fn uses_templates(response: &str) -> bool {
+    response.contains("{{") || CARLBOT_VARIABLE.is_match(response)
+}
+
+fn message_response(mut responses: Vec<String>) -> Option<ResponseKind> {
+    responses.retain(|response| !response.trim().is_empty());
+
+    match responses.len() {
+        0 => None,
+        1 => Some(ResponseKind::Text {
+            content: responses.remove(0),
+        }),
+        _ => Some(ResponseKind::RandomText { content: responses }),
+    }
+}
+
+impl Import {
+    fn add(
+        &mut self,
+        trigger: &str,
+        kind: Option<TriggerKind>,
+        case_sensitive: bool,
+        responses: Vec<String>,
+    ) {
+        let Some(kind) = kind else {
+            self.skipped
+                .push(format!("`{}`: its trigger type has no equivalent", trigger));
+            return;
+        };
+
+        let Some(ruleset) = trigger_to_ruleset(trigger, kind, case_sensitive) else {
+            self.skipped
+                .push(format!("`{}`: the trigger isn't a usable regex", trigger));
+            return;
+        };
+
+        if responses.iter().any(|response| uses_templates(response)) {
+            self.skipped
+                .push(format!("`{}`: the response uses templates", trigger));
+            return;
+        }
+
+        let Some(message_response) = message_response(responses) else {
+            self.skipped
+                .push(format!("`{}`: there's no response", trigger));
+            return;
+        };
+
+        self.responses.push(ImportedResponse {
+            name: trigger.trim().to_owned(),
+            ruleset,
+            message_response,
+        });
+    }
+
+    /// Converts an export from the given bot.
+    pub fn parse(format: ImportFormat, json: &str) -> Result<Import> {
+        let mut import = Import::default();
+
+        match format {
+            ImportFormat::Yagpdb => {
+                let commands: Vec<YagpdbCommand> =
+                    serde_json::from_str(json).wrap_err("Not a list of YAGPDB custom commands")?;
+
+                for command in commands {
+                    let kind = command.trigger_kind();
+                    import.add(
+                        &command.trigger,
+                        kind,
+                        command.case_sensitive,
+                        command.responses,
+                    );
+                }
+            }
+            ImportFormat::Carlbot => {
+                let autoresponders: Vec<CarlbotAutoresponder> =
+                    serde_json::from_str(json).wrap_err("Not a list of Carl-bot autoresponders")?;
+
+                for autoresponder in autoresponders {
+                    let kind = autoresponder.trigger_kind();
+                    // Carl-bot ignores case
+                    import.add(
+                        &autoresponder.trigger,
+                        kind,
+                        false,
+                        vec![autoresponder.response],
+                    );
+                }
+            }
+        }
+
+        Ok(import)
+    }
+
+    /// The responses as `[[responses]]` tables, ready to paste into a config.
+    pub fn to_toml(&self) -> Result<String> {
+        #[derive(Serialize)]
+        struct Responses<'a> {
+            responses: &'a [ImportedResponse],
+        }
+
+        Ok(toml::to_string(&Responses {
+            responses: &self.responses,
+        })?)
+    }
+}
+
+/// Reads an export and returns the converted responses, warning about anything skipped.
+pub fn run(format: ImportFormat, path: &Path) -> Result<String> {
+    let json = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("Could not read {}", path.display()))?;
+    let import = Import::parse(format, &json)?;
+
+    for skipped in &import.skipped {
+        eprintln!("Skipped {}", skipped);
+    }
+
+    if import.responses.is_empty() {
+        bail!("Nothing in {} could be imported", path.display());
+    }
+
+    eprintln!("Imported {} responses", import.responses.len());
+
+    import.to_toml()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::RegisteredResponse;
+
+    #[test]
+    fn converts_trigger_types() {
+        let ruleset = |trigger, kind| {
+            Ruleset::parse(&trigger_to_ruleset(trigger, kind, false).unwrap()).unwrap()
+        };
+
+        assert!(ruleset("-help", TriggerKind::Command).matches("--help me"));
+        assert!(ruleset("help", TriggerKind::Command).matches("-help"));
+        assert!(!ruleset("help", TriggerKind::Command).matches("-helpful"));
+        assert!(ruleset("hi", TriggerKind::StartsWith).matches("Hi there"));
+        assert!(!ruleset("hi", TriggerKind::StartsWith).matches("oh hi"));
+        assert!(ruleset("c++", TriggerKind::Contains).matches("I love C++!"));
+        assert!(!ruleset("cat", TriggerKind::Word).matches("concatenate"));
+        assert!(ruleset("cat", TriggerKind::Word).matches("my cat"));
+        assert!(ruleset("bye", TriggerKind::EndsWith).matches("ok bye "));
+        assert!(!ruleset("gg", TriggerKind::Exact).matches("gg wp"));
+        assert!(ruleset(r"\d{4}", TriggerKind::Regex).matches("cs 3500"));
+        assert_eq!(trigger_to_ruleset("(", TriggerKind::Regex, false), None);
+    }
+
+    #[test]
+    fn imports_yagpdb_commands() {
+        let import = Import::parse(
+            ImportFormat::Yagpdb,
+            r#"[
+                {"trigger_type": 2, "trigger": "segfault", "responses": ["core dumped"]},
+                {"trigger_type": "exact", "trigger": "ping", "case_sensitive": true, "responses": ["pong", "pang"]},
+                {"trigger_type": 5, "trigger": "", "responses": ["a reaction trigger"]},
+                {"trigger_type": 0, "trigger": "hello", "responses": ["Hi {{.User.Mention}}"]}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(import.responses.len(), 2);
+        assert_eq!(import.skipped.len(), 2);
+        assert_eq!(import.responses[1].ruleset, r"r ^\s*ping\s*$");
+        assert_eq!(
+            import.responses[1].message_response,
+            ResponseKind::RandomText {
+                content: vec!["pong".to_owned(), "pang".to_owned()]
+            }
+        );
+    }
+
+    #[test]
+    fn imports_carlbot_autoresponders_into_valid_config() {
+        let import = Import::parse(
+            ImportFormat::Carlbot,
+            r#"[
+                {"trigger": "rust", "response": "🦀", "match": "word"},
+                {"trigger": "hi", "response": "hey {user}"},
+                {"trigger": "lol", "response": "lmao", "match": "sounds like"}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(import.responses.len(), 1);
+        assert_eq!(import.skipped.len(), 2);
+
+        #[derive(Deserialize)]
+        struct Responses {
+            responses: Vec<RegisteredResponse>,
+        }
+
+        let parsed: Responses = toml::from_str(&import.to_toml().unwrap()).unwrap();
+        assert_eq!(parsed.responses[0].name(), "rust");
+        assert!(parsed.responses[0].matches("Rust is great"));
+        assert!(!parsed.responses[0].matches("trust me"));
+    }
+}
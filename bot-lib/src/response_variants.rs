@@ -0,0 +1,221 @@
+use crate::{data::AppState, db::KingFisherDb};
+use chrono::{Duration, Utc};
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, MessageId};
+use serde::{Deserialize, Serialize};
+
+const VARIANT_SENDS_TREE: &str = "response_variant_sends";
+/// How long after being sent reactions and replies still count for a variant.
+const ENGAGEMENT_WINDOW: Duration = match Duration::try_minutes(10) {
+    Some(window) => window,
+    None => panic!("Failed to create engagement window"),
+};
+
+/// A variant of a response that was sent, with how much attention it got.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct VariantSend {
+    response: String,
+    variant: usize,
+    sent_at: i64,
+    reactions: u64,
+    replies: u64,
+}
+
+impl VariantSend {
+    fn in_window(&self, now: i64) -> bool {
+        now - self.sent_at <= ENGAGEMENT_WINDOW.num_seconds()
+    }
+}
+
+/// How one variant of a response has done so far.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VariantResult {
+    pub variant: usize,
+    pub sends: u64,
+    pub reactions: u64,
+    pub replies: u64,
+}
+
+impl VariantResult {
+    /// Reactions and replies per send.
+    pub fn engagement(&self) -> f64 {
+        if self.sends == 0 {
+            return 0.0;
+        }
+
+        (self.reactions + self.replies) as f64 / self.sends as f64
+    }
+}
+
+/// Starts tracking a sent variant of a response.
+pub fn record_variant_send(
+    db: &KingFisherDb,
+    response: &str,
+    variant: usize,
+    message: &serenity::Message,
+) -> Result<()> {
+    let send = VariantSend {
+        response: response.to_owned(),
+        variant,
+        sent_at: message.timestamp.unix_timestamp(),
+        reactions: 0,
+        replies: 0,
+    };
+
+    db.insert(VARIANT_SENDS_TREE, message.id.get().to_be_bytes(), &send)
+}
+
+/// Counts the message as a reply to a variant, if it is one.
+pub fn track_variant_reply(data: &AppState, message: &serenity::Message) -> Result<()> {
+    let Some(replied_to) = message
+        .message_reference
+        .as_ref()
+        .and_then(|reference| reference.message_id)
+    else {
+        return Ok(());
+    };
+
+    update_send(data, replied_to, |send| send.replies += 1)
+}
+
+/// Updates the reaction count of a variant.
+pub fn track_variant_reactions(data: &AppState, message: &serenity::Message) -> Result<()> {
+    let reactions = message
+        .reactions
+        .iter()
+        .map(|reaction| reaction.count)
+        .sum();
+
+    update_send(data, message.id, |send| send.reactions = reactions)
+}
+
+fn update_send(
+    data: &AppState,
+    message_id: MessageId,
+    update: impl FnOnce(&mut VariantSend),
+) -> Result<()> {
+    let key = message_id.get().to_be_bytes();
+
+    let Some(mut send) = data.db.get::<VariantSend>(VARIANT_SENDS_TREE, key)? else {
+        return Ok(());
+    };
+
+    if !send.in_window(Utc::now().timestamp()) {
+        return Ok(());
+    }
+
+    update(&mut send);
+
+    data.db.insert(VARIANT_SENDS_TREE, key, &send)
+}
+
+/// How each variant of the response has done, by variant.
+pub fn variant_results(
+    db: &KingFisherDb,
+    response: &str,
+    variant_count: usize,
+) -> Result<Vec<VariantResult>> {
+    Ok(tally(
+        db.values::<VariantSend>(VARIANT_SENDS_TREE)?,
+        response,
+        variant_count,
+    ))
+}
+
+/// Variants that were never sent still get an empty result.
+fn tally(sends: Vec<VariantSend>, response: &str, variant_count: usize) -> Vec<VariantResult> {
+    let mut results: Vec<VariantResult> = Vec::new();
+    results.resize_with(variant_count, Default::default);
+
+    for send in sends.into_iter().filter(|send| send.response == response) {
+        if results.len() <= send.variant {
+            results.resize_with(send.variant + 1, Default::default);
+        }
+
+        let result = &mut results[send.variant];
+        result.sends += 1;
+        result.reactions += send.reactions;
+        result.replies += send.replies;
+    }
+
+    for (variant, result) in results.iter_mut().enumerate() {
+        result.variant = variant;
+    }
+
+    results
+}
+
+/// The variant with the most reactions and replies per send, if any has been sent.
+pub fn winner(results: &[VariantResult]) -> Option<&VariantResult> {
+    results
+        .iter()
+        .filter(|result| result.sends > 0)
+        .max_by(|a, b| a.engagement().total_cmp(&b.engagement()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn send(response: &str, variant: usize, reactions: u64, replies: u64) -> VariantSend {
+        VariantSend {
+            response: response.to_owned(),
+            variant,
+            sent_at: 0,
+            reactions,
+            replies,
+        }
+    }
+
+    #[test]
+    fn tallies_each_variant_of_the_response() {
+        let results = tally(
+            vec![
+                send("hello", 0, 1, 0),
+                send("hello", 2, 3, 1),
+                send("hello", 2, 0, 0),
+                send("other", 1, 9, 9),
+            ],
+            "hello",
+            2,
+        );
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1].sends, 0);
+        assert_eq!(
+            results[2],
+            VariantResult {
+                variant: 2,
+                sends: 2,
+                reactions: 3,
+                replies: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn winner_has_the_most_engagement_per_send() {
+        let results = tally(
+            vec![
+                send("hello", 0, 3, 0),
+                send("hello", 0, 3, 0),
+                send("hello", 0, 0, 0),
+                send("hello", 1, 2, 1),
+            ],
+            "hello",
+            3,
+        );
+
+        assert_eq!(results[2].sends, 0);
+        assert_eq!(winner(&results).map(|result| result.variant), Some(1));
+        assert_eq!(winner(&[]), None);
+    }
+
+    #[test]
+    fn only_counts_engagement_within_the_window() {
+        let send = send("hello", 0, 0, 0);
+
+        assert!(send.in_window(ENGAGEMENT_WINDOW.num_seconds()));
+        assert!(!send.in_window(ENGAGEMENT_WINDOW.num_seconds() + 1));
+    }
+}
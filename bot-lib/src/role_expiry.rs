@@ -0,0 +1,239 @@
+use crate::{data::AppState, db::KingFisherDb};
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, GuildId, RoleId, UserId};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+const ROLE_EXPIRIES_TREE: &str = "role_expiries";
+
+/// A role that gets taken away again a while after someone gets it, like "New member".
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TemporaryRole {
+    pub role_id: u64,
+    /// How long members keep it, in seconds.
+    #[serde_as(as = "DurationSeconds<i64>")]
+    pub duration: Duration,
+}
+
+/// When a member's role gets removed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleExpiry {
+    pub guild_id: u64,
+    pub user_id: u64,
+    pub role_id: u64,
+    pub expires_at: DateTime<Utc>,
+    /// The role was made just for this member, so it gets deleted instead of taken away.
+    #[serde(default)]
+    pub delete_role: bool,
+}
+
+fn expiry_key(user_id: UserId, role_id: RoleId) -> Vec<u8> {
+    [user_id.get().to_be_bytes(), role_id.get().to_be_bytes()].concat()
+}
+
+/// Removes the role from the member at the given time, replacing any earlier expiry.
+pub fn set_role_expiry(
+    db: &KingFisherDb,
+    guild_id: GuildId,
+    user_id: UserId,
+    role_id: RoleId,
+    expires_at: DateTime<Utc>,
+) -> Result<()> {
+    db.insert(
+        ROLE_EXPIRIES_TREE,
+        expiry_key(user_id, role_id),
+        &RoleExpiry {
+            guild_id: guild_id.get(),
+            user_id: user_id.get(),
+            role_id: role_id.get(),
+            expires_at,
+            delete_role: false,
+        },
+    )
+}
+
+/// Deletes a role made just for the member at the given time, replacing any earlier expiry.
+pub fn set_personal_role_expiry(
+    db: &KingFisherDb,
+    guild_id: GuildId,
+    user_id: UserId,
+    role_id: RoleId,
+    expires_at: DateTime<Utc>,
+) -> Result<()> {
+    db.insert(
+        ROLE_EXPIRIES_TREE,
+        expiry_key(user_id, role_id),
+        &RoleExpiry {
+            guild_id: guild_id.get(),
+            user_id: user_id.get(),
+            role_id: role_id.get(),
+            expires_at,
+            delete_role: true,
+        },
+    )
+}
+
+fn get_role_expiry(
+    db: &KingFisherDb,
+    user_id: UserId,
+    role_id: RoleId,
+) -> Result<Option<RoleExpiry>> {
+    db.get(ROLE_EXPIRIES_TREE, expiry_key(user_id, role_id))
+}
+
+fn remove_role_expiry(db: &KingFisherDb, user_id: UserId, role_id: RoleId) -> Result<()> {
+    db.remove::<RoleExpiry>(ROLE_EXPIRIES_TREE, expiry_key(user_id, role_id))?;
+
+    Ok(())
+}
+
+/// Expiries that are due, soonest first.
+fn due_expiries(db: &KingFisherDb, now: DateTime<Utc>) -> Result<Vec<RoleExpiry>> {
+    let mut due = db
+        .values::<RoleExpiry>(ROLE_EXPIRIES_TREE)?
+        .into_iter()
+        .filter(|expiry| expiry.expires_at <= now)
+        .collect::<Vec<_>>();
+
+    due.sort_by_key(|expiry| expiry.expires_at);
+
+    Ok(due)
+}
+
+/// Starts the clock on temporary roles a member just got, however they got them,
+/// and forgets the ones they already lost.
+pub async fn track_temporary_roles(data: &AppState, member: &serenity::Member) -> Result<()> {
+    let temporary_roles = data.config.read().await.temporary_roles.clone();
+
+    for temporary in temporary_roles {
+        let role_id = RoleId::new(temporary.role_id);
+        let existing = get_role_expiry(&data.db, member.user.id, role_id)?;
+
+        match (member.roles.contains(&role_id), existing) {
+            (true, None) => set_role_expiry(
+                &data.db,
+                member.guild_id,
+                member.user.id,
+                role_id,
+                Utc::now() + temporary.duration,
+            )?,
+            (false, Some(_)) => remove_role_expiry(&data.db, member.user.id, role_id)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Takes away every role whose time is up.
+pub async fn remove_expired_roles(ctx: &serenity::Context, data: &AppState) -> Result<()> {
+    for expiry in due_expiries(&data.db, Utc::now())? {
+        let (user_id, role_id) = (UserId::new(expiry.user_id), RoleId::new(expiry.role_id));
+        let guild_id = GuildId::new(expiry.guild_id);
+
+        let removed = if expiry.delete_role {
+            ctx.http
+                .delete_role(guild_id, role_id, Some("Temporary role expired"))
+                .await
+        } else {
+            ctx.http
+                .remove_member_role(guild_id, user_id, role_id, Some("Temporary role expired"))
+                .await
+        };
+
+        if let Err(e) = removed {
+            // They probably left, or the role was deleted
+            tracing::debug!(
+                "Couldn't remove expired role {} from {}: {:?}",
+                role_id,
+                user_id,
+                e
+            );
+        }
+
+        remove_role_expiry(&data.db, user_id, role_id)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_due_expiries() {
+        let db = KingFisherDb::temporary().unwrap();
+        let guild_id = GuildId::new(1);
+        let now = Utc::now();
+
+        set_role_expiry(
+            &db,
+            guild_id,
+            UserId::new(1),
+            RoleId::new(10),
+            now + Duration::days(1),
+        )
+        .unwrap();
+        set_role_expiry(
+            &db,
+            guild_id,
+            UserId::new(2),
+            RoleId::new(10),
+            now - Duration::hours(1),
+        )
+        .unwrap();
+        set_role_expiry(
+            &db,
+            guild_id,
+            UserId::new(3),
+            RoleId::new(10),
+            now - Duration::days(1),
+        )
+        .unwrap();
+
+        let due = due_expiries(&db, now).unwrap();
+        assert_eq!(
+            due.iter().map(|expiry| expiry.user_id).collect::<Vec<_>>(),
+            vec![3, 2]
+        );
+
+        // Setting it again pushes it back instead of adding another
+        set_role_expiry(
+            &db,
+            guild_id,
+            UserId::new(2),
+            RoleId::new(10),
+            now + Duration::days(1),
+        )
+        .unwrap();
+        assert_eq!(due_expiries(&db, now).unwrap().len(), 1);
+
+        set_personal_role_expiry(
+            &db,
+            guild_id,
+            UserId::new(4),
+            RoleId::new(11),
+            now - Duration::days(2),
+        )
+        .unwrap();
+        let due = due_expiries(&db, now).unwrap();
+        assert!(due[0].delete_role);
+        assert!(!due[1].delete_role);
+    }
+
+    #[test]
+    fn reads_temporary_roles() {
+        let temporary: TemporaryRole = toml::from_str(
+            r#"
+role_id = 5
+duration = 604800
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(temporary.duration, Duration::weeks(1));
+    }
+}
@@ -0,0 +1,337 @@
+use crate::{data::AppState, db::KingFisherDb, mod_log::mod_log, role_expiry::set_role_expiry};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, GuildId, Mentionable, RoleId, UserId};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const ROLE_GRANTS_TREE: &str = "role_grants";
+/// Time between role changes, so a big server doesn't get us rate limited.
+const GRANT_INTERVAL: Duration = Duration::from_millis(1000);
+/// Role changes per run of the job, so each run finishes well before the next one starts.
+const MAX_CHANGES_PER_RUN: usize = 45;
+/// Members read from Discord at a time, the most it allows.
+const MEMBER_PAGE: u64 = 1000;
+/// Failed members named in the report, the rest are only counted.
+const MAX_FAILED_MENTIONS: usize = 20;
+
+/// Which members get the role. Every set condition has to match.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemberFilter {
+    pub has_role: Option<RoleId>,
+    pub joined_before: Option<DateTime<Utc>>,
+    pub no_roles: bool,
+}
+
+impl MemberFilter {
+    pub fn is_empty(&self) -> bool {
+        self == &MemberFilter::default()
+    }
+
+    fn matches(&self, roles: &[RoleId], joined_at: Option<DateTime<Utc>>) -> bool {
+        self.has_role.is_none_or(|role_id| roles.contains(&role_id))
+            && self
+                .joined_before
+                .is_none_or(|before| joined_at.is_some_and(|joined_at| joined_at < before))
+            && (!self.no_roles || roles.is_empty())
+    }
+}
+
+/// A `/grant_role` run, worked through a batch at a time by the scheduler so it survives restarts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleGrant {
+    pub id: u64,
+    pub guild_id: u64,
+    pub role_id: u64,
+    pub role_name: String,
+    pub filter: MemberFilter,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub requested_by: u64,
+    /// The progress message, edited after each batch.
+    pub channel_id: u64,
+    pub message_id: u64,
+    /// Whether it was queued in a dry run, so it only counts who would get the role.
+    pub dry_run: bool,
+    /// The last member looked at, where the next batch picks up.
+    pub after: Option<u64>,
+    pub checked: usize,
+    pub granted: Vec<u64>,
+    pub failed: Vec<u64>,
+    pub finished: bool,
+}
+
+impl RoleGrant {
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "{}{} {}: checked {} members, {} granted, {} failed.",
+            if self.dry_run {
+                "Dry run, nothing changed. "
+            } else {
+                ""
+            },
+            if self.finished { "Granted" } else { "Granting" },
+            self.role_name,
+            self.checked,
+            self.granted.len(),
+            self.failed.len()
+        );
+
+        if self.finished && !self.failed.is_empty() {
+            let failed = self
+                .failed
+                .iter()
+                .take(MAX_FAILED_MENTIONS)
+                .map(|user_id| UserId::new(*user_id).mention().to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            report.push_str(&format!("\nCouldn't give it to: {}", failed));
+
+            if self.failed.len() > MAX_FAILED_MENTIONS {
+                report.push_str(&format!(
+                    " and {} more",
+                    self.failed.len() - MAX_FAILED_MENTIONS
+                ));
+            }
+        }
+
+        report
+    }
+}
+
+pub fn save_role_grant(db: &KingFisherDb, grant: &RoleGrant) -> Result<()> {
+    db.insert(ROLE_GRANTS_TREE, grant.id.to_be_bytes(), grant)
+}
+
+/// The oldest grant that isn't done yet. Grants run one at a time, so they share the rate limit.
+fn next_grant(db: &KingFisherDb) -> Result<Option<RoleGrant>> {
+    Ok(db
+        .values::<RoleGrant>(ROLE_GRANTS_TREE)?
+        .into_iter()
+        .find(|grant| !grant.finished))
+}
+
+/// Looks at the next page of members, giving the role to matching ones until the batch is used up.
+async fn run_batch(ctx: &serenity::Context, data: &AppState, grant: &mut RoleGrant) -> Result<()> {
+    let guild_id = GuildId::new(grant.guild_id);
+    let role_id = RoleId::new(grant.role_id);
+    let members = guild_id
+        .members(ctx, Some(MEMBER_PAGE), grant.after.map(UserId::new))
+        .await?;
+    let mut changes = 0;
+
+    for member in &members {
+        if changes == MAX_CHANGES_PER_RUN {
+            return Ok(());
+        }
+
+        grant.after = Some(member.user.id.get());
+
+        if member.user.bot || member.roles.contains(&role_id) {
+            continue;
+        }
+
+        grant.checked += 1;
+
+        if !grant
+            .filter
+            .matches(&member.roles, member.joined_at.map(|joined_at| *joined_at))
+        {
+            continue;
+        }
+
+        if grant.dry_run {
+            grant.granted.push(member.user.id.get());
+            continue;
+        }
+
+        match member.add_role(ctx, role_id).await {
+            Ok(_) => {
+                if let Some(expires_at) = grant.expires_at {
+                    set_role_expiry(&data.db, guild_id, member.user.id, role_id, expires_at)?;
+                }
+
+                grant.granted.push(member.user.id.get());
+            }
+            Err(e) => {
+                tracing::debug!(
+                    "Couldn't give {} to {}: {:?}",
+                    grant.role_name,
+                    member.user.id,
+                    e
+                );
+                grant.failed.push(member.user.id.get());
+            }
+        }
+
+        save_role_grant(&data.db, grant)?;
+        changes += 1;
+        tokio::time::sleep(GRANT_INTERVAL).await;
+    }
+
+    grant.finished = (members.len() as u64) < MEMBER_PAGE;
+
+    Ok(())
+}
+
+/// Works through the next batch of the oldest queued `/grant_role`, reporting once it's done.
+pub async fn run_role_grants(ctx: &serenity::Context, data: &AppState) -> Result<()> {
+    let Some(mut grant) = next_grant(&data.db)? else {
+        return Ok(());
+    };
+
+    let batch = run_batch(ctx, data, &mut grant).await;
+    save_role_grant(&data.db, &grant)?;
+    batch?;
+
+    if let Err(e) = serenity::ChannelId::new(grant.channel_id)
+        .edit_message(
+            ctx,
+            grant.message_id,
+            serenity::EditMessage::new().content(grant.report()),
+        )
+        .await
+    {
+        tracing::debug!("Couldn't update role grant {}: {:?}", grant.id, e);
+    }
+
+    if !grant.finished {
+        return Ok(());
+    }
+
+    mod_log(
+        ctx,
+        data,
+        serenity::CreateEmbed::new()
+            .title(format!("Mass role grant: {}", grant.role_name))
+            .description(format!(
+                "{} gave out {}{}",
+                UserId::new(grant.requested_by).mention(),
+                RoleId::new(grant.role_id).mention(),
+                if grant.dry_run { " (dry run)" } else { "" }
+            ))
+            .field("Granted", grant.granted.len().to_string(), true)
+            .field("Failed", grant.failed.len().to_string(), true)
+            .field(
+                "Expires",
+                grant.expires_at.map_or("Never".to_owned(), |expires_at| {
+                    format!("<t:{}:R>", expires_at.timestamp())
+                }),
+                true,
+            ),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn grant(id: u64) -> RoleGrant {
+        RoleGrant {
+            id,
+            guild_id: 1,
+            role_id: 2,
+            role_name: "TA".to_owned(),
+            filter: MemberFilter {
+                no_roles: true,
+                ..Default::default()
+            },
+            expires_at: None,
+            requested_by: 3,
+            channel_id: 4,
+            message_id: 5,
+            dry_run: false,
+            after: None,
+            checked: 0,
+            granted: vec![],
+            failed: vec![],
+            finished: false,
+        }
+    }
+
+    #[test]
+    fn filters_members() {
+        let class_role = RoleId::new(1);
+        let cutoff = Utc::now();
+        let earlier = Some(cutoff - chrono::Duration::days(1));
+        let later = Some(cutoff + chrono::Duration::days(1));
+
+        let in_class = MemberFilter {
+            has_role: Some(class_role),
+            ..Default::default()
+        };
+        assert!(in_class.matches(&[class_role], None));
+        assert!(!in_class.matches(&[RoleId::new(2)], None));
+
+        let old_members = MemberFilter {
+            joined_before: Some(cutoff),
+            ..Default::default()
+        };
+        assert!(old_members.matches(&[], earlier));
+        assert!(!old_members.matches(&[], later));
+        assert!(!old_members.matches(&[], None));
+
+        let roleless = MemberFilter {
+            no_roles: true,
+            joined_before: Some(cutoff),
+            ..Default::default()
+        };
+        assert!(roleless.matches(&[], earlier));
+        assert!(!roleless.matches(&[class_role], earlier));
+
+        assert!(MemberFilter::default().is_empty());
+        assert!(!roleless.is_empty());
+    }
+
+    #[test]
+    fn picks_up_the_oldest_unfinished_grant() {
+        let db = KingFisherDb::temporary().unwrap();
+
+        save_role_grant(
+            &db,
+            &RoleGrant {
+                finished: true,
+                ..grant(1)
+            },
+        )
+        .unwrap();
+        save_role_grant(
+            &db,
+            &RoleGrant {
+                after: Some(99),
+                checked: 10,
+                ..grant(2)
+            },
+        )
+        .unwrap();
+        save_role_grant(&db, &grant(3)).unwrap();
+
+        let next = next_grant(&db).unwrap().unwrap();
+        assert_eq!(next.id, 2);
+        assert_eq!(next.after, Some(99));
+    }
+
+    #[test]
+    fn reports_progress() {
+        let running = RoleGrant {
+            checked: 3,
+            granted: vec![6],
+            ..grant(1)
+        };
+        assert_eq!(
+            running.report(),
+            "Granting TA: checked 3 members, 1 granted, 0 failed."
+        );
+
+        let finished = RoleGrant {
+            failed: (1..=30).collect(),
+            dry_run: true,
+            finished: true,
+            ..running
+        };
+        let report = finished.report();
+        assert!(report.starts_with("Dry run, nothing changed. Granted TA"));
+        assert!(report.ends_with("<@20> and 10 more"));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{data::AppState, db::KingFisherDb};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, ChannelId};
+use serde::{Deserialize, Serialize};
+
+const SCHEDULED_MESSAGES_TREE: &str = "scheduled_messages";
+
+/// A message waiting to be posted by the scheduler.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledMessage {
+    pub id: u64,
+    pub channel_id: u64,
+    pub author_id: u64,
+    pub content: String,
+    pub send_at: DateTime<Utc>,
+    /// Whether a mod let it ping the roles and people it mentions.
+    #[serde(default)]
+    pub allow_pings: bool,
+}
+
+impl ScheduledMessage {
+    /// A one line version for listing, like `#3 in #general <t:..:R>: Club meeting...`.
+    pub fn summary(&self) -> String {
+        let preview = self.content.chars().take(60).collect::<String>();
+
+        format!(
+            "**#{}** <#{}> <t:{}:R>: {}{}",
+            self.id,
+            self.channel_id,
+            self.send_at.timestamp(),
+            preview.replace('\n', " "),
+            if preview.len() < self.content.len() {
+                "..."
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+pub fn save_scheduled_message(db: &KingFisherDb, message: &ScheduledMessage) -> Result<()> {
+    db.insert(SCHEDULED_MESSAGES_TREE, message.id.to_be_bytes(), message)
+}
+
+pub fn get_scheduled_message(db: &KingFisherDb, id: u64) -> Result<Option<ScheduledMessage>> {
+    db.get(SCHEDULED_MESSAGES_TREE, id.to_be_bytes())
+}
+
+pub fn remove_scheduled_message(db: &KingFisherDb, id: u64) -> Result<Option<ScheduledMessage>> {
+    db.remove(SCHEDULED_MESSAGES_TREE, id.to_be_bytes())
+}
+
+/// Every message still waiting, soonest first.
+pub fn get_scheduled_messages(db: &KingFisherDb) -> Result<Vec<ScheduledMessage>> {
+    let mut messages = db.values::<ScheduledMessage>(SCHEDULED_MESSAGES_TREE)?;
+
+    messages.sort_by_key(|message| message.send_at);
+
+    Ok(messages)
+}
+
+/// Posts every message whose time has come.
+pub async fn send_scheduled_messages(ctx: &serenity::Context, data: &AppState) -> Result<()> {
+    let now = Utc::now();
+
+    for message in get_scheduled_messages(&data.db)? {
+        if message.send_at > now {
+            break;
+        }
+
+        // Removed first, so a message that fails to send doesn't get retried forever
+        remove_scheduled_message(&data.db, message.id)?;
+
+        let allowed_mentions = if message.allow_pings {
+            serenity::CreateAllowedMentions::new()
+                .all_roles(true)
+                .all_users(true)
+        } else {
+            serenity::CreateAllowedMentions::new()
+        };
+
+        if let Err(e) = ChannelId::new(message.channel_id)
+            .send_message(
+                ctx,
+                serenity::CreateMessage::new()
+                    .content(&message.content)
+                    .allowed_mentions(allowed_mentions),
+            )
+            .await
+        {
+            tracing::warn!("Couldn't send scheduled message #{}: {:?}", message.id, e);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::Duration;
+
+    fn scheduled(id: u64, send_at: DateTime<Utc>, content: &str) -> ScheduledMessage {
+        ScheduledMessage {
+            id,
+            channel_id: 1,
+            author_id: 2,
+            content: content.to_owned(),
+            send_at,
+            allow_pings: false,
+        }
+    }
+
+    #[test]
+    fn orders_scheduled_messages() {
+        let db = KingFisherDb::temporary().unwrap();
+        let now = Utc::now();
+
+        save_scheduled_message(&db, &scheduled(1, now + Duration::days(2), "later")).unwrap();
+        save_scheduled_message(&db, &scheduled(2, now + Duration::hours(1), "sooner")).unwrap();
+
+        assert_eq!(
+            get_scheduled_messages(&db)
+                .unwrap()
+                .iter()
+                .map(|message| message.id)
+                .collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+
+        assert!(remove_scheduled_message(&db, 2).unwrap().is_some());
+        assert_eq!(get_scheduled_message(&db, 2).unwrap(), None);
+    }
+
+    #[test]
+    fn summarizes_long_messages() {
+        let send_at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        assert_eq!(
+            scheduled(3, send_at, "Club meeting\ntonight").summary(),
+            "**#3** <#1> <t:1700000000:R>: Club meeting tonight"
+        );
+        assert!(scheduled(3, send_at, &"a".repeat(100))
+            .summary()
+            .ends_with("..."));
+    }
+}
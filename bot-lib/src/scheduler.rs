@@ -0,0 +1,304 @@
+use crate::auto_slowmode::tune_slowmode;
+use crate::class_digest::post_class_digests;
+use crate::commands::lynch::refill_lynch_opportunities;
+use crate::commands::tempcheck::close_tempchecks;
+use crate::data::{AppState, Data};
+use crate::job_board::archive_expired_postings;
+use crate::name_policy::enforce_name_policy_everywhere;
+use crate::outbound::retry_outbound;
+use crate::rename_votes::close_rename_votes;
+use crate::role_expiry::remove_expired_roles;
+use crate::role_grants::run_role_grants;
+use crate::scheduled_messages::send_scheduled_messages;
+use crate::server_themes::update_server_theme;
+use crate::starboard_export::export_starboard;
+use crate::starboard_rewind::post_semester_rewind;
+use crate::voice_activity::{post_study_shout_out, refresh_voice_sessions};
+use chrono::Utc;
+use color_eyre::eyre::{OptionExt, Result};
+use futures::future::BoxFuture;
+use poise::serenity_prelude as serenity;
+use std::{sync::Arc, time::Duration};
+
+/// A background task that runs on a fixed interval.
+pub struct Job {
+    pub name: &'static str,
+    pub interval: Duration,
+    /// Whether the schedule carries over restarts. Jobs that refill in-memory state should run
+    /// right away instead, since that state starts empty.
+    pub remembers_last_run: bool,
+    pub run: for<'a> fn(&'a serenity::Context, &'a AppState) -> BoxFuture<'a, Result<()>>,
+}
+
+fn lynch_refill<'a>(_ctx: &'a serenity::Context, _data: &'a AppState) -> BoxFuture<'a, Result<()>> {
+    Box::pin(async {
+        refill_lynch_opportunities().await;
+        Ok(())
+    })
+}
+
+fn class_digest<'a>(ctx: &'a serenity::Context, data: &'a AppState) -> BoxFuture<'a, Result<()>> {
+    Box::pin(post_class_digests(ctx, data))
+}
+
+fn name_policy<'a>(ctx: &'a serenity::Context, data: &'a AppState) -> BoxFuture<'a, Result<()>> {
+    Box::pin(async {
+        let guild_id = data.config.read().await.guild_id;
+
+        enforce_name_policy_everywhere(ctx, data, guild_id.into(), false).await?;
+
+        Ok(())
+    })
+}
+
+fn study_shout_out<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a AppState,
+) -> BoxFuture<'a, Result<()>> {
+    Box::pin(post_study_shout_out(ctx, data))
+}
+
+fn semester_rewind<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a AppState,
+) -> BoxFuture<'a, Result<()>> {
+    Box::pin(post_semester_rewind(ctx, data))
+}
+
+fn job_board<'a>(ctx: &'a serenity::Context, data: &'a AppState) -> BoxFuture<'a, Result<()>> {
+    Box::pin(archive_expired_postings(ctx, data))
+}
+
+fn role_expiry<'a>(ctx: &'a serenity::Context, data: &'a AppState) -> BoxFuture<'a, Result<()>> {
+    Box::pin(remove_expired_roles(ctx, data))
+}
+
+fn scheduled_messages<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a AppState,
+) -> BoxFuture<'a, Result<()>> {
+    Box::pin(send_scheduled_messages(ctx, data))
+}
+
+fn auto_slowmode<'a>(ctx: &'a serenity::Context, data: &'a AppState) -> BoxFuture<'a, Result<()>> {
+    Box::pin(tune_slowmode(ctx, data))
+}
+
+fn role_grants<'a>(ctx: &'a serenity::Context, data: &'a AppState) -> BoxFuture<'a, Result<()>> {
+    Box::pin(run_role_grants(ctx, data))
+}
+
+fn voice_sessions<'a>(ctx: &'a serenity::Context, data: &'a AppState) -> BoxFuture<'a, Result<()>> {
+    Box::pin(refresh_voice_sessions(ctx, data))
+}
+
+fn outbound<'a>(_ctx: &'a serenity::Context, data: &'a AppState) -> BoxFuture<'a, Result<()>> {
+    Box::pin(retry_outbound(data))
+}
+
+fn rename_votes<'a>(ctx: &'a serenity::Context, data: &'a AppState) -> BoxFuture<'a, Result<()>> {
+    Box::pin(close_rename_votes(ctx, data))
+}
+
+fn tempchecks<'a>(ctx: &'a serenity::Context, data: &'a AppState) -> BoxFuture<'a, Result<()>> {
+    Box::pin(close_tempchecks(ctx, data))
+}
+
+fn starboard_export<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a AppState,
+) -> BoxFuture<'a, Result<()>> {
+    Box::pin(export_starboard(ctx, data))
+}
+
+fn server_theme<'a>(ctx: &'a serenity::Context, data: &'a AppState) -> BoxFuture<'a, Result<()>> {
+    Box::pin(update_server_theme(ctx, data))
+}
+
+pub const JOBS: &[Job] = &[
+    Job {
+        name: "lynch_refill",
+        interval: Duration::from_secs(3600),
+        remembers_last_run: false,
+        run: lynch_refill,
+    },
+    Job {
+        name: "class_digest",
+        interval: Duration::from_secs(7 * 24 * 3600),
+        remembers_last_run: true,
+        run: class_digest,
+    },
+    Job {
+        name: "name_policy",
+        interval: Duration::from_secs(24 * 3600),
+        remembers_last_run: true,
+        run: name_policy,
+    },
+    // Only posts on the first of the month
+    Job {
+        name: "study_shout_out",
+        interval: Duration::from_secs(24 * 3600),
+        remembers_last_run: true,
+        run: study_shout_out,
+    },
+    // Only posts on the last day of a semester
+    Job {
+        name: "semester_rewind",
+        interval: Duration::from_secs(24 * 3600),
+        remembers_last_run: true,
+        run: semester_rewind,
+    },
+    Job {
+        name: "job_board",
+        interval: Duration::from_secs(3600),
+        remembers_last_run: true,
+        run: job_board,
+    },
+    Job {
+        name: "role_expiry",
+        interval: Duration::from_secs(600),
+        remembers_last_run: true,
+        run: role_expiry,
+    },
+    Job {
+        name: "scheduled_messages",
+        interval: Duration::from_secs(60),
+        remembers_last_run: true,
+        run: scheduled_messages,
+    },
+    Job {
+        name: "auto_slowmode",
+        interval: Duration::from_secs(60),
+        remembers_last_run: true,
+        run: auto_slowmode,
+    },
+    Job {
+        name: "role_grants",
+        interval: Duration::from_secs(60),
+        remembers_last_run: true,
+        run: role_grants,
+    },
+    Job {
+        name: "voice_sessions",
+        interval: Duration::from_secs(60),
+        remembers_last_run: true,
+        run: voice_sessions,
+    },
+    Job {
+        name: "outbound",
+        interval: Duration::from_secs(60),
+        remembers_last_run: true,
+        run: outbound,
+    },
+    Job {
+        name: "rename_votes",
+        interval: Duration::from_secs(60),
+        remembers_last_run: true,
+        run: rename_votes,
+    },
+    Job {
+        name: "tempchecks",
+        interval: Duration::from_secs(60),
+        remembers_last_run: true,
+        run: tempchecks,
+    },
+    Job {
+        name: "starboard_export",
+        interval: Duration::from_secs(24 * 3600),
+        remembers_last_run: true,
+        run: starboard_export,
+    },
+    Job {
+        name: "server_theme",
+        interval: Duration::from_secs(3600),
+        remembers_last_run: true,
+        run: server_theme,
+    },
+];
+
+const SCHEDULER_TREE: &str = "scheduler_last_run";
+
+/// How long until the job should next run, based on when it last ran (even before a restart).
+fn time_until_next_run(job: &Job, last_run: Option<i64>, now: i64) -> Duration {
+    if !job.remembers_last_run {
+        return Duration::ZERO;
+    }
+
+    let Some(last_run) = last_run else {
+        return job.interval;
+    };
+
+    let elapsed = Duration::from_secs(now.saturating_sub(last_run).max(0) as u64);
+
+    job.interval.saturating_sub(elapsed)
+}
+
+/// Spawns every job in [`JOBS`] onto its own interval.
+pub fn start(ctx: serenity::Context, data: Data) {
+    for job in JOBS {
+        let ctx = ctx.clone();
+        let data = Arc::clone(&data);
+
+        tokio::spawn(async move {
+            let last_run = data.db.get::<i64>(SCHEDULER_TREE, job.name).ok().flatten();
+            let first_run = time_until_next_run(job, last_run, Utc::now().timestamp());
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + first_run, job.interval);
+
+            loop {
+                interval.tick().await;
+
+                if let Err(e) = (job.run)(&ctx, &data).await {
+                    tracing::error!("Job {} failed: {:?}", job.name, e);
+                    data.record_error(format!("job {}: {:?}", job.name, e));
+                }
+
+                if let Err(e) = data
+                    .db
+                    .insert(SCHEDULER_TREE, job.name, &Utc::now().timestamp())
+                {
+                    tracing::warn!("Couldn't record run of job {}: {:?}", job.name, e);
+                }
+            }
+        });
+    }
+}
+
+/// Runs a job right now, outside of its usual interval.
+pub async fn run_job(name: &str, ctx: &serenity::Context, data: &AppState) -> Result<()> {
+    let job = JOBS
+        .iter()
+        .find(|job| job.name == name)
+        .ok_or_eyre("No job with that name")?;
+
+    (job.run)(ctx, data).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn next_run_accounts_for_last_run() {
+        let job = JOBS.iter().find(|job| job.name == "class_digest").unwrap();
+        let interval = job.interval.as_secs() as i64;
+
+        assert_eq!(time_until_next_run(job, None, 1000), job.interval);
+        assert_eq!(
+            time_until_next_run(job, Some(1000), 1000 + interval - 10),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            time_until_next_run(job, Some(1000), 1000 + interval * 2),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn lynch_refill_runs_at_startup() {
+        let job = JOBS.iter().find(|job| job.name == "lynch_refill").unwrap();
+
+        assert_eq!(time_until_next_run(job, None, 1000), Duration::ZERO);
+        assert_eq!(time_until_next_run(job, Some(990), 1000), Duration::ZERO);
+    }
+}
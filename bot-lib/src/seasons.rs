@@ -0,0 +1,211 @@
+use crate::config::RegisteredResponse;
+use chrono::{Datelike, NaiveDate};
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::{fmt, str::FromStr};
+
+/// A day of the year, written `"MM-DD"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SeasonDate {
+    month: u32,
+    day: u32,
+}
+
+impl SeasonDate {
+    fn of(date: NaiveDate) -> Self {
+        SeasonDate {
+            month: date.month(),
+            day: date.day(),
+        }
+    }
+
+    /// This day in the given year, if it has one (Feb 29).
+    fn in_year(self, year: i32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, self.month, self.day)
+    }
+}
+
+impl FromStr for SeasonDate {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (month, day) = s
+            .split_once('-')
+            .ok_or_else(|| eyre!("Expected a date like \"04-01\", got \"{}\"", s))?;
+
+        let date = SeasonDate {
+            month: month.parse()?,
+            day: day.parse()?,
+        };
+
+        // 2000 is a leap year, so Feb 29 counts as a real day
+        date.in_year(2000)
+            .ok_or_else(|| eyre!("\"{}\" isn't a day of the year", s))?;
+
+        Ok(date)
+    }
+}
+
+impl fmt::Display for SeasonDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}-{:02}", self.month, self.day)
+    }
+}
+
+/// A yearly date range (April Fools, Halloween, finals week), both ends included.
+#[serde_as]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Season {
+    #[serde_as(as = "DisplayFromStr")]
+    pub start: SeasonDate,
+    /// May be before `start` for seasons going past new year.
+    #[serde_as(as = "DisplayFromStr")]
+    pub end: SeasonDate,
+}
+
+impl Season {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        let date = SeasonDate::of(date);
+
+        if self.start <= self.end {
+            self.start <= date && date <= self.end
+        } else {
+            self.start <= date || date <= self.end
+        }
+    }
+
+    /// The first day on or after `date` that the season starts.
+    pub fn next_start(&self, date: NaiveDate) -> Option<NaiveDate> {
+        // Feb 29 starts can be up to 8 years apart
+        (date.year()..=date.year() + 8)
+            .filter_map(|year| self.start.in_year(year))
+            .find(|start| *start >= date)
+    }
+}
+
+impl fmt::Display for Season {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} to {}", self.start, self.end)
+    }
+}
+
+/// A file of responses that are only active during a season.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ResponsePack {
+    pub name: String,
+    pub season: Season,
+    /// A toml file with a `[[responses]]` table per response.
+    pub path: String,
+    #[serde(skip)]
+    pub responses: Vec<RegisteredResponse>,
+}
+
+#[derive(Deserialize)]
+struct ResponsePackFile {
+    responses: Vec<RegisteredResponse>,
+}
+
+impl ResponsePack {
+    pub fn load(&mut self) -> Result<()> {
+        let file = std::fs::read_to_string(&self.path)
+            .wrap_err_with(|| format!("Could not read response pack `{}`", self.name))?;
+
+        let pack: ResponsePackFile = toml::from_str(&file)
+            .wrap_err_with(|| format!("Could not parse response pack `{}`", self.name))?;
+
+        self.responses = pack.responses;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn season(start: &str, end: &str) -> Season {
+        Season {
+            start: start.parse().unwrap(),
+            end: end.parse().unwrap(),
+        }
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn parses_season_dates() {
+        assert_eq!(
+            "04-01".parse::<SeasonDate>().unwrap(),
+            SeasonDate { month: 4, day: 1 }
+        );
+        assert!("02-29".parse::<SeasonDate>().is_ok());
+        assert!("02-30".parse::<SeasonDate>().is_err());
+        assert!("13-01".parse::<SeasonDate>().is_err());
+        assert!("0401".parse::<SeasonDate>().is_err());
+        assert_eq!(SeasonDate { month: 4, day: 1 }.to_string(), "04-01");
+    }
+
+    #[test]
+    fn checks_seasons() {
+        let april_fools = season("04-01", "04-01");
+        let winter_break = season("12-15", "01-05");
+
+        assert!(april_fools.contains(date(2024, 4, 1)));
+        assert!(!april_fools.contains(date(2024, 4, 2)));
+        assert!(winter_break.contains(date(2024, 12, 31)));
+        assert!(winter_break.contains(date(2025, 1, 5)));
+        assert!(!winter_break.contains(date(2025, 1, 6)));
+    }
+
+    #[test]
+    fn finds_next_start() {
+        assert_eq!(
+            season("04-01", "04-01").next_start(date(2024, 4, 2)),
+            Some(date(2025, 4, 1))
+        );
+        assert_eq!(
+            season("10-25", "10-31").next_start(date(2024, 10, 25)),
+            Some(date(2024, 10, 25))
+        );
+        assert_eq!(
+            season("02-29", "03-01").next_start(date(2025, 1, 1)),
+            Some(date(2028, 2, 29))
+        );
+    }
+
+    #[test]
+    fn loads_response_packs() {
+        let path =
+            std::env::temp_dir().join(format!("kingfisher-pack-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+[[responses]]
+name = "spooky"
+ruleset = "r boo"
+content = "AAAAAAAAAAAAA"
+"#,
+        )
+        .unwrap();
+
+        let mut pack: ResponsePack = toml::from_str(&format!(
+            r#"
+name = "halloween"
+season = {{ start = "10-25", end = "10-31" }}
+path = '{}'
+"#,
+            path.display()
+        ))
+        .unwrap();
+
+        pack.load().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(pack.season, season("10-25", "10-31"));
+        assert_eq!(pack.responses.len(), 1);
+        assert_eq!(pack.responses[0].name(), "spooky");
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{
+    data::AppState,
+    feature_flags::{feature_enabled, Feature},
+    lang::ruleset::Ruleset,
+    llm::{Llm, LlmMessage},
+};
+use serde::{Deserialize, Serialize};
+
+const CLASSIFIER_PROMPT: &str = "You decide whether a Discord message in a university computer \
+science server seems distressed, upset or serious, so a bot knows not to reply with a joke. \
+Answer with only yes or no.";
+
+/// Keeps the fun responses away from messages that seem upset or serious.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SeriousGate {
+    /// Messages matching this are serious, e.g. `r i'?m (so )?(stressed|scared|done)`.
+    pub ruleset: Option<Ruleset>,
+    /// Ask the `llm` about messages the ruleset lets through.
+    #[serde(default)]
+    pub use_llm: bool,
+}
+
+/// Whether a response to the message should be held back.
+pub async fn is_serious(data: &AppState, content: &str) -> bool {
+    let (gate, llm) = {
+        let config = data.config.read().await;
+
+        let Some(gate) = config.serious_gate.clone() else {
+            return false;
+        };
+
+        let llm = config
+            .llm
+            .clone()
+            .filter(|_| feature_enabled(&data.db, &config, Feature::Llm));
+
+        (gate, llm)
+    };
+
+    if gate
+        .ruleset
+        .as_ref()
+        .is_some_and(|ruleset| ruleset.matches(content))
+    {
+        return true;
+    }
+
+    match llm {
+        Some(llm) if gate.use_llm => classify(&llm, content).await,
+        _ => false,
+    }
+}
+
+/// A failed classification lets the response through, so an LLM outage doesn't silence kingfisher.
+async fn classify(llm: &Llm, content: &str) -> bool {
+    let messages = [
+        LlmMessage::system(CLASSIFIER_PROMPT),
+        LlmMessage::user(content),
+    ];
+
+    match llm.chat(&messages).await {
+        Ok(answer) => is_yes(&answer),
+        Err(e) => {
+            tracing::warn!("Couldn't classify message: {:?}", e);
+            false
+        }
+    }
+}
+
+fn is_yes(answer: &str) -> bool {
+    answer
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+        .starts_with("yes")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_classifier_answers() {
+        assert!(is_yes("Yes."));
+        assert!(is_yes("**yes**"));
+        assert!(!is_yes("No"));
+        assert!(!is_yes("I'm not sure, yes maybe"));
+    }
+
+    #[test]
+    fn deserializes_gate() {
+        let gate: SeriousGate = toml::from_str(
+            r#"
+ruleset = "r stressed"
+"#,
+        )
+        .unwrap();
+
+        assert!(!gate.use_llm);
+        assert!(gate
+            .ruleset
+            .is_some_and(|ruleset| ruleset.matches("i'm so stressed about finals")));
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{data::AppState, departments::HexColor, seasons::Season};
+use chrono::{Local, NaiveDate};
+use color_eyre::eyre::Result;
+use poise::serenity_prelude::{self as serenity, CreateAttachment, EditGuild, EditRole, RoleId};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+
+const SERVER_THEME_TREE: &str = "server_theme";
+const APPLIED_KEY: &str = "applied";
+
+/// A look for the server during part of the year, like finals week or game day.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ServerTheme {
+    pub name: String,
+    pub season: Season,
+    /// Image for the server icon.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Image for the server banner, only used if the server is boosted enough for one.
+    #[serde(default)]
+    pub banner: Option<String>,
+    #[serde(default)]
+    pub role_colors: Vec<RoleColor>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RoleColor {
+    pub role_id: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub color: HexColor,
+}
+
+/// An image the theme replaced, `None` if there wasn't one.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+struct OriginalImage {
+    bytes: Option<Vec<u8>>,
+}
+
+/// The theme that's on, and what it replaced so it can be put back.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+struct AppliedTheme {
+    name: String,
+    icon: Option<OriginalImage>,
+    banner: Option<OriginalImage>,
+    role_colors: Vec<(u64, u32)>,
+}
+
+/// The theme for the day, the first one listed if seasons overlap.
+fn theme_for(themes: &[ServerTheme], date: NaiveDate) -> Option<&ServerTheme> {
+    themes.iter().find(|theme| theme.season.contains(date))
+}
+
+async fn download(url: Option<String>) -> Result<OriginalImage> {
+    let Some(url) = url else {
+        return Ok(OriginalImage { bytes: None });
+    };
+
+    let bytes = reqwest::get(url)
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?
+        .to_vec();
+
+    Ok(OriginalImage { bytes: Some(bytes) })
+}
+
+async fn load(path: Option<&String>) -> Result<Option<CreateAttachment>> {
+    Ok(match path {
+        Some(path) => Some(CreateAttachment::path(path).await?),
+        None => None,
+    })
+}
+
+/// Sets whichever of the icon and banner are given, `Some(None)` removes one.
+async fn edit_images(
+    ctx: &serenity::Context,
+    guild_id: serenity::GuildId,
+    reason: &str,
+    icon: Option<Option<&CreateAttachment>>,
+    banner: Option<Option<&CreateAttachment>>,
+) -> Result<()> {
+    if icon.is_none() && banner.is_none() {
+        return Ok(());
+    }
+
+    let mut edit = EditGuild::new().audit_log_reason(reason);
+
+    if let Some(icon) = icon {
+        edit = edit.icon(icon);
+    }
+
+    if let Some(banner) = banner {
+        edit = edit.banner(banner.map(CreateAttachment::to_base64));
+    }
+
+    guild_id.edit(ctx, edit).await?;
+
+    Ok(())
+}
+
+async fn apply(
+    ctx: &serenity::Context,
+    guild_id: serenity::GuildId,
+    theme: &ServerTheme,
+) -> Result<AppliedTheme> {
+    let guild = guild_id.to_partial_guild(ctx).await?;
+
+    let mut applied = AppliedTheme {
+        name: theme.name.clone(),
+        icon: None,
+        banner: None,
+        role_colors: vec![],
+    };
+    let icon = load(theme.icon.as_ref()).await?;
+    let banner = load(theme.banner.as_ref()).await?;
+
+    if icon.is_some() {
+        applied.icon = Some(download(guild.icon_url()).await?);
+    }
+
+    if banner.is_some() {
+        applied.banner = Some(download(guild.banner_url()).await?);
+    }
+
+    edit_images(
+        ctx,
+        guild_id,
+        &format!("{} theme", theme.name),
+        icon.as_ref().map(Some),
+        banner.as_ref().map(Some),
+    )
+    .await?;
+
+    for role_color in &theme.role_colors {
+        let role_id = RoleId::new(role_color.role_id);
+        let Some(role) = guild.roles.get(&role_id) else {
+            tracing::warn!(
+                "{} theme has a role that doesn't exist: {}",
+                theme.name,
+                role_id
+            );
+            continue;
+        };
+
+        // The icon has already changed, so keep going to make sure it gets restored later
+        match guild_id
+            .edit_role(ctx, role_id, EditRole::new().colour(role_color.color.0))
+            .await
+        {
+            Ok(_) => applied.role_colors.push((role_id.get(), role.colour.0)),
+            Err(e) => tracing::warn!("Couldn't color role {}: {:?}", role_id, e),
+        }
+    }
+
+    Ok(applied)
+}
+
+async fn restore(
+    ctx: &serenity::Context,
+    guild_id: serenity::GuildId,
+    applied: &AppliedTheme,
+) -> Result<()> {
+    let original = |image: &Option<OriginalImage>| {
+        image.as_ref().map(|image| {
+            image
+                .bytes
+                .as_ref()
+                .map(|bytes| CreateAttachment::bytes(bytes.as_slice(), "original.png"))
+        })
+    };
+    let icon = original(&applied.icon);
+    let banner = original(&applied.banner);
+
+    edit_images(
+        ctx,
+        guild_id,
+        &format!("{} theme is over", applied.name),
+        icon.as_ref().map(Option::as_ref),
+        banner.as_ref().map(Option::as_ref),
+    )
+    .await?;
+
+    for &(role_id, color) in &applied.role_colors {
+        if let Err(e) = guild_id
+            .edit_role(ctx, RoleId::new(role_id), EditRole::new().colour(color))
+            .await
+        {
+            tracing::warn!("Couldn't restore the color of role {}: {:?}", role_id, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Scheduled job, switches the server to the theme for today, putting the normal look back between themes.
+pub async fn update_server_theme(ctx: &serenity::Context, data: &AppState) -> Result<()> {
+    let (themes, guild_id) = {
+        let config = data.config.read().await;
+        (config.server_themes.clone(), config.guild_id)
+    };
+    let guild_id = serenity::GuildId::new(guild_id);
+
+    let wanted = theme_for(&themes, Local::now().date_naive());
+    let current = data
+        .db
+        .get::<AppliedTheme>(SERVER_THEME_TREE, APPLIED_KEY)?;
+
+    if wanted.map(|theme| &theme.name) == current.as_ref().map(|applied| &applied.name) {
+        return Ok(());
+    }
+
+    if let Some(current) = current {
+        restore(ctx, guild_id, &current).await?;
+        data.db
+            .remove::<AppliedTheme>(SERVER_THEME_TREE, APPLIED_KEY)?;
+    }
+
+    if let Some(wanted) = wanted {
+        let applied = apply(ctx, guild_id, wanted).await?;
+        data.db.insert(SERVER_THEME_TREE, APPLIED_KEY, &applied)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn picks_the_first_matching_theme() {
+        let themes: Vec<ServerTheme> = toml::from_str::<toml::Table>(
+            r##"
+            [[themes]]
+            name = "Game day"
+            season = { start = "11-25", end = "11-25" }
+            role_colors = [{ role_id = 1, color = "#cc0000" }]
+
+            [[themes]]
+            name = "Thanksgiving"
+            season = { start = "11-20", end = "11-30" }
+            icon = "turkey.png"
+            "##,
+        )
+        .unwrap()["themes"]
+            .clone()
+            .try_into()
+            .unwrap();
+
+        assert_eq!(themes[0].role_colors[0].color, HexColor(0xcc0000));
+
+        let date = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+        assert_eq!(
+            theme_for(&themes, date(11, 25)).map(|theme| theme.name.as_str()),
+            Some("Game day")
+        );
+        assert_eq!(
+            theme_for(&themes, date(11, 26)).map(|theme| theme.name.as_str()),
+            Some("Thanksgiving")
+        );
+        assert_eq!(theme_for(&themes, date(12, 1)), None);
+    }
+}
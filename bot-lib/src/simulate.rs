@@ -0,0 +1,224 @@
+use crate::{config::Config, moderation::ModerationAction};
+use chrono::{DateTime, Local, Utc};
+use color_eyre::eyre::{Result, WrapErr};
+use itertools::Itertools;
+use serde::Deserialize;
+use std::{collections::HashMap, io::BufRead, path::Path};
+
+/// A captured message, one JSON object per line of the log.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CapturedMessage {
+    pub content: String,
+    #[serde(default)]
+    pub channel_id: u64,
+    #[serde(default)]
+    pub author: Option<String>,
+    /// When the message was sent, for seasonal responses. Defaults to now.
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// What kingfisher would have done about a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// The moderation action and the rule that called for it.
+    pub moderation: Option<(ModerationAction, String)>,
+    /// Every response whose ruleset matched, the first is the one that would reply.
+    pub responses: Vec<String>,
+}
+
+pub fn read_messages(path: &Path) -> Result<Vec<CapturedMessage>> {
+    let file =
+        std::fs::File::open(path).wrap_err_with(|| format!("Could not open {}", path.display()))?;
+
+    std::io::BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|(i, line)| {
+            serde_json::from_str(&line?)
+                .wrap_err_with(|| format!("Could not parse line {} of {}", i + 1, path.display()))
+        })
+        .collect()
+}
+
+/// Runs a message past moderation and text detection without touching Discord.
+///
+/// Hit rates and cooldowns are ignored, so this shows what could fire rather than
+/// what a roll of the dice would have picked.
+pub fn simulate_message(config: &Config, message: &CapturedMessage) -> Outcome {
+    let moderation = config.moderation.as_ref().and_then(|moderation| {
+        moderation
+            .find_action(message.channel_id, &message.content)
+            .map(|(action, rule)| (action, rule.to_owned()))
+    });
+
+    // Deleted messages never reach text detection
+    if moderation
+        .as_ref()
+        .is_some_and(|(action, _)| *action >= ModerationAction::Delete)
+    {
+        return Outcome {
+            moderation,
+            responses: vec![],
+        };
+    }
+
+    if config.ignored_channels.contains(&message.channel_id) {
+        return Outcome {
+            moderation,
+            responses: vec![],
+        };
+    }
+
+    let date = message.timestamp.map_or_else(
+        || Local::now().date_naive(),
+        |timestamp| timestamp.with_timezone(&Local).date_naive(),
+    );
+
+    let responses = config
+        .active_responses(date)
+        .filter(|response| response.matches(&message.content))
+        .map(|response| response.name().to_owned())
+        .collect();
+
+    Outcome {
+        moderation,
+        responses,
+    }
+}
+
+/// Replays the messages, printing what would have fired and a tally at the end.
+pub fn run(config: &Config, messages: &[CapturedMessage]) {
+    let mut response_counts = HashMap::<String, usize>::new();
+    let mut rule_counts = HashMap::<String, usize>::new();
+
+    for (i, message) in messages.iter().enumerate() {
+        let outcome = simulate_message(config, message);
+
+        if outcome.moderation.is_none() && outcome.responses.is_empty() {
+            continue;
+        }
+
+        println!(
+            "#{} {}: {}",
+            i + 1,
+            message.author.as_deref().unwrap_or("unknown"),
+            message.content.replace('\n', " ")
+        );
+
+        if let Some((action, rule)) = &outcome.moderation {
+            println!("  moderation: {:?} (rule `{}`)", action, rule);
+            *rule_counts.entry(rule.clone()).or_default() += 1;
+        }
+
+        if let Some((first, rest)) = outcome.responses.split_first() {
+            println!("  response: `{}`", first);
+            *response_counts.entry(first.clone()).or_default() += 1;
+
+            if !rest.is_empty() {
+                println!(
+                    "  also matched: {}",
+                    rest.iter().map(|name| format!("`{}`", name)).join(", ")
+                );
+            }
+        }
+    }
+
+    println!("\n{} messages replayed", messages.len());
+
+    for (title, counts) in [
+        ("Responses", response_counts),
+        ("Moderation rules", rule_counts),
+    ] {
+        println!("\n{}:", title);
+
+        for (name, count) in counts.into_iter().sorted_by_key(|(_, count)| *count).rev() {
+            println!("  {:>5}  {}", count, name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::RegisteredResponse,
+        fast_ruleset,
+        moderation::{Moderation, ModerationRule},
+    };
+
+    fn message(channel_id: u64, content: &str) -> CapturedMessage {
+        CapturedMessage {
+            content: content.to_owned(),
+            channel_id,
+            author: None,
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn parses_captured_messages() {
+        let message: CapturedMessage = serde_json::from_str(
+            r#"{"content": "hi", "channel_id": 5, "timestamp": "2024-04-01T12:00:00Z"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(message.channel_id, 5);
+        assert!(message.timestamp.is_some());
+    }
+
+    #[test]
+    fn simulates_moderation_then_responses() {
+        let response = |name: &str, ruleset: &str| {
+            toml::from_str::<RegisteredResponse>(&format!(
+                "name = \"{}\"\nruleset = \"{}\"\ncontent = \"hi\"",
+                name, ruleset
+            ))
+            .unwrap()
+        };
+
+        let config = Config {
+            responses: vec![response("meme", "r meme"), response("memes", "r memes")],
+            moderation: Some(Moderation {
+                rules: vec![
+                    ModerationRule {
+                        name: "mild".to_owned(),
+                        action: ModerationAction::Warn,
+                        ruleset: fast_ruleset!("r darn"),
+                    },
+                    ModerationRule {
+                        name: "severe".to_owned(),
+                        action: ModerationAction::Delete,
+                        ruleset: fast_ruleset!("r badword"),
+                    },
+                ],
+                timeout: chrono::Duration::minutes(10),
+                channel_strictness: HashMap::new(),
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            simulate_message(&config, &message(1, "darn memes")),
+            Outcome {
+                moderation: Some((ModerationAction::Warn, "mild".to_owned())),
+                responses: vec!["meme".to_owned(), "memes".to_owned()],
+            }
+        );
+        assert_eq!(
+            simulate_message(&config, &message(1, "badword meme")),
+            Outcome {
+                moderation: Some((ModerationAction::Delete, "severe".to_owned())),
+                responses: vec![],
+            }
+        );
+        assert_eq!(
+            simulate_message(&config, &message(1, "hello")),
+            Outcome {
+                moderation: None,
+                responses: vec![],
+            }
+        );
+    }
+}
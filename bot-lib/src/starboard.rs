@@ -1,3 +1,4 @@
+use crate::discord_api::DiscordApi;
 use color_eyre::eyre::Result;
 use parking_lot::RwLock;
 use poise::serenity_prelude::ChannelId;
@@ -12,13 +13,100 @@ pub enum EmoteType {
     CustomEmote { emote_name: String },
 }
 
+/// Where else to send new starboard entries, like a club website's "best of" feed.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ExternalWebhook {
+    pub url: String,
+    /// The JSON body, with `{author}`, `{content}`, `{link}`, `{channel}`, `{image}`, `{reactions}`
+    /// and `{timestamp}` filled in. Values are escaped for use inside JSON strings.
+    #[serde(default = "get_default_webhook_template")]
+    pub template: String,
+}
+
+fn get_default_webhook_template() -> String {
+    r#"{"author":"{author}","content":"{content}","link":"{link}","channel":"{channel}","image":"{image}","reactions":{reactions},"timestamp":{timestamp}}"#.to_owned()
+}
+
+impl ExternalWebhook {
+    /// Fills in the template for a starboarded message.
+    pub fn render(&self, message: &serenity::Message, channel: &str, reactions: u64) -> String {
+        let image = message
+            .attachments
+            .iter()
+            .find(|attachment| {
+                attachment
+                    .content_type
+                    .as_ref()
+                    .is_some_and(|content_type| content_type.starts_with("image"))
+            })
+            .map_or("", |attachment| attachment.url.as_str());
+
+        let fields = [
+            ("{author}", escape_json(&message.author.name)),
+            ("{content}", escape_json(&message.content)),
+            ("{link}", escape_json(&message.link())),
+            ("{channel}", escape_json(channel)),
+            ("{image}", escape_json(image)),
+            ("{reactions}", reactions.to_string()),
+            (
+                "{timestamp}",
+                message.timestamp.unix_timestamp().to_string(),
+            ),
+        ];
+
+        fields
+            .iter()
+            .fold(self.template.clone(), |body, (placeholder, value)| {
+                body.replace(placeholder, value)
+            })
+    }
+}
+
+/// The text as it'd go between the quotes of a JSON string.
+fn escape_json(text: &str) -> String {
+    let quoted = serde_json::Value::from(text).to_string();
+
+    quoted[1..quoted.len() - 1].to_owned()
+}
+
+/// A different number of reactions needed for messages from one channel.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ChannelReactionCount {
+    pub channel_id: u64,
+    pub reaction_count: u64,
+}
+
+/// Scales `reaction_count` by how busy the message's channel is, so a quiet class channel
+/// needs fewer reactions than the meme channel.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ActivityScaling {
+    /// Messages per day where exactly `reaction_count` is needed.
+    pub typical_messages_per_day: f64,
+    /// Quiet channels never need fewer than this, the board's `reaction_count` if not given.
+    #[serde(default)]
+    pub min_reaction_count: Option<u64>,
+    pub max_reaction_count: Option<u64>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Starboard {
     pub reaction_count: u64,
+    /// A text channel, forum or thread. Archived threads get reopened.
     pub channel_id: u64,
+    /// Post in the thread (or forum post) of `channel_id` with this name instead, making it if
+    /// it's missing, e.g. "Best of CS 3500" in the class's own channel.
+    #[serde(default)]
+    pub thread_name: Option<String>,
     pub ignored_channel_ids: Option<Vec<u64>>,
     #[serde(flatten)]
     pub emote_type: EmoteType,
+    #[serde(default)]
+    pub external_webhook: Option<ExternalWebhook>,
+    /// Channels that always need a set number of reactions, whatever the scaling says.
+    #[serde(default)]
+    pub channel_reaction_counts: Vec<ChannelReactionCount>,
+    #[serde(default)]
+    pub activity_scaling: Option<ActivityScaling>,
     /// This stores a string hash of the message link
     #[serde(skip)]
     pub recently_added_messages: RwLock<HashSet<String>>,
@@ -28,8 +116,12 @@ impl PartialEq for Starboard {
     fn eq(&self, other: &Self) -> bool {
         self.reaction_count == other.reaction_count
             && self.channel_id == other.channel_id
+            && self.thread_name == other.thread_name
             && self.ignored_channel_ids == other.ignored_channel_ids
             && self.emote_type == other.emote_type
+            && self.external_webhook == other.external_webhook
+            && self.channel_reaction_counts == other.channel_reaction_counts
+            && self.activity_scaling == other.activity_scaling
     }
 }
 
@@ -40,29 +132,36 @@ impl Default for Starboard {
         Self {
             reaction_count: 1,
             channel_id: 0,
+            thread_name: None,
             ignored_channel_ids: None,
             emote_type: EmoteType::AllEmotes { all_emotes: true },
+            external_webhook: None,
+            channel_reaction_counts: vec![],
+            activity_scaling: None,
             recently_added_messages: RwLock::new(HashSet::new()),
         }
     }
 }
 
 impl Starboard {
-    #[tracing::instrument(level = "trace", skip(self, ctx, message), fields(message_link = %message.link()))]
-    pub async fn does_starboard_apply(
+    #[tracing::instrument(level = "trace", skip(self, api, message), fields(message_link = %message.link()))]
+    pub(crate) async fn does_starboard_apply(
         &self,
-        ctx: &serenity::Context,
+        api: &impl DiscordApi,
         message: &serenity::Message,
         reaction_count: u64,
         emote_name: &str,
+        messages_per_day: f64,
     ) -> bool {
-        let check = self.enough_reactions(reaction_count)
+        let needed = self.needed_reactions(message.channel_id.get(), messages_per_day);
+
+        let check = self.enough_reactions(reaction_count, needed)
             && self.is_message_recent(&message.timestamp)
             && self.is_channel_allowed(message.channel_id.into())
             && self.is_emote_allowed(emote_name)
             && self.is_message_unseen(&message.link())
             && self.is_message_a_lynch(message).await
-            && self.is_channel_missing_reply(ctx, message).await;
+            && self.is_channel_missing_reply(api, message).await;
 
         let check_msg = if check { "applies" } else { "does not apply" };
         tracing::trace!("starboard {}", check_msg);
@@ -70,15 +169,43 @@ impl Starboard {
         check
     }
 
-    fn enough_reactions(&self, reaction_count: u64) -> bool {
-        let check = reaction_count >= self.reaction_count;
+    /// Whether the starboard needs to know how busy channels are.
+    pub fn scales_by_activity(&self) -> bool {
+        self.activity_scaling.is_some()
+    }
+
+    /// How many reactions a message from the channel needs, given the channel's messages per day.
+    fn needed_reactions(&self, channel_id: u64, messages_per_day: f64) -> u64 {
+        if let Some(channel) = self
+            .channel_reaction_counts
+            .iter()
+            .find(|channel| channel.channel_id == channel_id)
+        {
+            return channel.reaction_count;
+        }
+
+        let Some(scaling) = &self.activity_scaling else {
+            return self.reaction_count;
+        };
+
+        // Square root, so a channel 4x as busy needs 2x the reactions
+        let scale = (messages_per_day / scaling.typical_messages_per_day.max(1.)).sqrt();
+        let needed = (self.reaction_count as f64 * scale).round() as u64;
+
+        needed
+            .max(scaling.min_reaction_count.unwrap_or(self.reaction_count))
+            .min(scaling.max_reaction_count.unwrap_or(u64::MAX))
+    }
+
+    fn enough_reactions(&self, reaction_count: u64, needed: u64) -> bool {
+        let check = reaction_count >= needed;
         let check_text = if check { "enough" } else { "not enough" };
 
         tracing::trace!(
             "reaction_count {} is {} (needed {})",
             reaction_count,
             check_text,
-            self.reaction_count
+            needed
         );
 
         check
@@ -145,15 +272,22 @@ impl Starboard {
 
     async fn is_channel_missing_reply(
         &self,
-        ctx: &serenity::Context,
+        api: &impl DiscordApi,
         message: &serenity::Message,
     ) -> bool {
         let message_link = message.link();
 
-        let Ok(messages) = ChannelId::new(self.channel_id)
-            .messages(ctx, serenity::GetMessages::new())
-            .await
-        else {
+        let destination = match &self.thread_name {
+            Some(name) => match self.find_thread(api, name).await {
+                Ok(Some(thread)) => thread.id,
+                // Nothing's been posted yet
+                Ok(None) => return true,
+                Err(_) => return false,
+            },
+            None => ChannelId::new(self.channel_id),
+        };
+
+        let Ok(messages) = api.channel_messages(destination).await else {
             return false;
         };
 
@@ -174,9 +308,9 @@ impl Starboard {
         check
     }
 
-    pub async fn reply(
+    pub(crate) async fn reply(
         &self,
-        ctx: &serenity::Context,
+        api: &impl DiscordApi,
         message: &serenity::Message,
         reaction_type: &serenity::ReactionType,
     ) -> Result<()> {
@@ -185,14 +319,11 @@ impl Starboard {
         let reply = match reaction_type {
             serenity::ReactionType::Unicode(emoji) => reply.content(emoji),
             serenity::ReactionType::Custom { animated, id, .. } => reply.add_file(
-                serenity::CreateAttachment::url(
-                    ctx,
-                    &format!(
-                        "https://cdn.discordapp.com/emojis/{}.{}",
-                        id,
-                        if *animated { "gif" } else { "png" }
-                    ),
-                )
+                api.attachment_from_url(&format!(
+                    "https://cdn.discordapp.com/emojis/{}.{}",
+                    id,
+                    if *animated { "gif" } else { "png" }
+                ))
                 .await?,
             ),
             _ => reply,
@@ -211,16 +342,9 @@ impl Starboard {
                 "{}\n{}{}",
                 message.content,
                 message.link(),
-                message
-                    .channel(ctx)
+                api.channel_name(message.channel_id)
                     .await
-                    .map(|channel| {
-                        if let serenity::Channel::Guild(channel) = channel {
-                            format!(" ({})", channel.name)
-                        } else {
-                            "".to_string()
-                        }
-                    })
+                    .map(|name| format!(" ({})", name))
                     .unwrap_or("".to_string())
             ))
             .author(author)
@@ -239,14 +363,50 @@ impl Starboard {
 
         let reply = reply.embed(embed);
 
-        ChannelId::new(self.channel_id)
-            .send_message(ctx, reply)
-            .await?;
+        self.post(api, reply).await?;
 
         self.recently_added_messages.write().insert(message.link());
 
         Ok(())
     }
+
+    async fn find_thread(
+        &self,
+        api: &impl DiscordApi,
+        name: &str,
+    ) -> Result<Option<serenity::GuildChannel>> {
+        Ok(api
+            .threads(ChannelId::new(self.channel_id))
+            .await?
+            .into_iter()
+            .find(|thread| thread.name == name))
+    }
+
+    /// Sends to the starboard's channel or thread, making or reopening the thread if needed.
+    async fn post(&self, api: &impl DiscordApi, reply: serenity::CreateMessage) -> Result<()> {
+        let destination = match &self.thread_name {
+            Some(name) => match self.find_thread(api, name).await? {
+                Some(thread) => thread,
+                None => {
+                    api.create_thread(ChannelId::new(self.channel_id), name, reply)
+                        .await?;
+                    return Ok(());
+                }
+            },
+            None => api.guild_channel(ChannelId::new(self.channel_id)).await?,
+        };
+
+        if destination
+            .thread_metadata
+            .is_some_and(|metadata| metadata.archived)
+        {
+            api.reopen_thread(destination.id).await?;
+        }
+
+        api.send_message(destination.id, reply).await?;
+
+        Ok(())
+    }
 }
 
 #[test]
@@ -290,3 +450,242 @@ all_emotes = true
         }
     );
 }
+
+#[cfg(test)]
+fn recent_message(content: &str) -> serenity::Message {
+    let mut message = serenity::Message::default();
+    message.id = serenity::MessageId::new(100);
+    message.channel_id = ChannelId::new(200);
+    message.guild_id = Some(serenity::GuildId::new(1));
+    message.content = content.to_owned();
+    message.timestamp = serenity::Timestamp::now();
+    message
+}
+
+#[tokio::test]
+async fn starboard_applies_once() {
+    let discord = crate::discord_api::MockDiscord::default();
+    let starboard_channel = discord.add_channel("starboard");
+    let starboard = Starboard {
+        reaction_count: 3,
+        channel_id: starboard_channel.get(),
+        ..Default::default()
+    };
+    let message = recent_message("hello world");
+
+    assert!(
+        !starboard
+            .does_starboard_apply(&discord, &message, 2, "star", 0.)
+            .await
+    );
+    assert!(
+        starboard
+            .does_starboard_apply(&discord, &message, 3, "star", 0.)
+            .await
+    );
+
+    starboard
+        .reply(
+            &discord,
+            &message,
+            &serenity::ReactionType::Unicode("⭐".to_owned()),
+        )
+        .await
+        .unwrap();
+
+    let posted = discord.channel_messages(starboard_channel).await.unwrap();
+    assert_eq!(posted.len(), 1);
+    assert_eq!(posted[0].content, "⭐");
+    assert!(posted[0].embeds[0]
+        .description
+        .as_ref()
+        .is_some_and(|description| description.contains("hello world")));
+
+    assert!(
+        !starboard
+            .does_starboard_apply(&discord, &message, 3, "star", 0.)
+            .await
+    );
+}
+
+#[tokio::test]
+async fn starboard_skips_messages_already_posted() {
+    let discord = crate::discord_api::MockDiscord::default();
+    let starboard_channel = discord.add_channel("starboard");
+    let message = recent_message("hello world");
+
+    let mut post = serenity::Message::default();
+    post.channel_id = starboard_channel;
+    post.embeds = vec![serenity::Embed::default()];
+    post.embeds[0].description = Some(format!("hello world\n{}", message.link()));
+    discord.messages.lock().push(post);
+
+    // A fresh starboard, like after a restart, hasn't seen the message itself
+    let starboard = Starboard {
+        channel_id: starboard_channel.get(),
+        ..Default::default()
+    };
+
+    assert!(
+        !starboard
+            .does_starboard_apply(&discord, &message, 1, "star", 0.)
+            .await
+    );
+}
+
+#[test]
+fn starboard_checks_emotes_and_channels() {
+    let starboard = Starboard {
+        ignored_channel_ids: Some(vec![200]),
+        emote_type: EmoteType::CustomEmote {
+            emote_name: "kingfisher".to_owned(),
+        },
+        ..Default::default()
+    };
+
+    assert!(starboard.is_emote_allowed("kingfisher"));
+    assert!(!starboard.is_emote_allowed("star"));
+    assert!(starboard.is_channel_allowed(201));
+    assert!(!starboard.is_channel_allowed(200));
+}
+
+#[test]
+fn scales_reactions_needed_by_activity() {
+    let starboard: Starboard = toml::from_str(
+        r#"
+reaction_count = 6
+channel_id = 1
+all_emotes = true
+channel_reaction_counts = [{ channel_id = 10, reaction_count = 4 }]
+
+[activity_scaling]
+typical_messages_per_day = 100
+min_reaction_count = 2
+max_reaction_count = 10
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(starboard.needed_reactions(20, 100.), 6);
+    assert_eq!(starboard.needed_reactions(20, 25.), 3);
+    assert_eq!(starboard.needed_reactions(20, 0.), 2);
+    assert_eq!(starboard.needed_reactions(20, 10_000.), 10);
+    // Overrides aren't scaled
+    assert_eq!(starboard.needed_reactions(10, 10_000.), 4);
+
+    let fixed = Starboard {
+        reaction_count: 6,
+        ..Default::default()
+    };
+    assert_eq!(fixed.needed_reactions(20, 10_000.), 6);
+
+    // Without a minimum, quiet channels don't get an easier board than configured
+    let only_up = Starboard {
+        reaction_count: 6,
+        activity_scaling: Some(ActivityScaling {
+            typical_messages_per_day: 100.,
+            min_reaction_count: None,
+            max_reaction_count: None,
+        }),
+        ..Default::default()
+    };
+    assert_eq!(only_up.needed_reactions(20, 0.), 6);
+    assert_eq!(only_up.needed_reactions(20, 400.), 12);
+}
+
+#[test]
+fn renders_webhook_template() {
+    let webhook: ExternalWebhook = toml::from_str(r#"url = "https://example.com/hook""#).unwrap();
+    let mut message = recent_message("he said \"hi\"\nthen left");
+    message.author.name = "alice".to_owned();
+
+    let body: serde_json::Value =
+        serde_json::from_str(&webhook.render(&message, "general", 4)).unwrap();
+
+    assert_eq!(body["author"], "alice");
+    assert_eq!(body["content"], "he said \"hi\"\nthen left");
+    assert_eq!(body["channel"], "general");
+    assert_eq!(body["reactions"], 4);
+    assert_eq!(body["image"], "");
+
+    let custom = ExternalWebhook {
+        template: r#"{"text":"{author}: {content}"}"#.to_owned(),
+        ..webhook
+    };
+    assert_eq!(
+        custom.render(&message, "general", 4),
+        r#"{"text":"alice: he said \"hi\"\nthen left"}"#
+    );
+}
+
+#[tokio::test]
+async fn starboard_posts_in_threads() {
+    let discord = crate::discord_api::MockDiscord::default();
+    let class_channel = discord.add_channel("cs-3500");
+    let starboard = Starboard {
+        channel_id: class_channel.get(),
+        thread_name: Some("Best of CS 3500".to_owned()),
+        ..Default::default()
+    };
+    let star = serenity::ReactionType::Unicode("⭐".to_owned());
+
+    let message = recent_message("first");
+    assert!(
+        starboard
+            .does_starboard_apply(&discord, &message, 1, "star", 0.)
+            .await
+    );
+    starboard.reply(&discord, &message, &star).await.unwrap();
+
+    let thread = discord.channel_named("Best of CS 3500").unwrap();
+    assert_eq!(thread.parent_id, Some(class_channel));
+    assert_eq!(discord.channel_messages(thread.id).await.unwrap().len(), 1);
+    assert!(discord
+        .channel_messages(class_channel)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // A fresh starboard still finds what's in the thread
+    let restarted = Starboard {
+        channel_id: class_channel.get(),
+        thread_name: Some("Best of CS 3500".to_owned()),
+        ..Default::default()
+    };
+    assert!(
+        !restarted
+            .does_starboard_apply(&discord, &message, 1, "star", 0.)
+            .await
+    );
+
+    let mut second = recent_message("second");
+    second.id = serenity::MessageId::new(101);
+    starboard.reply(&discord, &second, &star).await.unwrap();
+
+    assert_eq!(discord.channel_messages(thread.id).await.unwrap().len(), 2);
+    assert_eq!(discord.threads(class_channel).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn starboard_reopens_archived_threads() {
+    let discord = crate::discord_api::MockDiscord::default();
+    let channel = discord.add_channel("cs-3500");
+    let thread = discord.add_thread(channel, "Best of CS 3500", true);
+    let starboard = Starboard {
+        channel_id: thread.get(),
+        ..Default::default()
+    };
+
+    starboard
+        .reply(
+            &discord,
+            &recent_message("hello"),
+            &serenity::ReactionType::Unicode("⭐".to_owned()),
+        )
+        .await
+        .unwrap();
+
+    let thread = discord.guild_channel(thread).await.unwrap();
+    assert!(!thread.thread_metadata.unwrap().archived);
+    assert_eq!(discord.channel_messages(thread.id).await.unwrap().len(), 1);
+}
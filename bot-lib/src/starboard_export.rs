@@ -0,0 +1,356 @@
+use crate::{
+    data::AppState,
+    link_preview::parse_message_link,
+    starboard_rewind::{starboard_posts, StarboardPost},
+};
+use chrono::DateTime;
+use color_eyre::eyre::{OptionExt, Result, WrapErr};
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{self as serenity, UserId};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+
+/// Where images get copied to, inside the export directory.
+const IMAGES_DIR: &str = "images";
+
+lazy_static! {
+    static ref USER_MENTION: Regex =
+        Regex::new(r"<@!?(\d+)>").expect("User mention regex should be valid");
+    /// A mention the 200 character preview cut off partway through.
+    static ref CUT_OFF_MENTION: Regex =
+        Regex::new(r"<@[!&]?\d*$").expect("Cut off mention regex should be valid");
+}
+
+/// A static copy of the starboards, so alumni can browse them outside Discord.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StarboardExport {
+    /// Directory `index.html` and `starboard.json` get written to.
+    pub path: String,
+    #[serde(default = "get_default_title")]
+    pub title: String,
+    #[serde(default)]
+    pub names: AuthorNames,
+}
+
+fn get_default_title() -> String {
+    "Hall of fame".to_owned()
+}
+
+/// How authors show up in the export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthorNames {
+    /// Their Discord username.
+    Full,
+    /// "Member 1", "Member 2", ... the same number for the same person within an export.
+    #[default]
+    Anonymous,
+    /// No author at all.
+    Hidden,
+}
+
+/// A post as it appears in the export.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct ExportedPost {
+    author: Option<String>,
+    content: String,
+    image_url: Option<String>,
+    reactions: u64,
+    timestamp: i64,
+}
+
+/// Most reacted posts first, with authors named according to `names`.
+fn export_posts(
+    posts: Vec<StarboardPost>,
+    names: AuthorNames,
+    usernames: &HashMap<u64, String>,
+) -> Vec<ExportedPost> {
+    let mut pseudonyms = HashMap::new();
+    let mut name_of = |user_id: u64| match names {
+        AuthorNames::Full => usernames.get(&user_id).cloned(),
+        AuthorNames::Anonymous => {
+            let next = pseudonyms.len() + 1;
+            let number = *pseudonyms.entry(user_id).or_insert(next);
+
+            Some(format!("Member {}", number))
+        }
+        AuthorNames::Hidden => None,
+    };
+
+    posts
+        .into_iter()
+        .sorted_by(|a, b| {
+            b.reactions
+                .cmp(&a.reactions)
+                .then(a.timestamp.cmp(&b.timestamp))
+        })
+        .map(|post| {
+            let author = name_of(post.author_id);
+
+            // Mentions would otherwise leave everyone's ids in the export
+            let content = USER_MENTION.replace_all(&post.preview, |captures: &regex::Captures| {
+                let name = captures[1]
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(&mut name_of)
+                    .unwrap_or("someone".to_owned());
+
+                format!("@{}", name)
+            });
+            let content = CUT_OFF_MENTION.replace(&content, "").into_owned();
+
+            ExportedPost {
+                author,
+                content,
+                image_url: post.image_url,
+                reactions: post.reactions,
+                timestamp: post.timestamp,
+            }
+        })
+        .collect()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn render_html(title: &str, posts: &[ExportedPost]) -> String {
+    let entries = posts
+        .iter()
+        .map(|post| {
+            let date = DateTime::from_timestamp(post.timestamp, 0)
+                .map(|date| date.format("%b %-d, %Y").to_string())
+                .unwrap_or_default();
+
+            let mut entry = format!(
+                "<article>\n<p class=\"meta\">{}{} &middot; {} reactions</p>\n<p>{}</p>\n",
+                post.author
+                    .as_deref()
+                    .map(|author| format!("{} &middot; ", escape_html(author)))
+                    .unwrap_or_default(),
+                date,
+                post.reactions,
+                escape_html(&post.content).replace('\n', "<br>")
+            );
+
+            if let Some(image_url) = &post.image_url {
+                entry.push_str(&format!(
+                    "<img src=\"{}\" alt=\"\" loading=\"lazy\">\n",
+                    escape_html(image_url)
+                ));
+            }
+
+            entry.push_str("</article>");
+            entry
+        })
+        .join("\n");
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; max-width: 40rem; margin: 2rem auto; padding: 0 1rem; }}
+article {{ border-bottom: 1px solid #ddd; padding: 1rem 0; }}
+.meta {{ color: #666; font-size: 0.9rem; }}
+img {{ max-width: 100%; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+{entries}
+</body>
+</html>
+"#,
+        title = escape_html(title),
+        entries = entries
+    )
+}
+
+/// Copies the post's image into the export, since Discord's attachment links expire. The stored
+/// link has likely expired already, so a fresh one comes from the original message.
+async fn copy_image(
+    ctx: &serenity::Context,
+    directory: &Path,
+    post: &StarboardPost,
+) -> Result<String> {
+    let (_, channel_id, message_id) =
+        parse_message_link(&post.link).ok_or_eyre("Starboard post has no message link")?;
+    let file_name = format!("{}.png", message_id);
+    let relative_path = format!("{}/{}", IMAGES_DIR, file_name);
+    let path = directory.join(IMAGES_DIR).join(&file_name);
+
+    if path.exists() {
+        return Ok(relative_path);
+    }
+
+    let message = channel_id.message(ctx, message_id).await?;
+    let attachment = message
+        .attachments
+        .iter()
+        .find(|attachment| {
+            attachment
+                .content_type
+                .as_ref()
+                .is_some_and(|content_type| content_type.starts_with("image"))
+        })
+        .ok_or_eyre("Starboard post no longer has an image")?;
+
+    std::fs::write(&path, attachment.download().await?)?;
+
+    Ok(relative_path)
+}
+
+/// Writes the starboards out as HTML and JSON.
+pub async fn export_starboard(ctx: &serenity::Context, data: &AppState) -> Result<()> {
+    let Some(export) = data.config.read().await.starboard_export.clone() else {
+        return Ok(());
+    };
+
+    let mut posts = starboard_posts(data)?;
+    let path = Path::new(&export.path);
+
+    std::fs::create_dir_all(path.join(IMAGES_DIR))
+        .wrap_err("Couldn't create starboard export directory")?;
+
+    for post in posts.iter_mut().filter(|post| post.image_url.is_some()) {
+        post.image_url = match copy_image(ctx, path, post).await {
+            Ok(image_path) => Some(image_path),
+            Err(e) => {
+                tracing::debug!("Couldn't copy image of {}: {:?}", post.link, e);
+                None
+            }
+        };
+    }
+
+    let mut usernames = HashMap::new();
+
+    if export.names == AuthorNames::Full {
+        let author_ids = posts
+            .iter()
+            .flat_map(|post| {
+                USER_MENTION
+                    .captures_iter(&post.preview)
+                    .filter_map(|captures| captures[1].parse::<u64>().ok())
+                    .chain([post.author_id])
+                    .collect_vec()
+            })
+            .unique()
+            .collect_vec();
+
+        for author_id in author_ids {
+            match UserId::new(author_id).to_user(ctx).await {
+                Ok(user) => {
+                    usernames.insert(author_id, user.name);
+                }
+                Err(e) => tracing::debug!("Couldn't get user {}: {:?}", author_id, e),
+            }
+        }
+    }
+
+    let posts = export_posts(posts, export.names, &usernames);
+
+    std::fs::write(path.join("index.html"), render_html(&export.title, &posts))?;
+    std::fs::write(
+        path.join("starboard.json"),
+        serde_json::to_string_pretty(&posts)?,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn post(author_id: u64, reactions: u64, preview: &str) -> StarboardPost {
+        StarboardPost {
+            author_id,
+            link: String::new(),
+            preview: preview.to_owned(),
+            image_url: None,
+            reactions,
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn anonymizes_authors() {
+        let posts = vec![post(7, 3, "c"), post(5, 9, "a"), post(7, 5, "b")];
+        let usernames = HashMap::from([(5, "alice".to_owned()), (7, "bob".to_owned())]);
+
+        let authors = |names| {
+            export_posts(posts.clone(), names, &usernames)
+                .into_iter()
+                .map(|post| post.author)
+                .collect_vec()
+        };
+
+        assert_eq!(
+            authors(AuthorNames::Anonymous),
+            vec![
+                Some("Member 1".to_owned()),
+                Some("Member 2".to_owned()),
+                Some("Member 2".to_owned())
+            ]
+        );
+        assert_eq!(
+            authors(AuthorNames::Full),
+            vec![
+                Some("alice".to_owned()),
+                Some("bob".to_owned()),
+                Some("bob".to_owned())
+            ]
+        );
+        assert_eq!(authors(AuthorNames::Hidden), vec![None, None, None]);
+    }
+
+    #[test]
+    fn replaces_mentions() {
+        let posts = vec![post(7, 3, "<@5> and <@!9> said hi <@12"), post(5, 9, "a")];
+        let usernames = HashMap::from([(5, "alice".to_owned()), (7, "bob".to_owned())]);
+
+        let contents = |names| {
+            export_posts(posts.clone(), names, &usernames)
+                .into_iter()
+                .map(|post| post.content)
+                .collect_vec()
+        };
+
+        assert_eq!(
+            contents(AuthorNames::Anonymous)[1],
+            "@Member 1 and @Member 3 said hi "
+        );
+        assert_eq!(
+            contents(AuthorNames::Full)[1],
+            "@alice and @someone said hi "
+        );
+        assert_eq!(
+            contents(AuthorNames::Hidden)[1],
+            "@someone and @someone said hi "
+        );
+    }
+
+    #[test]
+    fn escapes_html() {
+        let posts = export_posts(
+            vec![post(1, 1, "<script>alert('hi')</script>")],
+            AuthorNames::Hidden,
+            &HashMap::new(),
+        );
+        let html = render_html("Best of <CS>", &posts);
+
+        assert!(html.contains("<title>Best of &lt;CS&gt;</title>"));
+        assert!(html.contains("&lt;script&gt;alert(&#39;hi&#39;)&lt;/script&gt;"));
+        assert!(!html.contains("<script>"));
+    }
+}
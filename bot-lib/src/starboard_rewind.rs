@@ -0,0 +1,226 @@
+use crate::data::AppState;
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity, ChannelId, Mentionable, UserId};
+use serde::{Deserialize, Serialize};
+
+const STARBOARD_POSTS_TREE: &str = "starboard_posts";
+
+/// The automatic end of semester rewind of the starboards.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StarboardRewind {
+    pub channel_id: u64,
+    /// How many posts make it into a rewind.
+    #[serde(default = "get_default_size")]
+    pub size: usize,
+    /// The last day of each semester, as `MM-DD`.
+    pub semester_ends: Vec<String>,
+}
+
+fn get_default_size() -> usize {
+    10
+}
+
+/// A message that made it onto a starboard.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StarboardPost {
+    pub author_id: u64,
+    pub link: String,
+    pub preview: String,
+    pub image_url: Option<String>,
+    pub reactions: u64,
+    pub timestamp: i64,
+}
+
+/// Remembers a message that was just posted to a starboard.
+pub fn record_starboard_post(data: &AppState, message: &serenity::Message) -> Result<()> {
+    let post = StarboardPost {
+        author_id: message.author.id.get(),
+        link: message.link(),
+        preview: message.content.chars().take(200).collect(),
+        image_url: message
+            .attachments
+            .iter()
+            .find(|attachment| {
+                attachment
+                    .content_type
+                    .as_ref()
+                    .is_some_and(|content_type| content_type.starts_with("image"))
+            })
+            .map(|attachment| attachment.url.clone()),
+        reactions: total_reactions(message),
+        timestamp: message.timestamp.unix_timestamp(),
+    };
+
+    data.db
+        .insert(STARBOARD_POSTS_TREE, message.id.get().to_be_bytes(), &post)
+}
+
+/// Keeps the reaction count of a starboard post up to date.
+pub fn update_starboard_post(data: &AppState, message: &serenity::Message) -> Result<()> {
+    let key = message.id.get().to_be_bytes();
+
+    let Some(mut post) = data.db.get::<StarboardPost>(STARBOARD_POSTS_TREE, key)? else {
+        return Ok(());
+    };
+
+    post.reactions = total_reactions(message);
+
+    data.db.insert(STARBOARD_POSTS_TREE, key, &post)
+}
+
+fn total_reactions(message: &serenity::Message) -> u64 {
+    message
+        .reactions
+        .iter()
+        .map(|reaction| reaction.count)
+        .sum()
+}
+
+/// The most reacted to posts between `from` and `to`, most first.
+pub fn top_posts(posts: Vec<StarboardPost>, from: i64, to: i64, size: usize) -> Vec<StarboardPost> {
+    posts
+        .into_iter()
+        .filter(|post| (from..to).contains(&post.timestamp))
+        .sorted_by(|a, b| {
+            b.reactions
+                .cmp(&a.reactions)
+                .then(a.timestamp.cmp(&b.timestamp))
+        })
+        .take(size)
+        .collect()
+}
+
+pub fn starboard_posts(data: &AppState) -> Result<Vec<StarboardPost>> {
+    data.db.values(STARBOARD_POSTS_TREE)
+}
+
+/// How a post shows up in a rewind.
+pub fn format_post(rank: usize, post: &StarboardPost) -> String {
+    format!(
+        "**#{}** by {} with {} reactions\n{}\n{}{}",
+        rank,
+        UserId::new(post.author_id).mention(),
+        post.reactions,
+        post.preview,
+        post.link,
+        post.image_url
+            .as_ref()
+            .map(|url| format!("\n{}", url))
+            .unwrap_or_default()
+    )
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_time(chrono::NaiveTime::MIN))
+}
+
+/// If `today` is the last day of a semester, when that semester started (the day after the previous one ended).
+fn semester_start(semester_ends: &[String], today: NaiveDate) -> Option<NaiveDate> {
+    let ends_in = |year: i32| {
+        semester_ends.iter().filter_map(move |end| {
+            NaiveDate::parse_from_str(&format!("{}-{}", year, end), "%Y-%m-%d").ok()
+        })
+    };
+
+    if !ends_in(today.year()).contains(&today) {
+        return None;
+    }
+
+    ends_in(today.year() - 1)
+        .chain(ends_in(today.year()))
+        .filter(|end| *end < today)
+        .max()
+        .map(|end| end + chrono::Days::new(1))
+}
+
+/// On the last day of a semester, posts the semester's best starboard posts.
+pub async fn post_semester_rewind(ctx: &serenity::Context, data: &AppState) -> Result<()> {
+    let Some(rewind) = data.config.read().await.starboard_rewind.clone() else {
+        return Ok(());
+    };
+
+    let today = Utc::now().date_naive();
+
+    let Some(start) = semester_start(&rewind.semester_ends, today) else {
+        return Ok(());
+    };
+
+    let posts = top_posts(
+        starboard_posts(data)?,
+        midnight(start).timestamp(),
+        Utc::now().timestamp(),
+        rewind.size,
+    );
+
+    if posts.is_empty() {
+        return Ok(());
+    }
+
+    // A message fits at most 10 embeds
+    let embeds = posts
+        .iter()
+        .enumerate()
+        .take(10)
+        .map(|(i, post)| {
+            let embed = serenity::CreateEmbed::new().description(format_post(i + 1, post));
+
+            match &post.image_url {
+                Some(url) => embed.image(url),
+                None => embed,
+            }
+        })
+        .collect_vec();
+
+    ChannelId::new(rewind.channel_id)
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .content("## Semester rewind\nThe best of the starboards this semester:")
+                .embeds(embeds)
+                .allowed_mentions(serenity::CreateAllowedMentions::new()),
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn post(reactions: u64, timestamp: i64) -> StarboardPost {
+        StarboardPost {
+            author_id: 1,
+            link: String::new(),
+            preview: String::new(),
+            image_url: None,
+            reactions,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn picks_top_posts_in_range() {
+        let posts = vec![post(3, 10), post(9, 20), post(5, 30), post(100, 1000)];
+
+        assert_eq!(top_posts(posts, 0, 100, 2), vec![post(9, 20), post(5, 30)]);
+    }
+
+    #[test]
+    fn finds_semester_start() {
+        let ends = vec!["05-10".to_owned(), "12-20".to_owned()];
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+        assert_eq!(semester_start(&ends, date(2024, 5, 9)), None);
+        assert_eq!(
+            semester_start(&ends, date(2024, 5, 10)),
+            Some(date(2023, 12, 21))
+        );
+        assert_eq!(
+            semester_start(&ends, date(2024, 12, 20)),
+            Some(date(2024, 5, 11))
+        );
+    }
+}
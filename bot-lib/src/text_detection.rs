@@ -1,4 +1,14 @@
-use crate::{config::ReactRole, data::AppState};
+use crate::{
+    author_guard::is_from_human,
+    burst_limit::{allow_response, has_response_budget},
+    cross_post::is_cross_post,
+    data::AppState,
+    feature_flags::{is_enabled, Feature},
+    mention_replies::is_mention,
+    quiet_hours::is_quiet,
+    serious_gate::is_serious,
+};
+use chrono::Utc;
 use color_eyre::eyre::{OptionExt, Result, WrapErr};
 use poise::serenity_prelude::{self as serenity};
 use serenity::Message;
@@ -8,26 +18,57 @@ pub async fn text_detection(
     data: &AppState,
     message: &Message,
 ) -> Result<()> {
-    if message.is_own(ctx) {
+    if !is_from_human(ctx, data, message).await || !is_enabled(data, Feature::Responses).await {
         return Ok(());
     }
 
-    if message.author.bot {
+    // Mentions get their own replies
+    if is_mention(ctx, data, message).await {
         return Ok(());
     }
 
-    let author_id: u64 = message.author.id.into();
+    let role_ids = message
+        .member
+        .as_ref()
+        .map(|member| {
+            member
+                .roles
+                .iter()
+                .map(|role_id| role_id.get())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
 
-    let author_has_role = data
-        .config
-        .read()
-        .await
-        .bot_react_role_members
-        .iter()
-        .find(|member| matches!(member, ReactRole { user_id, .. } if *user_id == author_id))
-        .map(|member| member.react);
+    if data.config.read().await.is_ignored(
+        message.author.id.get(),
+        &role_ids,
+        message.channel_id.get(),
+    ) {
+        return Ok(());
+    }
+
+    let author_has_role = match data.react_roles.wants_reactions(message.author.id) {
+        Some(known) => known,
+        None => {
+            let bot_react_role_id = data.config.read().await.bot_react_role_id;
+            let has_role = message
+                .author
+                .has_role(
+                    ctx,
+                    message.guild_id.ok_or_eyre("should have guild id")?,
+                    bot_react_role_id,
+                )
+                .await
+                .wrap_err("Couldn't get roles")?;
 
-    if let Some(false) = author_has_role {
+            data.react_roles
+                .set(&data.db, message.author.id, has_role)?;
+
+            has_role
+        }
+    };
+
+    if !author_has_role {
         let author_name = &message.author.name;
 
         tracing::event!(
@@ -39,28 +80,76 @@ pub async fn text_detection(
         return Ok(());
     }
 
-    let bot_react_role_id = data.config.read().await.bot_react_role_id;
-    let author_has_role = message
-        .author
-        .has_role(
-            ctx,
-            message.guild_id.ok_or_eyre("should have guild id")?,
-            bot_react_role_id,
-        )
-        .await
-        .wrap_err("Couldn't get roles")?;
-
-    data.config
-        .write()
-        .await
-        .bot_react_role_members
-        .push(ReactRole {
-            user_id: author_id,
-            react: author_has_role,
-        });
-
-    if let Some(message_response) = data.find_response(&message.content, &message.link()).await {
-        data.run_action(&message_response, message, ctx).await?;
+    if is_quiet(ctx, data, message.channel_id).await {
+        tracing::debug!("Quiet hours in {}", message.link());
+        return Ok(());
+    }
+
+    let cross_post_window = data.config.read().await.cross_post_window;
+
+    if is_cross_post(
+        message.channel_id,
+        &message.content,
+        cross_post_window,
+        Utc::now(),
+    ) {
+        tracing::debug!("Skipping cross-posted message {}", message.link());
+        return Ok(());
+    }
+
+    let mut matched_text = message.content.clone();
+    let mut response = data.find_response(&matched_text, &message.link()).await;
+
+    // Screenshots only get read when the text itself didn't match anything
+    if response.is_none() {
+        let ocr = data.config.read().await.ocr.clone();
+
+        if let Some(text) = match ocr {
+            Some(ocr) => ocr.read_images(message).await,
+            None => None,
+        } {
+            response = data.find_response(&text, &message.link()).await;
+            matched_text = text;
+        }
+    }
+
+    if let Some((name, message_response, persona)) = response {
+        let burst_limit = data.config.read().await.response_burst_limit;
+
+        // Checked up front too, so a full channel doesn't burn cooldowns (or LLM calls)
+        if burst_limit.is_some_and(|per_minute| {
+            !has_response_budget(message.channel_id, per_minute, Utc::now())
+        }) {
+            tracing::debug!("Response burst limit hit in {}", message.link());
+            return Ok(());
+        }
+
+        if is_serious(data, &message.content).await {
+            tracing::debug!(
+                "Holding `{}` back from a serious message {}",
+                name,
+                message.link()
+            );
+            return Ok(());
+        }
+
+        if !data
+            .mark_triggered(&name, &matched_text, &message.link())
+            .await
+        {
+            return Ok(());
+        }
+
+        // Only responses that really get sent use up the channel's budget
+        if burst_limit
+            .is_some_and(|per_minute| !allow_response(message.channel_id, per_minute, Utc::now()))
+        {
+            tracing::debug!("Response burst limit hit in {}", message.link());
+            return Ok(());
+        }
+
+        data.run_action(&name, &message_response, persona.as_ref(), message, ctx)
+            .await?;
     }
 
     Ok(())
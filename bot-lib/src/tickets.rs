@@ -0,0 +1,430 @@
+use crate::{data::AppState, db::KingFisherDb};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity, ChannelId, Mentionable, RoleId, UserId};
+use serde::{Deserialize, Serialize};
+
+const TICKETS_TREE: &str = "tickets";
+const TRANSCRIPTS_TREE: &str = "ticket_transcripts";
+/// Discord allows 10 files per message, and the transcripts take two.
+const MAX_KEPT_ATTACHMENTS: usize = 8;
+/// Stays under Discord's upload limit for the whole archive message, in bytes.
+const MAX_KEPT_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Private threads between a member and the mods, `[tickets]`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Tickets {
+    /// Tickets are private threads in this channel, which members don't need to see.
+    pub channel_id: u64,
+    /// Mentioned in each new ticket, which adds everyone with it to the thread.
+    pub mod_role_id: u64,
+    /// Where transcripts of closed tickets go.
+    pub archive_channel_id: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ticket {
+    pub id: u64,
+    pub user_id: u64,
+    pub thread_id: u64,
+    pub topic: String,
+    pub opened_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+/// A message as it appears in a transcript.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptLine {
+    pub author: String,
+    pub timestamp: DateTime<Utc>,
+    pub content: String,
+    pub attachments: Vec<TranscriptAttachment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptAttachment {
+    pub file_name: String,
+    /// Discord's link, which expires after a while.
+    pub url: String,
+    pub size: u32,
+    /// The name it was attached to the archive message as, if it was copied there.
+    pub kept_as: Option<String>,
+}
+
+impl TranscriptAttachment {
+    fn describe(&self) -> String {
+        match &self.kept_as {
+            Some(kept_as) => format!("{} (attached to the archive)", kept_as),
+            None => self.url.clone(),
+        }
+    }
+}
+
+impl From<&serenity::Message> for TranscriptLine {
+    fn from(message: &serenity::Message) -> Self {
+        TranscriptLine {
+            author: message.author.name.clone(),
+            timestamp: *message.timestamp,
+            content: message.content.clone(),
+            attachments: message
+                .attachments
+                .iter()
+                .map(|attachment| TranscriptAttachment {
+                    file_name: attachment.filename.clone(),
+                    url: attachment.url.clone(),
+                    size: attachment.size,
+                    kept_as: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+pub fn get_ticket(db: &KingFisherDb, thread_id: ChannelId) -> Result<Option<Ticket>> {
+    db.get(TICKETS_TREE, thread_id.get().to_be_bytes())
+}
+
+/// Makes the private thread and brings the member and mods into it.
+pub async fn open_ticket(
+    ctx: &serenity::Context,
+    data: &AppState,
+    settings: &Tickets,
+    user_id: UserId,
+    topic: &str,
+) -> Result<Ticket> {
+    let id = data.db.generate_id()?;
+    let thread = ChannelId::new(settings.channel_id)
+        .create_thread(
+            ctx,
+            serenity::CreateThread::new(format!("ticket-{}", id))
+                .kind(serenity::ChannelType::PrivateThread)
+                .invitable(false)
+                .audit_log_reason(topic),
+        )
+        .await?;
+
+    thread.id.add_thread_member(ctx, user_id).await?;
+    thread
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .content(format!(
+                    "{} opened a ticket for {}: {}\nUse `/ticket close` when it's sorted.",
+                    user_id.mention(),
+                    RoleId::new(settings.mod_role_id).mention(),
+                    topic
+                ))
+                .allowed_mentions(
+                    serenity::CreateAllowedMentions::new()
+                        .users([user_id])
+                        .roles([settings.mod_role_id]),
+                ),
+        )
+        .await?;
+
+    let ticket = Ticket {
+        id,
+        user_id: user_id.get(),
+        thread_id: thread.id.get(),
+        topic: topic.to_owned(),
+        opened_at: Utc::now(),
+        closed_at: None,
+    };
+    data.db
+        .insert(TICKETS_TREE, thread.id.get().to_be_bytes(), &ticket)?;
+
+    Ok(ticket)
+}
+
+/// Every message in the thread, oldest first.
+async fn read_thread(ctx: &serenity::Context, thread_id: ChannelId) -> Result<Vec<TranscriptLine>> {
+    let mut messages = vec![];
+    let mut before = None;
+
+    loop {
+        let mut request = serenity::GetMessages::new().limit(100);
+        if let Some(before) = before {
+            request = request.before(before);
+        }
+
+        let page = thread_id.messages(ctx, request).await?;
+        let Some(oldest) = page.last() else {
+            break;
+        };
+
+        before = Some(oldest.id);
+        let full = page.len() == 100;
+        messages.extend(page.iter().map(TranscriptLine::from));
+
+        if !full {
+            break;
+        }
+    }
+
+    messages.reverse();
+
+    Ok(messages)
+}
+
+pub fn text_transcript(ticket: &Ticket, lines: &[TranscriptLine]) -> String {
+    let header = format!(
+        "Ticket {}: {}\nOpened by user {} at {}\n\n",
+        ticket.id,
+        ticket.topic,
+        ticket.user_id,
+        ticket.opened_at.format("%Y-%m-%d %H:%M UTC")
+    );
+
+    let body = lines
+        .iter()
+        .map(|line| {
+            let mut text = format!(
+                "[{}] {}: {}",
+                line.timestamp.format("%Y-%m-%d %H:%M UTC"),
+                line.author,
+                line.content
+            );
+
+            for attachment in &line.attachments {
+                text.push_str(&format!("\n    attachment: {}", attachment.describe()));
+            }
+
+            text
+        })
+        .join("\n");
+
+    header + &body + "\n"
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn html_transcript(ticket: &Ticket, lines: &[TranscriptLine]) -> String {
+    let messages = lines
+        .iter()
+        .map(|line| {
+            let attachments = line
+                .attachments
+                .iter()
+                .map(|attachment| match &attachment.kept_as {
+                    Some(_) => escape_html(&attachment.describe()),
+                    None => format!("<a href=\"{0}\">{0}</a>", escape_html(&attachment.url)),
+                })
+                .join("<br>");
+
+            format!(
+                "<div class=\"message\"><span class=\"time\">{}</span> <b>{}</b><p>{}</p>{}</div>",
+                line.timestamp.format("%Y-%m-%d %H:%M UTC"),
+                escape_html(&line.author),
+                escape_html(&line.content).replace('\n', "<br>"),
+                attachments
+            )
+        })
+        .join("\n");
+
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Ticket {0}</title>\
+         <style>body{{font-family:sans-serif}}.time{{color:#888}}p{{margin:2px 0 10px}}</style>\
+         </head><body><h1>Ticket {0}: {1}</h1>\n{2}\n</body></html>\n",
+        ticket.id,
+        escape_html(&ticket.topic),
+        messages
+    )
+}
+
+/// Picks the attachments to copy to the archive before their links expire, oldest first, as many
+/// as fit in one message. Marks them with the names they'll be attached as.
+fn choose_attachments_to_keep(lines: &mut [TranscriptLine]) {
+    let mut kept = 0;
+    let mut size = 0;
+
+    for attachment in lines.iter_mut().flat_map(|line| &mut line.attachments) {
+        if kept == MAX_KEPT_ATTACHMENTS || size + u64::from(attachment.size) > MAX_KEPT_SIZE {
+            continue;
+        }
+
+        kept += 1;
+        size += u64::from(attachment.size);
+        attachment.kept_as = Some(format!("{}-{}", kept, attachment.file_name));
+    }
+}
+
+/// Downloads the attachments chosen to be kept. Ones that fail are left as links.
+async fn download_kept_attachments(
+    lines: &mut [TranscriptLine],
+) -> Vec<serenity::CreateAttachment> {
+    let mut files = vec![];
+
+    for attachment in lines.iter_mut().flat_map(|line| &mut line.attachments) {
+        let Some(kept_as) = attachment.kept_as.clone() else {
+            continue;
+        };
+
+        let downloaded = async {
+            reqwest::get(&attachment.url)
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await
+        }
+        .await;
+
+        match downloaded {
+            Ok(bytes) => files.push(serenity::CreateAttachment::bytes(bytes.to_vec(), kept_as)),
+            Err(e) => {
+                tracing::warn!(
+                    "Couldn't keep ticket attachment {}: {:?}",
+                    attachment.url,
+                    e
+                );
+                attachment.kept_as = None;
+            }
+        }
+    }
+
+    files
+}
+
+/// Saves a transcript, posts it to the archive channel with its attachments, and locks the
+/// thread.
+pub async fn close_ticket(
+    ctx: &serenity::Context,
+    data: &AppState,
+    settings: &Tickets,
+    mut ticket: Ticket,
+    closed_by: UserId,
+) -> Result<serenity::Message> {
+    let thread_id = ChannelId::new(ticket.thread_id);
+    let mut lines = read_thread(ctx, thread_id).await?;
+    choose_attachments_to_keep(&mut lines);
+    let kept_attachments = download_kept_attachments(&mut lines).await;
+    let text = text_transcript(&ticket, &lines);
+    let html = html_transcript(&ticket, &lines);
+
+    data.db
+        .insert(TRANSCRIPTS_TREE, ticket.id.to_be_bytes(), &text)?;
+
+    let archived = ChannelId::new(settings.archive_channel_id)
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .embed(
+                    serenity::CreateEmbed::new()
+                        .title(format!("Ticket {} closed", ticket.id))
+                        .description(&ticket.topic)
+                        .field("Opened by", format!("<@{}>", ticket.user_id), true)
+                        .field("Closed by", closed_by.mention().to_string(), true)
+                        .field("Messages", lines.len().to_string(), true)
+                        .timestamp(serenity::Timestamp::now()),
+                )
+                .add_file(serenity::CreateAttachment::bytes(
+                    text.into_bytes(),
+                    format!("ticket-{}.txt", ticket.id),
+                ))
+                .add_file(serenity::CreateAttachment::bytes(
+                    html.into_bytes(),
+                    format!("ticket-{}.html", ticket.id),
+                ))
+                .add_files(kept_attachments)
+                .allowed_mentions(serenity::CreateAllowedMentions::new()),
+        )
+        .await?;
+
+    ticket.closed_at = Some(Utc::now());
+    data.db
+        .insert(TICKETS_TREE, thread_id.get().to_be_bytes(), &ticket)?;
+
+    thread_id
+        .edit_thread(ctx, serenity::EditThread::new().archived(true).locked(true))
+        .await?;
+
+    Ok(archived)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn ticket() -> Ticket {
+        Ticket {
+            id: 7,
+            user_id: 1,
+            thread_id: 2,
+            topic: "Someone's <b>spamming</b>".to_owned(),
+            opened_at: Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap(),
+            closed_at: None,
+        }
+    }
+
+    fn attachment(file_name: &str, size: u32) -> TranscriptAttachment {
+        TranscriptAttachment {
+            file_name: file_name.to_owned(),
+            url: format!("https://cdn.example.com/{}", file_name),
+            size,
+            kept_as: None,
+        }
+    }
+
+    fn lines() -> Vec<TranscriptLine> {
+        vec![
+            TranscriptLine {
+                author: "student".to_owned(),
+                timestamp: Utc.with_ymd_and_hms(2024, 3, 1, 12, 1, 0).unwrap(),
+                content: "they keep posting <links> & stuff".to_owned(),
+                attachments: vec![attachment("proof.png", 1000)],
+            },
+            TranscriptLine {
+                author: "mod".to_owned(),
+                timestamp: Utc.with_ymd_and_hms(2024, 3, 1, 12, 5, 0).unwrap(),
+                content: "Handled, thanks".to_owned(),
+                attachments: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn text_transcripts_read_in_order() {
+        let text = text_transcript(&ticket(), &lines());
+
+        assert!(text.starts_with("Ticket 7: Someone's <b>spamming</b>\n"));
+        assert!(text.contains(
+            "[2024-03-01 12:01 UTC] student: they keep posting <links> & stuff\n    attachment: https://cdn.example.com/proof.png\n[2024-03-01 12:05 UTC] mod: Handled, thanks"
+        ));
+    }
+
+    #[test]
+    fn keeps_attachments_that_fit() {
+        let mut lines = lines();
+        lines[1].attachments = (0..9)
+            .map(|i| attachment(&format!("{}.png", i), 2 * 1024 * 1024))
+            .collect();
+
+        choose_attachments_to_keep(&mut lines);
+
+        let kept = lines
+            .iter()
+            .flat_map(|line| &line.attachments)
+            .filter_map(|attachment| attachment.kept_as.as_deref())
+            .collect_vec();
+        assert_eq!(kept, vec!["1-proof.png", "2-0.png", "3-1.png", "4-2.png"]);
+
+        let text = text_transcript(&ticket(), &lines);
+        assert!(text.contains("attachment: 1-proof.png (attached to the archive)"));
+        assert!(text.contains("attachment: https://cdn.example.com/8.png"));
+    }
+
+    #[test]
+    fn html_transcripts_are_escaped() {
+        let html = html_transcript(&ticket(), &lines());
+
+        assert!(html.contains("Someone's &lt;b&gt;spamming&lt;/b&gt;"));
+        assert!(html.contains("they keep posting &lt;links&gt; &amp; stuff"));
+        assert!(!html.contains("<links>"));
+    }
+}
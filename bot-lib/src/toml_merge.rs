@@ -0,0 +1,165 @@
+use color_eyre::eyre::Result;
+use toml_edit::{Array, DocumentMut, Item, TableLike, Value};
+
+/// Writes `new` over `existing`, keeping `existing`'s comments, whitespace and key order
+/// wherever the values didn't change. Keys missing from `new` get removed, new keys go at the end.
+pub fn merge_toml(existing: &str, new: &str) -> Result<String> {
+    let mut document = existing.parse::<DocumentMut>()?;
+    let new = new.parse::<DocumentMut>()?;
+
+    merge_table(document.as_table_mut(), new.as_table());
+
+    Ok(document.to_string())
+}
+
+fn merge_table(old: &mut dyn TableLike, new: &dyn TableLike) {
+    let removed = old
+        .iter()
+        .map(|(key, _)| key.to_owned())
+        .filter(|key| !new.contains_key(key))
+        .collect::<Vec<_>>();
+
+    for key in removed {
+        old.remove(&key);
+    }
+
+    for (key, new_item) in new.iter() {
+        match old.get_mut(key) {
+            Some(old_item) => merge_item(old_item, new_item),
+            None => {
+                old.insert(key, new_item.clone());
+            }
+        }
+    }
+}
+
+fn merge_item(old: &mut Item, new: &Item) {
+    match (old, new) {
+        (Item::ArrayOfTables(old), Item::ArrayOfTables(new)) => {
+            while old.len() > new.len() {
+                old.remove(old.len() - 1);
+            }
+
+            for (i, new_table) in new.iter().enumerate() {
+                match old.get_mut(i) {
+                    Some(old_table) => merge_table(old_table, new_table),
+                    None => old.push(new_table.clone()),
+                }
+            }
+        }
+        (Item::Value(old), Item::Value(new)) => merge_value(old, new),
+        (old, new) => match (old.as_table_like_mut(), new.as_table_like()) {
+            (Some(old), Some(new)) => merge_table(old, new),
+            _ => *old = new.clone(),
+        },
+    }
+}
+
+fn merge_value(old: &mut Value, new: &Value) {
+    match (&mut *old, new) {
+        (Value::Array(old), Value::Array(new)) => merge_array(old, new),
+        (Value::InlineTable(old), Value::InlineTable(new)) => merge_table(old, new),
+        (old, new) if same_scalar(old, new) => {}
+        (old, new) => {
+            let decor = old.decor().clone();
+
+            *old = new.clone();
+            *old.decor_mut() = decor;
+        }
+    }
+}
+
+fn merge_array(old: &mut Array, new: &Array) {
+    while old.len() > new.len() {
+        old.remove(old.len() - 1);
+    }
+
+    for (i, new_value) in new.iter().enumerate() {
+        match old.get_mut(i) {
+            Some(old_value) => merge_value(old_value, new_value),
+            None => old.push_formatted(new_value.clone()),
+        }
+    }
+}
+
+/// Compares what the values are, not how they're written.
+fn same_scalar(old: &Value, new: &Value) -> bool {
+    match (old, new) {
+        (Value::String(old), Value::String(new)) => old.value() == new.value(),
+        (Value::Integer(old), Value::Integer(new)) => old.value() == new.value(),
+        (Value::Float(old), Value::Float(new)) => old.value() == new.value(),
+        (Value::Boolean(old), Value::Boolean(new)) => old.value() == new.value(),
+        (Value::Datetime(old), Value::Datetime(new)) => old.value() == new.value(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXISTING: &str = r#"# Kingfisher's config
+guild_id = 1 # the server
+
+# Class categories, keep sorted!
+class_categories = [
+    10, # CS 1410
+    20,
+]
+
+removed = "gone soon"
+
+[presence]
+# What kingfisher is up to
+status = 'online'
+
+[[starboards]]
+channel_id = 100 # main
+reaction_count = 5
+
+[[starboards]]
+channel_id = 200
+reaction_count = 3
+"#;
+
+    #[test]
+    fn keeps_comments_on_unchanged_values() {
+        let new = EXISTING.replace("removed = \"gone soon\"\n", "");
+        let merged = merge_toml(EXISTING, &new).unwrap();
+
+        assert!(merged.contains("# Kingfisher's config"));
+        assert!(merged.contains("guild_id = 1 # the server"));
+        assert!(merged.contains("10, # CS 1410"));
+        assert!(merged.contains("status = 'online'"));
+        assert!(!merged.contains("removed"));
+    }
+
+    #[test]
+    fn updates_changed_values_in_place() {
+        let new = r#"
+guild_id = 2
+class_categories = [10, 20, 30]
+
+[presence]
+status = "idle"
+activity = "studying"
+
+[[starboards]]
+channel_id = 100
+reaction_count = 7
+"#;
+        let merged = merge_toml(EXISTING, new).unwrap();
+        let parsed = merged.parse::<DocumentMut>().unwrap();
+
+        assert!(merged.contains("guild_id = 2 # the server"));
+        assert!(merged.contains("10, # CS 1410"));
+        assert!(merged.contains("# What kingfisher is up to"));
+        assert!(merged.contains("reaction_count = 7"));
+        assert_eq!(parsed["class_categories"].as_array().unwrap().len(), 3);
+        assert_eq!(parsed["presence"]["activity"].as_str(), Some("studying"));
+        assert_eq!(parsed["starboards"].as_array_of_tables().unwrap().len(), 1);
+
+        // Key order stays as the file had it
+        assert!(merged.find("guild_id").unwrap() < merged.find("class_categories").unwrap());
+    }
+}
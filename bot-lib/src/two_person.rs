@@ -0,0 +1,180 @@
+use crate::{data::PoiseContext, db::KingFisherDb};
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use poise::{
+    serenity_prelude::{self as serenity, Mentionable},
+    CreateReply,
+};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+const PENDING_ACTIONS_TREE: &str = "pending_actions";
+/// How long a second mod has to approve.
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// A high risk command waiting on a second mod.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingAction {
+    pub id: u64,
+    pub command: String,
+    pub requested_by: u64,
+    pub description: String,
+    pub created_at: i64,
+}
+
+impl PendingAction {
+    fn is_expired(&self, now: i64) -> bool {
+        now - self.created_at > APPROVAL_TIMEOUT.as_secs() as i64
+    }
+}
+
+/// Removes the action, returning it only if it's still waiting.
+fn take_pending(db: &KingFisherDb, id: u64, now: i64) -> Result<Option<PendingAction>> {
+    Ok(db
+        .remove::<PendingAction>(PENDING_ACTIONS_TREE, id.to_be_bytes())?
+        .filter(|action| !action.is_expired(now)))
+}
+
+/// Asks a second mod, with the same permissions as the command needs, to approve it.
+///
+/// Returns whether it was approved in time, the command should do nothing otherwise.
+pub async fn require_second_mod(ctx: PoiseContext<'_>, description: String) -> Result<bool> {
+    let db = &ctx.data().db;
+    let requested_by = ctx.author().id;
+    let required_permissions = ctx.command().required_permissions;
+
+    let action = PendingAction {
+        id: db.generate_id()?,
+        command: ctx.command().qualified_name.clone(),
+        requested_by: requested_by.get(),
+        description,
+        created_at: Utc::now().timestamp(),
+    };
+    db.insert(PENDING_ACTIONS_TREE, action.id.to_be_bytes(), &action)?;
+
+    let approve_id = format!("pending_action_approve:{}", action.id);
+    let deny_id = format!("pending_action_deny:{}", action.id);
+
+    let request = ctx
+        .send(
+            CreateReply::default()
+                .embed(
+                    serenity::CreateEmbed::new()
+                        .title(format!("/{} needs a second mod", action.command))
+                        .description(&action.description)
+                        .field("Requested by", requested_by.mention().to_string(), true)
+                        .footer(serenity::CreateEmbedFooter::new(
+                            "Another mod has 5 minutes to approve",
+                        ))
+                        .color(serenity::Color::ORANGE),
+                )
+                .components(vec![serenity::CreateActionRow::Buttons(vec![
+                    serenity::CreateButton::new(&approve_id)
+                        .label("Approve")
+                        .style(serenity::ButtonStyle::Danger),
+                    serenity::CreateButton::new(&deny_id)
+                        .label("Deny")
+                        .style(serenity::ButtonStyle::Secondary),
+                ])]),
+        )
+        .await?;
+
+    let deadline = Instant::now() + APPROVAL_TIMEOUT;
+
+    loop {
+        let Some(click) = serenity::ComponentInteractionCollector::new(ctx)
+            .custom_ids(vec![approve_id.clone(), deny_id.clone()])
+            .timeout(deadline.saturating_duration_since(Instant::now()))
+            .await
+        else {
+            db.remove::<PendingAction>(PENDING_ACTIONS_TREE, action.id.to_be_bytes())?;
+            request
+                .edit(
+                    ctx,
+                    CreateReply::default()
+                        .content("Nobody approved it in time, nothing was done.")
+                        .components(vec![]),
+                )
+                .await?;
+            return Ok(false);
+        };
+
+        let can_approve = click
+            .member
+            .as_ref()
+            .and_then(|member| member.permissions)
+            .is_some_and(|permissions| permissions.contains(required_permissions));
+
+        let refusal = if click.user.id == requested_by {
+            Some("A different mod has to approve this.")
+        } else if !can_approve {
+            Some("You don't have the permissions this command needs.")
+        } else {
+            None
+        };
+
+        if let Some(refusal) = refusal {
+            click
+                .create_response(
+                    ctx,
+                    serenity::CreateInteractionResponse::Message(
+                        serenity::CreateInteractionResponseMessage::new()
+                            .ephemeral(true)
+                            .content(refusal),
+                    ),
+                )
+                .await?;
+            continue;
+        }
+
+        let approved = click.data.custom_id == approve_id
+            && take_pending(db, action.id, Utc::now().timestamp())?.is_some();
+
+        if !approved {
+            db.remove::<PendingAction>(PENDING_ACTIONS_TREE, action.id.to_be_bytes())?;
+        }
+
+        click
+            .create_response(
+                ctx,
+                serenity::CreateInteractionResponse::UpdateMessage(
+                    serenity::CreateInteractionResponseMessage::new()
+                        .content(format!(
+                            "{} by {}.",
+                            if approved { "Approved" } else { "Denied" },
+                            click.user.mention()
+                        ))
+                        .components(vec![]),
+                ),
+            )
+            .await?;
+
+        return Ok(approved);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_takes_actions_still_waiting() {
+        let db = KingFisherDb::temporary().unwrap();
+        let action = |id: u64, created_at: i64| PendingAction {
+            id,
+            command: "delete_class_category".to_owned(),
+            requested_by: 1,
+            description: String::new(),
+            created_at,
+        };
+
+        db.insert(PENDING_ACTIONS_TREE, 1u64.to_be_bytes(), &action(1, 0))
+            .unwrap();
+        db.insert(PENDING_ACTIONS_TREE, 2u64.to_be_bytes(), &action(2, 1000))
+            .unwrap();
+
+        assert_eq!(take_pending(&db, 1, 1000).unwrap(), None);
+        assert_eq!(take_pending(&db, 2, 1000).unwrap(), Some(action(2, 1000)));
+        assert_eq!(take_pending(&db, 2, 1000).unwrap(), None);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{
+    author_guard::is_from_human, data::Data, helpers::helpers_to_ping, lang::ruleset::Ruleset,
+    utils::get_class_category,
+};
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::Result;
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{self as serenity, ChannelId, Mentionable};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+/// Pings a class's helper role and some of its volunteers when a question in a class channel
+/// goes unanswered.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct UnansweredQuestions {
+    /// How long to wait for a reply before pinging, in seconds.
+    #[serde_as(as = "DurationSeconds<i64>")]
+    pub wait: Duration,
+    /// Minimum time between pings in the same channel, in seconds.
+    #[serde_as(as = "DurationSeconds<i64>")]
+    pub channel_cooldown: Duration,
+    /// The helper role name, `{}` is replaced with the class number. e.g. `"CS {} Helper"`
+    pub helper_role_name: String,
+    /// What counts as a question. Defaults to anything ending in a question mark.
+    pub ruleset: Option<Ruleset>,
+}
+
+lazy_static! {
+    static ref LAST_PINGED: DashMap<ChannelId, DateTime<Utc>> = DashMap::new();
+}
+
+impl UnansweredQuestions {
+    fn is_question(&self, content: &str) -> bool {
+        match &self.ruleset {
+            Some(ruleset) => ruleset.matches(content),
+            None => content.trim_end().ends_with('?'),
+        }
+    }
+}
+
+/// If the message is a question in a class channel, check back on it later.
+pub async fn watch_for_answer(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+) -> Result<()> {
+    if !is_from_human(ctx, data, message).await {
+        return Ok(());
+    }
+
+    let (settings, class_categories) = {
+        let config = data.config.read().await;
+
+        let Some(settings) = config.unanswered_questions.clone() else {
+            return Ok(());
+        };
+
+        (settings, config.class_categories.clone())
+    };
+
+    if !settings.is_question(&message.content) {
+        return Ok(());
+    }
+
+    let Some(category_id) = get_class_category(ctx, message.channel_id, &class_categories).await
+    else {
+        return Ok(());
+    };
+
+    let ctx = ctx.clone();
+    let message = message.clone();
+    let data = data.clone();
+
+    tokio::spawn(async move {
+        if let Err(e) = ping_if_unanswered(&ctx, &data, &settings, category_id, &message).await {
+            tracing::warn!("Couldn't check for an answer: {:?}", e);
+        }
+    });
+
+    Ok(())
+}
+
+async fn ping_if_unanswered(
+    ctx: &serenity::Context,
+    data: &Data,
+    settings: &UnansweredQuestions,
+    category_id: ChannelId,
+    question: &serenity::Message,
+) -> Result<()> {
+    tokio::time::sleep(settings.wait.to_std()?).await;
+
+    let later_messages = question
+        .channel_id
+        .messages(
+            ctx,
+            serenity::GetMessages::new().after(question.id).limit(100),
+        )
+        .await?;
+
+    let answered = later_messages.iter().any(|message| {
+        message.author.id != question.author.id
+            && (message
+                .message_reference
+                .as_ref()
+                .is_some_and(|reference| reference.message_id == Some(question.id))
+                || message.mentions_user_id(question.author.id))
+    });
+
+    if answered {
+        return Ok(());
+    }
+
+    let now = Utc::now();
+
+    if LAST_PINGED
+        .get(&question.channel_id)
+        .is_some_and(|last_pinged| now - *last_pinged < settings.channel_cooldown)
+    {
+        return Ok(());
+    }
+
+    let category_name = category_id.name(ctx).await?;
+    let class_number = category_name
+        .chars()
+        .filter(|c| c.is_ascii_digit())
+        .collect::<String>();
+    let role_name = settings.helper_role_name.replace("{}", &class_number);
+
+    let Some(guild_id) = question.guild_id else {
+        return Ok(());
+    };
+
+    let role_id = guild_id
+        .roles(ctx)
+        .await?
+        .into_values()
+        .find(|role| role.name == role_name)
+        .map(|role| role.id);
+
+    let helpers = match class_number.parse() {
+        Ok(class) => helpers_to_ping(&data.db, class, question.author.id)?,
+        Err(_) => vec![],
+    };
+
+    if role_id.is_none() && helpers.is_empty() {
+        return Ok(());
+    }
+
+    LAST_PINGED.insert(question.channel_id, now);
+
+    let mentions = role_id
+        .map(|role_id| role_id.mention().to_string())
+        .into_iter()
+        .chain(helpers.iter().map(|user_id| user_id.mention().to_string()))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    question
+        .channel_id
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .reference_message(question)
+                .allowed_mentions(
+                    serenity::CreateAllowedMentions::new()
+                        .roles(role_id)
+                        .users(helpers),
+                )
+                .content(format!(
+                    "{} this question hasn't gotten an answer yet, can anyone help?",
+                    mentions
+                )),
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast_ruleset;
+
+    fn settings(ruleset: Option<Ruleset>) -> UnansweredQuestions {
+        UnansweredQuestions {
+            wait: Duration::minutes(15),
+            channel_cooldown: Duration::hours(1),
+            helper_role_name: "CS {} Helper".to_owned(),
+            ruleset,
+        }
+    }
+
+    #[test]
+    fn detects_questions() {
+        let settings = settings(None);
+
+        assert!(settings.is_question("how do pointers work?"));
+        assert!(settings.is_question("how do pointers work?  "));
+        assert!(!settings.is_question("pointers work"));
+    }
+
+    #[test]
+    fn detects_questions_with_ruleset() {
+        let settings = settings(Some(fast_ruleset!("r (?i)^how")));
+
+        assert!(settings.is_question("how do pointers work"));
+        assert!(!settings.is_question("pointers work?"));
+    }
+}
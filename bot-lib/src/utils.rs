@@ -1,4 +1,7 @@
 use chrono::{DateTime, Utc};
+use color_eyre::eyre::{bail, eyre, Result};
+use poise::serenity_prelude::{self as serenity, ChannelId, ChannelType, GuildId, Permissions};
+use std::time::Duration;
 
 pub trait GetRelativeTimestamp {
     fn discord_relative_timestamp(&self) -> String;
@@ -9,3 +12,106 @@ impl GetRelativeTimestamp for DateTime<Utc> {
         format!("<t:{}:R>", self.timestamp())
     }
 }
+
+/// The class category a channel belongs to, if any.
+pub async fn get_class_category(
+    ctx: &serenity::Context,
+    channel_id: ChannelId,
+    class_categories: &[ChannelId],
+) -> Option<ChannelId> {
+    let channel = channel_id.to_channel(ctx).await.ok()?.guild()?;
+
+    channel
+        .parent_id
+        .filter(|parent_id| class_categories.contains(parent_id))
+}
+
+/// What the member can do in a channel of the guild, overwrites included.
+///
+/// Threads go by their parent channel, and private ones also need the member to be in them (or
+/// able to manage threads). Fails if the channel isn't in the guild, so callers can fail closed.
+pub async fn member_permissions_in(
+    ctx: &serenity::Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    member: &serenity::Member,
+) -> Result<Permissions> {
+    let channel = channel_id
+        .to_channel(ctx)
+        .await?
+        .guild()
+        .ok_or_else(|| eyre!("Not a guild channel"))?;
+
+    if channel.guild_id != guild_id {
+        bail!("Channel {} isn't in guild {}", channel_id, guild_id);
+    }
+
+    let private_thread = channel.kind == ChannelType::PrivateThread;
+    let channel = match (channel.thread_metadata, channel.parent_id) {
+        (Some(_), Some(parent_id)) => parent_id
+            .to_channel(ctx)
+            .await?
+            .guild()
+            .ok_or_else(|| eyre!("Not a guild channel"))?,
+        (Some(_), None) => bail!("Thread without a parent"),
+        (None, _) => channel,
+    };
+
+    let permissions = ctx
+        .cache
+        .guild(guild_id)
+        .map(|guild| guild.user_permissions_in(&channel, member))
+        .ok_or_else(|| eyre!("Guild isn't cached"))?;
+
+    if private_thread
+        && !permissions.manage_threads()
+        && !channel_id
+            .get_thread_members(ctx)
+            .await?
+            .iter()
+            .any(|thread_member| thread_member.user_id == member.user.id)
+    {
+        return Ok(Permissions::empty());
+    }
+
+    Ok(permissions)
+}
+
+/// Whether Discord said the thing being asked for doesn't exist, like a role deleted by hand.
+pub fn is_not_found(error: &serenity::Error) -> bool {
+    matches!(
+        error,
+        serenity::Error::Http(serenity::HttpError::UnsuccessfulRequest(response))
+            if response.status_code == serenity::StatusCode::NOT_FOUND
+    )
+}
+
+/// Shows a modal in response to a button press and waits (up to an hour) for it to be submitted.
+///
+/// Same as [`poise::execute_modal_on_component_interaction`], but usable from the event handler.
+pub async fn execute_modal_on_button<M: poise::Modal>(
+    ctx: &serenity::Context,
+    interaction: &serenity::ComponentInteraction,
+) -> Result<Option<M>> {
+    let custom_id = interaction.id.to_string();
+
+    interaction
+        .create_response(ctx, M::create(None, custom_id.clone()))
+        .await?;
+
+    let Some(response) = serenity::ModalInteractionCollector::new(ctx)
+        .filter(move |response| response.data.custom_id == custom_id)
+        .timeout(Duration::from_secs(3600))
+        .await
+    else {
+        return Ok(None);
+    };
+
+    response
+        .create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
+        .await?;
+
+    M::parse(response.data.clone())
+        .map(Some)
+        .map_err(|e| eyre!(e))
+}
@@ -0,0 +1,320 @@
+use crate::data::AppState;
+use chrono::Duration;
+use color_eyre::eyre::{OptionExt, Result, WrapErr};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{self as serenity, ChannelId, GuildId, UserId};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+/// Short clips kingfisher can play in voice channels.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Soundboard {
+    /// Every file in here is a sound, named after the file without its extension.
+    pub dir: String,
+    /// How long a voice channel has to wait between sounds, in seconds.
+    #[serde_as(as = "DurationSeconds<i64>")]
+    #[serde(default = "get_default_cooldown")]
+    pub cooldown: Duration,
+    /// The longest sound `/soundboard add` accepts, in seconds.
+    #[serde_as(as = "DurationSeconds<i64>")]
+    #[serde(default = "get_default_max_duration")]
+    pub max_duration: Duration,
+    /// The biggest file `/soundboard add` accepts.
+    #[serde(default = "get_default_max_bytes")]
+    pub max_bytes: u32,
+}
+
+fn get_default_cooldown() -> Duration {
+    Duration::seconds(30)
+}
+
+fn get_default_max_duration() -> Duration {
+    Duration::seconds(10)
+}
+
+fn get_default_max_bytes() -> u32 {
+    1024 * 1024
+}
+
+const SOUND_PLAYS_TREE: &str = "sound_plays";
+
+/// The formats `/soundboard add` accepts.
+pub const SOUND_EXTENSIONS: &[&str] = &["mp3", "ogg", "wav", "flac"];
+
+lazy_static! {
+    /// When a sound last started playing in each voice channel.
+    static ref LAST_PLAYED: DashMap<ChannelId, Instant> = DashMap::new();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayResult {
+    Playing,
+    NotInVoice,
+    /// How long until the channel can play another sound.
+    RateLimited(std::time::Duration),
+}
+
+/// Every sound in the soundboard directory, sorted by name.
+pub fn list_sounds(dir: &str) -> Result<Vec<(String, PathBuf)>> {
+    let mut sounds = std::fs::read_dir(dir)
+        .wrap_err_with(|| format!("Could not read {}", dir))?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+
+            if !path.is_file() {
+                return None;
+            }
+
+            Some((path.file_stem()?.to_string_lossy().into_owned(), path))
+        })
+        .collect::<Vec<_>>();
+
+    sounds.sort();
+
+    Ok(sounds)
+}
+
+/// Sound names double as file names, so keep them boring.
+pub fn is_valid_sound_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// How long a sound file plays for.
+pub fn sound_duration(bytes: Vec<u8>, extension: &str) -> Result<std::time::Duration> {
+    use symphonia::core::{
+        formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+    };
+
+    let source = MediaSourceStream::new(Box::new(std::io::Cursor::new(bytes)), Default::default());
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            Hint::new().with_extension(extension),
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .wrap_err("Not a sound file")?;
+
+    let params = &probed
+        .format
+        .default_track()
+        .ok_or_eyre("The file has no audio")?
+        .codec_params;
+
+    let frames = params
+        .n_frames
+        .ok_or_eyre("Couldn't tell how long the sound is")?;
+    let time = params
+        .time_base
+        .ok_or_eyre("Couldn't tell how long the sound is")?
+        .calc_time(frames);
+
+    Ok(
+        std::time::Duration::from_secs(time.seconds)
+            + std::time::Duration::from_secs_f64(time.frac),
+    )
+}
+
+/// Counts a play of a soundboard sound.
+pub fn record_play(data: &AppState, name: &str) -> Result<()> {
+    let plays = data
+        .db
+        .get::<u64>(SOUND_PLAYS_TREE, name)?
+        .unwrap_or_default();
+
+    data.db.insert(SOUND_PLAYS_TREE, name, &(plays + 1))
+}
+
+pub fn play_count(data: &AppState, name: &str) -> Result<u64> {
+    Ok(data
+        .db
+        .get::<u64>(SOUND_PLAYS_TREE, name)?
+        .unwrap_or_default())
+}
+
+pub fn forget_plays(data: &AppState, name: &str) -> Result<()> {
+    data.db.remove::<u64>(SOUND_PLAYS_TREE, name).map(|_| ())
+}
+
+/// The voice channel a member is currently in.
+pub fn get_voice_channel(
+    ctx: &serenity::Context,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Option<ChannelId> {
+    ctx.cache
+        .guild(guild_id)?
+        .voice_states
+        .get(&user_id)?
+        .channel_id
+}
+
+/// Claims the channel for a new sound, unless one played too recently.
+fn claim_channel(channel_id: ChannelId, cooldown: Duration, now: Instant) -> PlayResult {
+    let cooldown = cooldown.to_std().unwrap_or_default();
+
+    if let Some(last_played) = LAST_PLAYED.get(&channel_id) {
+        let elapsed = now.saturating_duration_since(*last_played);
+
+        if elapsed < cooldown {
+            return PlayResult::RateLimited(cooldown - elapsed);
+        }
+    }
+
+    LAST_PLAYED.insert(channel_id, now);
+
+    PlayResult::Playing
+}
+
+/// Plays a sound in whatever voice channel the user is in, `skip_cooldown` for a spent soundboard credit.
+pub async fn play_sound(
+    ctx: &serenity::Context,
+    data: &AppState,
+    guild_id: GuildId,
+    user_id: UserId,
+    path: &Path,
+    skip_cooldown: bool,
+) -> Result<PlayResult> {
+    let Some(channel_id) = get_voice_channel(ctx, guild_id, user_id) else {
+        return Ok(PlayResult::NotInVoice);
+    };
+
+    let cooldown = data
+        .config
+        .read()
+        .await
+        .soundboard
+        .as_ref()
+        .map_or(get_default_cooldown(), |soundboard| soundboard.cooldown);
+
+    let result = if skip_cooldown {
+        LAST_PLAYED.insert(channel_id, Instant::now());
+        PlayResult::Playing
+    } else {
+        claim_channel(channel_id, cooldown, Instant::now())
+    };
+
+    if result == PlayResult::Playing {
+        play_in_channel(ctx, guild_id, channel_id, path.to_owned()).await?;
+    }
+
+    Ok(result)
+}
+
+#[cfg(feature = "voice")]
+async fn play_in_channel(
+    ctx: &serenity::Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    path: PathBuf,
+) -> Result<()> {
+    let manager = songbird::get(ctx)
+        .await
+        .ok_or_eyre("Songbird isn't registered")?;
+
+    let call = manager.join(guild_id, channel_id).await?;
+    let track = call
+        .lock()
+        .await
+        .play_input(songbird::input::File::new(path).into());
+
+    // Leave once the clip is over
+    tokio::spawn(async move {
+        while track
+            .get_info()
+            .await
+            .is_ok_and(|info| !info.playing.is_done())
+        {
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+
+        if let Err(e) = manager.remove(guild_id).await {
+            tracing::warn!("Couldn't leave voice channel: {:?}", e);
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(not(feature = "voice"))]
+async fn play_in_channel(
+    _ctx: &serenity::Context,
+    _guild_id: GuildId,
+    _channel_id: ChannelId,
+    _path: PathBuf,
+) -> Result<()> {
+    color_eyre::eyre::bail!("Kingfisher was built without the `voice` feature")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rate_limits_per_channel() {
+        let now = Instant::now();
+        let later = now + std::time::Duration::from_secs(10);
+        let cooldown = Duration::seconds(30);
+
+        assert_eq!(
+            claim_channel(ChannelId::new(1), cooldown, now),
+            PlayResult::Playing
+        );
+        assert_eq!(
+            claim_channel(ChannelId::new(1), cooldown, later),
+            PlayResult::RateLimited(std::time::Duration::from_secs(20))
+        );
+        assert_eq!(
+            claim_channel(ChannelId::new(2), cooldown, later),
+            PlayResult::Playing
+        );
+    }
+
+    /// A mono 8 bit wav file, `seconds` long.
+    fn wav(seconds: u32) -> Vec<u8> {
+        let sample_rate: u32 = 8000;
+        let data_len = sample_rate * seconds;
+
+        let mut wav = b"RIFF".to_vec();
+        wav.extend((36 + data_len).to_le_bytes());
+        wav.extend(b"WAVEfmt ");
+        wav.extend(16u32.to_le_bytes());
+        wav.extend(1u16.to_le_bytes());
+        wav.extend(1u16.to_le_bytes());
+        wav.extend(sample_rate.to_le_bytes());
+        wav.extend(sample_rate.to_le_bytes());
+        wav.extend(1u16.to_le_bytes());
+        wav.extend(8u16.to_le_bytes());
+        wav.extend(b"data");
+        wav.extend(data_len.to_le_bytes());
+        wav.extend(std::iter::repeat_n(128u8, data_len as usize));
+        wav
+    }
+
+    #[test]
+    fn measures_sound_duration() {
+        assert_eq!(
+            sound_duration(wav(3), "wav").unwrap(),
+            std::time::Duration::from_secs(3)
+        );
+        assert!(sound_duration(b"not a sound".to_vec(), "mp3").is_err());
+    }
+
+    #[test]
+    fn validates_sound_names() {
+        assert!(is_valid_sound_name("vine-boom_2"));
+        assert!(!is_valid_sound_name("../config"));
+        assert!(!is_valid_sound_name(""));
+    }
+}
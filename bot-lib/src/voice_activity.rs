@@ -0,0 +1,414 @@
+use crate::{data::AppState, db::KingFisherDb};
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity, ChannelId, Mentionable, UserId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const VOICE_SESSIONS_TREE: &str = "voice_sessions";
+const OPEN_VOICE_SESSIONS_TREE: &str = "open_voice_sessions";
+const LEADERBOARD_SIZE: usize = 5;
+
+/// Voice channels people study in, and where to celebrate whoever studied the most.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StudyRooms {
+    pub channel_ids: Vec<u64>,
+    /// Where the monthly "most studious" shout-out is posted.
+    pub shout_out_channel_id: u64,
+}
+
+/// Time someone spent in a voice channel, in unix seconds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoiceSession {
+    pub user_id: u64,
+    pub channel_id: u64,
+    pub start: i64,
+    pub end: i64,
+}
+
+/// Someone who is currently in a voice channel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct OpenVoiceSession {
+    channel_id: u64,
+    start: i64,
+    /// When they were last known to still be there, in case they leave while kingfisher is down.
+    #[serde(default)]
+    last_seen: Option<i64>,
+}
+
+/// Closes the session of whoever left or moved, and opens one for where they went.
+pub fn track_voice_state(data: &AppState, new: &serenity::VoiceState) -> Result<()> {
+    if new.member.as_ref().is_some_and(|member| member.user.bot) {
+        return Ok(());
+    }
+
+    let key = new.user_id.get().to_be_bytes();
+    let now = Utc::now().timestamp();
+    let channel_id = new.channel_id.map(ChannelId::get);
+
+    let open = data
+        .db
+        .get::<OpenVoiceSession>(OPEN_VOICE_SESSIONS_TREE, key)?;
+
+    // Mute, deafen and such also end up here
+    if open.as_ref().map(|open| open.channel_id) == channel_id {
+        return Ok(());
+    }
+
+    if let Some(open) = open {
+        let session = VoiceSession {
+            user_id: new.user_id.get(),
+            channel_id: open.channel_id,
+            start: open.start,
+            end: now,
+        };
+
+        data.db.insert(
+            VOICE_SESSIONS_TREE,
+            data.db.generate_id()?.to_be_bytes(),
+            &session,
+        )?;
+    }
+
+    match channel_id {
+        Some(channel_id) => data.db.insert(
+            OPEN_VOICE_SESSIONS_TREE,
+            key,
+            &OpenVoiceSession {
+                channel_id,
+                start: now,
+                last_seen: Some(now),
+            },
+        ),
+        None => data
+            .db
+            .remove::<OpenVoiceSession>(OPEN_VOICE_SESSIONS_TREE, key)
+            .map(|_| ()),
+    }
+}
+
+/// Brings the open sessions in line with who is actually in voice, `in_voice` being each member
+/// and their channel. Sessions of members who left or moved while kingfisher wasn't watching end
+/// when they were last seen, instead of running on forever.
+fn reconcile_sessions(
+    db: &KingFisherDb,
+    in_voice: &HashMap<UserId, ChannelId>,
+    now: i64,
+) -> Result<()> {
+    let open = db.entries::<OpenVoiceSession>(OPEN_VOICE_SESSIONS_TREE)?;
+
+    for (key, session) in &open {
+        let Ok(user_id) = key.as_slice().try_into().map(u64::from_be_bytes) else {
+            continue;
+        };
+
+        if in_voice
+            .get(&UserId::new(user_id))
+            .map(|channel_id| channel_id.get())
+            == Some(session.channel_id)
+        {
+            db.insert(
+                OPEN_VOICE_SESSIONS_TREE,
+                key,
+                &OpenVoiceSession {
+                    last_seen: Some(now),
+                    ..session.clone()
+                },
+            )?;
+            continue;
+        }
+
+        db.insert(
+            VOICE_SESSIONS_TREE,
+            db.generate_id()?.to_be_bytes(),
+            &VoiceSession {
+                user_id,
+                channel_id: session.channel_id,
+                start: session.start,
+                end: session.last_seen.unwrap_or(session.start),
+            },
+        )?;
+        db.remove::<OpenVoiceSession>(OPEN_VOICE_SESSIONS_TREE, key)?;
+    }
+
+    // Everyone else joined (or moved) while kingfisher wasn't watching
+    for (user_id, channel_id) in in_voice {
+        let key = user_id.get().to_be_bytes();
+
+        if db
+            .get::<OpenVoiceSession>(OPEN_VOICE_SESSIONS_TREE, key)?
+            .is_none()
+        {
+            db.insert(
+                OPEN_VOICE_SESSIONS_TREE,
+                key,
+                &OpenVoiceSession {
+                    channel_id: channel_id.get(),
+                    start: now,
+                    last_seen: Some(now),
+                },
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn members_in_voice(guild: &serenity::Guild) -> HashMap<UserId, ChannelId> {
+    guild
+        .voice_states
+        .values()
+        .filter(|state| {
+            guild
+                .members
+                .get(&state.user_id)
+                .is_none_or(|member| !member.user.bot)
+        })
+        .filter_map(|state| Some((state.user_id, state.channel_id?)))
+        .collect()
+}
+
+/// Catches up on voice changes missed while kingfisher was down, when the guild comes in.
+pub fn reconcile_voice_sessions(data: &AppState, guild: &serenity::Guild) -> Result<()> {
+    reconcile_sessions(&data.db, &members_in_voice(guild), Utc::now().timestamp())
+}
+
+/// Keeps `last_seen` up to date, and closes anything a missed event left open.
+pub async fn refresh_voice_sessions(ctx: &serenity::Context, data: &AppState) -> Result<()> {
+    let guild_id = serenity::GuildId::new(data.config.read().await.guild_id);
+
+    let Some(in_voice) = ctx
+        .cache
+        .guild(guild_id)
+        .map(|guild| members_in_voice(&guild))
+    else {
+        return Ok(());
+    };
+
+    reconcile_sessions(&data.db, &in_voice, Utc::now().timestamp())
+}
+
+/// Every session, including ones still going on (which end now).
+pub fn all_sessions(data: &AppState) -> Result<Vec<VoiceSession>> {
+    let now = Utc::now().timestamp();
+
+    let open = data
+        .db
+        .entries::<OpenVoiceSession>(OPEN_VOICE_SESSIONS_TREE)?
+        .into_iter()
+        .filter_map(|(key, open)| {
+            Some(VoiceSession {
+                user_id: u64::from_be_bytes(key.try_into().ok()?),
+                channel_id: open.channel_id,
+                start: open.start,
+                end: now,
+            })
+        });
+
+    Ok(data
+        .db
+        .values::<VoiceSession>(VOICE_SESSIONS_TREE)?
+        .into_iter()
+        .chain(open)
+        .collect())
+}
+
+/// Seconds spent in `channel_ids` per user between `from` and `to`, most first.
+pub fn study_totals(
+    sessions: &[VoiceSession],
+    channel_ids: &[u64],
+    from: i64,
+    to: i64,
+) -> Vec<(u64, i64)> {
+    sessions
+        .iter()
+        .filter(|session| channel_ids.contains(&session.channel_id))
+        .map(|session| {
+            let seconds = session.end.min(to) - session.start.max(from);
+            (session.user_id, seconds.max(0))
+        })
+        .into_grouping_map()
+        .sum()
+        .into_iter()
+        .filter(|(_, seconds)| *seconds > 0)
+        .sorted_by(|(user_a, a), (user_b, b)| b.cmp(a).then(user_a.cmp(user_b)))
+        .collect()
+}
+
+/// The start of the month `date` is in, and the start of the month after.
+pub fn month_bounds(date: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start = date.with_day(1).expect("Every month has a first day");
+    let end = start
+        .checked_add_months(chrono::Months::new(1))
+        .expect("Date out of range");
+
+    (
+        Utc.from_utc_datetime(&start.and_time(chrono::NaiveTime::MIN)),
+        Utc.from_utc_datetime(&end.and_time(chrono::NaiveTime::MIN)),
+    )
+}
+
+pub fn format_seconds(seconds: i64) -> String {
+    format!("{}h {}m", seconds / 3600, seconds % 3600 / 60)
+}
+
+pub fn format_leaderboard(totals: &[(u64, i64)]) -> String {
+    totals
+        .iter()
+        .take(LEADERBOARD_SIZE)
+        .enumerate()
+        .map(|(i, (user_id, seconds))| {
+            format!(
+                "{}. {} ({})",
+                i + 1,
+                UserId::new(*user_id).mention(),
+                format_seconds(*seconds)
+            )
+        })
+        .join("\n")
+}
+
+/// On the first of the month, celebrates whoever spent the most time in study rooms last month.
+pub async fn post_study_shout_out(ctx: &serenity::Context, data: &AppState) -> Result<()> {
+    let Some(study_rooms) = data.config.read().await.study_rooms.clone() else {
+        return Ok(());
+    };
+
+    let today = Utc::now().date_naive();
+
+    if today.day() != 1 {
+        return Ok(());
+    }
+
+    let (from, to) = month_bounds(today - chrono::Days::new(1));
+    let totals = study_totals(
+        &all_sessions(data)?,
+        &study_rooms.channel_ids,
+        from.timestamp(),
+        to.timestamp(),
+    );
+
+    let Some((winner, seconds)) = totals.first() else {
+        return Ok(());
+    };
+
+    ChannelId::new(study_rooms.shout_out_channel_id)
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .content(format!(
+                    "Congrats to {}, {}'s most studious member with {} in the study rooms! 📚",
+                    UserId::new(*winner).mention(),
+                    from.format("%B"),
+                    format_seconds(*seconds)
+                ))
+                .embed(
+                    serenity::CreateEmbed::new()
+                        .title("Study room leaderboard")
+                        .description(format_leaderboard(&totals)),
+                ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn session(user_id: u64, channel_id: u64, start: i64, end: i64) -> VoiceSession {
+        VoiceSession {
+            user_id,
+            channel_id,
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn totals_study_time_in_range() {
+        let sessions = vec![
+            session(1, 10, 100, 200),
+            session(1, 10, 300, 400),
+            // Not a study room
+            session(1, 20, 0, 10_000),
+            // Only partly in range
+            session(2, 10, 0, 1000),
+            // Out of range
+            session(3, 10, 2000, 3000),
+        ];
+
+        assert_eq!(
+            study_totals(&sessions, &[10], 50, 1000),
+            vec![(2, 950), (1, 200)]
+        );
+    }
+
+    #[test]
+    fn closes_sessions_missed_while_down() {
+        let db = KingFisherDb::temporary().unwrap();
+        let open = |channel_id, last_seen| OpenVoiceSession {
+            channel_id,
+            start: 100,
+            last_seen,
+        };
+
+        // Left, moved, and stayed while kingfisher was down
+        db.insert(
+            OPEN_VOICE_SESSIONS_TREE,
+            1u64.to_be_bytes(),
+            &open(10, Some(160)),
+        )
+        .unwrap();
+        db.insert(
+            OPEN_VOICE_SESSIONS_TREE,
+            2u64.to_be_bytes(),
+            &open(10, Some(160)),
+        )
+        .unwrap();
+        db.insert(
+            OPEN_VOICE_SESSIONS_TREE,
+            3u64.to_be_bytes(),
+            &open(10, None),
+        )
+        .unwrap();
+
+        let in_voice = HashMap::from([
+            (UserId::new(2), ChannelId::new(20)),
+            (UserId::new(3), ChannelId::new(10)),
+            (UserId::new(4), ChannelId::new(10)),
+        ]);
+        reconcile_sessions(&db, &in_voice, 1000).unwrap();
+
+        let closed = db.values::<VoiceSession>(VOICE_SESSIONS_TREE).unwrap();
+        assert_eq!(
+            closed,
+            vec![session(1, 10, 100, 160), session(2, 10, 100, 160)]
+        );
+
+        let open = db
+            .entries::<OpenVoiceSession>(OPEN_VOICE_SESSIONS_TREE)
+            .unwrap()
+            .into_iter()
+            .map(|(key, open)| (key[7], open.channel_id, open.start, open.last_seen))
+            .collect_vec();
+        assert_eq!(
+            open,
+            vec![
+                (2, 20, 1000, Some(1000)),
+                (3, 10, 100, Some(1000)),
+                (4, 10, 1000, Some(1000))
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_month_bounds() {
+        let (from, to) = month_bounds(NaiveDate::from_ymd_opt(2024, 12, 15).unwrap());
+
+        assert_eq!(from.to_rfc3339(), "2024-12-01T00:00:00+00:00");
+        assert_eq!(to.to_rfc3339(), "2025-01-01T00:00:00+00:00");
+    }
+}
@@ -0,0 +1,62 @@
+use color_eyre::eyre::Result;
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{self as serenity, ChannelId};
+
+const WEBHOOK_NAME: &str = "Kingfisher";
+
+lazy_static! {
+    static ref WEBHOOKS: DashMap<ChannelId, serenity::Webhook> = DashMap::new();
+}
+
+/// Kingfisher's webhook in the channel, made if it doesn't exist yet.
+async fn get_webhook(ctx: &serenity::Context, channel_id: ChannelId) -> Result<serenity::Webhook> {
+    if let Some(webhook) = WEBHOOKS.get(&channel_id) {
+        return Ok(webhook.clone());
+    }
+
+    let existing =
+        channel_id.webhooks(ctx).await?.into_iter().find(|webhook| {
+            webhook.name.as_deref() == Some(WEBHOOK_NAME) && webhook.token.is_some()
+        });
+
+    let webhook = match existing {
+        Some(webhook) => webhook,
+        None => {
+            channel_id
+                .create_webhook(ctx, serenity::CreateWebhook::new(WEBHOOK_NAME))
+                .await?
+        }
+    };
+
+    WEBHOOKS.insert(channel_id, webhook.clone());
+
+    Ok(webhook)
+}
+
+/// Posts in a channel (or thread) through kingfisher's webhook, so it can look like someone else.
+///
+/// Returns the posted message.
+pub async fn execute_webhook(
+    ctx: &serenity::Context,
+    channel_id: ChannelId,
+    builder: serenity::ExecuteWebhook,
+) -> Result<Option<serenity::Message>> {
+    let channel = channel_id.to_channel(ctx).await?.guild();
+
+    // Threads don't have webhooks of their own, their parent's get used instead
+    let (webhook_channel_id, builder) = match channel {
+        Some(channel) if channel.thread_metadata.is_some() => (
+            channel.parent_id.unwrap_or(channel_id),
+            builder.in_thread(channel_id),
+        ),
+        _ => (channel_id, builder),
+    };
+
+    let message = get_webhook(ctx, webhook_channel_id)
+        .await?
+        .execute(ctx, true, builder)
+        .await?;
+
+    Ok(message)
+}
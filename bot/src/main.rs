@@ -1,26 +1,83 @@
 use bot_lib::{
+    audit_log::audit_command,
+    changelog,
+    command_channels::check_command_channels,
+    command_limits::{check_command_limits, release_command_limits},
     commands::{
         add_bot_role::add_bot_role,
+        admin::admin,
+        announce::announce,
+        bookmarks::bookmarks,
+        changelog::changelog,
+        class_audit::class_audit,
+        class_categories::class_categories,
+        class_info::{course_info, set_class_info},
         class_roles::{add_class_role, remove_class_role},
+        clear_reactions::clear_reactions,
+        command_stats::{command_stats, record_command_end, record_command_start},
         course_catalog::course_catalog,
+        course_reviews::{course_reviews, moderate_review, review_course},
         create_class_category::create_class_category,
+        custom_role::custom_role,
+        dehoist::dehoist,
         delete_class_category::delete_class_category,
+        describe_image::describe_image,
+        dm_class::{dm_class, dm_opt_out},
+        faq::faq,
+        feature::feature,
+        find_partner::find_partner,
+        grant_role::grant_role,
+        growth_report::growth_report,
         help::help,
-        lynch::{lynch, update_interval},
+        helpers::{helpers, volunteer},
+        invites::{create_invite, invites},
+        jobs::{jobs, post_job},
+        lynch::lynch,
+        modmail::modmail,
+        play::play,
+        profile::profile,
+        quiet_hours::quiet_hours,
+        refresh_class_directory::refresh_class_directory,
         register::register,
         remove_bot_role::remove_bot_role,
+        rename_vote::rename_vote,
+        report_message::{report_message, report_stats},
         reset_class_categories::{reset_class_categories, reset_class_category},
+        resources::resources,
+        response::response,
+        retheme_class_roles::retheme_class_roles,
         sathya::sathya,
+        schedule_message::schedule_message,
+        season::season,
+        set_status::set_status,
+        setup_classes::setup_classes,
+        shop::{pin_with_token, shop},
+        soundboard::soundboard,
+        starboard_rewind::starboard_rewind,
+        sync_emojis::sync_emojis,
+        tempcheck::tempcheck,
+        ticket::ticket,
         timeout::timeout,
+        undo_last_deletion::undo_last_deletion,
+        voice_stats::voice_stats,
+        when::when,
     },
     config,
-    data::AppState,
+    data::{AppState, Data},
+    db::KingFisherDb,
+    error_reporting,
     event_handler::event_handler,
+    init,
+    instance_lock::{start_heartbeat, warn_mods_of_duplicate, InstanceLock},
+    intents, presence,
+    response_import::{self, ImportFormat},
+    scheduler, simulate,
 };
-use clap::Parser;
-use color_eyre::eyre::{Result, WrapErr};
+use clap::{Parser, Subcommand};
+use color_eyre::eyre::{eyre, Result, WrapErr};
 use dotenvy::dotenv;
 use poise::serenity_prelude as serenity;
+use std::path::PathBuf;
 use tracing_subscriber::util::SubscriberInitExt;
 
 /// The cli arguments for the bot
@@ -34,11 +91,49 @@ pub struct Args {
     /// Path to the config file
     #[arg(short, long, default_value_t = String::from("config.toml"))]
     pub config: String,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Connect to discord and run the bot, the default
+    Run,
+    /// Walk through first time setup and write a new config file
+    Init,
+    /// Check that a config file loads, including its response packs
+    ValidateConfig { path: String },
+    /// Dump the database as JSON
+    ExportDb {
+        /// Where to write it, stdout if not given
+        output: Option<PathBuf>,
+    },
+    /// Copy the database into a new one, e.g. to compact it or move it across sled versions
+    MigrateDb {
+        /// Where the new database goes, it must not exist yet
+        to: String,
+    },
+    /// Replay captured messages through moderation and text detection, without connecting to discord
+    Simulate {
+        /// A JSON lines file of messages, each with `content` and optionally `channel_id`, `author` and `timestamp`
+        messages: PathBuf,
+    },
+    /// Convert another bot's triggers and responses into `[[responses]]` for the config
+    Import {
+        /// Which bot the export came from, yagpdb or carlbot
+        #[arg(long)]
+        format: ImportFormat,
+        /// The JSON export
+        file: PathBuf,
+        /// Where to write the responses, stdout if not given
+        output: Option<PathBuf>,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    dotenv().wrap_err("Failed to load .env file")?;
+    let env = dotenv();
     color_eyre::install()?;
 
     tracing_subscriber::fmt()
@@ -51,10 +146,86 @@ async fn main() -> Result<()> {
         .init();
 
     let args = Args::parse();
+    let load_config =
+        || config::Config::create_from_file(&args.config).wrap_err("Failed to load config");
+
+    match args.command.unwrap_or(Command::Run) {
+        Command::Run => {
+            env.wrap_err("Failed to load .env file")?;
+            run(load_config()?, args.dry_run).await
+        }
+        Command::Init => init::run(&args.config),
+        Command::ValidateConfig { path } => {
+            let config = config::Config::create_from_file(&path)?;
+            println!(
+                "{} is valid, with {} responses",
+                path,
+                config.all_responses().count()
+            );
+            Ok(())
+        }
+        Command::ExportDb { output } => {
+            let db = KingFisherDb::new(&load_config()?.db_path)?;
+            let json = serde_json::to_string_pretty(&db.export_json()?)?;
+
+            match output {
+                Some(output) => std::fs::write(output, json)?,
+                None => println!("{}", json),
+            }
+            Ok(())
+        }
+        Command::MigrateDb { to } => {
+            let config = load_config()?;
+            KingFisherDb::new(&config.db_path)?.copy_to(&to)?;
+            println!(
+                "Copied {} to {}, point db_path at it to use it",
+                config.db_path, to
+            );
+            Ok(())
+        }
+        Command::Simulate { messages } => {
+            simulate::run(&load_config()?, &simulate::read_messages(&messages)?);
+            Ok(())
+        }
+        Command::Import {
+            format,
+            file,
+            output,
+        } => {
+            let toml = response_import::run(format, &file)?;
+
+            match output {
+                Some(output) => std::fs::write(output, toml)?,
+                None => print!("{}", toml),
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Connects to discord and runs the bot until it stops.
+async fn run(config: config::Config, dry_run: bool) -> Result<()> {
     let token =
         std::env::var("DISCORD_TOKEN").wrap_err("Expected a discord token environment variable")?;
-    let config =
-        config::Config::create_from_file(&args.config).wrap_err("Failed to load config")?;
+
+    let intents = intents::gateway_intents(config.intents, config.privacy_mode);
+
+    if let Some(error_reporting) = &config.error_reporting {
+        error_reporting::install(error_reporting).wrap_err("Invalid error_reporting dsn")?;
+    }
+
+    // Before the database is opened, so a running copy isn't disturbed. Dry runs never open it.
+    let _instance_lock = if dry_run {
+        None
+    } else {
+        match InstanceLock::acquire(&config.db_path) {
+            Ok(instance_lock) => Some(instance_lock),
+            Err(e) => {
+                warn_mods_of_duplicate(&token, config.mod_log_channel_id, &e.to_string()).await;
+                return Err(e);
+            }
+        }
+    };
 
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
@@ -73,24 +244,106 @@ async fn main() -> Result<()> {
                 add_class_role(),
                 sathya(),
                 remove_class_role(),
+                command_stats(),
+                admin(),
+                report_message(),
+                report_stats(),
+                dehoist(),
+                describe_image(),
+                voice_stats(),
+                starboard_rewind(),
+                response(),
+                quiet_hours(),
+                play(),
+                soundboard(),
+                season(),
+                dm_class(),
+                dm_opt_out(),
+                tempcheck(),
+                profile(),
+                when(),
+                faq(),
+                bookmarks(),
+                set_status(),
+                sync_emojis(),
+                resources(),
+                grant_role(),
+                class_audit(),
+                class_categories(),
+                retheme_class_roles(),
+                refresh_class_directory(),
+                course_info(),
+                set_class_info(),
+                volunteer(),
+                helpers(),
+                find_partner(),
+                course_reviews(),
+                review_course(),
+                moderate_review(),
+                post_job(),
+                jobs(),
+                announce(),
+                schedule_message(),
+                undo_last_deletion(),
+                growth_report(),
+                create_invite(),
+                invites(),
+                changelog(),
+                shop(),
+                pin_with_token(),
+                custom_role(),
+                setup_classes(),
+                ticket(),
+                modmail(),
+                rename_vote(),
+                feature(),
+                clear_reactions(),
             ],
             event_handler: |ctx, event, framework, data| {
                 Box::pin(event_handler(ctx, event, framework, data))
             },
+            command_check: Some(|ctx| {
+                Box::pin(async move {
+                    // Channels first, so a command used in the wrong place doesn't start a cooldown
+                    Ok(check_command_channels(ctx).await? && check_command_limits(ctx).await?)
+                })
+            }),
+            pre_command: |ctx| Box::pin(record_command_start(ctx)),
+            post_command: |ctx| {
+                Box::pin(async move {
+                    release_command_limits(ctx);
+                    record_command_end(ctx, true).await;
+                    audit_command(ctx, None).await;
+                })
+            },
             on_error: |error| {
-                async fn on_error(
-                    error: poise::FrameworkError<'_, AppState, color_eyre::eyre::Error>,
-                ) {
+                async fn on_error(error: poise::FrameworkError<'_, Data, color_eyre::eyre::Error>) {
                     tracing::error!("{}", error);
+
+                    if let poise::FrameworkError::Command { ctx, error, .. } = &error {
+                        ctx.data().record_error(format!(
+                            "/{}: {:?}",
+                            ctx.command().qualified_name,
+                            error
+                        ));
+                        error_reporting::report_error(&ctx.command().qualified_name, error);
+                    }
+
+                    if let Some(ctx) = error.ctx() {
+                        release_command_limits(ctx);
+                        audit_command(ctx, Some(error.to_string())).await;
+                    }
+
+                    if let poise::FrameworkError::Command { ctx, .. } = error {
+                        record_command_end(ctx, false).await;
+                    }
                 }
 
                 Box::pin(on_error(error))
             },
             ..Default::default()
         })
-        .setup(|ctx, _ready, framework| {
-            tokio::spawn(async { update_interval().await });
-
+        .setup(move |ctx, _ready, framework| {
             Box::pin(async move {
                 poise::builtins::register_in_guild(
                     ctx,
@@ -98,31 +351,42 @@ async fn main() -> Result<()> {
                     serenity::GuildId::from(config.guild_id),
                 )
                 .await?;
-                Ok(AppState::new(config))
+
+                let data = Data::new(AppState::new(config));
+                start_heartbeat(ctx.clone(), Data::clone(&data));
+                scheduler::start(ctx.clone(), Data::clone(&data));
+                presence::start(ctx.clone(), Data::clone(&data));
+                changelog::start(ctx.clone(), Data::clone(&data));
+
+                Ok(data)
             })
         });
 
-    let client = serenity::ClientBuilder::new(
-        token,
-        serenity::GatewayIntents::non_privileged()
-            | serenity::GatewayIntents::MESSAGE_CONTENT
-            | serenity::GatewayIntents::GUILD_MEMBERS
-            | serenity::GatewayIntents::GUILD_MESSAGE_REACTIONS
-            | serenity::GatewayIntents::GUILD_MESSAGES,
-    )
-    .framework(framework.build())
-    .await;
-
-    if args.dry_run {
+    let client = serenity::ClientBuilder::new(token, intents).framework(framework.build());
+
+    #[cfg(feature = "voice")]
+    let client = songbird::SerenityInit::register_songbird(client);
+
+    let client = client.await;
+
+    if dry_run {
         println!("Bot setup worked, dry run enabled, exiting");
         return Ok(());
     }
 
     tracing::info!("Starting bot");
 
-    client
+    match client
         .wrap_err("Failed to start bot (serenity)")?
         .start()
         .await
-        .wrap_err("Failed to start bot (startup)")
+    {
+        Err(serenity::Error::Gateway(serenity::GatewayError::DisallowedGatewayIntents)) => {
+            Err(eyre!(
+                "Discord refused the privileged intents, turn them on in the developer portal \
+                 or turn them off with [intents] or privacy_mode in the config"
+            ))
+        }
+        result => result.wrap_err("Failed to start bot (startup)"),
+    }
 }